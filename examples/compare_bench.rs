@@ -0,0 +1,95 @@
+//! Compares CLSAG verification on this machine against timing baselines
+//!
+//! Run with `cargo run --release --example compare-bench --features monero-compat [baselines]`.
+//!
+//! Verifies CLSAG signatures over 2 columns (a spend key and a commitment to zero, as in Monero)
+//! for the ring sizes Monero has used, and prints the median time and weight of each. The optional
+//! baselines file has one `ring_size microseconds` pair per line, measured with a reference
+//! implementation on the same hardware, and adds the ratio of nazgul's time to the baseline.
+//! Lines starting with `#` are ignored. No baselines ship with nazgul, since they only mean
+//! something for the machine they were measured on.
+
+extern crate nazgul;
+extern crate rand;
+extern crate sha2;
+
+use std::collections::BTreeMap;
+use std::time::{Duration, Instant};
+
+use curve25519_dalek::ristretto::RistrettoPoint;
+use curve25519_dalek::scalar::Scalar;
+use nazgul::clsag::CLSAG;
+use nazgul::traits::{SignWithRng, VerifyRef};
+use nazgul::weight;
+use rand::rngs::OsRng;
+use sha2::Sha512;
+
+const RING_SIZES: [usize; 3] = [11, 16, 32];
+const COLUMNS: usize = 2;
+const ROUNDS: usize = 21;
+
+fn read_baselines(path: &str) -> BTreeMap<usize, f64> {
+    let text = std::fs::read_to_string(path).expect("could not read the baselines file");
+    let mut baselines = BTreeMap::new();
+    for line in text.lines().map(str::trim) {
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let fields: Vec<&str> = line.split_whitespace().collect();
+        match (fields.first().map(|f| f.parse()), fields.get(1).map(|f| f.parse())) {
+            (Some(Ok(ring_size)), Some(Ok(micros))) => {
+                baselines.insert(ring_size, micros);
+            }
+            _ => panic!("expected `ring_size microseconds`, found `{}`", line),
+        }
+    }
+    baselines
+}
+
+/// Median time of verifying a fresh CLSAG with `ring_size` members over `ROUNDS` rounds
+fn median_verify(ring_size: usize) -> Duration {
+    let message: Vec<u8> = b"nazgul compare-bench".to_vec();
+    let mut times: Vec<Duration> = (0..ROUNDS)
+        .map(|_| {
+            let ks: Vec<Scalar> = (0..COLUMNS).map(|_| Scalar::random(&mut OsRng)).collect();
+            let ring: Vec<Vec<RistrettoPoint>> = (0..(ring_size - 1))
+                .map(|_| (0..COLUMNS).map(|_| RistrettoPoint::random(&mut OsRng)).collect())
+                .collect();
+            let signature = CLSAG::sign_with_rng_unchecked::<Sha512, _>(ks, ring, 0, &message, &mut OsRng).unwrap();
+            let start = Instant::now();
+            let result = signature.verify_ref::<Sha512>(&message);
+            let elapsed = start.elapsed();
+            assert!(result);
+            elapsed
+        })
+        .collect();
+    times.sort();
+    times[ROUNDS / 2]
+}
+
+fn main() {
+    let baselines = std::env::args()
+        .nth(1)
+        .map(|path| read_baselines(&path))
+        .unwrap_or_default();
+
+    println!(
+        "{:>9} {:>8} {:>12} {:>13} {:>7}",
+        "ring size", "weight", "nazgul (us)", "baseline (us)", "ratio"
+    );
+    for &ring_size in RING_SIZES.iter() {
+        let micros = median_verify(ring_size).as_secs_f64() * 1e6;
+        let (baseline, ratio) = match baselines.get(&ring_size) {
+            Some(baseline) => (format!("{:.1}", baseline), format!("{:.2}", micros / baseline)),
+            None => ("-".to_string(), "-".to_string()),
+        };
+        println!(
+            "{:>9} {:>8} {:>12.1} {:>13} {:>7}",
+            ring_size,
+            weight::clsag(ring_size, COLUMNS),
+            micros,
+            baseline,
+            ratio
+        );
+    }
+}
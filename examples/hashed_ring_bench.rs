@@ -0,0 +1,69 @@
+//! Compares bLSAG verification against a hashed ring table with plain verification
+//!
+//! Run with `cargo run --release --example hashed-ring-bench [--features parallel]`.
+//!
+//! Signs a few messages over one ring of 10,000 members, then verifies each signature with
+//! `verify_ref`, which hashes every member to a point, and against a `HashedRingTable` built once,
+//! which does not. Prints the time to build the table and the median time of each verification.
+
+extern crate nazgul;
+extern crate rand;
+extern crate sha2;
+
+use std::time::{Duration, Instant};
+
+use curve25519_dalek::ristretto::RistrettoPoint;
+use curve25519_dalek::scalar::Scalar;
+use nazgul::blsag::BLSAG;
+use nazgul::hashed_ring::HashedRingTable;
+use nazgul::traits::{SignWithRng, VerifyRef};
+use rand::rngs::OsRng;
+use sha2::Sha512;
+
+const RING_SIZE: usize = 10_000;
+const ROUNDS: usize = 5;
+
+fn median(mut times: Vec<Duration>) -> Duration {
+    times.sort();
+    times[times.len() / 2]
+}
+
+fn main() {
+    let message: Vec<u8> = b"nazgul hashed-ring-bench".to_vec();
+    let k: Scalar = Scalar::random(&mut OsRng);
+    let decoys: Vec<RistrettoPoint> = (0..(RING_SIZE - 1)).map(|_| RistrettoPoint::random(&mut OsRng)).collect();
+    let signatures: Vec<BLSAG> = (0..ROUNDS)
+        .map(|_| BLSAG::sign_with_rng::<Sha512, _>(k, decoys.clone(), 0, &message, &mut OsRng).unwrap())
+        .collect();
+
+    let start = Instant::now();
+    let table = HashedRingTable::<Sha512>::new(signatures[0].ring.clone());
+    let build = start.elapsed();
+
+    let plain = median(
+        signatures
+            .iter()
+            .map(|signature| {
+                let start = Instant::now();
+                assert!(signature.verify_ref::<Sha512>(&message));
+                start.elapsed()
+            })
+            .collect(),
+    );
+    let hashed = median(
+        signatures
+            .iter()
+            .map(|signature| {
+                let start = Instant::now();
+                assert!(table.verify(signature, &message));
+                start.elapsed()
+            })
+            .collect(),
+    );
+
+    println!("ring of {} members", RING_SIZE);
+    println!("{:>24} {:>10.1} ms", "building the table", build.as_secs_f64() * 1e3);
+    println!("{:>24} {:>10.1} ms", "verify_ref", plain.as_secs_f64() * 1e3);
+    println!("{:>24} {:>10.1} ms", "HashedRingTable::verify", hashed.as_secs_f64() * 1e3);
+    println!("{:>24} {:>10.2}", "speedup", plain.as_secs_f64() / hashed.as_secs_f64());
+}
@@ -0,0 +1,14 @@
+//! Prints the negative test vectors of nazgul as JSON
+//!
+//! Run with `cargo run --example negative-vectors --features test-vectors > vectors.json`. The
+//! vectors are for SHA-512, see `nazgul::testing::vectors`.
+
+extern crate nazgul;
+extern crate sha2;
+
+use nazgul::testing::vectors;
+use sha2::Sha512;
+
+fn main() {
+    print!("{}", vectors::to_json(&vectors::negative_vectors::<Sha512>()));
+}
@@ -0,0 +1,174 @@
+//! Anonymity analysis
+//!
+//! A linkable signature reveals that its signer is one of the ring members, and its key image
+//! reveals which outputs it can not have spent again. Once some spends are known, for instance
+//! from exchanges or from disclosed proofs, members of other rings can be ruled out as their
+//! signer, and every ring narrowed down to a single member rules that member out everywhere else.
+//! [`eliminate`] carries this chain reaction to its end, so that the anonymity actually left to
+//! a set of signatures can be measured.
+
+use crate::prelude::*;
+
+use alloc::collections::btree_map::{BTreeMap, Entry};
+
+#[cfg(feature = "blsag")]
+use crate::blsag::BLSAG;
+#[cfg(feature = "clsag")]
+use crate::clsag::CLSAG;
+use crate::link::{KeyImage, LinkingKeyImage};
+#[cfg(feature = "lsag")]
+use crate::lsag::LSAG;
+#[cfg(feature = "mlsag")]
+use crate::mlsag::MLSAG;
+
+/// A ring member as the compressed encoding of the public key its linking key image derives from
+pub type Member = [u8; 32];
+
+/// Linkable signatures whose linking key image is derived from one public key per ring member
+pub trait RingMembers: LinkingKeyImage {
+    /// The public key of every ring member the linking key image may derive from, in ring order
+    fn ring_members(&self) -> Vec<Member>;
+}
+
+#[cfg(feature = "lsag")]
+impl RingMembers for LSAG {
+    fn ring_members(&self) -> Vec<Member> {
+        return self.ring.iter().map(|k_point| k_point.compress().to_bytes()).collect();
+    }
+}
+
+#[cfg(feature = "blsag")]
+impl RingMembers for BLSAG {
+    fn ring_members(&self) -> Vec<Member> {
+        return self.ring.iter().map(|k_point| k_point.compress().to_bytes()).collect();
+    }
+}
+
+#[cfg(feature = "mlsag")]
+impl RingMembers for MLSAG {
+    fn ring_members(&self) -> Vec<Member> {
+        return self.ring.iter().map(first_member).collect();
+    }
+}
+
+#[cfg(feature = "clsag")]
+impl RingMembers for CLSAG {
+    fn ring_members(&self) -> Vec<Member> {
+        return self.ring.iter().map(first_member).collect();
+    }
+}
+
+/// The first key of a row of a ring matrix, which the linking key image derives from. A row
+/// without keys, which only a malformed signature has, gives the encoding of the identity
+#[cfg(any(feature = "mlsag", feature = "clsag"))]
+fn first_member(row: &Vec<curve25519_dalek::ristretto::RistrettoPoint>) -> Member {
+    return match row.first() {
+        Some(k_point) => k_point.compress().to_bytes(),
+        None => [0u8; 32],
+    };
+}
+
+/// For each of `signatures`, the indices of the ring members that can not be its signer, in
+/// ascending order
+///
+/// `spent_images` are the spends known from elsewhere, each a key image together with the member
+/// it spent. The signatures are assumed valid, so that no member is spent twice.
+pub fn eliminate<S: RingMembers>(signatures: &[S], spent_images: &[(KeyImage, Member)]) -> Vec<Vec<usize>> {
+    let rings: Vec<Vec<Member>> = signatures.iter().map(|signature| signature.ring_members()).collect();
+    let key_images: Vec<Option<KeyImage>> = signatures
+        .iter()
+        .map(|signature| signature.linking_key_image())
+        .collect();
+    let mut spent_by: BTreeMap<Member, KeyImage> = spent_images
+        .iter()
+        .map(|(key_image, member)| (*member, *key_image))
+        .collect();
+    let mut candidates: Vec<Vec<bool>> = rings.iter().map(|ring| vec![true; ring.len()]).collect();
+
+    loop {
+        let mut changed = false;
+        for s in 0..rings.len() {
+            // A malformed signature without a key image rules nothing out
+            let key_image = match key_images[s] {
+                Some(key_image) => key_image,
+                None => continue,
+            };
+            for j in 0..rings[s].len() {
+                if !candidates[s][j] {
+                    continue;
+                }
+                // A member spent by another key image, or another member spent by this one
+                let ruled_out = match spent_by.get(&rings[s][j]) {
+                    Some(spender) => *spender != key_image,
+                    None => spent_by.values().any(|spender| *spender == key_image),
+                };
+                if ruled_out {
+                    candidates[s][j] = false;
+                    changed = true;
+                }
+            }
+            let mut left = (0..rings[s].len()).filter(|j| candidates[s][*j]);
+            if let (Some(j), None) = (left.next(), left.next()) {
+                if let Entry::Vacant(entry) = spent_by.entry(rings[s][j]) {
+                    entry.insert(key_image);
+                    changed = true;
+                }
+            }
+        }
+        if !changed {
+            break;
+        }
+    }
+
+    return candidates
+        .iter()
+        .map(|candidate| (0..candidate.len()).filter(|j| !candidate[*j]).collect())
+        .collect();
+}
+
+#[cfg(test)]
+#[cfg(feature = "std")]
+#[cfg(feature = "sign")]
+#[cfg(feature = "blsag")]
+mod test {
+    extern crate rand;
+    extern crate sha2;
+
+    use curve25519_dalek::constants;
+    use curve25519_dalek::ristretto::RistrettoPoint;
+    use curve25519_dalek::scalar::Scalar;
+    use rand::rngs::OsRng;
+    use sha2::Sha512;
+
+    use super::*;
+    use crate::link;
+    use crate::traits::{KeyImageGenRef, SignWithRng};
+
+    #[test]
+    fn eliminate() {
+        let message: Vec<u8> = b"This is the message".to_vec();
+        let ks: Vec<Scalar> = (0..4).map(|_| Scalar::random(&mut OsRng)).collect();
+        let keys: Vec<RistrettoPoint> = ks.iter().map(|k| k * constants::RISTRETTO_BASEPOINT_POINT).collect();
+
+        // The first ring hides output 0 behind output 1, the others outputs 2 and 3 behind both
+        let signatures: Vec<BLSAG> = vec![
+            BLSAG::sign_with_rng_unchecked::<Sha512, _>(ks[0], vec![keys[1]], 0, &message, &mut OsRng).unwrap(),
+            BLSAG::sign_with_rng_unchecked::<Sha512, _>(ks[2], vec![keys[0], keys[1]], 2, &message, &mut OsRng)
+                .unwrap(),
+            BLSAG::sign_with_rng_unchecked::<Sha512, _>(ks[3], vec![keys[0], keys[1]], 2, &message, &mut OsRng)
+                .unwrap(),
+        ];
+        assert_eq!(super::eliminate(&signatures, &[]), vec![vec![], vec![], vec![]]);
+
+        // Knowing who spent output 1 singles out the signer of the first ring, and from there
+        // the signers of the others
+        let spent = (
+            link::to_key_image(&BLSAG::generate_key_image_ref::<Sha512>(&ks[1])),
+            keys[1].compress().to_bytes(),
+        );
+        assert_eq!(
+            super::eliminate(&signatures, &[spent]),
+            vec![vec![1], vec![0, 1], vec![0, 1]]
+        );
+    }
+}
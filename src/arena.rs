@@ -0,0 +1,116 @@
+//! Verification with its temporary vectors allocated from an arena the caller provides
+//!
+//! Enabled by the `allocator-api` feature. The arena is any [`Allocator`] of the `allocator-api2`
+//! crate, which works on stable Rust, e.g. a `&bumpalo::Bump` with bumpalo's `allocator-api2`
+//! feature. A service verifying many signatures can then reset one arena between them in place
+//! of going to the global allocator for every verification:
+//!
+//! ```ignore
+//! let mut arena = bumpalo::Bump::new();
+//! for (signature, message) in batch {
+//!     valid.push(signature.verify_in::<Sha512, _>(&message, &arena));
+//!     arena.reset();
+//! }
+//! ```
+//!
+//! MLSAG, CLSAG and MDLSAG take their temporary vectors from the arena. SAG, LSAG, bLSAG and
+//! DLSAG verify without allocating, so theirs is `verify_ref`. Signing still allocates globally.
+
+use digest::generic_array::typenum::U64;
+use digest::Digest;
+
+use crate::prelude::*;
+
+pub use allocator_api2::alloc::Allocator;
+
+/// [`crate::traits::VerifyRef`] with the temporary vectors allocated in `alloc`. `verify_ref`
+/// gives the same result
+pub trait VerifyIn {
+    fn verify_in<Hash: Digest<OutputSize = U64> + Clone + Default, A: Allocator>(
+        &self,
+        message: &Vec<u8>,
+        alloc: A,
+    ) -> bool;
+}
+
+#[cfg(test)]
+#[cfg(feature = "std")]
+#[cfg(feature = "sign")]
+#[cfg(all(feature = "blsag", feature = "mlsag", feature = "clsag", feature = "mdlsag"))]
+mod test {
+    extern crate bumpalo;
+    extern crate rand;
+    extern crate sha2;
+
+    use super::*;
+    use crate::blsag::BLSAG;
+    use crate::clsag::CLSAG;
+    use crate::mdlsag::MDLSAG;
+    use crate::mlsag::MLSAG;
+    use crate::traits::{SignWithRng, VerifyRef};
+    use bumpalo::Bump;
+    use curve25519_dalek::ristretto::RistrettoPoint;
+    use curve25519_dalek::scalar::Scalar;
+    use rand::rngs::OsRng;
+    use sha2::Sha512;
+
+    #[test]
+    fn verify_in() {
+        let mut arena = Bump::new();
+        let message: Vec<u8> = b"This is the message".to_vec();
+        let another_message: Vec<u8> = b"This is another message".to_vec();
+
+        let ks: Vec<Scalar> = (0..2).map(|_| Scalar::random(&mut OsRng)).collect();
+        let ring: Vec<Vec<RistrettoPoint>> =
+            (0..2).map(|_| (0..2).map(|_| RistrettoPoint::random(&mut OsRng)).collect()).collect();
+
+        // The multilayer schemes take their temporary vectors from the arena
+        let mlsag =
+            MLSAG::sign_with_rng_unchecked::<Sha512, _>(ks.clone(), ring.clone(), 1, &message, &mut OsRng).unwrap();
+        assert!(mlsag.verify_in::<Sha512, _>(&message, &arena));
+        assert!(arena.allocated_bytes() > 0);
+        assert!(!mlsag.verify_in::<Sha512, _>(&another_message, &arena));
+        arena.reset();
+
+        let clsag =
+            CLSAG::sign_with_rng_unchecked::<Sha512, _>(ks.clone(), ring.clone(), 1, &message, &mut OsRng).unwrap();
+        assert!(clsag.verify_in::<Sha512, _>(&message, &arena));
+        assert!(!clsag.verify_in::<Sha512, _>(&another_message, &arena));
+        arena.reset();
+
+        let mdlsag_ks: Vec<(Scalar, RistrettoPoint, Scalar)> = (0..2)
+            .map(|_| (Scalar::random(&mut OsRng), RistrettoPoint::random(&mut OsRng), Scalar::random(&mut OsRng)))
+            .collect();
+        let member =
+            |_| (RistrettoPoint::random(&mut OsRng), RistrettoPoint::random(&mut OsRng), Scalar::random(&mut OsRng));
+        let mdlsag_ring: Vec<Vec<(RistrettoPoint, RistrettoPoint, Scalar)>> =
+            (0..2).map(|_| (0..2).map(member).collect()).collect();
+        let mdlsag =
+            MDLSAG::sign_with_rng_unchecked::<Sha512, _>(mdlsag_ks, mdlsag_ring, 1, &message, &mut OsRng).unwrap();
+        assert!(mdlsag.verify_in::<Sha512, _>(&message, &arena));
+        assert!(!mdlsag.verify_in::<Sha512, _>(&another_message, &arena));
+
+        // The others verify without allocating, with the same result as `verify_ref`
+        let arena = Bump::new();
+        let k: Scalar = Scalar::random(&mut OsRng);
+        let blsag_ring: Vec<RistrettoPoint> = (0..2).map(|_| RistrettoPoint::random(&mut OsRng)).collect();
+        let blsag = BLSAG::sign_with_rng_unchecked::<Sha512, _>(k, blsag_ring, 1, &message, &mut OsRng).unwrap();
+        assert!(blsag.verify_in::<Sha512, _>(&message, &arena));
+        assert_eq!(
+            blsag.verify_in::<Sha512, _>(&another_message, &arena),
+            blsag.verify_ref::<Sha512>(&another_message)
+        );
+        assert_eq!(arena.allocated_bytes(), 0);
+    }
+
+    #[test]
+    fn malformed() {
+        let arena = Bump::new();
+        let message: Vec<u8> = b"This is the message".to_vec();
+        let ks: Vec<Scalar> = (0..2).map(|_| Scalar::random(&mut OsRng)).collect();
+        let ring: Vec<Vec<RistrettoPoint>> = vec![(0..2).map(|_| RistrettoPoint::random(&mut OsRng)).collect()];
+        let mut signature = MLSAG::sign_with_rng_unchecked::<Sha512, _>(ks, ring, 1, &message, &mut OsRng).unwrap();
+        signature.responses.pop();
+        assert!(!signature.verify_in::<Sha512, _>(&message, &arena));
+    }
+}
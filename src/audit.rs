@@ -0,0 +1,394 @@
+//! Verification audit log
+//!
+//! [`AuditLog::verify`] verifies a signature and appends a record of the verification to an
+//! append-only file, for compliance teams that need a trail of what was verified and when. Each
+//! record is a small CBOR map
+//!
+//! `{"scheme": text, "ring": bytes, "key_images": [bytes], "result": bool, "time": uint, "prev": bytes}`
+//!
+//! where `ring` is a fingerprint of the ring, `time` is in seconds since the Unix epoch and `prev` is
+//! the hash of the previous record (all zeros for the first one). This chains the records together,
+//! so [`verify_chain`] detects any record being changed, removed or inserted, and a copy of the
+//! [`AuditLog::head`] kept elsewhere also detects records being cut from the end. Records are stored
+//! with a 32 bit little-endian length in front.
+
+use crate::prelude::*;
+
+use std::fs::{File, OpenOptions};
+use std::io::{self, Read, Write};
+use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use curve25519_dalek::ristretto::RistrettoPoint;
+use digest::generic_array::typenum::U64;
+use digest::Digest;
+
+#[cfg(feature = "blsag")]
+use crate::blsag::BLSAG;
+#[cfg(feature = "clsag")]
+use crate::clsag::CLSAG;
+#[cfg(feature = "dlsag")]
+use crate::dlsag::DLSAG;
+#[cfg(feature = "lsag")]
+use crate::lsag::LSAG;
+#[cfg(feature = "mdlsag")]
+use crate::mdlsag::MDLSAG;
+#[cfg(feature = "mlsag")]
+use crate::mlsag::MLSAG;
+#[cfg(feature = "sag")]
+use crate::sag::SAG;
+use crate::traits::VerifyRef;
+
+/// Signatures that can be described in an audit record
+pub trait Auditable: VerifyRef {
+    fn scheme(&self) -> &'static str;
+    /// Absorbs every member of the ring into `hash`, for the ring fingerprint
+    fn absorb_ring<Hash: Digest>(&self, hash: &mut Hash);
+    /// The key images of the signature, none for SAG
+    fn key_images(&self) -> Vec<RistrettoPoint>;
+}
+
+#[cfg(feature = "sag")]
+impl Auditable for SAG {
+    fn scheme(&self) -> &'static str {
+        return "sag";
+    }
+
+    fn absorb_ring<Hash: Digest>(&self, hash: &mut Hash) {
+        for k_point in &self.ring {
+            hash.update(k_point.compress().as_bytes());
+        }
+    }
+
+    fn key_images(&self) -> Vec<RistrettoPoint> {
+        return Vec::new();
+    }
+}
+
+#[cfg(feature = "lsag")]
+impl Auditable for LSAG {
+    fn scheme(&self) -> &'static str {
+        return "lsag";
+    }
+
+    fn absorb_ring<Hash: Digest>(&self, hash: &mut Hash) {
+        for k_point in &self.ring {
+            hash.update(k_point.compress().as_bytes());
+        }
+    }
+
+    fn key_images(&self) -> Vec<RistrettoPoint> {
+        return vec![self.key_image];
+    }
+}
+
+#[cfg(feature = "blsag")]
+impl Auditable for BLSAG {
+    fn scheme(&self) -> &'static str {
+        return "blsag";
+    }
+
+    fn absorb_ring<Hash: Digest>(&self, hash: &mut Hash) {
+        for k_point in &self.ring {
+            hash.update(k_point.compress().as_bytes());
+        }
+    }
+
+    fn key_images(&self) -> Vec<RistrettoPoint> {
+        return vec![self.key_image];
+    }
+}
+
+#[cfg(feature = "mlsag")]
+impl Auditable for MLSAG {
+    fn scheme(&self) -> &'static str {
+        return "mlsag";
+    }
+
+    fn absorb_ring<Hash: Digest>(&self, hash: &mut Hash) {
+        for row in &self.ring {
+            for k_point in row {
+                hash.update(k_point.compress().as_bytes());
+            }
+        }
+    }
+
+    fn key_images(&self) -> Vec<RistrettoPoint> {
+        return self.key_images.clone();
+    }
+}
+
+#[cfg(feature = "clsag")]
+impl Auditable for CLSAG {
+    fn scheme(&self) -> &'static str {
+        return "clsag";
+    }
+
+    fn absorb_ring<Hash: Digest>(&self, hash: &mut Hash) {
+        for row in &self.ring {
+            for k_point in row {
+                hash.update(k_point.compress().as_bytes());
+            }
+        }
+    }
+
+    /// Only the first key image is linkable
+    fn key_images(&self) -> Vec<RistrettoPoint> {
+        return self.key_images.iter().take(1).copied().collect();
+    }
+}
+
+#[cfg(feature = "dlsag")]
+impl Auditable for DLSAG {
+    fn scheme(&self) -> &'static str {
+        return "dlsag";
+    }
+
+    fn absorb_ring<Hash: Digest>(&self, hash: &mut Hash) {
+        for (k_point_1, k_point_2, scalar) in &self.ring {
+            hash.update(k_point_1.compress().as_bytes());
+            hash.update(k_point_2.compress().as_bytes());
+            hash.update(scalar.as_bytes());
+        }
+    }
+
+    fn key_images(&self) -> Vec<RistrettoPoint> {
+        return vec![self.key_image];
+    }
+}
+
+#[cfg(feature = "mdlsag")]
+impl Auditable for MDLSAG {
+    fn scheme(&self) -> &'static str {
+        return "mdlsag";
+    }
+
+    fn absorb_ring<Hash: Digest>(&self, hash: &mut Hash) {
+        for row in &self.ring {
+            for (k_point_1, k_point_2, scalar) in row {
+                hash.update(k_point_1.compress().as_bytes());
+                hash.update(k_point_2.compress().as_bytes());
+                hash.update(scalar.as_bytes());
+            }
+        }
+    }
+
+    fn key_images(&self) -> Vec<RistrettoPoint> {
+        return self.key_images.clone();
+    }
+}
+
+/// One verification, as recorded in the log
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Record {
+    pub scheme: &'static str,
+    pub ring_fingerprint: [u8; 64],
+    pub key_images: Vec<[u8; 32]>,
+    pub result: bool,
+    /// Seconds since the Unix epoch
+    pub timestamp: u64,
+}
+
+/// Writes the head of a CBOR data item of `major` type with argument `value`
+fn write_head(bytes: &mut Vec<u8>, major: u8, value: u64) {
+    let major = major << 5;
+    if value < 24 {
+        bytes.push(major | value as u8);
+    } else if value <= u8::MAX as u64 {
+        bytes.push(major | 24);
+        bytes.push(value as u8);
+    } else if value <= u16::MAX as u64 {
+        bytes.push(major | 25);
+        bytes.extend_from_slice(&(value as u16).to_be_bytes());
+    } else if value <= u32::MAX as u64 {
+        bytes.push(major | 26);
+        bytes.extend_from_slice(&(value as u32).to_be_bytes());
+    } else {
+        bytes.push(major | 27);
+        bytes.extend_from_slice(&value.to_be_bytes());
+    }
+}
+
+fn write_bytes(bytes: &mut Vec<u8>, value: &[u8]) {
+    write_head(bytes, 2, value.len() as u64);
+    bytes.extend_from_slice(value);
+}
+
+fn write_text(bytes: &mut Vec<u8>, value: &str) {
+    write_head(bytes, 3, value.len() as u64);
+    bytes.extend_from_slice(value.as_bytes());
+}
+
+impl Record {
+    /// The CBOR encoding of the record chained to the record hashing to `previous`. `prev` is
+    /// always encoded last, so it makes up the last 64 bytes of the encoding.
+    fn to_cbor(&self, previous: &[u8; 64]) -> Vec<u8> {
+        let mut bytes: Vec<u8> = Vec::new();
+        write_head(&mut bytes, 5, 6);
+        write_text(&mut bytes, "scheme");
+        write_text(&mut bytes, self.scheme);
+        write_text(&mut bytes, "ring");
+        write_bytes(&mut bytes, &self.ring_fingerprint);
+        write_text(&mut bytes, "key_images");
+        write_head(&mut bytes, 4, self.key_images.len() as u64);
+        for key_image in &self.key_images {
+            write_bytes(&mut bytes, key_image);
+        }
+        write_text(&mut bytes, "result");
+        bytes.push(if self.result { 0xf5 } else { 0xf4 });
+        write_text(&mut bytes, "time");
+        write_head(&mut bytes, 0, self.timestamp);
+        write_text(&mut bytes, "prev");
+        write_bytes(&mut bytes, previous);
+        return bytes;
+    }
+}
+
+fn record_hash<Hash: Digest<OutputSize = U64>>(record: &[u8]) -> [u8; 64] {
+    let mut hash = [0u8; 64];
+    hash.copy_from_slice(&Hash::digest(record));
+    return hash;
+}
+
+/// Reads every length prefixed record of the log at `path`
+fn read_records(path: &Path) -> io::Result<Vec<Vec<u8>>> {
+    let mut contents: Vec<u8> = Vec::new();
+    File::open(path)?.read_to_end(&mut contents)?;
+    let mut records: Vec<Vec<u8>> = Vec::new();
+    let mut rest: &[u8] = &contents;
+    while !rest.is_empty() {
+        let invalid = || io::Error::new(io::ErrorKind::InvalidData, "truncated audit record");
+        let mut len = [0u8; 4];
+        len.copy_from_slice(rest.get(..4).ok_or_else(invalid)?);
+        let len = u32::from_le_bytes(len) as usize;
+        let record = rest.get(4..4 + len).ok_or_else(invalid)?;
+        records.push(record.to_vec());
+        rest = &rest[4 + len..];
+    }
+    return Ok(records);
+}
+
+/// Checks that every record of the log at `path` is chained to the one before it
+pub fn verify_chain<Hash: Digest<OutputSize = U64>>(path: &Path) -> io::Result<bool> {
+    let mut previous = [0u8; 64];
+    for record in read_records(path)? {
+        if record.len() < 64 || record[record.len() - 64..] != previous[..] {
+            return Ok(false);
+        }
+        previous = record_hash::<Hash>(&record);
+    }
+    return Ok(true);
+}
+
+/// An append-only log of verifications, see [`crate::audit`]
+pub struct AuditLog<Hash> {
+    file: File,
+    head: [u8; 64],
+    hash: core::marker::PhantomData<Hash>,
+}
+
+impl<Hash: Digest<OutputSize = U64> + Clone + Default> AuditLog<Hash> {
+    /// Opens the log at `path` for appending, creating it if needed. Existing records are not
+    /// checked, use [`verify_chain`] for that.
+    pub fn open(path: &Path) -> io::Result<AuditLog<Hash>> {
+        let file = OpenOptions::new().create(true).append(true).open(path)?;
+        let head = match read_records(path)?.last() {
+            Some(record) => record_hash::<Hash>(record),
+            None => [0u8; 64],
+        };
+        return Ok(AuditLog {
+            file: file,
+            head: head,
+            hash: core::marker::PhantomData,
+        });
+    }
+
+    /// The hash of the last record, or all zeros if the log is empty
+    pub fn head(&self) -> [u8; 64] {
+        return self.head;
+    }
+
+    /// Appends `record`, chaining it to the last record
+    pub fn append(&mut self, record: &Record) -> io::Result<()> {
+        let cbor = record.to_cbor(&self.head);
+        let mut bytes: Vec<u8> = Vec::with_capacity(4 + cbor.len());
+        bytes.extend_from_slice(&(cbor.len() as u32).to_le_bytes());
+        bytes.extend_from_slice(&cbor);
+        self.file.write_all(&bytes)?;
+        self.file.flush()?;
+        self.head = record_hash::<Hash>(&cbor);
+        return Ok(());
+    }
+
+    /// Verifies `signature` over `message` and records the result before returning it
+    pub fn verify<S: Auditable>(&mut self, signature: S, message: &Vec<u8>) -> io::Result<bool> {
+        let mut fingerprint: Hash = Hash::default();
+        signature.absorb_ring(&mut fingerprint);
+        let mut ring_fingerprint = [0u8; 64];
+        ring_fingerprint.copy_from_slice(&fingerprint.finalize());
+        let scheme = signature.scheme();
+        let key_images: Vec<[u8; 32]> =
+            signature.key_images().iter().map(|key_image| key_image.compress().to_bytes()).collect();
+        let result = signature.verify_ref::<Hash>(message);
+        let timestamp = SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |elapsed| elapsed.as_secs());
+        self.append(&Record {
+            scheme: scheme,
+            ring_fingerprint: ring_fingerprint,
+            key_images: key_images,
+            result: result,
+            timestamp: timestamp,
+        })?;
+        return Ok(result);
+    }
+}
+
+#[cfg(test)]
+#[cfg(feature = "sign")]
+#[cfg(feature = "blsag")]
+mod test {
+    extern crate rand;
+    extern crate sha2;
+
+    use curve25519_dalek::scalar::Scalar;
+    use rand::rngs::OsRng;
+    use sha2::Sha512;
+
+    use super::*;
+    use crate::traits::SignWithRng;
+
+    #[test]
+    fn audit() {
+        let path = std::env::temp_dir().join(format!("nazgul-audit-{}", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        let message: Vec<u8> = b"This is the message".to_vec();
+        let k: Scalar = Scalar::random(&mut OsRng);
+        let ring: Vec<RistrettoPoint> = (0..2).map(|_| RistrettoPoint::random(&mut OsRng)).collect();
+        let signature = BLSAG::sign_with_rng_unchecked::<Sha512, _>(k, ring, 1, &message, &mut OsRng).unwrap();
+
+        let mut log = AuditLog::<Sha512>::open(&path).unwrap();
+        assert!(log.verify(signature.clone(), &message).unwrap());
+        assert!(!log.verify(signature.clone(), &b"This is another message".to_vec()).unwrap());
+        let head = log.head();
+        drop(log);
+
+        // Reopening continues the chain
+        let mut log = AuditLog::<Sha512>::open(&path).unwrap();
+        assert_eq!(log.head(), head);
+        assert!(log.verify(signature, &message).unwrap());
+        drop(log);
+        assert!(verify_chain::<Sha512>(&path).unwrap());
+
+        // Flipping the result of the second record breaks the chain
+        let mut contents = std::fs::read(&path).unwrap();
+        let records = read_records(&path).unwrap();
+        let result_key: &[u8] = b"\x66result";
+        let result_offset = 4 + records[0].len() + 4
+            + records[1].windows(result_key.len()).position(|window| window == result_key).unwrap()
+            + result_key.len();
+        assert_eq!(contents[result_offset], 0xf4);
+        contents[result_offset] = 0xf5;
+        std::fs::write(&path, &contents).unwrap();
+        assert!(!verify_chain::<Sha512>(&path).unwrap());
+        std::fs::remove_file(&path).unwrap();
+    }
+}
@@ -0,0 +1,234 @@
+//! Signing many messages
+//!
+//! Services signing many messages with one key over one ring, e.g. a relay attesting to every
+//! message it forwards, call [`SignBatch::sign_batch`] once in place of signing each in turn. The
+//! ring is checked once, against [`SignPolicy::default()`] as [`TrySign::try_sign`] checks it or
+//! against another policy with [`SignBatch::sign_batch_with_policy`], and the hash of the key and
+//! ring that the nonces start from is computed once and shared. With the `parallel` feature the
+//! messages are signed across threads.
+//!
+//! Every signature draws its nonces from a [`HedgedRng`] of its own, seeded from 32 bytes that
+//! `csprng` gives that signature alone and from its message. The generator is only drawn from
+//! before signing starts, on the calling thread, so no two signatures share a stream whatever the
+//! order the threads run in, and a broken generator still gives different nonces for different
+//! messages, see [`crate::nonce`].
+
+use crate::prelude::*;
+
+use digest::generic_array::typenum::U64;
+use digest::Digest;
+use rand_core::{CryptoRng, RngCore};
+
+use crate::error::Error;
+use crate::generator::{Basepoint, Generator};
+use crate::nonce::{HedgedRng, NonceInput};
+use crate::policy::SignPolicy;
+use crate::signature::SignatureBytes;
+use crate::traits::{Decoys, SignWithRng, TrySign};
+
+/// Signing many messages with one key over one ring
+pub trait SignBatch<PrivateKey, Ring>:
+    SignWithRng<PrivateKey, Ring> + TrySign<PrivateKey, Ring> + SignatureBytes + Send
+where
+    PrivateKey: NonceInput + Clone + Sync,
+    Ring: NonceInput + Decoys + Clone + Sync,
+{
+    /// One signature of each of `messages`, in order. Returns the error of
+    /// [`TrySign::check_sign_input`] if the key, ring and position do not agree, and
+    /// [`Error::RingTooSmall`] for rings smaller than [`crate::policy::DEFAULT_MINIMUM_RING_SIZE`],
+    /// signer included
+    fn sign_batch<Hash: Digest<OutputSize = U64> + Clone + Default, CSPRNG: CryptoRng + RngCore>(
+        k: &PrivateKey,
+        ring: &Ring,
+        secret_index: usize,
+        messages: &[&[u8]],
+        csprng: &mut CSPRNG,
+    ) -> Result<Vec<Self>, Error> {
+        let policy = SignPolicy::default();
+        return Self::sign_batch_with_policy::<Hash, CSPRNG>(&policy, k, ring, secret_index, messages, csprng);
+    }
+
+    /// `sign_batch` with the ring checked against `policy`
+    fn sign_batch_with_policy<Hash: Digest<OutputSize = U64> + Clone + Default, CSPRNG: CryptoRng + RngCore>(
+        policy: &SignPolicy,
+        k: &PrivateKey,
+        ring: &Ring,
+        secret_index: usize,
+        messages: &[&[u8]],
+        csprng: &mut CSPRNG,
+    ) -> Result<Vec<Self>, Error> {
+        Self::check_sign_input(k, ring, secret_index)?;
+        policy.check(ring.decoys() + 1)?;
+        let generator = Basepoint::generator();
+        let signer: Hash = HedgedRng::<Hash>::absorb_signer(Self::SCHEME, &generator, k, ring, secret_index);
+        let seeds: Vec<[u8; 64]> = messages
+            .iter()
+            .map(|message| {
+                let mut entropy = [0u8; 32];
+                csprng.fill_bytes(&mut entropy);
+                return HedgedRng::<Hash>::seed(signer.clone(), message, &entropy);
+            })
+            .collect();
+        return sign_seeded::<Hash, PrivateKey, Ring, Self>(k, ring, secret_index, messages, seeds);
+    }
+}
+
+impl<PrivateKey, Ring, S> SignBatch<PrivateKey, Ring> for S
+where
+    PrivateKey: NonceInput + Clone + Sync,
+    Ring: NonceInput + Decoys + Clone + Sync,
+    S: SignWithRng<PrivateKey, Ring> + TrySign<PrivateKey, Ring> + SignatureBytes + Send,
+{
+}
+
+fn sign_one<Hash, PrivateKey, Ring, S>(
+    k: &PrivateKey,
+    ring: &Ring,
+    secret_index: usize,
+    message: &[u8],
+    seed: [u8; 64],
+) -> Result<S, Error>
+where
+    Hash: Digest<OutputSize = U64> + Clone + Default,
+    PrivateKey: Clone,
+    Ring: Clone,
+    S: SignWithRng<PrivateKey, Ring>,
+{
+    let mut nonces = HedgedRng::<Hash>::from_seed(seed);
+    let message: Vec<u8> = message.to_vec();
+    return S::sign_with_rng_unchecked::<Hash, HedgedRng<Hash>>(
+        k.clone(),
+        ring.clone(),
+        secret_index,
+        &message,
+        &mut nonces,
+    );
+}
+
+#[cfg(not(feature = "parallel"))]
+fn sign_seeded<Hash, PrivateKey, Ring, S>(
+    k: &PrivateKey,
+    ring: &Ring,
+    secret_index: usize,
+    messages: &[&[u8]],
+    seeds: Vec<[u8; 64]>,
+) -> Result<Vec<S>, Error>
+where
+    Hash: Digest<OutputSize = U64> + Clone + Default,
+    PrivateKey: Clone + Sync,
+    Ring: Clone + Sync,
+    S: SignWithRng<PrivateKey, Ring> + Send,
+{
+    return messages
+        .iter()
+        .zip(seeds)
+        .map(|(message, seed)| sign_one::<Hash, PrivateKey, Ring, S>(k, ring, secret_index, message, seed))
+        .collect();
+}
+
+#[cfg(feature = "parallel")]
+fn sign_seeded<Hash, PrivateKey, Ring, S>(
+    k: &PrivateKey,
+    ring: &Ring,
+    secret_index: usize,
+    messages: &[&[u8]],
+    seeds: Vec<[u8; 64]>,
+) -> Result<Vec<S>, Error>
+where
+    Hash: Digest<OutputSize = U64> + Clone + Default,
+    PrivateKey: Clone + Sync,
+    Ring: Clone + Sync,
+    S: SignWithRng<PrivateKey, Ring> + Send,
+{
+    use rayon::prelude::*;
+
+    return messages
+        .par_iter()
+        .zip(seeds)
+        .map(|(message, seed)| sign_one::<Hash, PrivateKey, Ring, S>(k, ring, secret_index, message, seed))
+        .collect();
+}
+
+#[cfg(test)]
+#[cfg(feature = "std")]
+#[cfg(all(feature = "blsag", feature = "mlsag"))]
+mod test {
+    extern crate rand;
+    extern crate sha2;
+
+    use curve25519_dalek::ristretto::RistrettoPoint;
+    use curve25519_dalek::scalar::Scalar;
+    use rand::rngs::OsRng;
+    use rand_core::impls;
+    use sha2::Sha512;
+
+    use super::*;
+    use crate::blsag::BLSAG;
+    use crate::mlsag::MLSAG;
+    use crate::traits::VerifyRef;
+
+    /// A generator that has lost its entropy
+    struct Broken;
+
+    impl RngCore for Broken {
+        fn next_u32(&mut self) -> u32 {
+            return impls::next_u32_via_fill(self);
+        }
+
+        fn next_u64(&mut self) -> u64 {
+            return impls::next_u64_via_fill(self);
+        }
+
+        fn fill_bytes(&mut self, dest: &mut [u8]) {
+            dest.fill(7);
+        }
+
+        fn try_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), rand_core::Error> {
+            self.fill_bytes(dest);
+            return Ok(());
+        }
+    }
+
+    impl CryptoRng for Broken {}
+
+    #[test]
+    fn sign_batch() {
+        let k: Scalar = Scalar::random(&mut OsRng);
+        let ring: Vec<RistrettoPoint> = (0..10).map(|_| RistrettoPoint::random(&mut OsRng)).collect();
+        let messages: Vec<&[u8]> = vec![b"first", b"second", b"first"];
+
+        let signatures = BLSAG::sign_batch::<Sha512, _>(&k, &ring, 1, &messages, &mut OsRng).unwrap();
+        assert_eq!(signatures.len(), 3);
+        for (signature, message) in signatures.iter().zip(&messages) {
+            assert!(signature.verify_ref::<Sha512>(&message.to_vec()));
+        }
+        // The same message twice still gets nonces of its own
+        assert_ne!(signatures[0].challenge, signatures[2].challenge);
+
+        // Without entropy the nonces differ between messages
+        let signatures = BLSAG::sign_batch::<Sha512, _>(&k, &ring, 1, &messages[..2], &mut Broken).unwrap();
+        assert_ne!(signatures[0].responses[0], signatures[1].responses[0]);
+
+        assert!(BLSAG::sign_batch::<Sha512, _>(&k, &ring, 1, &[], &mut OsRng).unwrap().is_empty());
+        assert_eq!(
+            BLSAG::sign_batch::<Sha512, _>(&k, &ring, 11, &messages, &mut OsRng).err(),
+            Some(Error::SecretIndexOutOfRange { index: 11, ring_size: 11 })
+        );
+
+        // Rings too small to hide the signer are rejected as `try_sign` rejects them
+        assert_eq!(
+            BLSAG::sign_batch::<Sha512, _>(&k, &ring[..1].to_vec(), 1, &messages, &mut OsRng).err(),
+            Some(Error::RingTooSmall { size: 2, minimum: 11 })
+        );
+
+        let ks: Vec<Scalar> = (0..2).map(|_| Scalar::random(&mut OsRng)).collect();
+        let ring: Vec<Vec<RistrettoPoint>> =
+            (0..2).map(|_| (0..2).map(|_| RistrettoPoint::random(&mut OsRng)).collect()).collect();
+        let policy = SignPolicy::new().allow_small_rings();
+        let signatures =
+            MLSAG::sign_batch_with_policy::<Sha512, _>(&policy, &ks, &ring, 0, &messages, &mut OsRng).unwrap();
+        for (signature, message) in signatures.iter().zip(&messages) {
+            assert!(signature.verify_ref::<Sha512>(&message.to_vec()));
+        }
+    }
+}
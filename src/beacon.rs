@@ -0,0 +1,111 @@
+//! Beacon bound signatures
+//!
+//! A signature whose challenges absorb a beacon value, such as the output of a verifiable delay
+//! function or a randomness beacon round, can not have been produced before that value existed.
+//! [`sign`] wraps the signature in a [`BeaconEnvelope`] carrying the beacon, and [`verify`] takes
+//! a callback that decides whether the beacon is valid (e.g. checks the VDF proof, or that the
+//! round is the one the auction closes at) before checking the signature. This gives "signed
+//! after time T" semantics, for example for anonymous sealed-bid auctions built on SAG.
+
+use crate::prelude::*;
+
+use digest::generic_array::typenum::U64;
+use digest::Digest;
+#[cfg(feature = "sign")]
+use rand_core::{CryptoRng, RngCore};
+
+use crate::codec;
+#[cfg(feature = "sign")]
+use crate::error::Error;
+use crate::traits::VerifyRef;
+#[cfg(feature = "sign")]
+use crate::traits::{Decoys, SignWithRng};
+
+/// A signature together with the beacon value bound into it
+#[derive(Clone)]
+pub struct BeaconEnvelope<S> {
+    pub beacon: Vec<u8>,
+    pub signature: S,
+}
+
+/// The message actually signed: the beacon before `message`
+fn bind_beacon(beacon: &[u8], message: &Vec<u8>) -> Vec<u8> {
+    let mut bytes: Vec<u8> = b"nazgul/beacon".to_vec();
+    codec::write_u32(&mut bytes, beacon.len());
+    bytes.extend_from_slice(beacon);
+    bytes.extend_from_slice(message);
+    return bytes;
+}
+
+/// Signs `message` with any scheme, binding `beacon` into every challenge. Takes the same
+/// arguments as the scheme's `sign` otherwise
+#[cfg(feature = "sign")]
+pub fn sign<
+    Hash: Digest<OutputSize = U64> + Clone + Default,
+    CSPRNG: CryptoRng + RngCore + Default,
+    PrivateKey,
+    Ring: Decoys,
+    S: SignWithRng<PrivateKey, Ring>,
+>(
+    k: PrivateKey,
+    ring: Ring,
+    secret_index: usize,
+    beacon: &[u8],
+    message: &Vec<u8>,
+) -> Result<BeaconEnvelope<S>, Error> {
+    let mut csprng = CSPRNG::default();
+    let signature =
+        S::sign_with_rng::<Hash, CSPRNG>(k, ring, secret_index, &bind_beacon(beacon, message), &mut csprng)?;
+    return Ok(BeaconEnvelope {
+        beacon: beacon.to_vec(),
+        signature: signature,
+    });
+}
+
+/// Verifies an `envelope` made with [`sign`], accepting it only if `validate_beacon` accepts the
+/// beacon it carries
+pub fn verify<Hash: Digest<OutputSize = U64> + Clone + Default, S: VerifyRef>(
+    envelope: BeaconEnvelope<S>,
+    message: &Vec<u8>,
+    validate_beacon: impl FnOnce(&[u8]) -> bool,
+) -> bool {
+    if !validate_beacon(&envelope.beacon) {
+        return false;
+    }
+    let bound_message = bind_beacon(&envelope.beacon, message);
+    return envelope.signature.verify_ref::<Hash>(&bound_message);
+}
+
+#[cfg(test)]
+#[cfg(feature = "std")]
+#[cfg(feature = "sign")]
+#[cfg(feature = "sag")]
+mod test {
+    extern crate rand;
+    extern crate sha2;
+
+    use curve25519_dalek::ristretto::RistrettoPoint;
+    use curve25519_dalek::scalar::Scalar;
+    use rand::rngs::OsRng;
+    use sha2::Sha512;
+
+    use super::*;
+    use crate::sag::SAG;
+
+    #[test]
+    fn beacon() {
+        let k: Scalar = Scalar::random(&mut OsRng);
+        let ring: Vec<RistrettoPoint> = (0..10).map(|_| RistrettoPoint::random(&mut OsRng)).collect();
+        let message: Vec<u8> = b"Bid 100".to_vec();
+        let round: &[u8] = b"beacon round 1000";
+
+        let envelope: BeaconEnvelope<SAG> = super::sign::<Sha512, OsRng, _, _, _>(k, ring, 1, round, &message).unwrap();
+        assert!(super::verify::<Sha512, _>(envelope.clone(), &message, |beacon| beacon == round));
+        // The beacon is rejected by the callback
+        assert!(!super::verify::<Sha512, _>(envelope.clone(), &message, |_| false));
+        // Swapping in another beacon the callback would accept breaks the signature
+        let mut replaced = envelope;
+        replaced.beacon = b"beacon round 1001".to_vec();
+        assert!(!super::verify::<Sha512, _>(replaced, &message, |_| true));
+    }
+}
@@ -0,0 +1,102 @@
+//! Calibration measurements
+//!
+//! Signs or verifies one MLSAG signature over random keys and reports how long it took on the
+//! machine running it, next to its [`crate::weight`]. Applications can run these at startup on
+//! their target hardware to pick the largest ring that fits their latency budget, since the time
+//! scales with the weight. Rings of any size are measured, whatever the sign policy. Only available
+//! with the `std` feature.
+
+use crate::prelude::*;
+
+use std::time::{Duration, Instant};
+
+use curve25519_dalek::ristretto::RistrettoPoint;
+use curve25519_dalek::scalar::Scalar;
+use digest::generic_array::typenum::U64;
+use digest::Digest;
+use rand_core::OsRng;
+
+use crate::error::Error;
+use crate::mlsag::MLSAG;
+use crate::traits::{SignWithRng, VerifyRef};
+use crate::weight;
+
+/// The outcome of one measured operation
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Measurement {
+    /// Wall-clock time the operation took
+    pub elapsed: Duration,
+    /// Scalar multiplications, hashes to point and challenge hashes done, see [`crate::weight`]
+    pub weight: u64,
+}
+
+/// Random private keys for `cols` columns and `ring_size - 1` rows of random decoys
+fn synthetic(ring_size: usize, cols: usize) -> (Vec<Scalar>, Vec<Vec<RistrettoPoint>>) {
+    let mut csprng = OsRng;
+    let ks: Vec<Scalar> = (0..cols).map(|_| Scalar::random(&mut csprng)).collect();
+    let ring: Vec<Vec<RistrettoPoint>> = (0..ring_size.saturating_sub(1))
+        .map(|_| (0..cols).map(|_| RistrettoPoint::random(&mut csprng)).collect())
+        .collect();
+    return (ks, ring);
+}
+
+/// Measures signing a synthetic MLSAG with `ring_size` members (the signer included, at least 2)
+/// of `cols` keys each. Sizes signing rejects are its [`Error`]
+pub fn measure_sign<Hash: Digest<OutputSize = U64> + Clone + Default>(
+    ring_size: usize,
+    cols: usize,
+) -> Result<Measurement, Error> {
+    let (ks, ring) = synthetic(ring_size, cols);
+    let message: Vec<u8> = b"nazgul calibration".to_vec();
+
+    let start = Instant::now();
+    let signature = MLSAG::sign_with_rng_unchecked::<Hash, _>(ks, ring, 0, &message, &mut OsRng)?;
+    let elapsed = start.elapsed();
+
+    // Signing does the work of verifying, with the signer's slot computed from a nonce instead
+    return Ok(Measurement {
+        elapsed: elapsed,
+        weight: weight::mlsag(signature.ring.len(), cols),
+    });
+}
+
+/// Measures verifying a synthetic MLSAG with `ring_size` members (the signer included, at least
+/// 2) of `cols` keys each. Only the verification is timed. Sizes signing rejects are its [`Error`]
+pub fn measure_verify<Hash: Digest<OutputSize = U64> + Clone + Default>(
+    ring_size: usize,
+    cols: usize,
+) -> Result<Measurement, Error> {
+    let (ks, ring) = synthetic(ring_size, cols);
+    let message: Vec<u8> = b"nazgul calibration".to_vec();
+    let signature = MLSAG::sign_with_rng_unchecked::<Hash, _>(ks, ring, 0, &message, &mut OsRng)?;
+
+    let start = Instant::now();
+    let result = signature.verify_ref::<Hash>(&message);
+    let elapsed = start.elapsed();
+    debug_assert!(result);
+
+    return Ok(Measurement {
+        elapsed: elapsed,
+        weight: weight::mlsag(ring_size, cols),
+    });
+}
+
+#[cfg(test)]
+mod test {
+    extern crate sha2;
+
+    use sha2::Sha512;
+
+    use super::*;
+
+    #[test]
+    fn bench() {
+        let small = measure_verify::<Sha512>(2, 1).unwrap();
+        let large = measure_verify::<Sha512>(8, 2).unwrap();
+        assert_eq!(small.weight, weight::mlsag(2, 1));
+        assert!(large.weight > small.weight);
+        assert_eq!(measure_sign::<Sha512>(8, 2).unwrap().weight, large.weight);
+        assert!(measure_sign::<Sha512>(0, 0).is_err());
+        assert!(measure_verify::<Sha512>(0, 0).is_err());
+    }
+}
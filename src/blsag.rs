@@ -1,114 +1,338 @@
-use crate::traits::{KeyImageGen, Link, Sign, Verify};
+use crate::error::Error;
+use crate::scalars::{Challenge, Response};
+#[cfg(feature = "sign")]
+use crate::scalars;
+use crate::traits::{check_responses, ChallengeChain, KeyImageGenRef, LinkRef, TryVerify, VerifyRef, Weight};
+#[cfg(feature = "sign")]
+use crate::traits::{
+    check_secret_index, take_signer, Scheme, SignWithFullRing, SignWithHiddenIndex, SignWithRng, TrySign,
+};
+use crate::weight;
+use crate::generator::{Basepoint, Generator, VerifyWithGenerator};
+#[cfg(feature = "sign")]
+use crate::generator::SignWithGenerator;
+use crate::hash_to_point::{DefaultHashToPoint, HashToPoint};
+use crate::hash_to_scalar::{HashToScalar, WideReduction};
+use crate::codec::{self, Codec, Reader};
+use crate::ops;
+use crate::proofs::key_image_binding::KeyImageBinding;
 use crate::prelude::*;
+#[cfg(feature = "allocator-api")]
+use crate::arena::{Allocator, VerifyIn};
+use alloc::boxed::Box;
+use core::ops::Range;
 use curve25519_dalek::constants;
 use curve25519_dalek::ristretto::RistrettoPoint;
 use curve25519_dalek::scalar::Scalar;
 use digest::generic_array::typenum::U64;
 use digest::Digest;
+#[cfg(feature = "sign")]
 use rand_core::{CryptoRng, RngCore};
-use curve25519_dalek::traits::MultiscalarMul;
 
 /// Back’s Linkable Spontaneous Anonymous Group (bLSAG) signatures
 /// > This an enhanced version of the LSAG algorithm where linkability
-/// is independent of the ring’s decoy members.
+/// > is independent of the ring’s decoy members.
 ///
 /// Please read tests at the bottom of the source code for this module for examples on how to use
 /// it
+#[derive(Clone)]
 pub struct BLSAG {
-    pub challenge: Scalar,
-    pub responses: Vec<Scalar>,
+    pub challenge: Challenge,
+    pub responses: Vec<Response>,
     pub ring: Vec<RistrettoPoint>,
     pub key_image: RistrettoPoint,
+    /// Boxed so that signatures without one, and `RingSignature`, do not carry its 64 bytes, see
+    /// [`BLSAG::ring_digest`]
+    ring_digest: Option<Box<[u8; 64]>>,
 }
 
-impl KeyImageGen<Scalar, RistrettoPoint> for BLSAG {
+/// Hash state shared by all challenges `H_n(m, ....)`
+///
+/// # Advanced
+///
+/// This and [`compute_challenge_step`] are the transcript math used by `sign` and `verify`,
+/// exposed for protocols that compute bLSAG challenges over data produced elsewhere. Most users
+/// should only ever need `sign` and `verify`.
+pub fn compute_challenge_prefix<Hash: Digest<OutputSize = U64> + Clone + Default>(
+    message: &[u8],
+) -> Hash {
+    let mut message_hash = Hash::default();
+    message_hash.update(message);
+    return message_hash;
+}
+
+/// The challenge `c_(i+1) = H_n(m, point_a, point_b)` where `point_a = r_i G + c_i K_i` and
+/// `point_b = r_i H_p(K_i) + c_i K~`, given the `hash_state` from [`compute_challenge_prefix`]
+///
+/// # Advanced
+///
+/// See [`compute_challenge_prefix`]
+pub fn compute_challenge_step<Hash: Digest<OutputSize = U64>>(
+    hash_state: Hash,
+    point_a: &RistrettoPoint,
+    point_b: &RistrettoPoint,
+) -> Scalar {
+    return compute_challenge_step_with::<Hash, WideReduction>(hash_state, point_a, point_b);
+}
+
+/// As [`compute_challenge_step`] with the hash to scalar `Hs`, see [`crate::hash_to_scalar`]
+///
+/// # Advanced
+///
+/// See [`compute_challenge_prefix`]
+pub fn compute_challenge_step_with<Hash: Digest<OutputSize = U64>, Hs: HashToScalar>(
+    mut hash_state: Hash,
+    point_a: &RistrettoPoint,
+    point_b: &RistrettoPoint,
+) -> Scalar {
+    hash_state.update(point_a.compress().as_bytes());
+    hash_state.update(point_b.compress().as_bytes());
+    return Hs::hash_to_scalar(hash_state);
+}
+
+/// The message the proofs of [`BLSAG::derive_key_image_with_proof`] are bound to, so they can not
+/// pass for proofs made for another purpose
+const KEY_IMAGE_DERIVATION: &[u8] = b"nazgul/blsag/key_image_derivation";
+
+impl KeyImageGenRef<Scalar, RistrettoPoint> for BLSAG {
     /// Some signature schemes require the key images to be signed as well.
     /// Use this method to generate them
-    fn generate_key_image<Hash: Digest<OutputSize = U64> + Clone + Default>(
+    fn generate_key_image_ref<Hash: Digest<OutputSize = U64> + Clone + Default>(
+        k: &Scalar,
+    ) -> RistrettoPoint {
+        return BLSAG::generate_key_image_with::<Hash, DefaultHashToPoint>(*k);
+    }
+}
+
+#[cfg(feature = "sign")]
+impl SignWithRng<Scalar, Vec<RistrettoPoint>> for BLSAG {
+    /// To sign you need `k` your private key, and `ring` which is the public keys of everyone
+    /// except you. You are signing the `message`
+    fn sign_with_rng_unchecked<Hash: Digest<OutputSize = U64> + Clone + Default, CSPRNG: CryptoRng + RngCore>(
+        k: Scalar,
+        ring: Vec<RistrettoPoint>,
+        secret_index: usize,
+        message: &Vec<u8>,
+        csprng: &mut CSPRNG,
+    ) -> Result<BLSAG, Error> {
+        return BLSAG::sign_with_csprng::<Hash, CSPRNG, Basepoint, DefaultHashToPoint, WideReduction>(
+            k,
+            ring,
+            secret_index,
+            message,
+            csprng,
+        );
+    }
+}
+
+#[cfg(feature = "sign")]
+impl SignWithGenerator<Scalar, Vec<RistrettoPoint>> for BLSAG {
+    fn sign_with_generator_and_rng<
+        Hash: Digest<OutputSize = U64> + Clone + Default,
+        CSPRNG: CryptoRng + RngCore,
+        G: Generator,
+    >(
+        k: Scalar,
+        ring: Vec<RistrettoPoint>,
+        secret_index: usize,
+        message: &Vec<u8>,
+        csprng: &mut CSPRNG,
+    ) -> Result<BLSAG, Error> {
+        return BLSAG::sign_with_csprng::<Hash, CSPRNG, G, DefaultHashToPoint, WideReduction>(
+            k,
+            ring,
+            secret_index,
+            message,
+            csprng,
+        );
+    }
+}
+
+impl BLSAG {
+    /// As [`KeyImageGenRef::generate_key_image_ref`] with the hash to point `Hp`, see
+    /// [`crate::hash_to_point`]
+    pub fn generate_key_image_with<Hash: Digest<OutputSize = U64> + Clone + Default, Hp: HashToPoint>(
         k: Scalar,
     ) -> RistrettoPoint {
-        let k_point: RistrettoPoint = k * constants::RISTRETTO_BASEPOINT_POINT;
+        let k_point: RistrettoPoint = ops::mul_base(&k, &constants::RISTRETTO_BASEPOINT_POINT);
 
-        let key_image: RistrettoPoint =
-            k * RistrettoPoint::from_hash(Hash::default().chain_update(k_point.compress().as_bytes()));
+        let key_image: RistrettoPoint = ops::mul(&k, &Hp::hash_to_point::<Hash>(&k_point));
 
         return key_image;
     }
-}
 
-impl Sign<Scalar, Vec<RistrettoPoint>> for BLSAG {
-    /// To sign you need `k` your private key, and `ring` which is the public keys of everyone
-    /// except you. You are signing the `message`
-    fn sign<
+    /// The key image of `k`, as [`KeyImageGenRef::generate_key_image_ref`], together with a proof that
+    /// it belongs to the public key of `k`. With the key holder's cooperation an auditor can
+    /// then check with [`BLSAG::verify_key_image_derivation`] which key image a public key has,
+    /// without learning `k`.
+    #[cfg(feature = "sign")]
+    pub fn derive_key_image_with_proof<
         Hash: Digest<OutputSize = U64> + Clone + Default,
         CSPRNG: CryptoRng + RngCore + Default,
     >(
         k: Scalar,
-        mut ring: Vec<RistrettoPoint>,
+    ) -> (RistrettoPoint, KeyImageBinding) {
+        let proof = KeyImageBinding::prove::<Hash, CSPRNG>(k, &KEY_IMAGE_DERIVATION.to_vec());
+        return (proof.key_image, proof);
+    }
+
+    /// Whether `proof`, made by [`BLSAG::derive_key_image_with_proof`], shows that `key_image`
+    /// is the key image of `public_key`
+    pub fn verify_key_image_derivation<Hash: Digest<OutputSize = U64> + Clone + Default>(
+        public_key: &RistrettoPoint,
+        key_image: &RistrettoPoint,
+        proof: &KeyImageBinding,
+    ) -> bool {
+        return proof.public_key == *public_key
+            && proof.key_image == *key_image
+            && proof.verify::<Hash>(&KEY_IMAGE_DERIVATION.to_vec());
+    }
+
+    /// A digest binding every member of `ring`, in order
+    pub fn compute_ring_digest<Hash: Digest<OutputSize = U64> + Clone + Default>(ring: &[RistrettoPoint]) -> [u8; 64] {
+        let mut h: Hash = Hash::default();
+        h.update(b"nazgul/blsag/ring_digest");
+        h.update((ring.len() as u32).to_le_bytes());
+        for k_point in ring {
+            h.update(k_point.compress().as_bytes());
+        }
+        let mut ring_digest = [0u8; 64];
+        ring_digest.copy_from_slice(&h.finalize());
+        return ring_digest;
+    }
+
+    /// Digest of `ring` from [`BLSAG::compute_ring_digest`], if one was attached with
+    /// [`BLSAG::attach_ring_digest`] for storing the ring apart from the signature.
+    ///
+    /// This is an unauthenticated hint: the hash is unkeyed and not part of the challenges, so
+    /// anyone can recompute it over another ring or strip it, and signatures verify the same with
+    /// or without it. It only catches a ring and a signature mismatched by accident; the ring
+    /// itself is what the challenges bind.
+    pub fn ring_digest(&self) -> Option<&[u8; 64]> {
+        return self.ring_digest.as_deref();
+    }
+
+    /// The signature with `ring_digest` set to `ring_digest`, whether or not it is that of the ring
+    pub(crate) fn with_ring_digest(mut self, ring_digest: [u8; 64]) -> BLSAG {
+        self.ring_digest = Some(Box::new(ring_digest));
+        return self;
+    }
+
+    /// Sets `ring_digest` to the digest of the ring, so that a ring stored apart from the
+    /// signature and put back later can be checked to be the one signed over
+    pub fn attach_ring_digest<Hash: Digest<OutputSize = U64> + Clone + Default>(mut self) -> BLSAG {
+        self.ring_digest = Some(Box::new(BLSAG::compute_ring_digest::<Hash>(&self.ring)));
+        return self;
+    }
+
+    /// Whether the ring is the one `ring_digest` was computed over, or there is no digest
+    pub fn ring_digest_matches<Hash: Digest<OutputSize = U64> + Clone + Default>(&self) -> bool {
+        return match &self.ring_digest {
+            Some(ring_digest) => **ring_digest == BLSAG::compute_ring_digest::<Hash>(&self.ring),
+            None => true,
+        };
+    }
+
+    /// As [`crate::traits::Sign::sign`] with the hash to point `Hp` and the hash to scalar `Hs`, see
+    /// [`crate::hash_to_point`] and [`crate::hash_to_scalar`]
+    #[cfg(feature = "sign")]
+    pub fn sign_with<
+        Hash: Digest<OutputSize = U64> + Clone + Default,
+        CSPRNG: CryptoRng + RngCore + Default,
+        Hp: HashToPoint,
+        Hs: HashToScalar,
+    >(
+        k: Scalar,
+        ring: Vec<RistrettoPoint>,
         secret_index: usize,
         message: &Vec<u8>,
-    ) -> BLSAG {
+    ) -> Result<BLSAG, Error> {
         let mut csprng = CSPRNG::default();
+        return BLSAG::sign_with_csprng::<Hash, CSPRNG, Basepoint, Hp, Hs>(k, ring, secret_index, message, &mut csprng);
+    }
+
+    /// As [`BLSAG::sign_with`] with randomness drawn from `csprng` and the generator `G`
+    #[cfg(feature = "sign")]
+    fn sign_with_csprng<
+        Hash: Digest<OutputSize = U64> + Clone + Default,
+        CSPRNG: CryptoRng + RngCore,
+        G: Generator,
+        Hp: HashToPoint,
+        Hs: HashToScalar,
+    >(
+        k: Scalar,
+        ring: Vec<RistrettoPoint>,
+        secret_index: usize,
+        message: &Vec<u8>,
+        csprng: &mut CSPRNG,
+    ) -> Result<BLSAG, Error> {
+        let message_hash: Hash = compute_challenge_prefix::<Hash>(message);
+        return BLSAG::sign_with_map::<Hash, CSPRNG, Hs, _>(
+            k,
+            ring,
+            secret_index,
+            message_hash,
+            csprng,
+            &G::generator(),
+            |point| Hp::hash_to_point::<Hash>(point),
+        );
+    }
+
+    /// As [`BLSAG::sign_with_csprng`], given the hash state with the message already absorbed, see
+    /// [`crate::session`], and with the hash to point a function rather than a type, for mappings
+    /// that depend on more than the point, see [`crate::claim`], and the generator a point `g`
+    #[cfg(feature = "sign")]
+    pub(crate) fn sign_with_map<
+        Hash: Digest<OutputSize = U64> + Clone + Default,
+        CSPRNG: CryptoRng + RngCore,
+        Hs: HashToScalar,
+        F: Fn(&RistrettoPoint) -> RistrettoPoint,
+    >(
+        k: Scalar,
+        mut ring: Vec<RistrettoPoint>,
+        secret_index: usize,
+        message_hash: Hash,
+        csprng: &mut CSPRNG,
+        g: &RistrettoPoint,
+        hash_to_point: F,
+    ) -> Result<BLSAG, Error> {
+        check_secret_index(ring.len(), secret_index)?;
 
         // Provers public key
-        let k_point: RistrettoPoint = k * constants::RISTRETTO_BASEPOINT_POINT;
+        let k_point: RistrettoPoint = ops::mul_base(&k, g);
 
-        let key_image: RistrettoPoint = BLSAG::generate_key_image::<Hash>(k);
+        let key_image: RistrettoPoint = ops::mul(&k, &hash_to_point(&k_point));
 
         let n = ring.len() + 1;
 
         ring.insert(secret_index, k_point);
 
-        let a: Scalar = Scalar::random(&mut csprng);
+        let a: Scalar = Scalar::random(csprng);
 
-        let mut rs: Vec<Scalar> = (0..n).map(|_| Scalar::random(&mut csprng)).collect();
+        let mut rs: Vec<Scalar> = (0..n).map(|_| Scalar::random(csprng)).collect();
 
         let mut cs: Vec<Scalar> = (0..n).map(|_| Scalar::ZERO).collect();
 
         // Hash of message is shared by all challenges H_n(m, ....)
-        let mut message_hash = Hash::default();
-
-        message_hash.update(message);
-
-        let mut hashes: Vec<Hash> = (0..n).map(|_| message_hash.clone()).collect();
-
-        hashes[(secret_index + 1) % n].update(
-            (a * constants::RISTRETTO_BASEPOINT_POINT)
-                .compress()
-                .as_bytes(),
+        cs[(secret_index + 1) % n] = compute_challenge_step_with::<Hash, Hs>(
+            message_hash.clone(),
+            &ops::mul_base(&a, g),
+            &ops::mul(&a, &hash_to_point(&k_point)),
         );
-        hashes[(secret_index + 1) % n].update(
-            (a * RistrettoPoint::from_hash(Hash::default().chain_update(k_point.compress().as_bytes())))
-                .compress()
-                .as_bytes(),
-        );
-        cs[(secret_index + 1) % n] = Scalar::from_hash(hashes[(secret_index + 1) % n].clone());
 
         let mut i = (secret_index + 1) % n;
 
         loop {
-            hashes[(i + 1) % n].update(
-                RistrettoPoint::multiscalar_mul(
+            cs[(i + 1) % n] = compute_challenge_step_with::<Hash, Hs>(
+                message_hash.clone(),
+                &ops::multiscalar_mul(
                     &[rs[i % n], cs[i % n]],
-                    &[constants::RISTRETTO_BASEPOINT_POINT, ring[i % n]]
-                )
-                    .compress()
-                    .as_bytes(),
-            );
-            hashes[(i + 1) % n].update(
-                RistrettoPoint::multiscalar_mul(
+                    &[*g, ring[i % n]]
+                ),
+                &ops::multiscalar_mul(
                     &[rs[i % n], cs[i % n]],
-                    &[
-                        RistrettoPoint::from_hash(
-                            Hash::default()
-                                .chain_update(ring[i % n].compress().as_bytes())
-                        ),
-                        key_image
-                    ])
-                    .compress()
-                    .as_bytes(),
+                    &[hash_to_point(&ring[i % n]), key_image]),
             );
-            cs[(i + 1) % n] = Scalar::from_hash(hashes[(i + 1) % n].clone());
 
             if secret_index >= 1 && i % n == (secret_index - 1) % n {
                 break;
@@ -121,65 +345,320 @@ impl Sign<Scalar, Vec<RistrettoPoint>> for BLSAG {
 
         rs[secret_index] = a - (cs[secret_index] * k);
 
-        return BLSAG {
-            challenge: cs[0],
-            responses: rs,
+        return Ok(BLSAG {
+            challenge: Challenge(cs[0]),
+            responses: scalars::responses(rs),
             ring: ring,
             key_image: key_image,
-        };
+            ring_digest: None,
+        });
     }
-}
 
-impl Verify for BLSAG {
-    /// To verify a `signature` you need the `message` too
-    fn verify<Hash: Digest<OutputSize = U64> + Clone + Default>(
+    /// As [`VerifyRef::verify_ref`] with the hash to point `Hp` and the hash to scalar `Hs`, see
+    /// [`crate::hash_to_point`] and [`crate::hash_to_scalar`]
+    pub fn verify_with<Hash: Digest<OutputSize = U64> + Clone + Default, Hp: HashToPoint, Hs: HashToScalar>(
         signature: BLSAG,
         message: &Vec<u8>,
     ) -> bool {
-        let mut reconstructed_c: Scalar = signature.challenge;
-        let n = signature.ring.len();
-        for j in 0..n {
-            let mut h: Hash = Hash::default();
-            h.update(message);
-            h.update(
-                RistrettoPoint::multiscalar_mul(
-                    &[signature.responses[j], reconstructed_c],
-                    &[constants::RISTRETTO_BASEPOINT_POINT, signature.ring[j]]
-                )
-                    .compress()
-                    .as_bytes(),
-            );
+        return signature.verify_ref_with::<Hash, Hp, Hs>(message);
+    }
+
+    /// As [`BLSAG::verify_with`], by reference
+    pub fn verify_ref_with<Hash: Digest<OutputSize = U64> + Clone + Default, Hp: HashToPoint, Hs: HashToScalar>(
+        &self,
+        message: &Vec<u8>,
+    ) -> bool {
+        return self.verify_ref_with_generator::<Hash, Basepoint, Hp, Hs>(message);
+    }
+
+    /// As [`BLSAG::verify_ref_with`] with the generator `G`
+    fn verify_ref_with_generator<
+        Hash: Digest<OutputSize = U64> + Clone + Default,
+        G: Generator,
+        Hp: HashToPoint,
+        Hs: HashToScalar,
+    >(
+        &self,
+        message: &Vec<u8>,
+    ) -> bool {
+        // Signatures built by hand may not have one response per member
+        if self.check_shape().is_err() || !self.ring_digest_matches::<Hash>() {
+            return false;
+        }
+        let n = self.ring.len();
+        let message_hash: Hash = compute_challenge_prefix::<Hash>(message);
+        let g: RistrettoPoint = G::generator();
+        let reconstructed_c: Scalar =
+            self.reconstruct_challenge_with_map::<Hash, Hs, _>(message_hash, 0..n, self.challenge.0, &g, |point| {
+                Hp::hash_to_point::<Hash>(point)
+            });
+        return self.challenge.0 == reconstructed_c;
+    }
 
-            h.update(
-                RistrettoPoint::multiscalar_mul(
-                    &[signature.responses[j], reconstructed_c],
-                    &[RistrettoPoint::from_hash(
-                            Hash::default().chain_update(
-                                signature.ring[j].compress().as_bytes()
-                            ),
-                        ),
-                        signature.key_image
-                    ]
-                )
-                    .compress()
-                    .as_bytes(),
+    /// As [`ChallengeChain::reconstruct_challenge`], given the hash state with the message already
+    /// absorbed, see [`crate::session`]
+    pub(crate) fn reconstruct_challenge_from_prefix<
+        Hash: Digest<OutputSize = U64> + Clone + Default,
+        Hp: HashToPoint,
+        Hs: HashToScalar,
+    >(
+        &self,
+        message_hash: Hash,
+        slots: Range<usize>,
+        challenge: Scalar,
+    ) -> Scalar {
+        let g: RistrettoPoint = Basepoint::generator();
+        return self.reconstruct_challenge_with_map::<Hash, Hs, _>(message_hash, slots, challenge, &g, |point| {
+            Hp::hash_to_point::<Hash>(point)
+        });
+    }
+
+    /// As [`BLSAG::reconstruct_challenge_from_prefix`], with the hash to point a function, see
+    /// [`BLSAG::sign_with_map`]
+    pub(crate) fn reconstruct_challenge_with_map<
+        Hash: Digest<OutputSize = U64> + Clone + Default,
+        Hs: HashToScalar,
+        F: Fn(&RistrettoPoint) -> RistrettoPoint,
+    >(
+        &self,
+        message_hash: Hash,
+        slots: Range<usize>,
+        challenge: Scalar,
+        g: &RistrettoPoint,
+        hash_to_point: F,
+    ) -> Scalar {
+        let mut reconstructed_c: Scalar = challenge;
+        for j in slots {
+            reconstructed_c = compute_challenge_step_with::<Hash, Hs>(
+                message_hash.clone(),
+                &ops::multiscalar_mul(
+                    &[self.responses[j].0, reconstructed_c],
+                    &[*g, self.ring[j]]
+                ),
+                &ops::multiscalar_mul(
+                    &[self.responses[j].0, reconstructed_c],
+                    &[hash_to_point(&self.ring[j]), self.key_image]
+                ),
             );
-            reconstructed_c = Scalar::from_hash(h);
         }
 
-        return signature.challenge == reconstructed_c;
+        return reconstructed_c;
+    }
+}
+
+impl ChallengeChain for BLSAG {
+    fn ring_size(&self) -> usize {
+        return self.ring.len();
+    }
+
+    fn challenge(&self) -> Scalar {
+        return self.challenge.0;
+    }
+
+    /// Starting from `challenge` entering the first slot in `slots`, recomputes the challenge
+    /// leaving the last one
+    fn reconstruct_challenge<Hash: Digest<OutputSize = U64> + Clone + Default>(
+        &self,
+        message: &Vec<u8>,
+        slots: Range<usize>,
+        challenge: Scalar,
+    ) -> Option<Scalar> {
+        // A malformed signature has no chain to run
+        if self.check_shape().is_err() || slots.end > self.ring.len() {
+            return None;
+        }
+        let message_hash: Hash = compute_challenge_prefix::<Hash>(message);
+        return Some(self.reconstruct_challenge_from_prefix::<Hash, DefaultHashToPoint, WideReduction>(
+            message_hash,
+            slots,
+            challenge,
+        ));
+    }
+
+    fn is_consistent<Hash: Digest<OutputSize = U64> + Clone + Default>(&self) -> bool {
+        return self.ring_digest_matches::<Hash>();
+    }
+}
+
+impl VerifyRef for BLSAG {
+    /// To verify a `signature` you need the `message` too
+    fn verify_ref<Hash: Digest<OutputSize = U64> + Clone + Default>(&self, message: &Vec<u8>) -> bool {
+        return self.verify_ref_with::<Hash, DefaultHashToPoint, WideReduction>(message);
+    }
+}
+
+#[cfg(feature = "allocator-api")]
+impl VerifyIn for BLSAG {
+    /// Verifying allocates nothing, so `alloc` goes unused
+    fn verify_in<Hash: Digest<OutputSize = U64> + Clone + Default, A: Allocator>(
+        &self,
+        message: &Vec<u8>,
+        _alloc: A,
+    ) -> bool {
+        return self.verify_ref::<Hash>(message);
+    }
+}
+
+impl VerifyWithGenerator for BLSAG {
+    fn verify_with_generator<Hash: Digest<OutputSize = U64> + Clone + Default, G: Generator>(
+        &self,
+        message: &Vec<u8>,
+    ) -> bool {
+        return self.verify_ref_with_generator::<Hash, G, DefaultHashToPoint, WideReduction>(message);
+    }
+}
+
+#[cfg(feature = "sign")]
+impl TrySign<Scalar, Vec<RistrettoPoint>> for BLSAG {
+    fn check_sign_input(_k: &Scalar, ring: &Vec<RistrettoPoint>, secret_index: usize) -> Result<(), Error> {
+        return check_secret_index(ring.len(), secret_index);
     }
 }
 
-impl Link for BLSAG {
+#[cfg(feature = "sign")]
+impl SignWithHiddenIndex<Scalar, RistrettoPoint> for BLSAG {}
+
+#[cfg(feature = "sign")]
+impl SignWithFullRing<Scalar, Vec<RistrettoPoint>> for BLSAG {
+    fn take_signer(k: &Scalar, ring: &mut Vec<RistrettoPoint>, secret_index: usize) -> Result<(), Error> {
+        return take_signer(ring, secret_index, &(k * constants::RISTRETTO_BASEPOINT_POINT));
+    }
+}
+
+#[cfg(feature = "sign")]
+impl Scheme for BLSAG {
+    type SecretKey = Scalar;
+    type Ring = Vec<RistrettoPoint>;
+
+    fn sign_with_key<Hash: Digest<OutputSize = U64> + Clone + Default, CSPRNG: CryptoRng + RngCore + Default>(
+        k: Scalar,
+        ring: Vec<RistrettoPoint>,
+        secret_index: usize,
+        message: &Vec<u8>,
+    ) -> Result<BLSAG, Error> {
+        let mut csprng = CSPRNG::default();
+        return BLSAG::sign_with_rng::<Hash, CSPRNG>(k, ring, secret_index, message, &mut csprng);
+    }
+
+    fn try_sign_with_key_and_rng<Hash: Digest<OutputSize = U64> + Clone + Default, CSPRNG: CryptoRng + RngCore>(
+        k: Scalar,
+        ring: Vec<RistrettoPoint>,
+        secret_index: usize,
+        message: &Vec<u8>,
+        csprng: &mut CSPRNG,
+    ) -> Result<BLSAG, Error> {
+        return BLSAG::sign_with_rng_unchecked::<Hash, CSPRNG>(k, ring, secret_index, message, csprng);
+    }
+}
+
+impl TryVerify for BLSAG {
+    fn check_shape(&self) -> Result<(), Error> {
+        return check_responses(self.ring.len(), self.responses.len());
+    }
+}
+
+impl LinkRef for BLSAG {
     /// This is for linking two signatures and checking if they are signed by the same person
-    fn link(signature_1: BLSAG, signature_2: BLSAG) -> bool {
-        return signature_1.key_image == signature_2.key_image;
+    fn link_ref(&self, other: &BLSAG) -> bool {
+        return self.key_image == other.key_image;
     }
 }
 
+impl Weight for BLSAG {
+    /// Approximate cost of verifying this signature, see [`crate::weight`]
+    fn weight(&self) -> u64 {
+        return weight::blsag(self.ring.len());
+    }
+}
+
+impl BLSAG {
+    /// Assembles a signature from its fields, returning `None` if the ring and responses do
+    /// not agree on the ring size
+    pub fn from_parts(
+        challenge: Challenge,
+        responses: Vec<Response>,
+        ring: Vec<RistrettoPoint>,
+        key_image: RistrettoPoint,
+    ) -> Option<BLSAG> {
+        if ring.is_empty() || responses.len() != ring.len() {
+            return None;
+        }
+        return Some(BLSAG::from_parts_unchecked(challenge, responses, ring, key_image));
+    }
+
+    /// Assembles a signature from its fields without checking them
+    ///
+    /// This is for fields that have been checked before, e.g. by [`BLSAG::from_parts`] or by
+    /// decoding, and are read back from storage that is trusted. A signature assembled from
+    /// fields that do not agree is rejected: verifying it returns false.
+    pub fn from_parts_unchecked(
+        challenge: Challenge,
+        responses: Vec<Response>,
+        ring: Vec<RistrettoPoint>,
+        key_image: RistrettoPoint,
+    ) -> BLSAG {
+        return BLSAG {
+            challenge: challenge,
+            responses: responses,
+            ring: ring,
+            key_image: key_image,
+            ring_digest: None,
+        };
+    }
+}
+
+impl Codec for BLSAG {
+    /// Encodes the signature canonically, see [`crate::codec`]
+    fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes: Vec<u8> = Vec::new();
+        codec::write_challenge(&mut bytes, &self.challenge);
+        codec::write_u32(&mut bytes, self.ring.len());
+        for response in &self.responses {
+            codec::write_response(&mut bytes, response);
+        }
+        for k_point in &self.ring {
+            codec::write_point(&mut bytes, k_point);
+        }
+        codec::write_point(&mut bytes, &self.key_image);
+        if let Some(ring_digest) = &self.ring_digest {
+            bytes.extend_from_slice(&ring_digest[..]);
+        }
+        return bytes;
+    }
+
+    /// Decodes a signature, returning the [`Error`] describing why if `bytes` is not a canonical encoding
+    ///
+    /// The ring digest, if any, follows the key image, so signatures without one encode as
+    /// they always have.
+    fn decode(bytes: &[u8]) -> Result<BLSAG, Error> {
+        let mut reader = Reader::new(bytes);
+        let challenge = reader.read_challenge()?;
+        let n = reader.read_len(64)?;
+        let responses = reader.read_responses(n)?;
+        let ring = reader.read_points(n)?;
+        let key_image = reader.read_point()?;
+        let mut ring_digest: Option<Box<[u8; 64]>> = None;
+        if !reader.is_empty() {
+            let mut digest = [0u8; 64];
+            digest.copy_from_slice(reader.read_bytes(64).map_err(|_| Error::TrailingBytes)?);
+            ring_digest = Some(Box::new(digest));
+        }
+        reader.finish()?;
+        return Ok(BLSAG {
+            challenge: challenge,
+            responses: responses,
+            ring: ring,
+            key_image: key_image,
+            ring_digest: ring_digest,
+        });
+    }
+}
+
+crate::encoding::display_via_codec!(BLSAG);
+
 #[cfg(test)]
 #[cfg(feature = "std")]
+#[cfg(feature = "sign")]
 mod test {
     extern crate blake2;
     extern crate rand;
@@ -206,21 +685,26 @@ mod test {
         let message: Vec<u8> = b"This is the message".iter().cloned().collect();
 
         {
-            let signature = BLSAG::sign::<Sha512, OsRng>(k, ring.clone(), secret_index, &message);
-            let result = BLSAG::verify::<Sha512>(signature, &message);
+            let signature =
+                BLSAG::sign_with_rng_unchecked::<Sha512, _>(k, ring.clone(), secret_index, &message, &mut OsRng)
+                    .unwrap();
+            let result = signature.verify_ref::<Sha512>(&message);
             assert!(result);
         }
 
         {
             let signature =
-                BLSAG::sign::<Keccak512, OsRng>(k, ring.clone(), secret_index, &message);
-            let result = BLSAG::verify::<Keccak512>(signature, &message);
+                BLSAG::sign_with_rng_unchecked::<Keccak512, _>(k, ring.clone(), secret_index, &message, &mut OsRng)
+                    .unwrap();
+            let result = signature.verify_ref::<Keccak512>(&message);
             assert!(result);
         }
 
         {
-            let signature = BLSAG::sign::<Blake2b512, OsRng>(k, ring.clone(), secret_index, &message);
-            let result = BLSAG::verify::<Blake2b512>(signature, &message);
+            let signature =
+                BLSAG::sign_with_rng_unchecked::<Blake2b512, _>(k, ring.clone(), secret_index, &message, &mut OsRng)
+                    .unwrap();
+            let result = signature.verify_ref::<Blake2b512>(&message);
             assert!(result);
         }
 
@@ -229,10 +713,170 @@ mod test {
                 .map(|_| RistrettoPoint::random(&mut csprng))
                 .collect();
         let another_message: Vec<u8> = b"This is another message".iter().cloned().collect();
-        let signature_1 =
-            BLSAG::sign::<Blake2b512, OsRng>(k, another_ring.clone(), secret_index, &another_message);
-        let signature_2 = BLSAG::sign::<Blake2b512, OsRng>(k, ring.clone(), secret_index, &message);
-        let result = BLSAG::link(signature_1, signature_2);
+        let signature_1 = BLSAG::sign_with_rng_unchecked::<Blake2b512, _>(
+            k,
+            another_ring.clone(),
+            secret_index,
+            &another_message,
+            &mut OsRng,
+        )
+        .unwrap();
+        let signature_2 =
+            BLSAG::sign_with_rng_unchecked::<Blake2b512, _>(k, ring.clone(), secret_index, &message, &mut OsRng)
+                .unwrap();
+        let result = signature_1.link_ref(&signature_2);
         assert!(result);
     }
+
+    #[test]
+    fn compute_challenge() {
+        let mut csprng = OsRng;
+        let k: Scalar = Scalar::random(&mut csprng);
+        let ring: Vec<RistrettoPoint> = (0..2).map(|_| RistrettoPoint::random(&mut csprng)).collect();
+        let message: Vec<u8> = b"This is the message".to_vec();
+        let signature = BLSAG::sign_with_rng_unchecked::<Sha512, _>(k, ring, 1, &message, &mut OsRng).unwrap();
+
+        // Walk the ring with the exposed transcript math, as an external protocol would
+        let message_hash: Sha512 = compute_challenge_prefix::<Sha512>(&message);
+        let mut c = signature.challenge.0;
+        for (r, k_point) in signature.responses.iter().zip(signature.ring.iter()) {
+            let hashed_k_point = RistrettoPoint::from_hash(Sha512::default().chain_update(k_point.compress().as_bytes()));
+            c = compute_challenge_step(
+                message_hash.clone(),
+                &(r.0 * constants::RISTRETTO_BASEPOINT_POINT + c * k_point),
+                &(r.0 * hashed_k_point + c * signature.key_image),
+            );
+        }
+        assert_eq!(c, signature.challenge.0);
+    }
+
+    #[test]
+    fn derive_key_image_with_proof() {
+        let k: Scalar = Scalar::random(&mut OsRng);
+        let public_key: RistrettoPoint = k * constants::RISTRETTO_BASEPOINT_POINT;
+        let (key_image, proof) = BLSAG::derive_key_image_with_proof::<Sha512, OsRng>(k);
+        assert_eq!(key_image, BLSAG::generate_key_image_ref::<Sha512>(&k));
+        assert!(BLSAG::verify_key_image_derivation::<Sha512>(&public_key, &key_image, &proof));
+
+        let other: RistrettoPoint = RistrettoPoint::random(&mut OsRng);
+        assert!(!BLSAG::verify_key_image_derivation::<Sha512>(&other, &key_image, &proof));
+        assert!(!BLSAG::verify_key_image_derivation::<Sha512>(&public_key, &other, &proof));
+        // A proof bound to another message does not pass for a derivation proof
+        let withdrawal = KeyImageBinding::prove::<Sha512, OsRng>(k, &b"Withdrawal 42".to_vec());
+        assert!(!BLSAG::verify_key_image_derivation::<Sha512>(&public_key, &key_image, &withdrawal));
+    }
+    #[test]
+    fn sign_with_rng() {
+        use rand::rngs::StdRng;
+        use rand::SeedableRng;
+
+        let k: Scalar = Scalar::random(&mut OsRng);
+        let ring: Vec<RistrettoPoint> = (0..2).map(|_| RistrettoPoint::random(&mut OsRng)).collect();
+        let message: Vec<u8> = b"This is the message".to_vec();
+
+        // The same seed gives the same signature
+        let signature_1 =
+            BLSAG::sign_with_rng_unchecked::<Sha512, _>(k, ring.clone(), 1, &message, &mut StdRng::seed_from_u64(7))
+                .unwrap();
+        let signature_2 =
+            BLSAG::sign_with_rng_unchecked::<Sha512, _>(k, ring, 1, &message, &mut StdRng::seed_from_u64(7)).unwrap();
+        assert_eq!(signature_1.to_bytes(), signature_2.to_bytes());
+        assert!(signature_1.verify_ref::<Sha512>(&message));
+    }
+    #[test]
+    fn ring_digest() {
+        let k: Scalar = Scalar::random(&mut OsRng);
+        let ring: Vec<RistrettoPoint> = (0..2).map(|_| RistrettoPoint::random(&mut OsRng)).collect();
+        let message: Vec<u8> = b"This is the message".to_vec();
+        let legacy = BLSAG::sign_with_rng_unchecked::<Sha512, _>(k, ring, 1, &message, &mut OsRng).unwrap();
+        let signature = legacy.clone().attach_ring_digest::<Sha512>();
+
+        // The digest is appended to the legacy encoding
+        let bytes = signature.to_bytes();
+        assert_eq!(bytes.len(), legacy.to_bytes().len() + 64);
+        assert_eq!(BLSAG::from_bytes(&legacy.to_bytes()).unwrap().ring_digest(), None);
+        let decoded = BLSAG::from_bytes(&bytes).unwrap();
+        assert_eq!(decoded.ring_digest(), signature.ring_digest());
+        assert!(BLSAG::from_bytes(&bytes[..bytes.len() - 1]).is_none());
+        assert!(decoded.verify_ref::<Sha512>(&message));
+
+        // Putting back another ring is caught
+        let mut substituted = signature;
+        substituted.ring[0] = RistrettoPoint::random(&mut OsRng);
+        assert!(!substituted.ring_digest_matches::<Sha512>());
+        assert!(!substituted.verify_ref::<Sha512>(&message));
+    }
+
+    #[test]
+    fn sign_with_full_ring() {
+        use rand::rngs::StdRng;
+        use rand::SeedableRng;
+
+        let k: Scalar = Scalar::random(&mut OsRng);
+        let decoys: Vec<RistrettoPoint> = (0..10).map(|_| RistrettoPoint::random(&mut OsRng)).collect();
+        let mut ring: Vec<RistrettoPoint> = decoys.clone();
+        ring.insert(2, k * constants::RISTRETTO_BASEPOINT_POINT);
+        let message: Vec<u8> = b"This is the message".to_vec();
+
+        // The same signature as signing with the signer taken out of the ring
+        let mut csprng = StdRng::seed_from_u64(7);
+        let signature =
+            BLSAG::sign_with_full_ring_and_rng::<Sha512, _>(k, ring.clone(), 2, &message, &mut csprng).unwrap();
+        let expected =
+            BLSAG::sign_with_rng::<Sha512, _>(k, decoys, 2, &message, &mut StdRng::seed_from_u64(7)).unwrap();
+        assert_eq!(signature.to_bytes(), expected.to_bytes());
+        assert_eq!(signature.ring, ring);
+        assert!(signature.verify_ref::<Sha512>(&message));
+
+        assert_eq!(
+            BLSAG::sign_with_full_ring::<Sha512, OsRng>(k, ring.clone(), 1, &message).err(),
+            Some(Error::NotInRing)
+        );
+        assert_eq!(
+            BLSAG::sign_with_full_ring::<Sha512, OsRng>(k, ring, 11, &message).err(),
+            Some(Error::SecretIndexOutOfRange { index: 11, ring_size: 11 })
+        );
+    }
+
+    #[test]
+    fn sign_with_hidden_index() {
+        use crate::traits::random_index;
+        use rand::rngs::StdRng;
+        use rand::SeedableRng;
+
+        let k: Scalar = Scalar::random(&mut OsRng);
+        let k_point: RistrettoPoint = k * constants::RISTRETTO_BASEPOINT_POINT;
+        let ring: Vec<RistrettoPoint> = (0..10).map(|_| RistrettoPoint::random(&mut OsRng)).collect();
+        let message: Vec<u8> = b"This is the message".to_vec();
+
+        // The signer lands where `random_index` puts it, with the decoys in order around it
+        for seed in 0..4 {
+            let expected = random_index(&mut StdRng::seed_from_u64(seed), 11);
+            let mut csprng = StdRng::seed_from_u64(seed);
+            let signature = BLSAG::sign_with_hidden_index::<Sha512, _>(k, ring.clone(), &message, &mut csprng).unwrap();
+            assert!(signature.verify_ref::<Sha512>(&message));
+            let secret_index = signature.ring.iter().position(|member| *member == k_point).unwrap();
+            assert_eq!(secret_index, expected);
+            let mut decoys = signature.ring.clone();
+            decoys.remove(secret_index);
+            assert_eq!(decoys, ring);
+        }
+        // which is every position
+        let mut seen = [false; 11];
+        for _ in 0..1024 {
+            seen[random_index(&mut OsRng, 11)] = true;
+        }
+        assert_eq!(seen, [true; 11]);
+    }
+
+    #[test]
+    fn sign_does_not_panic() {
+        let k: Scalar = Scalar::random(&mut OsRng);
+        let ring: Vec<RistrettoPoint> = (0..2).map(|_| RistrettoPoint::random(&mut OsRng)).collect();
+        let message: Vec<u8> = b"This is the message".to_vec();
+
+        // A secret index outside the ring is an error, not a panic
+        let signature = BLSAG::sign_with_rng_unchecked::<Sha512, _>(k, ring, 3, &message, &mut OsRng);
+        assert_eq!(signature.err(), Some(Error::SecretIndexOutOfRange { index: 3, ring_size: 3 }));
+    }
 }
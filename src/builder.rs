@@ -0,0 +1,308 @@
+//! Builders
+//!
+//! The traits take every argument of signing at once, with the hash and the random number
+//! generator as type parameters. A [`Signer`] collects them one at a time instead, e.g.
+//! `CLSAGSigner::new(ks).ring(decoys).hasher::<Sha512>().rng(&mut csprng).sign(message)`, and a
+//! [`Verifier`] does the same for verifying. Leaving out the hasher or the generator does not
+//! compile.
+//!
+//! Options that the traits have no room for live here. With [`Signer::context`], the message is
+//! domain separated by the context string, so that the signature does not verify in any other
+//! context. It is the signature `SignWithContext::sign_ctx` makes. Without it, the signature is
+//! the one `sign` would make. Rings smaller than
+//! [`crate::policy::DEFAULT_MINIMUM_RING_SIZE`] are rejected unless [`Signer::allow_small_rings`]
+//! says otherwise.
+
+use crate::prelude::*;
+
+use core::marker::PhantomData;
+
+use digest::generic_array::typenum::U64;
+use digest::Digest;
+#[cfg(feature = "sign")]
+use rand_core::{CryptoRng, RngCore};
+
+#[cfg(all(feature = "sign", feature = "blsag"))]
+use crate::blsag::BLSAG;
+#[cfg(all(feature = "sign", feature = "clsag"))]
+use crate::clsag::CLSAG;
+#[cfg(all(feature = "sign", feature = "dlsag"))]
+use crate::dlsag::DLSAG;
+#[cfg(feature = "sign")]
+use crate::error::Error;
+#[cfg(feature = "sign")]
+use crate::policy::SignPolicy;
+#[cfg(all(feature = "sign", feature = "lsag"))]
+use crate::lsag::LSAG;
+#[cfg(all(feature = "sign", feature = "mdlsag"))]
+use crate::mdlsag::MDLSAG;
+#[cfg(all(feature = "sign", feature = "mlsag"))]
+use crate::mlsag::MLSAG;
+#[cfg(all(feature = "sign", feature = "sag"))]
+use crate::sag::SAG;
+use crate::traits::{self, VerifyRef};
+#[cfg(feature = "sign")]
+use crate::traits::{random_index, Scheme};
+
+/// The bytes actually signed, see [`crate::traits::SignWithContext`]
+fn bind_context(context: &Option<Vec<u8>>, message: &[u8]) -> Vec<u8> {
+    return match context {
+        None => message.to_vec(),
+        Some(context) => traits::bind_context(context, message),
+    };
+}
+
+/// Signs with scheme `S`, see the module documentation
+///
+/// `Hash` and `CSPRNG` are `()` until [`Signer::hasher`] and [`Signer::rng`] set them.
+#[cfg(feature = "sign")]
+pub struct Signer<S: Scheme, Hash, CSPRNG> {
+    k: S::SecretKey,
+    ring: Option<S::Ring>,
+    secret_index: Option<usize>,
+    context: Option<Vec<u8>>,
+    policy: SignPolicy,
+    csprng: CSPRNG,
+    hash: PhantomData<fn() -> Hash>,
+}
+
+#[cfg(all(feature = "sign", feature = "sag"))]
+pub type SAGSigner<Hash = (), CSPRNG = ()> = Signer<SAG, Hash, CSPRNG>;
+#[cfg(all(feature = "sign", feature = "lsag"))]
+pub type LSAGSigner<Hash = (), CSPRNG = ()> = Signer<LSAG, Hash, CSPRNG>;
+#[cfg(all(feature = "sign", feature = "blsag"))]
+pub type BLSAGSigner<Hash = (), CSPRNG = ()> = Signer<BLSAG, Hash, CSPRNG>;
+#[cfg(all(feature = "sign", feature = "mlsag"))]
+pub type MLSAGSigner<Hash = (), CSPRNG = ()> = Signer<MLSAG, Hash, CSPRNG>;
+#[cfg(all(feature = "sign", feature = "clsag"))]
+pub type CLSAGSigner<Hash = (), CSPRNG = ()> = Signer<CLSAG, Hash, CSPRNG>;
+#[cfg(all(feature = "sign", feature = "dlsag"))]
+pub type DLSAGSigner<Hash = (), CSPRNG = ()> = Signer<DLSAG, Hash, CSPRNG>;
+#[cfg(all(feature = "sign", feature = "mdlsag"))]
+pub type MDLSAGSigner<Hash = (), CSPRNG = ()> = Signer<MDLSAG, Hash, CSPRNG>;
+
+#[cfg(feature = "sign")]
+impl<S: Scheme> Signer<S, (), ()> {
+    /// Signs with the private key `k`
+    pub fn new(k: S::SecretKey) -> Signer<S, (), ()> {
+        return Signer {
+            k: k,
+            ring: None,
+            secret_index: None,
+            context: None,
+            policy: SignPolicy::default(),
+            csprng: (),
+            hash: PhantomData,
+        };
+    }
+}
+
+#[cfg(feature = "sign")]
+impl<S: Scheme, Hash, CSPRNG> Signer<S, Hash, CSPRNG> {
+    /// The public keys of everyone except you
+    pub fn ring(mut self, ring: S::Ring) -> Signer<S, Hash, CSPRNG> {
+        self.ring = Some(ring);
+        return self;
+    }
+
+    /// Your position in the ring. Without it, the position is drawn from the generator, which
+    /// hides it best
+    pub fn secret_index(mut self, secret_index: usize) -> Signer<S, Hash, CSPRNG> {
+        self.secret_index = Some(secret_index);
+        return self;
+    }
+
+    /// Signs `context` along with the message, see the module documentation
+    pub fn context(mut self, context: &[u8]) -> Signer<S, Hash, CSPRNG> {
+        self.context = Some(context.to_vec());
+        return self;
+    }
+
+    /// Signs over rings of any size. By default, rings smaller than
+    /// [`crate::policy::DEFAULT_MINIMUM_RING_SIZE`], you included, are rejected: they hide next to
+    /// nothing. Only meant for tests and deployments that have a reason to sign with fewer members
+    pub fn allow_small_rings(mut self) -> Signer<S, Hash, CSPRNG> {
+        self.policy = self.policy.allow_small_rings();
+        return self;
+    }
+
+    pub fn hasher<H: Digest<OutputSize = U64> + Clone + Default>(self) -> Signer<S, H, CSPRNG> {
+        return Signer {
+            k: self.k,
+            ring: self.ring,
+            secret_index: self.secret_index,
+            context: self.context,
+            policy: self.policy,
+            csprng: self.csprng,
+            hash: PhantomData,
+        };
+    }
+
+    /// Draws randomness from `csprng`, which may be a `&mut` borrow
+    pub fn rng<R: CryptoRng + RngCore>(self, csprng: R) -> Signer<S, Hash, R> {
+        return Signer {
+            k: self.k,
+            ring: self.ring,
+            secret_index: self.secret_index,
+            context: self.context,
+            policy: self.policy,
+            csprng: csprng,
+            hash: PhantomData,
+        };
+    }
+}
+
+#[cfg(feature = "sign")]
+impl<S: Scheme<Ring = Vec<M>>, M, Hash: Digest<OutputSize = U64> + Clone + Default, CSPRNG: CryptoRng + RngCore>
+    Signer<S, Hash, CSPRNG>
+{
+    /// Signs `message`, as [`Scheme::try_sign_with_key`] would
+    ///
+    /// Returns [`Error::EmptyRing`] if no ring was given: a signature by you alone hides nothing.
+    /// Returns [`Error::RingTooSmall`] for a small ring, see [`Signer::allow_small_rings`].
+    pub fn sign(mut self, message: &[u8]) -> Result<S, Error> {
+        let ring: Vec<M> = self.ring.ok_or(Error::EmptyRing)?;
+        self.policy.check(ring.len() + 1)?;
+        let secret_index = match self.secret_index {
+            Some(secret_index) => secret_index,
+            None => random_index(&mut self.csprng, ring.len() + 1),
+        };
+        return S::try_sign_with_key_and_rng::<Hash, CSPRNG>(
+            self.k,
+            ring,
+            secret_index,
+            &bind_context(&self.context, message),
+            &mut self.csprng,
+        );
+    }
+}
+
+/// Verifies signatures of scheme `S`, see the module documentation
+///
+/// `Hash` is `()` until [`Verifier::hasher`] sets it.
+pub struct Verifier<S, Hash> {
+    context: Option<Vec<u8>>,
+    types: PhantomData<fn() -> (S, Hash)>,
+}
+
+impl<S: VerifyRef> Verifier<S, ()> {
+    pub fn new() -> Verifier<S, ()> {
+        return Verifier {
+            context: None,
+            types: PhantomData,
+        };
+    }
+}
+
+impl<S: VerifyRef> Default for Verifier<S, ()> {
+    fn default() -> Verifier<S, ()> {
+        return Verifier::new();
+    }
+}
+
+impl<S: VerifyRef, Hash> Verifier<S, Hash> {
+    /// Expects signatures made in `context`, see [`Signer::context`]
+    pub fn context(mut self, context: &[u8]) -> Verifier<S, Hash> {
+        self.context = Some(context.to_vec());
+        return self;
+    }
+
+    pub fn hasher<H: Digest<OutputSize = U64> + Clone + Default>(self) -> Verifier<S, H> {
+        return Verifier {
+            context: self.context,
+            types: PhantomData,
+        };
+    }
+}
+
+impl<S: VerifyRef, Hash: Digest<OutputSize = U64> + Clone + Default> Verifier<S, Hash> {
+    /// Whether `signature` is over `message`, in the context if one was given
+    pub fn verify(&self, signature: &S, message: &[u8]) -> bool {
+        return signature.verify_ref::<Hash>(&bind_context(&self.context, message));
+    }
+}
+
+#[cfg(test)]
+#[cfg(feature = "std")]
+#[cfg(feature = "sign")]
+#[cfg(feature = "clsag")]
+mod test {
+    extern crate rand;
+    extern crate sha2;
+
+    use curve25519_dalek::ristretto::RistrettoPoint;
+    use curve25519_dalek::scalar::Scalar;
+    use rand::rngs::OsRng;
+    use sha2::Sha512;
+
+    use super::*;
+    use crate::traits::{SignWithContext, VerifyWithContext};
+
+    #[test]
+    fn builder() {
+        let mut csprng = OsRng;
+        let ks: Vec<Scalar> = (0..2).map(|_| Scalar::random(&mut csprng)).collect();
+        let decoys: Vec<Vec<RistrettoPoint>> = (0..3)
+            .map(|_| (0..2).map(|_| RistrettoPoint::random(&mut csprng)).collect())
+            .collect();
+        let message = b"This is the message";
+
+        let signature = CLSAGSigner::new(ks.clone())
+            .ring(decoys.clone())
+            .allow_small_rings()
+            .hasher::<Sha512>()
+            .rng(&mut csprng)
+            .sign(message)
+            .unwrap();
+        assert_eq!(signature.ring.len(), 4);
+        let verifier = Verifier::<CLSAG, _>::new().hasher::<Sha512>();
+        assert!(verifier.verify(&signature, message));
+        assert!(signature.verify_ref::<Sha512>(&message.to_vec()));
+        assert!(!verifier.verify(&signature, b"This is another message"));
+
+        // A signature in a context verifies only in that context
+        let signature = CLSAGSigner::new(ks.clone())
+            .ring(decoys.clone())
+            .secret_index(3)
+            .context(b"vote 1")
+            .allow_small_rings()
+            .hasher::<Sha512>()
+            .rng(OsRng)
+            .sign(message)
+            .unwrap();
+        assert!(Verifier::<CLSAG, _>::new()
+            .context(b"vote 1")
+            .hasher::<Sha512>()
+            .verify(&signature, message));
+        assert!(!Verifier::<CLSAG, _>::new()
+            .context(b"vote 2")
+            .hasher::<Sha512>()
+            .verify(&signature, message));
+        assert!(!verifier.verify(&signature, message));
+        assert!(signature.verify_ctx::<Sha512>(b"vote 1", &message.to_vec()));
+
+        // The same contexts through the traits, which check the default policy
+        let crowd: Vec<Vec<RistrettoPoint>> = (0..10)
+            .map(|_| (0..2).map(|_| RistrettoPoint::random(&mut csprng)).collect())
+            .collect();
+        let signature =
+            CLSAG::sign_ctx::<Sha512, OsRng>(ks.clone(), crowd, 0, b"vote 2", &message.to_vec()).unwrap();
+        assert!(signature.verify_ctx::<Sha512>(b"vote 2", &message.to_vec()));
+        assert!(!signature.verify_ctx::<Sha512>(b"vote 1", &message.to_vec()));
+        assert!(!signature.verify_ref::<Sha512>(&message.to_vec()));
+        assert!(Verifier::<CLSAG, _>::new()
+            .context(b"vote 2")
+            .hasher::<Sha512>()
+            .verify(&signature, message));
+
+        let unchecked = CLSAGSigner::new(ks.clone()).ring(decoys.clone()).secret_index(4).allow_small_rings();
+        assert_eq!(
+            unchecked.hasher::<Sha512>().rng(OsRng).sign(message).err(),
+            Some(Error::SecretIndexOutOfRange { index: 4, ring_size: 4 })
+        );
+        let small = CLSAGSigner::new(ks.clone()).ring(decoys).hasher::<Sha512>().rng(OsRng);
+        assert_eq!(small.sign(message).err(), Some(Error::RingTooSmall { size: 4, minimum: 11 }));
+        let alone = CLSAGSigner::new(ks).hasher::<Sha512>().rng(OsRng);
+        assert_eq!(alone.sign(message).err(), Some(Error::EmptyRing));
+    }
+}
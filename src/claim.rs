@@ -0,0 +1,236 @@
+//! Airdrop claims
+//!
+//! An airdrop pays each public key of a snapshot once. A [`Claim`] proves that the claimant holds
+//! the private key of some member of the snapshot without revealing which, and carries a key image
+//! scoped to the airdrop, `I = k H_p(claim_id || P)`. Every key has exactly one such key image per
+//! airdrop, so a second claim by the same key is caught, while the key images of different
+//! airdrops, or of ordinary bLSAGs, can not be linked to each other.
+//!
+//! A claim is a bLSAG with `H_p(P)` replaced by the scoped mapping throughout. A [`ClaimVerifier`]
+//! is what the server keeps per airdrop: the claim id, the snapshot and the key images claimed so
+//! far.
+
+use crate::prelude::*;
+
+use alloc::collections::BTreeSet;
+use core::marker::PhantomData;
+
+use curve25519_dalek::constants;
+use curve25519_dalek::ristretto::RistrettoPoint;
+use curve25519_dalek::scalar::Scalar;
+use digest::generic_array::typenum::U64;
+use digest::Digest;
+#[cfg(feature = "sign")]
+use rand_core::{CryptoRng, RngCore};
+
+use crate::blsag::{compute_challenge_prefix, BLSAG};
+use crate::codec::Codec;
+use crate::error::Error;
+use crate::hash_to_scalar::WideReduction;
+use crate::link::{to_key_image, KeyImage};
+use crate::traits::TryVerify;
+use crate::ops;
+
+/// Domain separation of the scoped hash to point
+const CLAIM: &[u8] = b"nazgul/claim";
+
+/// The scoped hash to point `H_p(claim_id || P)`. The length of `claim_id` is hashed first, so
+/// that no two claim ids share a mapping
+fn claim_point<Hash: Digest<OutputSize = U64> + Default>(claim_id: &[u8], point: &RistrettoPoint) -> RistrettoPoint {
+    return ops::point_from_hash(
+        Hash::default()
+            .chain_update(CLAIM)
+            .chain_update((claim_id.len() as u64).to_le_bytes())
+            .chain_update(claim_id)
+            .chain_update(point.compress().as_bytes()),
+    );
+}
+
+/// The key image every claim of `k` on `claim_id` carries
+pub fn claim_key_image<Hash: Digest<OutputSize = U64> + Clone + Default>(
+    k: &Scalar,
+    claim_id: &[u8],
+) -> RistrettoPoint {
+    return k * claim_point::<Hash>(claim_id, &(k * constants::RISTRETTO_BASEPOINT_POINT));
+}
+
+/// Proof that the claimant is one of the members of a snapshot, see the module documentation
+///
+/// Please read tests at the bottom of the source code for this module for examples on how to use
+/// it
+#[derive(Clone)]
+pub struct Claim {
+    /// The bLSAG over the whole snapshot, with the key image scoped to the claim id
+    pub signature: BLSAG,
+}
+
+impl Claim {
+    /// Claims on `claim_id` as the member of `snapshot` whose private key is `k`, signing
+    /// `message`, e.g. the address to pay out to
+    ///
+    /// Returns [`Error::NotInRing`] if the public key of `k` is not in `snapshot`.
+    #[cfg(feature = "sign")]
+    pub fn create<Hash: Digest<OutputSize = U64> + Clone + Default, CSPRNG: CryptoRng + RngCore + Default>(
+        k: Scalar,
+        snapshot: &[RistrettoPoint],
+        claim_id: &[u8],
+        message: &Vec<u8>,
+    ) -> Result<Claim, Error> {
+        let k_point: RistrettoPoint = k * constants::RISTRETTO_BASEPOINT_POINT;
+        let secret_index = snapshot
+            .iter()
+            .position(|member| *member == k_point)
+            .ok_or(Error::NotInRing)?;
+        let mut decoys: Vec<RistrettoPoint> = snapshot.to_vec();
+        decoys.remove(secret_index);
+        let mut csprng = CSPRNG::default();
+        let signature = BLSAG::sign_with_map::<Hash, CSPRNG, WideReduction, _>(
+            k,
+            decoys,
+            secret_index,
+            compute_challenge_prefix::<Hash>(message),
+            &mut csprng,
+            &constants::RISTRETTO_BASEPOINT_POINT,
+            |point| claim_point::<Hash>(claim_id, point),
+        )?;
+        return Ok(Claim { signature: signature });
+    }
+
+    /// The key image scoped to the claim id, the same for every claim of the same key
+    pub fn key_image(&self) -> KeyImage {
+        return to_key_image(&self.signature.key_image);
+    }
+
+    /// Whether the claim is one on `claim_id` over `message`. It says nothing about whether the
+    /// ring is the snapshot, which [`ClaimVerifier`] checks
+    pub fn verify<Hash: Digest<OutputSize = U64> + Clone + Default>(&self, claim_id: &[u8], message: &Vec<u8>) -> bool {
+        let signature = &self.signature;
+        if signature.check_shape().is_err() || !signature.ring_digest_matches::<Hash>() {
+            return false;
+        }
+        let reconstructed_c: Scalar = signature.reconstruct_challenge_with_map::<Hash, WideReduction, _>(
+            compute_challenge_prefix::<Hash>(message),
+            0..signature.ring.len(),
+            signature.challenge.0,
+            &constants::RISTRETTO_BASEPOINT_POINT,
+            |point| claim_point::<Hash>(claim_id, point),
+        );
+        return signature.challenge.0 == reconstructed_c;
+    }
+}
+
+impl Codec for Claim {
+    /// The encoding of the bLSAG
+    fn to_bytes(&self) -> Vec<u8> {
+        return self.signature.to_bytes();
+    }
+
+    fn decode(bytes: &[u8]) -> Result<Claim, Error> {
+        return Ok(Claim {
+            signature: BLSAG::decode(bytes)?,
+        });
+    }
+}
+
+crate::encoding::display_via_codec!(Claim);
+
+#[cfg(feature = "serde")]
+crate::codec::serde_via_codec!(Claim);
+
+/// The server side of one airdrop
+pub struct ClaimVerifier<Hash> {
+    claim_id: Vec<u8>,
+    snapshot: Vec<RistrettoPoint>,
+    claimed: BTreeSet<KeyImage>,
+    hash: PhantomData<fn() -> Hash>,
+}
+
+impl<Hash: Digest<OutputSize = U64> + Clone + Default> ClaimVerifier<Hash> {
+    /// Accepts claims on `claim_id` by the members of `snapshot`, in this order
+    pub fn new(claim_id: &[u8], snapshot: Vec<RistrettoPoint>) -> ClaimVerifier<Hash> {
+        return ClaimVerifier {
+            claim_id: claim_id.to_vec(),
+            snapshot: snapshot,
+            claimed: BTreeSet::new(),
+            hash: PhantomData,
+        };
+    }
+
+    /// Accepts `claim` over `message` and records its key image
+    ///
+    /// Returns [`Error::InvalidSignature`] if the claim is not one on this airdrop over the
+    /// snapshot, and [`Error::AlreadyClaimed`] if the same key claimed before.
+    pub fn accept(&mut self, claim: &Claim, message: &Vec<u8>) -> Result<(), Error> {
+        if claim.signature.ring != self.snapshot || !claim.verify::<Hash>(&self.claim_id, message) {
+            return Err(Error::InvalidSignature);
+        }
+        if !self.claimed.insert(claim.key_image()) {
+            return Err(Error::AlreadyClaimed);
+        }
+        return Ok(());
+    }
+
+    pub fn is_claimed(&self, key_image: &KeyImage) -> bool {
+        return self.claimed.contains(key_image);
+    }
+
+    /// The number of claims accepted
+    pub fn claims(&self) -> usize {
+        return self.claimed.len();
+    }
+}
+
+#[cfg(test)]
+#[cfg(feature = "std")]
+#[cfg(feature = "sign")]
+mod test {
+    extern crate rand;
+    extern crate sha2;
+
+    use rand::rngs::OsRng;
+    use sha2::Sha512;
+
+    use super::*;
+    use crate::traits::{KeyImageGenRef, VerifyRef};
+
+    #[test]
+    fn claim() {
+        let keys: Vec<Scalar> = (0..4).map(|_| Scalar::random(&mut OsRng)).collect();
+        let snapshot: Vec<RistrettoPoint> = keys.iter().map(|k| k * constants::RISTRETTO_BASEPOINT_POINT).collect();
+        let address: Vec<u8> = b"payout address".to_vec();
+        let mut verifier = ClaimVerifier::<Sha512>::new(b"airdrop 1", snapshot.clone());
+
+        let claim = Claim::create::<Sha512, OsRng>(keys[2], &snapshot, b"airdrop 1", &address).unwrap();
+        assert!(claim.verify::<Sha512>(b"airdrop 1", &address));
+        assert!(!claim.verify::<Sha512>(b"airdrop 2", &address));
+        assert!(!claim.signature.verify_ref::<Sha512>(&address));
+        assert_eq!(
+            claim.signature.key_image,
+            claim_key_image::<Sha512>(&keys[2], b"airdrop 1")
+        );
+        assert_ne!(claim.signature.key_image, BLSAG::generate_key_image_ref::<Sha512>(&keys[2]));
+        assert_eq!(verifier.accept(&claim, &address), Ok(()));
+        assert!(verifier.is_claimed(&claim.key_image()));
+
+        // The same key claims once, whatever it signs
+        let again = Claim::create::<Sha512, OsRng>(keys[2], &snapshot, b"airdrop 1", &b"other".to_vec()).unwrap();
+        assert_eq!(verifier.accept(&again, &b"other".to_vec()), Err(Error::AlreadyClaimed));
+        let other = Claim::create::<Sha512, OsRng>(keys[0], &snapshot, b"airdrop 1", &address).unwrap();
+        assert_eq!(verifier.accept(&other, &address), Ok(()));
+        assert_eq!(verifier.claims(), 2);
+
+        // Claims on another airdrop, or over a ring that is not the snapshot, are rejected
+        let elsewhere = Claim::create::<Sha512, OsRng>(keys[1], &snapshot, b"airdrop 2", &address).unwrap();
+        assert_eq!(verifier.accept(&elsewhere, &address), Err(Error::InvalidSignature));
+        let smaller = Claim::create::<Sha512, OsRng>(keys[1], &snapshot[..2], b"airdrop 1", &address).unwrap();
+        assert_eq!(verifier.accept(&smaller, &address), Err(Error::InvalidSignature));
+        let outsider = Scalar::random(&mut OsRng);
+        assert_eq!(
+            Claim::create::<Sha512, OsRng>(outsider, &snapshot, b"airdrop 1", &address).err(),
+            Some(Error::NotInRing)
+        );
+
+        let decoded = Claim::from_bytes(&claim.to_bytes()).unwrap();
+        assert!(decoded.verify::<Sha512>(b"airdrop 1", &address));
+    }
+}
@@ -1,26 +1,52 @@
-use crate::traits::{KeyImageGen, Link, Sign, Verify};
+use crate::error::Error;
+use crate::scalars::{Challenge, Response};
+#[cfg(feature = "sign")]
+use crate::scalars;
+use crate::traits::{
+    check_columns, check_responses, ChallengeChain, KeyImageGenRef, LinkRef, TryVerify, VerifyRef, Weight,
+};
+#[cfg(feature = "sign")]
+use crate::traits::{
+    check_secret_index, take_signer, Scheme, SignWithFullRing, SignWithHiddenIndex, SignWithRng, TrySign,
+};
+use crate::weight;
+use crate::generator::{Basepoint, Generator, VerifyWithGenerator};
+#[cfg(feature = "sign")]
+use crate::generator::SignWithGenerator;
+use crate::hash_to_point::{DefaultHashToPoint, HashToPoint};
+use crate::hash_to_scalar::{HashToScalar, WideReduction};
+use crate::codec::{self, Codec, Reader};
+use crate::ops;
+use crate::link::{self, KeyImage};
 use crate::prelude::*;
+use crate::scratch::Scratch;
+#[cfg(feature = "allocator-api")]
+use crate::arena::{Allocator, VerifyIn};
+use alloc::collections::BTreeMap;
+use core::fmt::{self, Write};
+use core::ops::Range;
 use curve25519_dalek::constants;
 use curve25519_dalek::ristretto::RistrettoPoint;
 use curve25519_dalek::scalar::Scalar;
 use digest::generic_array::typenum::U64;
 use digest::Digest;
+#[cfg(feature = "sign")]
 use rand_core::{CryptoRng, RngCore};
-use curve25519_dalek::traits::MultiscalarMul;
 
 /// Concise Linkable Spontaneous Anonymous Group (CLSAG) signatures
 /// > CLSAG is sort of half-way between bLSAG and MLSAG. Suppose you have a ‘primary’ key, and
-/// associated with it are several ‘auxiliary’ keys. It is important to prove knowledge of all
-/// private keys, but linkability only applies to the primary. This linkability retraction allows
-/// smaller, faster signatures than afforded by MLSAG.
+/// > associated with it are several ‘auxiliary’ keys. It is important to prove knowledge of all
+/// > private keys, but linkability only applies to the primary. This linkability retraction allows
+/// > smaller, faster signatures than afforded by MLSAG.
 ///
 /// Please read tests at the bottom of the source code for this module for examples on how to use
 /// it
+#[derive(Clone)]
 pub struct CLSAG {
     /// This is the challenge generated non-interactievely
-    pub challenge: Scalar,
+    pub challenge: Challenge,
     /// These responses are mostly fake, except one which is real.
-    pub responses: Vec<Scalar>,
+    pub responses: Vec<Response>,
     /// These are public keys most of which does not belong to the signer, except one which is the
     /// signer.
     pub ring: Vec<Vec<RistrettoPoint>>,
@@ -29,172 +55,352 @@ pub struct CLSAG {
     pub key_images: Vec<RistrettoPoint>,
 }
 
-impl KeyImageGen<Vec<Scalar>, Vec<RistrettoPoint>> for CLSAG {
+/// Hash state shared by all challenges `H_n("CSLAG_c", L, m, ....)`, where `L` is the complete
+/// ring
+///
+/// # Advanced
+///
+/// This and [`compute_challenge_step`] are the transcript math used by `sign` and `verify`,
+/// exposed for protocols that compute CLSAG challenges over data produced elsewhere. Most users
+/// should only ever need `sign` and `verify`.
+pub fn compute_challenge_prefix<Hash: Digest<OutputSize = U64> + Clone + Default>(
+    ring: &[Vec<RistrettoPoint>],
+    message: &[u8],
+) -> Hash {
+    let mut h: Hash = Hash::default();
+    h.update(format!("CSLAG_c"));
+    for row in ring {
+        for k_point in row {
+            h.update(k_point.compress().as_bytes());
+        }
+    }
+    h.update(message);
+    return h;
+}
+
+/// The challenge `c_(i+1) = H_n("CSLAG_c", L, m, point_a, point_b)` where
+/// `point_a = r_i G + c_i W_i` and `point_b = r_i H_p(K_i,0) + c_i W~` for the aggregated public
+/// key `W_i` and aggregated key image `W~`, given the `hash_state` from
+/// [`compute_challenge_prefix`]
+///
+/// # Advanced
+///
+/// See [`compute_challenge_prefix`]
+pub fn compute_challenge_step<Hash: Digest<OutputSize = U64>>(
+    hash_state: Hash,
+    point_a: &RistrettoPoint,
+    point_b: &RistrettoPoint,
+) -> Scalar {
+    return compute_challenge_step_with::<Hash, WideReduction>(hash_state, point_a, point_b);
+}
+
+/// As [`compute_challenge_step`] with the hash to scalar `Hs`, see [`crate::hash_to_scalar`]
+///
+/// # Advanced
+///
+/// See [`compute_challenge_prefix`]
+pub fn compute_challenge_step_with<Hash: Digest<OutputSize = U64>, Hs: HashToScalar>(
+    mut hash_state: Hash,
+    point_a: &RistrettoPoint,
+    point_b: &RistrettoPoint,
+) -> Scalar {
+    hash_state.update(point_a.compress().as_bytes());
+    hash_state.update(point_b.compress().as_bytes());
+    return Hs::hash_to_scalar(hash_state);
+}
+
+/// The aggregation coefficients `mu_j = H_j(L, I)`, one per column, computed from the domain
+/// separated hashes `H_j` labelled `CSLAG_j` as required by CSLAG paper
+fn aggregation_coefficients<Hash: Digest<OutputSize = U64> + Clone + Default, Hs: HashToScalar>(
+    ring: &Vec<Vec<RistrettoPoint>>,
+    key_images: &Vec<RistrettoPoint>,
+) -> Vec<Scalar> {
+    let mut coefficients: Vec<Scalar> = Vec::with_capacity(key_images.len());
+    aggregation_coefficients_in::<Hash, Hs, _>(ring, key_images, &mut coefficients);
+    return coefficients;
+}
+
+/// As [`aggregation_coefficients`], into `coefficients`
+fn aggregation_coefficients_in<
+    Hash: Digest<OutputSize = U64> + Clone + Default,
+    Hs: HashToScalar,
+    S: Scratch<Scalar>,
+>(
+    ring: &Vec<Vec<RistrettoPoint>>,
+    key_images: &Vec<RistrettoPoint>,
+    coefficients: &mut S,
+) {
+    let nc = key_images.len();
+    coefficients.extend((0..nc).map(|index| {
+        let mut h: Hash = Hash::default();
+        // The label is hashed as it is written, so that computing it allocates nothing
+        let _ = write!(Label(&mut h), "CSLAG_{}", index);
+        for row in ring {
+            for k_point in row.iter().take(nc) {
+                h.update(k_point.compress().as_bytes());
+            }
+        }
+        for key_image in key_images {
+            h.update(key_image.compress().as_bytes());
+        }
+        return Hs::hash_to_scalar(h);
+    }));
+}
+
+/// Writes the label of a domain separated hash into the hash
+struct Label<'a, Hash>(&'a mut Hash);
+
+impl<'a, Hash: Digest> fmt::Write for Label<'a, Hash> {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        self.0.update(s.as_bytes());
+        return Ok(());
+    }
+}
+
+/// The aggregate public key `W_i = sum_j mu_j K_(i,j)` of every row
+fn aggregate_public_keys(
+    ring: &Vec<Vec<RistrettoPoint>>,
+    coefficients: &Vec<Scalar>,
+) -> Vec<RistrettoPoint> {
+    return ring
+        .iter()
+        .map(|row| coefficients.iter().zip(row).map(|(coefficient, k_point)| coefficient * k_point).sum())
+        .collect();
+}
+
+/// The aggregate key image `W~ = sum_j mu_j I_j`
+fn aggregate_key_image(key_images: &Vec<RistrettoPoint>, coefficients: &[Scalar]) -> RistrettoPoint {
+    return coefficients.iter().zip(key_images).map(|(coefficient, key_image)| ops::mul(coefficient, key_image)).sum();
+}
+
+impl CLSAG {
+    /// The aggregate public key of every row of the ring, as checked against by `verify`.
+    /// Applications can cache these or use them for external balance checks.
+    pub fn aggregate_public_keys<Hash: Digest<OutputSize = U64> + Clone + Default>(
+        &self,
+    ) -> Vec<RistrettoPoint> {
+        let coefficients = aggregation_coefficients::<Hash, WideReduction>(&self.ring, &self.key_images);
+        return aggregate_public_keys(&self.ring, &coefficients);
+    }
+
+    /// The aggregate of all key images, as checked against by `verify`
+    pub fn aggregate_key_image<Hash: Digest<OutputSize = U64> + Clone + Default>(
+        &self,
+    ) -> RistrettoPoint {
+        let coefficients = aggregation_coefficients::<Hash, WideReduction>(&self.ring, &self.key_images);
+        return aggregate_key_image(&self.key_images, &coefficients);
+    }
+}
+
+impl KeyImageGenRef<Vec<Scalar>, Vec<RistrettoPoint>> for CLSAG {
     /// Some signature schemes require the key images to be signed as well.
     /// Use this method to generate them
-    fn generate_key_image<Hash: Digest<OutputSize = U64> + Clone + Default>(
+    fn generate_key_image_ref<Hash: Digest<OutputSize = U64> + Clone + Default>(
+        ks: &Vec<Scalar>,
+    ) -> Vec<RistrettoPoint> {
+        return CLSAG::key_images_with::<Hash, Basepoint, DefaultHashToPoint>(ks);
+    }
+}
+
+#[cfg(feature = "sign")]
+impl SignWithRng<Vec<Scalar>, Vec<Vec<RistrettoPoint>>> for CLSAG {
+    /// To sign you need `ks` which is the set of private keys you want to sign with. Only the
+    /// first one is linkable. The `ring` contains public keys for everybody except you. Your
+    /// public key will be inserted into it at random (secret) index. The `message` is what you are signing
+    fn sign_with_rng_unchecked<Hash: Digest<OutputSize = U64> + Clone + Default, CSPRNG: CryptoRng + RngCore>(
+        ks: Vec<Scalar>,
+        ring: Vec<Vec<RistrettoPoint>>,
+        secret_index: usize,
+        message: &Vec<u8>,
+        csprng: &mut CSPRNG,
+    ) -> Result<CLSAG, Error> {
+        return CLSAG::sign_with_csprng::<Hash, CSPRNG, Basepoint, DefaultHashToPoint, WideReduction>(
+            &ks,
+            ring,
+            secret_index,
+            message,
+            csprng,
+        );
+    }
+}
+
+#[cfg(feature = "sign")]
+impl SignWithGenerator<Vec<Scalar>, Vec<Vec<RistrettoPoint>>> for CLSAG {
+    fn sign_with_generator_and_rng<
+        Hash: Digest<OutputSize = U64> + Clone + Default,
+        CSPRNG: CryptoRng + RngCore,
+        G: Generator,
+    >(
+        ks: Vec<Scalar>,
+        ring: Vec<Vec<RistrettoPoint>>,
+        secret_index: usize,
+        message: &Vec<u8>,
+        csprng: &mut CSPRNG,
+    ) -> Result<CLSAG, Error> {
+        return CLSAG::sign_with_csprng::<Hash, CSPRNG, G, DefaultHashToPoint, WideReduction>(
+            &ks,
+            ring,
+            secret_index,
+            message,
+            csprng,
+        );
+    }
+}
+
+impl CLSAG {
+    /// As [`KeyImageGenRef::generate_key_image_ref`] with the hash to point `Hp`, see
+    /// [`crate::hash_to_point`]
+    pub fn generate_key_image_with<Hash: Digest<OutputSize = U64> + Clone + Default, Hp: HashToPoint>(
         ks: Vec<Scalar>,
     ) -> Vec<RistrettoPoint> {
-        let k_points: Vec<RistrettoPoint> = ks
-            .iter()
-            .map(|k| k * constants::RISTRETTO_BASEPOINT_POINT)
-            .collect();
+        return CLSAG::key_images_with::<Hash, Basepoint, Hp>(&ks);
+    }
+
+    /// As [`CLSAG::generate_key_image_with`], from borrowed keys and with the generator `G`
+    fn key_images_with<Hash: Digest<OutputSize = U64> + Clone + Default, G: Generator, Hp: HashToPoint>(
+        ks: &[Scalar],
+    ) -> Vec<RistrettoPoint> {
+        // Without keys there is no base key, and no key images
+        if ks.is_empty() {
+            return Vec::new();
+        }
+        let k_points: Vec<RistrettoPoint> = ks.iter().map(|k| G::public_key(k)).collect();
 
         // This is the base key
         // i.e. the first public key for which the prover has the private key
-        let base_key_hashed_to_point: RistrettoPoint =
-            RistrettoPoint::from_hash(Hash::default().chain_update(k_points[0].compress().as_bytes()));
+        let base_key_hashed_to_point: RistrettoPoint = Hp::hash_to_point::<Hash>(&k_points[0]);
 
         let key_images: Vec<RistrettoPoint> =
-            ks.iter().map(|k| k * base_key_hashed_to_point).collect();
+            ks.iter().map(|k| ops::mul(k, &base_key_hashed_to_point)).collect();
 
         return key_images;
     }
-}
 
-impl Sign<Vec<Scalar>, Vec<Vec<RistrettoPoint>>> for CLSAG {
-    /// To sign you need `ks` which is the set of private keys you want to sign with. Only the
-    /// first one is linkable. The `ring` contains public keys for everybody except you. Your
-    /// public key will be inserted into it at random (secret) index. The `message` is what you are signing
-    fn sign<
+    /// As [`crate::traits::Sign::sign`] with borrowed keys, which are not copied
+    #[cfg(feature = "sign")]
+    pub fn sign_ref<Hash: Digest<OutputSize = U64> + Clone + Default, CSPRNG: CryptoRng + RngCore + Default>(
+        ks: &[Scalar],
+        ring: Vec<Vec<RistrettoPoint>>,
+        secret_index: usize,
+        message: &Vec<u8>,
+    ) -> Result<CLSAG, Error> {
+        let mut csprng = CSPRNG::default();
+        return CLSAG::sign_ref_with_rng::<Hash, CSPRNG>(ks, ring, secret_index, message, &mut csprng);
+    }
+
+    /// As [`SignWithRng::sign_with_rng`] with borrowed keys, which are not copied
+    #[cfg(feature = "sign")]
+    pub fn sign_ref_with_rng<Hash: Digest<OutputSize = U64> + Clone + Default, CSPRNG: CryptoRng + RngCore>(
+        ks: &[Scalar],
+        ring: Vec<Vec<RistrettoPoint>>,
+        secret_index: usize,
+        message: &Vec<u8>,
+        csprng: &mut CSPRNG,
+    ) -> Result<CLSAG, Error> {
+        return CLSAG::sign_with_csprng::<Hash, CSPRNG, Basepoint, DefaultHashToPoint, WideReduction>(
+            ks,
+            ring,
+            secret_index,
+            message,
+            csprng,
+        );
+    }
+
+    /// As [`crate::traits::Sign::sign`] with the hash to point `Hp` and the hash to scalar `Hs`, see
+    /// [`crate::hash_to_point`] and [`crate::hash_to_scalar`]
+    #[cfg(feature = "sign")]
+    pub fn sign_with<
         Hash: Digest<OutputSize = U64> + Clone + Default,
         CSPRNG: CryptoRng + RngCore + Default,
+        Hp: HashToPoint,
+        Hs: HashToScalar,
     >(
         ks: Vec<Scalar>,
-        mut ring: Vec<Vec<RistrettoPoint>>,
+        ring: Vec<Vec<RistrettoPoint>>,
         secret_index: usize,
         message: &Vec<u8>,
-    ) -> CLSAG {
+    ) -> Result<CLSAG, Error> {
         let mut csprng = CSPRNG::default();
+        return CLSAG::sign_with_csprng::<Hash, CSPRNG, Basepoint, Hp, Hs>(
+            &ks,
+            ring,
+            secret_index,
+            message,
+            &mut csprng,
+        );
+    }
+
+    /// As [`CLSAG::sign_with`] with randomness drawn from `csprng` and the generator `G`
+    #[cfg(feature = "sign")]
+    fn sign_with_csprng<
+        Hash: Digest<OutputSize = U64> + Clone + Default,
+        CSPRNG: CryptoRng + RngCore,
+        G: Generator,
+        Hp: HashToPoint,
+        Hs: HashToScalar,
+    >(
+        ks: &[Scalar],
+        mut ring: Vec<Vec<RistrettoPoint>>,
+        secret_index: usize,
+        message: &Vec<u8>,
+        csprng: &mut CSPRNG,
+    ) -> Result<CLSAG, Error> {
+        check_columns(ks.len(), &ring)?;
+        check_secret_index(ring.len(), secret_index)?;
 
         let nr = ring.len() + 1;
-        let nc = ring[0].len();
+        let nc = ks.len();
+        let g: RistrettoPoint = G::generator();
 
         //Provers public keys
-        let k_points: Vec<RistrettoPoint> = ks
-            .iter()
-            .map(|k| k * constants::RISTRETTO_BASEPOINT_POINT)
-            .collect();
+        let k_points: Vec<RistrettoPoint> = ks.iter().map(|k| ops::mul_base(k, &g)).collect();
 
         // This is the base key
         // i.e. the first public key for which the prover has the private key
-        let base_key_hashed_to_point: RistrettoPoint =
-            RistrettoPoint::from_hash(Hash::default().chain_update(k_points[0].compress().as_bytes()));
+        let base_key_hashed_to_point: RistrettoPoint = Hp::hash_to_point::<Hash>(&k_points[0]);
 
-        let key_images: Vec<RistrettoPoint> = CLSAG::generate_key_image::<Hash>(ks.clone());
+        let key_images: Vec<RistrettoPoint> = CLSAG::key_images_with::<Hash, G, Hp>(ks);
 
         ring.insert(secret_index, k_points.clone());
 
-        let a: Scalar = Scalar::random(&mut csprng);
+        let a: Scalar = Scalar::random(csprng);
 
-        let mut rs: Vec<Scalar> = (0..nr).map(|_| Scalar::random(&mut csprng)).collect();
+        let mut rs: Vec<Scalar> = (0..nr).map(|_| Scalar::random(csprng)).collect();
 
         let mut cs: Vec<Scalar> = (0..nr).map(|_| Scalar::ZERO).collect();
 
-        // Domain separated hashes as required by CSLAG paper
-        // The hash functions have a label, and the ring members fed into it
-        let prefixed_hashes: Vec<Hash> = (0..nc)
-            .map(|index| {
-                let mut h: Hash = Hash::default();
-                h.update(format!("CSLAG_{}", index));
-                for i in 0..nr {
-                    for j in 0..nc {
-                        h.update(ring[i][j].compress().as_bytes());
-                    }
-                }
-                return h;
-            })
-            .collect();
+        let coefficients: Vec<Scalar> = aggregation_coefficients::<Hash, Hs>(&ring, &key_images);
 
-        // These prefixed hash functions have a label,
-        // and the ring members, and key images fed into it
-        let prefixed_hashes_with_key_images: Vec<Hash> = (0..nc)
-            .map(|index| {
-                let mut h: Hash = prefixed_hashes[index].clone();
-                for j in 0..nc {
-                    h.update(key_images[j].compress().as_bytes());
-                }
-                return h;
-            })
-            .collect();
+        let aggregate_private_key: Scalar = (0..nc).map(|j| coefficients[j] * ks[j]).sum();
 
-        let aggregate_private_key: Scalar = (0..nc)
-            .map(|j| {
-                let h: Hash = prefixed_hashes_with_key_images[j].clone();
-                return Scalar::from_hash(h) * ks[j];
-            })
-            .sum();
-
-        let aggregate_public_keys: Vec<RistrettoPoint> = (0..nr)
-            .map(|i| {
-                return (0..nc)
-                    .map(|j| {
-                        let h: Hash = prefixed_hashes_with_key_images[j].clone();
-                        return Scalar::from_hash(h.clone()) * ring[i][j];
-                    })
-                    .sum();
-            })
-            .collect();
+        let aggregate_public_keys: Vec<RistrettoPoint> =
+            aggregate_public_keys(&ring, &coefficients);
 
-        let aggregate_key_image: RistrettoPoint = (0..nc)
-            .map(|j| {
-                let h: Hash = prefixed_hashes_with_key_images[j].clone();
-                return Scalar::from_hash(h.clone()) * key_images[j];
-            })
-            .sum();
+        let aggregate_key_image: RistrettoPoint = aggregate_key_image(&key_images, &coefficients);
 
-        let mut hashes: Vec<Hash> = (0..nr)
-            .map(|_| {
-                let mut h: Hash = Hash::default();
-                h.update(format!("CSLAG_c"));
-                for i in 0..nr {
-                    for j in 0..nc {
-                        h.update(ring[i][j].compress().as_bytes());
-                    }
-                }
-                h.update(message);
-                return h;
-            })
-            .collect();
+        // The label, ring members and message are shared by all challenges
+        let round_hash: Hash = compute_challenge_prefix::<Hash>(&ring, message);
 
-        hashes[(secret_index + 1) % nr].update(
-            (a * constants::RISTRETTO_BASEPOINT_POINT)
-                .compress()
-                .as_bytes(),
+        cs[(secret_index + 1) % nr] = compute_challenge_step_with::<Hash, Hs>(
+            round_hash.clone(),
+            &ops::mul_base(&a, &g),
+            &ops::mul(&a, &base_key_hashed_to_point),
         );
-        hashes[(secret_index + 1) % nr].update((a * base_key_hashed_to_point).compress().as_bytes());
-        cs[(secret_index + 1) % nr] = Scalar::from_hash(hashes[(secret_index + 1) % nr].clone());
 
         let mut i = (secret_index + 1) % nr;
 
         loop {
-            hashes[(i + 1) % nr].update(
-                RistrettoPoint::multiscalar_mul(
+            cs[(i + 1) % nr] = compute_challenge_step_with::<Hash, Hs>(
+                round_hash.clone(),
+                &ops::multiscalar_mul(
                     &[rs[i % nr], cs[i % nr]],
-                    &[
-                        constants::RISTRETTO_BASEPOINT_POINT,
-                        aggregate_public_keys[i % nr]
-                    ]
-                )
-                    .compress()
-                    .as_bytes(),
-            );
-            hashes[(i + 1) % nr].update(
-                RistrettoPoint::multiscalar_mul(
+                    &[g, aggregate_public_keys[i % nr]]
+                ),
+                &ops::multiscalar_mul(
                     &[rs[i % nr], cs[i % nr]],
-                    &[
-                        RistrettoPoint::from_hash(
-                            Hash::default().chain_update(
-                                ring[i % nr][0].compress().as_bytes()
-                            ),
-                        ),
-                        aggregate_key_image
-                    ]
-                )
-                    .compress()
-                    .as_bytes(),
+                    &[Hp::hash_to_point::<Hash>(&ring[i % nr][0]), aggregate_key_image]
+                ),
             );
-            cs[(i + 1) % nr] = Scalar::from_hash(hashes[(i + 1) % nr].clone());
 
             if secret_index >= 1 && i % nr == (secret_index - 1) % nr {
                 break;
@@ -207,121 +413,389 @@ impl Sign<Vec<Scalar>, Vec<Vec<RistrettoPoint>>> for CLSAG {
 
         rs[secret_index] = a - (cs[secret_index] * aggregate_private_key);
 
-        return CLSAG {
-            challenge: cs[0],
-            responses: rs,
+        return Ok(CLSAG {
+            challenge: Challenge(cs[0]),
+            responses: scalars::responses(rs),
             ring: ring,
             key_images: key_images,
-        };
+        });
     }
-}
 
-impl Verify for CLSAG {
-    /// To verify a `signature` you need the `message` too
-    fn verify<Hash: Digest<OutputSize = U64> + Clone + Default>(
+    /// Whether the ring, responses and key images all agree on the row and column counts. The
+    /// aggregation coefficients are derived per key image, so a signature with more or fewer key
+    /// images than the ring has columns is rejected rather than aggregated differently.
+    /// Signatures that are not well formed never verify or decode.
+    pub fn is_well_formed(&self) -> bool {
+        let nc = self.key_images.len();
+        return !self.ring.is_empty()
+            && nc > 0
+            && self.responses.len() == self.ring.len()
+            && self.ring.iter().all(|row| row.len() == nc);
+    }
+
+    /// As [`VerifyRef::verify_ref`] with the hash to point `Hp` and the hash to scalar `Hs`, see
+    /// [`crate::hash_to_point`] and [`crate::hash_to_scalar`]
+    pub fn verify_with<Hash: Digest<OutputSize = U64> + Clone + Default, Hp: HashToPoint, Hs: HashToScalar>(
         signature: CLSAG,
         message: &Vec<u8>,
     ) -> bool {
-        let nr = signature.ring.len();
-        let nc = signature.ring[0].len();
-
-        let mut reconstructed_c: Scalar = signature.challenge;
-        // Domain separated hashes as required by CSLAG paper
-        // The hash functions have a label, and the ring members fed into it
-        let prefixed_hashes: Vec<Hash> = (0..nc)
-            .map(|index| {
-                let mut h: Hash = Hash::default();
-                h.update(format!("CSLAG_{}", index));
-                for i in 0..nr {
-                    for j in 0..nc {
-                        h.update(signature.ring[i][j].compress().as_bytes());
-                    }
-                }
-                return h;
-            })
-            .collect();
+        return signature.verify_ref_with::<Hash, Hp, Hs>(message);
+    }
 
-        // These prefixed hash functions have a label,
-        // and the ring members, and key images fed into it
-        let prefixed_hashes_with_key_images: Vec<Hash> = (0..nc)
-            .map(|index| {
-                let mut h: Hash = prefixed_hashes[index].clone();
-                for j in 0..nc {
-                    h.update(signature.key_images[j].compress().as_bytes());
-                }
-                return h;
-            })
-            .collect();
+    /// As [`CLSAG::verify_with`], by reference
+    pub fn verify_ref_with<Hash: Digest<OutputSize = U64> + Clone + Default, Hp: HashToPoint, Hs: HashToScalar>(
+        &self,
+        message: &Vec<u8>,
+    ) -> bool {
+        return self.verify_ref_with_generator::<Hash, Basepoint, Hp, Hs>(message);
+    }
 
-        let aggregate_public_keys: Vec<RistrettoPoint> = (0..nr)
-            .map(|i| {
-                return (0..nc)
-                    .map(|j| {
-                        let h: Hash = prefixed_hashes_with_key_images[j].clone();
-                        return Scalar::from_hash(h.clone()) * signature.ring[i][j];
-                    })
-                    .sum();
-            })
-            .collect();
+    /// As [`CLSAG::verify_ref_with`] with the generator `G`
+    fn verify_ref_with_generator<
+        Hash: Digest<OutputSize = U64> + Clone + Default,
+        G: Generator,
+        Hp: HashToPoint,
+        Hs: HashToScalar,
+    >(
+        &self,
+        message: &Vec<u8>,
+    ) -> bool {
+        if !self.is_well_formed() {
+            return false;
+        }
+        let n = self.ring.len();
+        let reconstructed_c: Scalar =
+            self.reconstruct_challenge_with::<Hash, G, Hp, Hs>(message, 0..n, self.challenge.0);
+        return self.challenge.0 == reconstructed_c;
+    }
 
-        let aggregate_key_image: RistrettoPoint = (0..nc)
-            .map(|j| {
-                let h: Hash = prefixed_hashes_with_key_images[j].clone();
-                return Scalar::from_hash(h.clone()) * signature.key_images[j];
-            })
-            .sum();
-        for _i in 0..nr {
-            let mut h: Hash = Hash::default();
-            h.update(format!("CSLAG_c"));
-            for i in 0..nr {
-                for j in 0..nc {
-                    h.update(signature.ring[i][j].compress().as_bytes());
-                }
-            }
-            h.update(message);
-            h.update(
-                RistrettoPoint::multiscalar_mul(
-                    &[signature.responses[_i], reconstructed_c],
-                    &[
-                        constants::RISTRETTO_BASEPOINT_POINT,
-                        aggregate_public_keys[_i]
-                    ]
-                )
-                    .compress()
-                    .as_bytes(),
+    /// As [`VerifyRef::verify_ref`], aggregating the public keys of each row as the chain reaches
+    /// it rather than all of them up front, so that nothing in proportion to the ring is allocated.
+    /// See [`crate::policy::MemoryBudget`]
+    pub(crate) fn verify_ref_streaming<Hash: Digest<OutputSize = U64> + Clone + Default>(
+        &self,
+        message: &Vec<u8>,
+    ) -> bool {
+        if !self.is_well_formed() {
+            return false;
+        }
+        let coefficients: Vec<Scalar> = aggregation_coefficients::<Hash, WideReduction>(&self.ring, &self.key_images);
+        return self.verify_with_coefficients::<Hash>(&coefficients, message);
+    }
+
+    /// As [`CLSAG::verify_ref_streaming`], given the aggregation coefficients
+    fn verify_with_coefficients<Hash: Digest<OutputSize = U64> + Clone + Default>(
+        &self,
+        coefficients: &[Scalar],
+        message: &Vec<u8>,
+    ) -> bool {
+        let aggregate_key_image: RistrettoPoint = aggregate_key_image(&self.key_images, coefficients);
+        let round_hash: Hash = compute_challenge_prefix::<Hash>(&self.ring, message);
+        let mut reconstructed_c: Scalar = self.challenge.0;
+        for (i, row) in self.ring.iter().enumerate() {
+            let aggregate_public_key: RistrettoPoint = ops::multiscalar_mul(coefficients, row);
+            reconstructed_c = compute_challenge_step_with::<Hash, WideReduction>(
+                round_hash.clone(),
+                &ops::multiscalar_mul(
+                    &[self.responses[i].0, reconstructed_c],
+                    &[constants::RISTRETTO_BASEPOINT_POINT, aggregate_public_key],
+                ),
+                &ops::multiscalar_mul(
+                    &[self.responses[i].0, reconstructed_c],
+                    &[DefaultHashToPoint::hash_to_point::<Hash>(&row[0]), aggregate_key_image],
+                ),
             );
+        }
+        return self.challenge.0 == reconstructed_c;
+    }
 
-            h.update(
-                RistrettoPoint::multiscalar_mul(
-                    &[signature.responses[_i], reconstructed_c],
-                    &[
-                        RistrettoPoint::from_hash(
-                            Hash::new().chain_update(
-                                signature.ring[_i][0].compress().as_bytes()
-                            )
-                        ),
-                        aggregate_key_image
-                    ]
-                )
-                    .compress()
-                    .as_bytes(),
+    /// As [`ChallengeChain::reconstruct_challenge`] with the generator `G`, the hash to point `Hp`
+    /// and the hash to scalar `Hs`
+    pub(crate) fn reconstruct_challenge_with<
+        Hash: Digest<OutputSize = U64> + Clone + Default,
+        G: Generator,
+        Hp: HashToPoint,
+        Hs: HashToScalar,
+    >(
+        &self,
+        message: &Vec<u8>,
+        slots: Range<usize>,
+        challenge: Scalar,
+    ) -> Scalar {
+        let g: RistrettoPoint = G::generator();
+        let mut reconstructed_c: Scalar = challenge;
+        let coefficients: Vec<Scalar> = aggregation_coefficients::<Hash, Hs>(&self.ring, &self.key_images);
+        let aggregate_public_keys: Vec<RistrettoPoint> = aggregate_public_keys(&self.ring, &coefficients);
+        let aggregate_key_image: RistrettoPoint = aggregate_key_image(&self.key_images, &coefficients);
+        let round_hash: Hash = compute_challenge_prefix::<Hash>(&self.ring, message);
+        for _i in slots {
+            reconstructed_c = compute_challenge_step_with::<Hash, Hs>(
+                round_hash.clone(),
+                &ops::multiscalar_mul(
+                    &[self.responses[_i].0, reconstructed_c],
+                    &[g, aggregate_public_keys[_i]]
+                ),
+                &ops::multiscalar_mul(
+                    &[self.responses[_i].0, reconstructed_c],
+                    &[Hp::hash_to_point::<Hash>(&self.ring[_i][0]), aggregate_key_image]
+                ),
             );
-            reconstructed_c = Scalar::from_hash(h);
         }
 
-        return signature.challenge == reconstructed_c;
+        return reconstructed_c;
+    }
+}
+
+impl ChallengeChain for CLSAG {
+    fn ring_size(&self) -> usize {
+        return self.ring.len();
+    }
+
+    fn challenge(&self) -> Scalar {
+        return self.challenge.0;
+    }
+
+    /// Starting from `challenge` entering the first slot in `slots`, recomputes the challenge
+    /// leaving the last one
+    fn reconstruct_challenge<Hash: Digest<OutputSize = U64> + Clone + Default>(
+        &self,
+        message: &Vec<u8>,
+        slots: Range<usize>,
+        challenge: Scalar,
+    ) -> Option<Scalar> {
+        // A malformed signature has no chain to run
+        if self.check_shape().is_err() || slots.end > self.ring.len() {
+            return None;
+        }
+        return Some(self.reconstruct_challenge_with::<Hash, Basepoint, DefaultHashToPoint, WideReduction>(
+            message,
+            slots,
+            challenge,
+        ));
+    }
+}
+
+impl VerifyRef for CLSAG {
+    /// To verify a `signature` you need the `message` too
+    fn verify_ref<Hash: Digest<OutputSize = U64> + Clone + Default>(&self, message: &Vec<u8>) -> bool {
+        return self.verify_ref_with::<Hash, DefaultHashToPoint, WideReduction>(message);
     }
 }
 
-impl Link for CLSAG {
+#[cfg(feature = "allocator-api")]
+impl VerifyIn for CLSAG {
+    /// The aggregate public key of each row is computed as the chain reaches it, so only the
+    /// aggregation coefficients are allocated
+    fn verify_in<Hash: Digest<OutputSize = U64> + Clone + Default, A: Allocator>(
+        &self,
+        message: &Vec<u8>,
+        alloc: A,
+    ) -> bool {
+        if !self.is_well_formed() {
+            return false;
+        }
+        let mut coefficients = allocator_api2::vec::Vec::with_capacity_in(self.key_images.len(), alloc);
+        aggregation_coefficients_in::<Hash, WideReduction, _>(&self.ring, &self.key_images, &mut coefficients);
+        return self.verify_with_coefficients::<Hash>(&coefficients, message);
+    }
+}
+
+impl VerifyWithGenerator for CLSAG {
+    fn verify_with_generator<Hash: Digest<OutputSize = U64> + Clone + Default, G: Generator>(
+        &self,
+        message: &Vec<u8>,
+    ) -> bool {
+        return self.verify_ref_with_generator::<Hash, G, DefaultHashToPoint, WideReduction>(message);
+    }
+}
+
+#[cfg(feature = "sign")]
+impl TrySign<Vec<Scalar>, Vec<Vec<RistrettoPoint>>> for CLSAG {
+    fn check_sign_input(ks: &Vec<Scalar>, ring: &Vec<Vec<RistrettoPoint>>, secret_index: usize) -> Result<(), Error> {
+        check_columns(ks.len(), ring)?;
+        return check_secret_index(ring.len(), secret_index);
+    }
+}
+
+#[cfg(feature = "sign")]
+impl SignWithHiddenIndex<Vec<Scalar>, Vec<RistrettoPoint>> for CLSAG {}
+
+#[cfg(feature = "sign")]
+impl SignWithFullRing<Vec<Scalar>, Vec<Vec<RistrettoPoint>>> for CLSAG {
+    fn take_signer(ks: &Vec<Scalar>, ring: &mut Vec<Vec<RistrettoPoint>>, secret_index: usize) -> Result<(), Error> {
+        let k_points: Vec<RistrettoPoint> = ks.iter().map(|k| k * constants::RISTRETTO_BASEPOINT_POINT).collect();
+        return take_signer(ring, secret_index, &k_points);
+    }
+}
+
+#[cfg(feature = "sign")]
+impl Scheme for CLSAG {
+    type SecretKey = Vec<Scalar>;
+    type Ring = Vec<Vec<RistrettoPoint>>;
+
+    fn sign_with_key<Hash: Digest<OutputSize = U64> + Clone + Default, CSPRNG: CryptoRng + RngCore + Default>(
+        k: Vec<Scalar>,
+        ring: Vec<Vec<RistrettoPoint>>,
+        secret_index: usize,
+        message: &Vec<u8>,
+    ) -> Result<CLSAG, Error> {
+        let mut csprng = CSPRNG::default();
+        return CLSAG::sign_with_rng::<Hash, CSPRNG>(k, ring, secret_index, message, &mut csprng);
+    }
+
+    fn try_sign_with_key_and_rng<Hash: Digest<OutputSize = U64> + Clone + Default, CSPRNG: CryptoRng + RngCore>(
+        k: Vec<Scalar>,
+        ring: Vec<Vec<RistrettoPoint>>,
+        secret_index: usize,
+        message: &Vec<u8>,
+        csprng: &mut CSPRNG,
+    ) -> Result<CLSAG, Error> {
+        return CLSAG::sign_with_rng_unchecked::<Hash, CSPRNG>(k, ring, secret_index, message, csprng);
+    }
+}
+
+impl TryVerify for CLSAG {
+    fn check_shape(&self) -> Result<(), Error> {
+        check_responses(self.ring.len(), self.responses.len())?;
+        return check_columns(self.key_images.len(), &self.ring);
+    }
+}
+
+impl LinkRef for CLSAG {
     /// This is for linking two signatures and checking if they are signed by the same person
-    fn link(signature_1: CLSAG, signature_2: CLSAG) -> bool {
-        return signature_1.key_images[0] == signature_2.key_images[0];
+    fn link_ref(&self, other: &CLSAG) -> bool {
+        // Signatures built by hand may have no key images, and link to nothing
+        return !self.key_images.is_empty() && self.key_images.first() == other.key_images.first();
+    }
+}
+
+impl Weight for CLSAG {
+    /// Approximate cost of verifying this signature, see [`crate::weight`]
+    fn weight(&self) -> u64 {
+        return weight::clsag(self.ring.len(), self.ring.first().map_or(0, |row| row.len()));
+    }
+}
+
+impl CLSAG {
+    /// Assembles a signature from its fields, returning `None` if it is not
+    /// [`CLSAG::is_well_formed`]
+    pub fn from_parts(
+        challenge: Challenge,
+        responses: Vec<Response>,
+        ring: Vec<Vec<RistrettoPoint>>,
+        key_images: Vec<RistrettoPoint>,
+    ) -> Option<CLSAG> {
+        let signature = CLSAG::from_parts_unchecked(challenge, responses, ring, key_images);
+        if !signature.is_well_formed() {
+            return None;
+        }
+        return Some(signature);
+    }
+
+    /// Assembles a signature from its fields without checking them
+    ///
+    /// This is for fields that have been checked before, e.g. by [`CLSAG::from_parts`] or by
+    /// decoding, and are read back from storage that is trusted. A signature assembled from
+    /// fields that do not agree is rejected: verifying it returns false.
+    pub fn from_parts_unchecked(
+        challenge: Challenge,
+        responses: Vec<Response>,
+        ring: Vec<Vec<RistrettoPoint>>,
+        key_images: Vec<RistrettoPoint>,
+    ) -> CLSAG {
+        return CLSAG {
+            challenge: challenge,
+            responses: responses,
+            ring: ring,
+            key_images: key_images,
+        };
     }
 }
 
+impl Codec for CLSAG {
+    /// Encodes the signature canonically, see [`crate::codec`]
+    fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes: Vec<u8> = Vec::new();
+        codec::write_challenge(&mut bytes, &self.challenge);
+        codec::write_u32(&mut bytes, self.ring.len());
+        codec::write_u32(&mut bytes, self.key_images.len());
+        for response in &self.responses {
+            codec::write_response(&mut bytes, response);
+        }
+        for row in &self.ring {
+            for k_point in row {
+                codec::write_point(&mut bytes, k_point);
+            }
+        }
+        for key_image in &self.key_images {
+            codec::write_point(&mut bytes, key_image);
+        }
+        return bytes;
+    }
+
+    /// Decodes a signature, returning the [`Error`] describing why if `bytes` is not a canonical encoding
+    fn decode(bytes: &[u8]) -> Result<CLSAG, Error> {
+        let mut reader = Reader::new(bytes);
+        let challenge = reader.read_challenge()?;
+        let nr = reader.read_len(1)?;
+        let nc = reader.read_len(1)?;
+        let responses = reader.read_responses(nr)?;
+        let ring = (0..nr)
+            .map(|_| reader.read_points(nc))
+            .collect::<Result<Vec<Vec<RistrettoPoint>>, Error>>()?;
+        let key_images = reader.read_points(nc)?;
+        reader.finish()?;
+        let signature = CLSAG {
+            challenge: challenge,
+            responses: responses,
+            ring: ring,
+            key_images: key_images,
+        };
+        signature.check_shape()?;
+        return Ok(signature);
+    }
+}
+
+crate::encoding::display_via_codec!(CLSAG);
+
+/// A signature spending one of the scanned key images
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct SpendHit {
+    /// Index of the signature in the scanned signatures
+    pub signature_index: usize,
+    /// Index of its key image in the scanned key images
+    pub key_image_index: usize,
+}
+
+/// Finds the signatures among `signatures` whose linking key image is one of `my_key_images`,
+/// e.g. so that a wallet notices its outputs being spent by someone holding a leaked key
+///
+/// Takes a single pass over `signatures` and looks each one up in a map of `my_key_images`, so
+/// the cost is linear in the number of signatures rather than in their product.
+pub fn scan_for_spends(signatures: &[CLSAG], my_key_images: &[KeyImage]) -> Vec<SpendHit> {
+    let mut index: BTreeMap<KeyImage, usize> = BTreeMap::new();
+    for (key_image_index, key_image) in my_key_images.iter().enumerate() {
+        index.entry(*key_image).or_insert(key_image_index);
+    }
+    let mut hits: Vec<SpendHit> = Vec::new();
+    for (signature_index, signature) in signatures.iter().enumerate() {
+        let key_image = match signature.key_images.first() {
+            Some(key_image) => link::to_key_image(key_image),
+            None => continue,
+        };
+        if let Some(key_image_index) = index.get(&key_image) {
+            hits.push(SpendHit {
+                signature_index: signature_index,
+                key_image_index: *key_image_index,
+            });
+        }
+    }
+    return hits;
+}
+
 #[cfg(test)]
 #[cfg(feature = "std")]
+#[cfg(feature = "sign")]
 mod test {
     extern crate blake2;
     extern crate rand;
@@ -329,6 +803,7 @@ mod test {
     extern crate sha3;
 
     use super::*;
+    use crate::traits::{Link, Sign, Verify};
     use blake2::Blake2b512;
     use curve25519_dalek::ristretto::RistrettoPoint;
     use curve25519_dalek::scalar::Scalar;
@@ -357,23 +832,41 @@ mod test {
         let message: Vec<u8> = b"This is the message".iter().cloned().collect();
 
         {
-            let signature =
-                CLSAG::sign::<Sha512, OsRng>(ks.clone(), ring.clone(), secret_index, &message);
-            let result = CLSAG::verify::<Sha512>(signature, &message);
+            let signature = CLSAG::sign_with_rng_unchecked::<Sha512, _>(
+                ks.clone(),
+                ring.clone(),
+                secret_index,
+                &message,
+                &mut OsRng,
+            )
+            .unwrap();
+            let result = signature.verify_ref::<Sha512>(&message);
             assert!(result);
         }
 
         {
-            let signature =
-                CLSAG::sign::<Keccak512, OsRng>(ks.clone(), ring.clone(), secret_index, &message);
-            let result = CLSAG::verify::<Keccak512>(signature, &message);
+            let signature = CLSAG::sign_with_rng_unchecked::<Keccak512, _>(
+                ks.clone(),
+                ring.clone(),
+                secret_index,
+                &message,
+                &mut OsRng,
+            )
+            .unwrap();
+            let result = signature.verify_ref::<Keccak512>(&message);
             assert!(result);
         }
 
         {
-            let signature =
-                CLSAG::sign::<Blake2b512, OsRng>(ks.clone(), ring.clone(), secret_index, &message);
-            let result = CLSAG::verify::<Blake2b512>(signature, &message);
+            let signature = CLSAG::sign_with_rng_unchecked::<Blake2b512, _>(
+                ks.clone(),
+                ring.clone(),
+                secret_index,
+                &message,
+                &mut OsRng,
+            )
+            .unwrap();
+            let result = signature.verify_ref::<Blake2b512>(&message);
             assert!(result);
         }
 
@@ -385,15 +878,227 @@ mod test {
             })
             .collect();
         let another_message: Vec<u8> = b"This is another message".iter().cloned().collect();
-        let signature_1 = CLSAG::sign::<Blake2b512, OsRng>(
+        let signature_1 = CLSAG::sign_with_rng_unchecked::<Blake2b512, _>(
             ks.clone(),
             another_ring.clone(),
             secret_index,
             &another_message,
+            &mut OsRng,
+        )
+        .unwrap();
+        let signature_2 = CLSAG::sign_with_rng_unchecked::<Blake2b512, _>(
+            ks.clone(),
+            ring.clone(),
+            secret_index,
+            &message,
+            &mut OsRng,
+        )
+        .unwrap();
+        let result = signature_1.link_ref(&signature_2);
+        assert!(result);
+    }
+
+    #[test]
+    fn aggregates() {
+        let mut csprng = OsRng;
+        let ks: Vec<Scalar> = (0..2).map(|_| Scalar::random(&mut csprng)).collect();
+        let ring: Vec<Vec<RistrettoPoint>> = (0..2)
+            .map(|_| (0..2).map(|_| RistrettoPoint::random(&mut csprng)).collect())
+            .collect();
+        let message: Vec<u8> = b"This is the message".to_vec();
+        let signature = CLSAG::sign_with_rng_unchecked::<Sha512, _>(ks.clone(), ring, 1, &message, &mut OsRng).unwrap();
+
+        // The signer's row aggregates to z G and the key images to z H_p(K_0) for the same z
+        let coefficients = aggregation_coefficients::<Sha512, WideReduction>(&signature.ring, &signature.key_images);
+        let z: Scalar = (0..2).map(|j| coefficients[j] * ks[j]).sum();
+        let base_key_hashed_to_point = RistrettoPoint::from_hash(
+            Sha512::default().chain_update(signature.ring[1][0].compress().as_bytes()),
+        );
+        assert_eq!(
+            signature.aggregate_public_keys::<Sha512>()[1],
+            z * constants::RISTRETTO_BASEPOINT_POINT
         );
+        assert_eq!(signature.aggregate_key_image::<Sha512>(), z * base_key_hashed_to_point);
+    }
+
+    #[test]
+    fn scan_for_spends() {
+        let mut csprng = OsRng;
+        let message: Vec<u8> = b"This is the message".to_vec();
+        let signatures: Vec<CLSAG> = (0..3)
+            .map(|_| {
+                let ks: Vec<Scalar> = (0..2).map(|_| Scalar::random(&mut csprng)).collect();
+                let ring: Vec<Vec<RistrettoPoint>> = (0..2)
+                    .map(|_| (0..2).map(|_| RistrettoPoint::random(&mut csprng)).collect())
+                    .collect();
+                CLSAG::sign_with_rng_unchecked::<Sha512, _>(ks, ring, 0, &message, &mut OsRng).unwrap()
+            })
+            .collect();
+
+        // Only the linking key image counts, not the auxiliary ones
+        let my_key_images: Vec<KeyImage> = vec![
+            link::to_key_image(&RistrettoPoint::random(&mut csprng)),
+            link::to_key_image(&signatures[2].key_images[0]),
+            link::to_key_image(&signatures[1].key_images[1]),
+        ];
+        assert_eq!(
+            super::scan_for_spends(&signatures, &my_key_images),
+            vec![SpendHit {
+                signature_index: 2,
+                key_image_index: 1,
+            }]
+        );
+    }
+
+    #[test]
+    fn column_counts() {
+        let mut csprng = OsRng;
+        let ks: Vec<Scalar> = (0..2).map(|_| Scalar::random(&mut csprng)).collect();
+        let ring: Vec<Vec<RistrettoPoint>> = (0..2)
+            .map(|_| (0..2).map(|_| RistrettoPoint::random(&mut csprng)).collect())
+            .collect();
+        let message: Vec<u8> = b"This is the message".to_vec();
+        let signature = CLSAG::sign_with_rng_unchecked::<Sha512, _>(ks, ring, 1, &message, &mut OsRng).unwrap();
+        assert!(signature.is_well_formed());
+
+        // An extra key image, a short row or a missing response is rejected without panicking
+        let mut extra_key_image = signature.clone();
+        extra_key_image.key_images.push(RistrettoPoint::random(&mut csprng));
+        assert!(!extra_key_image.verify_ref::<Sha512>(&message));
+        assert!(CLSAG::from_bytes(&extra_key_image.to_bytes()).is_none());
+        let mut short_row = signature.clone();
+        short_row.ring[0].pop();
+        assert!(!short_row.verify_ref::<Sha512>(&message));
+        let mut missing_response = signature;
+        missing_response.responses.pop();
+        assert!(!missing_response.verify_ref::<Sha512>(&message));
+
+        // A ring without columns does not decode
+        let mut bytes: Vec<u8> = Vec::new();
+        codec::write_scalar(&mut bytes, &Scalar::ONE);
+        codec::write_u32(&mut bytes, 1);
+        codec::write_u32(&mut bytes, 0);
+        codec::write_scalar(&mut bytes, &Scalar::ONE);
+        assert!(CLSAG::from_bytes(&bytes).is_none());
+    }
+    #[test]
+    fn verify_ref() {
+        let ks: Vec<Scalar> = (0..2).map(|_| Scalar::random(&mut OsRng)).collect();
+        let ring: Vec<Vec<RistrettoPoint>> = (0..10)
+            .map(|_| (0..2).map(|_| RistrettoPoint::random(&mut OsRng)).collect())
+            .collect();
+        let message: Vec<u8> = b"This is the message".to_vec();
+        let signature_1 =
+            CLSAG::sign_with_rng_unchecked::<Sha512, _>(ks.clone(), ring.clone(), 3, &message, &mut OsRng).unwrap();
         let signature_2 =
-            CLSAG::sign::<Blake2b512, OsRng>(ks.clone(), ring.clone(), secret_index, &message);
-        let result = CLSAG::link(signature_1, signature_2);
-        assert!(result);
+            CLSAG::sign_with_rng_unchecked::<Sha512, _>(ks.clone(), ring.clone(), 7, &message, &mut OsRng).unwrap();
+
+        // The signatures are still owned after verifying and linking
+        assert!(signature_1.verify_ref::<Sha512>(&message));
+        assert!(!signature_1.verify_ref::<Sha512>(&b"Another message".to_vec()));
+        assert!(signature_1.link_ref(&signature_2));
+        // The deprecated by value methods agree with them
+        #[allow(deprecated)]
+        {
+            let verified = CLSAG::verify::<Sha512>(signature_1.clone(), &message);
+            assert_eq!(signature_1.verify_ref::<Sha512>(&message), verified);
+            let signature_3 = CLSAG::sign::<Sha512, OsRng>(ks, ring, 5, &message);
+            assert!(signature_3.verify_ref::<Sha512>(&message));
+            assert!(signature_3.link_ref(&signature_2));
+            assert!(CLSAG::link(signature_1, signature_2));
+        }
+    }
+
+    #[test]
+    fn memory_budget() {
+        use crate::policy::MemoryBudget;
+        use crate::signature::{self, RingSignature, SignatureBytes};
+
+        let ks: Vec<Scalar> = (0..2).map(|_| Scalar::random(&mut OsRng)).collect();
+        let ring: Vec<Vec<RistrettoPoint>> = (0..10)
+            .map(|_| (0..2).map(|_| RistrettoPoint::random(&mut OsRng)).collect())
+            .collect();
+        let message: Vec<u8> = b"This is the message".to_vec();
+        let clsag = CLSAG::sign_with_rng_unchecked::<Sha512, _>(ks, ring, 3, &message, &mut OsRng).unwrap();
+        assert!(clsag.verify_ref_streaming::<Sha512>(&message));
+        assert!(!clsag.verify_ref_streaming::<Sha512>(&b"Another message".to_vec()));
+
+        let bytes = clsag.to_tagged_bytes();
+        assert!(signature::parse_within(&bytes, &MemoryBudget::new(1024)).is_err());
+        let signature: RingSignature = signature::parse_within(&bytes, &MemoryBudget::unlimited()).unwrap();
+        assert_eq!(signature.verify_within::<Sha512>(&message, &MemoryBudget::unlimited()), Ok(true));
+
+        // Too little for the aggregate public keys of all 11 rows, enough for the coefficients
+        let tight = MemoryBudget::new(100);
+        assert_eq!(signature.verify_within::<Sha512>(&message, &tight), Ok(true));
+        assert_eq!(signature.verify_within::<Sha512>(&b"Another message".to_vec(), &tight), Ok(false));
+        assert_eq!(
+            signature.verify_within::<Sha512>(&message, &MemoryBudget::new(0)),
+            Err(Error::BudgetExceeded { needed: 64, budget: 0 })
+        );
+    }
+
+
+    #[test]
+    fn sign_with_full_ring() {
+        let ks: Vec<Scalar> = (0..2).map(|_| Scalar::random(&mut OsRng)).collect();
+        let mut ring: Vec<Vec<RistrettoPoint>> = (0..10)
+            .map(|_| (0..2).map(|_| RistrettoPoint::random(&mut OsRng)).collect())
+            .collect();
+        ring.insert(1, ks.iter().map(|k| k * constants::RISTRETTO_BASEPOINT_POINT).collect());
+        let message: Vec<u8> = b"This is the message".to_vec();
+
+        let signature = CLSAG::sign_with_full_ring::<Sha512, OsRng>(ks.clone(), ring.clone(), 1, &message).unwrap();
+        assert_eq!(signature.ring, ring);
+        assert!(signature.verify_ref::<Sha512>(&message));
+
+        // Every key of the row must be the signer's
+        let mut swapped = ring.clone();
+        swapped[1].swap(0, 1);
+        assert_eq!(
+            CLSAG::sign_with_full_ring::<Sha512, OsRng>(ks.clone(), swapped, 1, &message).err(),
+            Some(Error::NotInRing)
+        );
+        assert_eq!(
+            CLSAG::sign_with_full_ring::<Sha512, OsRng>(ks[..1].to_vec(), ring, 1, &message).err(),
+            Some(Error::NotInRing)
+        );
+    }
+
+    #[test]
+    fn does_not_panic() {
+        use crate::link::LinkingKeyImage;
+
+        let ks: Vec<Scalar> = (0..2).map(|_| Scalar::random(&mut OsRng)).collect();
+        let ring: Vec<Vec<RistrettoPoint>> = vec![(0..2).map(|_| RistrettoPoint::random(&mut OsRng)).collect()];
+        let message: Vec<u8> = b"This is the message".to_vec();
+
+        // Signing returns an error for what it can not sign with
+        let out_of_range =
+            CLSAG::sign_with_rng_unchecked::<Sha512, _>(ks.clone(), ring.clone(), 2, &message, &mut OsRng);
+        assert_eq!(out_of_range.err(), Some(Error::SecretIndexOutOfRange { index: 2, ring_size: 2 }));
+        let ragged = vec![ring[0][..1].to_vec()];
+        let ragged = CLSAG::sign_with_rng_unchecked::<Sha512, _>(ks.clone(), ragged, 0, &message, &mut OsRng);
+        assert_eq!(ragged.err(), Some(Error::RaggedRingMatrix));
+        let keyless = CLSAG::sign_with_rng_unchecked::<Sha512, _>(Vec::new(), Vec::new(), 0, &message, &mut OsRng);
+        assert_eq!(keyless.err(), Some(Error::EmptyRing));
+        // No keys give no key images
+        assert!(CLSAG::generate_key_image_ref::<Sha512>(&Vec::new()).is_empty());
+
+        // Signatures built by hand without key images or a ring verify, link and run their chain
+        // to a refusal
+        let signature = CLSAG::sign_with_rng_unchecked::<Sha512, _>(ks, ring, 0, &message, &mut OsRng).unwrap();
+        let challenge = signature.challenge.0;
+        let mut imageless = signature.clone();
+        imageless.key_images.clear();
+        assert!(!imageless.verify_ref::<Sha512>(&message));
+        assert!(!imageless.link_ref(&imageless));
+        assert!(imageless.linking_key_image().is_none());
+        assert_eq!(imageless.reconstruct_challenge::<Sha512>(&message, 0..2, challenge), None);
+        let mut ringless = signature;
+        ringless.ring.clear();
+        ringless.responses.clear();
+        assert!(!ringless.verify_ref::<Sha512>(&message));
+        assert_eq!(ringless.reconstruct_challenge::<Sha512>(&message, 0..0, challenge), None);
     }
 }
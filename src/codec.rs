@@ -0,0 +1,327 @@
+//! Canonical byte encoding
+//!
+//! Every signature has exactly one encoding. Scalars are written as their canonical 32 byte
+//! little-endian representation, points as compressed Ristretto points, and lengths as 32 bit
+//! little-endian integers. Decoding rejects non-canonical scalars, invalid points and trailing
+//! bytes, and [`Codec::decode`] reports which of these it ran into.
+
+use crate::prelude::*;
+
+use curve25519_dalek::ristretto::{CompressedRistretto, RistrettoPoint};
+use curve25519_dalek::scalar::Scalar;
+
+use crate::error::Error;
+#[cfg(any(
+    feature = "sag",
+    feature = "lsag",
+    feature = "blsag",
+    feature = "mlsag",
+    feature = "clsag",
+    feature = "dlsag",
+    feature = "mdlsag"
+))]
+use crate::scalars::{Challenge, Response};
+
+pub trait Codec: Sized {
+    fn to_bytes(&self) -> Vec<u8>;
+
+    /// Decodes a canonical encoding, or reports the first field that is not one
+    fn decode(bytes: &[u8]) -> Result<Self, Error>;
+
+    /// As [`Codec::decode`], without the reason
+    fn from_bytes(bytes: &[u8]) -> Option<Self> {
+        return Self::decode(bytes).ok();
+    }
+}
+
+pub(crate) fn write_u32(bytes: &mut Vec<u8>, value: usize) {
+    bytes.extend_from_slice(&(value as u32).to_le_bytes());
+}
+
+pub(crate) fn write_scalar(bytes: &mut Vec<u8>, scalar: &Scalar) {
+    bytes.extend_from_slice(scalar.as_bytes());
+}
+
+#[cfg(any(
+    feature = "sag",
+    feature = "lsag",
+    feature = "blsag",
+    feature = "mlsag",
+    feature = "clsag",
+    feature = "dlsag",
+    feature = "mdlsag"
+))]
+pub(crate) fn write_challenge(bytes: &mut Vec<u8>, challenge: &Challenge) {
+    write_scalar(bytes, &challenge.0);
+}
+
+#[cfg(any(
+    feature = "sag",
+    feature = "lsag",
+    feature = "blsag",
+    feature = "mlsag",
+    feature = "clsag",
+    feature = "dlsag",
+    feature = "mdlsag"
+))]
+pub(crate) fn write_response(bytes: &mut Vec<u8>, response: &Response) {
+    write_scalar(bytes, &response.0);
+}
+
+pub(crate) fn write_point(bytes: &mut Vec<u8>, point: &RistrettoPoint) {
+    bytes.extend_from_slice(point.compress().as_bytes());
+}
+
+// The flag of DLSAG and MDLSAG, and the validity of the entries of `testing::corpus`
+#[cfg(any(
+    feature = "dlsag",
+    feature = "mdlsag",
+    all(
+        feature = "sign",
+        any(feature = "sag", feature = "lsag", feature = "blsag", feature = "mlsag", feature = "clsag")
+    )
+))]
+pub(crate) fn write_bool(bytes: &mut Vec<u8>, value: bool) {
+    bytes.push(value as u8);
+}
+
+/// Reads the fields of an encoding in order, failing on the first malformed one
+pub(crate) struct Reader<'a> {
+    bytes: &'a [u8],
+}
+
+impl<'a> Reader<'a> {
+    pub(crate) fn new(bytes: &'a [u8]) -> Reader<'a> {
+        return Reader { bytes: bytes };
+    }
+
+    fn take(&mut self, n: usize) -> Result<&'a [u8], Error> {
+        if self.bytes.len() < n {
+            return Err(Error::Truncated);
+        }
+        let (head, tail) = self.bytes.split_at(n);
+        self.bytes = tail;
+        return Ok(head);
+    }
+
+    fn take_32(&mut self) -> Result<[u8; 32], Error> {
+        let mut array = [0u8; 32];
+        array.copy_from_slice(self.take(32)?);
+        return Ok(array);
+    }
+
+    /// Reads a length, rejecting lengths that could not possibly fit in the remaining bytes
+    /// given that each element takes at least `element_size` bytes
+    pub(crate) fn read_len(&mut self, element_size: usize) -> Result<usize, Error> {
+        let mut array = [0u8; 4];
+        array.copy_from_slice(self.take(4)?);
+        let len = u32::from_le_bytes(array) as usize;
+        match len.checked_mul(element_size) {
+            Some(size) if size <= self.bytes.len() => return Ok(len),
+            _ => return Err(Error::Truncated),
+        }
+    }
+
+    pub(crate) fn read_bytes(&mut self, n: usize) -> Result<&'a [u8], Error> {
+        return self.take(n);
+    }
+
+    pub(crate) fn read_scalar(&mut self) -> Result<Scalar, Error> {
+        let scalar: Option<Scalar> = Option::from(Scalar::from_canonical_bytes(self.take_32()?));
+        return scalar.ok_or(Error::NonCanonicalScalar);
+    }
+
+    #[cfg(any(
+        feature = "sag",
+        feature = "lsag",
+        feature = "blsag",
+        feature = "mlsag",
+        feature = "clsag",
+        feature = "dlsag",
+        feature = "mdlsag"
+    ))]
+    pub(crate) fn read_challenge(&mut self) -> Result<Challenge, Error> {
+        return self.read_scalar().map(Challenge);
+    }
+
+    pub(crate) fn read_point(&mut self) -> Result<RistrettoPoint, Error> {
+        return CompressedRistretto(self.take_32()?)
+            .decompress()
+            .ok_or(Error::InvalidPointEncoding);
+    }
+
+    // The flag of DLSAG and MDLSAG, and the validity of the entries of `testing::corpus`
+    #[cfg(any(
+        feature = "dlsag",
+        feature = "mdlsag",
+        all(
+            feature = "sign",
+            any(feature = "sag", feature = "lsag", feature = "blsag", feature = "mlsag", feature = "clsag")
+        )
+    ))]
+    pub(crate) fn read_bool(&mut self) -> Result<bool, Error> {
+        return match self.take(1)?[0] {
+            0 => Ok(false),
+            1 => Ok(true),
+            _ => Err(Error::Malformed),
+        };
+    }
+
+    #[cfg(any(
+        feature = "sag",
+        feature = "lsag",
+        feature = "blsag",
+        feature = "mlsag",
+        feature = "clsag",
+        feature = "dlsag",
+        feature = "mdlsag"
+    ))]
+    pub(crate) fn read_responses(&mut self, n: usize) -> Result<Vec<Response>, Error> {
+        return (0..n).map(|_| self.read_scalar().map(Response)).collect();
+    }
+
+    #[cfg(any(
+        feature = "sag",
+        feature = "lsag",
+        feature = "blsag",
+        feature = "mlsag",
+        feature = "clsag",
+        feature = "mdlsag"
+    ))]
+    pub(crate) fn read_points(&mut self, n: usize) -> Result<Vec<RistrettoPoint>, Error> {
+        return (0..n).map(|_| self.read_point()).collect();
+    }
+
+    /// The remaining bytes, for encodings that end in one of unprefixed length
+    #[cfg(feature = "blsag")]
+    pub(crate) fn rest(&mut self) -> &'a [u8] {
+        let rest = self.bytes;
+        self.bytes = &[];
+        return rest;
+    }
+
+    /// Whether every byte has been consumed, for encodings that end in an optional field
+    #[cfg(feature = "blsag")]
+    pub(crate) fn is_empty(&self) -> bool {
+        return self.bytes.is_empty();
+    }
+
+    /// Succeeds only if every byte has been consumed
+    pub(crate) fn finish(self) -> Result<(), Error> {
+        if self.bytes.is_empty() {
+            return Ok(());
+        }
+        return Err(Error::TrailingBytes);
+    }
+}
+
+/// Deserializes a canonical encoding, from bytes or from a sequence of bytes for formats without
+/// a byte string type, or from hex in human readable formats
+#[cfg(feature = "serde")]
+pub(crate) struct CodecVisitor<T>(pub(crate) core::marker::PhantomData<T>);
+
+#[cfg(feature = "serde")]
+impl<'de, T: Codec> serde::de::Visitor<'de> for CodecVisitor<T> {
+    type Value = T;
+
+    fn expecting(&self, formatter: &mut core::fmt::Formatter) -> core::fmt::Result {
+        return formatter.write_str("a canonical encoding");
+    }
+
+    fn visit_bytes<E: serde::de::Error>(self, v: &[u8]) -> Result<T, E> {
+        return T::from_bytes(v).ok_or_else(|| E::invalid_value(serde::de::Unexpected::Bytes(v), &self));
+    }
+
+    fn visit_str<E: serde::de::Error>(self, v: &str) -> Result<T, E> {
+        let bytes =
+            crate::encoding::decode_hex(v).map_err(|_| E::invalid_value(serde::de::Unexpected::Str(v), &self))?;
+        return self.visit_bytes(&bytes);
+    }
+
+    fn visit_seq<A: serde::de::SeqAccess<'de>>(self, mut seq: A) -> Result<T, A::Error> {
+        let mut bytes: Vec<u8> = Vec::new();
+        while let Some(byte) = seq.next_element::<u8>()? {
+            bytes.push(byte);
+        }
+        return self.visit_bytes(&bytes);
+    }
+}
+
+/// Implements `serde::Serialize` and `serde::Deserialize` for a [`Codec`] type through its
+/// canonical encoding, so deserializing validates exactly as `from_bytes` does. Human readable
+/// formats get the encoding as a hex string, others as bytes.
+#[cfg(feature = "serde")]
+macro_rules! serde_via_codec {
+    ($t:ty) => {
+        impl serde::Serialize for $t {
+            fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+                let bytes = crate::codec::Codec::to_bytes(self);
+                if serializer.is_human_readable() {
+                    return serializer.serialize_str(&crate::encoding::encode_hex(&bytes));
+                }
+                return serializer.serialize_bytes(&bytes);
+            }
+        }
+
+        impl<'de> serde::Deserialize<'de> for $t {
+            fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<$t, D::Error> {
+                let visitor = crate::codec::CodecVisitor(core::marker::PhantomData);
+                if deserializer.is_human_readable() {
+                    return deserializer.deserialize_str(visitor);
+                }
+                return deserializer.deserialize_bytes(visitor);
+            }
+        }
+    };
+}
+
+#[cfg(feature = "serde")]
+pub(crate) use serde_via_codec;
+
+#[cfg(test)]
+#[cfg(feature = "std")]
+#[cfg(feature = "sign")]
+#[cfg(feature = "sag")]
+mod test {
+    extern crate rand;
+    extern crate sha2;
+
+    use curve25519_dalek::ristretto::RistrettoPoint;
+    use curve25519_dalek::scalar::Scalar;
+    use rand::rngs::OsRng;
+    use sha2::Sha512;
+
+    use super::*;
+    use crate::sag::SAG;
+    use crate::traits::SignWithRng;
+
+    #[test]
+    fn codec() {
+        let mut csprng = OsRng;
+        let k: Scalar = Scalar::random(&mut csprng);
+        let ring: Vec<RistrettoPoint> = (0..2).map(|_| RistrettoPoint::random(&mut csprng)).collect();
+        let message: Vec<u8> = b"This is the message".to_vec();
+        let signature = SAG::sign_with_rng_unchecked::<Sha512, _>(k, ring, 1, &message, &mut OsRng).unwrap();
+        let bytes = signature.to_bytes();
+
+        assert!(SAG::from_bytes(&bytes).is_some());
+        // Truncated and extended encodings are rejected
+        assert!(SAG::from_bytes(&bytes[..bytes.len() - 1]).is_none());
+        let mut extended = bytes.clone();
+        extended.push(0);
+        assert!(SAG::from_bytes(&extended).is_none());
+        // A non-canonical challenge is rejected
+        let mut non_canonical = bytes.clone();
+        non_canonical[..32].copy_from_slice(&[0xff; 32]);
+        assert!(SAG::from_bytes(&non_canonical).is_none());
+
+        // `decode` reports why
+        assert_eq!(SAG::decode(&bytes[..bytes.len() - 1]).err(), Some(Error::Truncated));
+        assert_eq!(SAG::decode(&extended).err(), Some(Error::TrailingBytes));
+        assert_eq!(SAG::decode(&non_canonical).err(), Some(Error::NonCanonicalScalar));
+        let mut invalid_point = bytes.clone();
+        let len = invalid_point.len();
+        invalid_point[len - 32..].copy_from_slice(&[0xff; 32]);
+        assert_eq!(SAG::decode(&invalid_point).err(), Some(Error::InvalidPointEncoding));
+    }
+}
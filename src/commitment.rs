@@ -0,0 +1,142 @@
+//! Key image commitments
+//!
+//! Sealed-bid auctions and commit-reveal votes built on bLSAGs run in two phases. While bids are
+//! open, a participant publishes only a [`KeyImageCommitment`], `H(I || salt)` of the key image
+//! `I` its signature will carry, so that nobody learns which bids come from the same key before
+//! the reveal. Once bidding closes it reveals the signature together with the salt, and anyone
+//! checks with [`KeyImageCommitment::verify_reveal`] that the signature verifies and carries the
+//! key image committed to. A key that committed twice is caught at the reveal, as its two
+//! signatures link.
+//!
+//! The salt is 32 bytes drawn at random, see [`KeyImageCommitment::new`], so that a commitment
+//! does not give away its key image to someone trying the key images of a known set of keys.
+
+use crate::prelude::*;
+
+use curve25519_dalek::ristretto::RistrettoPoint;
+#[cfg(feature = "sign")]
+use curve25519_dalek::scalar::Scalar;
+use digest::generic_array::typenum::U64;
+use digest::Digest;
+#[cfg(feature = "sign")]
+use rand_core::{CryptoRng, RngCore};
+
+use crate::blsag::BLSAG;
+use crate::error::Error;
+use crate::traits::VerifyRef;
+#[cfg(feature = "sign")]
+use crate::traits::KeyImageGenRef;
+
+/// Domain separation of the commitment hash
+const COMMITMENT: &[u8] = b"nazgul/commitment/key_image";
+
+/// The salt a [`KeyImageCommitment`] is opened with, which its owner keeps until the reveal
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Salt(pub [u8; 32]);
+
+/// `H(I || salt)` of a key image `I`, published before the signature carrying `I`
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct KeyImageCommitment(pub [u8; 64]);
+
+impl KeyImageCommitment {
+    /// Commits to the key image the bLSAGs of `k` carry, returning the commitment to publish and
+    /// the salt to keep
+    #[cfg(feature = "sign")]
+    pub fn new<Hash: Digest<OutputSize = U64> + Clone + Default, CSPRNG: CryptoRng + RngCore>(
+        k: &Scalar,
+        csprng: &mut CSPRNG,
+    ) -> (KeyImageCommitment, Salt) {
+        let mut salt = [0u8; 32];
+        csprng.fill_bytes(&mut salt);
+        let salt = Salt(salt);
+        return (KeyImageCommitment::create::<Hash>(&BLSAG::generate_key_image_ref::<Hash>(k), &salt), salt);
+    }
+
+    /// The commitment to `key_image` with `salt`
+    pub fn create<Hash: Digest<OutputSize = U64> + Clone + Default>(
+        key_image: &RistrettoPoint,
+        salt: &Salt,
+    ) -> KeyImageCommitment {
+        let mut h: Hash = Hash::default();
+        h.update(COMMITMENT);
+        h.update(key_image.compress().as_bytes());
+        h.update(salt.0);
+        let mut digest = [0u8; 64];
+        digest.copy_from_slice(&h.finalize());
+        return KeyImageCommitment(digest);
+    }
+
+    /// Whether the commitment is to `key_image` with `salt`
+    pub fn open<Hash: Digest<OutputSize = U64> + Clone + Default>(
+        &self,
+        key_image: &RistrettoPoint,
+        salt: &Salt,
+    ) -> bool {
+        return KeyImageCommitment::create::<Hash>(key_image, salt) == *self;
+    }
+
+    /// Checks the signature revealed for this commitment: it must verify for `message`, giving
+    /// [`Error::InvalidSignature`] otherwise, and carry the key image committed to, giving
+    /// [`Error::CommitmentMismatch`] otherwise
+    pub fn verify_reveal<Hash: Digest<OutputSize = U64> + Clone + Default>(
+        &self,
+        signature: &BLSAG,
+        salt: &Salt,
+        message: &Vec<u8>,
+    ) -> Result<(), Error> {
+        if !self.open::<Hash>(&signature.key_image, salt) {
+            return Err(Error::CommitmentMismatch);
+        }
+        if !signature.verify_ref::<Hash>(message) {
+            return Err(Error::InvalidSignature);
+        }
+        return Ok(());
+    }
+}
+
+#[cfg(test)]
+#[cfg(feature = "std")]
+#[cfg(feature = "sign")]
+mod test {
+    extern crate rand;
+    extern crate sha2;
+
+    use rand::rngs::OsRng;
+    use sha2::Sha512;
+
+    use super::*;
+    use crate::traits::SignWithRng;
+
+    #[test]
+    fn verify_reveal() {
+        let k: Scalar = Scalar::random(&mut OsRng);
+        let ring: Vec<RistrettoPoint> = (0..3).map(|_| RistrettoPoint::random(&mut OsRng)).collect();
+        let message: Vec<u8> = b"Bid 100".to_vec();
+
+        let (commitment, salt) = KeyImageCommitment::new::<Sha512, OsRng>(&k, &mut OsRng);
+        let signature = BLSAG::sign_with_rng_unchecked::<Sha512, _>(k, ring.clone(), 1, &message, &mut OsRng).unwrap();
+        assert!(commitment.open::<Sha512>(&signature.key_image, &salt));
+        assert_eq!(commitment.verify_reveal::<Sha512>(&signature, &salt, &message), Ok(()));
+        assert_eq!(
+            commitment.verify_reveal::<Sha512>(&signature, &salt, &b"Bid 1000".to_vec()),
+            Err(Error::InvalidSignature)
+        );
+        assert_eq!(
+            commitment.verify_reveal::<Sha512>(&signature, &Salt([0u8; 32]), &message),
+            Err(Error::CommitmentMismatch)
+        );
+
+        // The signature of another key does not open the commitment
+        let other =
+            BLSAG::sign_with_rng_unchecked::<Sha512, _>(Scalar::random(&mut OsRng), ring, 1, &message, &mut OsRng)
+                .unwrap();
+        assert_eq!(
+            commitment.verify_reveal::<Sha512>(&other, &salt, &message),
+            Err(Error::CommitmentMismatch)
+        );
+
+        // Commitments of one key image with different salts are unrelated
+        let (again, _) = KeyImageCommitment::new::<Sha512, OsRng>(&k, &mut OsRng);
+        assert_ne!(commitment, again);
+    }
+}
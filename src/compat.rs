@@ -0,0 +1,179 @@
+//! Compatibility with newer RustCrypto trait versions
+//!
+//! The schemes are generic over `digest` 0.10 hash functions and `rand_core` 0.6 generators,
+//! which are the versions curve25519-dalek builds on. The adapters here wrap a hash function or
+//! generator implementing a newer major version so it can be passed wherever the schemes expect
+//! the older one, without pulling a second copy of every hash function into the dependency tree.
+//!
+//! - `digest011` enables [`Digest011`], for hash functions implementing `digest` 0.11
+//! - `rand_core09` enables [`Rng09`], for generators implementing `rand_core` 0.9
+//!
+//! ```ignore
+//! let mut csprng = Rng09(ThreadRng::default());
+//! let signature = BLSAG::sign_with_rng::<Digest011<sha2::Sha512>, _>(k, ring, 1, &message, &mut csprng)?;
+//! ```
+//!
+//! [`Xof`] is always available. It reads 64 bytes from an extendable-output function such as
+//! SHAKE256, for deployments standardized on one, e.g. `BLSAG::sign_with_rng::<Xof<sha3::Shake256>, _>`.
+
+use digest::{ExtendableOutput, Update, XofReader};
+
+/// An extendable-output function, usable as a hash function with its first 64 bytes of output
+#[derive(Clone, Default)]
+pub struct Xof<H>(pub H);
+
+impl<H> digest::OutputSizeUser for Xof<H> {
+    type OutputSize = digest::generic_array::typenum::U64;
+}
+
+impl<H: Update> Update for Xof<H> {
+    fn update(&mut self, data: &[u8]) {
+        self.0.update(data);
+    }
+}
+
+impl<H: ExtendableOutput> digest::FixedOutput for Xof<H> {
+    fn finalize_into(self, out: &mut digest::Output<Self>) {
+        self.0.finalize_xof().read(out);
+    }
+}
+
+impl<H> digest::HashMarker for Xof<H> {}
+
+/// A `digest` 0.11 hash function with 64 byte output, usable as a `digest` 0.10 one
+#[cfg(feature = "digest011")]
+#[derive(Clone, Default)]
+pub struct Digest011<H>(pub H);
+
+#[cfg(feature = "digest011")]
+impl<H> digest::OutputSizeUser for Digest011<H> {
+    type OutputSize = digest::generic_array::typenum::U64;
+}
+
+#[cfg(feature = "digest011")]
+impl<H: digest011::Update> digest::Update for Digest011<H> {
+    fn update(&mut self, data: &[u8]) {
+        self.0.update(data);
+    }
+}
+
+#[cfg(feature = "digest011")]
+impl<H: digest011::FixedOutput + digest011::OutputSizeUser<OutputSize = digest011::consts::U64>>
+    digest::FixedOutput for Digest011<H>
+{
+    fn finalize_into(self, out: &mut digest::Output<Self>) {
+        out.copy_from_slice(self.0.finalize_fixed().as_slice());
+    }
+}
+
+#[cfg(feature = "digest011")]
+impl<H> digest::HashMarker for Digest011<H> {}
+
+/// A `rand_core` 0.9 generator, usable as a `rand_core` 0.6 one
+#[cfg(feature = "rand_core09")]
+#[derive(Clone, Default)]
+pub struct Rng09<R>(pub R);
+
+#[cfg(feature = "rand_core09")]
+impl<R: rand_core09::RngCore> rand_core::RngCore for Rng09<R> {
+    fn next_u32(&mut self) -> u32 {
+        return self.0.next_u32();
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        return self.0.next_u64();
+    }
+
+    fn fill_bytes(&mut self, dest: &mut [u8]) {
+        self.0.fill_bytes(dest);
+    }
+
+    fn try_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), rand_core::Error> {
+        self.0.fill_bytes(dest);
+        return Ok(());
+    }
+}
+
+#[cfg(feature = "rand_core09")]
+impl<R: rand_core09::CryptoRng> rand_core::CryptoRng for Rng09<R> {}
+
+#[cfg(test)]
+#[cfg(all(feature = "std", feature = "digest011", feature = "rand_core09"))]
+#[cfg(feature = "blsag")]
+mod test {
+    extern crate rand_09;
+    extern crate sha2;
+    extern crate sha2_011;
+
+    use curve25519_dalek::ristretto::RistrettoPoint;
+    use curve25519_dalek::scalar::Scalar;
+    use digest::Digest;
+    use rand_09::rngs::ThreadRng;
+
+    use super::*;
+    use crate::blsag::BLSAG;
+    use crate::prelude::*;
+    use crate::traits::{SignWithRng, VerifyRef};
+
+    #[test]
+    fn compat() {
+        // Both versions of SHA-512 agree
+        let data = b"This is the message";
+        assert_eq!(
+            Digest011::<sha2_011::Sha512>::digest(data).as_slice(),
+            sha2::Sha512::digest(data).as_slice()
+        );
+
+        let mut csprng = rand_core::OsRng;
+        let k: Scalar = Scalar::random(&mut csprng);
+        let ring: Vec<RistrettoPoint> = (0..2).map(|_| RistrettoPoint::random(&mut csprng)).collect();
+        let message: Vec<u8> = data.to_vec();
+        let signature =
+            BLSAG::sign_with_rng_unchecked::<Digest011<sha2_011::Sha512>, _>(
+                k,
+                ring,
+                1,
+                &message,
+                &mut Rng09::<ThreadRng>::default(),
+            )
+            .unwrap();
+        // Signatures interoperate with the older version of the same hash function
+        assert!(signature.verify_ref::<sha2::Sha512>(&message));
+    }
+}
+
+#[cfg(test)]
+#[cfg(feature = "std")]
+#[cfg(feature = "sign")]
+#[cfg(feature = "blsag")]
+mod test_xof {
+    extern crate rand;
+    extern crate sha3;
+
+    use curve25519_dalek::ristretto::RistrettoPoint;
+    use curve25519_dalek::scalar::Scalar;
+    use digest::Digest;
+    use rand::rngs::OsRng;
+    use sha3::{Shake128, Shake256};
+
+    use super::*;
+    use crate::blsag::BLSAG;
+    use crate::prelude::*;
+    use crate::traits::{SignWithRng, VerifyRef};
+
+    #[test]
+    fn xof() {
+        // The output is the first 64 bytes SHAKE256 reads
+        let data = b"This is the message";
+        let mut expected = [0u8; 64];
+        sha3::Shake256::default().chain(data).finalize_xof().read(&mut expected);
+        assert_eq!(Xof::<Shake256>::digest(data).as_slice(), &expected[..]);
+
+        let k: Scalar = Scalar::random(&mut OsRng);
+        let ring: Vec<RistrettoPoint> = (0..2).map(|_| RistrettoPoint::random(&mut OsRng)).collect();
+        let message: Vec<u8> = data.to_vec();
+        let signature = BLSAG::sign_with_rng_unchecked::<Xof<Shake256>, _>(k, ring, 1, &message, &mut OsRng).unwrap();
+        assert!(signature.verify_ref::<Xof<Shake256>>(&message));
+        assert!(!signature.verify_ref::<Xof<Shake128>>(&message));
+    }
+}
@@ -0,0 +1,197 @@
+//! Detached bLSAGs
+//!
+//! Most of a bLSAG over a large ring is the ring, which is often the same for many signatures
+//! and already stored elsewhere, e.g. in a database of anonymity sets. A [`DetachedBLSAG`] leaves
+//! the ring out and refers to it by its digest, as computed by [`BLSAG::compute_ring_digest`].
+//! [`verify_detached`] asks a [`RingResolver`] for the ring with that digest, so applications
+//! plug a database or a network fetcher into verification once, in place of fetching the ring at
+//! every call site. [`MemoryResolver`] keeps the rings in memory.
+//!
+//! The resolver is not trusted: a ring whose digest is not the one signed over does not verify.
+
+use crate::prelude::*;
+
+use alloc::collections::BTreeMap;
+use core::marker::PhantomData;
+
+use curve25519_dalek::ristretto::RistrettoPoint;
+use digest::generic_array::typenum::U64;
+use digest::Digest;
+
+use crate::blsag::BLSAG;
+use crate::codec::{self, Codec, Reader};
+use crate::error::Error;
+use crate::scalars::{Challenge, Response};
+use crate::traits::VerifyRef;
+
+/// A source of rings by their digest
+pub trait RingResolver {
+    /// The ring with `ring_digest`, or [`Error::RingNotFound`]. Resolvers backed by a database
+    /// or network may return other errors of their own
+    fn fetch(&self, ring_digest: &[u8; 64]) -> Result<Vec<RistrettoPoint>, Error>;
+}
+
+/// Rings kept in memory, by their digest under `Hash`
+#[derive(Clone)]
+pub struct MemoryResolver<Hash> {
+    rings: BTreeMap<[u8; 64], Vec<RistrettoPoint>>,
+    _hash: PhantomData<fn() -> Hash>,
+}
+
+impl<Hash: Digest<OutputSize = U64> + Clone + Default> Default for MemoryResolver<Hash> {
+    fn default() -> MemoryResolver<Hash> {
+        return MemoryResolver {
+            rings: BTreeMap::new(),
+            _hash: PhantomData,
+        };
+    }
+}
+
+impl<Hash: Digest<OutputSize = U64> + Clone + Default> MemoryResolver<Hash> {
+    pub fn new() -> MemoryResolver<Hash> {
+        return MemoryResolver::default();
+    }
+
+    /// Keeps `ring`, returning its digest
+    pub fn insert(&mut self, ring: Vec<RistrettoPoint>) -> [u8; 64] {
+        let ring_digest: [u8; 64] = BLSAG::compute_ring_digest::<Hash>(&ring);
+        self.rings.insert(ring_digest, ring);
+        return ring_digest;
+    }
+
+    pub fn remove(&mut self, ring_digest: &[u8; 64]) -> Option<Vec<RistrettoPoint>> {
+        return self.rings.remove(ring_digest);
+    }
+}
+
+impl<Hash> RingResolver for MemoryResolver<Hash> {
+    fn fetch(&self, ring_digest: &[u8; 64]) -> Result<Vec<RistrettoPoint>, Error> {
+        return self.rings.get(ring_digest).cloned().ok_or(Error::RingNotFound);
+    }
+}
+
+/// A bLSAG without its ring, which it refers to by digest
+#[derive(Clone)]
+pub struct DetachedBLSAG {
+    pub challenge: Challenge,
+    pub responses: Vec<Response>,
+    pub key_image: RistrettoPoint,
+    pub ring_digest: [u8; 64],
+}
+
+impl DetachedBLSAG {
+    /// Leaves the ring of `signature` out, referring to it by its digest under `Hash`
+    pub fn detach<Hash: Digest<OutputSize = U64> + Clone + Default>(signature: &BLSAG) -> DetachedBLSAG {
+        return DetachedBLSAG {
+            challenge: signature.challenge,
+            responses: signature.responses.clone(),
+            key_image: signature.key_image,
+            ring_digest: BLSAG::compute_ring_digest::<Hash>(&signature.ring),
+        };
+    }
+
+    /// The signature with `ring` put back. Its ring digest is attached, so it verifies only if
+    /// `ring` is the ring signed over
+    pub fn attach(self, ring: Vec<RistrettoPoint>) -> BLSAG {
+        let signature = BLSAG::from_parts_unchecked(self.challenge, self.responses, ring, self.key_image);
+        return signature.with_ring_digest(self.ring_digest);
+    }
+}
+
+impl Codec for DetachedBLSAG {
+    /// The bLSAG encoding without the ring, with the ring digest in its place
+    fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes: Vec<u8> = Vec::new();
+        codec::write_challenge(&mut bytes, &self.challenge);
+        codec::write_u32(&mut bytes, self.responses.len());
+        for response in &self.responses {
+            codec::write_response(&mut bytes, response);
+        }
+        codec::write_point(&mut bytes, &self.key_image);
+        bytes.extend_from_slice(&self.ring_digest);
+        return bytes;
+    }
+
+    fn decode(bytes: &[u8]) -> Result<DetachedBLSAG, Error> {
+        let mut reader = Reader::new(bytes);
+        let challenge = reader.read_challenge()?;
+        let n = reader.read_len(32)?;
+        let responses = reader.read_responses(n)?;
+        let key_image = reader.read_point()?;
+        let mut ring_digest = [0u8; 64];
+        ring_digest.copy_from_slice(reader.read_bytes(64)?);
+        reader.finish()?;
+        return Ok(DetachedBLSAG {
+            challenge: challenge,
+            responses: responses,
+            key_image: key_image,
+            ring_digest: ring_digest,
+        });
+    }
+}
+
+/// Verifies `signature` over the ring `resolver` gives for its digest
+///
+/// Returns the error of the resolver if it has no such ring. A ring that does not match the
+/// digest, or does not have one member per response, gives `Ok(false)`.
+pub fn verify_detached<Hash: Digest<OutputSize = U64> + Clone + Default, R: RingResolver + ?Sized>(
+    signature: &DetachedBLSAG,
+    message: &Vec<u8>,
+    resolver: &R,
+) -> Result<bool, Error> {
+    let ring: Vec<RistrettoPoint> = resolver.fetch(&signature.ring_digest)?;
+    return Ok(signature.clone().attach(ring).verify_ref::<Hash>(message));
+}
+
+#[cfg(test)]
+#[cfg(feature = "std")]
+#[cfg(feature = "sign")]
+mod test {
+    extern crate rand;
+    extern crate sha2;
+
+    use curve25519_dalek::scalar::Scalar;
+    use rand::rngs::OsRng;
+    use sha2::Sha512;
+
+    use super::*;
+    use crate::traits::SignWithRng;
+
+    /// A resolver that answers with the wrong ring
+    struct Lying(Vec<RistrettoPoint>);
+
+    impl RingResolver for Lying {
+        fn fetch(&self, _ring_digest: &[u8; 64]) -> Result<Vec<RistrettoPoint>, Error> {
+            return Ok(self.0.clone());
+        }
+    }
+
+    #[test]
+    fn verify_detached() {
+        let k: Scalar = Scalar::random(&mut OsRng);
+        let ring: Vec<RistrettoPoint> = (0..4).map(|_| RistrettoPoint::random(&mut OsRng)).collect();
+        let message: Vec<u8> = b"This is the message".to_vec();
+        let signature = BLSAG::sign_with_rng_unchecked::<Sha512, _>(k, ring, 2, &message, &mut OsRng).unwrap();
+
+        let mut resolver = MemoryResolver::<Sha512>::new();
+        let detached = DetachedBLSAG::detach::<Sha512>(&signature);
+        assert_eq!(
+            super::verify_detached::<Sha512, _>(&detached, &message, &resolver).err(),
+            Some(Error::RingNotFound)
+        );
+        assert_eq!(resolver.insert(signature.ring.clone()), detached.ring_digest);
+
+        let decoded = DetachedBLSAG::decode(&detached.to_bytes()).unwrap();
+        assert_eq!(detached.to_bytes().len(), signature.to_bytes().len() - 5 * 32 + 64);
+        assert_eq!(super::verify_detached::<Sha512, _>(&decoded, &message, &resolver), Ok(true));
+        assert_eq!(
+            super::verify_detached::<Sha512, _>(&decoded, &b"Another message".to_vec(), &resolver),
+            Ok(false)
+        );
+
+        let mut other_ring = signature.ring.clone();
+        other_ring[0] = RistrettoPoint::random(&mut OsRng);
+        assert_eq!(super::verify_detached::<Sha512, _>(&decoded, &message, &Lying(other_ring)), Ok(false));
+        assert!(resolver.remove(&detached.ring_digest).is_some());
+    }
+}
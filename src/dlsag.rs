@@ -1,14 +1,30 @@
 use crate::prelude::*;
+#[cfg(feature = "allocator-api")]
+use crate::arena::{Allocator, VerifyIn};
+
+use core::ops::Range;
 
 use curve25519_dalek::constants;
 use curve25519_dalek::ristretto::RistrettoPoint;
 use curve25519_dalek::scalar::Scalar;
-use curve25519_dalek::traits::MultiscalarMul;
 use digest::Digest;
 use digest::generic_array::typenum::U64;
+#[cfg(feature = "sign")]
 use rand_core::{CryptoRng, RngCore};
 
-use crate::traits::{KeyImageGen, Link, Sign, Verify};
+use crate::error::Error;
+use crate::generator::{Basepoint, Generator, VerifyWithGenerator};
+#[cfg(feature = "sign")]
+use crate::generator::SignWithGenerator;
+use crate::scalars::{Challenge, Response};
+#[cfg(feature = "sign")]
+use crate::scalars;
+use crate::traits::{check_responses, ChallengeChain, KeyImageGenRef, LinkRef, TryVerify, VerifyRef, Weight};
+#[cfg(feature = "sign")]
+use crate::traits::{check_secret_index, take_signer, Scheme, SignWithFullRing, SignWithRng, TrySign};
+use crate::weight;
+use crate::codec::{self, Codec, Reader};
+use crate::ops;
 
 /// Dual Linkable Spontaneous Anonymous Group Signature for Ad Hoc Groups
 ///
@@ -23,48 +39,92 @@ use crate::traits::{KeyImageGen, Link, Sign, Verify};
 /// examples on how to use it
 #[derive(Clone)]
 pub struct DLSAG {
-    pub challenge: Scalar,
-    pub responses: Vec<Scalar>,
+    pub challenge: Challenge,
+    pub responses: Vec<Response>,
     pub ring: Vec<(RistrettoPoint, RistrettoPoint, Scalar)>,
     pub key_image: RistrettoPoint,
     pub b: bool,
 }
 
-impl KeyImageGen<(Scalar, RistrettoPoint, Scalar), RistrettoPoint> for DLSAG {
+/// The private key of either side of a channel, see the two implementations of [`SignWithRng`]
+#[derive(Clone, Copy, Debug)]
+pub enum DLSAGKey {
+    /// Your private key, the public key of the other end of the channel and the bitstring
+    Left(Scalar, RistrettoPoint, Scalar),
+    /// The public key of the other end of the channel, your private key and the bitstring
+    Right(RistrettoPoint, Scalar, Scalar),
+}
+
+/// Hash state shared by all challenges `H_n(m, ....)`
+///
+/// # Advanced
+///
+/// This and [`compute_challenge_step`] are the transcript math used by `sign` and `verify`,
+/// exposed for protocols that compute DLSAG challenges over data produced elsewhere. Most users
+/// should only ever need `sign` and `verify`.
+pub fn compute_challenge_prefix<Hash: Digest<OutputSize=U64> + Clone + Default>(
+    message: &[u8],
+) -> Hash {
+    let mut message_hash = Hash::default();
+    message_hash.update(message);
+    return message_hash;
+}
+
+/// The challenge `c_(i+1) = H_n(m, point_a, point_b)` where `point_a = r_i G + c_i K_i` and
+/// `point_b = r_i b_i H_p(K'_i) + c_i K~` for the signer's side `K_i` and other side `K'_i` of
+/// the channel, given the `hash_state` from [`compute_challenge_prefix`]
+///
+/// # Advanced
+///
+/// See [`compute_challenge_prefix`]
+pub fn compute_challenge_step<Hash: Digest<OutputSize=U64>>(
+    mut hash_state: Hash,
+    point_a: &RistrettoPoint,
+    point_b: &RistrettoPoint,
+) -> Scalar {
+    hash_state.update(point_a.compress().as_bytes());
+    hash_state.update(point_b.compress().as_bytes());
+    return Scalar::from_hash(hash_state);
+}
+
+impl KeyImageGenRef<(Scalar, RistrettoPoint, Scalar), RistrettoPoint> for DLSAG {
     /// Some signature schemes require the key images to be signed as well.
     /// Use this method to generate them
-    fn generate_key_image<Hash: Digest<OutputSize=U64> + Clone + Default>(
-        k: (Scalar, RistrettoPoint, Scalar),
+    fn generate_key_image_ref<Hash: Digest<OutputSize=U64> + Clone + Default>(
+        k: &(Scalar, RistrettoPoint, Scalar),
     ) -> RistrettoPoint {
         let k_point: (RistrettoPoint, RistrettoPoint, Scalar) =
-            (k.0 * constants::RISTRETTO_BASEPOINT_POINT, k.1, k.2);
+            (ops::mul_base(&k.0, &constants::RISTRETTO_BASEPOINT_POINT), k.1, k.2);
 
-        let key_image: RistrettoPoint = k.2
-            * k.0
-            * RistrettoPoint::from_hash(Hash::default().chain_update(k_point.1.compress().as_bytes()));
+        let key_image: RistrettoPoint = ops::mul(
+            &(k.2 * k.0),
+            &ops::point_from_hash(Hash::default().chain_update(k_point.1.compress().as_bytes())),
+        );
 
         return key_image;
     }
 }
 
-impl KeyImageGen<(RistrettoPoint, Scalar, Scalar), RistrettoPoint> for DLSAG {
+impl KeyImageGenRef<(RistrettoPoint, Scalar, Scalar), RistrettoPoint> for DLSAG {
     /// Some signature schemes require the key images to be signed as well.
     /// Use this method to generate them
-    fn generate_key_image<Hash: Digest<OutputSize=U64> + Clone + Default>(
-        k: (RistrettoPoint, Scalar, Scalar),
+    fn generate_key_image_ref<Hash: Digest<OutputSize=U64> + Clone + Default>(
+        k: &(RistrettoPoint, Scalar, Scalar),
     ) -> RistrettoPoint {
         let k_point: (RistrettoPoint, RistrettoPoint, Scalar) =
-            (k.0, k.1 * constants::RISTRETTO_BASEPOINT_POINT, k.2);
+            (k.0, ops::mul_base(&k.1, &constants::RISTRETTO_BASEPOINT_POINT), k.2);
 
-        let key_image: RistrettoPoint = k.2
-            * k.1
-            * RistrettoPoint::from_hash(Hash::default().chain_update(k_point.0.compress().as_bytes()));
+        let key_image: RistrettoPoint = ops::mul(
+            &(k.2 * k.1),
+            &ops::point_from_hash(Hash::default().chain_update(k_point.0.compress().as_bytes())),
+        );
 
         return key_image;
     }
 }
 
-impl Sign<(Scalar, RistrettoPoint, Scalar), Vec<(RistrettoPoint, RistrettoPoint, Scalar)>>
+#[cfg(feature = "sign")]
+impl SignWithRng<(Scalar, RistrettoPoint, Scalar), Vec<(RistrettoPoint, RistrettoPoint, Scalar)>>
 for DLSAG
 {
     /// To sign you need `k` your private key, and `ring` which is the public keys of everyone
@@ -77,85 +137,89 @@ for DLSAG
     /// The ring contains public key pairs from the blockchain together with their random
     /// bitstrings as mentioned above.
     ///
-    /// This implementation of `sign(...)` is for the user who has the private key for the left
+    /// This implementation of `sign_with_rng(...)` is for the user who has the private key for the left
     /// side of the channel
-    fn sign<
+    fn sign_with_rng_unchecked<
         Hash: Digest<OutputSize=U64> + Clone + Default,
-        CSPRNG: CryptoRng + RngCore + Default,
+        CSPRNG: CryptoRng + RngCore,
+    >(
+        k: (Scalar, RistrettoPoint, Scalar),
+        ring: Vec<(RistrettoPoint, RistrettoPoint, Scalar)>,
+        secret_index: usize,
+        message: &Vec<u8>,
+        csprng: &mut CSPRNG,
+    ) -> Result<DLSAG, Error> {
+        return DLSAG::sign_with_generator_and_rng::<Hash, CSPRNG, Basepoint>(k, ring, secret_index, message, csprng);
+    }
+}
+
+#[cfg(feature = "sign")]
+impl SignWithGenerator<(Scalar, RistrettoPoint, Scalar), Vec<(RistrettoPoint, RistrettoPoint, Scalar)>>
+for DLSAG
+{
+    fn sign_with_generator_and_rng<
+        Hash: Digest<OutputSize=U64> + Clone + Default,
+        CSPRNG: CryptoRng + RngCore,
+        G: Generator,
     >(
         k: (Scalar, RistrettoPoint, Scalar),
         mut ring: Vec<(RistrettoPoint, RistrettoPoint, Scalar)>,
         secret_index: usize,
         message: &Vec<u8>,
-    ) -> DLSAG {
-        let mut csprng = CSPRNG::default();
+        csprng: &mut CSPRNG,
+    ) -> Result<DLSAG, Error> {
+        check_secret_index(ring.len(), secret_index)?;
+        let g: RistrettoPoint = G::generator();
 
         // Provers public key
         let k_point: (RistrettoPoint, RistrettoPoint, Scalar) =
-            (k.0 * constants::RISTRETTO_BASEPOINT_POINT, k.1, k.2);
+            (ops::mul_base(&k.0, &g), k.1, k.2);
 
-        let key_image: RistrettoPoint = DLSAG::generate_key_image::<Hash>(k);
+        let key_image: RistrettoPoint = DLSAG::generate_key_image_ref::<Hash>(&k);
 
         // Ring size (at least 4 but maximum 32)
         let n = ring.len() + 1;
 
         ring.insert(secret_index, k_point);
 
-        let a: Scalar = Scalar::random(&mut csprng);
+        let a: Scalar = Scalar::random(csprng);
 
-        let mut rs: Vec<Scalar> = (0..n).map(|_| Scalar::random(&mut csprng)).collect();
+        let mut rs: Vec<Scalar> = (0..n).map(|_| Scalar::random(csprng)).collect();
 
         let mut cs: Vec<Scalar> = (0..n).map(|_| Scalar::ZERO).collect();
 
         // Hash of message is shared by all challenges H_n(m, ....)
-        let mut message_hash = Hash::default();
+        let message_hash: Hash = compute_challenge_prefix::<Hash>(message);
 
-        message_hash.update(message);
-
-        let mut hashes: Vec<Hash> = (0..n).map(|_| message_hash.clone()).collect();
-
-        hashes[(secret_index + 1) % n].update(
-            (a * constants::RISTRETTO_BASEPOINT_POINT)
-                .compress()
-                .as_bytes(),
-        );
-        hashes[(secret_index + 1) % n].update(
-            (a * ring[secret_index].2
-                * RistrettoPoint::from_hash(
+        cs[(secret_index + 1) % n] = compute_challenge_step(
+            message_hash.clone(),
+            &ops::mul_base(&a, &g),
+            &ops::mul(&(a * ring[secret_index].2), &ops::point_from_hash(
                 Hash::default().chain_update(k_point.1.compress().as_bytes()),
-            ))
-                .compress()
-                .as_bytes(),
+            )),
         );
-        cs[(secret_index + 1) % n] = Scalar::from_hash(hashes[(secret_index + 1) % n].clone());
 
         let mut i = (secret_index + 1) % n;
 
         loop {
-            hashes[(i + 1) % n].update(
-                RistrettoPoint::multiscalar_mul(
+            cs[(i + 1) % n] = compute_challenge_step(
+                message_hash.clone(),
+                &ops::multiscalar_mul(
                     &[rs[i % n], cs[i % n]],
-                    &[constants::RISTRETTO_BASEPOINT_POINT, ring[i % n].0],
-                )
-                    .compress()
-                    .as_bytes(),
-            );
-            hashes[(i + 1) % n].update(
-                RistrettoPoint::multiscalar_mul(
+                    &[g, ring[i % n].0],
+                ),
+                &ops::multiscalar_mul(
                     &[rs[i % n], cs[i % n]],
                     &[
-                        ring[i % n].2 * RistrettoPoint::from_hash(
+                        ops::mul(&ring[i % n].2, &ops::point_from_hash(
                             Hash::default().chain_update(
                                 ring[i % n].1.compress().as_bytes()
                             )
-                        ),
+                        )),
                         key_image
                     ],
-                )
-                    .compress()
-                    .as_bytes(),
+                ),
             );
-            cs[(i + 1) % n] = Scalar::from_hash(hashes[(i + 1) % n].clone());
 
             if secret_index >= 1 && i % n == (secret_index - 1) % n {
                 break;
@@ -168,17 +232,18 @@ for DLSAG
 
         rs[secret_index] = a - (cs[secret_index] * k.0);
 
-        return DLSAG {
-            challenge: cs[0],
-            responses: rs,
+        return Ok(DLSAG {
+            challenge: Challenge(cs[0]),
+            responses: scalars::responses(rs),
             ring: ring,
             key_image: key_image,
             b: false,
-        };
+        });
     }
 }
 
-impl Sign<(RistrettoPoint, Scalar, Scalar), Vec<(RistrettoPoint, RistrettoPoint, Scalar)>>
+#[cfg(feature = "sign")]
+impl SignWithRng<(RistrettoPoint, Scalar, Scalar), Vec<(RistrettoPoint, RistrettoPoint, Scalar)>>
 for DLSAG
 {
     /// To sign you need `k` your private key, and `ring` which is the public keys of everyone
@@ -191,88 +256,89 @@ for DLSAG
     /// The ring contains public key pairs from the blockchain together with their random
     /// bitstrings as mentioned above.
     ///
-    /// This implementation of `sign(...)` is for the user who has the private key for the right
+    /// This implementation of `sign_with_rng(...)` is for the user who has the private key for the right
     /// side of the channel
-    fn sign<
+    fn sign_with_rng_unchecked<
         Hash: Digest<OutputSize=U64> + Clone + Default,
-        CSPRNG: CryptoRng + RngCore + Default,
+        CSPRNG: CryptoRng + RngCore,
+    >(
+        k: (RistrettoPoint, Scalar, Scalar),
+        ring: Vec<(RistrettoPoint, RistrettoPoint, Scalar)>,
+        secret_index: usize,
+        message: &Vec<u8>,
+        csprng: &mut CSPRNG,
+    ) -> Result<DLSAG, Error> {
+        return DLSAG::sign_with_generator_and_rng::<Hash, CSPRNG, Basepoint>(k, ring, secret_index, message, csprng);
+    }
+}
+
+#[cfg(feature = "sign")]
+impl SignWithGenerator<(RistrettoPoint, Scalar, Scalar), Vec<(RistrettoPoint, RistrettoPoint, Scalar)>>
+for DLSAG
+{
+    fn sign_with_generator_and_rng<
+        Hash: Digest<OutputSize=U64> + Clone + Default,
+        CSPRNG: CryptoRng + RngCore,
+        G: Generator,
     >(
         k: (RistrettoPoint, Scalar, Scalar),
         mut ring: Vec<(RistrettoPoint, RistrettoPoint, Scalar)>,
         secret_index: usize,
         message: &Vec<u8>,
-    ) -> DLSAG {
-        let mut csprng = CSPRNG::default();
+        csprng: &mut CSPRNG,
+    ) -> Result<DLSAG, Error> {
+        check_secret_index(ring.len(), secret_index)?;
+        let g: RistrettoPoint = G::generator();
 
         // Provers public key
         let k_point: (RistrettoPoint, RistrettoPoint, Scalar) =
-            (k.0, k.1 * constants::RISTRETTO_BASEPOINT_POINT, k.2);
+            (k.0, ops::mul_base(&k.1, &g), k.2);
 
-        let key_image: RistrettoPoint = DLSAG::generate_key_image::<Hash>(k);
+        let key_image: RistrettoPoint = DLSAG::generate_key_image_ref::<Hash>(&k);
 
         // Ring size (at least 4 but maximum 32)
         let n = ring.len() + 1;
 
         ring.insert(secret_index, k_point);
 
-        let a: Scalar = Scalar::random(&mut csprng);
+        let a: Scalar = Scalar::random(csprng);
 
-        let mut rs: Vec<Scalar> = (0..n).map(|_| Scalar::random(&mut csprng)).collect();
+        let mut rs: Vec<Scalar> = (0..n).map(|_| Scalar::random(csprng)).collect();
 
         let mut cs: Vec<Scalar> = (0..n).map(|_| Scalar::ZERO).collect();
 
         // Hash of message is shared by all challenges H_n(m, ....)
-        let mut message_hash = Hash::default();
+        let message_hash: Hash = compute_challenge_prefix::<Hash>(message);
 
-        message_hash.update(message);
-
-        let mut hashes: Vec<Hash> = (0..n).map(|_| message_hash.clone()).collect();
-
-        hashes[(secret_index + 1) % n].update(
-            (a * constants::RISTRETTO_BASEPOINT_POINT)
-                .compress()
-                .as_bytes(),
-        );
-        hashes[(secret_index + 1) % n].update(
-            (a * ring[secret_index].2
-                * RistrettoPoint::from_hash(
+        cs[(secret_index + 1) % n] = compute_challenge_step(
+            message_hash.clone(),
+            &ops::mul_base(&a, &g),
+            &ops::mul(&(a * ring[secret_index].2), &ops::point_from_hash(
                 Hash::default().chain_update(k_point.0.compress().as_bytes()),
-            ))
-                .compress()
-                .as_bytes(),
+            )),
         );
-        cs[(secret_index + 1) % n] = Scalar::from_hash(hashes[(secret_index + 1) % n].clone());
 
         let mut i = (secret_index + 1) % n;
 
         loop {
-            hashes[(i + 1) % n].update(
-                RistrettoPoint::multiscalar_mul(
+            cs[(i + 1) % n] = compute_challenge_step(
+                message_hash.clone(),
+                &ops::multiscalar_mul(
                     &[rs[i % n], cs[i % n]],
-                    &[
-                        constants::RISTRETTO_BASEPOINT_POINT,
-                        ring[i % n].1
-                    ],
-                )
-                    .compress()
-                    .as_bytes(),
-            );
-            hashes[(i + 1) % n].update(
-                RistrettoPoint::multiscalar_mul(
+                    &[g, ring[i % n].1],
+                ),
+                &ops::multiscalar_mul(
                     &[rs[i % n], cs[i % n]],
                     &[
-                        ring[i % n].2 * RistrettoPoint::from_hash(
+                        ops::mul(&ring[i % n].2, &ops::point_from_hash(
                             Hash::default().chain_update(
                                 ring[i % n].0.compress().as_bytes()
-                            ),
-                        ),
+                            )
+                        )),
                         key_image
                     ],
-                )
-                    .compress()
-                    .as_bytes(),
+                ),
             );
-            cs[(i + 1) % n] = Scalar::from_hash(hashes[(i + 1) % n].clone());
 
             if secret_index >= 1 && i % n == (secret_index - 1) % n {
                 break;
@@ -285,99 +351,324 @@ for DLSAG
 
         rs[secret_index] = a - (cs[secret_index] * k.1);
 
-        return DLSAG {
-            challenge: cs[0],
-            responses: rs,
+        return Ok(DLSAG {
+            challenge: Challenge(cs[0]),
+            responses: scalars::responses(rs),
             ring: ring,
             key_image: key_image,
             b: true,
-        };
+        });
+    }
+}
+
+impl DLSAG {
+    /// As [`ChallengeChain::reconstruct_challenge`], given the hash state with the message already
+    /// absorbed, see [`crate::session`]
+    pub(crate) fn reconstruct_challenge_from_prefix<Hash: Digest<OutputSize = U64> + Clone + Default>(
+        &self,
+        message_hash: Hash,
+        slots: Range<usize>,
+        challenge: Scalar,
+    ) -> Scalar {
+        return self.reconstruct_challenge_with_generator::<Hash, Basepoint>(message_hash, slots, challenge);
+    }
+
+    /// As [`DLSAG::reconstruct_challenge_from_prefix`] with the generator `G`
+    fn reconstruct_challenge_with_generator<Hash: Digest<OutputSize = U64> + Clone + Default, G: Generator>(
+        &self,
+        message_hash: Hash,
+        slots: Range<usize>,
+        challenge: Scalar,
+    ) -> Scalar {
+        let g: RistrettoPoint = G::generator();
+        let mut reconstructed_c: Scalar = challenge;
+        for j in slots {
+            // The signer's side of the channel, and the other side which is hashed to a point
+            let (own, other) = if self.b {
+                (self.ring[j].1, self.ring[j].0)
+            } else {
+                (self.ring[j].0, self.ring[j].1)
+            };
+            reconstructed_c = compute_challenge_step(
+                message_hash.clone(),
+                &ops::multiscalar_mul(
+                    &[self.responses[j].0, reconstructed_c],
+                    &[g, own],
+                ),
+                &ops::multiscalar_mul(
+                    &[self.responses[j].0, reconstructed_c],
+                    &[
+                        ops::mul(&self.ring[j].2, &ops::point_from_hash(
+                            Hash::default().chain_update(other.compress().as_bytes())
+                        )),
+                        self.key_image
+                    ]
+                ),
+            );
+        }
+
+        return reconstructed_c;
+    }
+}
+
+impl ChallengeChain for DLSAG {
+    fn ring_size(&self) -> usize {
+        return self.ring.len();
+    }
+
+    fn challenge(&self) -> Scalar {
+        return self.challenge.0;
+    }
+
+    /// Starting from `challenge` entering the first slot in `slots`, recomputes the challenge
+    /// leaving the last one
+    fn reconstruct_challenge<Hash: Digest<OutputSize = U64> + Clone + Default>(
+        &self,
+        message: &Vec<u8>,
+        slots: Range<usize>,
+        challenge: Scalar,
+    ) -> Option<Scalar> {
+        // A malformed signature has no chain to run
+        if self.check_shape().is_err() || slots.end > self.ring.len() {
+            return None;
+        }
+        let message_hash: Hash = compute_challenge_prefix::<Hash>(message);
+        return Some(self.reconstruct_challenge_from_prefix(message_hash, slots, challenge));
     }
 }
 
-impl Verify for DLSAG {
+impl VerifyRef for DLSAG {
     /// To verify a `signature` you need the `message` too
-    fn verify<Hash: Digest<OutputSize=U64> + Clone + Default>(
-        signature: DLSAG,
+    fn verify_ref<Hash: Digest<OutputSize = U64> + Clone + Default>(&self, message: &Vec<u8>) -> bool {
+        return self.verify_with_generator::<Hash, Basepoint>(message);
+    }
+}
+
+#[cfg(feature = "allocator-api")]
+impl VerifyIn for DLSAG {
+    /// Verifying allocates nothing, so `alloc` goes unused
+    fn verify_in<Hash: Digest<OutputSize = U64> + Clone + Default, A: Allocator>(
+        &self,
         message: &Vec<u8>,
+        _alloc: A,
     ) -> bool {
-        let mut reconstructed_c: Scalar = signature.challenge;
-        let n = signature.ring.len();
-        for j in 0..n {
-            let mut h: Hash = Hash::default();
-            h.update(message);
-            if signature.b {
-                h.update(
-                    RistrettoPoint::multiscalar_mul(
-                        &[signature.responses[j], reconstructed_c],
-                        &[
-                            constants::RISTRETTO_BASEPOINT_POINT,
-                            signature.ring[j].1
-                        ],
-                    )
-                        .compress()
-                        .as_bytes(),
-                );
-
-                h.update(
-                    RistrettoPoint::multiscalar_mul(
-                        &[signature.responses[j], reconstructed_c],
-                        &[
-                            signature.ring[j].2 * RistrettoPoint::from_hash(
-                            Hash::default().chain_update(
-                                    signature.ring[j].0.compress().as_bytes()
-                                ),
-                            ),
-                            signature.key_image
-                        ]
-                    )
-                        .compress()
-                        .as_bytes(),
-                );
-            } else {
-                h.update(
-                    RistrettoPoint::multiscalar_mul(
-                        &[signature.responses[j], reconstructed_c],
-                        &[
-                            constants::RISTRETTO_BASEPOINT_POINT,
-                            signature.ring[j].0
-                        ]
-                    )
-                        .compress()
-                        .as_bytes(),
-                );
-
-                h.update(
-                    RistrettoPoint::multiscalar_mul(
-                        &[signature.responses[j], reconstructed_c],
-                        &[
-                            signature.ring[j].2 * RistrettoPoint::from_hash(
-                                Hash::default().chain_update(
-                                    signature.ring[j].1.compress().as_bytes()
-                                )
-                            ),
-                            signature.key_image
-                        ])
-                        .compress()
-                        .as_bytes(),
-                );
-            }
-            reconstructed_c = Scalar::from_hash(h);
+        return self.verify_ref::<Hash>(message);
+    }
+}
+
+impl VerifyWithGenerator for DLSAG {
+    fn verify_with_generator<Hash: Digest<OutputSize = U64> + Clone + Default, G: Generator>(
+        &self,
+        message: &Vec<u8>,
+    ) -> bool {
+        // Signatures built by hand may not have one response per member
+        if self.check_shape().is_err() {
+            return false;
         }
+        let n = self.ring.len();
+        let message_hash: Hash = compute_challenge_prefix::<Hash>(message);
+        let reconstructed_c: Scalar =
+            self.reconstruct_challenge_with_generator::<Hash, G>(message_hash, 0..n, self.challenge.0);
+        return self.challenge.0 == reconstructed_c;
+    }
+}
 
-        return signature.challenge == reconstructed_c;
+#[cfg(feature = "sign")]
+impl TrySign<(Scalar, RistrettoPoint, Scalar), Vec<(RistrettoPoint, RistrettoPoint, Scalar)>> for DLSAG {
+    fn check_sign_input(
+        _k: &(Scalar, RistrettoPoint, Scalar),
+        ring: &Vec<(RistrettoPoint, RistrettoPoint, Scalar)>,
+        secret_index: usize,
+    ) -> Result<(), Error> {
+        return check_secret_index(ring.len(), secret_index);
     }
 }
 
-impl Link for DLSAG {
+#[cfg(feature = "sign")]
+impl TrySign<(RistrettoPoint, Scalar, Scalar), Vec<(RistrettoPoint, RistrettoPoint, Scalar)>> for DLSAG {
+    fn check_sign_input(
+        _k: &(RistrettoPoint, Scalar, Scalar),
+        ring: &Vec<(RistrettoPoint, RistrettoPoint, Scalar)>,
+        secret_index: usize,
+    ) -> Result<(), Error> {
+        return check_secret_index(ring.len(), secret_index);
+    }
+}
+
+#[cfg(feature = "sign")]
+impl SignWithFullRing<(Scalar, RistrettoPoint, Scalar), Vec<(RistrettoPoint, RistrettoPoint, Scalar)>> for DLSAG {
+    fn take_signer(
+        k: &(Scalar, RistrettoPoint, Scalar),
+        ring: &mut Vec<(RistrettoPoint, RistrettoPoint, Scalar)>,
+        secret_index: usize,
+    ) -> Result<(), Error> {
+        return take_signer(ring, secret_index, &(k.0 * constants::RISTRETTO_BASEPOINT_POINT, k.1, k.2));
+    }
+}
+
+#[cfg(feature = "sign")]
+impl SignWithFullRing<(RistrettoPoint, Scalar, Scalar), Vec<(RistrettoPoint, RistrettoPoint, Scalar)>> for DLSAG {
+    fn take_signer(
+        k: &(RistrettoPoint, Scalar, Scalar),
+        ring: &mut Vec<(RistrettoPoint, RistrettoPoint, Scalar)>,
+        secret_index: usize,
+    ) -> Result<(), Error> {
+        return take_signer(ring, secret_index, &(k.0, k.1 * constants::RISTRETTO_BASEPOINT_POINT, k.2));
+    }
+}
+
+#[cfg(feature = "sign")]
+impl Scheme for DLSAG {
+    type SecretKey = DLSAGKey;
+    type Ring = Vec<(RistrettoPoint, RistrettoPoint, Scalar)>;
+
+    fn sign_with_key<Hash: Digest<OutputSize = U64> + Clone + Default, CSPRNG: CryptoRng + RngCore + Default>(
+        k: DLSAGKey,
+        ring: Vec<(RistrettoPoint, RistrettoPoint, Scalar)>,
+        secret_index: usize,
+        message: &Vec<u8>,
+    ) -> Result<DLSAG, Error> {
+        let mut csprng = CSPRNG::default();
+        return match k {
+            DLSAGKey::Left(a, b, c) => DLSAG::sign_with_rng::<Hash, CSPRNG>(
+                (a, b, c),
+                ring,
+                secret_index,
+                message,
+                &mut csprng,
+            ),
+            DLSAGKey::Right(a, b, c) => DLSAG::sign_with_rng::<Hash, CSPRNG>(
+                (a, b, c),
+                ring,
+                secret_index,
+                message,
+                &mut csprng,
+            ),
+        };
+    }
+
+    fn try_sign_with_key_and_rng<Hash: Digest<OutputSize = U64> + Clone + Default, CSPRNG: CryptoRng + RngCore>(
+        k: DLSAGKey,
+        ring: Vec<(RistrettoPoint, RistrettoPoint, Scalar)>,
+        secret_index: usize,
+        message: &Vec<u8>,
+        csprng: &mut CSPRNG,
+    ) -> Result<DLSAG, Error> {
+        return match k {
+            DLSAGKey::Left(a, b, c) => {
+                DLSAG::sign_with_rng_unchecked::<Hash, CSPRNG>((a, b, c), ring, secret_index, message, csprng)
+            }
+            DLSAGKey::Right(a, b, c) => {
+                DLSAG::sign_with_rng_unchecked::<Hash, CSPRNG>((a, b, c), ring, secret_index, message, csprng)
+            }
+        };
+    }
+}
+
+impl TryVerify for DLSAG {
+    fn check_shape(&self) -> Result<(), Error> {
+        return check_responses(self.ring.len(), self.responses.len());
+    }
+}
+
+impl LinkRef for DLSAG {
     /// This is for linking two signatures and checking if they are signed by the same person
-    fn link(signature_1: DLSAG, signature_2: DLSAG) -> bool {
-        return signature_1.key_image == signature_2.key_image;
+    fn link_ref(&self, other: &DLSAG) -> bool {
+        return self.key_image == other.key_image;
+    }
+}
+
+impl Weight for DLSAG {
+    /// Approximate cost of verifying this signature, see [`crate::weight`]
+    fn weight(&self) -> u64 {
+        return weight::dlsag(self.ring.len());
+    }
+}
+
+impl DLSAG {
+    /// Assembles a signature from its fields, returning `None` if the ring and responses do
+    /// not agree on the ring size
+    pub fn from_parts(
+        challenge: Challenge,
+        responses: Vec<Response>,
+        ring: Vec<(RistrettoPoint, RistrettoPoint, Scalar)>,
+        key_image: RistrettoPoint,
+        b: bool,
+    ) -> Option<DLSAG> {
+        if ring.is_empty() || responses.len() != ring.len() {
+            return None;
+        }
+        return Some(DLSAG::from_parts_unchecked(challenge, responses, ring, key_image, b));
+    }
+
+    /// Assembles a signature from its fields without checking them
+    ///
+    /// This is for fields that have been checked before, e.g. by [`DLSAG::from_parts`] or by
+    /// decoding, and are read back from storage that is trusted. A signature assembled from
+    /// fields that do not agree is rejected: verifying it returns false.
+    pub fn from_parts_unchecked(
+        challenge: Challenge,
+        responses: Vec<Response>,
+        ring: Vec<(RistrettoPoint, RistrettoPoint, Scalar)>,
+        key_image: RistrettoPoint,
+        b: bool,
+    ) -> DLSAG {
+        return DLSAG {
+            challenge: challenge,
+            responses: responses,
+            ring: ring,
+            key_image: key_image,
+            b: b,
+        };
+    }
+}
+
+impl Codec for DLSAG {
+    /// Encodes the signature canonically, see [`crate::codec`]
+    fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes: Vec<u8> = Vec::new();
+        codec::write_challenge(&mut bytes, &self.challenge);
+        codec::write_u32(&mut bytes, self.ring.len());
+        for response in &self.responses {
+            codec::write_response(&mut bytes, response);
+        }
+        for member in &self.ring {
+            codec::write_point(&mut bytes, &member.0);
+            codec::write_point(&mut bytes, &member.1);
+            codec::write_scalar(&mut bytes, &member.2);
+        }
+        codec::write_point(&mut bytes, &self.key_image);
+        codec::write_bool(&mut bytes, self.b);
+        return bytes;
+    }
+
+    /// Decodes a signature, returning the [`Error`] describing why if `bytes` is not a canonical encoding
+    fn decode(bytes: &[u8]) -> Result<DLSAG, Error> {
+        let mut reader = Reader::new(bytes);
+        let challenge = reader.read_challenge()?;
+        let n = reader.read_len(128)?;
+        let responses = reader.read_responses(n)?;
+        let ring = (0..n)
+            .map(|_| Ok((reader.read_point()?, reader.read_point()?, reader.read_scalar()?)))
+            .collect::<Result<Vec<(RistrettoPoint, RistrettoPoint, Scalar)>, Error>>()?;
+        let key_image = reader.read_point()?;
+        let b = reader.read_bool()?;
+        reader.finish()?;
+        return Ok(DLSAG {
+            challenge: challenge,
+            responses: responses,
+            ring: ring,
+            key_image: key_image,
+            b: b,
+        });
     }
 }
 
+crate::encoding::display_via_codec!(DLSAG);
+
 #[cfg(test)]
 #[cfg(feature = "std")]
+#[cfg(feature = "sign")]
 mod test {
     extern crate blake2;
     extern crate rand;
@@ -421,43 +712,61 @@ mod test {
         let message: Vec<u8> = b"This is the message".iter().cloned().collect();
 
         {
-            let signature = DLSAG::sign::<Sha512, OsRng>(k, ring.clone(), secret_index, &message);
-            let result = DLSAG::verify::<Sha512>(signature, &message);
+            let signature =
+                DLSAG::sign_with_rng_unchecked::<Sha512, _>(k, ring.clone(), secret_index, &message, &mut OsRng)
+                    .unwrap();
+            let result = signature.verify_ref::<Sha512>(&message);
             assert!(result);
         }
 
         {
             let signature =
-                DLSAG::sign::<Keccak512, OsRng>(k, ring.clone(), secret_index, &message);
-            let result = DLSAG::verify::<Keccak512>(signature, &message);
+                DLSAG::sign_with_rng_unchecked::<Keccak512, _>(k, ring.clone(), secret_index, &message, &mut OsRng)
+                    .unwrap();
+            let result = signature.verify_ref::<Keccak512>(&message);
             assert!(result);
         }
 
         {
-            let signature = DLSAG::sign::<Blake2b512, OsRng>(k, ring.clone(), secret_index, &message);
-            let result = DLSAG::verify::<Blake2b512>(signature, &message);
+            let signature =
+                DLSAG::sign_with_rng_unchecked::<Blake2b512, _>(k, ring.clone(), secret_index, &message, &mut OsRng)
+                    .unwrap();
+            let result = signature.verify_ref::<Blake2b512>(&message);
             assert!(result);
         }
 
         // Tests for signatures using the other end of the channel
         {
             let signature =
-                DLSAG::sign::<Sha512, OsRng>(other_k, ring.clone(), secret_index, &message);
-            let result = DLSAG::verify::<Sha512>(signature, &message);
+                DLSAG::sign_with_rng_unchecked::<Sha512, _>(other_k, ring.clone(), secret_index, &message, &mut OsRng)
+                    .unwrap();
+            let result = signature.verify_ref::<Sha512>(&message);
             assert!(result);
         }
 
         {
-            let signature =
-                DLSAG::sign::<Keccak512, OsRng>(other_k, ring.clone(), secret_index, &message);
-            let result = DLSAG::verify::<Keccak512>(signature, &message);
+            let signature = DLSAG::sign_with_rng_unchecked::<Keccak512, _>(
+                other_k,
+                ring.clone(),
+                secret_index,
+                &message,
+                &mut OsRng,
+            )
+            .unwrap();
+            let result = signature.verify_ref::<Keccak512>(&message);
             assert!(result);
         }
 
         {
-            let signature =
-                DLSAG::sign::<Blake2b512, OsRng>(other_k, ring.clone(), secret_index, &message);
-            let result = DLSAG::verify::<Blake2b512>(signature, &message);
+            let signature = DLSAG::sign_with_rng_unchecked::<Blake2b512, _>(
+                other_k,
+                ring.clone(),
+                secret_index,
+                &message,
+                &mut OsRng,
+            )
+            .unwrap();
+            let result = signature.verify_ref::<Blake2b512>(&message);
             assert!(result);
         }
 
@@ -471,14 +780,125 @@ mod test {
             })
             .collect();
         let another_message: Vec<u8> = b"This is another message".iter().cloned().collect();
-        let signature_1 =
-            DLSAG::sign::<Blake2b512, OsRng>(k, another_ring.clone(), secret_index, &another_message);
-        let signature_2 = DLSAG::sign::<Blake2b512, OsRng>(k, ring.clone(), secret_index, &message);
+        let signature_1 = DLSAG::sign_with_rng_unchecked::<Blake2b512, _>(
+            k,
+            another_ring.clone(),
+            secret_index,
+            &another_message,
+            &mut OsRng,
+        )
+        .unwrap();
+        let signature_2 =
+            DLSAG::sign_with_rng_unchecked::<Blake2b512, _>(k, ring.clone(), secret_index, &message, &mut OsRng)
+                .unwrap();
         let signature_3 =
-            DLSAG::sign::<Blake2b512, OsRng>(other_k, ring.clone(), secret_index, &message);
-        let result_1 = DLSAG::link(signature_1.clone(), signature_2);
+            DLSAG::sign_with_rng_unchecked::<Blake2b512, _>(other_k, ring.clone(), secret_index, &message, &mut OsRng)
+                .unwrap();
+        let result_1 = signature_1.link_ref(&signature_2);
         assert!(result_1);
-        let result_2 = DLSAG::link(signature_1.clone(), signature_3);
+        let result_2 = signature_1.link_ref(&signature_3);
         assert!(result_2);
     }
+
+    /// Generic code naming the key and ring types of the scheme
+    fn sign_and_verify<S: Scheme + VerifyRef>(k: S::SecretKey, ring: S::Ring, message: &Vec<u8>) -> bool {
+        return S::sign_with_key::<Sha512, OsRng>(k, ring, 0, message).unwrap().verify_ref::<Sha512>(message);
+    }
+
+    #[test]
+    fn scheme() {
+        let mut csprng = OsRng;
+        let (k, other_end, bitstring) = (
+            Scalar::random(&mut csprng),
+            RistrettoPoint::random(&mut csprng),
+            Scalar::random(&mut csprng),
+        );
+        let ring: Vec<(RistrettoPoint, RistrettoPoint, Scalar)> = (0..10)
+            .map(|_| {
+                (
+                    RistrettoPoint::random(&mut csprng),
+                    RistrettoPoint::random(&mut csprng),
+                    Scalar::random(&mut csprng),
+                )
+            })
+            .collect();
+        let message: Vec<u8> = b"This is the message".to_vec();
+
+        let left = DLSAGKey::Left(k, other_end, bitstring);
+        let right = DLSAGKey::Right(other_end, k, bitstring);
+        assert!(sign_and_verify::<DLSAG>(left, ring.clone(), &message));
+        assert!(sign_and_verify::<DLSAG>(right, ring.clone(), &message));
+
+        // Either side gives the key image `sign` does
+        let signature = DLSAG::sign_with_key::<Sha512, OsRng>(right, ring.clone(), 1, &message).unwrap();
+        let expected = DLSAG::sign_with_rng::<Sha512, _>(
+            (other_end, k, bitstring),
+            ring.clone(),
+            1,
+            &message,
+            &mut OsRng,
+        )
+        .unwrap();
+        assert_eq!(signature.key_image, expected.key_image);
+
+        assert!(matches!(
+            DLSAG::try_sign_with_key_and_rng::<Sha512, OsRng>(left, ring.clone(), 11, &message, &mut OsRng),
+            Err(Error::SecretIndexOutOfRange { index: 11, ring_size: 11 })
+        ));
+        assert!(matches!(
+            DLSAG::try_sign_with_key::<Sha512, OsRng>(left, ring[..1].to_vec(), 1, &message),
+            Err(Error::RingTooSmall { size: 2, minimum: 11 })
+        ));
+    }
+
+
+    #[test]
+    fn sign_with_full_ring() {
+        let mut csprng = OsRng;
+        let (k, other_end, bitstring) = (
+            Scalar::random(&mut csprng),
+            RistrettoPoint::random(&mut csprng),
+            Scalar::random(&mut csprng),
+        );
+        let mut ring: Vec<(RistrettoPoint, RistrettoPoint, Scalar)> = (0..10)
+            .map(|_| {
+                (
+                    RistrettoPoint::random(&mut csprng),
+                    RistrettoPoint::random(&mut csprng),
+                    Scalar::random(&mut csprng),
+                )
+            })
+            .collect();
+        ring.insert(1, (k * constants::RISTRETTO_BASEPOINT_POINT, other_end, bitstring));
+        let message: Vec<u8> = b"This is the message".to_vec();
+
+        // The channel as it is on chain, signed from either side
+        let left = DLSAG::sign_with_full_ring::<Sha512, OsRng>((k, other_end, bitstring), ring.clone(), 1, &message);
+        assert!(left.unwrap().verify_ref::<Sha512>(&message));
+        let mut reversed = ring.clone();
+        reversed[1] = (other_end, k * constants::RISTRETTO_BASEPOINT_POINT, bitstring);
+        let right = DLSAG::sign_with_full_ring::<Sha512, OsRng>((other_end, k, bitstring), reversed, 1, &message);
+        assert!(right.unwrap().verify_ref::<Sha512>(&message));
+
+        // The bitstring is part of the member
+        let other_bitstring = Scalar::random(&mut csprng);
+        assert_eq!(
+            DLSAG::sign_with_full_ring::<Sha512, OsRng>((k, other_end, other_bitstring), ring, 1, &message).err(),
+            Some(Error::NotInRing)
+        );
+    }
+
+    #[test]
+    fn sign_does_not_panic() {
+        let k: (Scalar, RistrettoPoint, Scalar) =
+            (Scalar::random(&mut OsRng), RistrettoPoint::random(&mut OsRng), Scalar::random(&mut OsRng));
+        let member =
+            |_| (RistrettoPoint::random(&mut OsRng), RistrettoPoint::random(&mut OsRng), Scalar::random(&mut OsRng));
+        let ring: Vec<(RistrettoPoint, RistrettoPoint, Scalar)> = (0..2).map(member).collect();
+        let message: Vec<u8> = b"This is the message".to_vec();
+
+        // A secret index outside the ring is an error, not a panic
+        let signature = DLSAG::sign_with_rng_unchecked::<Sha512, _>(k, ring, 3, &message, &mut OsRng);
+        assert_eq!(signature.err(), Some(Error::SecretIndexOutOfRange { index: 3, ring_size: 3 }));
+    }
 }
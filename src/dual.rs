@@ -0,0 +1,85 @@
+//! Dual-hash mode
+//!
+//! [`DualHash`] combines two 512-bit hash functions into one, so that passing it as the `Hash`
+//! of any scheme makes every challenge and every hash to a point absorb both transcripts. Its
+//! output is the exclusive or of both outputs, which stays unpredictable as long as either hash
+//! function does. Archival signatures made with e.g. `DualHash<Sha512, Blake2b512>` therefore
+//! survive a future break of one of the two.
+//!
+//! Dual-hash signatures only verify in dual-hash mode with the same two hash functions.
+
+use digest::generic_array::typenum::U64;
+use digest::{Digest, FixedOutput, HashMarker, Output, OutputSizeUser, Update};
+
+/// Two hash functions absorbing the same input, with the exclusive or of their outputs as output
+#[derive(Clone, Default)]
+pub struct DualHash<A, B> {
+    first: A,
+    second: B,
+}
+
+impl<A, B> OutputSizeUser for DualHash<A, B> {
+    type OutputSize = U64;
+}
+
+impl<A: Digest<OutputSize = U64>, B: Digest<OutputSize = U64>> Update for DualHash<A, B> {
+    fn update(&mut self, data: &[u8]) {
+        Digest::update(&mut self.first, data);
+        Digest::update(&mut self.second, data);
+    }
+}
+
+impl<A: Digest<OutputSize = U64>, B: Digest<OutputSize = U64>> FixedOutput for DualHash<A, B> {
+    fn finalize_into(self, out: &mut Output<Self>) {
+        let first = self.first.finalize();
+        let second = self.second.finalize();
+        for i in 0..out.len() {
+            out[i] = first[i] ^ second[i];
+        }
+    }
+}
+
+impl<A, B> HashMarker for DualHash<A, B> {}
+
+#[cfg(test)]
+#[cfg(feature = "std")]
+#[cfg(feature = "sign")]
+#[cfg(feature = "blsag")]
+mod test {
+    extern crate blake2;
+    extern crate rand;
+    extern crate sha2;
+
+    use blake2::Blake2b512;
+    use curve25519_dalek::ristretto::RistrettoPoint;
+    use curve25519_dalek::scalar::Scalar;
+    use rand::rngs::OsRng;
+    use sha2::Sha512;
+
+    use super::*;
+    use crate::blsag::BLSAG;
+    use crate::prelude::*;
+    use crate::traits::{SignWithRng, VerifyRef};
+
+    #[test]
+    fn dual() {
+        let data = b"This is the message";
+        let combined = DualHash::<Sha512, Blake2b512>::digest(data);
+        let first = Sha512::digest(data);
+        let second = Blake2b512::digest(data);
+        for i in 0..64 {
+            assert_eq!(combined[i], first[i] ^ second[i]);
+        }
+
+        let k: Scalar = Scalar::random(&mut OsRng);
+        let ring: Vec<RistrettoPoint> = (0..2).map(|_| RistrettoPoint::random(&mut OsRng)).collect();
+        let message: Vec<u8> = data.to_vec();
+        let signature =
+            BLSAG::sign_with_rng_unchecked::<DualHash<Sha512, Blake2b512>, _>(k, ring, 1, &message, &mut OsRng)
+                .unwrap();
+        assert!(signature.verify_ref::<DualHash<Sha512, Blake2b512>>(&message));
+        // Neither hash function alone verifies it
+        assert!(!signature.verify_ref::<Sha512>(&message));
+        assert!(!signature.verify_ref::<Blake2b512>(&message));
+    }
+}
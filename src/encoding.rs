@@ -0,0 +1,233 @@
+//! Hex and base64
+//!
+//! Text encodings of the canonical encodings of [`crate::codec`], for logging and for formats that
+//! carry text, without `std` or any other crate. [`Hex`] and [`Base64`] format bytes in place, so
+//! that printing a key image allocates nothing, and [`hex_32`] encodes the 32 byte encoding of a
+//! key, key image or scalar into a fixed size array, also in a `const` context.
+//!
+//! Keys, key images and signatures display as the lowercase hex of their canonical encoding, the
+//! same string the `serde` feature serializes them as in human readable formats. Private keys do
+//! not display.
+//!
+//! Base64 is the standard alphabet of RFC 4648 with padding. Decoding accepts hex in either case
+//! and rejects everything the encoders can not have written, with [`Error::Malformed`].
+
+use crate::prelude::*;
+
+use alloc::string::String;
+use core::fmt;
+
+use crate::error::Error;
+
+const HEX_DIGITS: &[u8; 16] = b"0123456789abcdef";
+
+const BASE64_DIGITS: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+/// The length of the hex of `bytes` bytes
+pub const fn hex_len(bytes: usize) -> usize {
+    return 2 * bytes;
+}
+
+/// The length of the padded base64 of `bytes` bytes
+pub const fn base64_len(bytes: usize) -> usize {
+    return 4 * bytes.div_ceil(3);
+}
+
+/// The two lowercase hex digits of `byte`
+const fn hex_pair(byte: u8) -> [u8; 2] {
+    return [HEX_DIGITS[(byte >> 4) as usize], HEX_DIGITS[(byte & 0xf) as usize]];
+}
+
+/// The lowercase hex of a 32 byte encoding, as ASCII
+pub const fn hex_32(bytes: &[u8; 32]) -> [u8; 64] {
+    let mut hex = [0u8; 64];
+    let mut i = 0;
+    while i < 32 {
+        let pair = hex_pair(bytes[i]);
+        hex[2 * i] = pair[0];
+        hex[2 * i + 1] = pair[1];
+        i += 1;
+    }
+    return hex;
+}
+
+/// The four base64 digits of up to three bytes, padded
+const fn base64_quad(chunk: &[u8]) -> [u8; 4] {
+    let b0 = chunk[0] as u32;
+    let b1 = if chunk.len() > 1 { chunk[1] as u32 } else { 0 };
+    let b2 = if chunk.len() > 2 { chunk[2] as u32 } else { 0 };
+    let triple = (b0 << 16) | (b1 << 8) | b2;
+    let mut quad = [b'='; 4];
+    quad[0] = BASE64_DIGITS[((triple >> 18) & 0x3f) as usize];
+    quad[1] = BASE64_DIGITS[((triple >> 12) & 0x3f) as usize];
+    if chunk.len() > 1 {
+        quad[2] = BASE64_DIGITS[((triple >> 6) & 0x3f) as usize];
+    }
+    if chunk.len() > 2 {
+        quad[3] = BASE64_DIGITS[(triple & 0x3f) as usize];
+    }
+    return quad;
+}
+
+/// Formats bytes as lowercase hex
+#[derive(Clone, Copy, Debug)]
+pub struct Hex<'a>(pub &'a [u8]);
+
+impl<'a> fmt::Display for Hex<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for byte in self.0 {
+            let pair = hex_pair(*byte);
+            f.write_str(core::str::from_utf8(&pair).map_err(|_| fmt::Error)?)?;
+        }
+        return Ok(());
+    }
+}
+
+/// Formats bytes as padded base64
+#[derive(Clone, Copy, Debug)]
+pub struct Base64<'a>(pub &'a [u8]);
+
+impl<'a> fmt::Display for Base64<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for chunk in self.0.chunks(3) {
+            let quad = base64_quad(chunk);
+            f.write_str(core::str::from_utf8(&quad).map_err(|_| fmt::Error)?)?;
+        }
+        return Ok(());
+    }
+}
+
+/// Lowercase hex of `bytes`
+pub fn encode_hex(bytes: &[u8]) -> String {
+    let mut hex = String::with_capacity(hex_len(bytes.len()));
+    for byte in bytes {
+        let pair = hex_pair(*byte);
+        hex.push(pair[0] as char);
+        hex.push(pair[1] as char);
+    }
+    return hex;
+}
+
+/// Decodes hex in either case
+pub fn decode_hex(hex: &str) -> Result<Vec<u8>, Error> {
+    let digits: Vec<u8> = hex
+        .chars()
+        .map(|c| c.to_digit(16).map(|d| d as u8))
+        .collect::<Option<Vec<u8>>>()
+        .ok_or(Error::Malformed)?;
+    let pairs = digits.chunks_exact(2);
+    if !pairs.remainder().is_empty() {
+        return Err(Error::Malformed);
+    }
+    return Ok(pairs.map(|pair| (pair[0] << 4) | pair[1]).collect());
+}
+
+/// Padded base64 of `bytes`
+pub fn encode_base64(bytes: &[u8]) -> String {
+    let mut base64 = String::with_capacity(base64_len(bytes.len()));
+    for chunk in bytes.chunks(3) {
+        for digit in base64_quad(chunk) {
+            base64.push(digit as char);
+        }
+    }
+    return base64;
+}
+
+/// The value of a base64 digit
+fn base64_value(digit: u8) -> Option<u32> {
+    return BASE64_DIGITS.iter().position(|d| *d == digit).map(|value| value as u32);
+}
+
+/// Decodes padded base64, rejecting a missing or misplaced padding and unused bits that are set
+pub fn decode_base64(base64: &str) -> Result<Vec<u8>, Error> {
+    let quads = base64.as_bytes().chunks_exact(4);
+    if !quads.remainder().is_empty() {
+        return Err(Error::Malformed);
+    }
+    let n = quads.len();
+    let mut bytes: Vec<u8> = Vec::with_capacity(3 * n);
+    for (i, quad) in quads.enumerate() {
+        // Padding may only end the last quad, and not its first two digits
+        let padding = quad.iter().rev().take_while(|digit| **digit == b'=').count();
+        if padding > 2 || (padding > 0 && i + 1 != n) {
+            return Err(Error::Malformed);
+        }
+        let mut triple: u32 = 0;
+        for digit in &quad[..4 - padding] {
+            triple = (triple << 6) | base64_value(*digit).ok_or(Error::Malformed)?;
+        }
+        triple <<= 6 * padding as u32;
+        let decoded = [(triple >> 16) as u8, (triple >> 8) as u8, triple as u8];
+        // The bits of the padded digits' positions must all be zero
+        if decoded[3 - padding..].iter().any(|byte| *byte != 0) {
+            return Err(Error::Malformed);
+        }
+        bytes.extend_from_slice(&decoded[..3 - padding]);
+    }
+    return Ok(bytes);
+}
+
+/// Implements `Display` for a [`crate::codec::Codec`] type as the hex of its canonical encoding
+macro_rules! display_via_codec {
+    ($t:ty) => {
+        impl core::fmt::Display for $t {
+            fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+                return core::fmt::Display::fmt(&crate::encoding::Hex(&crate::codec::Codec::to_bytes(self)), f);
+            }
+        }
+    };
+}
+
+pub(crate) use display_via_codec;
+
+#[cfg(test)]
+#[cfg(feature = "std")]
+#[cfg(feature = "sign")]
+mod test {
+    extern crate rand;
+
+    use curve25519_dalek::ristretto::RistrettoPoint;
+    use rand::rngs::OsRng;
+
+    use super::*;
+    use crate::codec::Codec;
+    use crate::keys::PublicKey;
+
+    #[test]
+    fn encoding() {
+        // RFC 4648
+        let vectors = [
+            ("", ""),
+            ("f", "Zg=="),
+            ("fo", "Zm8="),
+            ("foo", "Zm9v"),
+            ("foob", "Zm9vYg=="),
+            ("fooba", "Zm9vYmE="),
+            ("foobar", "Zm9vYmFy"),
+        ];
+        for (bytes, base64) in vectors.iter() {
+            assert_eq!(encode_base64(bytes.as_bytes()), *base64);
+            assert_eq!(format!("{}", Base64(bytes.as_bytes())), *base64);
+            assert_eq!(base64_len(bytes.len()), base64.len());
+            assert_eq!(decode_base64(base64), Ok(bytes.as_bytes().to_vec()));
+        }
+        for malformed in ["Zg=", "Zh==", "Z===", "Zg==Zm8=", "Zm9v!A==", "=Zm9"].iter() {
+            assert_eq!(decode_base64(malformed), Err(Error::Malformed), "{}", malformed);
+        }
+
+        assert_eq!(encode_hex(&[0x00, 0xab, 0x7f]), "00ab7f");
+        assert_eq!(decode_hex("00AB7f"), Ok(vec![0x00, 0xab, 0x7f]));
+        assert_eq!(decode_hex("00a"), Err(Error::Malformed));
+        assert_eq!(decode_hex("0g"), Err(Error::Malformed));
+
+        const ZERO: [u8; 64] = hex_32(&[0u8; 32]);
+        assert!(ZERO.iter().all(|digit| *digit == b'0'));
+
+        // Keys display as the hex of their encoding, as serde writes them
+        let public_key = PublicKey(RistrettoPoint::random(&mut OsRng));
+        let hex = format!("{}", public_key);
+        assert_eq!(hex, encode_hex(&public_key.to_bytes()));
+        assert_eq!(hex.as_bytes(), &hex_32(public_key.0.compress().as_bytes())[..]);
+        assert_eq!(PublicKey::decode(&decode_hex(&hex).unwrap()), Ok(public_key));
+    }
+}
@@ -0,0 +1,119 @@
+//! Errors
+
+use core::fmt;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum Error {
+    /// The ring, signer included, has fewer members than the policy requires
+    RingTooSmall { size: usize, minimum: usize },
+    /// The scheme tag is not one of a scheme enabled in this build
+    UnknownScheme { tag: u8 },
+    /// The scheme is not one the policy accepts
+    SchemeNotAllowed { tag: u8 },
+    /// Decoding or verifying would allocate `needed` bytes, more than the memory budget allows
+    BudgetExceeded { needed: usize, budget: usize },
+    /// The scheme is not enabled in the runtime scheme registry
+    SchemeDisabled { tag: u8 },
+    /// The bytes are not a canonical encoding, for a reason no other variant names
+    Malformed,
+    /// The bytes end before the encoding does
+    Truncated,
+    /// Bytes are left over after the encoding
+    TrailingBytes,
+    /// A point is not a canonical compressed Ristretto encoding
+    InvalidPointEncoding,
+    /// A scalar is not reduced modulo the group order
+    NonCanonicalScalar,
+    /// The ring has no members, or its members no keys
+    EmptyRing,
+    /// The secret index is not a position in the ring of `ring_size` members, signer included
+    SecretIndexOutOfRange { index: usize, ring_size: usize },
+    /// The public key of the signer is not a member of the ring
+    NotInRing,
+    /// The members of the ring, or the signer, do not all have the same number of keys
+    RaggedRingMatrix,
+    /// The signature does not have one response per ring member and key
+    ResponseCountMismatch,
+    /// The signature is well formed but does not verify
+    InvalidSignature,
+    /// The key image was seen before, e.g. the key already claimed
+    AlreadyClaimed,
+    /// No ring with the digest a detached signature refers to is known
+    RingNotFound,
+    /// The format version is not one this build verifies
+    UnknownVersion { version: u8 },
+    /// The key image of a revealed signature is not the one committed to
+    CommitmentMismatch,
+    /// The weight of the signer is below the threshold of a weighted ring
+    BelowThreshold { weight: u64, threshold: u64 },
+}
+
+impl Error {
+    /// A stable snake case name of the variant, e.g. for test vectors read by other
+    /// implementations
+    pub fn code(&self) -> &'static str {
+        return match self {
+            Error::RingTooSmall { .. } => "ring_too_small",
+            Error::UnknownScheme { .. } => "unknown_scheme",
+            Error::SchemeNotAllowed { .. } => "scheme_not_allowed",
+            Error::BudgetExceeded { .. } => "budget_exceeded",
+            Error::SchemeDisabled { .. } => "scheme_disabled",
+            Error::Malformed => "malformed",
+            Error::Truncated => "truncated",
+            Error::TrailingBytes => "trailing_bytes",
+            Error::InvalidPointEncoding => "invalid_point_encoding",
+            Error::NonCanonicalScalar => "non_canonical_scalar",
+            Error::EmptyRing => "empty_ring",
+            Error::SecretIndexOutOfRange { .. } => "secret_index_out_of_range",
+            Error::NotInRing => "not_in_ring",
+            Error::RaggedRingMatrix => "ragged_ring_matrix",
+            Error::ResponseCountMismatch => "response_count_mismatch",
+            Error::InvalidSignature => "invalid_signature",
+            Error::AlreadyClaimed => "already_claimed",
+            Error::RingNotFound => "ring_not_found",
+            Error::UnknownVersion { .. } => "unknown_version",
+            Error::CommitmentMismatch => "commitment_mismatch",
+            Error::BelowThreshold { .. } => "below_threshold",
+        };
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        return match self {
+            Error::RingTooSmall { size, minimum } => {
+                write!(f, "ring of {} members is smaller than the minimum of {}", size, minimum)
+            }
+            Error::UnknownScheme { tag } => write!(f, "unknown or disabled scheme {}", tag),
+            Error::SchemeNotAllowed { tag } => write!(f, "scheme {} is not allowed", tag),
+            Error::BudgetExceeded { needed, budget } => {
+                write!(f, "needs {} bytes, more than the memory budget of {}", needed, budget)
+            }
+            Error::SchemeDisabled { tag } => write!(f, "scheme {} is disabled", tag),
+            Error::Malformed => write!(f, "malformed signature"),
+            Error::Truncated => write!(f, "encoding is truncated"),
+            Error::TrailingBytes => write!(f, "trailing bytes after the encoding"),
+            Error::InvalidPointEncoding => write!(f, "invalid point encoding"),
+            Error::NonCanonicalScalar => write!(f, "non-canonical scalar"),
+            Error::EmptyRing => write!(f, "empty ring"),
+            Error::SecretIndexOutOfRange { index, ring_size } => {
+                write!(f, "secret index {} is out of range for a ring of {} members", index, ring_size)
+            }
+            Error::NotInRing => write!(f, "signer is not a member of the ring"),
+            Error::RaggedRingMatrix => write!(f, "ring members have different numbers of keys"),
+            Error::ResponseCountMismatch => write!(f, "responses do not match the ring"),
+            Error::InvalidSignature => write!(f, "invalid signature"),
+            Error::AlreadyClaimed => write!(f, "key image was already claimed"),
+            Error::RingNotFound => write!(f, "ring not found"),
+            Error::UnknownVersion { version } => write!(f, "unknown format version {}", version),
+            Error::CommitmentMismatch => write!(f, "key image does not open the commitment"),
+            Error::BelowThreshold { weight, threshold } => {
+                write!(f, "signer weight {} is below the threshold of {}", weight, threshold)
+            }
+        };
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for Error {}
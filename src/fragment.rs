@@ -0,0 +1,185 @@
+//! Fragmentation
+//!
+//! Large-ring signatures do not fit in a single frame of LoRa or BLE class transports.
+//! [`Fragments::split`] cuts the canonical encoding of any signature into [`Fragment`]s no larger
+//! than a given MTU, each carrying a header with its position, the fragment count, the total
+//! length and a CRC-32 of the whole encoding. [`Fragments::reassemble`] accepts the fragments in
+//! any order and only returns a signature once every fragment is present and consistent.
+//!
+//! The checksum catches transport corruption, not forgery: a reassembled signature still has to
+//! be verified.
+
+use crate::prelude::*;
+
+use crate::codec::Codec;
+use crate::error::Error;
+
+/// Length of the header in front of the payload of every encoded fragment
+pub const HEADER_LEN: usize = 16;
+
+/// A piece of the canonical encoding of a signature
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Fragment {
+    pub index: u32,
+    pub count: u32,
+    /// Length of the whole encoding
+    pub total_len: u32,
+    /// CRC-32 of the whole encoding
+    pub checksum: u32,
+    pub payload: Vec<u8>,
+}
+
+impl Codec for Fragment {
+    fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes: Vec<u8> = Vec::with_capacity(HEADER_LEN + self.payload.len());
+        bytes.extend_from_slice(&self.index.to_le_bytes());
+        bytes.extend_from_slice(&self.count.to_le_bytes());
+        bytes.extend_from_slice(&self.total_len.to_le_bytes());
+        bytes.extend_from_slice(&self.checksum.to_le_bytes());
+        bytes.extend_from_slice(&self.payload);
+        return bytes;
+    }
+
+    /// Decodes a fragment, returning [`Error::Truncated`] if `bytes` is shorter than the header
+    /// and [`Error::Malformed`] if the index is out of range
+    fn decode(bytes: &[u8]) -> Result<Fragment, Error> {
+        if bytes.len() < HEADER_LEN {
+            return Err(Error::Truncated);
+        }
+        let fragment = Fragment {
+            index: read_u32(bytes, 0),
+            count: read_u32(bytes, 4),
+            total_len: read_u32(bytes, 8),
+            checksum: read_u32(bytes, 12),
+            payload: bytes[HEADER_LEN..].to_vec(),
+        };
+        if fragment.index >= fragment.count {
+            return Err(Error::Malformed);
+        }
+        return Ok(fragment);
+    }
+}
+
+fn read_u32(bytes: &[u8], offset: usize) -> u32 {
+    let mut array = [0u8; 4];
+    array.copy_from_slice(&bytes[offset..offset + 4]);
+    return u32::from_le_bytes(array);
+}
+
+/// CRC-32 as used by Ethernet and zlib
+fn crc32(bytes: &[u8]) -> u32 {
+    let mut crc: u32 = 0xffff_ffff;
+    for byte in bytes {
+        crc ^= *byte as u32;
+        for _ in 0..8 {
+            let mask = (crc & 1).wrapping_neg();
+            crc = (crc >> 1) ^ (0xedb8_8320 & mask);
+        }
+    }
+    return !crc;
+}
+
+/// Splitting into and reassembling from [`Fragment`]s, for every signature with a canonical
+/// encoding
+pub trait Fragments: Codec {
+    /// Cuts the encoding of the signature into fragments whose encodings are at most `mtu` bytes
+    ///
+    /// # Panics
+    ///
+    /// If `mtu` leaves no room for payload after the [`HEADER_LEN`] bytes of header
+    fn split(&self, mtu: usize) -> Vec<Fragment> {
+        assert!(mtu > HEADER_LEN, "mtu must be larger than the fragment header");
+        let bytes = self.to_bytes();
+        let checksum = crc32(&bytes);
+        let chunks: Vec<&[u8]> = bytes.chunks(mtu - HEADER_LEN).collect();
+        let count = chunks.len();
+        return chunks
+            .iter()
+            .enumerate()
+            .map(|(index, chunk)| Fragment {
+                index: index as u32,
+                count: count as u32,
+                total_len: bytes.len() as u32,
+                checksum: checksum,
+                payload: chunk.to_vec(),
+            })
+            .collect();
+    }
+
+    /// Reassembles a signature from all of its `fragments`, in any order. Returns `None` if a
+    /// fragment is missing or duplicated, the fragments disagree about the signature they belong
+    /// to, or the reassembled encoding fails its checksum or does not decode.
+    fn reassemble(fragments: &[Fragment]) -> Option<Self> {
+        let first = fragments.first()?;
+        if fragments.len() != first.count as usize {
+            return None;
+        }
+        let mut ordered: Vec<Option<&Fragment>> = vec![None; fragments.len()];
+        for fragment in fragments {
+            if fragment.count != first.count
+                || fragment.total_len != first.total_len
+                || fragment.checksum != first.checksum
+            {
+                return None;
+            }
+            let slot = ordered.get_mut(fragment.index as usize)?;
+            if slot.is_some() {
+                return None;
+            }
+            *slot = Some(fragment);
+        }
+        let mut bytes: Vec<u8> = Vec::new();
+        for fragment in ordered {
+            bytes.extend_from_slice(&fragment?.payload);
+        }
+        if bytes.len() != first.total_len as usize || crc32(&bytes) != first.checksum {
+            return None;
+        }
+        return Self::from_bytes(&bytes);
+    }
+}
+
+impl<S: Codec> Fragments for S {}
+
+#[cfg(test)]
+#[cfg(feature = "std")]
+#[cfg(feature = "sign")]
+#[cfg(feature = "mlsag")]
+mod test {
+    extern crate rand;
+    extern crate sha2;
+
+    use curve25519_dalek::ristretto::RistrettoPoint;
+    use curve25519_dalek::scalar::Scalar;
+    use rand::rngs::OsRng;
+    use sha2::Sha512;
+
+    use super::*;
+    use crate::mlsag::MLSAG;
+    use crate::traits::{SignWithRng, VerifyRef};
+
+    #[test]
+    fn fragment() {
+        assert_eq!(crc32(b"123456789"), 0xcbf4_3926);
+
+        let ks: Vec<Scalar> = (0..2).map(|_| Scalar::random(&mut OsRng)).collect();
+        let ring: Vec<Vec<RistrettoPoint>> =
+            (0..7).map(|_| (0..2).map(|_| RistrettoPoint::random(&mut OsRng)).collect()).collect();
+        let message: Vec<u8> = b"This is the message".to_vec();
+        let signature = MLSAG::sign_with_rng_unchecked::<Sha512, _>(ks, ring, 3, &message, &mut OsRng).unwrap();
+
+        let mut fragments = signature.split(64);
+        assert!(fragments.iter().all(|fragment| fragment.to_bytes().len() <= 64));
+        let received: Vec<Fragment> =
+            fragments.iter().rev().map(|fragment| Fragment::from_bytes(&fragment.to_bytes()).unwrap()).collect();
+        assert!(MLSAG::reassemble(&received).unwrap().verify_ref::<Sha512>(&message));
+
+        // Duplicated, missing or corrupted fragments are rejected
+        let mut duplicated = fragments.clone();
+        duplicated[1] = duplicated[0].clone();
+        assert!(MLSAG::reassemble(&duplicated).is_none());
+        assert!(MLSAG::reassemble(&fragments[1..]).is_none());
+        fragments[2].payload[0] ^= 1;
+        assert!(MLSAG::reassemble(&fragments).is_none());
+    }
+}
@@ -0,0 +1,212 @@
+//! Base generators
+//!
+//! Public keys are `k G` and every challenge of every scheme commits to `r G + c K` for the
+//! Ristretto basepoint `G`. Protocols that derive a generator of their own, e.g. by hash to curve
+//! from an application tag, sign and verify with it through [`SignWithGenerator`] and
+//! [`VerifyWithGenerator`], which take the generator as a [`Generator`] type parameter the way
+//! `sign_with` takes the hash to point, see [`crate::hash_to_point`]. `sign` and `verify` use
+//! [`Basepoint`].
+//!
+//! The keys of the ring must be public keys under the same generator, see
+//! [`Generator::public_key`]. A signature only verifies with the generator it was made with.
+
+use crate::prelude::*;
+
+use curve25519_dalek::constants;
+use curve25519_dalek::ristretto::RistrettoPoint;
+use curve25519_dalek::scalar::Scalar;
+use digest::generic_array::typenum::U64;
+use digest::Digest;
+#[cfg(feature = "sign")]
+use rand_core::{CryptoRng, RngCore};
+
+#[cfg(feature = "sign")]
+use crate::error::Error;
+
+pub trait Generator {
+    /// A point whose discrete logarithm relative to the hash to point of any key is unknown
+    fn generator() -> RistrettoPoint;
+
+    /// The public key of `k` under this generator
+    fn public_key(k: &Scalar) -> RistrettoPoint {
+        return crate::ops::mul_base(k, &Self::generator());
+    }
+}
+
+/// The Ristretto basepoint
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Basepoint;
+
+impl Generator for Basepoint {
+    fn generator() -> RistrettoPoint {
+        return constants::RISTRETTO_BASEPOINT_POINT;
+    }
+}
+
+/// Signing with the generator `G` in place of the basepoint
+#[cfg(feature = "sign")]
+pub trait SignWithGenerator<PrivateKey, Ring>: Sized {
+    fn sign_with_generator<
+        Hash: Digest<OutputSize = U64> + Clone + Default,
+        CSPRNG: CryptoRng + RngCore + Default,
+        G: Generator,
+    >(
+        k: PrivateKey,
+        ring: Ring,
+        secret_index: usize,
+        message: &Vec<u8>,
+    ) -> Result<Self, Error> {
+        let mut csprng = CSPRNG::default();
+        return Self::sign_with_generator_and_rng::<Hash, CSPRNG, G>(k, ring, secret_index, message, &mut csprng);
+    }
+
+    /// `sign_with_generator` with randomness drawn from `csprng`, see
+    /// [`SignWithRng`](crate::traits::SignWithRng)
+    fn sign_with_generator_and_rng<
+        Hash: Digest<OutputSize = U64> + Clone + Default,
+        CSPRNG: CryptoRng + RngCore,
+        G: Generator,
+    >(
+        k: PrivateKey,
+        ring: Ring,
+        secret_index: usize,
+        message: &Vec<u8>,
+        csprng: &mut CSPRNG,
+    ) -> Result<Self, Error>;
+}
+
+/// Verifying signatures made with [`SignWithGenerator`]
+pub trait VerifyWithGenerator {
+    fn verify_with_generator<Hash: Digest<OutputSize = U64> + Clone + Default, G: Generator>(
+        &self,
+        message: &Vec<u8>,
+    ) -> bool;
+}
+
+#[cfg(test)]
+#[cfg(feature = "std")]
+#[cfg(feature = "sign")]
+mod test {
+    extern crate rand;
+    extern crate sha2;
+
+    use rand::rngs::OsRng;
+    use sha2::Sha512;
+
+    use super::*;
+    use crate::traits::VerifyRef;
+
+    /// A generator derived from an application tag
+    struct Tagged;
+
+    impl Generator for Tagged {
+        fn generator() -> RistrettoPoint {
+            return RistrettoPoint::hash_from_bytes::<Sha512>(b"nazgul/test/generator");
+        }
+    }
+
+    fn random_ring(n: usize) -> Vec<RistrettoPoint> {
+        return (0..n).map(|_| RistrettoPoint::random(&mut OsRng)).collect();
+    }
+
+    fn check<S: VerifyWithGenerator + VerifyRef>(signature: S) {
+        let message: Vec<u8> = b"This is the message".to_vec();
+        assert!(signature.verify_with_generator::<Sha512, Tagged>(&message));
+        assert!(!signature.verify_with_generator::<Sha512, Tagged>(&b"Another message".to_vec()));
+        assert!(!signature.verify_with_generator::<Sha512, Basepoint>(&message));
+        assert!(!signature.verify_ref::<Sha512>(&message));
+    }
+
+    #[test]
+    fn basepoint() {
+        let k: Scalar = Scalar::random(&mut OsRng);
+        assert_eq!(Basepoint::public_key(&k), RistrettoPoint::mul_base(&k));
+        assert_eq!(Tagged::public_key(&k), k * Tagged::generator());
+    }
+
+    #[test]
+    #[cfg(feature = "sag")]
+    fn sag() {
+        let message: Vec<u8> = b"This is the message".to_vec();
+        let k: Scalar = Scalar::random(&mut OsRng);
+        let signature =
+            crate::sag::SAG::sign_with_generator::<Sha512, OsRng, Tagged>(k, random_ring(2), 1, &message).unwrap();
+        assert_eq!(signature.ring[1], Tagged::public_key(&k));
+        check(signature);
+    }
+
+    #[test]
+    #[cfg(feature = "lsag")]
+    fn lsag() {
+        let message: Vec<u8> = b"This is the message".to_vec();
+        let k: Scalar = Scalar::random(&mut OsRng);
+        check(crate::lsag::LSAG::sign_with_generator::<Sha512, OsRng, Tagged>(k, random_ring(2), 0, &message).unwrap());
+    }
+
+    #[test]
+    #[cfg(feature = "blsag")]
+    fn blsag() {
+        let message: Vec<u8> = b"This is the message".to_vec();
+        let k: Scalar = Scalar::random(&mut OsRng);
+        let signature =
+            crate::blsag::BLSAG::sign_with_generator::<Sha512, OsRng, Tagged>(k, random_ring(3), 2, &message).unwrap();
+        check(signature);
+    }
+
+    #[test]
+    #[cfg(feature = "mlsag")]
+    fn mlsag() {
+        let message: Vec<u8> = b"This is the message".to_vec();
+        let ks: Vec<Scalar> = (0..2).map(|_| Scalar::random(&mut OsRng)).collect();
+        let ring: Vec<Vec<RistrettoPoint>> = (0..2).map(|_| random_ring(2)).collect();
+        let signature =
+            crate::mlsag::MLSAG::sign_with_generator::<Sha512, OsRng, Tagged>(ks, ring, 1, &message).unwrap();
+        check(signature);
+    }
+
+    #[test]
+    #[cfg(feature = "clsag")]
+    fn clsag() {
+        let message: Vec<u8> = b"This is the message".to_vec();
+        let ks: Vec<Scalar> = (0..2).map(|_| Scalar::random(&mut OsRng)).collect();
+        let ring: Vec<Vec<RistrettoPoint>> = (0..2).map(|_| random_ring(2)).collect();
+        check(crate::clsag::CLSAG::sign_with_generator::<Sha512, OsRng, Tagged>(ks, ring, 1, &message).unwrap());
+    }
+
+    #[test]
+    #[cfg(feature = "dlsag")]
+    fn dlsag() {
+        let message: Vec<u8> = b"This is the message".to_vec();
+        let k: (Scalar, RistrettoPoint, Scalar) =
+            (Scalar::random(&mut OsRng), RistrettoPoint::random(&mut OsRng), Scalar::random(&mut OsRng));
+        let ring: Vec<(RistrettoPoint, RistrettoPoint, Scalar)> = random_ring(2)
+            .into_iter()
+            .map(|k_point| (k_point, RistrettoPoint::random(&mut OsRng), Scalar::random(&mut OsRng)))
+            .collect();
+        check(crate::dlsag::DLSAG::sign_with_generator::<Sha512, OsRng, Tagged>(k, ring.clone(), 1, &message).unwrap());
+        let other_k: (RistrettoPoint, Scalar, Scalar) = (k.1, k.0, k.2);
+        check(crate::dlsag::DLSAG::sign_with_generator::<Sha512, OsRng, Tagged>(other_k, ring, 1, &message).unwrap());
+    }
+
+    #[test]
+    #[cfg(feature = "mdlsag")]
+    fn mdlsag() {
+        use crate::mdlsag::MDLSAG;
+
+        let message: Vec<u8> = b"This is the message".to_vec();
+        let ks: Vec<(Scalar, RistrettoPoint, Scalar)> = (0..2)
+            .map(|_| (Scalar::random(&mut OsRng), RistrettoPoint::random(&mut OsRng), Scalar::random(&mut OsRng)))
+            .collect();
+        let ring: Vec<Vec<(RistrettoPoint, RistrettoPoint, Scalar)>> = (0..2)
+            .map(|_| {
+                random_ring(2)
+                    .into_iter()
+                    .map(|k_point| (k_point, RistrettoPoint::random(&mut OsRng), Scalar::random(&mut OsRng)))
+                    .collect()
+            })
+            .collect();
+        check(MDLSAG::sign_with_generator::<Sha512, OsRng, Tagged>(ks.clone(), ring.clone(), 1, &message).unwrap());
+        let other_ks: Vec<(RistrettoPoint, Scalar, Scalar)> = ks.iter().map(|k| (k.1, k.0, k.2)).collect();
+        check(MDLSAG::sign_with_generator::<Sha512, OsRng, Tagged>(other_ks, ring, 1, &message).unwrap());
+    }
+}
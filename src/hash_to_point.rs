@@ -0,0 +1,80 @@
+//! Hash to point
+//!
+//! Key images and the second half of every challenge of bLSAG, MLSAG and CLSAG use a hash to
+//! point `H_p`. Their `sign_with`, `verify_with` and `generate_key_image_with` take the mapping
+//! as a [`HashToPoint`] type parameter, so that signatures can interoperate with systems that use
+//! another mapping (e.g. RFC 9380 `hash_to_curve`, or a custom domain tag) without forking each
+//! module. `sign`, `verify` and `generate_key_image` use [`DefaultHashToPoint`]. The reduction of
+//! challenges to scalars is chosen the same way, see [`crate::hash_to_scalar`].
+
+use curve25519_dalek::ristretto::RistrettoPoint;
+use digest::generic_array::typenum::U64;
+use digest::Digest;
+
+use crate::ops;
+
+pub trait HashToPoint {
+    /// Maps `point` to a point whose discrete logarithm relative to any other is unknown
+    fn hash_to_point<Hash: Digest<OutputSize = U64> + Default>(point: &RistrettoPoint) -> RistrettoPoint;
+}
+
+/// `H_p(P) = from_hash(H(P))`, hashing the compressed encoding of `P` with the scheme's `Hash`
+#[derive(Clone, Copy, Debug, Default)]
+pub struct DefaultHashToPoint;
+
+impl HashToPoint for DefaultHashToPoint {
+    fn hash_to_point<Hash: Digest<OutputSize = U64> + Default>(point: &RistrettoPoint) -> RistrettoPoint {
+        return ops::point_from_hash(Hash::default().chain_update(point.compress().as_bytes()));
+    }
+}
+
+#[cfg(test)]
+#[cfg(feature = "std")]
+#[cfg(feature = "sign")]
+#[cfg(all(feature = "blsag", feature = "clsag"))]
+mod test {
+    extern crate rand;
+    extern crate sha2;
+
+    use curve25519_dalek::scalar::Scalar;
+    use rand::rngs::OsRng;
+    use sha2::Sha512;
+
+    use super::*;
+    use crate::blsag::BLSAG;
+    use crate::clsag::CLSAG;
+    use crate::hash_to_scalar::WideReduction;
+    use crate::prelude::*;
+    use crate::traits::{KeyImageGenRef, VerifyRef};
+
+    /// The default mapping behind a domain tag
+    struct Tagged;
+
+    impl HashToPoint for Tagged {
+        fn hash_to_point<Hash: Digest<OutputSize = U64> + Default>(point: &RistrettoPoint) -> RistrettoPoint {
+            return RistrettoPoint::from_hash(
+                Hash::default().chain_update(b"tag").chain_update(point.compress().as_bytes()),
+            );
+        }
+    }
+
+    #[test]
+    fn hash_to_point() {
+        let message: Vec<u8> = b"This is the message".to_vec();
+
+        let k: Scalar = Scalar::random(&mut OsRng);
+        let ring: Vec<RistrettoPoint> = (0..2).map(|_| RistrettoPoint::random(&mut OsRng)).collect();
+        let signature = BLSAG::sign_with::<Sha512, OsRng, Tagged, WideReduction>(k, ring, 1, &message).unwrap();
+        assert_eq!(signature.key_image, BLSAG::generate_key_image_with::<Sha512, Tagged>(k));
+        assert_ne!(signature.key_image, BLSAG::generate_key_image_ref::<Sha512>(&k));
+        assert!(BLSAG::verify_with::<Sha512, Tagged, WideReduction>(signature.clone(), &message));
+        assert!(!signature.verify_ref::<Sha512>(&message));
+
+        let ks: Vec<Scalar> = (0..2).map(|_| Scalar::random(&mut OsRng)).collect();
+        let ring: Vec<Vec<RistrettoPoint>> =
+            (0..2).map(|_| (0..2).map(|_| RistrettoPoint::random(&mut OsRng)).collect()).collect();
+        let signature = CLSAG::sign_with::<Sha512, OsRng, Tagged, WideReduction>(ks, ring, 1, &message).unwrap();
+        assert!(CLSAG::verify_with::<Sha512, Tagged, WideReduction>(signature.clone(), &message));
+        assert!(!CLSAG::verify_with::<Sha512, DefaultHashToPoint, WideReduction>(signature, &message));
+    }
+}
@@ -0,0 +1,93 @@
+//! Hash to scalar
+//!
+//! Every challenge, and the aggregation coefficients of CLSAG, reduce a 512-bit hash to a scalar.
+//! Host protocols that embed signatures may prescribe their own `hash_to_scalar`, so the `_with`
+//! functions of bLSAG, MLSAG and CLSAG take the reduction as a [`HashToScalar`] type parameter
+//! `Hs`, next to the [`crate::hash_to_point::HashToPoint`] one. `sign`, `verify` and the
+//! `compute_challenge_step` helpers use [`WideReduction`].
+
+use curve25519_dalek::scalar::Scalar;
+use digest::generic_array::typenum::U64;
+use digest::Digest;
+
+pub trait HashToScalar {
+    /// Finalizes `hash_state` into a scalar
+    fn hash_to_scalar<Hash: Digest<OutputSize = U64>>(hash_state: Hash) -> Scalar;
+}
+
+/// Reduces the whole 64 byte output modulo the group order, which is close to uniform
+#[derive(Clone, Copy, Debug, Default)]
+pub struct WideReduction;
+
+impl HashToScalar for WideReduction {
+    fn hash_to_scalar<Hash: Digest<OutputSize = U64>>(hash_state: Hash) -> Scalar {
+        return Scalar::from_hash(hash_state);
+    }
+}
+
+/// Keeps the first 32 bytes of the output and reduces them modulo the group order, as protocols
+/// with a 32 byte `hash_to_scalar` (e.g. Monero's `H_n` over Keccak-256) do
+#[derive(Clone, Copy, Debug, Default)]
+pub struct TruncatedReduction;
+
+impl HashToScalar for TruncatedReduction {
+    fn hash_to_scalar<Hash: Digest<OutputSize = U64>>(hash_state: Hash) -> Scalar {
+        let mut bytes = [0u8; 32];
+        bytes.copy_from_slice(&hash_state.finalize()[..32]);
+        return Scalar::from_bytes_mod_order(bytes);
+    }
+}
+
+#[cfg(test)]
+#[cfg(feature = "std")]
+#[cfg(feature = "sign")]
+#[cfg(all(feature = "blsag", feature = "clsag"))]
+mod test {
+    extern crate rand;
+    extern crate sha2;
+
+    use curve25519_dalek::ristretto::RistrettoPoint;
+    use rand::rngs::OsRng;
+    use sha2::Sha512;
+
+    use super::*;
+    use crate::blsag::BLSAG;
+    use crate::clsag::CLSAG;
+    use crate::hash_to_point::DefaultHashToPoint;
+    use crate::prelude::*;
+    use crate::traits::VerifyRef;
+
+    #[test]
+    fn hash_to_scalar() {
+        let message: Vec<u8> = b"This is the message".to_vec();
+        let hash = Sha512::default().chain_update(&message);
+        let mut truncated = [0u8; 32];
+        truncated.copy_from_slice(&hash.clone().finalize()[..32]);
+        assert_eq!(
+            TruncatedReduction::hash_to_scalar(hash),
+            Scalar::from_bytes_mod_order(truncated)
+        );
+
+        let k: Scalar = Scalar::random(&mut OsRng);
+        let ring: Vec<RistrettoPoint> = (0..2).map(|_| RistrettoPoint::random(&mut OsRng)).collect();
+        let signature =
+            BLSAG::sign_with::<Sha512, OsRng, DefaultHashToPoint, TruncatedReduction>(k, ring, 1, &message).unwrap();
+        assert!(BLSAG::verify_with::<Sha512, DefaultHashToPoint, TruncatedReduction>(
+            signature.clone(),
+            &message
+        ));
+        assert!(!signature.verify_ref::<Sha512>(&message));
+
+        let ks: Vec<Scalar> = (0..2).map(|_| Scalar::random(&mut OsRng)).collect();
+        let ring: Vec<Vec<RistrettoPoint>> = (0..2)
+            .map(|_| (0..2).map(|_| RistrettoPoint::random(&mut OsRng)).collect())
+            .collect();
+        let signature =
+            CLSAG::sign_with::<Sha512, OsRng, DefaultHashToPoint, TruncatedReduction>(ks, ring, 1, &message).unwrap();
+        assert!(CLSAG::verify_with::<Sha512, DefaultHashToPoint, TruncatedReduction>(
+            signature.clone(),
+            &message
+        ));
+        assert!(!signature.verify_ref::<Sha512>(&message));
+    }
+}
@@ -0,0 +1,163 @@
+//! Hashed rings
+//!
+//! Verifying a bLSAG hashes every ring member to a point, `H_p(K_i)`, each time. Services
+//! verifying many signatures over one fixed ring, e.g. a large anonymity set used by every voter
+//! of an election, can hash the ring once into a [`HashedRingTable`] and verify against it, which
+//! leaves the scalar multiplications. The `hashed-ring-bench` example measures the difference.
+//! With the `parallel` feature the hashes are computed across threads.
+//!
+//! A table is not modified after it is built, so one table can be shared between threads behind
+//! an `Arc`.
+
+use crate::prelude::*;
+
+use core::marker::PhantomData;
+
+use curve25519_dalek::constants;
+use curve25519_dalek::ristretto::RistrettoPoint;
+use curve25519_dalek::scalar::Scalar;
+use digest::generic_array::typenum::U64;
+use digest::Digest;
+
+use crate::blsag::{compute_challenge_prefix, compute_challenge_step_with, BLSAG};
+use crate::hash_to_point::{DefaultHashToPoint, HashToPoint};
+use crate::hash_to_scalar::WideReduction;
+use crate::traits::TryVerify;
+use crate::ops;
+
+/// A ring together with the hash to point of each of its members
+///
+/// Please read tests at the bottom of the source code for this module for examples on how to use
+/// it
+#[derive(Clone)]
+pub struct HashedRingTable<Hash> {
+    ring: Vec<RistrettoPoint>,
+    hashed: Vec<RistrettoPoint>,
+    // A `fn` so that the table is `Send` and `Sync` whatever `Hash` is
+    _hash: PhantomData<fn() -> Hash>,
+}
+
+impl<Hash: Digest<OutputSize = U64> + Clone + Default> HashedRingTable<Hash> {
+    /// Hashes the complete `ring`, the signer included
+    pub fn new(ring: Vec<RistrettoPoint>) -> HashedRingTable<Hash> {
+        return HashedRingTable::new_with::<DefaultHashToPoint>(ring);
+    }
+
+    /// As [`HashedRingTable::new`] with the hash to point `Hp`, for signatures made with
+    /// [`BLSAG::sign_with`]
+    pub fn new_with<Hp: HashToPoint>(ring: Vec<RistrettoPoint>) -> HashedRingTable<Hash> {
+        let hashed: Vec<RistrettoPoint> = hash_ring::<Hash, Hp>(&ring);
+        return HashedRingTable {
+            ring: ring,
+            hashed: hashed,
+            _hash: PhantomData,
+        };
+    }
+
+    pub fn ring(&self) -> &[RistrettoPoint] {
+        return &self.ring;
+    }
+
+    /// `H_p` of each ring member, in ring order
+    pub fn hashed(&self) -> &[RistrettoPoint] {
+        return &self.hashed;
+    }
+
+    /// Same as [`BLSAG::verify_ref`](crate::traits::VerifyRef::verify_ref), also requiring that
+    /// `signature` is over this ring
+    pub fn verify(&self, signature: &BLSAG, message: &Vec<u8>) -> bool {
+        if signature.ring != self.ring
+            || signature.check_shape().is_err()
+            || !signature.ring_digest_matches::<Hash>()
+        {
+            return false;
+        }
+        let message_hash: Hash = compute_challenge_prefix::<Hash>(message);
+        let mut reconstructed_c: Scalar = signature.challenge.0;
+        for ((response, k_point), hashed_k_point) in
+            signature.responses.iter().zip(self.ring.iter()).zip(self.hashed.iter())
+        {
+            reconstructed_c = compute_challenge_step_with::<Hash, WideReduction>(
+                message_hash.clone(),
+                &ops::multiscalar_mul(
+                    &[response.0, reconstructed_c],
+                    &[constants::RISTRETTO_BASEPOINT_POINT, *k_point],
+                ),
+                &ops::multiscalar_mul(
+                    &[response.0, reconstructed_c],
+                    &[*hashed_k_point, signature.key_image],
+                ),
+            );
+        }
+        return signature.challenge.0 == reconstructed_c;
+    }
+}
+
+#[cfg(not(feature = "parallel"))]
+fn hash_ring<Hash: Digest<OutputSize = U64> + Default, Hp: HashToPoint>(ring: &[RistrettoPoint]) -> Vec<RistrettoPoint> {
+    return ring.iter().map(|k_point| Hp::hash_to_point::<Hash>(k_point)).collect();
+}
+
+#[cfg(feature = "parallel")]
+fn hash_ring<Hash: Digest<OutputSize = U64> + Default, Hp: HashToPoint>(ring: &[RistrettoPoint]) -> Vec<RistrettoPoint> {
+    use rayon::prelude::*;
+
+    return ring.par_iter().map(|k_point| Hp::hash_to_point::<Hash>(k_point)).collect();
+}
+
+#[cfg(test)]
+#[cfg(feature = "std")]
+#[cfg(feature = "sign")]
+mod test {
+    extern crate rand;
+    extern crate sha2;
+
+    use std::sync::Arc;
+    use std::thread;
+
+    use rand::rngs::OsRng;
+    use sha2::Sha512;
+
+    use super::*;
+    use crate::traits::{SignWithRng, VerifyRef};
+
+    #[test]
+    fn hashed_ring_table() {
+        let ks: Vec<Scalar> = (0..3).map(|_| Scalar::random(&mut OsRng)).collect();
+        let mut ring: Vec<RistrettoPoint> = (0..5).map(|_| RistrettoPoint::random(&mut OsRng)).collect();
+        for (i, k) in ks.iter().enumerate() {
+            ring[i] = k * constants::RISTRETTO_BASEPOINT_POINT;
+        }
+        let table = Arc::new(HashedRingTable::<Sha512>::new(ring.clone()));
+
+        // Each thread verifies against the same table
+        let handles: Vec<thread::JoinHandle<()>> = ks
+            .into_iter()
+            .enumerate()
+            .map(|(i, k)| {
+                let table = Arc::clone(&table);
+                let mut decoys = table.ring().to_vec();
+                decoys.remove(i);
+                thread::spawn(move || {
+                    let message: Vec<u8> = b"This is the message".to_vec();
+                    let signature =
+                        BLSAG::sign_with_rng_unchecked::<Sha512, _>(k, decoys, i, &message, &mut OsRng).unwrap();
+                    assert_eq!(table.verify(&signature, &message), signature.verify_ref::<Sha512>(&message));
+                    assert!(table.verify(&signature, &message));
+                    assert!(!table.verify(&signature, &b"Another message".to_vec()));
+                })
+            })
+            .collect();
+        for handle in handles {
+            handle.join().unwrap();
+        }
+
+        // Signatures over another ring do not verify against the table
+        let k: Scalar = Scalar::random(&mut OsRng);
+        let message: Vec<u8> = b"This is the message".to_vec();
+        let signature =
+            BLSAG::sign_with_rng_unchecked::<Sha512, _>(k, ring[1..].to_vec(), 0, &message, &mut OsRng).unwrap();
+        assert!(signature.verify_ref::<Sha512>(&message));
+        assert!(!table.verify(&signature, &message));
+    }
+}
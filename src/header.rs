@@ -0,0 +1,226 @@
+//! Envelope headers
+//!
+//! A [`Header`] declares the scheme, hash function and shape of the signature body that follows
+//! it. [`verify_structure_against_params`] checks that a header is one the receiver accepts and
+//! that the lengths encoded in the body agree with it, looking only at lengths so that a gateway
+//! can drop malformed or oversized signatures before any scalar or point is decoded.
+
+use crate::prelude::*;
+
+use crate::codec::{self, Codec};
+use crate::error::Error;
+
+/// The scheme of an encoded signature body, as written in a [`Header`]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SchemeId {
+    SAG = 1,
+    LSAG = 2,
+    BLSAG = 3,
+    MLSAG = 4,
+    CLSAG = 5,
+    DLSAG = 6,
+    MDLSAG = 7,
+}
+
+impl SchemeId {
+    pub fn from_u8(id: u8) -> Option<SchemeId> {
+        return match id {
+            1 => Some(SchemeId::SAG),
+            2 => Some(SchemeId::LSAG),
+            3 => Some(SchemeId::BLSAG),
+            4 => Some(SchemeId::MLSAG),
+            5 => Some(SchemeId::CLSAG),
+            6 => Some(SchemeId::DLSAG),
+            7 => Some(SchemeId::MDLSAG),
+            _ => None,
+        };
+    }
+
+    /// Whether each ring member is a single key, in which case the ring has exactly one column
+    pub(crate) fn single_key(&self) -> bool {
+        return match self {
+            SchemeId::SAG | SchemeId::LSAG | SchemeId::BLSAG | SchemeId::DLSAG => true,
+            SchemeId::MLSAG | SchemeId::CLSAG | SchemeId::MDLSAG => false,
+        };
+    }
+
+    /// The length of the canonical encoding of a signature over `ring_size` members of `columns`
+    /// keys each
+    pub(crate) fn encoded_len(&self, ring_size: usize, columns: usize) -> Option<usize> {
+        let keys = ring_size.checked_mul(columns)?;
+        let key_images = columns.checked_mul(32)?;
+        let len = match self {
+            SchemeId::SAG => keys.checked_mul(64)?.checked_add(36)?,
+            SchemeId::LSAG | SchemeId::BLSAG => keys.checked_mul(64)?.checked_add(68)?,
+            SchemeId::DLSAG => keys.checked_mul(128)?.checked_add(69)?,
+            SchemeId::MLSAG => keys.checked_mul(64)?.checked_add(key_images)?.checked_add(40)?,
+            SchemeId::CLSAG => keys
+                .checked_add(ring_size)?
+                .checked_mul(32)?
+                .checked_add(key_images)?
+                .checked_add(40)?,
+            SchemeId::MDLSAG => keys.checked_mul(128)?.checked_add(key_images)?.checked_add(41)?,
+        };
+        return Some(len);
+    }
+}
+
+/// What an envelope declares about the signature body it carries
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Header {
+    pub scheme: SchemeId,
+    /// Identifies the hash function, as agreed between sender and receiver
+    pub hash_id: u8,
+    pub ring_size: usize,
+    /// Keys per ring member, 1 for schemes over single keys
+    pub columns: usize,
+}
+
+impl Codec for Header {
+    fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes: Vec<u8> = vec![self.scheme as u8, self.hash_id];
+        codec::write_u32(&mut bytes, self.ring_size);
+        codec::write_u32(&mut bytes, self.columns);
+        return bytes;
+    }
+
+    /// Decodes a header, returning [`Error::UnknownScheme`] for unknown schemes
+    fn decode(bytes: &[u8]) -> Result<Header, Error> {
+        if bytes.len() < 10 {
+            return Err(Error::Truncated);
+        }
+        if bytes.len() > 10 {
+            return Err(Error::TrailingBytes);
+        }
+        return Ok(Header {
+            scheme: SchemeId::from_u8(bytes[0]).ok_or(Error::UnknownScheme { tag: bytes[0] })?,
+            hash_id: bytes[1],
+            ring_size: read_u32(bytes, 2).ok_or(Error::Truncated)?,
+            columns: read_u32(bytes, 6).ok_or(Error::Truncated)?,
+        });
+    }
+}
+
+/// What a receiver accepts
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Params {
+    pub scheme: SchemeId,
+    pub hash_id: u8,
+    /// The largest ring worth verifying
+    pub max_ring_size: usize,
+}
+
+pub(crate) fn read_u32(bytes: &[u8], offset: usize) -> Option<usize> {
+    let mut array = [0u8; 4];
+    array.copy_from_slice(bytes.get(offset..offset + 4)?);
+    return Some(u32::from_le_bytes(array) as usize);
+}
+
+/// Checks that `header` matches `params` and that `body`, the canonical encoding of a signature,
+/// has exactly the shape `header` declares
+///
+/// Only lengths are read, so a `body` passing this check may still fail to decode or to verify.
+pub fn verify_structure_against_params(header: &Header, body: &[u8], params: &Params) -> bool {
+    if header.scheme != params.scheme || header.hash_id != params.hash_id {
+        return false;
+    }
+    if header.ring_size == 0 || header.ring_size > params.max_ring_size || header.columns == 0 {
+        return false;
+    }
+    // Every encoding starts with the challenge followed by the lengths
+    let declared = if header.scheme.single_key() {
+        read_u32(body, 32).map(|ring_size| (ring_size, 1))
+    } else {
+        read_u32(body, 32).and_then(|ring_size| Some((ring_size, read_u32(body, 36)?)))
+    };
+    if declared != Some((header.ring_size, header.columns)) {
+        return false;
+    }
+    let len = header.scheme.encoded_len(header.ring_size, header.columns);
+    // A bLSAG may end in the digest of its ring
+    if header.scheme == SchemeId::BLSAG && len.and_then(|len| len.checked_add(64)) == Some(body.len()) {
+        return true;
+    }
+    return len == Some(body.len());
+}
+
+#[cfg(test)]
+#[cfg(feature = "std")]
+#[cfg(feature = "sign")]
+#[cfg(all(feature = "blsag", feature = "mlsag"))]
+mod test {
+    extern crate rand;
+    extern crate sha2;
+
+    use curve25519_dalek::ristretto::RistrettoPoint;
+    use curve25519_dalek::scalar::Scalar;
+    use rand::rngs::OsRng;
+    use sha2::Sha512;
+
+    use super::*;
+    use crate::blsag::BLSAG;
+    use crate::mlsag::MLSAG;
+    use crate::traits::SignWithRng;
+
+    #[test]
+    fn header() {
+        let message: Vec<u8> = b"This is the message".to_vec();
+        let params = Params {
+            scheme: SchemeId::BLSAG,
+            hash_id: 1,
+            max_ring_size: 4,
+        };
+
+        let k: Scalar = Scalar::random(&mut OsRng);
+        let ring: Vec<RistrettoPoint> = (0..2).map(|_| RistrettoPoint::random(&mut OsRng)).collect();
+        let body = BLSAG::sign_with_rng_unchecked::<Sha512, _>(k, ring, 1, &message, &mut OsRng).unwrap().to_bytes();
+        let header = Header {
+            scheme: SchemeId::BLSAG,
+            hash_id: 1,
+            ring_size: 3,
+            columns: 1,
+        };
+        assert_eq!(Header::from_bytes(&header.to_bytes()), Some(header));
+        assert!(verify_structure_against_params(&header, &body, &params));
+        let with_digest = BLSAG::from_bytes(&body).unwrap().attach_ring_digest::<Sha512>().to_bytes();
+        assert!(verify_structure_against_params(&header, &with_digest, &params));
+
+        // Truncated body, wrong declared ring size, scheme or hash, or a ring larger than accepted
+        assert!(!verify_structure_against_params(
+            &header,
+            &body[..body.len() - 1],
+            &params
+        ));
+        let mut lying = header;
+        lying.ring_size = 2;
+        assert!(!verify_structure_against_params(&lying, &body, &params));
+        lying = header;
+        lying.hash_id = 2;
+        assert!(!verify_structure_against_params(&lying, &body, &params));
+        lying = header;
+        lying.scheme = SchemeId::LSAG;
+        assert!(!verify_structure_against_params(&lying, &body, &params));
+        let small = Params {
+            max_ring_size: 2,
+            ..params
+        };
+        assert!(!verify_structure_against_params(&header, &body, &small));
+
+        let ks: Vec<Scalar> = (0..2).map(|_| Scalar::random(&mut OsRng)).collect();
+        let ring: Vec<Vec<RistrettoPoint>> = (0..2)
+            .map(|_| (0..2).map(|_| RistrettoPoint::random(&mut OsRng)).collect())
+            .collect();
+        let body = MLSAG::sign_with_rng_unchecked::<Sha512, _>(ks, ring, 1, &message, &mut OsRng).unwrap().to_bytes();
+        let header = Header {
+            scheme: SchemeId::MLSAG,
+            hash_id: 1,
+            ring_size: 3,
+            columns: 2,
+        };
+        let params = Params {
+            scheme: SchemeId::MLSAG,
+            ..params
+        };
+        assert!(verify_structure_against_params(&header, &body, &params));
+    }
+}
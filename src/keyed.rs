@@ -0,0 +1,127 @@
+//! Keyed and personalized hash functions
+//!
+//! The schemes start every hash from `Hash::default()`, which leaves no room for a key or a
+//! personalization string, e.g. BLAKE2b keyed per deployment. A [`HasherBuilder`] says how to
+//! start the hash instead, and [`Keyed`] uses it wherever a hash function is expected:
+//!
+//! ```ignore
+//! struct Deployment;
+//!
+//! impl HasherBuilder for Deployment {
+//!     type Hasher = Blake2bMac512;
+//!
+//!     fn build() -> Blake2bMac512 {
+//!         return Blake2bMac512::new_with_salt_and_personal(KEY, b"", b"nazgul-votes").unwrap();
+//!     }
+//! }
+//!
+//! let signature = BLSAG::sign_with_rng::<Keyed<Deployment>, _>(k, ring, 1, &message, &mut OsRng)?;
+//! ```
+//!
+//! The builder is a type, so the key is fixed when the crate using it is built, as a key per
+//! deployment is. Signatures made with one builder do not verify with another.
+
+use core::marker::PhantomData;
+
+use digest::generic_array::typenum::U64;
+use digest::{FixedOutput, Update};
+
+/// Starts the hash state of [`Keyed`]
+pub trait HasherBuilder {
+    type Hasher: Update + FixedOutput<OutputSize = U64> + Clone;
+
+    fn build() -> Self::Hasher;
+}
+
+/// The hash function of `B`, started from `B::build()` in place of `Default`
+pub struct Keyed<B: HasherBuilder> {
+    hasher: B::Hasher,
+    builder: PhantomData<fn() -> B>,
+}
+
+impl<B: HasherBuilder> Default for Keyed<B> {
+    fn default() -> Keyed<B> {
+        return Keyed {
+            hasher: B::build(),
+            builder: PhantomData,
+        };
+    }
+}
+
+impl<B: HasherBuilder> Clone for Keyed<B> {
+    fn clone(&self) -> Keyed<B> {
+        return Keyed {
+            hasher: self.hasher.clone(),
+            builder: PhantomData,
+        };
+    }
+}
+
+impl<B: HasherBuilder> digest::OutputSizeUser for Keyed<B> {
+    type OutputSize = U64;
+}
+
+impl<B: HasherBuilder> Update for Keyed<B> {
+    fn update(&mut self, data: &[u8]) {
+        self.hasher.update(data);
+    }
+}
+
+impl<B: HasherBuilder> FixedOutput for Keyed<B> {
+    fn finalize_into(self, out: &mut digest::Output<Self>) {
+        self.hasher.finalize_into(out);
+    }
+}
+
+impl<B: HasherBuilder> digest::HashMarker for Keyed<B> {}
+
+#[cfg(test)]
+#[cfg(feature = "std")]
+#[cfg(feature = "sign")]
+#[cfg(feature = "blsag")]
+mod test {
+    extern crate blake2;
+    extern crate rand;
+
+    use blake2::{Blake2b512, Blake2bMac512};
+    use curve25519_dalek::ristretto::RistrettoPoint;
+    use curve25519_dalek::scalar::Scalar;
+    use rand::rngs::OsRng;
+
+    use super::*;
+    use crate::blsag::BLSAG;
+    use crate::prelude::*;
+    use crate::traits::{SignWithRng, VerifyRef};
+
+    struct Votes;
+
+    impl HasherBuilder for Votes {
+        type Hasher = Blake2bMac512;
+
+        fn build() -> Blake2bMac512 {
+            return Blake2bMac512::new_with_salt_and_personal(b"deployment key", b"", b"nazgul-votes").unwrap();
+        }
+    }
+
+    struct Claims;
+
+    impl HasherBuilder for Claims {
+        type Hasher = Blake2bMac512;
+
+        fn build() -> Blake2bMac512 {
+            return Blake2bMac512::new_with_salt_and_personal(b"deployment key", b"", b"nazgul-claims").unwrap();
+        }
+    }
+
+    #[test]
+    fn keyed() {
+        let k: Scalar = Scalar::random(&mut OsRng);
+        let ring: Vec<RistrettoPoint> = (0..2).map(|_| RistrettoPoint::random(&mut OsRng)).collect();
+        let message: Vec<u8> = b"This is the message".to_vec();
+
+        let signature = BLSAG::sign_with_rng_unchecked::<Keyed<Votes>, _>(k, ring, 1, &message, &mut OsRng).unwrap();
+        assert!(signature.verify_ref::<Keyed<Votes>>(&message));
+        assert!(!signature.verify_ref::<Keyed<Claims>>(&message));
+        assert!(!signature.verify_ref::<Blake2b512>(&message));
+    }
+}
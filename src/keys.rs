@@ -0,0 +1,173 @@
+//! Key types
+//!
+//! The schemes take and return raw `Scalar`s and `RistrettoPoint`s. Applications that persist
+//! keys and key images can wrap them in [`PublicKey`], [`PrivateKey`] and [`KeyImage`], which
+//! have the same canonical 32 byte encoding as the fields of a signature (see [`crate::codec`])
+//! and reject more on decoding: the identity as a public key or key image, and zero as a private
+//! key, since none of them can come from an honest key pair.
+//!
+//! With the `serde` feature they serialize as their canonical encoding, as lowercase hex in
+//! human readable formats. Public keys and key images display as the same hex, see
+//! [`crate::encoding`].
+
+use crate::prelude::*;
+
+use curve25519_dalek::constants;
+use curve25519_dalek::ristretto::RistrettoPoint;
+use curve25519_dalek::scalar::Scalar;
+use curve25519_dalek::traits::IsIdentity;
+
+use crate::codec::{self, Codec, Reader};
+use crate::error::Error;
+use crate::link;
+
+/// A public key `K = k G`
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct PublicKey(pub RistrettoPoint);
+
+/// A private key `k`. It is deliberately not `Debug`, so it does not end up in logs
+#[derive(Clone, PartialEq, Eq)]
+pub struct PrivateKey(pub Scalar);
+
+/// A key image as a point, see [`crate::link::KeyImage`] for its compressed form
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct KeyImage(pub RistrettoPoint);
+
+impl PrivateKey {
+    pub fn public_key(&self) -> PublicKey {
+        return PublicKey(self.0 * constants::RISTRETTO_BASEPOINT_POINT);
+    }
+}
+
+impl KeyImage {
+    /// The compressed encoding, as kept in a spent set
+    pub fn compressed(&self) -> link::KeyImage {
+        return link::to_key_image(&self.0);
+    }
+}
+
+/// Reads a single point that is not the identity
+fn read_non_identity_point(bytes: &[u8]) -> Result<RistrettoPoint, Error> {
+    let mut reader = Reader::new(bytes);
+    let point = reader.read_point()?;
+    reader.finish()?;
+    if point.is_identity() {
+        return Err(Error::Malformed);
+    }
+    return Ok(point);
+}
+
+impl Codec for PublicKey {
+    fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes: Vec<u8> = Vec::new();
+        codec::write_point(&mut bytes, &self.0);
+        return bytes;
+    }
+
+    /// Decodes a public key, returning [`Error::Malformed`] for the identity
+    fn decode(bytes: &[u8]) -> Result<PublicKey, Error> {
+        return read_non_identity_point(bytes).map(PublicKey);
+    }
+}
+
+crate::encoding::display_via_codec!(PublicKey);
+
+impl Codec for PrivateKey {
+    fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes: Vec<u8> = Vec::new();
+        codec::write_scalar(&mut bytes, &self.0);
+        return bytes;
+    }
+
+    /// Decodes a private key, returning [`Error::Malformed`] for zero
+    fn decode(bytes: &[u8]) -> Result<PrivateKey, Error> {
+        let mut reader = Reader::new(bytes);
+        let k = reader.read_scalar()?;
+        reader.finish()?;
+        if k == Scalar::ZERO {
+            return Err(Error::Malformed);
+        }
+        return Ok(PrivateKey(k));
+    }
+}
+
+impl Codec for KeyImage {
+    fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes: Vec<u8> = Vec::new();
+        codec::write_point(&mut bytes, &self.0);
+        return bytes;
+    }
+
+    /// Decodes a key image, returning [`Error::Malformed`] for the identity
+    fn decode(bytes: &[u8]) -> Result<KeyImage, Error> {
+        return read_non_identity_point(bytes).map(KeyImage);
+    }
+}
+
+crate::encoding::display_via_codec!(KeyImage);
+
+#[cfg(feature = "serde")]
+codec::serde_via_codec!(PublicKey);
+#[cfg(feature = "serde")]
+codec::serde_via_codec!(PrivateKey);
+#[cfg(feature = "serde")]
+codec::serde_via_codec!(KeyImage);
+
+#[cfg(test)]
+#[cfg(feature = "std")]
+#[cfg(feature = "sign")]
+#[cfg(feature = "blsag")]
+mod test {
+    extern crate rand;
+    extern crate sha2;
+
+    use curve25519_dalek::traits::Identity;
+    use rand::rngs::OsRng;
+    use sha2::Sha512;
+
+    use super::*;
+    use crate::blsag::BLSAG;
+    use crate::traits::KeyImageGenRef;
+
+    #[test]
+    fn keys() {
+        let private_key = PrivateKey(Scalar::random(&mut OsRng));
+        let public_key = private_key.public_key();
+        let key_image = KeyImage(BLSAG::generate_key_image_ref::<Sha512>(&private_key.0));
+
+        assert!(PrivateKey::from_bytes(&private_key.to_bytes()) == Some(private_key.clone()));
+        assert_eq!(PublicKey::from_bytes(&public_key.to_bytes()), Some(public_key));
+        assert_eq!(KeyImage::from_bytes(&key_image.to_bytes()), Some(key_image));
+        assert_eq!(key_image.compressed().to_vec(), key_image.to_bytes());
+
+        // Values no honest key pair produces are rejected
+        assert!(PrivateKey::from_bytes(&Scalar::ZERO.to_bytes()).is_none());
+        assert!(PrivateKey::from_bytes(&[0xff; 32]).is_none());
+        let identity = PublicKey(RistrettoPoint::identity()).to_bytes();
+        assert!(PublicKey::from_bytes(&identity).is_none());
+        assert!(KeyImage::from_bytes(&identity).is_none());
+        assert!(PublicKey::from_bytes(&public_key.to_bytes()[1..]).is_none());
+
+        #[cfg(feature = "serde")]
+        {
+            use serde::de::value::{Error, StrDeserializer};
+            use serde::Deserialize;
+
+            use crate::encoding::encode_hex;
+
+            let hex = encode_hex(&public_key.to_bytes());
+            assert_eq!(
+                PublicKey::deserialize(StrDeserializer::<Error>::new(&hex)).ok(),
+                Some(public_key)
+            );
+            let upper = hex.to_uppercase();
+            assert_eq!(
+                PublicKey::deserialize(StrDeserializer::<Error>::new(&upper)).ok(),
+                Some(public_key)
+            );
+            let identity = encode_hex(&identity);
+            assert!(KeyImage::deserialize(StrDeserializer::<Error>::new(&identity)).is_err());
+            assert!(PublicKey::deserialize(StrDeserializer::<Error>::new(&hex[1..])).is_err());
+        }
+    }
+}
@@ -2,6 +2,7 @@
 //! A library that implements [Ring Signatures](https://en.wikipedia.org/wiki/Ring_signature). The following schemes have been implemented based on [Chapter 3 of Zero to Monero 2.0 (Z2M2)](https://www.getmonero.org/library/Zero-to-Monero-2-0-0.pdf):
 //!
 //!  - Spontaneous Anonymous Group (SAG) signatures
+//!  - Linkable Spontaneous Anonymous Group (LSAG) signatures, as originally proposed by Liu, Wei and Wong
 //!  - Back’s Linkable Spontaneous Anonymous Group (bLSAG) signatures
 //!  - Multilayer Linkable Spontaneous Anonymous Group (MLSAG) signatures
 //!  - Concise Linkable Spontaneous Anonymous Group (CLSAG) signatures
@@ -16,27 +17,205 @@
 //! This library is designed to work with any 512-bit (64 byte output) hashing function. It uses the
 //! [Ristretto elliptic curve](https://doc.dalek.rs/curve25519_dalek/ristretto/) for ease of use and better security.
 //!
+//! The schemes are implemented over Ristretto only. With the `group` feature, `RistrettoPoint` and `Scalar` implement the
+//! `group::Group` and `ff::PrimeField` traits, so rings and keys can be handed to code from the zkcrypto ecosystem.
+//!
 //! This library is `#![no_std]` by default so it is possible to compile this library for embedded devices and WebAssembly but we haven't tried.
+//!
+//! It only needs `alloc`. Build with `default-features = false` for targets without `std`; the default `std` feature is purely additive
+//! (it enables precomputed basepoint tables among other things). The old `no_std` feature enables `sign` and every scheme, so
+//! builds enabling it keep everything they had.
+//!
+//! Every scheme has a feature of the same name (`sag`, `lsag`, `blsag`, `mlsag`, `clsag`, `dlsag` and `mdlsag`), all enabled by
+//! default. To carry only the schemes you use, disable default features and list them, e.g.
+//! `default-features = false, features = ["std", "blsag"]`.
+//!
+//! Signing, key image proofs and everything else drawing randomness are behind the default `sign` feature. Validators
+//! that only parse and verify signatures can leave it out, which also drops the `rand_core` dependency, e.g.
+//! `default-features = false, features = ["std", "clsag"]`.
 
 #![no_std]
 
-#[cfg(all(feature = "no_std", not(feature = "std")))]
+// Only `alloc` is needed by the schemes, so it is always used. `std` only adds to it.
 #[macro_use]
 extern crate alloc;
 
 #[cfg(feature = "std")]
-#[macro_use]
 extern crate std;
 
 extern crate curve25519_dalek;
 extern crate digest;
+#[cfg(feature = "sign")]
 extern crate rand_core;
+#[cfg(feature = "parallel")]
+extern crate rayon;
+#[cfg(feature = "quick")]
+extern crate sha2;
+#[cfg(feature = "digest011")]
+extern crate digest011;
+#[cfg(feature = "rand_core09")]
+extern crate rand_core09;
+#[cfg(feature = "serde")]
+extern crate serde;
+#[cfg(feature = "signature2")]
+extern crate signature2;
+#[cfg(feature = "allocator-api")]
+extern crate allocator_api2;
 
+pub mod analysis;
+#[cfg(feature = "allocator-api")]
+pub mod arena;
+#[cfg(feature = "std")]
+pub mod audit;
+#[cfg(all(
+    feature = "sign",
+    any(
+        feature = "sag",
+        feature = "lsag",
+        feature = "blsag",
+        feature = "mlsag",
+        feature = "clsag",
+        feature = "dlsag",
+        feature = "mdlsag"
+    )
+))]
+pub mod batch;
+pub mod beacon;
+#[cfg(all(feature = "std", feature = "sign", feature = "mlsag"))]
+pub mod bench;
+#[cfg(feature = "blsag")]
 pub mod blsag;
+pub mod builder;
+#[cfg(feature = "blsag")]
+pub mod claim;
+#[cfg(feature = "clsag")]
 pub mod clsag;
+pub mod codec;
+#[cfg(feature = "blsag")]
+pub mod commitment;
+pub mod compat;
+#[cfg(feature = "blsag")]
+pub mod detached;
+#[cfg(feature = "dlsag")]
 pub mod dlsag;
+pub mod dual;
+pub mod encoding;
+pub mod error;
+pub mod fragment;
+pub mod generator;
+pub mod hash_to_point;
+pub mod hash_to_scalar;
+#[cfg(feature = "blsag")]
+pub mod hashed_ring;
+pub mod header;
+pub mod keyed;
+pub mod keys;
+pub mod link;
+#[cfg(feature = "lsag")]
+pub mod lsag;
+#[cfg(feature = "mdlsag")]
 pub mod mdlsag;
+pub mod merkle_ring;
+pub mod message;
+#[cfg(all(feature = "sign", feature = "mlsag", feature = "clsag"))]
+pub mod migration;
+#[cfg(feature = "mlsag")]
 pub mod mlsag;
+#[cfg(all(
+    feature = "sign",
+    any(
+        feature = "sag",
+        feature = "lsag",
+        feature = "blsag",
+        feature = "mlsag",
+        feature = "clsag",
+        feature = "dlsag",
+        feature = "mdlsag"
+    )
+))]
+pub mod nonce;
+pub mod partial;
+pub mod policy;
+#[cfg(any(
+    feature = "sag",
+    feature = "lsag",
+    feature = "blsag",
+    feature = "mlsag",
+    feature = "clsag",
+    feature = "dlsag",
+    feature = "mdlsag"
+))]
+pub mod precheck;
+#[cfg(feature = "profile")]
+pub mod profile;
+pub mod proofs;
+#[cfg(feature = "quick")]
+pub mod quick;
+pub mod registry;
+pub mod replay;
+pub mod ring;
+#[cfg(feature = "wasm")]
+pub mod ring_buffer;
+#[cfg(feature = "async")]
+pub mod ring_stream;
+pub mod ring_view;
+#[cfg(feature = "sag")]
 pub mod sag;
+pub mod sanity;
+pub mod scalars;
+#[cfg(any(
+    feature = "sag",
+    feature = "lsag",
+    feature = "blsag",
+    feature = "mlsag",
+    feature = "clsag",
+    feature = "dlsag",
+    feature = "mdlsag"
+))]
+pub mod scheme_registry;
+pub mod session;
+#[cfg(any(
+    feature = "sag",
+    feature = "lsag",
+    feature = "blsag",
+    feature = "mlsag",
+    feature = "clsag",
+    feature = "dlsag",
+    feature = "mdlsag"
+))]
+pub mod signature;
+#[cfg(all(feature = "signature2", feature = "sign", any(feature = "sag", feature = "blsag")))]
+pub mod signer;
+pub mod stream;
+pub mod suite;
+pub mod testing;
 pub mod traits;
+#[cfg(any(
+    feature = "sag",
+    feature = "lsag",
+    feature = "blsag",
+    feature = "mlsag",
+    feature = "clsag",
+    feature = "dlsag",
+    feature = "mdlsag"
+))]
+pub mod versioned;
+pub mod watermark;
+pub mod weight;
+#[cfg(feature = "blsag")]
+pub mod weighted;
+pub(crate) mod ops;
 pub(crate) mod prelude;
+#[cfg(any(feature = "mlsag", feature = "clsag", feature = "mdlsag"))]
+pub(crate) mod scratch;
+
+#[cfg(any(
+    feature = "sag",
+    feature = "lsag",
+    feature = "blsag",
+    feature = "mlsag",
+    feature = "clsag",
+    feature = "dlsag",
+    feature = "mdlsag"
+))]
+pub use signature::parse;
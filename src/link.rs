@@ -0,0 +1,377 @@
+//! Spent set lookups
+//!
+//! Validators keep the key images of every spent output and check each new key image against
+//! them. [`batch_contains`] answers that for a whole block at once against a sorted spent set,
+//! spreading the binary searches across threads with the `parallel` feature.
+//!
+//! Lookups that go through a [`KeyImageStore`] report a [`LinkOutcome`], so that a store which
+//! can answer falsely positive, such as a Bloom filter kept in front of the exact set, reports
+//! [`LinkOutcome::PossiblyLinked`] and the caller confirms against the exact set.
+//!
+//! [`Linkable`] gives the key images of a signature of any linkable scheme, so that such checks
+//! can be written once for all of them. [`link_all`] finds every linked pair among many
+//! signatures, as vote tallies and double-spend scanners need.
+
+use crate::prelude::*;
+
+use curve25519_dalek::ristretto::RistrettoPoint;
+use digest::generic_array::typenum::U64;
+use digest::Digest;
+
+#[cfg(feature = "blsag")]
+use crate::blsag::BLSAG;
+#[cfg(feature = "clsag")]
+use crate::clsag::CLSAG;
+#[cfg(feature = "dlsag")]
+use crate::dlsag::DLSAG;
+use crate::error::Error;
+#[cfg(feature = "lsag")]
+use crate::lsag::LSAG;
+#[cfg(feature = "mdlsag")]
+use crate::mdlsag::MDLSAG;
+#[cfg(feature = "mlsag")]
+use crate::mlsag::MLSAG;
+use crate::traits::{LinkRef, VerifyRef};
+
+/// A key image as its compressed encoding, which orders and compares like the point itself
+pub type KeyImage = [u8; 32];
+
+/// The compressed encoding of `key_image`, as kept in a spent set
+pub fn to_key_image(key_image: &RistrettoPoint) -> KeyImage {
+    return key_image.compress().to_bytes();
+}
+
+/// Linkable signatures, identified by the key image they are linked by
+pub trait LinkingKeyImage {
+    /// The key image of the signer. For MLSAG and MDLSAG, which link by any shared key image,
+    /// this is the key image of the first column. `None` for a signature without key images,
+    /// which only a malformed one is.
+    fn linking_key_image(&self) -> Option<KeyImage>;
+}
+
+#[cfg(feature = "lsag")]
+impl LinkingKeyImage for LSAG {
+    fn linking_key_image(&self) -> Option<KeyImage> {
+        return Some(to_key_image(&self.key_image));
+    }
+}
+
+#[cfg(feature = "blsag")]
+impl LinkingKeyImage for BLSAG {
+    fn linking_key_image(&self) -> Option<KeyImage> {
+        return Some(to_key_image(&self.key_image));
+    }
+}
+
+#[cfg(feature = "mlsag")]
+impl LinkingKeyImage for MLSAG {
+    fn linking_key_image(&self) -> Option<KeyImage> {
+        return self.key_images.first().map(to_key_image);
+    }
+}
+
+#[cfg(feature = "clsag")]
+impl LinkingKeyImage for CLSAG {
+    fn linking_key_image(&self) -> Option<KeyImage> {
+        return self.key_images.first().map(to_key_image);
+    }
+}
+
+#[cfg(feature = "dlsag")]
+impl LinkingKeyImage for DLSAG {
+    fn linking_key_image(&self) -> Option<KeyImage> {
+        return Some(to_key_image(&self.key_image));
+    }
+}
+
+#[cfg(feature = "mdlsag")]
+impl LinkingKeyImage for MDLSAG {
+    fn linking_key_image(&self) -> Option<KeyImage> {
+        return self.key_images.first().map(to_key_image);
+    }
+}
+
+/// Signatures carrying key images, for code that checks them against a blocklist or spent set
+/// whatever the scheme
+pub trait Linkable {
+    /// Every key image of the signature. LSAG, bLSAG and DLSAG have one; MLSAG, CLSAG and MDLSAG
+    /// have one per column. MLSAG and MDLSAG link by any of them, CLSAG by the first
+    fn key_images(&self) -> &[RistrettoPoint];
+}
+
+#[cfg(feature = "lsag")]
+impl Linkable for LSAG {
+    fn key_images(&self) -> &[RistrettoPoint] {
+        return core::slice::from_ref(&self.key_image);
+    }
+}
+
+#[cfg(feature = "blsag")]
+impl Linkable for BLSAG {
+    fn key_images(&self) -> &[RistrettoPoint] {
+        return core::slice::from_ref(&self.key_image);
+    }
+}
+
+#[cfg(feature = "mlsag")]
+impl Linkable for MLSAG {
+    fn key_images(&self) -> &[RistrettoPoint] {
+        return &self.key_images;
+    }
+}
+
+#[cfg(feature = "clsag")]
+impl Linkable for CLSAG {
+    fn key_images(&self) -> &[RistrettoPoint] {
+        return &self.key_images;
+    }
+}
+
+#[cfg(feature = "dlsag")]
+impl Linkable for DLSAG {
+    fn key_images(&self) -> &[RistrettoPoint] {
+        return core::slice::from_ref(&self.key_image);
+    }
+}
+
+#[cfg(feature = "mdlsag")]
+impl Linkable for MDLSAG {
+    fn key_images(&self) -> &[RistrettoPoint] {
+        return &self.key_images;
+    }
+}
+
+/// Every pair `(i, j)` with `i < j` of `signatures` that are linked, as [`LinkRef::link_ref`]
+/// decides, in order
+///
+/// Signatures are grouped by key image by sorting, so only those sharing one are compared. Linked
+/// signatures always share a key image, though LSAGs also need the same ring and CLSAGs the same
+/// first key image, which `link_ref` then checks.
+pub fn link_all<S: Linkable + LinkRef>(signatures: &[S]) -> Vec<(usize, usize)> {
+    let mut key_images: Vec<(KeyImage, usize)> = signatures
+        .iter()
+        .enumerate()
+        .flat_map(|(index, signature)| {
+            signature.key_images().iter().map(move |key_image| (to_key_image(key_image), index))
+        })
+        .collect();
+    key_images.sort_unstable();
+
+    let mut candidates: Vec<(usize, usize)> = Vec::new();
+    for group in key_images.chunk_by(|a, b| a.0 == b.0) {
+        for (position, (_, i)) in group.iter().enumerate() {
+            for (_, j) in &group[position + 1..] {
+                // The same signature twice, if it repeats a key image
+                if i != j {
+                    candidates.push((*i, *j));
+                }
+            }
+        }
+    }
+    candidates.sort_unstable();
+    candidates.dedup();
+    candidates.retain(|(i, j)| signatures[*i].link_ref(&signatures[*j]));
+    return candidates;
+}
+
+/// Whether a key image was found in a store of spent key images
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum LinkOutcome {
+    /// The key image is in the store
+    Linked,
+    /// The key image may be in the store, which can answer falsely positive. Confirm it with an
+    /// exact store.
+    PossiblyLinked,
+    /// The key image is not in the store
+    Unlinked,
+}
+
+impl LinkOutcome {
+    /// An exact answer of whether the key image is in the store
+    pub fn exact(contains: bool) -> LinkOutcome {
+        if contains {
+            return LinkOutcome::Linked;
+        }
+        return LinkOutcome::Unlinked;
+    }
+}
+
+/// A store of spent key images
+pub trait KeyImageStore {
+    fn outcome(&self, key_image: &KeyImage) -> LinkOutcome;
+}
+
+/// A sorted spent set, as for [`batch_contains`], which answers exactly
+impl KeyImageStore for [KeyImage] {
+    fn outcome(&self, key_image: &KeyImage) -> LinkOutcome {
+        return LinkOutcome::exact(self.binary_search(key_image).is_ok());
+    }
+}
+
+impl KeyImageStore for Vec<KeyImage> {
+    fn outcome(&self, key_image: &KeyImage) -> LinkOutcome {
+        return self.as_slice().outcome(key_image);
+    }
+}
+
+/// Verifies `signature` and looks its key image up in `store`
+///
+/// Returns [`Error::InvalidSignature`] if `signature` does not verify, without looking it up.
+pub fn verify_and_check<Hash, S, Store>(signature: &S, message: &Vec<u8>, store: &Store) -> Result<LinkOutcome, Error>
+where
+    Hash: Digest<OutputSize = U64> + Clone + Default,
+    S: VerifyRef + LinkingKeyImage,
+    Store: KeyImageStore + ?Sized,
+{
+    if !signature.verify_ref::<Hash>(message) {
+        return Err(Error::InvalidSignature);
+    }
+    return match signature.linking_key_image() {
+        Some(key_image) => Ok(store.outcome(&key_image)),
+        None => Err(Error::InvalidSignature),
+    };
+}
+
+/// For each of `key_images`, whether it is in `sorted_spent_images`, which must be sorted in
+/// ascending order
+#[cfg(not(feature = "parallel"))]
+pub fn batch_contains(sorted_spent_images: &[KeyImage], key_images: &[KeyImage]) -> Vec<bool> {
+    return key_images
+        .iter()
+        .map(|key_image| sorted_spent_images.binary_search(key_image).is_ok())
+        .collect();
+}
+
+/// For each of `key_images`, whether it is in `sorted_spent_images`, which must be sorted in
+/// ascending order
+#[cfg(feature = "parallel")]
+pub fn batch_contains(sorted_spent_images: &[KeyImage], key_images: &[KeyImage]) -> Vec<bool> {
+    use rayon::prelude::*;
+
+    return key_images
+        .par_iter()
+        .map(|key_image| sorted_spent_images.binary_search(key_image).is_ok())
+        .collect();
+}
+
+#[cfg(test)]
+#[cfg(feature = "std")]
+#[cfg(feature = "sign")]
+mod test {
+    extern crate rand;
+
+    use curve25519_dalek::ristretto::RistrettoPoint;
+    use rand::rngs::OsRng;
+
+    use super::*;
+
+    #[test]
+    fn batch_contains() {
+        let mut csprng = OsRng;
+        let spent: Vec<RistrettoPoint> = (0..100).map(|_| RistrettoPoint::random(&mut csprng)).collect();
+        let mut sorted_spent_images: Vec<KeyImage> = spent.iter().map(to_key_image).collect();
+        sorted_spent_images.sort();
+
+        let fresh = RistrettoPoint::random(&mut csprng);
+        let key_images: Vec<KeyImage> = vec![to_key_image(&spent[7]), to_key_image(&fresh), to_key_image(&spent[99])];
+        assert_eq!(super::batch_contains(&sorted_spent_images, &key_images), vec![true, false, true]);
+        assert_eq!(super::batch_contains(&[], &key_images), vec![false, false, false]);
+    }
+
+    #[test]
+    #[cfg(feature = "blsag")]
+    fn verify_and_check() {
+        extern crate sha2;
+
+        use curve25519_dalek::scalar::Scalar;
+        use sha2::Sha512;
+
+        use crate::traits::SignWithRng;
+
+        let mut csprng = OsRng;
+        let k: Scalar = Scalar::random(&mut csprng);
+        let ring: Vec<RistrettoPoint> = (0..2).map(|_| RistrettoPoint::random(&mut csprng)).collect();
+        let message: Vec<u8> = b"This is the message".to_vec();
+        let signature = BLSAG::sign_with_rng_unchecked::<Sha512, _>(k, ring, 1, &message, &mut OsRng).unwrap();
+
+        let mut spent: Vec<KeyImage> = vec![to_key_image(&RistrettoPoint::random(&mut csprng))];
+        let outcome = super::verify_and_check::<Sha512, _, _>(&signature, &message, &spent);
+        assert_eq!(outcome, Ok(LinkOutcome::Unlinked));
+        spent.push(signature.linking_key_image().unwrap());
+        spent.sort();
+        let outcome = super::verify_and_check::<Sha512, _, _>(&signature, &message, &spent);
+        assert_eq!(outcome, Ok(LinkOutcome::Linked));
+        let another: Vec<u8> = b"Another message".to_vec();
+        let outcome = super::verify_and_check::<Sha512, _, _>(&signature, &another, &spent);
+        assert_eq!(outcome, Err(Error::InvalidSignature));
+    }
+
+    #[test]
+    #[cfg(all(feature = "blsag", feature = "mlsag"))]
+    fn linkable() {
+        extern crate sha2;
+
+        use curve25519_dalek::scalar::Scalar;
+        use sha2::Sha512;
+
+        use crate::traits::{KeyImageGenRef, SignWithRng};
+
+        /// Double-sign detection written once for every scheme
+        fn blocked<S: Linkable>(signature: &S, blocklist: &[RistrettoPoint]) -> bool {
+            return signature.key_images().iter().any(|key_image| blocklist.contains(key_image));
+        }
+
+        let message: Vec<u8> = b"This is the message".to_vec();
+        let k: Scalar = Scalar::random(&mut OsRng);
+        let ring: Vec<RistrettoPoint> = (0..2).map(|_| RistrettoPoint::random(&mut OsRng)).collect();
+        let blsag = BLSAG::sign_with_rng_unchecked::<Sha512, _>(k, ring, 1, &message, &mut OsRng).unwrap();
+        assert_eq!(blsag.key_images(), &[BLSAG::generate_key_image_ref::<Sha512>(&k)]);
+
+        let ks: Vec<Scalar> = (0..2).map(|_| Scalar::random(&mut OsRng)).collect();
+        let ring: Vec<Vec<RistrettoPoint>> =
+            (0..2).map(|_| (0..2).map(|_| RistrettoPoint::random(&mut OsRng)).collect()).collect();
+        let mlsag = MLSAG::sign_with_rng_unchecked::<Sha512, _>(ks, ring, 0, &message, &mut OsRng).unwrap();
+        assert_eq!(mlsag.key_images().len(), 2);
+
+        let blocklist: Vec<RistrettoPoint> = vec![blsag.key_image, mlsag.key_images[1]];
+        assert!(blocked(&blsag, &blocklist));
+        assert!(blocked(&mlsag, &blocklist));
+        assert!(!blocked(&mlsag, &blocklist[..1]));
+    }
+
+    #[test]
+    #[cfg(feature = "mlsag")]
+    fn link_all() {
+        extern crate sha2;
+
+        use curve25519_dalek::scalar::Scalar;
+        use sha2::Sha512;
+
+        use crate::traits::SignWithRng;
+
+        let message: Vec<u8> = b"This is the message".to_vec();
+        let decoys = || -> Vec<Vec<RistrettoPoint>> {
+            (0..2).map(|_| (0..2).map(|_| RistrettoPoint::random(&mut OsRng)).collect()).collect()
+        };
+        let shared = Scalar::random(&mut OsRng);
+        let keys: Vec<Vec<Scalar>> = vec![
+            vec![Scalar::random(&mut OsRng), shared],
+            vec![Scalar::random(&mut OsRng), Scalar::random(&mut OsRng)],
+            vec![shared, Scalar::random(&mut OsRng)],
+        ];
+        let mut signatures: Vec<MLSAG> = keys
+            .iter()
+            .map(|ks| {
+                MLSAG::sign_with_rng_unchecked::<Sha512, _>(ks.clone(), decoys(), 1, &message, &mut OsRng).unwrap()
+            })
+            .collect();
+        let signature =
+            MLSAG::sign_with_rng_unchecked::<Sha512, _>(keys[1].clone(), decoys(), 0, &message, &mut OsRng).unwrap();
+        signatures.push(signature);
+
+        // Linked through different columns, and through both columns at once
+        assert_eq!(super::link_all(&signatures), vec![(0, 2), (1, 3)]);
+        assert!(super::link_all(&signatures[..2]).is_empty());
+        assert!(super::link_all::<MLSAG>(&[]).is_empty());
+    }
+}
@@ -0,0 +1,509 @@
+use crate::prelude::*;
+#[cfg(feature = "allocator-api")]
+use crate::arena::{Allocator, VerifyIn};
+
+use core::ops::Range;
+
+#[cfg(feature = "sign")]
+use curve25519_dalek::constants;
+use curve25519_dalek::ristretto::RistrettoPoint;
+use curve25519_dalek::scalar::Scalar;
+use digest::Digest;
+use digest::generic_array::typenum::U64;
+#[cfg(feature = "sign")]
+use rand_core::{CryptoRng, RngCore};
+
+use crate::error::Error;
+use crate::generator::{Basepoint, Generator, VerifyWithGenerator};
+#[cfg(feature = "sign")]
+use crate::generator::SignWithGenerator;
+use crate::scalars::{Challenge, Response};
+#[cfg(feature = "sign")]
+use crate::scalars;
+use crate::traits::{check_responses, ChallengeChain, KeyImageGenRef, LinkRef, TryVerify, VerifyRef, Weight};
+#[cfg(feature = "sign")]
+use crate::traits::{check_secret_index, take_signer, Scheme, SignWithFullRing, SignWithRng, TrySign};
+use crate::weight;
+use crate::codec::{self, Codec, Reader};
+use crate::ops;
+
+/// Linkable Spontaneous Anonymous Group (LSAG) signatures
+/// > Liu, Wei and Wong's original linkable ring signature, where the key image is derived from
+/// > the whole ring rather than from the signer's key alone
+///
+/// Two signatures only link if they were made by the same key over the same ring. Use bLSAG if
+/// linkability should hold regardless of the decoys chosen.
+///
+/// Please read tests at the bottom of the source code for this module for examples on how to use
+/// it
+#[derive(Clone)]
+pub struct LSAG {
+    pub challenge: Challenge,
+    pub responses: Vec<Response>,
+    pub ring: Vec<RistrettoPoint>,
+    pub key_image: RistrettoPoint,
+}
+
+/// The ring hashed to a point `H_p(L)`, which is the base of the ring-scoped key image
+fn hash_ring_to_point<Hash: Digest<OutputSize = U64> + Clone + Default>(
+    ring: &Vec<RistrettoPoint>,
+) -> RistrettoPoint {
+    let mut h: Hash = Hash::default();
+    for k_point in ring {
+        h.update(k_point.compress().as_bytes());
+    }
+    return ops::point_from_hash(h);
+}
+
+/// Hash state shared by all challenges `H_n(L, I, m, ....)`, where `L` is the complete ring and
+/// `I` the key image
+///
+/// # Advanced
+///
+/// This and [`compute_challenge_step`] are the transcript math used by `sign` and `verify`,
+/// exposed for protocols that compute LSAG challenges over data produced elsewhere. Most users
+/// should only ever need `sign` and `verify`.
+pub fn compute_challenge_prefix<Hash: Digest<OutputSize = U64> + Clone + Default>(
+    ring: &[RistrettoPoint],
+    key_image: &RistrettoPoint,
+    message: &[u8],
+) -> Hash {
+    let mut prefix_hash = Hash::default();
+    for k_point in ring {
+        prefix_hash.update(k_point.compress().as_bytes());
+    }
+    prefix_hash.update(key_image.compress().as_bytes());
+    prefix_hash.update(message);
+    return prefix_hash;
+}
+
+/// The challenge `c_(i+1) = H_n(L, I, m, point_a, point_b)` where `point_a = r_i G + c_i K_i` and
+/// `point_b = r_i H_p(L) + c_i I`, given the `hash_state` from [`compute_challenge_prefix`]
+///
+/// # Advanced
+///
+/// See [`compute_challenge_prefix`]
+pub fn compute_challenge_step<Hash: Digest<OutputSize = U64>>(
+    mut hash_state: Hash,
+    point_a: &RistrettoPoint,
+    point_b: &RistrettoPoint,
+) -> Scalar {
+    hash_state.update(point_a.compress().as_bytes());
+    hash_state.update(point_b.compress().as_bytes());
+    return Scalar::from_hash(hash_state);
+}
+
+impl KeyImageGenRef<(Scalar, Vec<RistrettoPoint>), RistrettoPoint> for LSAG {
+    /// Some signature schemes require the key images to be signed as well.
+    /// Use this method to generate them
+    ///
+    /// The key image of LSAG depends on the ring, so `k` is your private key together with the
+    /// complete ring, including your own public key.
+    fn generate_key_image_ref<Hash: Digest<OutputSize = U64> + Clone + Default>(
+        k: &(Scalar, Vec<RistrettoPoint>),
+    ) -> RistrettoPoint {
+        return k.0 * hash_ring_to_point::<Hash>(&k.1);
+    }
+}
+
+#[cfg(feature = "sign")]
+impl SignWithRng<Scalar, Vec<RistrettoPoint>> for LSAG {
+    /// To sign you need `k` your private key, and `ring` which is the public keys of everyone
+    /// except you. You are signing the `message`
+    fn sign_with_rng_unchecked<
+        Hash: Digest<OutputSize = U64> + Clone + Default,
+        CSPRNG: CryptoRng + RngCore,
+    >(
+        k: Scalar,
+        ring: Vec<RistrettoPoint>,
+        secret_index: usize,
+        message: &Vec<u8>,
+        csprng: &mut CSPRNG,
+    ) -> Result<LSAG, Error> {
+        return LSAG::sign_with_generator_and_rng::<Hash, CSPRNG, Basepoint>(k, ring, secret_index, message, csprng);
+    }
+}
+
+#[cfg(feature = "sign")]
+impl SignWithGenerator<Scalar, Vec<RistrettoPoint>> for LSAG {
+    fn sign_with_generator_and_rng<
+        Hash: Digest<OutputSize = U64> + Clone + Default,
+        CSPRNG: CryptoRng + RngCore,
+        G: Generator,
+    >(
+        k: Scalar,
+        mut ring: Vec<RistrettoPoint>,
+        secret_index: usize,
+        message: &Vec<u8>,
+        csprng: &mut CSPRNG,
+    ) -> Result<LSAG, Error> {
+        check_secret_index(ring.len(), secret_index)?;
+        let g: RistrettoPoint = G::generator();
+
+        // Provers public key
+        let k_point: RistrettoPoint = ops::mul_base(&k, &g);
+
+        let n = ring.len() + 1;
+
+        ring.insert(secret_index, k_point);
+
+        let ring_hashed_to_point: RistrettoPoint = hash_ring_to_point::<Hash>(&ring);
+
+        let key_image: RistrettoPoint = ops::mul(&k, &ring_hashed_to_point);
+
+        let a: Scalar = Scalar::random(csprng);
+
+        let mut rs: Vec<Scalar> = (0..n).map(|_| Scalar::random(csprng)).collect();
+
+        let mut cs: Vec<Scalar> = (0..n).map(|_| Scalar::ZERO).collect();
+
+        // The ring, key image and message are shared by all challenges H_n(L, I, m, ....)
+        let prefix_hash: Hash = compute_challenge_prefix::<Hash>(&ring, &key_image, message);
+
+        cs[(secret_index + 1) % n] = compute_challenge_step(
+            prefix_hash.clone(),
+            &ops::mul_base(&a, &g),
+            &ops::mul(&a, &ring_hashed_to_point),
+        );
+
+        let mut i = (secret_index + 1) % n;
+
+        loop {
+            cs[(i + 1) % n] = compute_challenge_step(
+                prefix_hash.clone(),
+                &ops::multiscalar_mul(
+                    &[rs[i % n], cs[i % n]],
+                    &[g, ring[i % n]],
+                ),
+                &ops::multiscalar_mul(
+                    &[rs[i % n], cs[i % n]],
+                    &[ring_hashed_to_point, key_image],
+                ),
+            );
+
+            if secret_index >= 1 && i % n == (secret_index - 1) % n {
+                break;
+            } else if secret_index == 0 && i % n == n - 1 {
+                break;
+            } else {
+                i = (i + 1) % n;
+            }
+        }
+
+        rs[secret_index] = a - (cs[secret_index] * k);
+
+        return Ok(LSAG {
+            challenge: Challenge(cs[0]),
+            responses: scalars::responses(rs),
+            ring: ring,
+            key_image: key_image,
+        });
+    }
+}
+
+impl ChallengeChain for LSAG {
+    fn ring_size(&self) -> usize {
+        return self.ring.len();
+    }
+
+    fn challenge(&self) -> Scalar {
+        return self.challenge.0;
+    }
+
+    /// Starting from `challenge` entering the first slot in `slots`, recomputes the challenge
+    /// leaving the last one
+    fn reconstruct_challenge<Hash: Digest<OutputSize = U64> + Clone + Default>(
+        &self,
+        message: &Vec<u8>,
+        slots: Range<usize>,
+        challenge: Scalar,
+    ) -> Option<Scalar> {
+        // A malformed signature has no chain to run
+        if self.check_shape().is_err() || slots.end > self.ring.len() {
+            return None;
+        }
+        return Some(self.reconstruct_challenge_with::<Hash, Basepoint>(message, slots, challenge));
+    }
+}
+
+impl LSAG {
+    /// As [`ChallengeChain::reconstruct_challenge`] with the generator `G`
+    fn reconstruct_challenge_with<Hash: Digest<OutputSize = U64> + Clone + Default, G: Generator>(
+        &self,
+        message: &Vec<u8>,
+        slots: Range<usize>,
+        challenge: Scalar,
+    ) -> Scalar {
+        let g: RistrettoPoint = G::generator();
+        let mut reconstructed_c: Scalar = challenge;
+        let ring_hashed_to_point: RistrettoPoint = hash_ring_to_point::<Hash>(&self.ring);
+        let prefix_hash: Hash =
+            compute_challenge_prefix::<Hash>(&self.ring, &self.key_image, message);
+        for j in slots {
+            reconstructed_c = compute_challenge_step(
+                prefix_hash.clone(),
+                &ops::multiscalar_mul(
+                    &[self.responses[j].0, reconstructed_c],
+                    &[g, self.ring[j]],
+                ),
+                &ops::multiscalar_mul(
+                    &[self.responses[j].0, reconstructed_c],
+                    &[ring_hashed_to_point, self.key_image],
+                ),
+            );
+        }
+
+        return reconstructed_c;
+    }
+}
+
+impl VerifyRef for LSAG {
+    /// To verify a `signature` you need the `message` too
+    fn verify_ref<Hash: Digest<OutputSize = U64> + Clone + Default>(&self, message: &Vec<u8>) -> bool {
+        return self.verify_with_generator::<Hash, Basepoint>(message);
+    }
+}
+
+#[cfg(feature = "allocator-api")]
+impl VerifyIn for LSAG {
+    /// Verifying allocates nothing, so `alloc` goes unused
+    fn verify_in<Hash: Digest<OutputSize = U64> + Clone + Default, A: Allocator>(
+        &self,
+        message: &Vec<u8>,
+        _alloc: A,
+    ) -> bool {
+        return self.verify_ref::<Hash>(message);
+    }
+}
+
+impl VerifyWithGenerator for LSAG {
+    fn verify_with_generator<Hash: Digest<OutputSize = U64> + Clone + Default, G: Generator>(
+        &self,
+        message: &Vec<u8>,
+    ) -> bool {
+        // Signatures built by hand may not have one response per member
+        if self.check_shape().is_err() {
+            return false;
+        }
+        let n = self.ring.len();
+        let reconstructed_c: Scalar = self.reconstruct_challenge_with::<Hash, G>(message, 0..n, self.challenge.0);
+        return self.challenge.0 == reconstructed_c;
+    }
+}
+
+#[cfg(feature = "sign")]
+impl TrySign<Scalar, Vec<RistrettoPoint>> for LSAG {
+    fn check_sign_input(_k: &Scalar, ring: &Vec<RistrettoPoint>, secret_index: usize) -> Result<(), Error> {
+        return check_secret_index(ring.len(), secret_index);
+    }
+}
+
+#[cfg(feature = "sign")]
+impl SignWithFullRing<Scalar, Vec<RistrettoPoint>> for LSAG {
+    fn take_signer(k: &Scalar, ring: &mut Vec<RistrettoPoint>, secret_index: usize) -> Result<(), Error> {
+        return take_signer(ring, secret_index, &(k * constants::RISTRETTO_BASEPOINT_POINT));
+    }
+}
+
+#[cfg(feature = "sign")]
+impl Scheme for LSAG {
+    type SecretKey = Scalar;
+    type Ring = Vec<RistrettoPoint>;
+
+    fn sign_with_key<Hash: Digest<OutputSize = U64> + Clone + Default, CSPRNG: CryptoRng + RngCore + Default>(
+        k: Scalar,
+        ring: Vec<RistrettoPoint>,
+        secret_index: usize,
+        message: &Vec<u8>,
+    ) -> Result<LSAG, Error> {
+        let mut csprng = CSPRNG::default();
+        return LSAG::sign_with_rng::<Hash, CSPRNG>(k, ring, secret_index, message, &mut csprng);
+    }
+
+    fn try_sign_with_key_and_rng<Hash: Digest<OutputSize = U64> + Clone + Default, CSPRNG: CryptoRng + RngCore>(
+        k: Scalar,
+        ring: Vec<RistrettoPoint>,
+        secret_index: usize,
+        message: &Vec<u8>,
+        csprng: &mut CSPRNG,
+    ) -> Result<LSAG, Error> {
+        return LSAG::sign_with_rng_unchecked::<Hash, CSPRNG>(k, ring, secret_index, message, csprng);
+    }
+}
+
+impl TryVerify for LSAG {
+    fn check_shape(&self) -> Result<(), Error> {
+        return check_responses(self.ring.len(), self.responses.len());
+    }
+}
+
+impl LinkRef for LSAG {
+    /// This is for linking two signatures and checking if they are signed by the same person
+    /// over the same ring. Signatures over different rings never link.
+    fn link_ref(&self, other: &LSAG) -> bool {
+        return self.ring == other.ring
+            && self.key_image == other.key_image;
+    }
+}
+
+impl Weight for LSAG {
+    /// Approximate cost of verifying this signature, see [`crate::weight`]
+    fn weight(&self) -> u64 {
+        return weight::lsag(self.ring.len());
+    }
+}
+
+impl LSAG {
+    /// Assembles a signature from its fields, returning `None` if the ring and responses do
+    /// not agree on the ring size
+    pub fn from_parts(
+        challenge: Challenge,
+        responses: Vec<Response>,
+        ring: Vec<RistrettoPoint>,
+        key_image: RistrettoPoint,
+    ) -> Option<LSAG> {
+        if ring.is_empty() || responses.len() != ring.len() {
+            return None;
+        }
+        return Some(LSAG::from_parts_unchecked(challenge, responses, ring, key_image));
+    }
+
+    /// Assembles a signature from its fields without checking them
+    ///
+    /// This is for fields that have been checked before, e.g. by [`LSAG::from_parts`] or by
+    /// decoding, and are read back from storage that is trusted. A signature assembled from
+    /// fields that do not agree is rejected: verifying it returns false.
+    pub fn from_parts_unchecked(
+        challenge: Challenge,
+        responses: Vec<Response>,
+        ring: Vec<RistrettoPoint>,
+        key_image: RistrettoPoint,
+    ) -> LSAG {
+        return LSAG {
+            challenge: challenge,
+            responses: responses,
+            ring: ring,
+            key_image: key_image,
+        };
+    }
+}
+
+impl Codec for LSAG {
+    /// Encodes the signature canonically, see [`crate::codec`]
+    fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes: Vec<u8> = Vec::new();
+        codec::write_challenge(&mut bytes, &self.challenge);
+        codec::write_u32(&mut bytes, self.ring.len());
+        for response in &self.responses {
+            codec::write_response(&mut bytes, response);
+        }
+        for k_point in &self.ring {
+            codec::write_point(&mut bytes, k_point);
+        }
+        codec::write_point(&mut bytes, &self.key_image);
+        return bytes;
+    }
+
+    /// Decodes a signature, returning the [`Error`] describing why if `bytes` is not a canonical encoding
+    fn decode(bytes: &[u8]) -> Result<LSAG, Error> {
+        let mut reader = Reader::new(bytes);
+        let challenge = reader.read_challenge()?;
+        let n = reader.read_len(64)?;
+        let responses = reader.read_responses(n)?;
+        let ring = reader.read_points(n)?;
+        let key_image = reader.read_point()?;
+        reader.finish()?;
+        return Ok(LSAG {
+            challenge: challenge,
+            responses: responses,
+            ring: ring,
+            key_image: key_image,
+        });
+    }
+}
+
+crate::encoding::display_via_codec!(LSAG);
+
+#[cfg(test)]
+#[cfg(feature = "std")]
+#[cfg(feature = "sign")]
+mod test {
+    extern crate blake2;
+    extern crate rand;
+    extern crate sha2;
+    extern crate sha3;
+
+    use blake2::Blake2b512;
+    use curve25519_dalek::ristretto::RistrettoPoint;
+    use curve25519_dalek::scalar::Scalar;
+    use rand::rngs::OsRng;
+    use sha2::Sha512;
+    use sha3::Keccak512;
+
+    use super::*;
+
+    #[test]
+    fn lsag() {
+        let mut csprng = OsRng;
+        let k: Scalar = Scalar::random(&mut csprng);
+        let secret_index = 1;
+        let n = 2;
+        let ring: Vec<RistrettoPoint> = (0..(n - 1)) // Prover is going to add our key into this mix
+            .map(|_| RistrettoPoint::random(&mut csprng))
+            .collect();
+        let message: Vec<u8> = b"This is the message".to_vec();
+
+        {
+            let signature =
+                LSAG::sign_with_rng_unchecked::<Sha512, _>(k, ring.clone(), secret_index, &message, &mut OsRng)
+                    .unwrap();
+            let result = signature.verify_ref::<Sha512>(&message);
+            assert!(result);
+        }
+
+        {
+            let signature =
+                LSAG::sign_with_rng_unchecked::<Keccak512, _>(k, ring.clone(), secret_index, &message, &mut OsRng)
+                    .unwrap();
+            let result = signature.verify_ref::<Keccak512>(&message);
+            assert!(result);
+        }
+
+        {
+            let signature =
+                LSAG::sign_with_rng_unchecked::<Blake2b512, _>(k, ring.clone(), secret_index, &message, &mut OsRng)
+                    .unwrap();
+            let result = signature.verify_ref::<Blake2b512>(&message);
+            assert!(result);
+        }
+
+        let another_ring: Vec<RistrettoPoint> = (0..(n - 1)) // Prover is going to add our key into this mix
+            .map(|_| RistrettoPoint::random(&mut csprng))
+            .collect();
+        let another_message: Vec<u8> = b"This is another message".to_vec();
+        let signature_1 =
+            LSAG::sign_with_rng_unchecked::<Blake2b512, _>(k, ring.clone(), secret_index, &another_message, &mut OsRng)
+                .unwrap();
+        let signature_2 =
+            LSAG::sign_with_rng_unchecked::<Blake2b512, _>(k, ring.clone(), secret_index, &message, &mut OsRng)
+                .unwrap();
+        let signature_3 =
+            LSAG::sign_with_rng_unchecked::<Blake2b512, _>(k, another_ring.clone(), secret_index, &message, &mut OsRng)
+                .unwrap();
+        // Same key and same ring links
+        assert!(signature_1.link_ref(&signature_2));
+        // Same key over a different ring does not
+        assert!(!signature_1.link_ref(&signature_3));
+    }
+
+    #[test]
+    fn sign_does_not_panic() {
+        let k: Scalar = Scalar::random(&mut OsRng);
+        let ring: Vec<RistrettoPoint> = (0..2).map(|_| RistrettoPoint::random(&mut OsRng)).collect();
+        let message: Vec<u8> = b"This is the message".to_vec();
+
+        // A secret index outside the ring is an error, not a panic
+        let signature = LSAG::sign_with_rng_unchecked::<Sha512, _>(k, ring, 3, &message, &mut OsRng);
+        assert_eq!(signature.err(), Some(Error::SecretIndexOutOfRange { index: 3, ring_size: 3 }));
+    }
+}
@@ -1,12 +1,30 @@
-use crate::traits::{KeyImageGen, Link, Sign, Verify};
+use crate::error::Error;
+use crate::scalars::{Challenge, Response};
+#[cfg(feature = "sign")]
+use crate::scalars;
+use crate::traits::{
+    check_columns, check_responses, ChallengeChain, KeyImageGenRef, LinkRef, TryVerify, VerifyRef, Weight,
+};
+#[cfg(feature = "sign")]
+use crate::traits::{check_secret_index, take_signer, Scheme, SignWithFullRing, SignWithRng, TrySign};
+use crate::weight;
+use crate::generator::{Basepoint, Generator, VerifyWithGenerator};
+#[cfg(feature = "sign")]
+use crate::generator::SignWithGenerator;
+use crate::codec::{self, Codec, Reader};
+use crate::ops;
 use crate::prelude::*;
+use crate::scratch::Scratch;
+#[cfg(feature = "allocator-api")]
+use crate::arena::{Allocator, VerifyIn};
+use core::ops::Range;
 use curve25519_dalek::constants;
 use curve25519_dalek::ristretto::RistrettoPoint;
 use curve25519_dalek::scalar::Scalar;
 use digest::generic_array::typenum::U64;
 use digest::Digest;
+#[cfg(feature = "sign")]
 use rand_core::{CryptoRng, RngCore};
-use curve25519_dalek::traits::MultiscalarMul;
 
 /// Multilayer Dual Linkable Spontaneous Anonymous Group Signature for Ad Hoc Groups
 ///
@@ -22,33 +40,75 @@ use curve25519_dalek::traits::MultiscalarMul;
 
 #[derive(Clone)]
 pub struct MDLSAG {
-    pub challenge: Scalar,
-    pub responses: Vec<Vec<Scalar>>,
+    pub challenge: Challenge,
+    pub responses: Vec<Vec<Response>>,
     pub ring: Vec<Vec<(RistrettoPoint, RistrettoPoint, Scalar)>>,
     pub key_images: Vec<RistrettoPoint>,
     pub b: bool,
 }
 
-impl KeyImageGen<Vec<(Scalar, RistrettoPoint, Scalar)>, Vec<RistrettoPoint>> for MDLSAG {
+/// The private keys of either side of a channel, see the two implementations of [`SignWithRng`]
+#[derive(Clone, Debug)]
+pub enum MDLSAGKey {
+    /// Your private keys, each with the public key of the other end of the channel and the
+    /// bitstring
+    Left(Vec<(Scalar, RistrettoPoint, Scalar)>),
+    /// Your private keys, each after the public key of the other end of the channel and before the
+    /// bitstring
+    Right(Vec<(RistrettoPoint, Scalar, Scalar)>),
+}
+
+/// Hash state shared by all challenges `H_n(m, ....)`
+///
+/// # Advanced
+///
+/// This and [`compute_challenge_step`] are the transcript math used by `sign` and `verify`,
+/// exposed for protocols that compute MDLSAG challenges over data produced elsewhere. Most users
+/// should only ever need `sign` and `verify`.
+pub fn compute_challenge_prefix<Hash: Digest<OutputSize = U64> + Clone + Default>(
+    message: &[u8],
+) -> Hash {
+    let mut message_hash = Hash::default();
+    message_hash.update(message);
+    return message_hash;
+}
+
+/// The challenge `c_(i+1) = H_n(m, L_1, R_1, ..., L_nc, R_nc)` where `points` holds the pair
+/// `L_j = r_i,j G + c_i K_i,j` and `R_j = r_i,j b_i,j H_p(K'_i,j) + c_i K~_j` for every column
+/// `j`, given the `hash_state` from [`compute_challenge_prefix`]
+///
+/// # Advanced
+///
+/// See [`compute_challenge_prefix`]
+pub fn compute_challenge_step<Hash: Digest<OutputSize = U64>>(
+    mut hash_state: Hash,
+    points: &[(RistrettoPoint, RistrettoPoint)],
+) -> Scalar {
+    for (point_a, point_b) in points {
+        hash_state.update(point_a.compress().as_bytes());
+        hash_state.update(point_b.compress().as_bytes());
+    }
+    return Scalar::from_hash(hash_state);
+}
+
+impl KeyImageGenRef<Vec<(Scalar, RistrettoPoint, Scalar)>, Vec<RistrettoPoint>> for MDLSAG {
     /// Some signature schemes require the key images to be signed as well.
     /// Use this method to generate them
-    fn generate_key_image<Hash: Digest<OutputSize = U64> + Clone + Default>(
-        ks: Vec<(Scalar, RistrettoPoint, Scalar)>,
+    fn generate_key_image_ref<Hash: Digest<OutputSize = U64> + Clone + Default>(
+        ks: &Vec<(Scalar, RistrettoPoint, Scalar)>,
     ) -> Vec<RistrettoPoint> {
         let nc = ks.len();
 
         let k_points: Vec<(RistrettoPoint, RistrettoPoint, Scalar)> = ks
             .iter()
-            .map(|k| (k.0 * constants::RISTRETTO_BASEPOINT_POINT, k.1, k.2))
+            .map(|k| (ops::mul_base(&k.0, &constants::RISTRETTO_BASEPOINT_POINT), k.1, k.2))
             .collect();
 
         let key_images: Vec<RistrettoPoint> = (0..nc)
             .map(|j| {
-                ks[j].2
-                    * ks[j].0
-                    * RistrettoPoint::from_hash(
-                        Hash::default().chain_update(k_points[j].1.compress().as_bytes()),
-                    )
+                ops::mul(&(ks[j].2 * ks[j].0), &ops::point_from_hash(
+                    Hash::default().chain_update(k_points[j].1.compress().as_bytes()),
+                ))
             })
             .collect();
 
@@ -56,26 +116,24 @@ impl KeyImageGen<Vec<(Scalar, RistrettoPoint, Scalar)>, Vec<RistrettoPoint>> for
     }
 }
 
-impl KeyImageGen<Vec<(RistrettoPoint, Scalar, Scalar)>, Vec<RistrettoPoint>> for MDLSAG {
+impl KeyImageGenRef<Vec<(RistrettoPoint, Scalar, Scalar)>, Vec<RistrettoPoint>> for MDLSAG {
     /// Some signature schemes require the key images to be signed as well.
     /// Use this method to generate them
-    fn generate_key_image<Hash: Digest<OutputSize = U64> + Clone + Default>(
-        ks: Vec<(RistrettoPoint, Scalar, Scalar)>,
+    fn generate_key_image_ref<Hash: Digest<OutputSize = U64> + Clone + Default>(
+        ks: &Vec<(RistrettoPoint, Scalar, Scalar)>,
     ) -> Vec<RistrettoPoint> {
         let nc = ks.len();
 
         let k_points: Vec<(RistrettoPoint, RistrettoPoint, Scalar)> = ks
             .iter()
-            .map(|k| (k.0, k.1 * constants::RISTRETTO_BASEPOINT_POINT, k.2))
+            .map(|k| (k.0, ops::mul_base(&k.1, &constants::RISTRETTO_BASEPOINT_POINT), k.2))
             .collect();
 
         let key_images: Vec<RistrettoPoint> = (0..nc)
             .map(|j| {
-                ks[j].2
-                    * ks[j].1
-                    * RistrettoPoint::from_hash(
-                        Hash::default().chain_update(k_points[j].0.compress().as_bytes()),
-                    )
+                ops::mul(&(ks[j].2 * ks[j].1), &ops::point_from_hash(
+                    Hash::default().chain_update(k_points[j].0.compress().as_bytes()),
+                ))
             })
             .collect();
 
@@ -83,7 +141,8 @@ impl KeyImageGen<Vec<(RistrettoPoint, Scalar, Scalar)>, Vec<RistrettoPoint>> for
     }
 }
 
-impl Sign<Vec<(Scalar, RistrettoPoint, Scalar)>, Vec<Vec<(RistrettoPoint, RistrettoPoint, Scalar)>>>
+#[cfg(feature = "sign")]
+impl SignWithRng<Vec<(Scalar, RistrettoPoint, Scalar)>, Vec<Vec<(RistrettoPoint, RistrettoPoint, Scalar)>>>
     for MDLSAG
 {
     /// To sign you need `k` your private key, and `ring` which is the public keys of everyone
@@ -95,96 +154,103 @@ impl Sign<Vec<(Scalar, RistrettoPoint, Scalar)>, Vec<Vec<(RistrettoPoint, Ristre
     ///
     /// The ring contains public key pairs from the blockchain together with their random
     /// bitstrings as mentioned above.
-    fn sign<
+    fn sign_with_rng_unchecked<
         Hash: Digest<OutputSize = U64> + Clone + Default,
-        CSPRNG: CryptoRng + RngCore + Default,
+        CSPRNG: CryptoRng + RngCore,
+    >(
+        ks: Vec<(Scalar, RistrettoPoint, Scalar)>,
+        ring: Vec<Vec<(RistrettoPoint, RistrettoPoint, Scalar)>>,
+        secret_index: usize,
+        message: &Vec<u8>,
+        csprng: &mut CSPRNG,
+    ) -> Result<MDLSAG, Error> {
+        return MDLSAG::sign_with_generator_and_rng::<Hash, CSPRNG, Basepoint>(ks, ring, secret_index, message, csprng);
+    }
+}
+
+#[cfg(feature = "sign")]
+impl SignWithGenerator<Vec<(Scalar, RistrettoPoint, Scalar)>, Vec<Vec<(RistrettoPoint, RistrettoPoint, Scalar)>>>
+    for MDLSAG
+{
+    fn sign_with_generator_and_rng<
+        Hash: Digest<OutputSize = U64> + Clone + Default,
+        CSPRNG: CryptoRng + RngCore,
+        G: Generator,
     >(
         ks: Vec<(Scalar, RistrettoPoint, Scalar)>,
         mut ring: Vec<Vec<(RistrettoPoint, RistrettoPoint, Scalar)>>,
         secret_index: usize,
         message: &Vec<u8>,
-    ) -> MDLSAG {
-        let mut csprng = CSPRNG::default();
+        csprng: &mut CSPRNG,
+    ) -> Result<MDLSAG, Error> {
+        check_columns(ks.len(), &ring)?;
+        check_secret_index(ring.len(), secret_index)?;
 
         // Row count of matrix
         let nr = ring.len() + 1;
         // Column count of matrix
-        let nc = ring[0].len();
+        let nc = ks.len();
+        let g: RistrettoPoint = G::generator();
 
         //Provers public keys
-        let k_points: Vec<(RistrettoPoint, RistrettoPoint, Scalar)> = ks
-            .iter()
-            .map(|k| (k.0 * constants::RISTRETTO_BASEPOINT_POINT, k.1, k.2))
-            .collect();
+        let k_points: Vec<(RistrettoPoint, RistrettoPoint, Scalar)> =
+            ks.iter().map(|k| (ops::mul_base(&k.0, &g), k.1, k.2)).collect();
 
-        let key_images: Vec<RistrettoPoint> = MDLSAG::generate_key_image::<Hash>(ks.clone());
+        let key_images: Vec<RistrettoPoint> = MDLSAG::generate_key_image_ref::<Hash>(&ks);
 
         ring.insert(secret_index, k_points.clone());
 
-        let a: Vec<Scalar> = (0..nc).map(|_| Scalar::random(&mut csprng)).collect();
+        let a: Vec<Scalar> = (0..nc).map(|_| Scalar::random(csprng)).collect();
 
         let mut rs: Vec<Vec<Scalar>> = (0..nr)
-            .map(|_| (0..nc).map(|_| Scalar::random(&mut csprng)).collect())
+            .map(|_| (0..nc).map(|_| Scalar::random(csprng)).collect())
             .collect();
 
         let mut cs: Vec<Scalar> = (0..nr).map(|_| Scalar::ZERO).collect();
 
         // Hash of message is shared by all challenges H_n(m, ....)
-        let mut message_hash = Hash::default();
-
-        message_hash.update(message);
-
-        let mut hashes: Vec<Hash> = (0..nr).map(|_| message_hash.clone()).collect();
-
-        for j in 0..nc {
-            hashes[(secret_index + 1) % nr].update(
-                (a[j] * constants::RISTRETTO_BASEPOINT_POINT)
-                    .compress()
-                    .as_bytes(),
-            );
-            hashes[(secret_index + 1) % nr].update(
-                (a[j]
-                    * ring[secret_index][j].2
-                    * RistrettoPoint::from_hash(
-                        Hash::default().chain_update(k_points[j].1.compress().as_bytes()),
-                    ))
-                .compress()
-                .as_bytes(),
-            );
-        }
-        cs[(secret_index + 1) % nr] = Scalar::from_hash(hashes[(secret_index + 1) % nr].clone());
+        let message_hash: Hash = compute_challenge_prefix::<Hash>(message);
+
+        cs[(secret_index + 1) % nr] = compute_challenge_step(
+            message_hash.clone(),
+            &(0..nc)
+                .map(|j| {
+                    (
+                        ops::mul_base(&a[j], &g),
+                        ops::mul(&(a[j] * ring[secret_index][j].2), &ops::point_from_hash(
+                            Hash::default().chain_update(k_points[j].1.compress().as_bytes()),
+                        )),
+                    )
+                })
+                .collect::<Vec<(RistrettoPoint, RistrettoPoint)>>(),
+        );
 
         let mut i = (secret_index + 1) % nr;
 
         loop {
-            for j in 0..nc {
-                hashes[(i + 1) % nr].update(
-                    RistrettoPoint::multiscalar_mul(
-                        &[rs[i % nr][j], cs[i % nr]],
-                        &[
-                            constants::RISTRETTO_BASEPOINT_POINT,
-                            ring[i % nr][j].0
-                        ]
-                    )
-                        .compress()
-                        .as_bytes(),
-                );
-                hashes[(i + 1) % nr].update(
-                    RistrettoPoint::multiscalar_mul(
-                        &[rs[i % nr][j], cs[i % nr]],
-                        &[
-                            ring[i % nr][j].2 * RistrettoPoint::from_hash(
-                                Hash::default().chain_update(
-                                    ring[i % nr][j].1.compress().as_bytes()),
+            cs[(i + 1) % nr] = compute_challenge_step(
+                message_hash.clone(),
+                &(0..nc)
+                    .map(|j| {
+                        (
+                            ops::multiscalar_mul(
+                                &[rs[i % nr][j], cs[i % nr]],
+                                &[g, ring[i % nr][j].0]
                             ),
-                            key_images[j]
-                        ]
-                    )
-                        .compress()
-                        .as_bytes(),
-                );
-            }
-            cs[(i + 1) % nr] = Scalar::from_hash(hashes[(i + 1) % nr].clone());
+                            ops::multiscalar_mul(
+                                &[rs[i % nr][j], cs[i % nr]],
+                                &[
+                                    ops::mul(&ring[i % nr][j].2, &ops::point_from_hash(
+                                        Hash::default().chain_update(
+                                            ring[i % nr][j].1.compress().as_bytes()),
+                                    )),
+                                    key_images[j]
+                                ]
+                            ),
+                        )
+                    })
+                    .collect::<Vec<(RistrettoPoint, RistrettoPoint)>>(),
+            );
 
             if secret_index >= 1 && i % nr == (secret_index - 1) % nr {
                 break;
@@ -199,17 +265,18 @@ impl Sign<Vec<(Scalar, RistrettoPoint, Scalar)>, Vec<Vec<(RistrettoPoint, Ristre
             rs[secret_index][j] = a[j] - (cs[secret_index] * ks[j].0);
         }
 
-        return MDLSAG {
-            challenge: cs[0],
-            responses: rs,
+        return Ok(MDLSAG {
+            challenge: Challenge(cs[0]),
+            responses: rs.into_iter().map(scalars::responses).collect(),
             ring: ring,
             key_images: key_images,
             b: false,
-        };
+        });
     }
 }
 
-impl Sign<Vec<(RistrettoPoint, Scalar, Scalar)>, Vec<Vec<(RistrettoPoint, RistrettoPoint, Scalar)>>>
+#[cfg(feature = "sign")]
+impl SignWithRng<Vec<(RistrettoPoint, Scalar, Scalar)>, Vec<Vec<(RistrettoPoint, RistrettoPoint, Scalar)>>>
     for MDLSAG
 {
     /// To sign you need `k` your private key, and `ring` which is the public keys of everyone
@@ -221,97 +288,103 @@ impl Sign<Vec<(RistrettoPoint, Scalar, Scalar)>, Vec<Vec<(RistrettoPoint, Ristre
     ///
     /// The ring contains public key pairs from the blockchain together with their random
     /// bitstrings as mentioned above.
-    fn sign<
+    fn sign_with_rng_unchecked<
+        Hash: Digest<OutputSize = U64> + Clone + Default,
+        CSPRNG: CryptoRng + RngCore,
+    >(
+        ks: Vec<(RistrettoPoint, Scalar, Scalar)>,
+        ring: Vec<Vec<(RistrettoPoint, RistrettoPoint, Scalar)>>,
+        secret_index: usize,
+        message: &Vec<u8>,
+        csprng: &mut CSPRNG,
+    ) -> Result<MDLSAG, Error> {
+        return MDLSAG::sign_with_generator_and_rng::<Hash, CSPRNG, Basepoint>(ks, ring, secret_index, message, csprng);
+    }
+}
+
+#[cfg(feature = "sign")]
+impl SignWithGenerator<Vec<(RistrettoPoint, Scalar, Scalar)>, Vec<Vec<(RistrettoPoint, RistrettoPoint, Scalar)>>>
+    for MDLSAG
+{
+    fn sign_with_generator_and_rng<
         Hash: Digest<OutputSize = U64> + Clone + Default,
-        CSPRNG: CryptoRng + RngCore + Default,
+        CSPRNG: CryptoRng + RngCore,
+        G: Generator,
     >(
         ks: Vec<(RistrettoPoint, Scalar, Scalar)>,
         mut ring: Vec<Vec<(RistrettoPoint, RistrettoPoint, Scalar)>>,
         secret_index: usize,
         message: &Vec<u8>,
-    ) -> MDLSAG {
-        let mut csprng = CSPRNG::default();
+        csprng: &mut CSPRNG,
+    ) -> Result<MDLSAG, Error> {
+        check_columns(ks.len(), &ring)?;
+        check_secret_index(ring.len(), secret_index)?;
 
         // Row count of matrix
         let nr = ring.len() + 1;
         // Column count of matrix
-        let nc = ring[0].len();
+        let nc = ks.len();
+        let g: RistrettoPoint = G::generator();
 
         //Provers public keys
-        let k_points: Vec<(RistrettoPoint, RistrettoPoint, Scalar)> = ks
-            .iter()
-            .map(|k| (k.0, k.1 * constants::RISTRETTO_BASEPOINT_POINT, k.2))
-            .collect();
+        let k_points: Vec<(RistrettoPoint, RistrettoPoint, Scalar)> =
+            ks.iter().map(|k| (k.0, ops::mul_base(&k.1, &g), k.2)).collect();
 
-        let key_images: Vec<RistrettoPoint> = MDLSAG::generate_key_image::<Hash>(ks.clone());
+        let key_images: Vec<RistrettoPoint> = MDLSAG::generate_key_image_ref::<Hash>(&ks);
 
         ring.insert(secret_index, k_points.clone());
 
-        let a: Vec<Scalar> = (0..nc).map(|_| Scalar::random(&mut csprng)).collect();
+        let a: Vec<Scalar> = (0..nc).map(|_| Scalar::random(csprng)).collect();
 
         let mut rs: Vec<Vec<Scalar>> = (0..nr)
-            .map(|_| (0..nc).map(|_| Scalar::random(&mut csprng)).collect())
+            .map(|_| (0..nc).map(|_| Scalar::random(csprng)).collect())
             .collect();
 
         let mut cs: Vec<Scalar> = (0..nr).map(|_| Scalar::ZERO).collect();
 
         // Hash of message is shared by all challenges H_n(m, ....)
-        let mut message_hash = Hash::default();
-
-        message_hash.update(message);
-
-        let mut hashes: Vec<Hash> = (0..nr).map(|_| message_hash.clone()).collect();
-
-        for j in 0..nc {
-            hashes[(secret_index + 1) % nr].update(
-                (a[j] * constants::RISTRETTO_BASEPOINT_POINT)
-                    .compress()
-                    .as_bytes(),
-            );
-            hashes[(secret_index + 1) % nr].update(
-                (a[j]
-                    * ring[secret_index][j].2
-                    * RistrettoPoint::from_hash(
-                        Hash::default().chain_update(k_points[j].0.compress().as_bytes()),
-                    ))
-                .compress()
-                .as_bytes(),
-            );
-        }
-        cs[(secret_index + 1) % nr] = Scalar::from_hash(hashes[(secret_index + 1) % nr].clone());
+        let message_hash: Hash = compute_challenge_prefix::<Hash>(message);
+
+        cs[(secret_index + 1) % nr] = compute_challenge_step(
+            message_hash.clone(),
+            &(0..nc)
+                .map(|j| {
+                    (
+                        ops::mul_base(&a[j], &g),
+                        ops::mul(&(a[j] * ring[secret_index][j].2), &ops::point_from_hash(
+                            Hash::default().chain_update(k_points[j].0.compress().as_bytes()),
+                        )),
+                    )
+                })
+                .collect::<Vec<(RistrettoPoint, RistrettoPoint)>>(),
+        );
 
         let mut i = (secret_index + 1) % nr;
 
         loop {
-            for j in 0..nc {
-                hashes[(i + 1) % nr].update(
-                    RistrettoPoint::multiscalar_mul(
-                        &[rs[i % nr][j], cs[i % nr]],
-                        &[
-                            constants::RISTRETTO_BASEPOINT_POINT,
-                            ring[i % nr][j].1
-                        ]
-                    )
-                        .compress()
-                        .as_bytes(),
-                );
-                hashes[(i + 1) % nr].update(
-                    RistrettoPoint::multiscalar_mul(
-                        &[rs[i % nr][j], cs[i % nr]],
-                        &[
-                            ring[i % nr][j].2 * RistrettoPoint::from_hash(
-                                Hash::default().chain_update(
-                                    ring[i % nr][j].0.compress().as_bytes()
-                                )
+            cs[(i + 1) % nr] = compute_challenge_step(
+                message_hash.clone(),
+                &(0..nc)
+                    .map(|j| {
+                        (
+                            ops::multiscalar_mul(
+                                &[rs[i % nr][j], cs[i % nr]],
+                                &[g, ring[i % nr][j].1]
                             ),
-                            key_images[j]
-                        ]
-                    )
-                        .compress()
-                        .as_bytes(),
-                );
-            }
-            cs[(i + 1) % nr] = Scalar::from_hash(hashes[(i + 1) % nr].clone());
+                            ops::multiscalar_mul(
+                                &[rs[i % nr][j], cs[i % nr]],
+                                &[
+                                    ops::mul(&ring[i % nr][j].2, &ops::point_from_hash(
+                                        Hash::default().chain_update(
+                                            ring[i % nr][j].0.compress().as_bytes()),
+                                    )),
+                                    key_images[j]
+                                ]
+                            ),
+                        )
+                    })
+                    .collect::<Vec<(RistrettoPoint, RistrettoPoint)>>(),
+            );
 
             if secret_index >= 1 && i % nr == (secret_index - 1) % nr {
                 break;
@@ -326,107 +399,276 @@ impl Sign<Vec<(RistrettoPoint, Scalar, Scalar)>, Vec<Vec<(RistrettoPoint, Ristre
             rs[secret_index][j] = a[j] - (cs[secret_index] * ks[j].1);
         }
 
-        return MDLSAG {
-            challenge: cs[0],
-            responses: rs,
+        return Ok(MDLSAG {
+            challenge: Challenge(cs[0]),
+            responses: rs.into_iter().map(scalars::responses).collect(),
             ring: ring,
             key_images: key_images,
             b: true,
-        };
+        });
+    }
+}
+
+impl MDLSAG {
+    /// As [`ChallengeChain::reconstruct_challenge`], given the hash state with the message already
+    /// absorbed, see [`crate::session`]
+    pub(crate) fn reconstruct_challenge_from_prefix<Hash: Digest<OutputSize = U64> + Clone + Default>(
+        &self,
+        message_hash: Hash,
+        slots: Range<usize>,
+        challenge: Scalar,
+    ) -> Scalar {
+        return self.reconstruct_challenge_with_generator::<Hash, Basepoint>(message_hash, slots, challenge);
+    }
+
+    /// As [`MDLSAG::reconstruct_challenge_from_prefix`] with the generator `G`
+    fn reconstruct_challenge_with_generator<Hash: Digest<OutputSize = U64> + Clone + Default, G: Generator>(
+        &self,
+        message_hash: Hash,
+        slots: Range<usize>,
+        challenge: Scalar,
+    ) -> Scalar {
+        // Column count of matrix
+        let nc = self.key_images.len();
+        // A single buffer for the points of every row, rather than allocating one per row
+        let mut points: Vec<(RistrettoPoint, RistrettoPoint)> = Vec::with_capacity(nc);
+        return self.reconstruct_challenge_in::<Hash, G, _>(message_hash, slots, challenge, &mut points);
+    }
+
+    /// As [`MDLSAG::reconstruct_challenge_with_generator`], filling `points` for every row
+    fn reconstruct_challenge_in<
+        Hash: Digest<OutputSize = U64> + Clone + Default,
+        G: Generator,
+        S: Scratch<(RistrettoPoint, RistrettoPoint)>,
+    >(
+        &self,
+        message_hash: Hash,
+        slots: Range<usize>,
+        challenge: Scalar,
+        points: &mut S,
+    ) -> Scalar {
+        let g: RistrettoPoint = G::generator();
+        let mut reconstructed_c: Scalar = challenge;
+        // Column count of matrix
+        let nc = self.key_images.len();
+        for _i in slots {
+            points.clear();
+            points.extend((0..nc).map(|j| {
+                // The signer's side of the channel, and the other side which is hashed
+                // to a point
+                let (own, other) = if self.b {
+                    (self.ring[_i][j].1, self.ring[_i][j].0)
+                } else {
+                    (self.ring[_i][j].0, self.ring[_i][j].1)
+                };
+                (
+                    ops::multiscalar_mul(
+                        &[self.responses[_i][j].0, reconstructed_c],
+                        &[g, own]
+                    ),
+                    ops::multiscalar_mul(
+                        &[self.responses[_i][j].0, reconstructed_c],
+                        &[
+                            ops::mul(&self.ring[_i][j].2, &ops::point_from_hash(
+                                Hash::default().chain_update(other.compress().as_bytes())
+                            )),
+                            self.key_images[j]
+                        ]
+                    ),
+                )
+            }));
+            reconstructed_c = compute_challenge_step(message_hash.clone(), points);
+        }
+
+        return reconstructed_c;
+    }
+}
+
+impl ChallengeChain for MDLSAG {
+    fn ring_size(&self) -> usize {
+        return self.ring.len();
+    }
+
+    fn challenge(&self) -> Scalar {
+        return self.challenge.0;
+    }
+
+    /// Starting from `challenge` entering the first slot in `slots`, recomputes the challenge
+    /// leaving the last one
+    fn reconstruct_challenge<Hash: Digest<OutputSize = U64> + Clone + Default>(
+        &self,
+        message: &Vec<u8>,
+        slots: Range<usize>,
+        challenge: Scalar,
+    ) -> Option<Scalar> {
+        // A malformed signature has no chain to run
+        if self.check_shape().is_err() || slots.end > self.ring.len() {
+            return None;
+        }
+        let message_hash: Hash = compute_challenge_prefix::<Hash>(message);
+        return Some(self.reconstruct_challenge_from_prefix(message_hash, slots, challenge));
     }
 }
 
-impl Verify for MDLSAG {
+impl VerifyRef for MDLSAG {
     /// To verify a `signature` you need the `message` too
-    fn verify<Hash: Digest<OutputSize = U64> + Clone + Default>(
-        signature: MDLSAG,
+    fn verify_ref<Hash: Digest<OutputSize = U64> + Clone + Default>(&self, message: &Vec<u8>) -> bool {
+        return self.verify_with_generator::<Hash, Basepoint>(message);
+    }
+}
+
+#[cfg(feature = "allocator-api")]
+impl VerifyIn for MDLSAG {
+    fn verify_in<Hash: Digest<OutputSize = U64> + Clone + Default, A: Allocator>(
+        &self,
         message: &Vec<u8>,
+        alloc: A,
     ) -> bool {
-        let mut reconstructed_c: Scalar = signature.challenge;
-        // Row count of matrix
-        let nr = signature.ring.len();
-        // Column count of matrix
-        let nc = signature.ring[0].len();
-        for _i in 0..nr {
-            let mut h: Hash = Hash::default();
-            h.update(message);
-
-            for j in 0..nc {
-                if signature.b {
-                    h.update(
-                        RistrettoPoint::multiscalar_mul(
-                            &[signature.responses[_i][j], reconstructed_c],
-                            &[
-                                constants::RISTRETTO_BASEPOINT_POINT,
-                                signature.ring[_i][j].1
-                            ]
-                        )
-                            .compress()
-                            .as_bytes(),
-                    );
-
-                    h.update(
-                        RistrettoPoint::multiscalar_mul(
-                            &[signature.responses[_i][j], reconstructed_c],
-                            &[
-                                signature.ring[_i][j].2 * RistrettoPoint::from_hash(
-                                    Hash::default().chain_update(
-                                        signature.ring[_i][j].0.compress().as_bytes()
-                                    )
-                                ),
-                                signature.key_images[j]
-                            ])
-                            .compress()
-                            .as_bytes(),
-                    );
-                } else {
-                    h.update(
-                        RistrettoPoint::multiscalar_mul(
-                            &[signature.responses[_i][j], reconstructed_c],
-                            &[constants::RISTRETTO_BASEPOINT_POINT, signature.ring[_i][j].0
-                            ]
-                        )
-                            .compress()
-                            .as_bytes(),
-                    );
-
-                    h.update(
-                        RistrettoPoint::multiscalar_mul(
-                            &[signature.responses[_i][j], reconstructed_c],
-                            &[
-                                signature.ring[_i][j].2 * RistrettoPoint::from_hash(
-                                    Hash::default().chain_update(
-                                        signature.ring[_i][j].1.compress().as_bytes()
-                                    )
-                                ),
-                                signature.key_images[j]
-                            ]
-                        )
-                            .compress()
-                            .as_bytes(),
-                    );
-                }
-            }
-            reconstructed_c = Scalar::from_hash(h);
+        // Signatures built by hand may not have one response per member
+        if self.check_shape().is_err() {
+            return false;
+        }
+        let n = self.ring.len();
+        let nc = self.key_images.len();
+        let message_hash: Hash = compute_challenge_prefix::<Hash>(message);
+        let mut points = allocator_api2::vec::Vec::with_capacity_in(nc, alloc);
+        let reconstructed_c: Scalar =
+            self.reconstruct_challenge_in::<Hash, Basepoint, _>(message_hash, 0..n, self.challenge.0, &mut points);
+        return self.challenge.0 == reconstructed_c;
+    }
+}
+
+impl VerifyWithGenerator for MDLSAG {
+    fn verify_with_generator<Hash: Digest<OutputSize = U64> + Clone + Default, G: Generator>(
+        &self,
+        message: &Vec<u8>,
+    ) -> bool {
+        // Signatures built by hand may not have one response per member
+        if self.check_shape().is_err() {
+            return false;
         }
+        let n = self.ring.len();
+        let message_hash: Hash = compute_challenge_prefix::<Hash>(message);
+        let reconstructed_c: Scalar =
+            self.reconstruct_challenge_with_generator::<Hash, G>(message_hash, 0..n, self.challenge.0);
+        return self.challenge.0 == reconstructed_c;
+    }
+}
+
+#[cfg(feature = "sign")]
+impl TrySign<Vec<(Scalar, RistrettoPoint, Scalar)>, Vec<Vec<(RistrettoPoint, RistrettoPoint, Scalar)>>> for MDLSAG {
+    fn check_sign_input(
+        ks: &Vec<(Scalar, RistrettoPoint, Scalar)>,
+        ring: &Vec<Vec<(RistrettoPoint, RistrettoPoint, Scalar)>>,
+        secret_index: usize,
+    ) -> Result<(), Error> {
+        check_columns(ks.len(), ring)?;
+        return check_secret_index(ring.len(), secret_index);
+    }
+}
+
+#[cfg(feature = "sign")]
+impl TrySign<Vec<(RistrettoPoint, Scalar, Scalar)>, Vec<Vec<(RistrettoPoint, RistrettoPoint, Scalar)>>> for MDLSAG {
+    fn check_sign_input(
+        ks: &Vec<(RistrettoPoint, Scalar, Scalar)>,
+        ring: &Vec<Vec<(RistrettoPoint, RistrettoPoint, Scalar)>>,
+        secret_index: usize,
+    ) -> Result<(), Error> {
+        check_columns(ks.len(), ring)?;
+        return check_secret_index(ring.len(), secret_index);
+    }
+}
+
+#[cfg(feature = "sign")]
+impl SignWithFullRing<Vec<(Scalar, RistrettoPoint, Scalar)>, Vec<Vec<(RistrettoPoint, RistrettoPoint, Scalar)>>> for MDLSAG {
+    fn take_signer(
+        ks: &Vec<(Scalar, RistrettoPoint, Scalar)>,
+        ring: &mut Vec<Vec<(RistrettoPoint, RistrettoPoint, Scalar)>>,
+        secret_index: usize,
+    ) -> Result<(), Error> {
+        let k_points: Vec<(RistrettoPoint, RistrettoPoint, Scalar)> = ks
+            .iter()
+            .map(|k| (k.0 * constants::RISTRETTO_BASEPOINT_POINT, k.1, k.2))
+            .collect();
+        return take_signer(ring, secret_index, &k_points);
+    }
+}
+
+#[cfg(feature = "sign")]
+impl SignWithFullRing<Vec<(RistrettoPoint, Scalar, Scalar)>, Vec<Vec<(RistrettoPoint, RistrettoPoint, Scalar)>>> for MDLSAG {
+    fn take_signer(
+        ks: &Vec<(RistrettoPoint, Scalar, Scalar)>,
+        ring: &mut Vec<Vec<(RistrettoPoint, RistrettoPoint, Scalar)>>,
+        secret_index: usize,
+    ) -> Result<(), Error> {
+        let k_points: Vec<(RistrettoPoint, RistrettoPoint, Scalar)> = ks
+            .iter()
+            .map(|k| (k.0, k.1 * constants::RISTRETTO_BASEPOINT_POINT, k.2))
+            .collect();
+        return take_signer(ring, secret_index, &k_points);
+    }
+}
+
+#[cfg(feature = "sign")]
+impl Scheme for MDLSAG {
+    type SecretKey = MDLSAGKey;
+    type Ring = Vec<Vec<(RistrettoPoint, RistrettoPoint, Scalar)>>;
+
+    fn sign_with_key<Hash: Digest<OutputSize = U64> + Clone + Default, CSPRNG: CryptoRng + RngCore + Default>(
+        k: MDLSAGKey,
+        ring: Vec<Vec<(RistrettoPoint, RistrettoPoint, Scalar)>>,
+        secret_index: usize,
+        message: &Vec<u8>,
+    ) -> Result<MDLSAG, Error> {
+        let mut csprng = CSPRNG::default();
+        return match k {
+            MDLSAGKey::Left(ks) => MDLSAG::sign_with_rng::<Hash, CSPRNG>(ks, ring, secret_index, message, &mut csprng),
+            MDLSAGKey::Right(ks) => MDLSAG::sign_with_rng::<Hash, CSPRNG>(ks, ring, secret_index, message, &mut csprng),
+        };
+    }
+
+    fn try_sign_with_key_and_rng<Hash: Digest<OutputSize = U64> + Clone + Default, CSPRNG: CryptoRng + RngCore>(
+        k: MDLSAGKey,
+        ring: Vec<Vec<(RistrettoPoint, RistrettoPoint, Scalar)>>,
+        secret_index: usize,
+        message: &Vec<u8>,
+        csprng: &mut CSPRNG,
+    ) -> Result<MDLSAG, Error> {
+        return match k {
+            MDLSAGKey::Left(ks) => {
+                MDLSAG::sign_with_rng_unchecked::<Hash, CSPRNG>(ks, ring, secret_index, message, csprng)
+            }
+            MDLSAGKey::Right(ks) => {
+                MDLSAG::sign_with_rng_unchecked::<Hash, CSPRNG>(ks, ring, secret_index, message, csprng)
+            }
+        };
+    }
+}
 
-        return signature.challenge == reconstructed_c;
+impl TryVerify for MDLSAG {
+    fn check_shape(&self) -> Result<(), Error> {
+        check_responses(self.ring.len(), self.responses.len())?;
+        check_columns(self.key_images.len(), &self.ring)?;
+        if self.responses.iter().any(|row| row.len() != self.key_images.len()) {
+            return Err(Error::ResponseCountMismatch);
+        }
+        return Ok(());
     }
 }
 
-impl Link for MDLSAG {
+impl LinkRef for MDLSAG {
     /// This is for linking two signatures and checking if they are signed by the same person
-    fn link(signature_1: MDLSAG, signature_2: MDLSAG) -> bool {
+    fn link_ref(&self, other: &MDLSAG) -> bool {
         let mut vec: Vec<[u8; 32]> = Vec::new();
         vec.append(
-            &mut signature_1
+            &mut self
                 .key_images
                 .iter()
                 .map(|x| x.compress().to_bytes())
                 .collect(),
         );
         vec.append(
-            &mut signature_2
+            &mut other
                 .key_images
                 .iter()
                 .map(|x| x.compress().to_bytes())
@@ -437,8 +679,119 @@ impl Link for MDLSAG {
     }
 }
 
+impl Weight for MDLSAG {
+    /// Approximate cost of verifying this signature, see [`crate::weight`]
+    fn weight(&self) -> u64 {
+        return weight::mdlsag(self.ring.len(), self.ring.first().map_or(0, |row| row.len()));
+    }
+}
+
+impl MDLSAG {
+    /// Assembles a signature from its fields, returning `None` if the ring, responses and
+    /// key images do not agree on the row and column counts
+    pub fn from_parts(
+        challenge: Challenge,
+        responses: Vec<Vec<Response>>,
+        ring: Vec<Vec<(RistrettoPoint, RistrettoPoint, Scalar)>>,
+        key_images: Vec<RistrettoPoint>,
+        b: bool,
+    ) -> Option<MDLSAG> {
+        let nc = key_images.len();
+        if ring.is_empty()
+            || nc == 0
+            || responses.len() != ring.len()
+            || !ring.iter().all(|row| row.len() == nc)
+            || !responses.iter().all(|row| row.len() == nc)
+        {
+            return None;
+        }
+        return Some(MDLSAG::from_parts_unchecked(challenge, responses, ring, key_images, b));
+    }
+
+    /// Assembles a signature from its fields without checking them
+    ///
+    /// This is for fields that have been checked before, e.g. by [`MDLSAG::from_parts`] or by
+    /// decoding, and are read back from storage that is trusted. A signature assembled from
+    /// fields that do not agree is rejected: verifying it returns false.
+    pub fn from_parts_unchecked(
+        challenge: Challenge,
+        responses: Vec<Vec<Response>>,
+        ring: Vec<Vec<(RistrettoPoint, RistrettoPoint, Scalar)>>,
+        key_images: Vec<RistrettoPoint>,
+        b: bool,
+    ) -> MDLSAG {
+        return MDLSAG {
+            challenge: challenge,
+            responses: responses,
+            ring: ring,
+            key_images: key_images,
+            b: b,
+        };
+    }
+}
+
+impl Codec for MDLSAG {
+    /// Encodes the signature canonically, see [`crate::codec`]
+    fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes: Vec<u8> = Vec::new();
+        codec::write_challenge(&mut bytes, &self.challenge);
+        codec::write_u32(&mut bytes, self.ring.len());
+        codec::write_u32(&mut bytes, self.key_images.len());
+        for row in &self.responses {
+            for response in row {
+                codec::write_response(&mut bytes, response);
+            }
+        }
+        for row in &self.ring {
+            for member in row {
+                codec::write_point(&mut bytes, &member.0);
+                codec::write_point(&mut bytes, &member.1);
+                codec::write_scalar(&mut bytes, &member.2);
+            }
+        }
+        for key_image in &self.key_images {
+            codec::write_point(&mut bytes, key_image);
+        }
+        codec::write_bool(&mut bytes, self.b);
+        return bytes;
+    }
+
+    /// Decodes a signature, returning the [`Error`] describing why if `bytes` is not a canonical encoding
+    fn decode(bytes: &[u8]) -> Result<MDLSAG, Error> {
+        let mut reader = Reader::new(bytes);
+        let challenge = reader.read_challenge()?;
+        let nr = reader.read_len(1)?;
+        let nc = reader.read_len(1)?;
+        let responses = (0..nr)
+            .map(|_| reader.read_responses(nc))
+            .collect::<Result<Vec<Vec<Response>>, Error>>()?;
+        let ring = (0..nr)
+            .map(|_| {
+                (0..nc)
+                    .map(|_| Ok((reader.read_point()?, reader.read_point()?, reader.read_scalar()?)))
+                    .collect::<Result<Vec<(RistrettoPoint, RistrettoPoint, Scalar)>, Error>>()
+            })
+            .collect::<Result<Vec<Vec<(RistrettoPoint, RistrettoPoint, Scalar)>>, Error>>()?;
+        let key_images = reader.read_points(nc)?;
+        let b = reader.read_bool()?;
+        reader.finish()?;
+        let signature = MDLSAG {
+            challenge: challenge,
+            responses: responses,
+            ring: ring,
+            key_images: key_images,
+            b: b,
+        };
+        signature.check_shape()?;
+        return Ok(signature);
+    }
+}
+
+crate::encoding::display_via_codec!(MDLSAG);
+
 #[cfg(test)]
 #[cfg(feature = "std")]
+#[cfg(feature = "sign")]
 mod test {
     extern crate blake2;
     extern crate rand;
@@ -446,6 +799,7 @@ mod test {
     extern crate sha3;
 
     use super::*;
+    use crate::traits::KeyImageGen;
     use blake2::Blake2b512;
     use curve25519_dalek::ristretto::RistrettoPoint;
     use curve25519_dalek::scalar::Scalar;
@@ -495,56 +849,80 @@ mod test {
         let message: Vec<u8> = b"This is the message".iter().cloned().collect();
 
         {
-            let signature =
-                MDLSAG::sign::<Sha512, OsRng>(ks.clone(), ring.clone(), secret_index, &message);
-            let result = MDLSAG::verify::<Sha512>(signature, &message);
+            let signature = MDLSAG::sign_with_rng_unchecked::<Sha512, _>(
+                ks.clone(),
+                ring.clone(),
+                secret_index,
+                &message,
+                &mut OsRng,
+            )
+            .unwrap();
+            let result = signature.verify_ref::<Sha512>(&message);
             assert!(result);
         }
 
         {
-            let signature =
-                MDLSAG::sign::<Keccak512, OsRng>(ks.clone(), ring.clone(), secret_index, &message);
-            let result = MDLSAG::verify::<Keccak512>(signature, &message);
+            let signature = MDLSAG::sign_with_rng_unchecked::<Keccak512, _>(
+                ks.clone(),
+                ring.clone(),
+                secret_index,
+                &message,
+                &mut OsRng,
+            )
+            .unwrap();
+            let result = signature.verify_ref::<Keccak512>(&message);
             assert!(result);
         }
 
         {
-            let signature =
-                MDLSAG::sign::<Blake2b512, OsRng>(ks.clone(), ring.clone(), secret_index, &message);
-            let result = MDLSAG::verify::<Blake2b512>(signature, &message);
+            let signature = MDLSAG::sign_with_rng_unchecked::<Blake2b512, _>(
+                ks.clone(),
+                ring.clone(),
+                secret_index,
+                &message,
+                &mut OsRng,
+            )
+            .unwrap();
+            let result = signature.verify_ref::<Blake2b512>(&message);
             assert!(result);
         }
 
         {
-            let signature = MDLSAG::sign::<Sha512, OsRng>(
+            let signature = MDLSAG::sign_with_rng_unchecked::<Sha512, _>(
                 other_ks.clone(),
                 ring.clone(),
                 secret_index,
                 &message,
-            );
-            let result = MDLSAG::verify::<Sha512>(signature, &message);
+                &mut OsRng,
+            )
+            .unwrap();
+            let result = signature.verify_ref::<Sha512>(&message);
             assert!(result);
         }
 
         {
-            let signature = MDLSAG::sign::<Keccak512, OsRng>(
+            let signature = MDLSAG::sign_with_rng_unchecked::<Keccak512, _>(
                 other_ks.clone(),
                 ring.clone(),
                 secret_index,
                 &message,
-            );
-            let result = MDLSAG::verify::<Keccak512>(signature, &message);
+                &mut OsRng,
+            )
+            .unwrap();
+            let result = signature.verify_ref::<Keccak512>(&message);
             assert!(result);
         }
 
         {
-            let signature = MDLSAG::sign::<Blake2b512, OsRng>(
+            let signature = MDLSAG::sign_with_rng_unchecked::<Blake2b512, _>(
                 other_ks.clone(),
                 ring.clone(),
                 secret_index,
                 &message,
-            );
-            let result = MDLSAG::verify::<Blake2b512>(signature, &message);
+                &mut OsRng,
+            )
+            .unwrap();
+            let result = signature.verify_ref::<Blake2b512>(&message);
             assert!(result);
         }
 
@@ -563,19 +941,94 @@ mod test {
                 })
                 .collect();
         let another_message: Vec<u8> = b"This is another message".iter().cloned().collect();
-        let signature_1 = MDLSAG::sign::<Blake2b512, OsRng>(
+        let signature_1 = MDLSAG::sign_with_rng_unchecked::<Blake2b512, _>(
             ks.clone(),
             another_ring.clone(),
             secret_index,
             &another_message,
-        );
-        let signature_2 =
-            MDLSAG::sign::<Blake2b512, OsRng>(ks.clone(), ring.clone(), secret_index, &message);
-        let signature_3 =
-            MDLSAG::sign::<Blake2b512, OsRng>(other_ks.clone(), ring.clone(), secret_index, &message);
-        let result_1 = MDLSAG::link(signature_1.clone(), signature_2);
+            &mut OsRng,
+        )
+        .unwrap();
+        let signature_2 = MDLSAG::sign_with_rng_unchecked::<Blake2b512, _>(
+            ks.clone(),
+            ring.clone(),
+            secret_index,
+            &message,
+            &mut OsRng,
+        )
+        .unwrap();
+        let signature_3 = MDLSAG::sign_with_rng_unchecked::<Blake2b512, _>(
+            other_ks.clone(),
+            ring.clone(),
+            secret_index,
+            &message,
+            &mut OsRng,
+        )
+        .unwrap();
+        let result_1 = signature_1.link_ref(&signature_2);
         assert!(result_1);
-        let result_2 = MDLSAG::link(signature_1.clone(), signature_3);
+        let result_2 = signature_1.link_ref(&signature_3);
         assert!(result_2);
     }
+    #[test]
+    fn generate_key_image_ref() {
+        let ks: Vec<(Scalar, RistrettoPoint, Scalar)> = (0..2)
+            .map(|_| (Scalar::random(&mut OsRng), RistrettoPoint::random(&mut OsRng), Scalar::random(&mut OsRng)))
+            .collect();
+        let member = |_| {
+            (
+                RistrettoPoint::random(&mut OsRng),
+                RistrettoPoint::random(&mut OsRng),
+                Scalar::random(&mut OsRng),
+            )
+        };
+        let ring: Vec<Vec<(RistrettoPoint, RistrettoPoint, Scalar)>> = vec![(0..2).map(member).collect()];
+        let message: Vec<u8> = b"This is the message".to_vec();
+
+        let key_images = MDLSAG::generate_key_image_ref::<Sha512>(&ks);
+        #[allow(deprecated)]
+        let by_value = MDLSAG::generate_key_image::<Sha512>(ks.clone());
+        assert_eq!(key_images, by_value);
+        let signature = MDLSAG::sign_with_rng_unchecked::<Sha512, _>(ks, ring, 1, &message, &mut OsRng).unwrap();
+        assert_eq!(signature.key_images, key_images);
+    }
+
+    #[test]
+    fn does_not_panic() {
+        use crate::link::LinkingKeyImage;
+
+        let key = |_| (Scalar::random(&mut OsRng), RistrettoPoint::random(&mut OsRng), Scalar::random(&mut OsRng));
+        let member =
+            |_| (RistrettoPoint::random(&mut OsRng), RistrettoPoint::random(&mut OsRng), Scalar::random(&mut OsRng));
+        let ks: Vec<(Scalar, RistrettoPoint, Scalar)> = (0..2).map(key).collect();
+        let ring: Vec<Vec<(RistrettoPoint, RistrettoPoint, Scalar)>> = vec![(0..2).map(member).collect()];
+        let message: Vec<u8> = b"This is the message".to_vec();
+
+        // Signing returns an error for what it can not sign with
+        let out_of_range =
+            MDLSAG::sign_with_rng_unchecked::<Sha512, _>(ks.clone(), ring.clone(), 2, &message, &mut OsRng);
+        assert_eq!(out_of_range.err(), Some(Error::SecretIndexOutOfRange { index: 2, ring_size: 2 }));
+        let ragged = vec![ring[0][..1].to_vec()];
+        let ragged = MDLSAG::sign_with_rng_unchecked::<Sha512, _>(ks.clone(), ragged, 0, &message, &mut OsRng);
+        assert_eq!(ragged.err(), Some(Error::RaggedRingMatrix));
+        let no_keys: Vec<(Scalar, RistrettoPoint, Scalar)> = Vec::new();
+        let keyless = MDLSAG::sign_with_rng_unchecked::<Sha512, _>(no_keys, Vec::new(), 0, &message, &mut OsRng);
+        assert_eq!(keyless.err(), Some(Error::EmptyRing));
+
+        // Signatures built by hand without key images or a ring verify, link and run their chain
+        // to a refusal
+        let signature = MDLSAG::sign_with_rng_unchecked::<Sha512, _>(ks, ring, 0, &message, &mut OsRng).unwrap();
+        let challenge = signature.challenge.0;
+        let mut imageless = signature.clone();
+        imageless.key_images.clear();
+        assert!(!imageless.verify_ref::<Sha512>(&message));
+        assert!(!imageless.link_ref(&imageless));
+        assert!(imageless.linking_key_image().is_none());
+        assert_eq!(imageless.reconstruct_challenge::<Sha512>(&message, 0..2, challenge), None);
+        let mut ringless = signature;
+        ringless.ring.clear();
+        ringless.responses.clear();
+        assert!(!ringless.verify_ref::<Sha512>(&message));
+        assert_eq!(ringless.reconstruct_challenge::<Sha512>(&message, 0..0, challenge), None);
+    }
 }
@@ -0,0 +1,251 @@
+//! Merkle rings
+//!
+//! A ring committed to by a flat digest, such as the ring digest of a bLSAG, can only be checked
+//! by hashing every member. A [`MerkleRing`] commits to the ring by the root of a Merkle tree
+//! over its members instead, so that a light client holding only the root can check that a
+//! member was part of the ring from a [`MemberProof`] of logarithmic size, see
+//! [`MerkleRing::prove_member`] and [`check_member_proof`]. This lets it audit the members a
+//! signer claims for its anonymity set without downloading the ring.
+//!
+//! Leaves and inner nodes are hashed under distinct domains, and the root binds the ring size,
+//! so neither a node nor a ring of another size can pass as the ring. A node without a sibling,
+//! the last of a level of odd size, moves up unchanged.
+
+use crate::prelude::*;
+
+use core::marker::PhantomData;
+
+use curve25519_dalek::ristretto::RistrettoPoint;
+use digest::generic_array::typenum::U64;
+use digest::Digest;
+
+use crate::codec::{self, Codec, Reader};
+use crate::error::Error;
+
+const LEAF: &[u8] = b"nazgul/merkle/leaf";
+const NODE: &[u8] = b"nazgul/merkle/node";
+const ROOT: &[u8] = b"nazgul/merkle/root";
+
+fn hash_leaf<Hash: Digest<OutputSize = U64> + Default>(member: &RistrettoPoint) -> [u8; 64] {
+    let mut h: Hash = Hash::default();
+    h.update(LEAF);
+    h.update(member.compress().as_bytes());
+    return finalize(h);
+}
+
+fn hash_node<Hash: Digest<OutputSize = U64> + Default>(left: &[u8; 64], right: &[u8; 64]) -> [u8; 64] {
+    let mut h: Hash = Hash::default();
+    h.update(NODE);
+    h.update(left);
+    h.update(right);
+    return finalize(h);
+}
+
+fn hash_root<Hash: Digest<OutputSize = U64> + Default>(ring_size: usize, top: &[u8; 64]) -> [u8; 64] {
+    let mut h: Hash = Hash::default();
+    h.update(ROOT);
+    h.update((ring_size as u32).to_le_bytes());
+    h.update(top);
+    return finalize(h);
+}
+
+fn finalize<Hash: Digest<OutputSize = U64>>(h: Hash) -> [u8; 64] {
+    let mut digest = [0u8; 64];
+    digest.copy_from_slice(&h.finalize());
+    return digest;
+}
+
+/// A ring together with the Merkle tree over its members under `Hash`
+#[derive(Clone)]
+pub struct MerkleRing<Hash> {
+    ring: Vec<RistrettoPoint>,
+    // Every level of the tree, from the leaves up to the single top node
+    levels: Vec<Vec<[u8; 64]>>,
+    root: [u8; 64],
+    // A `fn` so that the ring is `Send` and `Sync` whatever `Hash` is
+    _hash: PhantomData<fn() -> Hash>,
+}
+
+impl<Hash: Digest<OutputSize = U64> + Clone + Default> MerkleRing<Hash> {
+    /// Builds the tree over the complete `ring`, the signer included. Returns
+    /// [`Error::EmptyRing`] if `ring` is empty
+    pub fn new(ring: Vec<RistrettoPoint>) -> Result<MerkleRing<Hash>, Error> {
+        if ring.is_empty() {
+            return Err(Error::EmptyRing);
+        }
+        let mut levels: Vec<Vec<[u8; 64]>> = vec![ring.iter().map(hash_leaf::<Hash>).collect()];
+        while levels[levels.len() - 1].len() > 1 {
+            let level: Vec<[u8; 64]> = levels[levels.len() - 1]
+                .chunks(2)
+                .map(|pair| match pair {
+                    [left, right] => hash_node::<Hash>(left, right),
+                    _ => pair[0],
+                })
+                .collect();
+            levels.push(level);
+        }
+        let root: [u8; 64] = hash_root::<Hash>(ring.len(), &levels[levels.len() - 1][0]);
+        return Ok(MerkleRing {
+            ring: ring,
+            levels: levels,
+            root: root,
+            _hash: PhantomData,
+        });
+    }
+
+    pub fn ring(&self) -> &[RistrettoPoint] {
+        return &self.ring;
+    }
+
+    /// The commitment to the ring light clients hold
+    pub fn root(&self) -> &[u8; 64] {
+        return &self.root;
+    }
+
+    /// The proof that the member at `index` is part of the ring, or `None` if there is no such
+    /// member
+    pub fn prove_member(&self, index: usize) -> Option<MemberProof> {
+        let member: RistrettoPoint = *self.ring.get(index)?;
+        let mut siblings: Vec<[u8; 64]> = Vec::new();
+        let mut position: usize = index;
+        for level in &self.levels[..self.levels.len() - 1] {
+            if let Some(sibling) = level.get(position ^ 1) {
+                siblings.push(*sibling);
+            }
+            position /= 2;
+        }
+        return Some(MemberProof {
+            index: index,
+            ring_size: self.ring.len(),
+            member: member,
+            siblings: siblings,
+        });
+    }
+}
+
+/// The path from a ring member to the root of a [`MerkleRing`]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct MemberProof {
+    pub index: usize,
+    pub ring_size: usize,
+    pub member: RistrettoPoint,
+    /// The sibling of each node on the path, from the leaf up, leaving out nodes without one
+    pub siblings: Vec<[u8; 64]>,
+}
+
+impl Codec for MemberProof {
+    fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes: Vec<u8> = Vec::new();
+        codec::write_u32(&mut bytes, self.index);
+        codec::write_u32(&mut bytes, self.ring_size);
+        codec::write_point(&mut bytes, &self.member);
+        codec::write_u32(&mut bytes, self.siblings.len());
+        for sibling in &self.siblings {
+            bytes.extend_from_slice(sibling);
+        }
+        return bytes;
+    }
+
+    fn decode(bytes: &[u8]) -> Result<MemberProof, Error> {
+        let mut reader = Reader::new(bytes);
+        let index = reader.read_len(0)?;
+        let ring_size = reader.read_len(0)?;
+        let member = reader.read_point()?;
+        let n = reader.read_len(64)?;
+        let mut siblings: Vec<[u8; 64]> = Vec::with_capacity(n);
+        for _ in 0..n {
+            let mut sibling = [0u8; 64];
+            sibling.copy_from_slice(reader.read_bytes(64)?);
+            siblings.push(sibling);
+        }
+        reader.finish()?;
+        return Ok(MemberProof {
+            index: index,
+            ring_size: ring_size,
+            member: member,
+            siblings: siblings,
+        });
+    }
+}
+
+/// Whether `proof` shows its member to be part of the ring committed to by `root`
+pub fn check_member_proof<Hash: Digest<OutputSize = U64> + Clone + Default>(
+    root: &[u8; 64],
+    proof: &MemberProof,
+) -> bool {
+    if proof.index >= proof.ring_size {
+        return false;
+    }
+    let mut siblings = proof.siblings.iter();
+    let mut node: [u8; 64] = hash_leaf::<Hash>(&proof.member);
+    let mut position: usize = proof.index;
+    let mut level_size: usize = proof.ring_size;
+    while level_size > 1 {
+        if position % 2 == 1 {
+            match siblings.next() {
+                Some(sibling) => node = hash_node::<Hash>(sibling, &node),
+                None => return false,
+            }
+        } else if position + 1 < level_size {
+            match siblings.next() {
+                Some(sibling) => node = hash_node::<Hash>(&node, sibling),
+                None => return false,
+            }
+        }
+        position /= 2;
+        level_size = level_size.div_ceil(2);
+    }
+    return siblings.next().is_none() && hash_root::<Hash>(proof.ring_size, &node) == *root;
+}
+
+#[cfg(test)]
+#[cfg(feature = "std")]
+#[cfg(feature = "sign")]
+mod test {
+    extern crate rand;
+    extern crate sha2;
+
+    use rand::rngs::OsRng;
+    use sha2::Sha512;
+
+    use super::*;
+
+    #[test]
+    fn prove_member() {
+        for n in 1..12 {
+            let ring: Vec<RistrettoPoint> = (0..n).map(|_| RistrettoPoint::random(&mut OsRng)).collect();
+            let merkle_ring = MerkleRing::<Sha512>::new(ring).unwrap();
+            for i in 0..n {
+                let proof = merkle_ring.prove_member(i).unwrap();
+                assert!(check_member_proof::<Sha512>(merkle_ring.root(), &proof));
+                assert_eq!(MemberProof::decode(&proof.to_bytes()), Ok(proof));
+            }
+            assert!(merkle_ring.prove_member(n).is_none());
+        }
+        assert_eq!(MerkleRing::<Sha512>::new(vec![]).err(), Some(Error::EmptyRing));
+    }
+
+    #[test]
+    fn check_member_proof_rejects() {
+        let ring: Vec<RistrettoPoint> = (0..5).map(|_| RistrettoPoint::random(&mut OsRng)).collect();
+        let merkle_ring = MerkleRing::<Sha512>::new(ring.clone()).unwrap();
+        let proof = merkle_ring.prove_member(2).unwrap();
+
+        let mut outsider = proof.clone();
+        outsider.member = RistrettoPoint::random(&mut OsRng);
+        assert!(!check_member_proof::<Sha512>(merkle_ring.root(), &outsider));
+        let mut moved = proof.clone();
+        moved.index = 3;
+        assert!(!check_member_proof::<Sha512>(merkle_ring.root(), &moved));
+        let mut resized = proof.clone();
+        resized.ring_size = 6;
+        assert!(!check_member_proof::<Sha512>(merkle_ring.root(), &resized));
+        let mut extended = proof.clone();
+        extended.siblings.push([0u8; 64]);
+        assert!(!check_member_proof::<Sha512>(merkle_ring.root(), &extended));
+
+        // A ring without the member commits to another root
+        let other = MerkleRing::<Sha512>::new(ring[..4].to_vec()).unwrap();
+        assert!(!check_member_proof::<Sha512>(other.root(), &proof));
+    }
+}
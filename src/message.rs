@@ -0,0 +1,123 @@
+//! Typed messages
+//!
+//! `sign` and `verify` take the message as plain bytes, so the same bytes signed as data and as a
+//! digest of other data produce interchangeable signatures. A [`Message`] carries its content
+//! type, and [`sign`] and [`verify`] separate messages by domain: what is signed is the prefix
+//! `"nazgul/message"`, then a tag for the content type, then the content. The challenges are
+//! computed over those bytes as over any other message. A signature over a prehashed digest
+//! therefore never verifies as a signature over raw bytes of the same value, nor as a signature
+//! made with plain `sign` over the content alone.
+
+use crate::prelude::*;
+
+use digest::generic_array::typenum::U64;
+use digest::Digest;
+#[cfg(feature = "sign")]
+use rand_core::{CryptoRng, RngCore};
+
+#[cfg(feature = "sign")]
+use crate::error::Error;
+use crate::traits::VerifyRef;
+#[cfg(feature = "sign")]
+use crate::traits::{Decoys, SignWithRng};
+
+/// Prefix of every encoded message, keeping them apart from any untyped message
+const DOMAIN: &[u8] = b"nazgul/message";
+
+/// A message together with the type of its content
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Message<'a> {
+    /// Arbitrary bytes, signed as they are
+    Raw(&'a [u8]),
+    /// The output of hashing the actual message, e.g. a 64 byte digest
+    Prehashed(&'a [u8]),
+    /// A CBOR encoded structure, which must be in deterministic encoding to be verified again
+    Cbor(&'a [u8]),
+}
+
+impl<'a> Message<'a> {
+    /// The content type tag following the domain prefix in the signed bytes
+    pub fn tag(&self) -> u8 {
+        return match self {
+            Message::Raw(_) => 0,
+            Message::Prehashed(_) => 1,
+            Message::Cbor(_) => 2,
+        };
+    }
+
+    pub fn content(&self) -> &'a [u8] {
+        return match self {
+            Message::Raw(content) => content,
+            Message::Prehashed(content) => content,
+            Message::Cbor(content) => content,
+        };
+    }
+
+    /// The bytes actually signed: the domain, the tag and then the content
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes: Vec<u8> = Vec::with_capacity(DOMAIN.len() + 1 + self.content().len());
+        bytes.extend_from_slice(DOMAIN);
+        bytes.push(self.tag());
+        bytes.extend_from_slice(self.content());
+        return bytes;
+    }
+}
+
+/// Signs a typed `message` with any scheme, taking the same arguments as its `sign`
+#[cfg(feature = "sign")]
+pub fn sign<
+    Hash: Digest<OutputSize = U64> + Clone + Default,
+    CSPRNG: CryptoRng + RngCore + Default,
+    PrivateKey,
+    Ring: Decoys,
+    S: SignWithRng<PrivateKey, Ring>,
+>(
+    k: PrivateKey,
+    ring: Ring,
+    secret_index: usize,
+    message: &Message,
+) -> Result<S, Error> {
+    let mut csprng = CSPRNG::default();
+    return S::sign_with_rng::<Hash, CSPRNG>(k, ring, secret_index, &message.to_bytes(), &mut csprng);
+}
+
+/// Verifies a `signature` made with [`sign`] over a typed `message`
+pub fn verify<Hash: Digest<OutputSize = U64> + Clone + Default, S: VerifyRef>(
+    signature: &S,
+    message: &Message,
+) -> bool {
+    return signature.verify_ref::<Hash>(&message.to_bytes());
+}
+
+#[cfg(test)]
+#[cfg(feature = "std")]
+#[cfg(feature = "sign")]
+#[cfg(feature = "lsag")]
+mod test {
+    extern crate rand;
+    extern crate sha2;
+
+    use curve25519_dalek::ristretto::RistrettoPoint;
+    use curve25519_dalek::scalar::Scalar;
+    use rand::rngs::OsRng;
+    use sha2::Sha512;
+
+    use super::*;
+    use crate::lsag::LSAG;
+
+    #[test]
+    fn message() {
+        let mut csprng = OsRng;
+        let k: Scalar = Scalar::random(&mut csprng);
+        let ring: Vec<RistrettoPoint> = (0..10).map(|_| RistrettoPoint::random(&mut csprng)).collect();
+        let digest = Sha512::digest(b"This is the message");
+
+        let signature: LSAG =
+            super::sign::<Sha512, OsRng, _, _, _>(k, ring, 1, &Message::Prehashed(&digest)).unwrap();
+        assert!(super::verify::<Sha512, _>(&signature, &Message::Prehashed(&digest)));
+        // The same bytes as a different content type, or untyped, do not verify
+        assert!(!super::verify::<Sha512, _>(&signature, &Message::Raw(&digest)));
+        assert!(!super::verify::<Sha512, _>(&signature, &Message::Cbor(&digest)));
+        assert!(!signature.verify_ref::<Sha512>(&digest.to_vec()));
+    }
+}
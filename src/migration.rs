@@ -0,0 +1,149 @@
+//! Migrating from MLSAG to CLSAG
+//!
+//! MLSAG and CLSAG sign with the same keys over the same ring matrix, and the first key image of
+//! both is `k_0 H_p(K_0)`. A protocol moving from one to the other may have to publish both
+//! signatures over one transaction for a while. A [`SignerContext`] holds the inputs once and
+//! signs either, and [`consistent_key_images`] checks that a pair of signatures spends the same
+//! key.
+
+use crate::prelude::*;
+
+use curve25519_dalek::ristretto::RistrettoPoint;
+use curve25519_dalek::scalar::Scalar;
+use digest::generic_array::typenum::U64;
+use digest::Digest;
+use rand_core::{CryptoRng, RngCore};
+
+use crate::clsag::CLSAG;
+use crate::error::Error;
+use crate::mlsag::MLSAG;
+use crate::traits::TrySign;
+
+/// The keys, ring and message of a signer, checked once for both schemes. It is deliberately not
+/// `Debug`, so the keys do not end up in logs
+#[derive(Clone)]
+pub struct SignerContext {
+    ks: Vec<Scalar>,
+    ring: Vec<Vec<RistrettoPoint>>,
+    secret_index: usize,
+    message: Vec<u8>,
+}
+
+impl SignerContext {
+    /// `ks` and `ring` as for [`crate::traits::SignWithRng::sign_with_rng`], the ring not including
+    /// the signer
+    ///
+    /// Returns the error [`TrySign::check_sign_input`] gives if the keys and the ring do not
+    /// agree, or `secret_index` is not a position in the ring.
+    pub fn new(
+        ks: Vec<Scalar>,
+        ring: Vec<Vec<RistrettoPoint>>,
+        secret_index: usize,
+        message: Vec<u8>,
+    ) -> Result<SignerContext, Error> {
+        MLSAG::check_sign_input(&ks, &ring, secret_index)?;
+        return Ok(SignerContext {
+            ks: ks,
+            ring: ring,
+            secret_index: secret_index,
+            message: message,
+        });
+    }
+
+    pub fn message(&self) -> &[u8] {
+        return &self.message;
+    }
+
+    pub fn sign_mlsag<Hash: Digest<OutputSize = U64> + Clone + Default, CSPRNG: CryptoRng + RngCore>(
+        &self,
+        csprng: &mut CSPRNG,
+    ) -> Result<MLSAG, Error> {
+        return MLSAG::sign_ref_with_rng::<Hash, CSPRNG>(
+            &self.ks,
+            self.ring.clone(),
+            self.secret_index,
+            &self.message,
+            csprng,
+        );
+    }
+
+    pub fn sign_clsag<Hash: Digest<OutputSize = U64> + Clone + Default, CSPRNG: CryptoRng + RngCore>(
+        &self,
+        csprng: &mut CSPRNG,
+    ) -> Result<CLSAG, Error> {
+        return CLSAG::sign_ref_with_rng::<Hash, CSPRNG>(
+            &self.ks,
+            self.ring.clone(),
+            self.secret_index,
+            &self.message,
+            csprng,
+        );
+    }
+
+    /// Both signatures, with the signer at the same position in both rings
+    pub fn sign_both<Hash: Digest<OutputSize = U64> + Clone + Default, CSPRNG: CryptoRng + RngCore>(
+        &self,
+        csprng: &mut CSPRNG,
+    ) -> Result<(MLSAG, CLSAG), Error> {
+        let mlsag = self.sign_mlsag::<Hash, CSPRNG>(csprng)?;
+        let clsag = self.sign_clsag::<Hash, CSPRNG>(csprng)?;
+        return Ok((mlsag, clsag));
+    }
+}
+
+/// Whether `mlsag` and `clsag` have the same first key image, the one CLSAG links by, i.e. were
+/// signed with the same first key. The other key images differ between the schemes even for the
+/// same keys
+pub fn consistent_key_images(mlsag: &MLSAG, clsag: &CLSAG) -> bool {
+    return match (mlsag.key_images.first(), clsag.key_images.first()) {
+        (Some(mlsag_key_image), Some(clsag_key_image)) => mlsag_key_image == clsag_key_image,
+        _ => false,
+    };
+}
+
+#[cfg(test)]
+#[cfg(feature = "std")]
+mod test {
+    extern crate rand;
+    extern crate sha2;
+
+    use rand::rngs::OsRng;
+    use sha2::Sha512;
+
+    use super::*;
+    use crate::traits::{SignWithRng, VerifyRef};
+
+    #[test]
+    fn signer_context() {
+        let ks: Vec<Scalar> = (0..2).map(|_| Scalar::random(&mut OsRng)).collect();
+        let ring: Vec<Vec<RistrettoPoint>> = (0..3)
+            .map(|_| (0..2).map(|_| RistrettoPoint::random(&mut OsRng)).collect())
+            .collect();
+        let context = SignerContext::new(ks.clone(), ring.clone(), 2, b"This is the message".to_vec()).unwrap();
+
+        let (mlsag, clsag) = context.sign_both::<Sha512, _>(&mut OsRng).unwrap();
+        assert!(mlsag.verify_ref::<Sha512>(&context.message().to_vec()));
+        assert!(clsag.verify_ref::<Sha512>(&context.message().to_vec()));
+        assert_eq!(mlsag.ring, clsag.ring);
+        assert!(consistent_key_images(&mlsag, &clsag));
+        // Only the first key image is shared
+        assert_ne!(mlsag.key_images[1], clsag.key_images[1]);
+
+        // Another signer's CLSAG over the same inputs is caught
+        let other_ks: Vec<Scalar> = (0..2).map(|_| Scalar::random(&mut OsRng)).collect();
+        let other = CLSAG::sign_with_rng_unchecked::<Sha512, _>(
+            other_ks,
+            ring.clone(),
+            2,
+            &context.message().to_vec(),
+            &mut OsRng,
+        )
+        .unwrap();
+        assert!(!consistent_key_images(&mlsag, &other));
+
+        assert_eq!(
+            SignerContext::new(ks[..1].to_vec(), ring, 0, Vec::new()).err(),
+            Some(Error::RaggedRingMatrix)
+        );
+    }
+}
@@ -1,145 +1,323 @@
-use crate::traits::{KeyImageGen, Link, Sign, Verify};
+use crate::error::Error;
+use crate::scalars::{Challenge, Response};
+#[cfg(feature = "sign")]
+use crate::scalars;
+use crate::traits::{
+    check_columns, check_responses, ChallengeChain, KeyImageGenRef, LinkRef, TryVerify, VerifyRef, Weight,
+};
+#[cfg(feature = "sign")]
+use crate::traits::{
+    check_secret_index, take_signer, Scheme, SignWithFullRing, SignWithHiddenIndex, SignWithRng, TrySign,
+};
+use crate::weight;
+use crate::generator::{Basepoint, Generator, VerifyWithGenerator};
+#[cfg(feature = "sign")]
+use crate::generator::SignWithGenerator;
+use crate::hash_to_point::{DefaultHashToPoint, HashToPoint};
+use crate::hash_to_scalar::{HashToScalar, WideReduction};
+use crate::codec::{self, Codec, Reader};
+use crate::ops;
 use crate::prelude::*;
+use crate::scratch::Scratch;
+#[cfg(feature = "allocator-api")]
+use crate::arena::{Allocator, VerifyIn};
+use core::ops::Range;
+#[cfg(feature = "sign")]
 use curve25519_dalek::constants;
 use curve25519_dalek::ristretto::RistrettoPoint;
 use curve25519_dalek::scalar::Scalar;
 use digest::generic_array::typenum::U64;
 use digest::Digest;
+#[cfg(feature = "sign")]
 use rand_core::{CryptoRng, RngCore};
-use curve25519_dalek::traits::MultiscalarMul;
 
 /// Multilayer Linkable Spontaneous Anonymous Group (MLSAG) signatures
 /// > In order to sign transactions, one has to sign with multiple private keys. In
-/// [this paper](https://web.getmonero.org/resources/research-lab/pubs/MRL-0005.pdf),
-/// Shen Noether et al. describe a multi-layered generalization of the bLSAG signature
-/// scheme applicable when we have a set of n · m keys
+/// > [this paper](https://web.getmonero.org/resources/research-lab/pubs/MRL-0005.pdf),
+/// > Shen Noether et al. describe a multi-layered generalization of the bLSAG signature
+/// > scheme applicable when we have a set of n · m keys
 ///
 /// Please read tests at the bottom of the source code for this module for examples on how to use
 /// it
+#[derive(Clone)]
 pub struct MLSAG {
-    pub challenge: Scalar,
-    pub responses: Vec<Vec<Scalar>>,
+    pub challenge: Challenge,
+    pub responses: Vec<Vec<Response>>,
     pub ring: Vec<Vec<RistrettoPoint>>,
     pub key_images: Vec<RistrettoPoint>,
 }
 
-impl KeyImageGen<Vec<Scalar>, Vec<RistrettoPoint>> for MLSAG {
+/// Hash state shared by all challenges `H_n(m, ....)`
+///
+/// # Advanced
+///
+/// This and [`compute_challenge_step`] are the transcript math used by `sign` and `verify`,
+/// exposed for protocols that compute MLSAG challenges over data produced elsewhere. Most users
+/// should only ever need `sign` and `verify`.
+pub fn compute_challenge_prefix<Hash: Digest<OutputSize = U64> + Clone + Default>(
+    message: &[u8],
+) -> Hash {
+    let mut message_hash = Hash::default();
+    message_hash.update(message);
+    return message_hash;
+}
+
+/// The challenge `c_(i+1) = H_n(m, L_1, R_1, ..., L_nc, R_nc)` where `points` holds the pair
+/// `L_j = r_i,j G + c_i K_i,j` and `R_j = r_i,j H_p(K_i,j) + c_i K~_j` for every column `j`,
+/// given the `hash_state` from [`compute_challenge_prefix`]
+///
+/// # Advanced
+///
+/// See [`compute_challenge_prefix`]
+pub fn compute_challenge_step<Hash: Digest<OutputSize = U64>>(
+    hash_state: Hash,
+    points: &[(RistrettoPoint, RistrettoPoint)],
+) -> Scalar {
+    return compute_challenge_step_with::<Hash, WideReduction>(hash_state, points);
+}
+
+/// As [`compute_challenge_step`] with the hash to scalar `Hs`, see [`crate::hash_to_scalar`]
+///
+/// # Advanced
+///
+/// See [`compute_challenge_prefix`]
+pub fn compute_challenge_step_with<Hash: Digest<OutputSize = U64>, Hs: HashToScalar>(
+    mut hash_state: Hash,
+    points: &[(RistrettoPoint, RistrettoPoint)],
+) -> Scalar {
+    for (point_a, point_b) in points {
+        hash_state.update(point_a.compress().as_bytes());
+        hash_state.update(point_b.compress().as_bytes());
+    }
+    return Hs::hash_to_scalar(hash_state);
+}
+
+impl KeyImageGenRef<Vec<Scalar>, Vec<RistrettoPoint>> for MLSAG {
     /// Some signature schemes require the key images to be signed as well.
     /// Use this method to generate them
-    fn generate_key_image<Hash: Digest<OutputSize = U64> + Clone + Default>(
+    fn generate_key_image_ref<Hash: Digest<OutputSize = U64> + Clone + Default>(
+        ks: &Vec<Scalar>,
+    ) -> Vec<RistrettoPoint> {
+        return MLSAG::key_images_with::<Hash, Basepoint, DefaultHashToPoint>(ks);
+    }
+}
+
+#[cfg(feature = "sign")]
+impl SignWithRng<Vec<Scalar>, Vec<Vec<RistrettoPoint>>> for MLSAG {
+    /// To sign you need `ks` which is the set of private keys you want to sign with. The `ring` contains
+    /// public keys for everybody except you. Your public keys will be inserted into it at `secret_index`,
+    /// see [`SignWithHiddenIndex`] to have it drawn at random. The `message` is what you are signing
+    fn sign_with_rng_unchecked<Hash: Digest<OutputSize = U64> + Clone + Default, CSPRNG: CryptoRng + RngCore>(
+        ks: Vec<Scalar>,
+        ring: Vec<Vec<RistrettoPoint>>,
+        secret_index: usize,
+        message: &Vec<u8>,
+        csprng: &mut CSPRNG,
+    ) -> Result<MLSAG, Error> {
+        return MLSAG::sign_with_csprng::<Hash, CSPRNG, Basepoint, DefaultHashToPoint, WideReduction>(
+            &ks,
+            ring,
+            secret_index,
+            message,
+            csprng,
+        );
+    }
+}
+
+#[cfg(feature = "sign")]
+impl SignWithGenerator<Vec<Scalar>, Vec<Vec<RistrettoPoint>>> for MLSAG {
+    fn sign_with_generator_and_rng<
+        Hash: Digest<OutputSize = U64> + Clone + Default,
+        CSPRNG: CryptoRng + RngCore,
+        G: Generator,
+    >(
+        ks: Vec<Scalar>,
+        ring: Vec<Vec<RistrettoPoint>>,
+        secret_index: usize,
+        message: &Vec<u8>,
+        csprng: &mut CSPRNG,
+    ) -> Result<MLSAG, Error> {
+        return MLSAG::sign_with_csprng::<Hash, CSPRNG, G, DefaultHashToPoint, WideReduction>(
+            &ks,
+            ring,
+            secret_index,
+            message,
+            csprng,
+        );
+    }
+}
+
+impl MLSAG {
+    /// As [`KeyImageGenRef::generate_key_image_ref`] with the hash to point `Hp`, see
+    /// [`crate::hash_to_point`]
+    pub fn generate_key_image_with<Hash: Digest<OutputSize = U64> + Clone + Default, Hp: HashToPoint>(
         ks: Vec<Scalar>,
+    ) -> Vec<RistrettoPoint> {
+        return MLSAG::key_images_with::<Hash, Basepoint, Hp>(&ks);
+    }
+
+    /// As [`MLSAG::generate_key_image_with`], from borrowed keys and with the generator `G`
+    fn key_images_with<Hash: Digest<OutputSize = U64> + Clone + Default, G: Generator, Hp: HashToPoint>(
+        ks: &[Scalar],
     ) -> Vec<RistrettoPoint> {
         let nc = ks.len();
 
-        let k_points: Vec<RistrettoPoint> = ks
-            .iter()
-            .map(|k| k * constants::RISTRETTO_BASEPOINT_POINT)
-            .collect();
+        let k_points: Vec<RistrettoPoint> = ks.iter().map(|k| G::public_key(k)).collect();
 
         let key_images: Vec<RistrettoPoint> = (0..nc)
-            .map(|j| {
-                ks[j]
-                    * RistrettoPoint::from_hash(
-                        Hash::default().chain_update(k_points[j].compress().as_bytes()),
-                    )
-            })
+            .map(|j| ops::mul(&ks[j], &Hp::hash_to_point::<Hash>(&k_points[j])))
             .collect();
 
         return key_images;
     }
-}
 
-impl Sign<Vec<Scalar>, Vec<Vec<RistrettoPoint>>> for MLSAG {
-    /// To sign you need `ks` which is the set of private keys you want to sign with. The `ring` contains
-    /// public keys for everybody except you. Your public key will be inserted into it at random (secret)
-    /// index. The `message` is what you are signing
-    fn sign<
+    /// As [`crate::traits::Sign::sign`] with borrowed keys, which are not copied
+    #[cfg(feature = "sign")]
+    pub fn sign_ref<Hash: Digest<OutputSize = U64> + Clone + Default, CSPRNG: CryptoRng + RngCore + Default>(
+        ks: &[Scalar],
+        ring: Vec<Vec<RistrettoPoint>>,
+        secret_index: usize,
+        message: &Vec<u8>,
+    ) -> Result<MLSAG, Error> {
+        let mut csprng = CSPRNG::default();
+        return MLSAG::sign_ref_with_rng::<Hash, CSPRNG>(ks, ring, secret_index, message, &mut csprng);
+    }
+
+    /// As [`SignWithRng::sign_with_rng`] with borrowed keys, which are not copied
+    #[cfg(feature = "sign")]
+    pub fn sign_ref_with_rng<Hash: Digest<OutputSize = U64> + Clone + Default, CSPRNG: CryptoRng + RngCore>(
+        ks: &[Scalar],
+        ring: Vec<Vec<RistrettoPoint>>,
+        secret_index: usize,
+        message: &Vec<u8>,
+        csprng: &mut CSPRNG,
+    ) -> Result<MLSAG, Error> {
+        return MLSAG::sign_with_csprng::<Hash, CSPRNG, Basepoint, DefaultHashToPoint, WideReduction>(
+            ks,
+            ring,
+            secret_index,
+            message,
+            csprng,
+        );
+    }
+
+    /// As [`crate::traits::Sign::sign`] with the hash to point `Hp` and the hash to scalar `Hs`, see
+    /// [`crate::hash_to_point`] and [`crate::hash_to_scalar`]
+    #[cfg(feature = "sign")]
+    pub fn sign_with<
         Hash: Digest<OutputSize = U64> + Clone + Default,
         CSPRNG: CryptoRng + RngCore + Default,
+        Hp: HashToPoint,
+        Hs: HashToScalar,
     >(
         ks: Vec<Scalar>,
-        mut ring: Vec<Vec<RistrettoPoint>>,
+        ring: Vec<Vec<RistrettoPoint>>,
         secret_index: usize,
         message: &Vec<u8>,
-    ) -> MLSAG {
+    ) -> Result<MLSAG, Error> {
         let mut csprng = CSPRNG::default();
+        return MLSAG::sign_with_csprng::<Hash, CSPRNG, Basepoint, Hp, Hs>(
+            &ks,
+            ring,
+            secret_index,
+            message,
+            &mut csprng,
+        );
+    }
+
+    /// As [`MLSAG::sign_with`] with randomness drawn from `csprng` and the generator `G`
+    #[cfg(feature = "sign")]
+    fn sign_with_csprng<
+        Hash: Digest<OutputSize = U64> + Clone + Default,
+        CSPRNG: CryptoRng + RngCore,
+        G: Generator,
+        Hp: HashToPoint,
+        Hs: HashToScalar,
+    >(
+        ks: &[Scalar],
+        ring: Vec<Vec<RistrettoPoint>>,
+        secret_index: usize,
+        message: &Vec<u8>,
+        csprng: &mut CSPRNG,
+    ) -> Result<MLSAG, Error> {
+        let message_hash: Hash = compute_challenge_prefix::<Hash>(message);
+        return MLSAG::sign_with_prefix::<Hash, CSPRNG, G, Hp, Hs>(ks, ring, secret_index, message_hash, csprng);
+    }
+
+    /// As [`MLSAG::sign_with_csprng`], given the hash state with the message already absorbed, see
+    /// [`crate::session`]
+    #[cfg(feature = "sign")]
+    pub(crate) fn sign_with_prefix<
+        Hash: Digest<OutputSize = U64> + Clone + Default,
+        CSPRNG: CryptoRng + RngCore,
+        G: Generator,
+        Hp: HashToPoint,
+        Hs: HashToScalar,
+    >(
+        ks: &[Scalar],
+        mut ring: Vec<Vec<RistrettoPoint>>,
+        secret_index: usize,
+        message_hash: Hash,
+        csprng: &mut CSPRNG,
+    ) -> Result<MLSAG, Error> {
+        check_columns(ks.len(), &ring)?;
+        check_secret_index(ring.len(), secret_index)?;
 
         // Row count of matrix
         let nr = ring.len() + 1;
         // Column count of matrix
-        let nc = ring[0].len();
+        let nc = ks.len();
+
+        let g: RistrettoPoint = G::generator();
 
         //Provers public keys
-        let k_points: Vec<RistrettoPoint> = ks
-            .iter()
-            .map(|k| k * constants::RISTRETTO_BASEPOINT_POINT)
-            .collect();
+        let k_points: Vec<RistrettoPoint> = ks.iter().map(|k| ops::mul_base(k, &g)).collect();
 
-        let key_images: Vec<RistrettoPoint> = MLSAG::generate_key_image::<Hash>(ks.clone());
+        let key_images: Vec<RistrettoPoint> = MLSAG::key_images_with::<Hash, G, Hp>(ks);
 
         ring.insert(secret_index, k_points.clone());
 
-        let a: Vec<Scalar> = (0..nc).map(|_| Scalar::random(&mut csprng)).collect();
+        let a: Vec<Scalar> = (0..nc).map(|_| Scalar::random(csprng)).collect();
 
         let mut rs: Vec<Vec<Scalar>> = (0..nr)
-            .map(|_| (0..nc).map(|_| Scalar::random(&mut csprng)).collect())
+            .map(|_| (0..nc).map(|_| Scalar::random(csprng)).collect())
             .collect();
 
         let mut cs: Vec<Scalar> = (0..nr).map(|_| Scalar::ZERO).collect();
 
         // Hash of message is shared by all challenges H_n(m, ....)
-        let mut message_hash = Hash::default();
-
-        message_hash.update(message);
-
-        let mut hashes: Vec<Hash> = (0..nr).map(|_| message_hash.clone()).collect();
-
-        for j in 0..nc {
-            hashes[(secret_index + 1) % nr].update(
-                (a[j] * constants::RISTRETTO_BASEPOINT_POINT)
-                    .compress()
-                    .as_bytes(),
-            );
-            hashes[(secret_index + 1) % nr].update(
-                (a[j]
-                    * RistrettoPoint::from_hash(
-                        Hash::default().chain_update(k_points[j].compress().as_bytes()),
-                    ))
-                .compress()
-                .as_bytes(),
-            );
-        }
-        cs[(secret_index + 1) % nr] = Scalar::from_hash(hashes[(secret_index + 1) % nr].clone());
+        cs[(secret_index + 1) % nr] = compute_challenge_step_with::<Hash, Hs>(
+            message_hash.clone(),
+            &(0..nc)
+                .map(|j| {
+                    (
+                        ops::mul_base(&a[j], &g),
+                        ops::mul(&a[j], &Hp::hash_to_point::<Hash>(&k_points[j])),
+                    )
+                })
+                .collect::<Vec<(RistrettoPoint, RistrettoPoint)>>(),
+        );
 
         let mut i = (secret_index + 1) % nr;
 
         loop {
-            for j in 0..nc {
-                hashes[(i + 1) % nr].update(
-                    RistrettoPoint::multiscalar_mul(
-                        &[rs[i % nr][j], cs[i % nr]],
-                        &[constants::RISTRETTO_BASEPOINT_POINT, ring[i % nr][j]]
-                    )
-                        .compress()
-                        .as_bytes(),
-                );
-                hashes[(i + 1) % nr].update(
-                    RistrettoPoint::multiscalar_mul(
-                        &[rs[i % nr][j], cs[i % nr]],
-                        &[
-                            RistrettoPoint::from_hash(
-                                Hash::default().chain_update(
-                                    ring[i % nr][j].compress().as_bytes()
-                                ),
+            cs[(i + 1) % nr] = compute_challenge_step_with::<Hash, Hs>(
+                message_hash.clone(),
+                &(0..nc)
+                    .map(|j| {
+                        (
+                            ops::multiscalar_mul(
+                                &[rs[i % nr][j], cs[i % nr]],
+                                &[g, ring[i % nr][j]]
                             ),
-                            key_images[j]
-                        ]
-                    )
-                        .compress()
-                        .as_bytes(),
-                );
-            }
-            cs[(i + 1) % nr] = Scalar::from_hash(hashes[(i + 1) % nr].clone());
+                            ops::multiscalar_mul(
+                                &[rs[i % nr][j], cs[i % nr]],
+                                &[Hp::hash_to_point::<Hash>(&ring[i % nr][j]), key_images[j]]
+                            ),
+                        )
+                    })
+                    .collect::<Vec<(RistrettoPoint, RistrettoPoint)>>(),
+            );
 
             if secret_index >= 1 && i % nr == (secret_index - 1) % nr {
                 break;
@@ -154,76 +332,261 @@ impl Sign<Vec<Scalar>, Vec<Vec<RistrettoPoint>>> for MLSAG {
             rs[secret_index][j] = a[j] - (cs[secret_index] * ks[j]);
         }
 
-        return MLSAG {
-            challenge: cs[0],
-            responses: rs,
+        return Ok(MLSAG {
+            challenge: Challenge(cs[0]),
+            responses: rs.into_iter().map(scalars::responses).collect(),
             ring: ring,
             key_images: key_images,
-        };
+        });
     }
-}
 
-impl Verify for MLSAG {
-    /// To verify a `signature` you need the `message` too
-    fn verify<Hash: Digest<OutputSize = U64> + Clone + Default>(
+    /// Whether the ring, responses and key images all agree on the row and column counts.
+    /// Signatures that are not well formed never verify or decode.
+    pub fn is_well_formed(&self) -> bool {
+        let nc = self.key_images.len();
+        return !self.ring.is_empty()
+            && nc > 0
+            && self.responses.len() == self.ring.len()
+            && self.ring.iter().all(|row| row.len() == nc)
+            && self.responses.iter().all(|row| row.len() == nc);
+    }
+
+    /// As [`VerifyRef::verify_ref`] with the hash to point `Hp` and the hash to scalar `Hs`, see
+    /// [`crate::hash_to_point`] and [`crate::hash_to_scalar`]
+    pub fn verify_with<Hash: Digest<OutputSize = U64> + Clone + Default, Hp: HashToPoint, Hs: HashToScalar>(
         signature: MLSAG,
         message: &Vec<u8>,
     ) -> bool {
-        let mut reconstructed_c: Scalar = signature.challenge;
-        // Row count of matrix
-        let nr = signature.ring.len();
+        return signature.verify_ref_with::<Hash, Hp, Hs>(message);
+    }
+
+    /// As [`MLSAG::verify_with`], by reference
+    pub fn verify_ref_with<Hash: Digest<OutputSize = U64> + Clone + Default, Hp: HashToPoint, Hs: HashToScalar>(
+        &self,
+        message: &Vec<u8>,
+    ) -> bool {
+        return self.verify_ref_with_generator::<Hash, Basepoint, Hp, Hs>(message);
+    }
+
+    /// As [`MLSAG::verify_ref_with`] with the generator `G`
+    fn verify_ref_with_generator<
+        Hash: Digest<OutputSize = U64> + Clone + Default,
+        G: Generator,
+        Hp: HashToPoint,
+        Hs: HashToScalar,
+    >(
+        &self,
+        message: &Vec<u8>,
+    ) -> bool {
+        if !self.is_well_formed() {
+            return false;
+        }
+        let n = self.ring.len();
+        let message_hash: Hash = compute_challenge_prefix::<Hash>(message);
+        let reconstructed_c: Scalar =
+            self.reconstruct_challenge_from_prefix::<Hash, G, Hp, Hs>(message_hash, 0..n, self.challenge.0);
+        return self.challenge.0 == reconstructed_c;
+    }
+
+    /// As [`ChallengeChain::reconstruct_challenge`], given the hash state with the message already
+    /// absorbed, see [`crate::session`], and with the generator `G`
+    pub(crate) fn reconstruct_challenge_from_prefix<
+        Hash: Digest<OutputSize = U64> + Clone + Default,
+        G: Generator,
+        Hp: HashToPoint,
+        Hs: HashToScalar,
+    >(
+        &self,
+        message_hash: Hash,
+        slots: Range<usize>,
+        challenge: Scalar,
+    ) -> Scalar {
         // Column count of matrix
-        let nc = signature.ring[0].len();
-        for _i in 0..nr {
-            let mut h: Hash = Hash::default();
-            h.update(message);
-
-            for j in 0..nc {
-                h.update(
-                    RistrettoPoint::multiscalar_mul(
-                        &[signature.responses[_i][j], reconstructed_c],
-                        &[constants::RISTRETTO_BASEPOINT_POINT, signature.ring[_i][j]]
-                    )
-                        .compress()
-                        .as_bytes(),
-                );
-
-                h.update(
-                    RistrettoPoint::multiscalar_mul(
-                        &[signature.responses[_i][j], reconstructed_c],
-                        &[
-                            RistrettoPoint::from_hash(
-                                Hash::default().chain_update(
-                                    signature.ring[_i][j].compress().as_bytes()
-                                ),
-                            ),
-                            signature.key_images[j]
-                        ]
-                    )
-                        .compress()
-                        .as_bytes(),
-                );
-            }
-            reconstructed_c = Scalar::from_hash(h);
+        let nc = self.key_images.len();
+        // A single buffer for the points of every row, rather than allocating one per row
+        let mut points: Vec<(RistrettoPoint, RistrettoPoint)> = Vec::with_capacity(nc);
+        return self.reconstruct_challenge_in::<Hash, G, Hp, Hs, _>(message_hash, slots, challenge, &mut points);
+    }
+
+    /// As [`MLSAG::reconstruct_challenge_from_prefix`], filling `points` for every row
+    fn reconstruct_challenge_in<
+        Hash: Digest<OutputSize = U64> + Clone + Default,
+        G: Generator,
+        Hp: HashToPoint,
+        Hs: HashToScalar,
+        S: Scratch<(RistrettoPoint, RistrettoPoint)>,
+    >(
+        &self,
+        message_hash: Hash,
+        slots: Range<usize>,
+        challenge: Scalar,
+        points: &mut S,
+    ) -> Scalar {
+        let g: RistrettoPoint = G::generator();
+        let mut reconstructed_c: Scalar = challenge;
+        // Column count of matrix
+        let nc = self.key_images.len();
+        for _i in slots {
+            points.clear();
+            points.extend((0..nc).map(|j| {
+                (
+                    ops::multiscalar_mul(
+                        &[self.responses[_i][j].0, reconstructed_c],
+                        &[g, self.ring[_i][j]]
+                    ),
+                    ops::multiscalar_mul(
+                        &[self.responses[_i][j].0, reconstructed_c],
+                        &[Hp::hash_to_point::<Hash>(&self.ring[_i][j]), self.key_images[j]]
+                    ),
+                )
+            }));
+            reconstructed_c = compute_challenge_step_with::<Hash, Hs>(message_hash.clone(), points);
         }
 
-        return signature.challenge == reconstructed_c;
+        return reconstructed_c;
+    }
+}
+
+impl ChallengeChain for MLSAG {
+    fn ring_size(&self) -> usize {
+        return self.ring.len();
+    }
+
+    fn challenge(&self) -> Scalar {
+        return self.challenge.0;
+    }
+
+    /// Starting from `challenge` entering the first slot in `slots`, recomputes the challenge
+    /// leaving the last one
+    fn reconstruct_challenge<Hash: Digest<OutputSize = U64> + Clone + Default>(
+        &self,
+        message: &Vec<u8>,
+        slots: Range<usize>,
+        challenge: Scalar,
+    ) -> Option<Scalar> {
+        // A malformed signature has no chain to run
+        if self.check_shape().is_err() || slots.end > self.ring.len() {
+            return None;
+        }
+        let message_hash: Hash = compute_challenge_prefix::<Hash>(message);
+        return Some(self.reconstruct_challenge_from_prefix::<Hash, Basepoint, DefaultHashToPoint, WideReduction>(
+            message_hash,
+            slots,
+            challenge,
+        ));
     }
 }
 
-impl Link for MLSAG {
+impl VerifyRef for MLSAG {
+    /// To verify a `signature` you need the `message` too
+    fn verify_ref<Hash: Digest<OutputSize = U64> + Clone + Default>(&self, message: &Vec<u8>) -> bool {
+        return self.verify_ref_with::<Hash, DefaultHashToPoint, WideReduction>(message);
+    }
+}
+
+#[cfg(feature = "allocator-api")]
+impl VerifyIn for MLSAG {
+    fn verify_in<Hash: Digest<OutputSize = U64> + Clone + Default, A: Allocator>(
+        &self,
+        message: &Vec<u8>,
+        alloc: A,
+    ) -> bool {
+        if !self.is_well_formed() {
+            return false;
+        }
+        let n = self.ring.len();
+        let nc = self.key_images.len();
+        let message_hash: Hash = compute_challenge_prefix::<Hash>(message);
+        let mut points = allocator_api2::vec::Vec::with_capacity_in(nc, alloc);
+        let reconstructed_c: Scalar = self
+            .reconstruct_challenge_in::<Hash, Basepoint, DefaultHashToPoint, WideReduction, _>(
+                message_hash,
+                0..n,
+                self.challenge.0,
+                &mut points,
+            );
+        return self.challenge.0 == reconstructed_c;
+    }
+}
+
+impl VerifyWithGenerator for MLSAG {
+    fn verify_with_generator<Hash: Digest<OutputSize = U64> + Clone + Default, G: Generator>(
+        &self,
+        message: &Vec<u8>,
+    ) -> bool {
+        return self.verify_ref_with_generator::<Hash, G, DefaultHashToPoint, WideReduction>(message);
+    }
+}
+
+#[cfg(feature = "sign")]
+impl TrySign<Vec<Scalar>, Vec<Vec<RistrettoPoint>>> for MLSAG {
+    fn check_sign_input(ks: &Vec<Scalar>, ring: &Vec<Vec<RistrettoPoint>>, secret_index: usize) -> Result<(), Error> {
+        check_columns(ks.len(), ring)?;
+        return check_secret_index(ring.len(), secret_index);
+    }
+}
+
+#[cfg(feature = "sign")]
+impl SignWithHiddenIndex<Vec<Scalar>, Vec<RistrettoPoint>> for MLSAG {}
+
+#[cfg(feature = "sign")]
+impl SignWithFullRing<Vec<Scalar>, Vec<Vec<RistrettoPoint>>> for MLSAG {
+    fn take_signer(ks: &Vec<Scalar>, ring: &mut Vec<Vec<RistrettoPoint>>, secret_index: usize) -> Result<(), Error> {
+        let k_points: Vec<RistrettoPoint> = ks.iter().map(|k| k * constants::RISTRETTO_BASEPOINT_POINT).collect();
+        return take_signer(ring, secret_index, &k_points);
+    }
+}
+
+#[cfg(feature = "sign")]
+impl Scheme for MLSAG {
+    type SecretKey = Vec<Scalar>;
+    type Ring = Vec<Vec<RistrettoPoint>>;
+
+    fn sign_with_key<Hash: Digest<OutputSize = U64> + Clone + Default, CSPRNG: CryptoRng + RngCore + Default>(
+        k: Vec<Scalar>,
+        ring: Vec<Vec<RistrettoPoint>>,
+        secret_index: usize,
+        message: &Vec<u8>,
+    ) -> Result<MLSAG, Error> {
+        let mut csprng = CSPRNG::default();
+        return MLSAG::sign_with_rng::<Hash, CSPRNG>(k, ring, secret_index, message, &mut csprng);
+    }
+
+    fn try_sign_with_key_and_rng<Hash: Digest<OutputSize = U64> + Clone + Default, CSPRNG: CryptoRng + RngCore>(
+        k: Vec<Scalar>,
+        ring: Vec<Vec<RistrettoPoint>>,
+        secret_index: usize,
+        message: &Vec<u8>,
+        csprng: &mut CSPRNG,
+    ) -> Result<MLSAG, Error> {
+        return MLSAG::sign_with_rng_unchecked::<Hash, CSPRNG>(k, ring, secret_index, message, csprng);
+    }
+}
+
+impl TryVerify for MLSAG {
+    fn check_shape(&self) -> Result<(), Error> {
+        check_responses(self.ring.len(), self.responses.len())?;
+        check_columns(self.key_images.len(), &self.ring)?;
+        if self.responses.iter().any(|row| row.len() != self.key_images.len()) {
+            return Err(Error::ResponseCountMismatch);
+        }
+        return Ok(());
+    }
+}
+
+impl LinkRef for MLSAG {
     /// This is for linking two signatures and checking if they are signed by the same person
-    fn link(signature_1: MLSAG, signature_2: MLSAG) -> bool {
+    fn link_ref(&self, other: &MLSAG) -> bool {
         let mut vec: Vec<[u8; 32]> = Vec::new();
         vec.append(
-            &mut signature_1
+            &mut self
                 .key_images
                 .iter()
                 .map(|x| x.compress().to_bytes())
                 .collect(),
         );
         vec.append(
-            &mut signature_2
+            &mut other
                 .key_images
                 .iter()
                 .map(|x| x.compress().to_bytes())
@@ -234,8 +597,126 @@ impl Link for MLSAG {
     }
 }
 
+impl MLSAG {
+    /// Like `link`, but only compares the key images of the columns for which `linkable` is
+    /// `true`, e.g. only the primary key column, so that columns holding commitments or other
+    /// shared keys do not link unrelated signatures.
+    ///
+    /// Every column still needs its key image to verify, so the others remain public. Use CLSAG
+    /// if only one column should have a key image at all.
+    pub fn link_columns(signature_1: &MLSAG, signature_2: &MLSAG, linkable: &[bool]) -> bool {
+        let linkable_key_images = |signature: &MLSAG| -> Vec<RistrettoPoint> {
+            return signature
+                .key_images
+                .iter()
+                .zip(linkable)
+                .filter(|(_, linkable)| **linkable)
+                .map(|(key_image, _)| *key_image)
+                .collect();
+        };
+        let key_images_2 = linkable_key_images(signature_2);
+        return linkable_key_images(signature_1)
+            .iter()
+            .any(|key_image| key_images_2.contains(key_image));
+    }
+}
+
+impl Weight for MLSAG {
+    /// Approximate cost of verifying this signature, see [`crate::weight`]
+    fn weight(&self) -> u64 {
+        return weight::mlsag(self.ring.len(), self.ring.first().map_or(0, |row| row.len()));
+    }
+}
+
+impl MLSAG {
+    /// Assembles a signature from its fields, returning `None` if it is not
+    /// [`MLSAG::is_well_formed`]
+    pub fn from_parts(
+        challenge: Challenge,
+        responses: Vec<Vec<Response>>,
+        ring: Vec<Vec<RistrettoPoint>>,
+        key_images: Vec<RistrettoPoint>,
+    ) -> Option<MLSAG> {
+        let signature = MLSAG::from_parts_unchecked(challenge, responses, ring, key_images);
+        if !signature.is_well_formed() {
+            return None;
+        }
+        return Some(signature);
+    }
+
+    /// Assembles a signature from its fields without checking them
+    ///
+    /// This is for fields that have been checked before, e.g. by [`MLSAG::from_parts`] or by
+    /// decoding, and are read back from storage that is trusted. A signature assembled from
+    /// fields that do not agree is rejected: verifying it returns false.
+    pub fn from_parts_unchecked(
+        challenge: Challenge,
+        responses: Vec<Vec<Response>>,
+        ring: Vec<Vec<RistrettoPoint>>,
+        key_images: Vec<RistrettoPoint>,
+    ) -> MLSAG {
+        return MLSAG {
+            challenge: challenge,
+            responses: responses,
+            ring: ring,
+            key_images: key_images,
+        };
+    }
+}
+
+impl Codec for MLSAG {
+    /// Encodes the signature canonically, see [`crate::codec`]
+    fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes: Vec<u8> = Vec::new();
+        codec::write_challenge(&mut bytes, &self.challenge);
+        codec::write_u32(&mut bytes, self.ring.len());
+        codec::write_u32(&mut bytes, self.key_images.len());
+        for row in &self.responses {
+            for response in row {
+                codec::write_response(&mut bytes, response);
+            }
+        }
+        for row in &self.ring {
+            for k_point in row {
+                codec::write_point(&mut bytes, k_point);
+            }
+        }
+        for key_image in &self.key_images {
+            codec::write_point(&mut bytes, key_image);
+        }
+        return bytes;
+    }
+
+    /// Decodes a signature, returning the [`Error`] describing why if `bytes` is not a canonical encoding
+    fn decode(bytes: &[u8]) -> Result<MLSAG, Error> {
+        let mut reader = Reader::new(bytes);
+        let challenge = reader.read_challenge()?;
+        let nr = reader.read_len(1)?;
+        let nc = reader.read_len(1)?;
+        let responses = (0..nr)
+            .map(|_| reader.read_responses(nc))
+            .collect::<Result<Vec<Vec<Response>>, Error>>()?;
+        let ring = (0..nr)
+            .map(|_| reader.read_points(nc))
+            .collect::<Result<Vec<Vec<RistrettoPoint>>, Error>>()?;
+        let key_images = reader.read_points(nc)?;
+        reader.finish()?;
+        let signature = MLSAG {
+            challenge: challenge,
+            responses: responses,
+            ring: ring,
+            key_images: key_images,
+        };
+        signature.check_shape()?;
+        return Ok(signature);
+    }
+}
+
+crate::encoding::display_via_codec!(MLSAG);
+
 #[cfg(test)]
 #[cfg(feature = "std")]
+#[cfg(feature = "sign")]
 mod test {
     extern crate blake2;
     extern crate rand;
@@ -271,23 +752,41 @@ mod test {
         let message: Vec<u8> = b"This is the message".iter().cloned().collect();
 
         {
-            let signature =
-                MLSAG::sign::<Sha512, OsRng>(ks.clone(), ring.clone(), secret_index, &message);
-            let result = MLSAG::verify::<Sha512>(signature, &message);
+            let signature = MLSAG::sign_with_rng_unchecked::<Sha512, _>(
+                ks.clone(),
+                ring.clone(),
+                secret_index,
+                &message,
+                &mut OsRng,
+            )
+            .unwrap();
+            let result = signature.verify_ref::<Sha512>(&message);
             assert!(result);
         }
 
         {
-            let signature =
-                MLSAG::sign::<Keccak512, OsRng>(ks.clone(), ring.clone(), secret_index, &message);
-            let result = MLSAG::verify::<Keccak512>(signature, &message);
+            let signature = MLSAG::sign_with_rng_unchecked::<Keccak512, _>(
+                ks.clone(),
+                ring.clone(),
+                secret_index,
+                &message,
+                &mut OsRng,
+            )
+            .unwrap();
+            let result = signature.verify_ref::<Keccak512>(&message);
             assert!(result);
         }
 
         {
-            let signature =
-                MLSAG::sign::<Blake2b512, OsRng>(ks.clone(), ring.clone(), secret_index, &message);
-            let result = MLSAG::verify::<Blake2b512>(signature, &message);
+            let signature = MLSAG::sign_with_rng_unchecked::<Blake2b512, _>(
+                ks.clone(),
+                ring.clone(),
+                secret_index,
+                &message,
+                &mut OsRng,
+            )
+            .unwrap();
+            let result = signature.verify_ref::<Blake2b512>(&message);
             assert!(result);
         }
 
@@ -299,15 +798,189 @@ mod test {
             })
             .collect();
         let another_message: Vec<u8> = b"This is another message".iter().cloned().collect();
-        let signature_1 = MLSAG::sign::<Blake2b512, OsRng>(
+        let signature_1 = MLSAG::sign_with_rng_unchecked::<Blake2b512, _>(
             ks.clone(),
             another_ring.clone(),
             secret_index,
             &another_message,
-        );
-        let signature_2 =
-            MLSAG::sign::<Blake2b512, OsRng>(ks.clone(), ring.clone(), secret_index, &message);
-        let result = MLSAG::link(signature_1, signature_2);
+            &mut OsRng,
+        )
+        .unwrap();
+        let signature_2 = MLSAG::sign_with_rng_unchecked::<Blake2b512, _>(
+            ks.clone(),
+            ring.clone(),
+            secret_index,
+            &message,
+            &mut OsRng,
+        )
+        .unwrap();
+        let result = signature_1.link_ref(&signature_2);
         assert!(result);
     }
+
+    #[test]
+    fn link_columns() {
+        let mut csprng = OsRng;
+        let ring: Vec<Vec<RistrettoPoint>> = vec![(0..2).map(|_| RistrettoPoint::random(&mut csprng)).collect()];
+        let message: Vec<u8> = b"This is the message".to_vec();
+        // Two signers sharing the key of the second column
+        let shared: Scalar = Scalar::random(&mut csprng);
+        let ks_1: Vec<Scalar> = vec![Scalar::random(&mut csprng), shared];
+        let ks_2: Vec<Scalar> = vec![Scalar::random(&mut csprng), shared];
+        let signature_1 =
+            MLSAG::sign_with_rng_unchecked::<Sha512, _>(ks_1.clone(), ring.clone(), 0, &message, &mut OsRng).unwrap();
+        let signature_2 =
+            MLSAG::sign_with_rng_unchecked::<Sha512, _>(ks_2, ring.clone(), 0, &message, &mut OsRng).unwrap();
+        let signature_3 = MLSAG::sign_with_rng_unchecked::<Sha512, _>(ks_1, ring, 1, &message, &mut OsRng).unwrap();
+
+        let primary_only = [true, false];
+        assert!(!MLSAG::link_columns(&signature_1, &signature_2, &primary_only));
+        assert!(MLSAG::link_columns(&signature_1, &signature_3, &primary_only));
+        assert!(MLSAG::link_columns(&signature_1, &signature_2, &[true, true]));
+        assert!(signature_1.link_ref(&signature_2));
+    }
+    #[test]
+    fn from_parts() {
+        let ks: Vec<Scalar> = (0..2).map(|_| Scalar::random(&mut OsRng)).collect();
+        let ring: Vec<Vec<RistrettoPoint>> = vec![(0..2).map(|_| RistrettoPoint::random(&mut OsRng)).collect()];
+        let message: Vec<u8> = b"This is the message".to_vec();
+        let signature = MLSAG::sign_with_rng_unchecked::<Sha512, _>(ks, ring, 0, &message, &mut OsRng).unwrap();
+
+        let assembled = MLSAG::from_parts(
+            signature.challenge,
+            signature.responses.clone(),
+            signature.ring.clone(),
+            signature.key_images.clone(),
+        )
+        .unwrap();
+        assert!(assembled.verify_ref::<Sha512>(&message));
+
+        // A key image short is refused by the checked constructor only
+        let short: Vec<RistrettoPoint> = signature.key_images[..1].to_vec();
+        let refused =
+            MLSAG::from_parts(signature.challenge, signature.responses.clone(), signature.ring.clone(), short.clone());
+        assert!(refused.is_none());
+        let unchecked = MLSAG::from_parts_unchecked(signature.challenge, signature.responses, signature.ring, short);
+        assert!(!unchecked.verify_ref::<Sha512>(&message));
+    }
+    #[test]
+    fn try_sign() {
+        let ks: Vec<Scalar> = (0..2).map(|_| Scalar::random(&mut OsRng)).collect();
+        let ring: Vec<Vec<RistrettoPoint>> = vec![(0..2).map(|_| RistrettoPoint::random(&mut OsRng)).collect()];
+        let message: Vec<u8> = b"This is the message".to_vec();
+
+        let out_of_range = MLSAG::try_sign::<Sha512, OsRng>(ks.clone(), ring.clone(), 2, &message);
+        assert_eq!(out_of_range.err(), Some(Error::SecretIndexOutOfRange { index: 2, ring_size: 2 }));
+        let ragged = vec![ring[0][..1].to_vec()];
+        let ragged = MLSAG::try_sign::<Sha512, OsRng>(ks.clone(), ragged, 0, &message);
+        assert_eq!(ragged.err(), Some(Error::RaggedRingMatrix));
+        let keyless = MLSAG::try_sign::<Sha512, OsRng>(Vec::new(), ring.clone(), 0, &message);
+        assert_eq!(keyless.err(), Some(Error::EmptyRing));
+        // No keys give no key images
+        assert!(MLSAG::generate_key_image_ref::<Sha512>(&Vec::new()).is_empty());
+
+        let small = MLSAG::try_sign::<Sha512, OsRng>(ks.clone(), ring, 1, &message);
+        assert_eq!(small.err(), Some(Error::RingTooSmall { size: 2, minimum: 11 }));
+
+        let ring: Vec<Vec<RistrettoPoint>> =
+            (0..10).map(|_| (0..2).map(|_| RistrettoPoint::random(&mut OsRng)).collect()).collect();
+        let signature = MLSAG::try_sign::<Sha512, OsRng>(ks, ring, 1, &message).unwrap();
+        assert_eq!(signature.try_verify::<Sha512>(&message), Ok(()));
+        assert_eq!(signature.try_verify::<Sha512>(&b"Another message".to_vec()), Err(Error::InvalidSignature));
+        let mut short = signature;
+        short.responses[0].pop();
+        assert_eq!(short.try_verify::<Sha512>(&message), Err(Error::ResponseCountMismatch));
+    }
+
+    #[test]
+    fn sign_with_hidden_index() {
+        use crate::traits::random_index;
+        use rand::rngs::StdRng;
+        use rand::SeedableRng;
+
+        let ks: Vec<Scalar> = (0..2).map(|_| Scalar::random(&mut OsRng)).collect();
+        let k_points: Vec<RistrettoPoint> = ks.iter().map(|k| k * constants::RISTRETTO_BASEPOINT_POINT).collect();
+        let ring: Vec<Vec<RistrettoPoint>> =
+            (0..10).map(|_| (0..2).map(|_| RistrettoPoint::random(&mut OsRng)).collect()).collect();
+        let message: Vec<u8> = b"This is the message".to_vec();
+
+        // The signer lands where `random_index` puts it, with the decoys in order around it
+        for seed in 0..4 {
+            let expected = random_index(&mut StdRng::seed_from_u64(seed), 11);
+            let mut csprng = StdRng::seed_from_u64(seed);
+            let signature =
+                MLSAG::sign_with_hidden_index::<Sha512, _>(ks.clone(), ring.clone(), &message, &mut csprng).unwrap();
+            assert!(signature.verify_ref::<Sha512>(&message));
+            let secret_index = signature.ring.iter().position(|member| *member == k_points).unwrap();
+            assert_eq!(secret_index, expected);
+            let mut decoys = signature.ring.clone();
+            decoys.remove(secret_index);
+            assert_eq!(decoys, ring);
+        }
+    }
+    #[test]
+    fn sign_ref() {
+        use rand::rngs::StdRng;
+        use rand::SeedableRng;
+
+        let ks: Vec<Scalar> = (0..2).map(|_| Scalar::random(&mut OsRng)).collect();
+        let ring: Vec<Vec<RistrettoPoint>> = vec![(0..2).map(|_| RistrettoPoint::random(&mut OsRng)).collect()];
+        let message: Vec<u8> = b"This is the message".to_vec();
+
+        // Borrowed keys sign as the owned ones do
+        let borrowed = MLSAG::sign_ref_with_rng::<Sha512, _>(
+            &ks,
+            ring.clone(),
+            1,
+            &message,
+            &mut StdRng::seed_from_u64(7),
+        )
+        .unwrap();
+        let owned = MLSAG::sign_with_rng_unchecked::<Sha512, _>(
+            ks.clone(),
+            ring.clone(),
+            1,
+            &message,
+            &mut StdRng::seed_from_u64(7),
+        )
+        .unwrap();
+        assert_eq!(borrowed.to_bytes(), owned.to_bytes());
+        assert!(borrowed.verify_ref::<Sha512>(&message));
+        assert!(MLSAG::sign_ref::<Sha512, OsRng>(&ks, ring, 0, &message).unwrap().verify_ref::<Sha512>(&message));
+    }
+
+    #[test]
+    fn does_not_panic() {
+        use crate::link::LinkingKeyImage;
+
+        let ks: Vec<Scalar> = (0..2).map(|_| Scalar::random(&mut OsRng)).collect();
+        let ring: Vec<Vec<RistrettoPoint>> = vec![(0..2).map(|_| RistrettoPoint::random(&mut OsRng)).collect()];
+        let message: Vec<u8> = b"This is the message".to_vec();
+
+        // Signing returns an error for what it can not sign with
+        let out_of_range =
+            MLSAG::sign_with_rng_unchecked::<Sha512, _>(ks.clone(), ring.clone(), 2, &message, &mut OsRng);
+        assert_eq!(out_of_range.err(), Some(Error::SecretIndexOutOfRange { index: 2, ring_size: 2 }));
+        let ragged = vec![ring[0][..1].to_vec()];
+        let ragged = MLSAG::sign_with_rng_unchecked::<Sha512, _>(ks.clone(), ragged, 0, &message, &mut OsRng);
+        assert_eq!(ragged.err(), Some(Error::RaggedRingMatrix));
+        let keyless = MLSAG::sign_with_rng_unchecked::<Sha512, _>(Vec::new(), Vec::new(), 0, &message, &mut OsRng);
+        assert_eq!(keyless.err(), Some(Error::EmptyRing));
+
+        // Signatures built by hand without key images or a ring verify, link and run their chain
+        // to a refusal
+        let signature = MLSAG::sign_with_rng_unchecked::<Sha512, _>(ks, ring, 0, &message, &mut OsRng).unwrap();
+        let challenge = signature.challenge.0;
+        let mut imageless = signature.clone();
+        imageless.key_images.clear();
+        assert!(!imageless.verify_ref::<Sha512>(&message));
+        assert!(!imageless.link_ref(&imageless));
+        assert!(imageless.linking_key_image().is_none());
+        assert_eq!(imageless.reconstruct_challenge::<Sha512>(&message, 0..2, challenge), None);
+        let mut ringless = signature;
+        ringless.ring.clear();
+        ringless.responses.clear();
+        assert!(!ringless.verify_ref::<Sha512>(&message));
+        assert_eq!(ringless.reconstruct_challenge::<Sha512>(&message, 0..0, challenge), None);
+    }
 }
@@ -0,0 +1,315 @@
+//! Hedged and deterministic nonces
+//!
+//! Signing draws the nonce `a` and the responses of the decoys from the generator it is given. A
+//! broken generator, e.g. one repeating its output after a fork or on a device without entropy,
+//! would give the same nonce twice and reveal the private key. [`HedgedRng`] hashes the private
+//! key, the ring, the position of the signer and the message together with 32 bytes from the
+//! generator, as RFC 6979 does for ECDSA, and draws the nonces from that. The signature is then
+//! as good as one with a sound generator as long as either the generator or the key is secret.
+//! The scheme, the hash function and the base generator are hashed in as well, since SAG and bLSAG
+//! signing the same inputs with the same nonce would give two equations to solve for the key.
+//!
+//! Without a generator the nonces depend on the inputs alone, which gives the same signature for
+//! the same inputs, e.g. for reproducible test vectors. Both are signed through [`SignHedged`],
+//! which every scheme implements.
+
+use crate::prelude::*;
+
+use core::marker::PhantomData;
+
+use curve25519_dalek::ristretto::RistrettoPoint;
+use curve25519_dalek::scalar::Scalar;
+use digest::generic_array::typenum::U64;
+use digest::Digest;
+use rand_core::{impls, CryptoRng, Error as RngError, RngCore};
+
+use crate::error::Error;
+use crate::generator::{Basepoint, Generator};
+use crate::header::SchemeId;
+use crate::signature::SignatureBytes;
+use crate::traits::{Decoys, SignWithRng};
+
+/// Private keys and rings, which are hashed into the nonces
+pub trait NonceInput {
+    fn absorb<Hash: Digest>(&self, hash: &mut Hash);
+}
+
+impl NonceInput for Scalar {
+    fn absorb<Hash: Digest>(&self, hash: &mut Hash) {
+        hash.update(self.as_bytes());
+    }
+}
+
+impl NonceInput for RistrettoPoint {
+    fn absorb<Hash: Digest>(&self, hash: &mut Hash) {
+        hash.update(self.compress().as_bytes());
+    }
+}
+
+impl<A: NonceInput, B: NonceInput, C: NonceInput> NonceInput for (A, B, C) {
+    fn absorb<Hash: Digest>(&self, hash: &mut Hash) {
+        self.0.absorb(hash);
+        self.1.absorb(hash);
+        self.2.absorb(hash);
+    }
+}
+
+impl<T: NonceInput> NonceInput for Vec<T> {
+    /// The length comes first, so that rings of different shape do not hash alike
+    fn absorb<Hash: Digest>(&self, hash: &mut Hash) {
+        hash.update((self.len() as u64).to_le_bytes());
+        for item in self.iter() {
+            item.absorb(hash);
+        }
+    }
+}
+
+/// A generator hashing the inputs of one signature and a seed drawn from another generator, with
+/// a counter
+///
+/// It must sign only the inputs it was made from. It is deliberately not `Clone` or `Debug`, so
+/// the same nonces are not drawn twice and the state does not end up in logs.
+pub struct HedgedRng<Hash> {
+    seed: [u8; 64],
+    counter: u64,
+    hash: PhantomData<Hash>,
+}
+
+impl<Hash: Digest<OutputSize = U64> + Default> HedgedRng<Hash> {
+    /// Nonces for a `scheme` signature of `message` with `k` over `ring` at `secret_index`, with
+    /// `generator` as the base of public keys, hedged with 32 bytes from `csprng`
+    pub fn new<PrivateKey: NonceInput, Ring: NonceInput, CSPRNG: CryptoRng + RngCore>(
+        scheme: SchemeId,
+        generator: &RistrettoPoint,
+        k: &PrivateKey,
+        ring: &Ring,
+        secret_index: usize,
+        message: &Vec<u8>,
+        csprng: &mut CSPRNG,
+    ) -> HedgedRng<Hash> {
+        let mut entropy = [0u8; 32];
+        csprng.fill_bytes(&mut entropy);
+        let hash: Hash = HedgedRng::<Hash>::absorb_signer(scheme, generator, k, ring, secret_index);
+        return HedgedRng::from_seed(HedgedRng::<Hash>::seed(hash, message, &entropy));
+    }
+
+    /// Nonces depending on the inputs alone, the same every time
+    pub fn deterministic<PrivateKey: NonceInput, Ring: NonceInput>(
+        scheme: SchemeId,
+        generator: &RistrettoPoint,
+        k: &PrivateKey,
+        ring: &Ring,
+        secret_index: usize,
+        message: &Vec<u8>,
+    ) -> HedgedRng<Hash> {
+        let hash: Hash = HedgedRng::<Hash>::absorb_signer(scheme, generator, k, ring, secret_index);
+        return HedgedRng::from_seed(HedgedRng::<Hash>::seed(hash, message, &[0u8; 32]));
+    }
+
+    /// The hash of the nonces with the scheme, the hash function, the generator and the signer
+    /// absorbed, which is the same for every message the signer signs over `ring`
+    pub(crate) fn absorb_signer<PrivateKey: NonceInput, Ring: NonceInput>(
+        scheme: SchemeId,
+        generator: &RistrettoPoint,
+        k: &PrivateKey,
+        ring: &Ring,
+        secret_index: usize,
+    ) -> Hash {
+        let mut hash = Hash::default();
+        hash.update(b"nazgul/nonce");
+        hash.update([scheme as u8]);
+        // Hash functions are told apart by their output on a fixed input
+        hash.update(Hash::default().chain_update(b"nazgul/nonce/hash").finalize());
+        hash.update(generator.compress().as_bytes());
+        k.absorb(&mut hash);
+        ring.absorb(&mut hash);
+        hash.update((secret_index as u64).to_le_bytes());
+        return hash;
+    }
+
+    /// The seed of the nonces for `message`, from the `hash` of [`HedgedRng::absorb_signer`]
+    pub(crate) fn seed(mut hash: Hash, message: &[u8], entropy: &[u8; 32]) -> [u8; 64] {
+        hash.update((message.len() as u64).to_le_bytes());
+        hash.update(message);
+        hash.update(entropy);
+        let mut seed = [0u8; 64];
+        seed.copy_from_slice(&hash.finalize());
+        return seed;
+    }
+
+    pub(crate) fn from_seed(seed: [u8; 64]) -> HedgedRng<Hash> {
+        return HedgedRng {
+            seed: seed,
+            counter: 0,
+            hash: PhantomData,
+        };
+    }
+}
+
+impl<Hash: Digest<OutputSize = U64> + Default> RngCore for HedgedRng<Hash> {
+    fn next_u32(&mut self) -> u32 {
+        return impls::next_u32_via_fill(self);
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        return impls::next_u64_via_fill(self);
+    }
+
+    fn fill_bytes(&mut self, dest: &mut [u8]) {
+        for chunk in dest.chunks_mut(64) {
+            let block = Hash::default()
+                .chain_update(self.seed)
+                .chain_update(self.counter.to_le_bytes())
+                .finalize();
+            self.counter += 1;
+            chunk.copy_from_slice(&block[..chunk.len()]);
+        }
+    }
+
+    fn try_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), RngError> {
+        self.fill_bytes(dest);
+        return Ok(());
+    }
+}
+
+impl<Hash> CryptoRng for HedgedRng<Hash> {}
+
+/// Signing with nonces from a [`HedgedRng`]
+pub trait SignHedged<PrivateKey: NonceInput, Ring: NonceInput>: SignWithRng<PrivateKey, Ring> + SignatureBytes {
+    /// [`SignWithRng::sign_with_rng`] with the nonces hedged with 32 bytes from `csprng`
+    fn sign_hedged<Hash: Digest<OutputSize = U64> + Clone + Default, CSPRNG: CryptoRng + RngCore>(
+        k: PrivateKey,
+        ring: Ring,
+        secret_index: usize,
+        message: &Vec<u8>,
+        csprng: &mut CSPRNG,
+    ) -> Result<Self, Error>
+    where
+        Ring: Decoys,
+    {
+        let generator = Basepoint::generator();
+        let mut nonces = HedgedRng::<Hash>::new(Self::SCHEME, &generator, &k, &ring, secret_index, message, csprng);
+        return Self::sign_with_rng::<Hash, HedgedRng<Hash>>(k, ring, secret_index, message, &mut nonces);
+    }
+
+    /// [`SignWithRng::sign_with_rng`] with nonces depending on the inputs alone, which gives the
+    /// same signature for the same inputs
+    fn sign_deterministic<Hash: Digest<OutputSize = U64> + Clone + Default>(
+        k: PrivateKey,
+        ring: Ring,
+        secret_index: usize,
+        message: &Vec<u8>,
+    ) -> Result<Self, Error>
+    where
+        Ring: Decoys,
+    {
+        let generator = Basepoint::generator();
+        let mut nonces = HedgedRng::<Hash>::deterministic(Self::SCHEME, &generator, &k, &ring, secret_index, message);
+        return Self::sign_with_rng::<Hash, HedgedRng<Hash>>(k, ring, secret_index, message, &mut nonces);
+    }
+}
+
+impl<PrivateKey: NonceInput, Ring: NonceInput, S: SignWithRng<PrivateKey, Ring> + SignatureBytes>
+    SignHedged<PrivateKey, Ring> for S
+{
+}
+
+#[cfg(test)]
+#[cfg(feature = "std")]
+#[cfg(feature = "blsag")]
+mod test {
+    extern crate rand;
+    extern crate sha2;
+
+    use rand::rngs::OsRng;
+    use sha2::Sha512;
+
+    use super::*;
+    use crate::blsag::BLSAG;
+    use crate::traits::VerifyRef;
+
+    /// A generator that has lost its entropy
+    struct Broken;
+
+    impl RngCore for Broken {
+        fn next_u32(&mut self) -> u32 {
+            return 0;
+        }
+
+        fn next_u64(&mut self) -> u64 {
+            return 0;
+        }
+
+        fn fill_bytes(&mut self, dest: &mut [u8]) {
+            dest.fill(0);
+        }
+
+        fn try_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), RngError> {
+            self.fill_bytes(dest);
+            return Ok(());
+        }
+    }
+
+    impl CryptoRng for Broken {}
+
+    #[test]
+    fn hedged_nonces() {
+        let k: Scalar = Scalar::random(&mut OsRng);
+        let ring: Vec<RistrettoPoint> = (0..10).map(|_| RistrettoPoint::random(&mut OsRng)).collect();
+        let message: Vec<u8> = b"This is the message".to_vec();
+        let another_message: Vec<u8> = b"This is another message".to_vec();
+
+        let signature = BLSAG::sign_hedged::<Sha512, _>(k, ring.clone(), 1, &message, &mut OsRng).unwrap();
+        assert!(signature.verify_ref::<Sha512>(&message));
+
+        // Deterministic signatures are reproducible and verify
+        let deterministic = BLSAG::sign_deterministic::<Sha512>(k, ring.clone(), 1, &message).unwrap();
+        assert!(deterministic.verify_ref::<Sha512>(&message));
+        let again = BLSAG::sign_deterministic::<Sha512>(k, ring.clone(), 1, &message).unwrap();
+        assert_eq!(deterministic.challenge, again.challenge);
+        assert_eq!(deterministic.responses, again.responses);
+        assert_ne!(deterministic.challenge, signature.challenge);
+
+        // A generator without entropy is the deterministic case, and still gives fresh nonces for
+        // every message
+        let broken = BLSAG::sign_hedged::<Sha512, _>(k, ring.clone(), 1, &message, &mut Broken).unwrap();
+        assert_eq!(broken.challenge, deterministic.challenge);
+        let another = BLSAG::sign_hedged::<Sha512, _>(k, ring.clone(), 1, &another_message, &mut Broken).unwrap();
+        assert!(another.verify_ref::<Sha512>(&another_message));
+        assert_ne!(another.responses[1], broken.responses[1]);
+    }
+
+    #[test]
+    #[cfg(feature = "sag")]
+    fn nonces_per_scheme() {
+        use crate::sag::SAG;
+        use crate::traits::ChallengeChain;
+        use curve25519_dalek::constants;
+
+        let k: Scalar = Scalar::random(&mut OsRng);
+        let ring: Vec<RistrettoPoint> = (0..10).map(|_| RistrettoPoint::random(&mut OsRng)).collect();
+        let message: Vec<u8> = b"This is the message".to_vec();
+        let basepoint = constants::RISTRETTO_BASEPOINT_POINT;
+        let first_nonce = |scheme: SchemeId, generator: &RistrettoPoint| -> Scalar {
+            let mut nonces = HedgedRng::<Sha512>::deterministic(scheme, generator, &k, &ring, 1, &message);
+            return Scalar::random(&mut nonces);
+        };
+
+        // SAG and bLSAG both draw the signer's nonce first. The same nonce under both would give
+        // the key from their responses and challenges
+        assert_ne!(first_nonce(SchemeId::SAG, &basepoint), first_nonce(SchemeId::BLSAG, &basepoint));
+        assert_ne!(first_nonce(SchemeId::MLSAG, &basepoint), first_nonce(SchemeId::CLSAG, &basepoint));
+        let other_generator = RistrettoPoint::random(&mut OsRng);
+        assert_ne!(first_nonce(SchemeId::BLSAG, &basepoint), first_nonce(SchemeId::BLSAG, &other_generator));
+        assert_eq!(first_nonce(SchemeId::BLSAG, &basepoint), first_nonce(SchemeId::BLSAG, &basepoint));
+
+        // Signing the same inputs under both, the responses at the signer's slot do not solve for
+        // the key
+        let sag = SAG::sign_deterministic::<Sha512>(k, ring.clone(), 1, &message).unwrap();
+        let blsag = BLSAG::sign_deterministic::<Sha512>(k, ring, 1, &message).unwrap();
+        let c_sag = sag.reconstruct_challenge::<Sha512>(&message, 0..1, sag.challenge.0).unwrap();
+        let c_blsag = blsag.reconstruct_challenge::<Sha512>(&message, 0..1, blsag.challenge.0).unwrap();
+        let recovered = (sag.responses[1].0 - blsag.responses[1].0) * (c_blsag - c_sag).invert();
+        assert_ne!(recovered, k);
+    }
+}
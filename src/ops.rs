@@ -0,0 +1,71 @@
+//! The curve operations counted by [`crate::profile`]
+//!
+//! The schemes call these in place of the arithmetic of `curve25519_dalek`, which they forward
+//! to. Without the `profile` feature they count nothing and compile down to the arithmetic.
+
+use curve25519_dalek::ristretto::RistrettoPoint;
+use curve25519_dalek::scalar::Scalar;
+#[cfg(any(
+    feature = "sag",
+    feature = "lsag",
+    feature = "blsag",
+    feature = "mlsag",
+    feature = "clsag",
+    feature = "dlsag",
+    feature = "mdlsag"
+))]
+use curve25519_dalek::traits::MultiscalarMul;
+use digest::generic_array::typenum::U64;
+use digest::Digest;
+
+/// `k G` for the generator `g`
+#[inline]
+pub(crate) fn mul_base(k: &Scalar, g: &RistrettoPoint) -> RistrettoPoint {
+    #[cfg(feature = "profile")]
+    crate::profile::record(|profile| profile.fixed_base_mults += 1);
+    return k * g;
+}
+
+/// `k P` for any other point `P`
+#[cfg(any(
+    all(feature = "lsag", feature = "sign"),
+    feature = "blsag",
+    feature = "mlsag",
+    feature = "clsag",
+    feature = "dlsag",
+    feature = "mdlsag"
+))]
+#[inline]
+pub(crate) fn mul(k: &Scalar, point: &RistrettoPoint) -> RistrettoPoint {
+    #[cfg(feature = "profile")]
+    crate::profile::record(|profile| profile.variable_base_mults += 1);
+    return k * point;
+}
+
+#[cfg(any(
+    feature = "sag",
+    feature = "lsag",
+    feature = "blsag",
+    feature = "mlsag",
+    feature = "clsag",
+    feature = "dlsag",
+    feature = "mdlsag"
+))]
+#[inline]
+pub(crate) fn multiscalar_mul(scalars: &[Scalar], points: &[RistrettoPoint]) -> RistrettoPoint {
+    #[cfg(feature = "profile")]
+    crate::profile::record(|profile| {
+        profile.multiscalar_mults += 1;
+        profile.multiscalar_terms += points.len() as u64;
+        profile.largest_multiscalar = profile.largest_multiscalar.max(points.len() as u64);
+    });
+    return RistrettoPoint::multiscalar_mul(scalars, points);
+}
+
+/// The point of the digest of `hash`
+#[inline]
+pub(crate) fn point_from_hash<Hash: Digest<OutputSize = U64> + Default>(hash: Hash) -> RistrettoPoint {
+    #[cfg(feature = "profile")]
+    crate::profile::record(|profile| profile.hashes_to_point += 1);
+    return RistrettoPoint::from_hash(hash);
+}
@@ -0,0 +1,213 @@
+//! Partial verification
+//!
+//! A signature verifies when walking the challenge chain `c_0 -> c_1 -> ... -> c_n` through
+//! every slot of the ring ends where it started. Interactive dispute games, as used by optimistic
+//! rollups, split that walk in two: [`verify_partial`] checks every slot except one off-chain,
+//! given the challenge the signer claims leaves the skipped slot, and [`verify_slot`] later checks
+//! only the contested slot. A signature passes both if and only if it passes `verify`.
+//!
+//! [`ChallengeChain::challenge_chain`] walks the whole chain one slot at a time instead, for
+//! integrators that want to stop early, report progress or hand slots out to workers.
+
+use crate::prelude::*;
+
+use core::marker::PhantomData;
+
+use curve25519_dalek::scalar::Scalar;
+use digest::generic_array::typenum::U64;
+use digest::Digest;
+
+use crate::traits::{ChallengeChain, TryVerify};
+
+/// Checks every slot of `signature` except `skip_index`, assuming the challenge leaving the
+/// skipped slot is `claimed_next_challenge`
+///
+/// Returns the challenge entering the skipped slot, which together with `claimed_next_challenge`
+/// is all [`verify_slot`] needs to settle the dispute. Returns `None` if any other slot does not
+/// verify, `skip_index` is not in the ring or the signature is malformed or not consistent, see
+/// [`ChallengeChain::is_consistent`].
+pub fn verify_partial<Hash: Digest<OutputSize = U64> + Clone + Default, S: ChallengeChain + TryVerify>(
+    signature: &S,
+    message: &Vec<u8>,
+    skip_index: usize,
+    claimed_next_challenge: Scalar,
+) -> Option<Scalar> {
+    let n = signature.ring_size();
+    if skip_index >= n || signature.check_shape().is_err() || !signature.is_consistent::<Hash>() {
+        return None;
+    }
+    // Slots before the skipped one start from the challenge stored in the signature
+    let entering_challenge: Scalar =
+        signature.reconstruct_challenge::<Hash>(message, 0..skip_index, signature.challenge())?;
+    // Slots after it must close the ring starting from the claimed challenge
+    let closing_challenge: Scalar =
+        signature.reconstruct_challenge::<Hash>(message, (skip_index + 1)..n, claimed_next_challenge)?;
+    if closing_challenge != signature.challenge() {
+        return None;
+    }
+    return Some(entering_challenge);
+}
+
+/// Checks that slot `index` of `signature` turns `entering_challenge` into `next_challenge`
+pub fn verify_slot<Hash: Digest<OutputSize = U64> + Clone + Default, S: ChallengeChain + TryVerify>(
+    signature: &S,
+    message: &Vec<u8>,
+    index: usize,
+    entering_challenge: Scalar,
+    next_challenge: Scalar,
+) -> bool {
+    if index >= signature.ring_size() || signature.check_shape().is_err() || !signature.is_consistent::<Hash>() {
+        return false;
+    }
+    return signature.reconstruct_challenge::<Hash>(message, index..(index + 1), entering_challenge)
+        == Some(next_challenge);
+}
+
+/// A step of [`ChallengeSteps`]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ChainStep {
+    /// The challenge leaving slot `index`, which enters slot `index + 1`
+    Slot { index: usize, challenge: Scalar },
+    /// The last step: whether the challenge leaving the last slot is the one the signature starts
+    /// from, i.e. whether the signature verifies. A malformed signature closes at once with `false`
+    Closed(bool),
+}
+
+/// Iterator over the challenge chain of a signature, see [`ChallengeChain::challenge_chain`]
+pub struct ChallengeSteps<'a, Hash, S> {
+    signature: &'a S,
+    message: &'a Vec<u8>,
+    index: usize,
+    challenge: Scalar,
+    closed: bool,
+    hash: PhantomData<fn() -> Hash>,
+}
+
+impl<'a, Hash: Digest<OutputSize = U64> + Clone + Default, S: ChallengeChain + TryVerify> ChallengeSteps<'a, Hash, S> {
+    pub(crate) fn new(signature: &'a S, message: &'a Vec<u8>) -> ChallengeSteps<'a, Hash, S> {
+        // A malformed or inconsistent signature skips straight to the end
+        let index = match signature.check_shape() {
+            Ok(()) if signature.is_consistent::<Hash>() => 0,
+            _ => usize::MAX,
+        };
+        return ChallengeSteps {
+            signature: signature,
+            message: message,
+            index: index,
+            challenge: signature.challenge(),
+            closed: false,
+            hash: PhantomData,
+        };
+    }
+}
+
+impl<'a, Hash: Digest<OutputSize = U64> + Clone + Default, S: ChallengeChain + TryVerify> Iterator
+    for ChallengeSteps<'a, Hash, S>
+{
+    type Item = ChainStep;
+
+    fn next(&mut self) -> Option<ChainStep> {
+        if self.closed {
+            return None;
+        }
+        let n = self.signature.ring_size();
+        if self.index >= n {
+            self.closed = true;
+            return Some(ChainStep::Closed(self.index == n && self.challenge == self.signature.challenge()));
+        }
+        let index = self.index;
+        let next = self.signature.reconstruct_challenge::<Hash>(self.message, index..(index + 1), self.challenge);
+        self.challenge = match next {
+            Some(challenge) => challenge,
+            None => {
+                self.closed = true;
+                return Some(ChainStep::Closed(false));
+            }
+        };
+        self.index += 1;
+        return Some(ChainStep::Slot {
+            index: index,
+            challenge: self.challenge,
+        });
+    }
+}
+
+#[cfg(test)]
+#[cfg(feature = "std")]
+#[cfg(feature = "sign")]
+#[cfg(feature = "blsag")]
+mod test {
+    extern crate rand;
+    extern crate sha2;
+
+    use curve25519_dalek::ristretto::RistrettoPoint;
+    use curve25519_dalek::scalar::Scalar;
+    use rand::rngs::OsRng;
+    use sha2::Sha512;
+
+    use super::*;
+    use crate::blsag::BLSAG;
+    use crate::traits::SignWithRng;
+
+    #[test]
+    fn partial() {
+        let mut csprng = OsRng;
+        let k: Scalar = Scalar::random(&mut csprng);
+        let ring: Vec<RistrettoPoint> = (0..3).map(|_| RistrettoPoint::random(&mut csprng)).collect();
+        let message: Vec<u8> = b"This is the message".to_vec();
+        let signature = BLSAG::sign_with_rng_unchecked::<Sha512, _>(k, ring, 1, &message, &mut OsRng).unwrap();
+
+        for skip_index in 0..4 {
+            // The honest claim is the challenge actually leaving the skipped slot
+            let entering =
+                signature.reconstruct_challenge::<Sha512>(&message, 0..skip_index, signature.challenge.0).unwrap();
+            let next =
+                signature.reconstruct_challenge::<Sha512>(&message, skip_index..(skip_index + 1), entering).unwrap();
+            assert_eq!(verify_partial::<Sha512, _>(&signature, &message, skip_index, next), Some(entering));
+            assert!(verify_slot::<Sha512, _>(&signature, &message, skip_index, entering, next));
+
+            // A false claim about the skipped slot is caught by whichever half it lies to
+            let forged = Scalar::random(&mut csprng);
+            assert!(verify_partial::<Sha512, _>(&signature, &message, skip_index, forged).is_none());
+            assert!(!verify_slot::<Sha512, _>(&signature, &message, skip_index, entering, forged));
+        }
+
+        // Walking the chain slot by slot passes through the same challenges
+        let steps: Vec<ChainStep> = signature.challenge_chain::<Sha512>(&message).collect();
+        assert_eq!(steps.len(), 5);
+        for (index, step) in steps[..4].iter().enumerate() {
+            let challenge =
+                signature.reconstruct_challenge::<Sha512>(&message, 0..(index + 1), signature.challenge.0).unwrap();
+            assert_eq!(*step, ChainStep::Slot { index: index, challenge: challenge });
+        }
+        assert_eq!(steps[4], ChainStep::Closed(true));
+        let another_message: Vec<u8> = b"This is another message".to_vec();
+        assert_eq!(
+            signature.challenge_chain::<Sha512>(&another_message).last(),
+            Some(ChainStep::Closed(false))
+        );
+        let mut malformed = signature.clone();
+        malformed.responses.pop();
+        let steps: Vec<ChainStep> = malformed.challenge_chain::<Sha512>(&message).collect();
+        assert_eq!(steps, vec![ChainStep::Closed(false)]);
+        assert_eq!(malformed.reconstruct_challenge::<Sha512>(&message, 0..1, malformed.challenge.0), None);
+        assert!(verify_partial::<Sha512, _>(&malformed, &message, 1, signature.challenge.0).is_none());
+        assert_eq!(signature.reconstruct_challenge::<Sha512>(&message, 0..5, signature.challenge.0), None);
+
+        // So does one whose ring digest is not that of its ring
+        let ring_digest = BLSAG::compute_ring_digest::<Sha512>(&signature.ring[1..]);
+        let substituted = signature.clone().with_ring_digest(ring_digest);
+        let entering = substituted.reconstruct_challenge::<Sha512>(&message, 0..1, substituted.challenge.0).unwrap();
+        let next = substituted.reconstruct_challenge::<Sha512>(&message, 1..2, entering).unwrap();
+        assert!(verify_partial::<Sha512, _>(&substituted, &message, 1, next).is_none());
+        assert!(!verify_slot::<Sha512, _>(&substituted, &message, 1, entering, next));
+        let steps: Vec<ChainStep> = substituted.challenge_chain::<Sha512>(&message).collect();
+        assert_eq!(steps, vec![ChainStep::Closed(false)]);
+
+        // The same walk over the wrong message fails
+        let entering =
+            signature.reconstruct_challenge::<Sha512>(&another_message, 0..1, signature.challenge.0).unwrap();
+        let next = signature.reconstruct_challenge::<Sha512>(&another_message, 1..2, entering).unwrap();
+        assert!(verify_partial::<Sha512, _>(&signature, &another_message, 1, next).is_none());
+    }
+}
@@ -0,0 +1,254 @@
+//! Signing and verifying policy
+//!
+//! A ring of one or two members gives next to no anonymity. `sign_with_rng`, `try_sign`,
+//! `Scheme::try_sign_with_key`, `builder::Signer` and the signing built on them reject rings
+//! smaller than [`DEFAULT_MINIMUM_RING_SIZE`] with [`Error::RingTooSmall`]. Signing through a
+//! [`SignPolicy`] sets another minimum, or none where small rings are explicitly allowed, e.g. in
+//! tests. `sign_with_rng_unchecked` checks no policy at all.
+//!
+//! Acceptors enforce the same on their side with a [`VerifyPolicy`], which
+//! `RingSignature::verify_with_policy` checks before verifying, so that signatures over rings
+//! too small or of schemes the acceptor does not take are rejected even though they are valid.
+//!
+//! A [`MemoryBudget`] bounds what decoding and verifying a signature from a peer may allocate, so
+//! that an absurdly large ring is rejected with [`Error::BudgetExceeded`] instead of exhausting
+//! memory, see `signature::parse_within` and `RingSignature::verify_within`.
+
+use crate::prelude::*;
+
+#[cfg(feature = "sign")]
+use digest::generic_array::typenum::U64;
+#[cfg(feature = "sign")]
+use digest::Digest;
+#[cfg(feature = "sign")]
+use rand_core::{CryptoRng, RngCore};
+
+use crate::error::Error;
+use crate::header::SchemeId;
+#[cfg(feature = "sign")]
+use crate::traits::{Decoys, SignWithRng};
+
+/// Smallest ring, signer included, accepted by default. This was the mandatory ring size of Monero
+/// from 2018 until 2022.
+pub const DEFAULT_MINIMUM_RING_SIZE: usize = 11;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct SignPolicy {
+    minimum_ring_size: usize,
+}
+
+impl Default for SignPolicy {
+    fn default() -> SignPolicy {
+        return SignPolicy {
+            minimum_ring_size: DEFAULT_MINIMUM_RING_SIZE,
+        };
+    }
+}
+
+impl SignPolicy {
+    pub fn new() -> SignPolicy {
+        return SignPolicy::default();
+    }
+
+    /// Requires rings of at least `minimum_ring_size` members, signer included
+    pub fn minimum_ring_size(mut self, minimum_ring_size: usize) -> SignPolicy {
+        self.minimum_ring_size = minimum_ring_size;
+        return self;
+    }
+
+    /// Accepts rings of any size. Only meant for tests and deployments that have a reason to
+    /// sign with fewer members
+    pub fn allow_small_rings(self) -> SignPolicy {
+        return self.minimum_ring_size(0);
+    }
+
+    /// Checks a ring of `ring_size` members, signer included, against the policy
+    pub fn check(&self, ring_size: usize) -> Result<(), Error> {
+        if ring_size < self.minimum_ring_size {
+            return Err(Error::RingTooSmall {
+                size: ring_size,
+                minimum: self.minimum_ring_size,
+            });
+        }
+        return Ok(());
+    }
+
+    /// Signs `message` with any scheme whose ring is a `Vec` of decoys, taking the same
+    /// arguments as its `sign`, if the decoys and the signer together satisfy the policy
+    #[cfg(feature = "sign")]
+    pub fn sign<
+        Hash: Digest<OutputSize = U64> + Clone + Default,
+        CSPRNG: CryptoRng + RngCore + Default,
+        PrivateKey,
+        T,
+        S: SignWithRng<PrivateKey, Vec<T>>,
+    >(
+        &self,
+        k: PrivateKey,
+        ring: Vec<T>,
+        secret_index: usize,
+        message: &Vec<u8>,
+    ) -> Result<S, Error> {
+        let mut csprng = CSPRNG::default();
+        return self.sign_with_rng::<Hash, CSPRNG, PrivateKey, Vec<T>, S>(k, ring, secret_index, message, &mut csprng);
+    }
+
+    /// Signs `message` with any scheme, taking the same arguments as its `sign_with_rng`, if the
+    /// decoys and the signer together satisfy the policy
+    #[cfg(feature = "sign")]
+    pub fn sign_with_rng<
+        Hash: Digest<OutputSize = U64> + Clone + Default,
+        CSPRNG: CryptoRng + RngCore,
+        PrivateKey,
+        Ring: Decoys,
+        S: SignWithRng<PrivateKey, Ring>,
+    >(
+        &self,
+        k: PrivateKey,
+        ring: Ring,
+        secret_index: usize,
+        message: &Vec<u8>,
+        csprng: &mut CSPRNG,
+    ) -> Result<S, Error> {
+        self.check(ring.decoys() + 1)?;
+        return S::sign_with_rng_unchecked::<Hash, CSPRNG>(k, ring, secret_index, message, csprng);
+    }
+}
+
+/// What an acceptor requires of a signature besides it being valid
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct VerifyPolicy {
+    /// Smallest ring, signer included, accepted
+    pub min_ring: usize,
+    /// Schemes accepted
+    pub allowed_schemes: Vec<SchemeId>,
+}
+
+impl Default for VerifyPolicy {
+    /// Rings of at least [`DEFAULT_MINIMUM_RING_SIZE`] members, of any scheme
+    fn default() -> VerifyPolicy {
+        return VerifyPolicy {
+            min_ring: DEFAULT_MINIMUM_RING_SIZE,
+            allowed_schemes: vec![
+                SchemeId::SAG,
+                SchemeId::LSAG,
+                SchemeId::BLSAG,
+                SchemeId::MLSAG,
+                SchemeId::CLSAG,
+                SchemeId::DLSAG,
+                SchemeId::MDLSAG,
+            ],
+        };
+    }
+}
+
+impl VerifyPolicy {
+    /// Checks a signature of `scheme` over a ring of `ring_size` members, signer included,
+    /// against the policy
+    pub fn check(&self, scheme: SchemeId, ring_size: usize) -> Result<(), Error> {
+        if !self.allowed_schemes.contains(&scheme) {
+            return Err(Error::SchemeNotAllowed { tag: scheme as u8 });
+        }
+        if ring_size < self.min_ring {
+            return Err(Error::RingTooSmall {
+                size: ring_size,
+                minimum: self.min_ring,
+            });
+        }
+        return Ok(());
+    }
+}
+
+/// Most bytes decoding or verifying may allocate
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct MemoryBudget {
+    max_bytes: usize,
+}
+
+impl Default for MemoryBudget {
+    /// No limit
+    fn default() -> MemoryBudget {
+        return MemoryBudget::unlimited();
+    }
+}
+
+impl MemoryBudget {
+    pub fn new(max_bytes: usize) -> MemoryBudget {
+        return MemoryBudget { max_bytes: max_bytes };
+    }
+
+    pub fn unlimited() -> MemoryBudget {
+        return MemoryBudget::new(usize::MAX);
+    }
+
+    pub fn max_bytes(&self) -> usize {
+        return self.max_bytes;
+    }
+
+    /// Checks an allocation of `bytes` against the budget
+    pub fn check(&self, bytes: usize) -> Result<(), Error> {
+        if bytes > self.max_bytes {
+            return Err(Error::BudgetExceeded {
+                needed: bytes,
+                budget: self.max_bytes,
+            });
+        }
+        return Ok(());
+    }
+}
+
+#[cfg(test)]
+#[cfg(feature = "std")]
+#[cfg(feature = "sign")]
+#[cfg(feature = "blsag")]
+mod test {
+    extern crate rand;
+    extern crate sha2;
+
+    use curve25519_dalek::ristretto::RistrettoPoint;
+    use curve25519_dalek::scalar::Scalar;
+    use rand::rngs::OsRng;
+    use sha2::Sha512;
+
+    use super::*;
+    use crate::blsag::BLSAG;
+    use crate::traits::{SignWithContext, SignWithRng, VerifyRef};
+
+    #[test]
+    fn policy() {
+        let k: Scalar = Scalar::random(&mut OsRng);
+        let ring: Vec<RistrettoPoint> = (0..2).map(|_| RistrettoPoint::random(&mut OsRng)).collect();
+        let message: Vec<u8> = b"This is the message".to_vec();
+
+        let rejected = SignPolicy::new().sign::<Sha512, OsRng, _, _, BLSAG>(k, ring.clone(), 1, &message);
+        assert_eq!(rejected.err(), Some(Error::RingTooSmall { size: 3, minimum: 11 }));
+        assert!(SignPolicy::new().minimum_ring_size(3).check(3).is_ok());
+
+        let signature: BLSAG = SignPolicy::new()
+            .allow_small_rings()
+            .sign::<Sha512, OsRng, _, _, _>(k, ring.clone(), 1, &message)
+            .unwrap();
+        assert!(signature.verify_ref::<Sha512>(&message));
+
+        // The primary signing path checks the default policy, unless explicitly opted out of
+        let rejected = BLSAG::sign_with_rng::<Sha512, _>(k, ring.clone(), 1, &message, &mut OsRng);
+        assert_eq!(rejected.err(), Some(Error::RingTooSmall { size: 3, minimum: 11 }));
+        let rejected = BLSAG::sign_ctx::<Sha512, OsRng>(k, ring.clone(), 1, b"context", &message);
+        assert_eq!(rejected.err(), Some(Error::RingTooSmall { size: 3, minimum: 11 }));
+        let signature = BLSAG::sign_with_rng_unchecked::<Sha512, _>(k, ring.clone(), 1, &message, &mut OsRng).unwrap();
+        assert!(signature.verify_ref::<Sha512>(&message));
+        let signature: BLSAG = SignPolicy::new()
+            .minimum_ring_size(3)
+            .sign_with_rng::<Sha512, _, _, _, _>(k, ring, 1, &message, &mut OsRng)
+            .unwrap();
+        assert!(signature.verify_ref::<Sha512>(&message));
+
+        let policy = VerifyPolicy {
+            min_ring: 3,
+            allowed_schemes: vec![SchemeId::BLSAG],
+        };
+        assert!(policy.check(SchemeId::BLSAG, 3).is_ok());
+        assert_eq!(policy.check(SchemeId::BLSAG, 2).err(), Some(Error::RingTooSmall { size: 2, minimum: 3 }));
+        assert_eq!(policy.check(SchemeId::SAG, 3).err(), Some(Error::SchemeNotAllowed { tag: 1 }));
+    }
+}
@@ -0,0 +1,245 @@
+//! Cheap checks ahead of verification
+//!
+//! Verifying a CLSAG over a large ring costs two multiscalar multiplications per member, while
+//! telling that its key image is already spent costs one lookup. A mempool receiving many
+//! payloads wants to reject everything it can before doing curve work. A [`PrecheckPipeline`]
+//! runs the stages it is configured with in order of cost and stops at the first that rejects:
+//!
+//! 1. [`Stage::Structure`]: the ring, responses and key images agree on their shape, see
+//!    [`TryVerify::check_shape`]
+//! 2. [`Stage::Policy`]: the scheme and ring size satisfy a [`VerifyPolicy`]
+//! 3. [`Stage::KeyImage`]: no key image is in a [`KeyImageStore`] of spent key images
+//! 4. [`Stage::Verification`]: the signature verifies
+//!
+//! The pipeline counts how many signatures each stage checked and rejected, so that operators
+//! can see where payloads are turned away, see [`PrecheckPipeline::metrics`].
+
+use crate::prelude::*;
+
+use digest::generic_array::typenum::U64;
+use digest::Digest;
+
+use crate::error::Error;
+use crate::link::{to_key_image, KeyImage, KeyImageStore, LinkOutcome};
+use crate::policy::VerifyPolicy;
+use crate::signature::RingSignature;
+use crate::traits::{TryVerify, VerifyRef};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Stage {
+    Structure,
+    Policy,
+    KeyImage,
+    Verification,
+}
+
+/// How many signatures a stage checked, and how many of those it rejected
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct StageMetrics {
+    pub checked: u64,
+    pub rejected: u64,
+}
+
+/// The [`StageMetrics`] of every stage, stages not configured staying at zero
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Metrics {
+    pub structure: StageMetrics,
+    pub policy: StageMetrics,
+    pub key_image: StageMetrics,
+    pub verification: StageMetrics,
+}
+
+impl Metrics {
+    pub fn stage(&self, stage: Stage) -> &StageMetrics {
+        return match stage {
+            Stage::Structure => &self.structure,
+            Stage::Policy => &self.policy,
+            Stage::KeyImage => &self.key_image,
+            Stage::Verification => &self.verification,
+        };
+    }
+
+    fn stage_mut(&mut self, stage: Stage) -> &mut StageMetrics {
+        return match stage {
+            Stage::Structure => &mut self.structure,
+            Stage::Policy => &mut self.policy,
+            Stage::KeyImage => &mut self.key_image,
+            Stage::Verification => &mut self.verification,
+        };
+    }
+}
+
+/// Checks of increasing cost, each run only if the ones before it passed
+///
+/// A new pipeline checks the structure and verifies. The policy and key image stages are added
+/// with [`PrecheckPipeline::policy`] and [`PrecheckPipeline::spent`].
+#[derive(Clone, Debug)]
+pub struct PrecheckPipeline<Store = Vec<KeyImage>> {
+    policy: Option<VerifyPolicy>,
+    spent: Option<Store>,
+    metrics: Metrics,
+}
+
+impl<Store> Default for PrecheckPipeline<Store> {
+    fn default() -> PrecheckPipeline<Store> {
+        return PrecheckPipeline {
+            policy: None,
+            spent: None,
+            metrics: Metrics::default(),
+        };
+    }
+}
+
+impl<Store: KeyImageStore> PrecheckPipeline<Store> {
+    pub fn new() -> PrecheckPipeline<Store> {
+        return PrecheckPipeline::default();
+    }
+
+    /// Rejects signatures `policy` does not accept
+    pub fn policy(mut self, policy: VerifyPolicy) -> PrecheckPipeline<Store> {
+        self.policy = Some(policy);
+        return self;
+    }
+
+    /// Rejects signatures with a key image in `spent`
+    ///
+    /// A store answering [`LinkOutcome::PossiblyLinked`], such as a Bloom filter, does not reject.
+    /// Such signatures go on to be verified and the caller confirms against the exact set.
+    pub fn spent(mut self, spent: Store) -> PrecheckPipeline<Store> {
+        self.spent = Some(spent);
+        return self;
+    }
+
+    /// The spent set, e.g. to add the key images of accepted signatures to
+    pub fn spent_mut(&mut self) -> Option<&mut Store> {
+        return self.spent.as_mut();
+    }
+
+    pub fn metrics(&self) -> &Metrics {
+        return &self.metrics;
+    }
+
+    pub fn reset_metrics(&mut self) {
+        self.metrics = Metrics::default();
+    }
+
+    /// Runs every configured stage on `signature`, stopping at the first that rejects
+    ///
+    /// Returns the error of the stage that rejected: that of [`TryVerify::check_shape`] or
+    /// [`VerifyPolicy::check`], [`Error::AlreadyClaimed`] for a spent key image, or
+    /// [`Error::InvalidSignature`].
+    pub fn check<Hash: Digest<OutputSize = U64> + Clone + Default>(
+        &mut self,
+        signature: &RingSignature,
+        message: &Vec<u8>,
+    ) -> Result<(), Error> {
+        run(&mut self.metrics, Stage::Structure, || signature.check_shape())?;
+        if let Some(policy) = &self.policy {
+            run(&mut self.metrics, Stage::Policy, || policy.check(signature.scheme(), signature.ring_size()))?;
+        }
+        if let Some(spent) = &self.spent {
+            run(&mut self.metrics, Stage::KeyImage, || {
+                let spent_key_image = signature
+                    .key_images()
+                    .iter()
+                    .any(|key_image| spent.outcome(&to_key_image(key_image)) == LinkOutcome::Linked);
+                if spent_key_image {
+                    return Err(Error::AlreadyClaimed);
+                }
+                return Ok(());
+            })?;
+        }
+        return run(&mut self.metrics, Stage::Verification, || {
+            if !signature.verify_ref::<Hash>(message) {
+                return Err(Error::InvalidSignature);
+            }
+            return Ok(());
+        });
+    }
+}
+
+/// Runs the check of `stage`, counting it in `metrics`
+fn run<F: FnOnce() -> Result<(), Error>>(metrics: &mut Metrics, stage: Stage, check: F) -> Result<(), Error> {
+    let outcome = check();
+    let metrics = metrics.stage_mut(stage);
+    metrics.checked += 1;
+    if outcome.is_err() {
+        metrics.rejected += 1;
+    }
+    return outcome;
+}
+
+#[cfg(test)]
+#[cfg(feature = "std")]
+#[cfg(feature = "sign")]
+#[cfg(feature = "clsag")]
+mod test {
+    extern crate rand;
+    extern crate sha2;
+
+    use curve25519_dalek::ristretto::RistrettoPoint;
+    use curve25519_dalek::scalar::Scalar;
+    use rand::rngs::OsRng;
+    use sha2::Sha512;
+
+    use super::*;
+    use crate::clsag::CLSAG;
+    use crate::header::SchemeId;
+    use crate::link::LinkingKeyImage;
+    use crate::traits::SignWithRng;
+
+    #[test]
+    fn precheck_pipeline() {
+        let message: Vec<u8> = b"This is the message".to_vec();
+        let ring: Vec<Vec<RistrettoPoint>> = (0..3)
+            .map(|_| (0..2).map(|_| RistrettoPoint::random(&mut OsRng)).collect())
+            .collect();
+        let sign = || {
+            let ks: Vec<Scalar> = (0..2).map(|_| Scalar::random(&mut OsRng)).collect();
+            return CLSAG::sign_with_rng_unchecked::<Sha512, _>(ks, ring.clone(), 0, &message, &mut OsRng).unwrap();
+        };
+        let spent = sign();
+        let fresh = sign();
+
+        let mut pipeline: PrecheckPipeline = PrecheckPipeline::new()
+            .policy(VerifyPolicy {
+                min_ring: 4,
+                allowed_schemes: vec![SchemeId::CLSAG],
+            })
+            .spent(vec![spent.linking_key_image().unwrap()]);
+
+        assert_eq!(pipeline.check::<Sha512>(&RingSignature::CLSAG(spent), &message), Err(Error::AlreadyClaimed));
+        assert_eq!(pipeline.check::<Sha512>(&RingSignature::CLSAG(fresh.clone()), &message), Ok(()));
+        assert_eq!(
+            pipeline.check::<Sha512>(&RingSignature::CLSAG(fresh.clone()), &b"Another message".to_vec()),
+            Err(Error::InvalidSignature)
+        );
+        let mut malformed = fresh.clone();
+        malformed.responses.pop();
+        assert_eq!(
+            pipeline.check::<Sha512>(&RingSignature::CLSAG(malformed), &message),
+            Err(Error::ResponseCountMismatch)
+        );
+        let mut small = fresh.clone();
+        small.ring.truncate(3);
+        small.responses.truncate(3);
+        assert_eq!(
+            pipeline.check::<Sha512>(&RingSignature::CLSAG(small), &message),
+            Err(Error::RingTooSmall { size: 3, minimum: 4 })
+        );
+
+        // Each stage saw only what the stages before it let through
+        let metrics = pipeline.metrics();
+        assert_eq!(metrics.structure, StageMetrics { checked: 5, rejected: 1 });
+        assert_eq!(metrics.policy, StageMetrics { checked: 4, rejected: 1 });
+        assert_eq!(metrics.key_image, StageMetrics { checked: 3, rejected: 1 });
+        assert_eq!(*metrics.stage(Stage::Verification), StageMetrics { checked: 2, rejected: 1 });
+
+        // Accepting a signature spends its key image
+        pipeline.spent_mut().unwrap().push(fresh.linking_key_image().unwrap());
+        pipeline.spent_mut().unwrap().sort();
+        assert_eq!(pipeline.check::<Sha512>(&RingSignature::CLSAG(fresh), &message), Err(Error::AlreadyClaimed));
+        pipeline.reset_metrics();
+        assert_eq!(*pipeline.metrics(), Metrics::default());
+    }
+}
@@ -0,0 +1,212 @@
+//! Operation profiles
+//!
+//! Before filing a performance bug it helps to know whether the time goes into hashing, curve
+//! arithmetic or allocation. [`SignProfiled`] and [`VerifyProfiled`] sign and verify as usual and
+//! return a [`Profile`] of the operations that took, counted as the schemes perform them:
+//!
+//! ```ignore
+//! let (valid, profile) = signature.verify_profiled::<Sha512>(&message);
+//! println!("{:?}", profile);
+//! ```
+//!
+//! Counting is per thread, so work done on other threads, e.g. by the `parallel` feature, is left
+//! out. Hash to point is counted for [`DefaultHashToPoint`](crate::hash_to_point::DefaultHashToPoint)
+//! and not for mappings of your own. Allocations are only counted with [`CountingAllocator`]
+//! installed as the global allocator, and are zero otherwise:
+//!
+//! ```ignore
+//! #[global_allocator]
+//! static ALLOCATOR: CountingAllocator = CountingAllocator(std::alloc::System);
+//! ```
+
+use crate::prelude::*;
+
+use core::cell::Cell;
+use std::alloc::{GlobalAlloc, Layout, System};
+
+use digest::generic_array::typenum::U64;
+use digest::Digest;
+#[cfg(feature = "sign")]
+use rand_core::{CryptoRng, RngCore};
+
+use crate::traits::VerifyRef;
+#[cfg(feature = "sign")]
+use crate::error::Error;
+#[cfg(feature = "sign")]
+use crate::traits::{Decoys, SignWithRng};
+
+/// The operations of one sign or verify
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Profile {
+    /// Points hashed to the curve, for key images and their counterparts in the challenges
+    pub hashes_to_point: u64,
+    /// Multiplications of the generator
+    pub fixed_base_mults: u64,
+    /// Multiplications of any other point
+    pub variable_base_mults: u64,
+    /// Multiscalar multiplications
+    pub multiscalar_mults: u64,
+    /// The terms of all multiscalar multiplications together
+    pub multiscalar_terms: u64,
+    /// The terms of the largest multiscalar multiplication
+    pub largest_multiscalar: u64,
+    /// Allocations, with [`CountingAllocator`] installed
+    pub allocations: u64,
+    /// Bytes allocated, with [`CountingAllocator`] installed
+    pub allocated_bytes: u64,
+}
+
+impl Profile {
+    fn merge(self, other: &Profile) -> Profile {
+        return Profile {
+            hashes_to_point: self.hashes_to_point + other.hashes_to_point,
+            fixed_base_mults: self.fixed_base_mults + other.fixed_base_mults,
+            variable_base_mults: self.variable_base_mults + other.variable_base_mults,
+            multiscalar_mults: self.multiscalar_mults + other.multiscalar_mults,
+            multiscalar_terms: self.multiscalar_terms + other.multiscalar_terms,
+            largest_multiscalar: self.largest_multiscalar.max(other.largest_multiscalar),
+            allocations: self.allocations + other.allocations,
+            allocated_bytes: self.allocated_bytes + other.allocated_bytes,
+        };
+    }
+}
+
+std::thread_local! {
+    // `Copy`, so that the allocator can count into it without allocating or borrowing
+    static CURRENT: Cell<Option<Profile>> = const { Cell::new(None) };
+}
+
+/// Counts into the profile being measured on this thread, if any
+pub(crate) fn record<F: FnOnce(&mut Profile)>(f: F) {
+    let _ = CURRENT.try_with(|current| {
+        if let Some(mut profile) = current.get() {
+            f(&mut profile);
+            current.set(Some(profile));
+        }
+    });
+}
+
+/// Runs `f`, returning its result with the operations it took on this thread
+pub fn measure<R, F: FnOnce() -> R>(f: F) -> (R, Profile) {
+    let outer: Option<Profile> = CURRENT.with(|current| current.replace(Some(Profile::default())));
+    let result: R = f();
+    let profile: Profile = CURRENT.with(|current| {
+        let profile: Profile = current.get().unwrap_or_default();
+        current.set(outer.map(|outer| outer.merge(&profile)));
+        return profile;
+    });
+    return (result, profile);
+}
+
+/// A global allocator counting the allocations of [`measure`] before passing them to `A`
+pub struct CountingAllocator<A = System>(pub A);
+
+unsafe impl<A: GlobalAlloc> GlobalAlloc for CountingAllocator<A> {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        record(|profile| {
+            profile.allocations += 1;
+            profile.allocated_bytes += layout.size() as u64;
+        });
+        return self.0.alloc(layout);
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        self.0.dealloc(ptr, layout);
+    }
+
+    unsafe fn alloc_zeroed(&self, layout: Layout) -> *mut u8 {
+        record(|profile| {
+            profile.allocations += 1;
+            profile.allocated_bytes += layout.size() as u64;
+        });
+        return self.0.alloc_zeroed(layout);
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        record(|profile| {
+            profile.allocations += 1;
+            profile.allocated_bytes += new_size as u64;
+        });
+        return self.0.realloc(ptr, layout, new_size);
+    }
+}
+
+/// Signing that also returns the [`Profile`] of the signing
+#[cfg(feature = "sign")]
+pub trait SignProfiled<PrivateKey, Ring>: SignWithRng<PrivateKey, Ring> {
+    fn sign_profiled<Hash: Digest<OutputSize = U64> + Clone + Default, CSPRNG: CryptoRng + RngCore + Default>(
+        k: PrivateKey,
+        ring: Ring,
+        secret_index: usize,
+        message: &Vec<u8>,
+    ) -> (Result<Self, Error>, Profile)
+    where
+        Ring: Decoys,
+    {
+        let mut csprng = CSPRNG::default();
+        return measure(|| Self::sign_with_rng::<Hash, CSPRNG>(k, ring, secret_index, message, &mut csprng));
+    }
+}
+
+#[cfg(feature = "sign")]
+impl<PrivateKey, Ring, S: SignWithRng<PrivateKey, Ring>> SignProfiled<PrivateKey, Ring> for S {}
+
+/// Verification that also returns the [`Profile`] of the verification
+pub trait VerifyProfiled: VerifyRef {
+    fn verify_profiled<Hash: Digest<OutputSize = U64> + Clone + Default>(&self, message: &Vec<u8>) -> (bool, Profile) {
+        return measure(|| self.verify_ref::<Hash>(message));
+    }
+}
+
+impl<S: VerifyRef> VerifyProfiled for S {}
+
+#[cfg(test)]
+#[cfg(feature = "std")]
+#[cfg(all(feature = "blsag", feature = "clsag"))]
+mod test {
+    extern crate rand;
+    extern crate sha2;
+
+    use curve25519_dalek::ristretto::RistrettoPoint;
+    use curve25519_dalek::scalar::Scalar;
+    use rand::rngs::OsRng;
+    use sha2::Sha512;
+
+    use super::*;
+    use crate::blsag::BLSAG;
+    use crate::clsag::CLSAG;
+
+    #[test]
+    fn profiled() {
+        let message: Vec<u8> = b"This is the message".to_vec();
+        let k: Scalar = Scalar::random(&mut OsRng);
+        let ring: Vec<RistrettoPoint> = (0..10).map(|_| RistrettoPoint::random(&mut OsRng)).collect();
+        let (signature, profile) = BLSAG::sign_profiled::<Sha512, OsRng>(k, ring, 1, &message);
+        let signature = signature.unwrap();
+        // One step of the ring of eleven is the signer's
+        assert_eq!(profile.fixed_base_mults, 2);
+        assert_eq!(profile.multiscalar_mults, 2 * 10);
+        assert_eq!(profile.largest_multiscalar, 2);
+
+        let (valid, profile) = signature.verify_profiled::<Sha512>(&message);
+        assert!(valid);
+        // Verification hashes every member of the ring to the curve
+        assert_eq!(profile.hashes_to_point, 11);
+        assert_eq!(profile.multiscalar_mults, 2 * 11);
+        assert_eq!(profile.multiscalar_terms, 2 * 2 * 11);
+        assert_eq!(profile.fixed_base_mults, 0);
+
+        // Nested measurements count into the outer one
+        let ks: Vec<Scalar> = (0..2).map(|_| Scalar::random(&mut OsRng)).collect();
+        let ring: Vec<Vec<RistrettoPoint>> =
+            (0..10).map(|_| (0..2).map(|_| RistrettoPoint::random(&mut OsRng)).collect()).collect();
+        let signature = CLSAG::sign_profiled::<Sha512, OsRng>(ks, ring, 2, &message).0.unwrap();
+        let ((_, inner), outer) = measure(|| {
+            let _ = signature.verify_profiled::<Sha512>(&message);
+            return signature.verify_profiled::<Sha512>(&message);
+        });
+        assert_eq!(outer.multiscalar_mults, 2 * inner.multiscalar_mults);
+        assert_eq!(inner.largest_multiscalar, 2);
+        assert_eq!(CURRENT.with(|current| current.get()), None);
+    }
+}
@@ -0,0 +1,6 @@
+//! Standalone zero-knowledge proofs about keys and key images
+
+pub mod key_image_binding;
+pub mod or_proof;
+#[cfg(feature = "blsag")]
+pub mod rotation;
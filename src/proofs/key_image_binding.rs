@@ -0,0 +1,144 @@
+//! Key image binding proofs
+//!
+//! Proves that a declared key image `I` belongs to a public key `P`, that is `P = k G` and
+//! `I = k H_p(P)` for the same private key `k`, without revealing `k` and without signing any
+//! spend. This is a discrete log equality proof over the bases `G` and `H_p(P)`. An exchange can
+//! whitelist the key image a user declares before a withdrawal and recognise the spend later, as
+//! bLSAG, MLSAG and CLSAG derive the (first) key image of `k` the same way.
+
+use crate::prelude::*;
+
+use curve25519_dalek::constants;
+use curve25519_dalek::ristretto::RistrettoPoint;
+use curve25519_dalek::scalar::Scalar;
+use curve25519_dalek::traits::MultiscalarMul;
+use digest::generic_array::typenum::U64;
+use digest::Digest;
+#[cfg(feature = "sign")]
+use rand_core::{CryptoRng, RngCore};
+
+use crate::hash_to_point::{DefaultHashToPoint, HashToPoint};
+
+/// Proof that `key_image` is the key image of `public_key`, bound to a message
+///
+/// Please read tests at the bottom of the source code for this module for examples on how to use
+/// it
+#[derive(Clone)]
+pub struct KeyImageBinding {
+    pub public_key: RistrettoPoint,
+    pub key_image: RistrettoPoint,
+    pub challenge: Scalar,
+    pub response: Scalar,
+}
+
+/// The challenge `c = H_n(P, I, m, point_a, point_b)`
+fn challenge<Hash: Digest<OutputSize = U64> + Clone + Default>(
+    public_key: &RistrettoPoint,
+    key_image: &RistrettoPoint,
+    message: &Vec<u8>,
+    point_a: &RistrettoPoint,
+    point_b: &RistrettoPoint,
+) -> Scalar {
+    let mut h: Hash = Hash::default();
+    h.update(b"nazgul/key_image_binding");
+    h.update(public_key.compress().as_bytes());
+    h.update(key_image.compress().as_bytes());
+    h.update(message);
+    h.update(point_a.compress().as_bytes());
+    h.update(point_b.compress().as_bytes());
+    return Scalar::from_hash(h);
+}
+
+impl KeyImageBinding {
+    /// To prove you need `k` your private key. The `message` (e.g. a withdrawal request) is bound
+    /// into the proof so it can not be replayed elsewhere
+    #[cfg(feature = "sign")]
+    pub fn prove<
+        Hash: Digest<OutputSize = U64> + Clone + Default,
+        CSPRNG: CryptoRng + RngCore + Default,
+    >(
+        k: Scalar,
+        message: &Vec<u8>,
+    ) -> KeyImageBinding {
+        let mut csprng = CSPRNG::default();
+        let public_key: RistrettoPoint = k * constants::RISTRETTO_BASEPOINT_POINT;
+        let public_key_hashed_to_point: RistrettoPoint = DefaultHashToPoint::hash_to_point::<Hash>(&public_key);
+        let key_image: RistrettoPoint = k * public_key_hashed_to_point;
+
+        let a: Scalar = Scalar::random(&mut csprng);
+        let c: Scalar = challenge::<Hash>(
+            &public_key,
+            &key_image,
+            message,
+            &(a * constants::RISTRETTO_BASEPOINT_POINT),
+            &(a * public_key_hashed_to_point),
+        );
+
+        return KeyImageBinding {
+            public_key: public_key,
+            key_image: key_image,
+            challenge: c,
+            response: a - c * k,
+        };
+    }
+
+    /// To verify the proof you need the `message` too
+    pub fn verify<Hash: Digest<OutputSize = U64> + Clone + Default>(&self, message: &Vec<u8>) -> bool {
+        let public_key_hashed_to_point: RistrettoPoint = DefaultHashToPoint::hash_to_point::<Hash>(&self.public_key);
+        let reconstructed_c: Scalar = challenge::<Hash>(
+            &self.public_key,
+            &self.key_image,
+            message,
+            &RistrettoPoint::multiscalar_mul(
+                &[self.response, self.challenge],
+                &[constants::RISTRETTO_BASEPOINT_POINT, self.public_key],
+            ),
+            &RistrettoPoint::multiscalar_mul(
+                &[self.response, self.challenge],
+                &[public_key_hashed_to_point, self.key_image],
+            ),
+        );
+        return self.challenge == reconstructed_c;
+    }
+}
+
+#[cfg(test)]
+#[cfg(feature = "std")]
+#[cfg(feature = "sign")]
+#[cfg(feature = "blsag")]
+mod test {
+    extern crate rand;
+    extern crate sha2;
+
+    use rand::rngs::OsRng;
+    use sha2::Sha512;
+
+    use super::*;
+    use crate::blsag::BLSAG;
+    use crate::traits::{KeyImageGenRef, SignWithRng};
+
+    #[test]
+    fn key_image_binding() {
+        let k: Scalar = Scalar::random(&mut OsRng);
+        let message: Vec<u8> = b"Withdrawal 42".to_vec();
+        let proof = KeyImageBinding::prove::<Sha512, OsRng>(k, &message);
+        assert!(proof.verify::<Sha512>(&message));
+        assert!(!proof.verify::<Sha512>(&b"Withdrawal 43".to_vec()));
+
+        // The declared key image is the one a later spend will reveal
+        assert_eq!(proof.key_image, BLSAG::generate_key_image_ref::<Sha512>(&k));
+        let ring: Vec<RistrettoPoint> = (0..2).map(|_| RistrettoPoint::random(&mut OsRng)).collect();
+        let spend =
+            BLSAG::sign_with_rng_unchecked::<Sha512, _>(k, ring, 0, &b"This is the message".to_vec(), &mut OsRng)
+                .unwrap();
+        assert_eq!(proof.key_image, spend.key_image);
+
+        // Binding the key image to someone else's public key fails
+        let mut forged = proof.clone();
+        forged.public_key = RistrettoPoint::random(&mut OsRng);
+        assert!(!forged.verify::<Sha512>(&message));
+        let mut forged = proof;
+        forged.key_image = RistrettoPoint::random(&mut OsRng);
+        assert!(!forged.verify::<Sha512>(&message));
+    }
+}
@@ -0,0 +1,200 @@
+//! Proofs of ownership of either of two keys
+//!
+//! Proves knowledge of `k` such that `P_0 = k G_0` or `P_1 = k G_1`, without revealing which,
+//! where the two keys may be over different generators, e.g. a payment key and a refund key. This
+//! is a SAG with a ring of two whose members each carry their own generator, the OR-composition of
+//! two Schnorr proofs, and needs none of the key image machinery of DLSAG.
+//!
+//! With the `serde` feature the proof serializes as its canonical encoding, see [`crate::codec`].
+
+use crate::prelude::*;
+
+use curve25519_dalek::ristretto::RistrettoPoint;
+use curve25519_dalek::scalar::Scalar;
+use curve25519_dalek::traits::MultiscalarMul;
+use digest::generic_array::typenum::U64;
+use digest::Digest;
+#[cfg(feature = "sign")]
+use rand_core::{CryptoRng, RngCore};
+
+use crate::codec::{self, Codec, Reader};
+use crate::error::Error;
+
+/// Proof of knowledge of the private key of one of `public_keys`, bound to a message
+///
+/// Please read tests at the bottom of the source code for this module for examples on how to use
+/// it
+#[derive(Clone)]
+pub struct OrProof {
+    pub public_keys: [RistrettoPoint; 2],
+    pub challenge: Scalar,
+    pub responses: [Scalar; 2],
+}
+
+/// A generator with no known discrete log relative to the basepoint, for use as `G_1`
+pub fn independent_generator<Hash: Digest<OutputSize = U64> + Clone + Default>() -> RistrettoPoint {
+    return RistrettoPoint::from_hash(Hash::default().chain_update(b"nazgul/or_proof/generator"));
+}
+
+/// The hash of the statement and message every challenge starts from
+fn prefix<Hash: Digest<OutputSize = U64> + Clone + Default>(
+    generators: &[RistrettoPoint; 2],
+    public_keys: &[RistrettoPoint; 2],
+    message: &Vec<u8>,
+) -> Hash {
+    let mut h: Hash = Hash::default();
+    h.update(b"nazgul/or_proof");
+    for i in 0..2 {
+        h.update(generators[i].compress().as_bytes());
+        h.update(public_keys[i].compress().as_bytes());
+    }
+    h.update(message);
+    return h;
+}
+
+fn challenge<Hash: Digest<OutputSize = U64> + Clone + Default>(prefix: &Hash, point: &RistrettoPoint) -> Scalar {
+    return Scalar::from_hash(prefix.clone().chain_update(point.compress().as_bytes()));
+}
+
+impl OrProof {
+    /// To prove you need `k` the private key of `public_keys[secret_index]` over
+    /// `generators[secret_index]`, and the other public key over its generator
+    #[cfg(feature = "sign")]
+    pub fn prove<Hash: Digest<OutputSize = U64> + Clone + Default, CSPRNG: CryptoRng + RngCore + Default>(
+        k: Scalar,
+        generators: &[RistrettoPoint; 2],
+        other_public_key: RistrettoPoint,
+        secret_index: usize,
+        message: &Vec<u8>,
+    ) -> OrProof {
+        let mut csprng = CSPRNG::default();
+        let j = secret_index % 2;
+        let mut public_keys = [other_public_key; 2];
+        public_keys[j] = k * generators[j];
+        let prefix = prefix::<Hash>(generators, &public_keys, message);
+
+        let a: Scalar = Scalar::random(&mut csprng);
+        let mut challenges: [Scalar; 2] = [Scalar::ZERO; 2];
+        let mut responses: [Scalar; 2] = [Scalar::ZERO; 2];
+        challenges[1 - j] = challenge::<Hash>(&prefix, &(a * generators[j]));
+        responses[1 - j] = Scalar::random(&mut csprng);
+        challenges[j] = challenge::<Hash>(
+            &prefix,
+            &RistrettoPoint::multiscalar_mul(
+                &[responses[1 - j], challenges[1 - j]],
+                &[generators[1 - j], public_keys[1 - j]],
+            ),
+        );
+        responses[j] = a - challenges[j] * k;
+
+        return OrProof {
+            public_keys: public_keys,
+            challenge: challenges[0],
+            responses: responses,
+        };
+    }
+
+    /// To verify the proof you need the `generators` and the `message` it was made with
+    pub fn verify<Hash: Digest<OutputSize = U64> + Clone + Default>(
+        &self,
+        generators: &[RistrettoPoint; 2],
+        message: &Vec<u8>,
+    ) -> bool {
+        let prefix = prefix::<Hash>(generators, &self.public_keys, message);
+        let mut reconstructed_c: Scalar = self.challenge;
+        for i in 0..2 {
+            reconstructed_c = challenge::<Hash>(
+                &prefix,
+                &RistrettoPoint::multiscalar_mul(
+                    &[self.responses[i], reconstructed_c],
+                    &[generators[i], self.public_keys[i]],
+                ),
+            );
+        }
+        return self.challenge == reconstructed_c;
+    }
+}
+
+impl Codec for OrProof {
+    /// Encodes the proof canonically, see [`crate::codec`]
+    fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes: Vec<u8> = Vec::new();
+        codec::write_scalar(&mut bytes, &self.challenge);
+        for response in &self.responses {
+            codec::write_scalar(&mut bytes, response);
+        }
+        for public_key in &self.public_keys {
+            codec::write_point(&mut bytes, public_key);
+        }
+        return bytes;
+    }
+
+    /// Decodes a proof, returning the [`Error`] describing why if `bytes` is not a canonical encoding
+    fn decode(bytes: &[u8]) -> Result<OrProof, Error> {
+        let mut reader = Reader::new(bytes);
+        let challenge = reader.read_scalar()?;
+        let responses = [reader.read_scalar()?, reader.read_scalar()?];
+        let public_keys = [reader.read_point()?, reader.read_point()?];
+        reader.finish()?;
+        return Ok(OrProof {
+            public_keys: public_keys,
+            challenge: challenge,
+            responses: responses,
+        });
+    }
+}
+
+#[cfg(feature = "serde")]
+codec::serde_via_codec!(OrProof);
+
+#[cfg(test)]
+#[cfg(feature = "std")]
+#[cfg(feature = "sign")]
+mod test {
+    extern crate rand;
+    extern crate sha2;
+
+    use curve25519_dalek::constants;
+    use rand::rngs::OsRng;
+    use sha2::Sha512;
+
+    use super::*;
+
+    #[test]
+    fn or_proof() {
+        let generators = [constants::RISTRETTO_BASEPOINT_POINT, independent_generator::<Sha512>()];
+        let message: Vec<u8> = b"Refund after block 1000".to_vec();
+        let payment_k: Scalar = Scalar::random(&mut OsRng);
+        let refund_k: Scalar = Scalar::random(&mut OsRng);
+        let payment_key = payment_k * generators[0];
+        let refund_key = refund_k * generators[1];
+
+        // Either key proves, and the proofs look alike
+        let by_payment = OrProof::prove::<Sha512, OsRng>(payment_k, &generators, refund_key, 0, &message);
+        let by_refund = OrProof::prove::<Sha512, OsRng>(refund_k, &generators, payment_key, 1, &message);
+        assert!(by_payment.verify::<Sha512>(&generators, &message));
+        assert!(by_refund.verify::<Sha512>(&generators, &message));
+        assert_eq!(by_payment.public_keys, by_refund.public_keys);
+
+        assert!(!by_payment.verify::<Sha512>(&generators, &b"Refund after block 1".to_vec()));
+        assert!(!by_payment.verify::<Sha512>(&[generators[1], generators[0]], &message));
+        let mut forged = by_payment.clone();
+        forged.public_keys[1] = RistrettoPoint::random(&mut OsRng);
+        assert!(!forged.verify::<Sha512>(&generators, &message));
+
+        let bytes = by_refund.to_bytes();
+        let decoded = OrProof::from_bytes(&bytes).unwrap();
+        assert!(decoded.verify::<Sha512>(&generators, &message));
+        assert!(OrProof::from_bytes(&bytes[1..]).is_none());
+
+        #[cfg(feature = "serde")]
+        {
+            use serde::de::value::{BytesDeserializer, Error};
+            use serde::Deserialize;
+
+            let decoded = OrProof::deserialize(BytesDeserializer::<Error>::new(&bytes)).unwrap();
+            assert!(decoded.verify::<Sha512>(&generators, &message));
+            assert!(OrProof::deserialize(BytesDeserializer::<Error>::new(&bytes[1..])).is_err());
+        }
+    }
+}
@@ -0,0 +1,164 @@
+//! Key rotation proofs
+//!
+//! Services that remember key images, e.g. to refuse a second vote or claim, lose track of a user
+//! who rotates to a new signing key. A [`RotationProof`] carries that state over: it links the old
+//! key image `I_old` to the new key image `I_new` by proving that whoever made it holds both
+//! private keys, without revealing either public key.
+//!
+//! The proof is two bLSAGs, one by each key, over the same statement naming both key images. Each
+//! hides its signer in its own ring, so the proof discloses no more than a spend would, and
+//! neither key alone can produce it.
+
+use crate::prelude::*;
+
+use curve25519_dalek::ristretto::RistrettoPoint;
+#[cfg(feature = "sign")]
+use curve25519_dalek::scalar::Scalar;
+use digest::generic_array::typenum::U64;
+use digest::Digest;
+#[cfg(feature = "sign")]
+use rand_core::{CryptoRng, RngCore};
+
+use crate::blsag::BLSAG;
+use crate::codec::{self, Codec, Reader};
+use crate::error::Error;
+use crate::traits::VerifyRef;
+#[cfg(feature = "sign")]
+use crate::traits::{KeyImageGenRef, SignWithRng};
+
+/// Domain separation of the statement signed by both keys
+const ROTATION: &[u8] = b"nazgul/rotation";
+
+/// Proof that the holder of the key of `old.key_image` also holds the key of `new.key_image`
+///
+/// Please read tests at the bottom of the source code for this module for examples on how to use
+/// it
+#[derive(Clone)]
+pub struct RotationProof {
+    /// Signed by the old key
+    pub old: BLSAG,
+    /// Signed by the new key
+    pub new: BLSAG,
+}
+
+/// The statement `"nazgul/rotation" || I_old || I_new || m` both keys sign
+fn statement(old_key_image: &RistrettoPoint, new_key_image: &RistrettoPoint, message: &Vec<u8>) -> Vec<u8> {
+    let mut statement: Vec<u8> = ROTATION.to_vec();
+    statement.extend_from_slice(old_key_image.compress().as_bytes());
+    statement.extend_from_slice(new_key_image.compress().as_bytes());
+    statement.extend_from_slice(message);
+    return statement;
+}
+
+impl RotationProof {
+    /// To prove you need both private keys, `k_old` and `k_new`, each with the decoys to hide it
+    /// among and its position in the ring, as for [`SignWithRng::sign_with_rng`]. The `message`
+    /// (e.g. the service and a date) is bound into the proof so it can not be replayed elsewhere
+    #[cfg(feature = "sign")]
+    pub fn prove<Hash: Digest<OutputSize = U64> + Clone + Default, CSPRNG: CryptoRng + RngCore + Default>(
+        k_old: Scalar,
+        ring_old: Vec<RistrettoPoint>,
+        secret_index_old: usize,
+        k_new: Scalar,
+        ring_new: Vec<RistrettoPoint>,
+        secret_index_new: usize,
+        message: &Vec<u8>,
+    ) -> Result<RotationProof, Error> {
+        let statement = statement(
+            &BLSAG::generate_key_image_ref::<Hash>(&k_old),
+            &BLSAG::generate_key_image_ref::<Hash>(&k_new),
+            message,
+        );
+        let mut csprng = CSPRNG::default();
+        return Ok(RotationProof {
+            old: BLSAG::sign_with_rng::<Hash, CSPRNG>(k_old, ring_old, secret_index_old, &statement, &mut csprng)?,
+            new: BLSAG::sign_with_rng::<Hash, CSPRNG>(k_new, ring_new, secret_index_new, &statement, &mut csprng)?,
+        });
+    }
+
+    /// The key image of the old key, as its spends reveal it
+    pub fn old_key_image(&self) -> &RistrettoPoint {
+        return &self.old.key_image;
+    }
+
+    /// The key image of the new key, as its spends will reveal it
+    pub fn new_key_image(&self) -> &RistrettoPoint {
+        return &self.new.key_image;
+    }
+
+    /// To verify the proof you need the `message` too
+    pub fn verify<Hash: Digest<OutputSize = U64> + Clone + Default>(&self, message: &Vec<u8>) -> bool {
+        let statement = statement(&self.old.key_image, &self.new.key_image, message);
+        return self.old.verify_ref::<Hash>(&statement) && self.new.verify_ref::<Hash>(&statement);
+    }
+}
+
+impl Codec for RotationProof {
+    /// The length of the encoding of `old`, then the encodings of `old` and `new`
+    fn to_bytes(&self) -> Vec<u8> {
+        let old = self.old.to_bytes();
+        let mut bytes: Vec<u8> = Vec::new();
+        codec::write_u32(&mut bytes, old.len());
+        bytes.extend_from_slice(&old);
+        bytes.extend_from_slice(&self.new.to_bytes());
+        return bytes;
+    }
+
+    fn decode(bytes: &[u8]) -> Result<RotationProof, Error> {
+        let mut reader = Reader::new(bytes);
+        let old_len = reader.read_len(1)?;
+        let old = BLSAG::decode(reader.read_bytes(old_len)?)?;
+        let new = BLSAG::decode(reader.rest())?;
+        return Ok(RotationProof { old: old, new: new });
+    }
+}
+
+#[cfg(feature = "serde")]
+codec::serde_via_codec!(RotationProof);
+
+#[cfg(test)]
+#[cfg(feature = "std")]
+#[cfg(feature = "sign")]
+mod test {
+    extern crate rand;
+    extern crate sha2;
+
+    use rand::rngs::OsRng;
+    use sha2::Sha512;
+
+    use super::*;
+    use crate::traits::KeyImageGenRef;
+
+    #[test]
+    fn rotation() {
+        let k_old: Scalar = Scalar::random(&mut OsRng);
+        let k_new: Scalar = Scalar::random(&mut OsRng);
+        let decoys = |n: usize| -> Vec<RistrettoPoint> { (0..n).map(|_| RistrettoPoint::random(&mut OsRng)).collect() };
+        let message: Vec<u8> = b"Poll 7, 2026-10-14".to_vec();
+
+        let proof =
+            RotationProof::prove::<Sha512, OsRng>(k_old, decoys(10), 1, k_new, decoys(10), 0, &message).unwrap();
+        assert!(proof.verify::<Sha512>(&message));
+        assert!(!proof.verify::<Sha512>(&b"Poll 8, 2026-10-14".to_vec()));
+        assert_eq!(*proof.old_key_image(), BLSAG::generate_key_image_ref::<Sha512>(&k_old));
+        assert_eq!(*proof.new_key_image(), BLSAG::generate_key_image_ref::<Sha512>(&k_new));
+
+        let decoded = RotationProof::from_bytes(&proof.to_bytes()).unwrap();
+        assert!(decoded.verify::<Sha512>(&message));
+
+        // Without the new key, a proof for someone else's key image fails
+        let other = RotationProof::prove::<Sha512, OsRng>(
+            k_old,
+            decoys(10),
+            1,
+            Scalar::random(&mut OsRng),
+            decoys(10),
+            0,
+            &message,
+        )
+        .unwrap();
+        let mut forged = proof.clone();
+        forged.new = other.new;
+        assert!(!forged.verify::<Sha512>(&message));
+    }
+}
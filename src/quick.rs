@@ -0,0 +1,72 @@
+//! One-shot bLSAG over bytes
+//!
+//! For scripts and simple integrations that just want to sign and verify byte strings. Keys and
+//! signatures are canonical bytes, the hash function is SHA-512 and the signer's position in the
+//! ring is chosen at random. Only available with the `quick` feature.
+//!
+//! Anything more involved should use [`crate::blsag`] and [`crate::codec`] directly.
+
+use crate::prelude::*;
+
+use curve25519_dalek::ristretto::RistrettoPoint;
+use curve25519_dalek::scalar::Scalar;
+use rand_core::{OsRng, RngCore};
+use sha2::Sha512;
+
+use crate::blsag::BLSAG;
+use crate::codec::{Codec, Reader};
+use crate::traits::{SignWithRng, VerifyRef};
+
+/// Signs `message` with the 32 byte private key `k_bytes` over `ring_bytes`, the concatenated 32
+/// byte compressed public keys of everyone except the signer. Returns the encoded signature, or
+/// `None` if a key is malformed or the ring is smaller than the default
+/// [`crate::policy::SignPolicy`] allows.
+pub fn blsag_sign(k_bytes: &[u8], ring_bytes: &[u8], message: &[u8]) -> Option<Vec<u8>> {
+    let k: Scalar = Reader::new(k_bytes).read_scalar().ok()?;
+    let mut reader = Reader::new(ring_bytes);
+    let ring: Vec<RistrettoPoint> = reader.read_points(ring_bytes.len() / 32).ok()?;
+    reader.finish().ok()?;
+
+    let secret_index = (OsRng.next_u64() % (ring.len() as u64 + 1)) as usize;
+    let signature = BLSAG::sign_with_rng::<Sha512, _>(k, ring, secret_index, &message.to_vec(), &mut OsRng).ok()?;
+    return Some(signature.to_bytes());
+}
+
+/// Verifies an encoded signature produced by [`blsag_sign`] over `message`
+pub fn blsag_verify(signature_bytes: &[u8], message: &[u8]) -> bool {
+    return match BLSAG::from_bytes(signature_bytes) {
+        Some(signature) => signature.verify_ref::<Sha512>(&message.to_vec()),
+        None => false,
+    };
+}
+
+#[cfg(test)]
+mod test {
+    extern crate rand;
+
+    use curve25519_dalek::constants;
+    use rand::rngs::OsRng;
+
+    use super::*;
+
+    #[test]
+    fn quick() {
+        let k: Scalar = Scalar::random(&mut OsRng);
+        let ring_bytes: Vec<u8> = (0..10)
+            .flat_map(|_| RistrettoPoint::random(&mut OsRng).compress().to_bytes().to_vec())
+            .collect();
+
+        let signature = blsag_sign(k.as_bytes(), &ring_bytes, b"This is the message").unwrap();
+        assert!(blsag_verify(&signature, b"This is the message"));
+        assert!(!blsag_verify(&signature, b"This is another message"));
+        assert!(!blsag_verify(&signature[1..], b"This is the message"));
+        // The signer's public key is somewhere in the ring
+        let k_point = (k * constants::RISTRETTO_BASEPOINT_POINT).compress().to_bytes();
+        assert!(signature.windows(32).any(|window| window == k_point));
+
+        assert!(blsag_sign(&[0xff; 32], &ring_bytes, b"This is the message").is_none());
+        assert!(blsag_sign(k.as_bytes(), &ring_bytes[1..], b"This is the message").is_none());
+        // A ring too small to hide the signer
+        assert!(blsag_sign(k.as_bytes(), &ring_bytes[..64], b"This is the message").is_none());
+    }
+}
@@ -0,0 +1,259 @@
+//! Ring registry
+//!
+//! Anonymous credential deployments keep a named ring per credential that grows over time. A
+//! [`Registry`] collects members into the open epoch of each ring, and freezing a ring fixes its
+//! current members as a numbered epoch that signatures can refer to by `(ring_id, epoch)`. The
+//! open epoch carries the members over, so each epoch is a superset of the previous one until
+//! old epochs are pruned.
+//!
+//! Signatures made with [`Registry::sign`] are wrapped in an [`Envelope`] naming the ring and
+//! epoch, both of which are bound into every challenge, so an envelope can not be relabelled to
+//! claim membership of another ring or epoch.
+
+use crate::prelude::*;
+
+use alloc::collections::BTreeMap;
+use alloc::string::String;
+
+#[cfg(feature = "sign")]
+use curve25519_dalek::constants;
+use curve25519_dalek::ristretto::RistrettoPoint;
+#[cfg(feature = "sign")]
+use curve25519_dalek::scalar::Scalar;
+use digest::generic_array::typenum::U64;
+use digest::Digest;
+#[cfg(feature = "sign")]
+use rand_core::{CryptoRng, RngCore};
+
+#[cfg(feature = "blsag")]
+use crate::blsag::BLSAG;
+use crate::codec;
+#[cfg(feature = "lsag")]
+use crate::lsag::LSAG;
+#[cfg(feature = "sag")]
+use crate::sag::SAG;
+use crate::traits::VerifyRef;
+#[cfg(feature = "sign")]
+use crate::traits::SignWithRng;
+
+/// Signatures over a ring of single public keys
+pub trait Members {
+    fn members(&self) -> &Vec<RistrettoPoint>;
+}
+
+#[cfg(feature = "sag")]
+impl Members for SAG {
+    fn members(&self) -> &Vec<RistrettoPoint> {
+        return &self.ring;
+    }
+}
+
+#[cfg(feature = "lsag")]
+impl Members for LSAG {
+    fn members(&self) -> &Vec<RistrettoPoint> {
+        return &self.ring;
+    }
+}
+
+#[cfg(feature = "blsag")]
+impl Members for BLSAG {
+    fn members(&self) -> &Vec<RistrettoPoint> {
+        return &self.ring;
+    }
+}
+
+/// A signature over the ring `ring_id` as frozen at `epoch`
+#[derive(Clone)]
+pub struct Envelope<S> {
+    pub ring_id: String,
+    pub epoch: u64,
+    pub signature: S,
+}
+
+struct NamedRing {
+    /// Frozen epochs by number
+    epochs: BTreeMap<u64, Vec<RistrettoPoint>>,
+    /// Members of the epoch that will be frozen next
+    open: Vec<RistrettoPoint>,
+    next_epoch: u64,
+}
+
+#[derive(Default)]
+pub struct Registry {
+    rings: BTreeMap<String, NamedRing>,
+}
+
+/// The message actually signed: the ring and epoch referred to, before `message`
+fn bind_reference(ring_id: &str, epoch: u64, message: &Vec<u8>) -> Vec<u8> {
+    let mut bytes: Vec<u8> = b"nazgul/registry".to_vec();
+    codec::write_u32(&mut bytes, ring_id.len());
+    bytes.extend_from_slice(ring_id.as_bytes());
+    bytes.extend_from_slice(&epoch.to_le_bytes());
+    bytes.extend_from_slice(message);
+    return bytes;
+}
+
+impl Registry {
+    pub fn new() -> Registry {
+        return Registry {
+            rings: BTreeMap::new(),
+        };
+    }
+
+    /// Adds `member` to the open epoch of `ring_id`, creating the ring if needed. Returns the
+    /// epoch the member will first appear in, or `None` if it is already a member.
+    pub fn insert(&mut self, ring_id: &str, member: RistrettoPoint) -> Option<u64> {
+        let ring = self.rings.entry(String::from(ring_id)).or_insert(NamedRing {
+            epochs: BTreeMap::new(),
+            open: Vec::new(),
+            next_epoch: 0,
+        });
+        if ring.open.contains(&member) {
+            return None;
+        }
+        ring.open.push(member);
+        return Some(ring.next_epoch);
+    }
+
+    /// Freezes the members of the open epoch of `ring_id`, returning the number of the new epoch
+    pub fn freeze(&mut self, ring_id: &str) -> Option<u64> {
+        let ring = self.rings.get_mut(ring_id)?;
+        let epoch = ring.next_epoch;
+        ring.epochs.insert(epoch, ring.open.clone());
+        ring.next_epoch += 1;
+        return Some(epoch);
+    }
+
+    /// The members of `ring_id` as frozen at `epoch`
+    pub fn ring(&self, ring_id: &str, epoch: u64) -> Option<&Vec<RistrettoPoint>> {
+        return self.rings.get(ring_id)?.epochs.get(&epoch);
+    }
+
+    /// The most recently frozen epoch of `ring_id`
+    pub fn latest_epoch(&self, ring_id: &str) -> Option<u64> {
+        return self.rings.get(ring_id)?.epochs.keys().next_back().copied();
+    }
+
+    /// A digest of the members of `ring_id` at `epoch`, for comparing registries without
+    /// exchanging the rings
+    pub fn fingerprint<Hash: Digest<OutputSize = U64> + Clone + Default>(
+        &self,
+        ring_id: &str,
+        epoch: u64,
+    ) -> Option<[u8; 64]> {
+        let mut h: Hash = Hash::default();
+        h.update(bind_reference(ring_id, epoch, &Vec::new()));
+        for member in self.ring(ring_id, epoch)? {
+            h.update(member.compress().as_bytes());
+        }
+        let mut fingerprint = [0u8; 64];
+        fingerprint.copy_from_slice(&h.finalize());
+        return Some(fingerprint);
+    }
+
+    /// Forgets every frozen epoch of `ring_id` older than `epoch`, after which signatures
+    /// referring to them no longer verify
+    pub fn prune(&mut self, ring_id: &str, epoch: u64) {
+        if let Some(ring) = self.rings.get_mut(ring_id) {
+            ring.epochs = ring.epochs.split_off(&epoch);
+        }
+    }
+
+    /// Signs `message` with `k` over `ring_id` as frozen at `epoch`. Returns `None` if the epoch
+    /// does not exist or the public key of `k` is not one of its members.
+    #[cfg(feature = "sign")]
+    pub fn sign<
+        Hash: Digest<OutputSize = U64> + Clone + Default,
+        CSPRNG: CryptoRng + RngCore + Default,
+        S: SignWithRng<Scalar, Vec<RistrettoPoint>>,
+    >(
+        &self,
+        ring_id: &str,
+        epoch: u64,
+        k: Scalar,
+        message: &Vec<u8>,
+    ) -> Option<Envelope<S>> {
+        let k_point: RistrettoPoint = k * constants::RISTRETTO_BASEPOINT_POINT;
+        let mut decoys: Vec<RistrettoPoint> = self.ring(ring_id, epoch)?.clone();
+        let secret_index = decoys.iter().position(|member| *member == k_point)?;
+        decoys.remove(secret_index);
+        let mut csprng = CSPRNG::default();
+        let signature = S::sign_with_rng::<Hash, CSPRNG>(
+            k,
+            decoys,
+            secret_index,
+            &bind_reference(ring_id, epoch, message),
+            &mut csprng,
+        )
+        .ok()?;
+        return Some(Envelope {
+            ring_id: String::from(ring_id),
+            epoch: epoch,
+            signature: signature,
+        });
+    }
+
+    /// Verifies that `envelope` was signed by a member of the ring and epoch it names
+    pub fn verify<Hash: Digest<OutputSize = U64> + Clone + Default, S: VerifyRef + Members>(
+        &self,
+        envelope: Envelope<S>,
+        message: &Vec<u8>,
+    ) -> bool {
+        let ring = match self.ring(&envelope.ring_id, envelope.epoch) {
+            Some(ring) => ring,
+            None => return false,
+        };
+        if envelope.signature.members() != ring {
+            return false;
+        }
+        let bound_message = bind_reference(&envelope.ring_id, envelope.epoch, message);
+        return envelope.signature.verify_ref::<Hash>(&bound_message);
+    }
+}
+
+#[cfg(test)]
+#[cfg(feature = "std")]
+#[cfg(feature = "sign")]
+#[cfg(feature = "blsag")]
+mod test {
+    extern crate rand;
+    extern crate sha2;
+
+    use rand::rngs::OsRng;
+    use sha2::Sha512;
+
+    use super::*;
+
+    #[test]
+    fn registry() {
+        let mut registry = Registry::new();
+        let k: Scalar = Scalar::random(&mut OsRng);
+        let message: Vec<u8> = b"This is the message".to_vec();
+
+        assert_eq!(registry.insert("voters", RistrettoPoint::random(&mut OsRng)), Some(0));
+        assert_eq!(registry.freeze("voters"), Some(0));
+        assert_eq!(registry.insert("voters", k * constants::RISTRETTO_BASEPOINT_POINT), Some(1));
+        assert_eq!(registry.insert("voters", k * constants::RISTRETTO_BASEPOINT_POINT), None);
+        for _ in 0..9 {
+            assert_eq!(registry.insert("voters", RistrettoPoint::random(&mut OsRng)), Some(1));
+        }
+        assert_eq!(registry.freeze("voters"), Some(1));
+        assert_eq!(registry.latest_epoch("voters"), Some(1));
+        assert_eq!(registry.ring("voters", 1).unwrap().len(), 11);
+        assert_ne!(registry.fingerprint::<Sha512>("voters", 0), registry.fingerprint::<Sha512>("voters", 1));
+
+        // The key was only added in epoch 1
+        assert!(registry.sign::<Sha512, OsRng, BLSAG>("voters", 0, k, &message).is_none());
+        let envelope = registry.sign::<Sha512, OsRng, BLSAG>("voters", 1, k, &message).unwrap();
+        assert!(registry.verify::<Sha512, BLSAG>(envelope.clone(), &message));
+
+        // Relabelling the envelope breaks it
+        let mut relabelled = envelope.clone();
+        relabelled.ring_id = String::from("delegates");
+        assert!(!registry.verify::<Sha512, BLSAG>(relabelled, &message));
+
+        registry.prune("voters", 2);
+        assert!(registry.ring("voters", 1).is_none());
+        assert!(!registry.verify::<Sha512, BLSAG>(envelope, &message));
+    }
+}
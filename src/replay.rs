@@ -0,0 +1,308 @@
+//! Counter based replay protection
+//!
+//! Embedded signers often have no reliable clock to timestamp their messages with, but can keep a
+//! monotonic counter. [`sign`] binds such a counter into every challenge and wraps the signature
+//! in a [`CounterEnvelope`]. Verifiers keep a [`ReplayWindow`] that remembers, per key image, the
+//! highest counter seen and which of the [`WINDOW`] counters below it have been seen, and accept
+//! each counter at most once. Counters may arrive out of order as long as they are within the
+//! window.
+
+use crate::prelude::*;
+
+use alloc::collections::{BTreeMap, BTreeSet};
+
+use digest::generic_array::typenum::U64;
+use digest::Digest;
+#[cfg(feature = "sign")]
+use rand_core::{CryptoRng, RngCore};
+
+#[cfg(feature = "sign")]
+use crate::error::Error;
+use crate::link::{KeyImage, LinkingKeyImage};
+use crate::traits::VerifyRef;
+#[cfg(feature = "sign")]
+use crate::traits::{Decoys, SignWithRng};
+
+/// How far below the highest counter seen a counter may be and still be accepted
+pub const WINDOW: u64 = 64;
+
+/// A signature together with the counter bound into it
+#[derive(Clone)]
+pub struct CounterEnvelope<S> {
+    pub counter: u64,
+    pub signature: S,
+}
+
+/// The message actually signed: the counter before `message`
+fn bind_counter(counter: u64, message: &Vec<u8>) -> Vec<u8> {
+    let mut bytes: Vec<u8> = b"nazgul/counter".to_vec();
+    bytes.extend_from_slice(&counter.to_le_bytes());
+    bytes.extend_from_slice(message);
+    return bytes;
+}
+
+/// Signs `message` with any linkable scheme, binding `counter` into every challenge. Takes the
+/// same arguments as the scheme's `sign` otherwise
+#[cfg(feature = "sign")]
+pub fn sign<
+    Hash: Digest<OutputSize = U64> + Clone + Default,
+    CSPRNG: CryptoRng + RngCore + Default,
+    PrivateKey,
+    Ring: Decoys,
+    S: SignWithRng<PrivateKey, Ring>,
+>(
+    k: PrivateKey,
+    ring: Ring,
+    secret_index: usize,
+    counter: u64,
+    message: &Vec<u8>,
+) -> Result<CounterEnvelope<S>, Error> {
+    let mut csprng = CSPRNG::default();
+    let signature =
+        S::sign_with_rng::<Hash, CSPRNG>(k, ring, secret_index, &bind_counter(counter, message), &mut csprng)?;
+    return Ok(CounterEnvelope {
+        counter: counter,
+        signature: signature,
+    });
+}
+
+/// The counters seen from one key image
+#[derive(Clone, Copy)]
+struct Seen {
+    highest: u64,
+    /// Bit `i` is set if `highest - i` has been seen
+    bitmap: u64,
+}
+
+/// The counters seen so far, per key image
+#[derive(Clone, Default)]
+pub struct ReplayWindow {
+    seen: BTreeMap<KeyImage, Seen>,
+}
+
+impl ReplayWindow {
+    pub fn new() -> ReplayWindow {
+        return ReplayWindow { seen: BTreeMap::new() };
+    }
+
+    /// Records `counter` for `key_image`, returning `false` if it has been seen before or is too
+    /// far below the highest counter seen to tell
+    pub fn accept(&mut self, key_image: KeyImage, counter: u64) -> bool {
+        let seen = match self.seen.get_mut(&key_image) {
+            Some(seen) => seen,
+            None => {
+                self.seen.insert(
+                    key_image,
+                    Seen {
+                        highest: counter,
+                        bitmap: 1,
+                    },
+                );
+                return true;
+            }
+        };
+        if counter > seen.highest {
+            let shift = counter - seen.highest;
+            seen.bitmap = if shift >= WINDOW { 1 } else { (seen.bitmap << shift) | 1 };
+            seen.highest = counter;
+            return true;
+        }
+        let age = seen.highest - counter;
+        if age >= WINDOW || seen.bitmap & (1 << age) != 0 {
+            return false;
+        }
+        seen.bitmap |= 1 << age;
+        return true;
+    }
+
+    /// Verifies an `envelope` made with [`sign`] and accepts its counter, so the same envelope
+    /// only ever verifies once. The counter of an envelope that does not verify is not recorded.
+    pub fn verify<Hash: Digest<OutputSize = U64> + Clone + Default, S: VerifyRef + LinkingKeyImage>(
+        &mut self,
+        envelope: CounterEnvelope<S>,
+        message: &Vec<u8>,
+    ) -> bool {
+        let bound_message = bind_counter(envelope.counter, message);
+        if !envelope.signature.verify_ref::<Hash>(&bound_message) {
+            return false;
+        }
+        return match envelope.signature.linking_key_image() {
+            Some(key_image) => self.accept(key_image, envelope.counter),
+            None => false,
+        };
+    }
+}
+
+/// A nonce issued by a [`NonceRegistry`]
+pub type Nonce = [u8; 32];
+
+/// A signature together with the nonce bound into it
+#[derive(Clone)]
+pub struct NonceEnvelope<S> {
+    pub nonce: Nonce,
+    pub signature: S,
+}
+
+/// The message actually signed: the nonce before `message`
+fn bind_nonce(nonce: &Nonce, message: &Vec<u8>) -> Vec<u8> {
+    let mut bytes: Vec<u8> = b"nazgul/nonce".to_vec();
+    bytes.extend_from_slice(nonce);
+    bytes.extend_from_slice(message);
+    return bytes;
+}
+
+/// Signs `message` with any scheme, binding `nonce`, as issued by the verifier, into every
+/// challenge. Takes the same arguments as the scheme's `sign` otherwise
+#[cfg(feature = "sign")]
+pub fn sign_with_nonce<
+    Hash: Digest<OutputSize = U64> + Clone + Default,
+    CSPRNG: CryptoRng + RngCore + Default,
+    PrivateKey,
+    Ring: Decoys,
+    S: SignWithRng<PrivateKey, Ring>,
+>(
+    k: PrivateKey,
+    ring: Ring,
+    secret_index: usize,
+    nonce: Nonce,
+    message: &Vec<u8>,
+) -> Result<NonceEnvelope<S>, Error> {
+    let mut csprng = CSPRNG::default();
+    let signature =
+        S::sign_with_rng::<Hash, CSPRNG>(k, ring, secret_index, &bind_nonce(&nonce, message), &mut csprng)?;
+    return Ok(NonceEnvelope {
+        nonce: nonce,
+        signature: signature,
+    });
+}
+
+/// The nonces issued and not yet used
+#[derive(Clone, Default)]
+pub struct NonceRegistry {
+    outstanding: BTreeSet<Nonce>,
+}
+
+impl NonceRegistry {
+    pub fn new() -> NonceRegistry {
+        return NonceRegistry {
+            outstanding: BTreeSet::new(),
+        };
+    }
+
+    /// Draws a fresh nonce from `csprng` for a signer to sign with
+    #[cfg(feature = "sign")]
+    pub fn issue<CSPRNG: CryptoRng + RngCore>(&mut self, csprng: &mut CSPRNG) -> Nonce {
+        loop {
+            let mut nonce: Nonce = [0u8; 32];
+            csprng.fill_bytes(&mut nonce);
+            if self.outstanding.insert(nonce) {
+                return nonce;
+            }
+        }
+    }
+
+    /// Forgets `nonce` unused, e.g. once the request it was issued for has expired. Returns
+    /// whether it was outstanding
+    pub fn revoke(&mut self, nonce: &Nonce) -> bool {
+        return self.outstanding.remove(nonce);
+    }
+
+    /// Number of nonces issued and not yet used or revoked
+    pub fn outstanding(&self) -> usize {
+        return self.outstanding.len();
+    }
+
+    /// Verifies an `envelope` made with [`sign_with_nonce`] and uses up its nonce, so the same
+    /// envelope only ever verifies once. Nonces not issued by this registry are rejected, and the
+    /// nonce of an envelope that does not verify stays outstanding.
+    pub fn verify<Hash: Digest<OutputSize = U64> + Clone + Default, S: VerifyRef>(
+        &mut self,
+        envelope: NonceEnvelope<S>,
+        message: &Vec<u8>,
+    ) -> bool {
+        if !self.outstanding.contains(&envelope.nonce) {
+            return false;
+        }
+        if !envelope.signature.verify_ref::<Hash>(&bind_nonce(&envelope.nonce, message)) {
+            return false;
+        }
+        return self.outstanding.remove(&envelope.nonce);
+    }
+}
+
+#[cfg(test)]
+#[cfg(feature = "std")]
+#[cfg(feature = "sign")]
+#[cfg(feature = "blsag")]
+mod test {
+    extern crate rand;
+    extern crate sha2;
+
+    use curve25519_dalek::ristretto::RistrettoPoint;
+    use curve25519_dalek::scalar::Scalar;
+    use rand::rngs::OsRng;
+    use sha2::Sha512;
+
+    use super::*;
+    use crate::blsag::BLSAG;
+
+    #[test]
+    fn replay() {
+        let k: Scalar = Scalar::random(&mut OsRng);
+        let ring: Vec<RistrettoPoint> = (0..10).map(|_| RistrettoPoint::random(&mut OsRng)).collect();
+        let message: Vec<u8> = b"Open the door".to_vec();
+        let mut window = ReplayWindow::new();
+
+        let first: CounterEnvelope<BLSAG> =
+            super::sign::<Sha512, OsRng, _, _, _>(k, ring.clone(), 1, 7, &message).unwrap();
+        let second: CounterEnvelope<BLSAG> = super::sign::<Sha512, OsRng, _, _, _>(k, ring, 1, 8, &message).unwrap();
+        // Out of order delivery is fine, replays are not
+        assert!(window.verify::<Sha512, _>(second.clone(), &message));
+        assert!(window.verify::<Sha512, _>(first.clone(), &message));
+        assert!(!window.verify::<Sha512, _>(first.clone(), &message));
+        assert!(!window.verify::<Sha512, _>(second, &message));
+
+        // The counter is bound into the signature
+        let mut bumped = first;
+        bumped.counter = 9;
+        assert!(!window.verify::<Sha512, _>(bumped, &message));
+
+        let key_image = [0u8; 32];
+        assert!(window.accept(key_image, 100));
+        assert!(window.accept(key_image, 100 - WINDOW + 1));
+        assert!(!window.accept(key_image, 100 - WINDOW));
+    }
+
+    #[test]
+    #[cfg(feature = "sag")]
+    fn nonce_registry() {
+        use crate::sag::SAG;
+
+        let k: Scalar = Scalar::random(&mut OsRng);
+        let ring: Vec<RistrettoPoint> = (0..10).map(|_| RistrettoPoint::random(&mut OsRng)).collect();
+        let message: Vec<u8> = b"Open the door".to_vec();
+        let mut registry = NonceRegistry::new();
+
+        let nonce = registry.issue(&mut OsRng);
+        let envelope: NonceEnvelope<SAG> =
+            sign_with_nonce::<Sha512, OsRng, _, _, _>(k, ring.clone(), 1, nonce, &message).unwrap();
+        assert!(!registry.verify::<Sha512, _>(envelope.clone(), &b"Close the door".to_vec()));
+        assert_eq!(registry.outstanding(), 1);
+        assert!(registry.verify::<Sha512, _>(envelope.clone(), &message));
+        assert!(!registry.verify::<Sha512, _>(envelope.clone(), &message));
+        assert_eq!(registry.outstanding(), 0);
+
+        // The nonce is bound into the signature
+        let other = registry.issue(&mut OsRng);
+        let mut relabelled = envelope;
+        relabelled.nonce = other;
+        assert!(!registry.verify::<Sha512, _>(relabelled, &message));
+        assert!(registry.revoke(&other));
+        assert!(!registry.revoke(&other));
+
+        // Nonces the registry did not issue are rejected
+        let forged: NonceEnvelope<SAG> =
+            sign_with_nonce::<Sha512, OsRng, _, _, _>(k, ring, 1, [7u8; 32], &message).unwrap();
+        assert!(!registry.verify::<Sha512, _>(forged, &message));
+    }
+}
@@ -0,0 +1,139 @@
+//! Validated rings
+//!
+//! `sign` takes the decoys as a plain `Vec`, and nothing stops an application from passing one
+//! that is empty, absurdly large or contains the same member twice, all of which weaken or break
+//! the anonymity the signature is supposed to provide. A [`Ring`] starts out [`Unvalidated`] and
+//! can only become [`Validated`] through [`Ring::validate`], and [`sign`] only accepts validated
+//! rings, so code signing through it can not skip the checks.
+
+use crate::prelude::*;
+
+use core::marker::PhantomData;
+
+#[cfg(feature = "sign")]
+use digest::generic_array::typenum::U64;
+#[cfg(feature = "sign")]
+use digest::Digest;
+#[cfg(feature = "sign")]
+use rand_core::{CryptoRng, RngCore};
+
+#[cfg(feature = "sign")]
+use crate::error::Error;
+#[cfg(feature = "sign")]
+use crate::traits::SignWithRng;
+
+/// State of a ring that has not been checked
+pub struct Unvalidated;
+
+/// State of a ring known to be non-empty, not too large and free of duplicates
+pub struct Validated;
+
+/// The decoys of a ring signature, i.e. a ring without the signer, of members `T`
+pub struct Ring<T, State = Unvalidated> {
+    members: Vec<T>,
+    state: PhantomData<State>,
+}
+
+impl<T, State> Ring<T, State> {
+    pub fn members(&self) -> &Vec<T> {
+        return &self.members;
+    }
+
+    pub fn len(&self) -> usize {
+        return self.members.len();
+    }
+
+    pub fn is_empty(&self) -> bool {
+        return self.members.is_empty();
+    }
+}
+
+impl<T: PartialEq> Ring<T, Unvalidated> {
+    pub fn new(members: Vec<T>) -> Ring<T, Unvalidated> {
+        return Ring {
+            members: members,
+            state: PhantomData,
+        };
+    }
+
+    /// Checks that there is at least one decoy, that the ring including the signer has at most
+    /// `max_size` members and that no decoy appears twice. Returns the ring unchanged if not.
+    pub fn validate(self, max_size: usize) -> Result<Ring<T, Validated>, Ring<T, Unvalidated>> {
+        if self.members.is_empty() || self.members.len() >= max_size {
+            return Err(self);
+        }
+        for i in 0..self.members.len() {
+            for j in 0..i {
+                if self.members[i] == self.members[j] {
+                    return Err(self);
+                }
+            }
+        }
+        return Ok(Ring {
+            members: self.members,
+            state: PhantomData,
+        });
+    }
+}
+
+impl<T> Ring<T, Validated> {
+    pub fn into_members(self) -> Vec<T> {
+        return self.members;
+    }
+}
+
+/// Signs `message` with any scheme whose ring is a `Vec` of members, taking the same arguments as
+/// its `sign` except that the decoys must be a validated ring
+#[cfg(feature = "sign")]
+pub fn sign<
+    Hash: Digest<OutputSize = U64> + Clone + Default,
+    CSPRNG: CryptoRng + RngCore + Default,
+    PrivateKey,
+    T,
+    S: SignWithRng<PrivateKey, Vec<T>>,
+>(
+    k: PrivateKey,
+    ring: Ring<T, Validated>,
+    secret_index: usize,
+    message: &Vec<u8>,
+) -> Result<S, Error> {
+    let mut csprng = CSPRNG::default();
+    return S::sign_with_rng::<Hash, CSPRNG>(k, ring.into_members(), secret_index, message, &mut csprng);
+}
+
+#[cfg(test)]
+#[cfg(feature = "std")]
+#[cfg(feature = "sign")]
+#[cfg(feature = "blsag")]
+mod test {
+    extern crate rand;
+    extern crate sha2;
+
+    use curve25519_dalek::ristretto::RistrettoPoint;
+    use curve25519_dalek::scalar::Scalar;
+    use rand::rngs::OsRng;
+    use sha2::Sha512;
+
+    use super::*;
+    use crate::blsag::BLSAG;
+    use crate::traits::VerifyRef;
+
+    #[test]
+    fn ring() {
+        let decoys: Vec<RistrettoPoint> = (0..10).map(|_| RistrettoPoint::random(&mut OsRng)).collect();
+
+        assert!(Ring::<RistrettoPoint>::new(Vec::new()).validate(16).is_err());
+        // Ten decoys and the signer do not fit in a ring of at most ten members
+        assert!(Ring::new(decoys.clone()).validate(10).is_err());
+        let mut duplicated = decoys.clone();
+        duplicated.push(decoys[0]);
+        let rejected = Ring::new(duplicated).validate(16).err().unwrap();
+        assert_eq!(rejected.len(), 11);
+
+        let ring = Ring::new(decoys).validate(16).ok().unwrap();
+        let k: Scalar = Scalar::random(&mut OsRng);
+        let message: Vec<u8> = b"This is the message".to_vec();
+        let signature: BLSAG = super::sign::<Sha512, OsRng, _, _, _>(k, ring, 1, &message).unwrap();
+        assert!(signature.verify_ref::<Sha512>(&message));
+    }
+}
@@ -0,0 +1,179 @@
+//! Rings written in place by the host
+//!
+//! Passing a large ring to WebAssembly through argument marshaling copies and converts every
+//! member, which for rings of 100k members costs more than the signature itself. Instead the
+//! host asks for a [`RingBuffer`] with [`alloc_ring`], writes the compressed members straight into
+//! linear memory at [`RingBuffer::as_mut_ptr`], and passes the buffer back to [`sign_blsag`] or
+//! [`verify_blsag`], which read the members where they were written.
+//!
+//! The layout is stable: member `i` is the 32 byte compressed Ristretto encoding at bytes
+//! `32 * i..32 * i + 32`, and nothing else is in the buffer. Bindings only need to wrap these
+//! functions, e.g. with `wasm_bindgen`; the crate itself depends on no binding generator.
+
+use crate::prelude::*;
+
+use curve25519_dalek::constants;
+use curve25519_dalek::ristretto::{CompressedRistretto, RistrettoPoint};
+use curve25519_dalek::scalar::Scalar;
+use digest::generic_array::typenum::U64;
+use digest::Digest;
+#[cfg(feature = "sign")]
+use rand_core::{CryptoRng, RngCore};
+
+use crate::blsag::{compute_challenge_prefix, compute_challenge_step};
+#[cfg(feature = "sign")]
+use crate::blsag::BLSAG;
+use crate::error::Error;
+use crate::hash_to_point::{DefaultHashToPoint, HashToPoint};
+use crate::scalars::{Challenge, Response};
+#[cfg(feature = "sign")]
+use crate::traits::{check_secret_index, SignWithRng};
+use crate::ops;
+
+/// The compressed members of a ring, laid out one after another
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RingBuffer {
+    bytes: Vec<u8>,
+}
+
+/// A zeroed buffer for `n` members
+pub fn alloc_ring(n: usize) -> RingBuffer {
+    return RingBuffer {
+        bytes: vec![0u8; 32 * n],
+    };
+}
+
+impl RingBuffer {
+    /// The number of members
+    pub fn len(&self) -> usize {
+        return self.bytes.len() / 32;
+    }
+
+    pub fn is_empty(&self) -> bool {
+        return self.bytes.is_empty();
+    }
+
+    /// Where the host writes the members, valid until the buffer is dropped
+    pub fn as_mut_ptr(&mut self) -> *mut u8 {
+        return self.bytes.as_mut_ptr();
+    }
+
+    pub fn as_mut_bytes(&mut self) -> &mut [u8] {
+        return &mut self.bytes;
+    }
+
+    /// Member `index`, or [`Error::InvalidPointEncoding`] if it is not a canonical encoding
+    ///
+    /// Panics if `index` is not below [`RingBuffer::len`].
+    pub fn member(&self, index: usize) -> Result<RistrettoPoint, Error> {
+        let mut encoding = [0u8; 32];
+        encoding.copy_from_slice(&self.bytes[32 * index..32 * index + 32]);
+        return CompressedRistretto(encoding).decompress().ok_or(Error::InvalidPointEncoding);
+    }
+
+    /// All members, or [`Error::InvalidPointEncoding`] if one is not a canonical encoding
+    pub fn members(&self) -> Result<Vec<RistrettoPoint>, Error> {
+        return (0..self.len()).map(|index| self.member(index)).collect();
+    }
+}
+
+/// Signs `message` as [`SignWithRng::sign_with_rng`] with the decoys in `ring`
+///
+/// Returns [`Error::InvalidPointEncoding`] if a member is not a canonical encoding and
+/// [`Error::SecretIndexOutOfRange`] if `secret_index` is past the end of the ring.
+#[cfg(feature = "sign")]
+pub fn sign_blsag<Hash: Digest<OutputSize = U64> + Clone + Default, CSPRNG: CryptoRng + RngCore + Default>(
+    k: Scalar,
+    ring: &RingBuffer,
+    secret_index: usize,
+    message: &Vec<u8>,
+) -> Result<BLSAG, Error> {
+    check_secret_index(ring.len(), secret_index)?;
+    let mut csprng = CSPRNG::default();
+    return BLSAG::sign_with_rng::<Hash, CSPRNG>(k, ring.members()?, secret_index, message, &mut csprng);
+}
+
+/// Verifies the bLSAG made of `challenge`, `responses` and `key_image` over the members in
+/// `ring`, decoding each as the challenge chain reaches it
+///
+/// Returns [`Error::InvalidPointEncoding`] if a member is not a canonical encoding and
+/// [`Error::ResponseCountMismatch`] if `ring` does not have one member per response.
+pub fn verify_blsag<Hash: Digest<OutputSize = U64> + Clone + Default>(
+    ring: &RingBuffer,
+    challenge: &Challenge,
+    responses: &[Response],
+    key_image: &RistrettoPoint,
+    message: &Vec<u8>,
+) -> Result<bool, Error> {
+    if ring.len() != responses.len() {
+        return Err(Error::ResponseCountMismatch);
+    }
+    let message_hash: Hash = compute_challenge_prefix::<Hash>(message);
+    let mut reconstructed_c: Scalar = challenge.0;
+    for (index, response) in responses.iter().enumerate() {
+        let member: RistrettoPoint = ring.member(index)?;
+        reconstructed_c = compute_challenge_step::<Hash>(
+            message_hash.clone(),
+            &ops::multiscalar_mul(
+                &[response.0, reconstructed_c],
+                &[constants::RISTRETTO_BASEPOINT_POINT, member],
+            ),
+            &ops::multiscalar_mul(
+                &[response.0, reconstructed_c],
+                &[DefaultHashToPoint::hash_to_point::<Hash>(&member), *key_image],
+            ),
+        );
+    }
+    return Ok(!responses.is_empty() && reconstructed_c == challenge.0);
+}
+
+#[cfg(test)]
+#[cfg(feature = "std")]
+mod test {
+    extern crate rand;
+    extern crate sha2;
+
+    use rand::rngs::OsRng;
+    use sha2::Sha512;
+
+    use super::*;
+    use crate::traits::VerifyRef;
+
+    #[test]
+    fn ring_buffer() {
+        let k: Scalar = Scalar::random(&mut OsRng);
+        let decoys: Vec<RistrettoPoint> = (0..10).map(|_| RistrettoPoint::random(&mut OsRng)).collect();
+        let message: Vec<u8> = b"This is the message".to_vec();
+
+        // The host writes the members in place
+        let mut ring = alloc_ring(decoys.len());
+        for (chunk, member) in ring.as_mut_bytes().chunks_mut(32).zip(decoys.iter()) {
+            chunk.copy_from_slice(member.compress().as_bytes());
+        }
+        let signature = sign_blsag::<Sha512, OsRng>(k, &ring, 1, &message).unwrap();
+        assert!(signature.verify_ref::<Sha512>(&message));
+        assert_eq!(
+            sign_blsag::<Sha512, OsRng>(k, &ring, 11, &message).err(),
+            Some(Error::SecretIndexOutOfRange { index: 11, ring_size: 11 })
+        );
+
+        let mut full = alloc_ring(signature.ring.len());
+        for (chunk, member) in full.as_mut_bytes().chunks_mut(32).zip(signature.ring.iter()) {
+            chunk.copy_from_slice(member.compress().as_bytes());
+        }
+        let verify = |ring: &RingBuffer, message: &Vec<u8>| {
+            verify_blsag::<Sha512>(
+                ring,
+                &signature.challenge,
+                &signature.responses,
+                &signature.key_image,
+                message,
+            )
+        };
+        assert_eq!(verify(&full, &message), Ok(true));
+        assert_eq!(verify(&full, &b"Another message".to_vec()), Ok(false));
+        assert_eq!(verify(&ring, &message), Err(Error::ResponseCountMismatch));
+        full.as_mut_bytes()[..32].copy_from_slice(&[0xff; 32]);
+        assert_eq!(verify(&full, &message), Err(Error::InvalidPointEncoding));
+    }
+}
@@ -0,0 +1,199 @@
+//! Rings streamed from an asynchronous source
+//!
+//! A verifier that fetches ring members from a remote index (a DHT, an RPC server) would
+//! otherwise wait for the whole ring before doing any curve work. The challenges of bLSAG take
+//! the members one at a time and do not hash the ring up front, so [`verify_from_stream`] can
+//! compute each step of the challenge chain as soon as its member arrives, while the source is
+//! fetching the next one.
+//!
+//! Sources implement [`RingStream`], which has the shape of `futures::Stream` with
+//! `CompressedRistretto` items, so wrapping a stream of any async runtime takes a one line
+//! `poll_next`. The crate itself does not depend on an async runtime.
+
+use crate::prelude::*;
+
+use core::future::Future;
+use core::pin::Pin;
+use core::task::{Context, Poll};
+
+use curve25519_dalek::constants;
+use curve25519_dalek::ristretto::{CompressedRistretto, RistrettoPoint};
+use curve25519_dalek::scalar::Scalar;
+use digest::generic_array::typenum::U64;
+use digest::Digest;
+
+use crate::blsag::{compute_challenge_prefix, compute_challenge_step, BLSAG};
+use crate::error::Error;
+use crate::hash_to_point::{DefaultHashToPoint, HashToPoint};
+use crate::scalars::{Challenge, Response};
+use crate::ops;
+
+/// An asynchronous source of ring members, in ring order
+pub trait RingStream {
+    /// As `futures::Stream::poll_next`
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<CompressedRistretto>>;
+}
+
+/// A bLSAG without its ring
+#[derive(Clone)]
+pub struct BlsagHeader {
+    pub challenge: Challenge,
+    pub responses: Vec<Response>,
+    pub key_image: RistrettoPoint,
+}
+
+impl BlsagHeader {
+    pub fn from_signature(signature: &BLSAG) -> BlsagHeader {
+        return BlsagHeader {
+            challenge: signature.challenge,
+            responses: signature.responses.clone(),
+            key_image: signature.key_image,
+        };
+    }
+}
+
+/// Resolves to the next member of the ring
+struct Next<'a, R> {
+    ring: &'a mut R,
+}
+
+impl<'a, R: RingStream + Unpin> Future for Next<'a, R> {
+    type Output = Option<CompressedRistretto>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<CompressedRistretto>> {
+        return Pin::new(&mut *self.ring).poll_next(cx);
+    }
+}
+
+/// Verifies the bLSAG `header` over the members yielded by `ring`, as [`crate::traits::VerifyRef`]
+/// would the signature with that ring
+///
+/// Returns [`Error::InvalidPointEncoding`] if a member is not a canonical encoding and
+/// [`Error::ResponseCountMismatch`] if the stream does not yield one member per response.
+pub async fn verify_from_stream<Hash: Digest<OutputSize = U64> + Clone + Default, R: RingStream + Unpin>(
+    header: &BlsagHeader,
+    mut ring: R,
+    message: &Vec<u8>,
+) -> Result<bool, Error> {
+    let message_hash: Hash = compute_challenge_prefix::<Hash>(message);
+    let mut reconstructed_c: Scalar = header.challenge.0;
+    for response in header.responses.iter() {
+        let member: RistrettoPoint = (Next { ring: &mut ring })
+            .await
+            .ok_or(Error::ResponseCountMismatch)?
+            .decompress()
+            .ok_or(Error::InvalidPointEncoding)?;
+        reconstructed_c = compute_challenge_step::<Hash>(
+            message_hash.clone(),
+            &ops::multiscalar_mul(
+                &[response.0, reconstructed_c],
+                &[constants::RISTRETTO_BASEPOINT_POINT, member],
+            ),
+            &ops::multiscalar_mul(
+                &[response.0, reconstructed_c],
+                &[DefaultHashToPoint::hash_to_point::<Hash>(&member), header.key_image],
+            ),
+        );
+    }
+    if (Next { ring: &mut ring }).await.is_some() {
+        return Err(Error::ResponseCountMismatch);
+    }
+    return Ok(!header.responses.is_empty() && reconstructed_c == header.challenge.0);
+}
+
+#[cfg(test)]
+#[cfg(feature = "std")]
+mod test {
+    extern crate rand;
+    extern crate sha2;
+
+    use std::boxed::Box;
+    use std::sync::Arc;
+    use std::task::{Wake, Waker};
+
+    use rand::rngs::OsRng;
+    use sha2::Sha512;
+
+    use super::*;
+    use crate::traits::SignWithRng;
+
+    /// Yields its members one poll late each, as a remote source would
+    struct Remote {
+        members: Vec<CompressedRistretto>,
+        ready: bool,
+    }
+
+    impl RingStream for Remote {
+        fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<CompressedRistretto>> {
+            if !self.ready {
+                self.ready = true;
+                cx.waker().wake_by_ref();
+                return Poll::Pending;
+            }
+            self.ready = false;
+            if self.members.is_empty() {
+                return Poll::Ready(None);
+            }
+            return Poll::Ready(Some(self.members.remove(0)));
+        }
+    }
+
+    struct Noop;
+
+    impl Wake for Noop {
+        fn wake(self: Arc<Self>) {}
+    }
+
+    fn block_on<F: Future>(future: F) -> F::Output {
+        let waker = Waker::from(Arc::new(Noop));
+        let mut cx = Context::from_waker(&waker);
+        let mut future = Box::pin(future);
+        loop {
+            if let Poll::Ready(output) = future.as_mut().poll(&mut cx) {
+                return output;
+            }
+        }
+    }
+
+    fn remote(ring: &[RistrettoPoint]) -> Remote {
+        return Remote {
+            members: ring.iter().map(|member| member.compress()).collect(),
+            ready: false,
+        };
+    }
+
+    #[test]
+    fn verify_from_stream() {
+        let k: Scalar = Scalar::random(&mut OsRng);
+        let ring: Vec<RistrettoPoint> = (0..3).map(|_| RistrettoPoint::random(&mut OsRng)).collect();
+        let message: Vec<u8> = b"This is the message".to_vec();
+        let signature = BLSAG::sign_with_rng_unchecked::<Sha512, _>(k, ring, 2, &message, &mut OsRng).unwrap();
+        let header = BlsagHeader::from_signature(&signature);
+
+        let verified = block_on(super::verify_from_stream::<Sha512, _>(
+            &header,
+            remote(&signature.ring),
+            &message,
+        ));
+        assert_eq!(verified, Ok(true));
+        let another: Vec<u8> = b"Another message".to_vec();
+        let verified = block_on(super::verify_from_stream::<Sha512, _>(
+            &header,
+            remote(&signature.ring),
+            &another,
+        ));
+        assert_eq!(verified, Ok(false));
+
+        let short = remote(&signature.ring[1..]);
+        let verified = block_on(super::verify_from_stream::<Sha512, _>(&header, short, &message));
+        assert_eq!(verified, Err(Error::ResponseCountMismatch));
+        let mut long = signature.ring.clone();
+        long.push(RistrettoPoint::random(&mut OsRng));
+        let verified = block_on(super::verify_from_stream::<Sha512, _>(&header, remote(&long), &message));
+        assert_eq!(verified, Err(Error::ResponseCountMismatch));
+        let mut corrupt = remote(&signature.ring);
+        corrupt.members[0] = CompressedRistretto([0xff; 32]);
+        let verified = block_on(super::verify_from_stream::<Sha512, _>(&header, corrupt, &message));
+        assert_eq!(verified, Err(Error::InvalidPointEncoding));
+    }
+}
@@ -0,0 +1,184 @@
+//! Ring pagination
+//!
+//! Block explorers list the ring members of a signature a page at a time. Decoding the signature
+//! for every page decompresses, and copies, the whole ring. A [`RingView`] borrows the encoding
+//! instead and hands out the encodings of ring members as they are: each member is its keys as
+//! compressed Ristretto points, in order, and for DLSAG and MDLSAG every key is followed by the
+//! public key of the other end of the channel and the random bitstring, see [`crate::codec`].
+//!
+//! Only lengths are read when making a view, as in [`crate::header`], so a member may still fail
+//! to decompress. Wallets verifying the signature decode it anyway.
+
+use crate::error::Error;
+use crate::header::{read_u32, SchemeId};
+
+/// The ring members of an encoded signature, see the module documentation
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct RingView<'a> {
+    members: &'a [u8],
+    member_len: usize,
+}
+
+impl<'a> RingView<'a> {
+    /// The ring of `body`, the canonical encoding of a signature of `scheme`
+    ///
+    /// Returns [`Error::Truncated`] or [`Error::TrailingBytes`] if `body` is not as long as the
+    /// lengths it starts with say.
+    pub fn new(scheme: SchemeId, body: &'a [u8]) -> Result<RingView<'a>, Error> {
+        let ring_size = read_u32(body, 32).ok_or(Error::Truncated)?;
+        let (columns, offset) = match scheme.single_key() {
+            true => (1, 36),
+            false => (read_u32(body, 36).ok_or(Error::Truncated)?, 40),
+        };
+        let len = scheme.encoded_len(ring_size, columns).ok_or(Error::Truncated)?;
+        // A bLSAG may end in the digest of its ring
+        let digest = if scheme == SchemeId::BLSAG && body.len() == len + 64 {
+            64
+        } else {
+            0
+        };
+        if body.len() < len {
+            return Err(Error::Truncated);
+        }
+        if body.len() > len + digest {
+            return Err(Error::TrailingBytes);
+        }
+        // Every encoding has the responses between the lengths and the ring
+        let responses = match scheme {
+            SchemeId::CLSAG => ring_size,
+            _ => ring_size * columns,
+        };
+        let key_len = match scheme {
+            SchemeId::DLSAG | SchemeId::MDLSAG => 96,
+            _ => 32,
+        };
+        let start = offset + 32 * responses;
+        return Ok(RingView {
+            members: &body[start..start + ring_size * columns * key_len],
+            member_len: columns * key_len,
+        });
+    }
+
+    /// The ring of `bytes`, a scheme tagged encoding, see [`crate::signature`]
+    pub fn parse(bytes: &'a [u8]) -> Result<RingView<'a>, Error> {
+        let (tag, body) = bytes.split_first().ok_or(Error::Truncated)?;
+        let scheme = SchemeId::from_u8(*tag).ok_or(Error::UnknownScheme { tag: *tag })?;
+        return RingView::new(scheme, body);
+    }
+
+    /// The number of ring members
+    pub fn len(&self) -> usize {
+        return self.members.len().checked_div(self.member_len).unwrap_or(0);
+    }
+
+    /// Whether there are no ring members, as on a page after the last
+    pub fn is_empty(&self) -> bool {
+        return self.members.is_empty();
+    }
+
+    /// The encoding of the member at `index`, if there is one
+    pub fn member(&self, index: usize) -> Option<&'a [u8]> {
+        let start = index.checked_mul(self.member_len)?;
+        return self.members.get(start..start.checked_add(self.member_len)?);
+    }
+
+    /// The number of pages of `per_page` members, the last possibly shorter
+    pub fn pages(&self, per_page: usize) -> usize {
+        if per_page == 0 {
+            return 0;
+        }
+        return self.len().div_ceil(per_page);
+    }
+
+    /// The members on page `page`, counting from 0, of `per_page` members each. Pages after the
+    /// last are empty
+    pub fn page(&self, page: usize, per_page: usize) -> RingView<'a> {
+        let len = self.len();
+        let start = page.saturating_mul(per_page).min(len);
+        let end = start.saturating_add(per_page).min(len);
+        return RingView {
+            members: &self.members[start * self.member_len..end * self.member_len],
+            member_len: self.member_len,
+        };
+    }
+
+    /// The encodings of the members, in ring order
+    pub fn iter_compressed(&self) -> core::slice::ChunksExact<'a, u8> {
+        return self.members.chunks_exact(self.member_len.max(1));
+    }
+}
+
+#[cfg(test)]
+#[cfg(feature = "std")]
+#[cfg(feature = "sign")]
+#[cfg(all(feature = "blsag", feature = "mlsag"))]
+mod test {
+    extern crate rand;
+    extern crate sha2;
+
+    use curve25519_dalek::ristretto::RistrettoPoint;
+    use curve25519_dalek::scalar::Scalar;
+    use rand::rngs::OsRng;
+    use sha2::Sha512;
+
+    use super::*;
+    use crate::blsag::BLSAG;
+    use crate::codec::Codec;
+    use crate::mlsag::MLSAG;
+    use crate::prelude::*;
+    use crate::signature::SignatureBytes;
+    use crate::traits::SignWithRng;
+
+    #[test]
+    fn ring_view() {
+        let message: Vec<u8> = b"This is the message".to_vec();
+        let k: Scalar = Scalar::random(&mut OsRng);
+        let decoys: Vec<RistrettoPoint> = (0..6).map(|_| RistrettoPoint::random(&mut OsRng)).collect();
+        let signature = BLSAG::sign_with_rng_unchecked::<Sha512, _>(k, decoys, 2, &message, &mut OsRng).unwrap();
+        let bytes = signature.to_tagged_bytes();
+        let view = RingView::parse(&bytes).unwrap();
+        assert_eq!(view.len(), 7);
+        let members: Vec<&[u8]> = view.iter_compressed().collect();
+        for (member, point) in members.iter().zip(signature.ring.iter()) {
+            assert_eq!(*member, point.compress().as_bytes());
+        }
+        assert_eq!(view.member(6), Some(members[6]));
+        assert_eq!(view.member(7), None);
+
+        // Pages of three: 3, 3 and 1 members, then nothing
+        assert_eq!(view.pages(3), 3);
+        assert_eq!(
+            view.page(1, 3).iter_compressed().collect::<Vec<&[u8]>>(),
+            members[3..6].to_vec()
+        );
+        assert_eq!(view.page(2, 3).len(), 1);
+        assert!(view.page(3, 3).is_empty());
+        assert_eq!(view.pages(0), 0);
+
+        // The ring digest changes the length, not the ring
+        let with_digest = signature.attach_ring_digest::<Sha512>().to_bytes();
+        assert_eq!(RingView::new(SchemeId::BLSAG, &with_digest).unwrap(), view);
+        assert_eq!(
+            RingView::new(SchemeId::BLSAG, &bytes[1..bytes.len() - 1]),
+            Err(Error::Truncated)
+        );
+        let mut longer = bytes[1..].to_vec();
+        longer.push(0);
+        assert_eq!(RingView::new(SchemeId::BLSAG, &longer), Err(Error::TrailingBytes));
+
+        // Members of multilayer rings are rows of keys
+        let ks: Vec<Scalar> = (0..2).map(|_| Scalar::random(&mut OsRng)).collect();
+        let decoys: Vec<Vec<RistrettoPoint>> = (0..2)
+            .map(|_| (0..2).map(|_| RistrettoPoint::random(&mut OsRng)).collect())
+            .collect();
+        let signature = MLSAG::sign_with_rng_unchecked::<Sha512, _>(ks, decoys, 0, &message, &mut OsRng).unwrap();
+        let bytes = signature.to_bytes();
+        let view = RingView::new(SchemeId::MLSAG, &bytes).unwrap();
+        assert_eq!(view.len(), 3);
+        let row: Vec<u8> = signature.ring[1]
+            .iter()
+            .flat_map(|key| key.compress().to_bytes())
+            .collect();
+        assert_eq!(view.member(1), Some(&row[..]));
+    }
+}
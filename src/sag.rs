@@ -1,111 +1,439 @@
 use crate::prelude::*;
+#[cfg(feature = "allocator-api")]
+use crate::arena::{Allocator, VerifyIn};
 
+use core::ops::Range;
+
+#[cfg(feature = "sign")]
 use curve25519_dalek::constants;
 use curve25519_dalek::ristretto::RistrettoPoint;
 use curve25519_dalek::scalar::Scalar;
-use curve25519_dalek::traits::MultiscalarMul;
 use digest::Digest;
 use digest::generic_array::typenum::U64;
+#[cfg(feature = "sign")]
 use rand_core::{CryptoRng, RngCore};
 
-use crate::traits::{Sign, Verify};
+use crate::error::Error;
+use crate::generator::{Basepoint, Generator, VerifyWithGenerator};
+#[cfg(feature = "sign")]
+use crate::generator::SignWithGenerator;
+use crate::scalars::{Challenge, Response};
+#[cfg(feature = "sign")]
+use crate::scalars;
+use crate::traits::{check_responses, ChallengeChain, TryVerify, VerifyRef, Weight};
+#[cfg(feature = "sign")]
+use crate::traits::{
+    check_secret_index, take_signer, Scheme, SignWithFullRing, SignWithHiddenIndex, SignWithRng, TrySign,
+};
+use crate::weight;
+use crate::codec::{self, Codec, Reader};
+use crate::ops;
 
 /// Spontaneous Anonymous Group (SAG) signatures
 /// > This non-linkable ring signature that allows spontaneous groups, provided here for conceptual clarity
 ///
 /// Please read tests at the bottom of the source code for this module for examples on how to use
 /// it
+#[derive(Clone)]
 pub struct SAG {
-    pub challenge: Scalar,
-    pub responses: Vec<Scalar>,
+    pub challenge: Challenge,
+    pub responses: Vec<Response>,
     pub ring: Vec<RistrettoPoint>,
 }
 
-impl Sign<Scalar, Vec<RistrettoPoint>> for SAG {
+/// Hash state shared by all challenges `H_n(R, m, ....)`, where `R` is the complete ring
+///
+/// # Advanced
+///
+/// This and [`compute_challenge_step`] are the transcript math used by `sign` and `verify`,
+/// exposed for protocols that compute SAG challenges over data produced elsewhere. Most users
+/// should only ever need `sign` and `verify`.
+pub fn compute_challenge_prefix<Hash: Digest<OutputSize = U64> + Clone>(
+    ring: &[RistrettoPoint],
+    message: &[u8],
+) -> Hash {
+    let mut group_and_message_hash = Hash::new();
+    for k_point in ring {
+        group_and_message_hash.update(k_point.compress().as_bytes());
+    }
+    group_and_message_hash.update(message);
+    return group_and_message_hash;
+}
+
+/// The challenge `c_(i+1) = H_n(R, m, point)` where `point = r_i G + c_i K_i`, given the
+/// `hash_state` from [`compute_challenge_prefix`]
+///
+/// # Advanced
+///
+/// See [`compute_challenge_prefix`]
+pub fn compute_challenge_step<Hash: Digest<OutputSize = U64>>(
+    mut hash_state: Hash,
+    point: &RistrettoPoint,
+) -> Scalar {
+    hash_state.update(point.compress().as_bytes());
+    return Scalar::from_hash(hash_state);
+}
+
+#[cfg(feature = "sign")]
+impl SignWithRng<Scalar, Vec<RistrettoPoint>> for SAG {
     /// To sign you need `k` your private key, and `ring` which is the public keys of everyone
     /// except you. You are signing the `message`
-    fn sign<Hash: Digest<OutputSize=U64> + Clone, CSPRNG: CryptoRng + RngCore + Default>(
+    fn sign_with_rng_unchecked<Hash: Digest<OutputSize = U64> + Clone + Default, CSPRNG: CryptoRng + RngCore>(
+        k: Scalar,
+        ring: Vec<RistrettoPoint>,
+        secret_index: usize,
+        message: &Vec<u8>,
+        csprng: &mut CSPRNG,
+    ) -> Result<SAG, Error> {
+        return SAG::sign_with_generator_and_rng::<Hash, CSPRNG, Basepoint>(k, ring, secret_index, message, csprng);
+    }
+}
+
+#[cfg(feature = "sign")]
+impl SignWithGenerator<Scalar, Vec<RistrettoPoint>> for SAG {
+    fn sign_with_generator_and_rng<
+        Hash: Digest<OutputSize = U64> + Clone + Default,
+        CSPRNG: CryptoRng + RngCore,
+        G: Generator,
+    >(
         k: Scalar,
         mut ring: Vec<RistrettoPoint>,
         secret_index: usize,
         message: &Vec<u8>,
-    ) -> SAG {
-        let mut csprng: CSPRNG = CSPRNG::default();
-        let k_point: RistrettoPoint = k * constants::RISTRETTO_BASEPOINT_POINT;
-        let n = ring.len() + 1;
+        csprng: &mut CSPRNG,
+    ) -> Result<SAG, Error> {
+        check_secret_index(ring.len(), secret_index)?;
+        let g: RistrettoPoint = G::generator();
+        let k_point: RistrettoPoint = ops::mul_base(&k, &g);
         ring.insert(secret_index, k_point);
-        let a: Scalar = Scalar::random(&mut csprng);
-        let mut rs: Vec<Scalar> = (0..n).map(|_| Scalar::random(&mut csprng)).collect();
-        let mut cs: Vec<Scalar> = (0..n).map(|_| Scalar::ZERO).collect();
-        let mut group_and_message_hash = Hash::new();
-        for k_point in &ring {
-            group_and_message_hash.update(k_point.compress().as_bytes());
-        }
-        group_and_message_hash.update(message);
-        let mut hashes: Vec<Hash> = (0..n).map(|_| group_and_message_hash.clone()).collect();
-        hashes[(secret_index + 1) % n].update(
-            (a * constants::RISTRETTO_BASEPOINT_POINT)
-                .compress()
-                .as_bytes(),
+        let group_and_message_hash: Hash = compute_challenge_prefix::<Hash>(&ring, message);
+        return sign_with_prefix::<Hash, CSPRNG>(k, ring, secret_index, group_and_message_hash, csprng, &g);
+    }
+}
+
+/// Signs over the complete `ring`, given the `group_and_message_hash` from
+/// [`compute_challenge_prefix`], with the generator `g`. `secret_index` must be within `ring`
+#[cfg(feature = "sign")]
+fn sign_with_prefix<Hash: Digest<OutputSize = U64> + Clone, CSPRNG: CryptoRng + RngCore>(
+    k: Scalar,
+    ring: Vec<RistrettoPoint>,
+    secret_index: usize,
+    group_and_message_hash: Hash,
+    csprng: &mut CSPRNG,
+    g: &RistrettoPoint,
+) -> Result<SAG, Error> {
+    let n = ring.len();
+    if secret_index >= n {
+        return Err(Error::SecretIndexOutOfRange {
+            index: secret_index,
+            ring_size: n,
+        });
+    }
+    let a: Scalar = Scalar::random(csprng);
+    let mut rs: Vec<Scalar> = (0..n).map(|_| Scalar::random(csprng)).collect();
+    let mut cs: Vec<Scalar> = (0..n).map(|_| Scalar::ZERO).collect();
+    cs[(secret_index + 1) % n] = compute_challenge_step(
+        group_and_message_hash.clone(),
+        &ops::mul_base(&a, g),
+    );
+    let mut i = (secret_index + 1) % n;
+    loop {
+        cs[(i + 1) % n] = compute_challenge_step(
+            group_and_message_hash.clone(),
+            &ops::multiscalar_mul(
+                &[rs[i % n], cs[i % n]],
+                &[*g, ring[i % n]],
+            ),
         );
-        cs[(secret_index + 1) % n] = Scalar::from_hash(hashes[(secret_index + 1) % n].clone());
-        let mut i = (secret_index + 1) % n;
-        loop {
-            hashes[(i + 1) % n].update(
-                RistrettoPoint::multiscalar_mul(
-                    &[rs[i % n], cs[i % n]],
-                    &[constants::RISTRETTO_BASEPOINT_POINT, ring[i % n]],
-                )
-                    .compress()
-                    .as_bytes(),
+        if secret_index >= 1 && i % n == (secret_index - 1) % n {
+            break;
+        } else if secret_index == 0 && i % n == n - 1 {
+            break;
+        } else {
+            i = (i + 1) % n;
+        }
+    }
+    rs[secret_index] = a - (cs[secret_index] * k);
+    return Ok(SAG {
+        challenge: Challenge(cs[0]),
+        responses: scalars::responses(rs),
+        ring: ring,
+    });
+}
+
+impl ChallengeChain for SAG {
+    fn ring_size(&self) -> usize {
+        return self.ring.len();
+    }
+
+    fn challenge(&self) -> Scalar {
+        return self.challenge.0;
+    }
+
+    /// Starting from `challenge` entering the first slot in `slots`, recomputes the challenge
+    /// leaving the last one
+    fn reconstruct_challenge<Hash: Digest<OutputSize = U64> + Clone + Default>(
+        &self,
+        message: &Vec<u8>,
+        slots: Range<usize>,
+        challenge: Scalar,
+    ) -> Option<Scalar> {
+        // A malformed signature has no chain to run
+        if self.check_shape().is_err() || slots.end > self.ring.len() {
+            return None;
+        }
+        let group_and_message_hash: Hash = compute_challenge_prefix::<Hash>(&self.ring, message);
+        let g: RistrettoPoint = Basepoint::generator();
+        return Some(self.reconstruct_challenge_from_prefix(group_and_message_hash, slots, challenge, &g));
+    }
+}
+
+impl SAG {
+    /// [`ChallengeChain::reconstruct_challenge`] given the `group_and_message_hash` from
+    /// [`compute_challenge_prefix`], with the generator `g`
+    fn reconstruct_challenge_from_prefix<Hash: Digest<OutputSize = U64> + Clone>(
+        &self,
+        group_and_message_hash: Hash,
+        slots: Range<usize>,
+        challenge: Scalar,
+        g: &RistrettoPoint,
+    ) -> Scalar {
+        let mut reconstructed_c: Scalar = challenge;
+        for j in slots {
+            reconstructed_c = compute_challenge_step(
+                group_and_message_hash.clone(),
+                &ops::multiscalar_mul(
+                    &[self.responses[j].0, reconstructed_c],
+                    &[*g, self.ring[j]],
+                ),
             );
-            cs[(i + 1) % n] = Scalar::from_hash(hashes[(i + 1) % n].clone());
-            if secret_index >= 1 && i % n == (secret_index - 1) % n {
-                break;
-            } else if secret_index == 0 && i % n == n - 1 {
-                break;
-            } else {
-                i = (i + 1) % n;
-            }
         }
-        rs[secret_index] = a - (cs[secret_index] * k);
-        return SAG {
-            challenge: cs[0],
-            responses: rs,
+
+        return reconstructed_c;
+    }
+}
+
+/// A ring prepared for signing or verifying many messages
+///
+/// Every challenge hashes the complete ring before the message. This keeps the hash state after
+/// the ring, so that each signature or verification only hashes its message and the points of
+/// the loop.
+///
+/// Please read tests at the bottom of the source code for this module for examples on how to use
+/// it
+#[derive(Clone)]
+pub struct PreparedSagRing<Hash> {
+    ring: Vec<RistrettoPoint>,
+    ring_hash: Hash,
+}
+
+impl<Hash: Digest<OutputSize = U64> + Clone + Default> PreparedSagRing<Hash> {
+    /// Prepares the complete `ring`, the signer included
+    pub fn new(ring: Vec<RistrettoPoint>) -> PreparedSagRing<Hash> {
+        let ring_hash: Hash = compute_challenge_prefix::<Hash>(&ring, &[]);
+        return PreparedSagRing {
             ring: ring,
+            ring_hash: ring_hash,
         };
     }
+
+    pub fn ring(&self) -> &[RistrettoPoint] {
+        return &self.ring;
+    }
+
+    /// Same as [`crate::traits::Sign::sign`] for a SAG, except that `ring[secret_index]` must
+    /// already be the public key of `k`
+    #[cfg(feature = "sign")]
+    pub fn sign<CSPRNG: CryptoRng + RngCore + Default>(
+        &self,
+        k: Scalar,
+        secret_index: usize,
+        message: &Vec<u8>,
+    ) -> Result<SAG, Error> {
+        let group_and_message_hash: Hash = self.ring_hash.clone().chain_update(message);
+        let mut csprng = CSPRNG::default();
+        let ring: Vec<RistrettoPoint> = self.ring.clone();
+        let g: RistrettoPoint = Basepoint::generator();
+        return sign_with_prefix::<Hash, CSPRNG>(k, ring, secret_index, group_and_message_hash, &mut csprng, &g);
+    }
+
+    /// Same as [`VerifyRef::verify_ref`] for a SAG, also requiring that `signature` is over this ring
+    pub fn verify(&self, signature: &SAG, message: &Vec<u8>) -> bool {
+        let n = self.ring.len();
+        if signature.ring != self.ring || signature.responses.len() != n {
+            return false;
+        }
+        let group_and_message_hash: Hash = self.ring_hash.clone().chain_update(message);
+        let reconstructed_c: Scalar = signature.reconstruct_challenge_from_prefix(
+            group_and_message_hash,
+            0..n,
+            signature.challenge.0,
+            &Basepoint::generator(),
+        );
+        return signature.challenge.0 == reconstructed_c;
+    }
 }
 
-impl Verify for SAG {
+impl VerifyRef for SAG {
     /// To verify a `signature` you need the `message` too
-    fn verify<Hash: Digest<OutputSize=U64> + Clone>(signature: SAG, message: &Vec<u8>) -> bool {
-        let n = signature.ring.len();
-        let mut reconstructed_c: Scalar = signature.challenge;
-        let mut group_and_message_hash = Hash::new();
-        for k_point in &signature.ring {
-            group_and_message_hash.update(k_point.compress().as_bytes());
+    fn verify_ref<Hash: Digest<OutputSize = U64> + Clone + Default>(&self, message: &Vec<u8>) -> bool {
+        return self.verify_with_generator::<Hash, Basepoint>(message);
+    }
+}
+
+#[cfg(feature = "allocator-api")]
+impl VerifyIn for SAG {
+    /// Verifying allocates nothing, so `alloc` goes unused
+    fn verify_in<Hash: Digest<OutputSize = U64> + Clone + Default, A: Allocator>(
+        &self,
+        message: &Vec<u8>,
+        _alloc: A,
+    ) -> bool {
+        return self.verify_ref::<Hash>(message);
+    }
+}
+
+impl VerifyWithGenerator for SAG {
+    fn verify_with_generator<Hash: Digest<OutputSize = U64> + Clone + Default, G: Generator>(
+        &self,
+        message: &Vec<u8>,
+    ) -> bool {
+        // Signatures built by hand may not have one response per member
+        if self.check_shape().is_err() {
+            return false;
         }
-        group_and_message_hash.update(message);
-        for j in 0..n {
-            let mut h: Hash = group_and_message_hash.clone();
-            h.update(
-                RistrettoPoint::multiscalar_mul(
-                    &[signature.responses[j], reconstructed_c],
-                    &[constants::RISTRETTO_BASEPOINT_POINT, signature.ring[j]],
-                )
-                    .compress()
-                    .as_bytes(),
-            );
-            reconstructed_c = Scalar::from_hash(h);
+        let n = self.ring.len();
+        let group_and_message_hash: Hash = compute_challenge_prefix::<Hash>(&self.ring, message);
+        let reconstructed_c: Scalar =
+            self.reconstruct_challenge_from_prefix(group_and_message_hash, 0..n, self.challenge.0, &G::generator());
+        return self.challenge.0 == reconstructed_c;
+    }
+}
+
+#[cfg(feature = "sign")]
+impl TrySign<Scalar, Vec<RistrettoPoint>> for SAG {
+    fn check_sign_input(_k: &Scalar, ring: &Vec<RistrettoPoint>, secret_index: usize) -> Result<(), Error> {
+        return check_secret_index(ring.len(), secret_index);
+    }
+}
+
+#[cfg(feature = "sign")]
+impl SignWithHiddenIndex<Scalar, RistrettoPoint> for SAG {}
+
+#[cfg(feature = "sign")]
+impl SignWithFullRing<Scalar, Vec<RistrettoPoint>> for SAG {
+    fn take_signer(k: &Scalar, ring: &mut Vec<RistrettoPoint>, secret_index: usize) -> Result<(), Error> {
+        return take_signer(ring, secret_index, &(k * constants::RISTRETTO_BASEPOINT_POINT));
+    }
+}
+
+#[cfg(feature = "sign")]
+impl Scheme for SAG {
+    type SecretKey = Scalar;
+    type Ring = Vec<RistrettoPoint>;
+
+    fn sign_with_key<Hash: Digest<OutputSize = U64> + Clone + Default, CSPRNG: CryptoRng + RngCore + Default>(
+        k: Scalar,
+        ring: Vec<RistrettoPoint>,
+        secret_index: usize,
+        message: &Vec<u8>,
+    ) -> Result<SAG, Error> {
+        let mut csprng = CSPRNG::default();
+        return SAG::sign_with_rng::<Hash, CSPRNG>(k, ring, secret_index, message, &mut csprng);
+    }
+
+    fn try_sign_with_key_and_rng<Hash: Digest<OutputSize = U64> + Clone + Default, CSPRNG: CryptoRng + RngCore>(
+        k: Scalar,
+        ring: Vec<RistrettoPoint>,
+        secret_index: usize,
+        message: &Vec<u8>,
+        csprng: &mut CSPRNG,
+    ) -> Result<SAG, Error> {
+        return SAG::sign_with_rng_unchecked::<Hash, CSPRNG>(k, ring, secret_index, message, csprng);
+    }
+}
+
+impl TryVerify for SAG {
+    fn check_shape(&self) -> Result<(), Error> {
+        return check_responses(self.ring.len(), self.responses.len());
+    }
+}
+
+impl Weight for SAG {
+    /// Approximate cost of verifying this signature, see [`crate::weight`]
+    fn weight(&self) -> u64 {
+        return weight::sag(self.ring.len());
+    }
+}
+
+impl SAG {
+    /// Assembles a signature from its fields, returning `None` if the ring and responses do
+    /// not agree on the ring size
+    pub fn from_parts(
+        challenge: Challenge,
+        responses: Vec<Response>,
+        ring: Vec<RistrettoPoint>,
+    ) -> Option<SAG> {
+        if ring.is_empty() || responses.len() != ring.len() {
+            return None;
         }
+        return Some(SAG::from_parts_unchecked(challenge, responses, ring));
+    }
 
-        return signature.challenge == reconstructed_c;
+    /// Assembles a signature from its fields without checking them
+    ///
+    /// This is for fields that have been checked before, e.g. by [`SAG::from_parts`] or by
+    /// decoding, and are read back from storage that is trusted. A signature assembled from
+    /// fields that do not agree is rejected: verifying it returns false.
+    pub fn from_parts_unchecked(
+        challenge: Challenge,
+        responses: Vec<Response>,
+        ring: Vec<RistrettoPoint>,
+    ) -> SAG {
+        return SAG {
+            challenge: challenge,
+            responses: responses,
+            ring: ring,
+        };
     }
 }
 
+impl Codec for SAG {
+    /// Encodes the signature canonically, see [`crate::codec`]
+    fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes: Vec<u8> = Vec::new();
+        codec::write_challenge(&mut bytes, &self.challenge);
+        codec::write_u32(&mut bytes, self.ring.len());
+        for response in &self.responses {
+            codec::write_response(&mut bytes, response);
+        }
+        for k_point in &self.ring {
+            codec::write_point(&mut bytes, k_point);
+        }
+        return bytes;
+    }
+
+    /// Decodes a signature, returning the [`Error`] describing why if `bytes` is not a canonical encoding
+    fn decode(bytes: &[u8]) -> Result<SAG, Error> {
+        let mut reader = Reader::new(bytes);
+        let challenge = reader.read_challenge()?;
+        let n = reader.read_len(64)?;
+        let responses = reader.read_responses(n)?;
+        let ring = reader.read_points(n)?;
+        reader.finish()?;
+        return Ok(SAG {
+            challenge: challenge,
+            responses: responses,
+            ring: ring,
+        });
+    }
+}
+
+crate::encoding::display_via_codec!(SAG);
+
 #[cfg(test)]
 #[cfg(feature = "std")]
+#[cfg(feature = "sign")]
 mod test {
     extern crate blake2;
     extern crate rand;
@@ -133,21 +461,57 @@ mod test {
         let message: Vec<u8> = b"This is the message".iter().cloned().collect();
 
         {
-            let signature = SAG::sign::<Sha512, OsRng>(k, ring.clone(), secret_index, &message);
-            let result = SAG::verify::<Sha512>(signature, &message);
+            let signature =
+                SAG::sign_with_rng_unchecked::<Sha512, _>(k, ring.clone(), secret_index, &message, &mut OsRng).unwrap();
+            let result = signature.verify_ref::<Sha512>(&message);
             assert!(result);
         }
 
         {
-            let signature = SAG::sign::<Keccak512, OsRng>(k, ring.clone(), secret_index, &message);
-            let result = SAG::verify::<Keccak512>(signature, &message);
+            let signature =
+                SAG::sign_with_rng_unchecked::<Keccak512, _>(k, ring.clone(), secret_index, &message, &mut OsRng)
+                    .unwrap();
+            let result = signature.verify_ref::<Keccak512>(&message);
             assert!(result);
         }
 
         {
-            let signature = SAG::sign::<Blake2b512, OsRng>(k, ring.clone(), secret_index, &message);
-            let result = SAG::verify::<Blake2b512>(signature, &message);
+            let signature =
+                SAG::sign_with_rng_unchecked::<Blake2b512, _>(k, ring.clone(), secret_index, &message, &mut OsRng)
+                    .unwrap();
+            let result = signature.verify_ref::<Blake2b512>(&message);
             assert!(result);
         }
     }
+
+    #[test]
+    fn prepared_sag_ring() {
+        let k: Scalar = Scalar::random(&mut OsRng);
+        let mut ring: Vec<RistrettoPoint> = (0..3).map(|_| RistrettoPoint::random(&mut OsRng)).collect();
+        ring.insert(2, k * constants::RISTRETTO_BASEPOINT_POINT);
+        let prepared = PreparedSagRing::<Sha512>::new(ring);
+
+        for message in [b"First message".to_vec(), b"Second message".to_vec()].iter() {
+            let signature = prepared.sign::<OsRng>(k, 2, message).unwrap();
+            assert!(prepared.verify(&signature, message));
+            // Prepared signatures are ordinary SAG signatures
+            assert!(signature.verify_ref::<Sha512>(message));
+        }
+
+        let other_ring: Vec<RistrettoPoint> = (0..3).map(|_| RistrettoPoint::random(&mut OsRng)).collect();
+        let message: Vec<u8> = b"This is the message".to_vec();
+        let signature = SAG::sign_with_rng_unchecked::<Sha512, _>(k, other_ring, 0, &message, &mut OsRng).unwrap();
+        assert!(!prepared.verify(&signature, &message));
+    }
+
+    #[test]
+    fn sign_does_not_panic() {
+        let k: Scalar = Scalar::random(&mut OsRng);
+        let ring: Vec<RistrettoPoint> = (0..2).map(|_| RistrettoPoint::random(&mut OsRng)).collect();
+        let message: Vec<u8> = b"This is the message".to_vec();
+
+        // A secret index outside the ring is an error, not a panic
+        let signature = SAG::sign_with_rng_unchecked::<Sha512, _>(k, ring, 3, &message, &mut OsRng);
+        assert_eq!(signature.err(), Some(Error::SecretIndexOutOfRange { index: 3, ring_size: 3 }));
+    }
 }
@@ -0,0 +1,120 @@
+//! Runtime self-tests
+//!
+//! Cheap known-answer and consistency checks of the curve arithmetic every scheme depends on.
+//! Deployments on embedded devices can run them at boot to detect miscompiled code or bit-rotted
+//! flash (e.g. corrupted basepoint tables) before producing signatures with a broken generator.
+//! Each check returns `false` if anything is off.
+
+use curve25519_dalek::constants;
+use curve25519_dalek::ristretto::RistrettoPoint;
+use curve25519_dalek::scalar::Scalar;
+use curve25519_dalek::traits::Identity;
+use digest::generic_array::typenum::U64;
+use digest::Digest;
+
+#[cfg(feature = "blsag")]
+use crate::blsag::BLSAG;
+#[cfg(feature = "blsag")]
+use crate::traits::KeyImageGenRef;
+
+/// Compressed encodings of `G`, `2G` and `3G` for the Ristretto basepoint `G` (RFC 9496)
+const BASEPOINT_MULTIPLES: [[u8; 32]; 3] = [
+    [
+        0xe2, 0xf2, 0xae, 0x0a, 0x6a, 0xbc, 0x4e, 0x71, 0xa8, 0x84, 0xa9, 0x61, 0xc5, 0x00, 0x51,
+        0x5f, 0x58, 0xe3, 0x0b, 0x6a, 0xa5, 0x82, 0xdd, 0x8d, 0xb6, 0xa6, 0x59, 0x45, 0xe0, 0x8d,
+        0x2d, 0x76,
+    ],
+    [
+        0x6a, 0x49, 0x32, 0x10, 0xf7, 0x49, 0x9c, 0xd1, 0x7f, 0xec, 0xb5, 0x10, 0xae, 0x0c, 0xea,
+        0x23, 0xa1, 0x10, 0xe8, 0xd5, 0xb9, 0x01, 0xf8, 0xac, 0xad, 0xd3, 0x09, 0x5c, 0x73, 0xa3,
+        0xb9, 0x19,
+    ],
+    [
+        0x94, 0x74, 0x1f, 0x5d, 0x5d, 0x52, 0x75, 0x5e, 0xce, 0x4f, 0x23, 0xf0, 0x44, 0xee, 0x27,
+        0xd5, 0xd1, 0xea, 0x1e, 0x2b, 0xd1, 0x96, 0xb4, 0x62, 0x16, 0x6b, 0x16, 0x15, 0x2a, 0x9d,
+        0x02, 0x59,
+    ],
+];
+
+/// Compressed encoding of the point the Ristretto map sends the bytes `0, 1, ..., 63` to
+const UNIFORM_BYTES_POINT: [u8; 32] = [
+    0x2e, 0x7c, 0x49, 0x64, 0xf9, 0x1f, 0x5f, 0x2b, 0x07, 0x4a, 0x9b, 0xc1, 0x47, 0xef, 0x97, 0x3c,
+    0x08, 0xdb, 0xe2, 0x96, 0x83, 0x74, 0x6f, 0x97, 0x9f, 0x11, 0x35, 0x80, 0x65, 0xa2, 0xd1, 0x55,
+];
+
+/// Checks the basepoint and the fixed-base multiplication tables against known answers, and
+/// fixed-base against variable-base multiplication for a few scalars
+pub fn check_basepoint_tables() -> bool {
+    for (i, expected) in BASEPOINT_MULTIPLES.iter().enumerate() {
+        let s = Scalar::from(i as u64 + 1);
+        if RistrettoPoint::mul_base(&s).compress().as_bytes() != expected {
+            return false;
+        }
+        if (s * constants::RISTRETTO_BASEPOINT_POINT).compress().as_bytes() != expected {
+            return false;
+        }
+    }
+    // Scalars spanning every window of the tables
+    let scalars = [Scalar::from(u64::MAX), -Scalar::ONE, Scalar::from_bytes_mod_order([0x5a; 32])];
+    for s in scalars.iter() {
+        if RistrettoPoint::mul_base(s) != s * constants::RISTRETTO_BASEPOINT_POINT {
+            return false;
+        }
+    }
+    return RistrettoPoint::mul_base(&Scalar::ZERO) == RistrettoPoint::identity();
+}
+
+/// Checks the map from hash output to points against a known answer, that hashing to a point
+/// with `Hash` is deterministic and input dependent, and that key images can be inverted back to
+/// the hashed public key they were derived from
+pub fn check_hash_to_point<Hash: Digest<OutputSize = U64> + Clone + Default>() -> bool {
+    let mut uniform_bytes = [0u8; 64];
+    for (i, byte) in uniform_bytes.iter_mut().enumerate() {
+        *byte = i as u8;
+    }
+    if RistrettoPoint::from_uniform_bytes(&uniform_bytes).compress().to_bytes() != UNIFORM_BYTES_POINT {
+        return false;
+    }
+
+    let k = Scalar::from_bytes_mod_order([0x5a; 32]);
+    let k_point = k * constants::RISTRETTO_BASEPOINT_POINT;
+    let hashed = RistrettoPoint::from_hash(Hash::default().chain_update(k_point.compress().as_bytes()));
+    let hashed_again = RistrettoPoint::from_hash(Hash::default().chain_update(k_point.compress().as_bytes()));
+    let hashed_other = RistrettoPoint::from_hash(
+        Hash::default().chain_update(constants::RISTRETTO_BASEPOINT_COMPRESSED.as_bytes()),
+    );
+    if hashed != hashed_again || hashed == hashed_other || hashed == RistrettoPoint::identity() {
+        return false;
+    }
+
+    #[cfg(feature = "blsag")]
+    {
+        let key_image = BLSAG::generate_key_image_ref::<Hash>(&k);
+        if k.invert() * key_image != hashed {
+            return false;
+        }
+    }
+    return true;
+}
+
+#[cfg(test)]
+#[cfg(feature = "std")]
+mod test {
+    extern crate blake2;
+    extern crate sha2;
+    extern crate sha3;
+
+    use blake2::Blake2b512;
+    use sha2::Sha512;
+    use sha3::Keccak512;
+
+    use super::*;
+
+    #[test]
+    fn sanity() {
+        assert!(check_basepoint_tables());
+        assert!(check_hash_to_point::<Sha512>());
+        assert!(check_hash_to_point::<Keccak512>());
+        assert!(check_hash_to_point::<Blake2b512>());
+    }
+}
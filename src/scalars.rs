@@ -0,0 +1,79 @@
+//! Challenges and responses
+//!
+//! A signature stores its first challenge and one response per ring member and key, all scalars.
+//! The fields of the signatures are a [`Challenge`] and [`Response`]s rather than bare `Scalar`s,
+//! so that passing responses where a challenge is expected, e.g. to `from_parts`, does not
+//! compile. The scalar is the public field of each, for the arithmetic of the schemes.
+
+#[cfg(feature = "sign")]
+#[cfg(any(
+    feature = "sag",
+    feature = "lsag",
+    feature = "blsag",
+    feature = "mlsag",
+    feature = "clsag",
+    feature = "dlsag",
+    feature = "mdlsag"
+))]
+use crate::prelude::*;
+
+use curve25519_dalek::scalar::Scalar;
+
+/// A challenge `c` of the challenge chain
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default)]
+pub struct Challenge(pub Scalar);
+
+/// A response `r`, the scalar a ring member is closed with
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default)]
+pub struct Response(pub Scalar);
+
+impl Challenge {
+    pub fn as_bytes(&self) -> &[u8; 32] {
+        return self.0.as_bytes();
+    }
+}
+
+impl Response {
+    pub fn as_bytes(&self) -> &[u8; 32] {
+        return self.0.as_bytes();
+    }
+}
+
+impl From<Scalar> for Challenge {
+    fn from(scalar: Scalar) -> Challenge {
+        return Challenge(scalar);
+    }
+}
+
+impl From<Scalar> for Response {
+    fn from(scalar: Scalar) -> Response {
+        return Response(scalar);
+    }
+}
+
+impl From<Challenge> for Scalar {
+    fn from(challenge: Challenge) -> Scalar {
+        return challenge.0;
+    }
+}
+
+impl From<Response> for Scalar {
+    fn from(response: Response) -> Scalar {
+        return response.0;
+    }
+}
+
+/// The responses `rs` as [`Response`]s
+#[cfg(feature = "sign")]
+#[cfg(any(
+    feature = "sag",
+    feature = "lsag",
+    feature = "blsag",
+    feature = "mlsag",
+    feature = "clsag",
+    feature = "dlsag",
+    feature = "mdlsag"
+))]
+pub(crate) fn responses(rs: Vec<Scalar>) -> Vec<Response> {
+    return rs.into_iter().map(Response).collect();
+}
@@ -0,0 +1,146 @@
+//! Runtime scheme registry
+//!
+//! Features fix which schemes a build can parse and verify at all. Protocols that negotiate
+//! capabilities with each peer need a finer, runtime control: a [`SchemeRegistry`] holds the
+//! schemes an application has enabled, e.g. at startup or after a handshake, and parsing or
+//! verifying through it rejects signatures of any other scheme with [`Error::SchemeDisabled`]
+//! before decoding them.
+
+use crate::prelude::*;
+
+use digest::generic_array::typenum::U64;
+use digest::Digest;
+
+use crate::error::Error;
+use crate::header::SchemeId;
+use crate::signature::{self, RingSignature};
+
+/// A set of enabled schemes, empty when created
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct SchemeRegistry {
+    /// Bit `tag` is set for every enabled scheme
+    enabled: u8,
+}
+
+/// Every scheme, in tag order
+const SCHEMES: [SchemeId; 7] = [
+    SchemeId::SAG,
+    SchemeId::LSAG,
+    SchemeId::BLSAG,
+    SchemeId::MLSAG,
+    SchemeId::CLSAG,
+    SchemeId::DLSAG,
+    SchemeId::MDLSAG,
+];
+
+impl SchemeRegistry {
+    pub fn new() -> SchemeRegistry {
+        return SchemeRegistry::default();
+    }
+
+    pub fn enable(&mut self, scheme: SchemeId) {
+        self.enabled |= 1 << scheme as u8;
+    }
+
+    pub fn disable(&mut self, scheme: SchemeId) {
+        self.enabled &= !(1 << scheme as u8);
+    }
+
+    pub fn is_enabled(&self, scheme: SchemeId) -> bool {
+        return self.enabled & (1 << scheme as u8) != 0;
+    }
+
+    /// The enabled schemes, in tag order, e.g. to advertise them in a handshake
+    pub fn enabled(&self) -> Vec<SchemeId> {
+        return SCHEMES
+            .iter()
+            .copied()
+            .filter(|scheme| self.is_enabled(*scheme))
+            .collect();
+    }
+
+    /// The schemes enabled both here and in `other`, i.e. those both sides of a handshake accept
+    pub fn intersection(&self, other: &SchemeRegistry) -> SchemeRegistry {
+        return SchemeRegistry {
+            enabled: self.enabled & other.enabled,
+        };
+    }
+
+    pub fn check(&self, scheme: SchemeId) -> Result<(), Error> {
+        if !self.is_enabled(scheme) {
+            return Err(Error::SchemeDisabled { tag: scheme as u8 });
+        }
+        return Ok(());
+    }
+
+    /// As [`signature::parse`], first rejecting tags of schemes that are not enabled
+    pub fn parse(&self, bytes: &[u8]) -> Result<RingSignature, Error> {
+        if let Some(scheme) = bytes.first().and_then(|tag| SchemeId::from_u8(*tag)) {
+            self.check(scheme)?;
+        }
+        return signature::parse(bytes);
+    }
+
+    /// Verifies `signature` if its scheme is enabled
+    pub fn verify<Hash: Digest<OutputSize = U64> + Clone + Default>(
+        &self,
+        signature: RingSignature,
+        message: &Vec<u8>,
+    ) -> Result<bool, Error> {
+        self.check(signature.scheme())?;
+        return Ok(signature.verify::<Hash>(message));
+    }
+}
+
+#[cfg(test)]
+#[cfg(feature = "std")]
+#[cfg(feature = "sign")]
+#[cfg(feature = "blsag")]
+mod test {
+    extern crate rand;
+    extern crate sha2;
+
+    use curve25519_dalek::ristretto::RistrettoPoint;
+    use curve25519_dalek::scalar::Scalar;
+    use rand::rngs::OsRng;
+    use sha2::Sha512;
+
+    use super::*;
+    use crate::blsag::BLSAG;
+    use crate::signature::SignatureBytes;
+    use crate::traits::SignWithRng;
+
+    #[test]
+    fn scheme_registry() {
+        let message: Vec<u8> = b"This is the message".to_vec();
+        let k: Scalar = Scalar::random(&mut OsRng);
+        let ring: Vec<RistrettoPoint> = (0..2).map(|_| RistrettoPoint::random(&mut OsRng)).collect();
+        let bytes = BLSAG::sign_with_rng_unchecked::<Sha512, _>(k, ring, 1, &message, &mut OsRng)
+            .unwrap()
+            .to_tagged_bytes();
+
+        let mut registry = SchemeRegistry::new();
+        assert_eq!(registry.parse(&bytes).err(), Some(Error::SchemeDisabled { tag: 3 }));
+        registry.enable(SchemeId::BLSAG);
+        registry.enable(SchemeId::CLSAG);
+        assert_eq!(registry.enabled(), vec![SchemeId::BLSAG, SchemeId::CLSAG]);
+        let signature = registry.parse(&bytes).unwrap();
+        assert_eq!(registry.verify::<Sha512>(signature.clone(), &message), Ok(true));
+
+        // Tags no scheme has are still reported as unknown
+        assert_eq!(registry.parse(&[0]).err(), Some(Error::UnknownScheme { tag: 0 }));
+
+        let mut peer = SchemeRegistry::new();
+        peer.enable(SchemeId::CLSAG);
+        let negotiated = registry.intersection(&peer);
+        assert_eq!(negotiated.enabled(), vec![SchemeId::CLSAG]);
+        assert_eq!(
+            negotiated.verify::<Sha512>(signature, &message),
+            Err(Error::SchemeDisabled { tag: 3 })
+        );
+
+        registry.disable(SchemeId::BLSAG);
+        assert!(!registry.is_enabled(SchemeId::BLSAG));
+        assert!(registry.parse(&bytes).is_err());
+    }
+}
@@ -0,0 +1,27 @@
+//! The temporary vectors of verification, allocated globally or, with the `allocator-api`
+//! feature, from the arena a caller passes to `arena::VerifyIn`
+
+use core::ops::Deref;
+
+use crate::prelude::*;
+
+/// A vector reused for the temporary values of a verification
+pub(crate) trait Scratch<T>: Extend<T> + Deref<Target = [T]> {
+    #[cfg(any(feature = "mlsag", feature = "mdlsag"))]
+    fn clear(&mut self);
+}
+
+impl<T> Scratch<T> for Vec<T> {
+    #[cfg(any(feature = "mlsag", feature = "mdlsag"))]
+    fn clear(&mut self) {
+        Vec::clear(self);
+    }
+}
+
+#[cfg(feature = "allocator-api")]
+impl<T, A: allocator_api2::alloc::Allocator> Scratch<T> for allocator_api2::vec::Vec<T, A> {
+    #[cfg(any(feature = "mlsag", feature = "mdlsag"))]
+    fn clear(&mut self) {
+        allocator_api2::vec::Vec::clear(self);
+    }
+}
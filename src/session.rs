@@ -0,0 +1,314 @@
+//! Message sessions
+//!
+//! bLSAG, MLSAG, DLSAG and MDLSAG start every challenge from the same hash state `H_n(m, ....)`
+//! with only the message absorbed. A [`MessageSession`] absorbs the message once and verifies any
+//! number of signatures over it, which saves rehashing a large message (e.g. an election
+//! descriptor in a voting tally) for every signature.
+//!
+//! A message arriving in pieces, e.g. over a socket, can be fed to a [`MessageWriter`] as it comes
+//! and need not be buffered. Finishing the writer gives the session. bLSAG and MLSAG can also be
+//! signed in a session, see [`SessionSign`].
+//!
+//! SAG, LSAG and CLSAG absorb the ring before the message, so they can not share a session.
+
+#[cfg(all(feature = "sign", any(feature = "blsag", feature = "mlsag")))]
+use crate::prelude::*;
+
+#[cfg(all(feature = "sign", feature = "blsag"))]
+use curve25519_dalek::constants;
+#[cfg(all(feature = "sign", any(feature = "blsag", feature = "mlsag")))]
+use curve25519_dalek::ristretto::RistrettoPoint;
+#[cfg(any(feature = "blsag", feature = "mlsag", feature = "dlsag", feature = "mdlsag"))]
+use curve25519_dalek::scalar::Scalar;
+use digest::generic_array::typenum::U64;
+use digest::Digest;
+#[cfg(feature = "sign")]
+use rand_core::{CryptoRng, RngCore};
+
+#[cfg(feature = "blsag")]
+use crate::blsag::BLSAG;
+#[cfg(feature = "dlsag")]
+use crate::dlsag::DLSAG;
+#[cfg(feature = "sign")]
+use crate::error::Error;
+#[cfg(any(feature = "blsag", feature = "mlsag"))]
+use crate::hash_to_point::DefaultHashToPoint;
+#[cfg(all(feature = "sign", feature = "blsag"))]
+use crate::hash_to_point::HashToPoint;
+#[cfg(any(feature = "blsag", feature = "mlsag"))]
+use crate::hash_to_scalar::WideReduction;
+#[cfg(feature = "mlsag")]
+use crate::generator::Basepoint;
+#[cfg(feature = "mdlsag")]
+use crate::mdlsag::MDLSAG;
+#[cfg(feature = "mlsag")]
+use crate::mlsag::MLSAG;
+#[cfg(feature = "blsag")]
+use crate::traits::TryVerify;
+
+/// Signatures whose challenges start from the hash of the message alone
+pub trait SessionVerify {
+    /// Verifies the signature given `message_hash`, the hash state with only the message absorbed
+    fn verify_in_session<Hash: Digest<OutputSize = U64> + Clone + Default>(&self, message_hash: &Hash) -> bool;
+}
+
+/// Signatures that can be signed given the hash of the message alone
+#[cfg(feature = "sign")]
+pub trait SessionSign<PrivateKey, Ring>: Sized {
+    /// Signs given `message_hash`, the hash state with only the message absorbed, as
+    /// [`SignWithRng::sign_with_rng`](crate::traits::SignWithRng::sign_with_rng) would sign the
+    /// message
+    fn sign_in_session<Hash: Digest<OutputSize = U64> + Clone + Default, CSPRNG: CryptoRng + RngCore>(
+        k: PrivateKey,
+        ring: Ring,
+        secret_index: usize,
+        message_hash: &Hash,
+        csprng: &mut CSPRNG,
+    ) -> Result<Self, Error>;
+}
+
+/// One message, hashed once, to verify many signatures against
+#[derive(Clone)]
+pub struct MessageSession<Hash> {
+    message_hash: Hash,
+}
+
+impl<Hash: Digest<OutputSize = U64> + Clone + Default> MessageSession<Hash> {
+    pub fn new(message: &[u8]) -> MessageSession<Hash> {
+        let mut message_hash = Hash::default();
+        message_hash.update(message);
+        return MessageSession {
+            message_hash: message_hash,
+        };
+    }
+
+    /// Same as `verify` with the message of this session
+    pub fn verify<S: SessionVerify>(&self, signature: &S) -> bool {
+        return signature.verify_in_session(&self.message_hash);
+    }
+
+    /// Same as `sign_with_rng` with the message of this session
+    #[cfg(feature = "sign")]
+    pub fn sign<PrivateKey, Ring, S: SessionSign<PrivateKey, Ring>, CSPRNG: CryptoRng + RngCore>(
+        &self,
+        k: PrivateKey,
+        ring: Ring,
+        secret_index: usize,
+        csprng: &mut CSPRNG,
+    ) -> Result<S, Error> {
+        return S::sign_in_session::<Hash, CSPRNG>(k, ring, secret_index, &self.message_hash, csprng);
+    }
+}
+
+/// A message absorbed a piece at a time, see the module documentation
+#[derive(Clone)]
+pub struct MessageWriter<Hash> {
+    message_hash: Hash,
+}
+
+impl<Hash: Digest<OutputSize = U64> + Clone + Default> MessageWriter<Hash> {
+    pub fn new() -> MessageWriter<Hash> {
+        return MessageWriter {
+            message_hash: Hash::default(),
+        };
+    }
+
+    /// Absorbs the next `chunk` of the message
+    pub fn update(&mut self, chunk: &[u8]) {
+        self.message_hash.update(chunk);
+    }
+
+    /// The session of the whole message, the same as [`MessageSession::new`] over the chunks
+    /// joined together
+    pub fn finalize(self) -> MessageSession<Hash> {
+        return MessageSession {
+            message_hash: self.message_hash,
+        };
+    }
+}
+
+impl<Hash: Digest<OutputSize = U64> + Clone + Default> Default for MessageWriter<Hash> {
+    fn default() -> MessageWriter<Hash> {
+        return MessageWriter::new();
+    }
+}
+
+/// Lets `std::io::copy` feed a reader, e.g. a socket, into the writer
+#[cfg(feature = "std")]
+impl<Hash: Digest<OutputSize = U64> + Clone + Default> std::io::Write for MessageWriter<Hash> {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.update(buf);
+        return Ok(buf.len());
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        return Ok(());
+    }
+}
+
+#[cfg(all(feature = "sign", feature = "blsag"))]
+impl SessionSign<Scalar, Vec<RistrettoPoint>> for BLSAG {
+    fn sign_in_session<Hash: Digest<OutputSize = U64> + Clone + Default, CSPRNG: CryptoRng + RngCore>(
+        k: Scalar,
+        ring: Vec<RistrettoPoint>,
+        secret_index: usize,
+        message_hash: &Hash,
+        csprng: &mut CSPRNG,
+    ) -> Result<BLSAG, Error> {
+        return BLSAG::sign_with_map::<Hash, CSPRNG, WideReduction, _>(
+            k,
+            ring,
+            secret_index,
+            message_hash.clone(),
+            csprng,
+            &constants::RISTRETTO_BASEPOINT_POINT,
+            |point| DefaultHashToPoint::hash_to_point::<Hash>(point),
+        );
+    }
+}
+
+#[cfg(all(feature = "sign", feature = "mlsag"))]
+impl SessionSign<Vec<Scalar>, Vec<Vec<RistrettoPoint>>> for MLSAG {
+    fn sign_in_session<Hash: Digest<OutputSize = U64> + Clone + Default, CSPRNG: CryptoRng + RngCore>(
+        ks: Vec<Scalar>,
+        ring: Vec<Vec<RistrettoPoint>>,
+        secret_index: usize,
+        message_hash: &Hash,
+        csprng: &mut CSPRNG,
+    ) -> Result<MLSAG, Error> {
+        return MLSAG::sign_with_prefix::<Hash, CSPRNG, Basepoint, DefaultHashToPoint, WideReduction>(
+            &ks,
+            ring,
+            secret_index,
+            message_hash.clone(),
+            csprng,
+        );
+    }
+}
+
+#[cfg(feature = "blsag")]
+impl SessionVerify for BLSAG {
+    fn verify_in_session<Hash: Digest<OutputSize = U64> + Clone + Default>(&self, message_hash: &Hash) -> bool {
+        if self.check_shape().is_err() || !self.ring_digest_matches::<Hash>() {
+            return false;
+        }
+        let n = self.ring.len();
+        let reconstructed_c: Scalar =
+            self.reconstruct_challenge_from_prefix::<Hash, DefaultHashToPoint, WideReduction>(message_hash.clone(), 0..n, self.challenge.0);
+        return self.challenge.0 == reconstructed_c;
+    }
+}
+
+#[cfg(feature = "mlsag")]
+impl SessionVerify for MLSAG {
+    fn verify_in_session<Hash: Digest<OutputSize = U64> + Clone + Default>(&self, message_hash: &Hash) -> bool {
+        if !self.is_well_formed() {
+            return false;
+        }
+        let n = self.ring.len();
+        let reconstructed_c: Scalar =
+            self.reconstruct_challenge_from_prefix::<Hash, Basepoint, DefaultHashToPoint, WideReduction>(
+                message_hash.clone(),
+                0..n,
+                self.challenge.0,
+            );
+        return self.challenge.0 == reconstructed_c;
+    }
+}
+
+#[cfg(feature = "dlsag")]
+impl SessionVerify for DLSAG {
+    fn verify_in_session<Hash: Digest<OutputSize = U64> + Clone + Default>(&self, message_hash: &Hash) -> bool {
+        let n = self.ring.len();
+        let reconstructed_c: Scalar =
+            self.reconstruct_challenge_from_prefix(message_hash.clone(), 0..n, self.challenge.0);
+        return self.challenge.0 == reconstructed_c;
+    }
+}
+
+#[cfg(feature = "mdlsag")]
+impl SessionVerify for MDLSAG {
+    fn verify_in_session<Hash: Digest<OutputSize = U64> + Clone + Default>(&self, message_hash: &Hash) -> bool {
+        let n = self.ring.len();
+        let reconstructed_c: Scalar =
+            self.reconstruct_challenge_from_prefix(message_hash.clone(), 0..n, self.challenge.0);
+        return self.challenge.0 == reconstructed_c;
+    }
+}
+
+#[cfg(test)]
+#[cfg(feature = "std")]
+#[cfg(feature = "sign")]
+#[cfg(all(feature = "blsag", feature = "mlsag"))]
+mod test {
+    extern crate rand;
+    extern crate sha2;
+
+    use curve25519_dalek::ristretto::RistrettoPoint;
+    use rand::rngs::OsRng;
+    use sha2::Sha512;
+
+    use super::*;
+    use crate::traits::SignWithRng;
+
+    #[test]
+    fn session() {
+        let message: Vec<u8> = b"This is the election descriptor".to_vec();
+        let session = MessageSession::<Sha512>::new(&message);
+        let another_session = MessageSession::<Sha512>::new(b"This is another message");
+
+        for _ in 0..3 {
+            let k: Scalar = Scalar::random(&mut OsRng);
+            let ring: Vec<RistrettoPoint> = (0..2).map(|_| RistrettoPoint::random(&mut OsRng)).collect();
+            let signature = BLSAG::sign_with_rng_unchecked::<Sha512, _>(k, ring, 1, &message, &mut OsRng).unwrap();
+            assert!(session.verify(&signature));
+            assert!(!another_session.verify(&signature));
+
+            // A ring digest that is not that of the ring is rejected, as by `verify`
+            let attached = signature.attach_ring_digest::<Sha512>();
+            assert!(session.verify(&attached));
+            assert!(!session.verify(&attached.with_ring_digest([0u8; 64])));
+        }
+
+        let ks: Vec<Scalar> = (0..2).map(|_| Scalar::random(&mut OsRng)).collect();
+        let ring: Vec<Vec<RistrettoPoint>> =
+            (0..2).map(|_| (0..2).map(|_| RistrettoPoint::random(&mut OsRng)).collect()).collect();
+        let signature = MLSAG::sign_with_rng_unchecked::<Sha512, _>(ks, ring, 1, &message, &mut OsRng).unwrap();
+        assert!(session.verify(&signature));
+        assert!(!another_session.verify(&signature));
+    }
+
+    #[test]
+    fn message_writer() {
+        use crate::traits::{SignWithRng, VerifyRef};
+        use rand::rngs::StdRng;
+        use rand::SeedableRng;
+
+        let message: Vec<u8> = b"This is the election descriptor".to_vec();
+        let mut writer = MessageWriter::<Sha512>::new();
+        for chunk in message.chunks(5) {
+            writer.update(chunk);
+        }
+        let session = writer.finalize();
+
+        // Signing in the session is signing the whole message
+        let k: Scalar = Scalar::random(&mut OsRng);
+        let ring: Vec<RistrettoPoint> = (0..2).map(|_| RistrettoPoint::random(&mut OsRng)).collect();
+        let signature: BLSAG = session.sign(k, ring.clone(), 1, &mut StdRng::seed_from_u64(7)).unwrap();
+        let expected =
+            BLSAG::sign_with_rng_unchecked::<Sha512, _>(k, ring, 1, &message, &mut StdRng::seed_from_u64(7)).unwrap();
+        assert_eq!(signature.challenge, expected.challenge);
+        assert!(signature.verify_ref::<Sha512>(&message));
+
+        let ks: Vec<Scalar> = (0..2).map(|_| Scalar::random(&mut OsRng)).collect();
+        let ring: Vec<Vec<RistrettoPoint>> =
+            (0..2).map(|_| (0..2).map(|_| RistrettoPoint::random(&mut OsRng)).collect()).collect();
+        let signature: MLSAG = session.sign(ks, ring, 0, &mut OsRng).unwrap();
+        assert!(signature.verify_ref::<Sha512>(&message));
+
+        // A reader copied in verifies the same
+        let mut writer = MessageWriter::<Sha512>::new();
+        std::io::copy(&mut &message[..], &mut writer).unwrap();
+        assert!(writer.finalize().verify(&signature));
+    }
+}
@@ -0,0 +1,564 @@
+//! Scheme tagged signatures
+//!
+//! Gateways handling mixed traffic need one decode path for every scheme. A [`SignatureBytes`]
+//! encoding is the [`SchemeId`] of the scheme as a single byte, followed by the canonical encoding
+//! of the signature. [`parse`] reads the tag and decodes the rest into a [`RingSignature`], which
+//! can be inspected and verified without knowing the scheme in advance.
+//!
+//! A [`WithHash`] pairs a signature with its hash as a [`RingSignatureVerifier`], so that
+//! signatures of different schemes and hashes can be verified together behind `Box<dyn RingSignatureVerifier>`.
+
+use crate::prelude::*;
+
+use core::marker::PhantomData;
+use core::mem::size_of;
+
+use curve25519_dalek::ristretto::RistrettoPoint;
+#[cfg(feature = "clsag")]
+use curve25519_dalek::scalar::Scalar;
+use digest::generic_array::typenum::U64;
+use digest::Digest;
+
+#[cfg(feature = "blsag")]
+use crate::blsag::BLSAG;
+#[cfg(feature = "clsag")]
+use crate::clsag::CLSAG;
+use crate::codec::Codec;
+#[cfg(feature = "dlsag")]
+use crate::dlsag::DLSAG;
+use crate::error::Error;
+use crate::header::SchemeId;
+use crate::policy::{MemoryBudget, VerifyPolicy};
+#[cfg(feature = "lsag")]
+use crate::lsag::LSAG;
+#[cfg(feature = "mdlsag")]
+use crate::mdlsag::MDLSAG;
+#[cfg(feature = "mlsag")]
+use crate::mlsag::MLSAG;
+#[cfg(feature = "sag")]
+use crate::sag::SAG;
+use crate::traits::{ChallengeChain, LinkRef, TryVerify, VerifyRef};
+
+/// Signatures with a scheme tagged encoding
+pub trait SignatureBytes: Codec {
+    const SCHEME: SchemeId;
+
+    /// The scheme tag followed by the canonical encoding
+    fn to_tagged_bytes(&self) -> Vec<u8> {
+        let mut bytes: Vec<u8> = vec![Self::SCHEME as u8];
+        bytes.extend_from_slice(&self.to_bytes());
+        return bytes;
+    }
+}
+
+#[cfg(feature = "sag")]
+impl SignatureBytes for SAG {
+    const SCHEME: SchemeId = SchemeId::SAG;
+}
+
+#[cfg(feature = "lsag")]
+impl SignatureBytes for LSAG {
+    const SCHEME: SchemeId = SchemeId::LSAG;
+}
+
+#[cfg(feature = "blsag")]
+impl SignatureBytes for BLSAG {
+    const SCHEME: SchemeId = SchemeId::BLSAG;
+}
+
+#[cfg(feature = "mlsag")]
+impl SignatureBytes for MLSAG {
+    const SCHEME: SchemeId = SchemeId::MLSAG;
+}
+
+#[cfg(feature = "clsag")]
+impl SignatureBytes for CLSAG {
+    const SCHEME: SchemeId = SchemeId::CLSAG;
+}
+
+#[cfg(feature = "dlsag")]
+impl SignatureBytes for DLSAG {
+    const SCHEME: SchemeId = SchemeId::DLSAG;
+}
+
+#[cfg(feature = "mdlsag")]
+impl SignatureBytes for MDLSAG {
+    const SCHEME: SchemeId = SchemeId::MDLSAG;
+}
+
+/// A signature of any enabled scheme
+///
+/// Its encoding, and with the `serde` feature its serialization, is the [`SignatureBytes`]
+/// encoding, so the [`SchemeId`] tag of each scheme is stable across releases and builds with
+/// other schemes enabled.
+#[derive(Clone)]
+pub enum RingSignature {
+    #[cfg(feature = "sag")]
+    SAG(SAG),
+    #[cfg(feature = "lsag")]
+    LSAG(LSAG),
+    #[cfg(feature = "blsag")]
+    BLSAG(BLSAG),
+    #[cfg(feature = "mlsag")]
+    MLSAG(MLSAG),
+    #[cfg(feature = "clsag")]
+    CLSAG(CLSAG),
+    #[cfg(feature = "dlsag")]
+    DLSAG(DLSAG),
+    #[cfg(feature = "mdlsag")]
+    MDLSAG(MDLSAG),
+}
+
+/// Decodes a [`SignatureBytes`] encoding of any enabled scheme
+pub fn parse(bytes: &[u8]) -> Result<RingSignature, Error> {
+    let (tag, body) = bytes.split_first().ok_or(Error::Truncated)?;
+    let unknown = Error::UnknownScheme { tag: *tag };
+    let signature: RingSignature = match SchemeId::from_u8(*tag).ok_or(unknown)? {
+        #[cfg(feature = "sag")]
+        SchemeId::SAG => RingSignature::SAG(SAG::decode(body)?),
+        #[cfg(feature = "lsag")]
+        SchemeId::LSAG => RingSignature::LSAG(LSAG::decode(body)?),
+        #[cfg(feature = "blsag")]
+        SchemeId::BLSAG => RingSignature::BLSAG(BLSAG::decode(body)?),
+        #[cfg(feature = "mlsag")]
+        SchemeId::MLSAG => RingSignature::MLSAG(MLSAG::decode(body)?),
+        #[cfg(feature = "clsag")]
+        SchemeId::CLSAG => RingSignature::CLSAG(CLSAG::decode(body)?),
+        #[cfg(feature = "dlsag")]
+        SchemeId::DLSAG => RingSignature::DLSAG(DLSAG::decode(body)?),
+        #[cfg(feature = "mdlsag")]
+        SchemeId::MDLSAG => RingSignature::MDLSAG(MDLSAG::decode(body)?),
+        #[allow(unreachable_patterns)]
+        _ => return Err(unknown),
+    };
+    return Ok(signature);
+}
+
+/// As [`parse`], if what decoding `bytes` allocates fits in `budget`
+///
+/// Every 32 bytes of the encoding decode to at most one point, so the bound is known before
+/// anything is allocated.
+pub fn parse_within(bytes: &[u8], budget: &MemoryBudget) -> Result<RingSignature, Error> {
+    let per_point = size_of::<RistrettoPoint>() + size_of::<Vec<RistrettoPoint>>();
+    budget.check((bytes.len() / 32).saturating_mul(per_point))?;
+    return parse(bytes);
+}
+
+impl RingSignature {
+    pub fn scheme(&self) -> SchemeId {
+        return match self {
+            #[cfg(feature = "sag")]
+            RingSignature::SAG(_) => SchemeId::SAG,
+            #[cfg(feature = "lsag")]
+            RingSignature::LSAG(_) => SchemeId::LSAG,
+            #[cfg(feature = "blsag")]
+            RingSignature::BLSAG(_) => SchemeId::BLSAG,
+            #[cfg(feature = "mlsag")]
+            RingSignature::MLSAG(_) => SchemeId::MLSAG,
+            #[cfg(feature = "clsag")]
+            RingSignature::CLSAG(_) => SchemeId::CLSAG,
+            #[cfg(feature = "dlsag")]
+            RingSignature::DLSAG(_) => SchemeId::DLSAG,
+            #[cfg(feature = "mdlsag")]
+            RingSignature::MDLSAG(_) => SchemeId::MDLSAG,
+        };
+    }
+
+    /// Number of members of the ring, signer included
+    pub fn ring_size(&self) -> usize {
+        return match self {
+            #[cfg(feature = "sag")]
+            RingSignature::SAG(signature) => signature.ring.len(),
+            #[cfg(feature = "lsag")]
+            RingSignature::LSAG(signature) => signature.ring.len(),
+            #[cfg(feature = "blsag")]
+            RingSignature::BLSAG(signature) => signature.ring.len(),
+            #[cfg(feature = "mlsag")]
+            RingSignature::MLSAG(signature) => signature.ring.len(),
+            #[cfg(feature = "clsag")]
+            RingSignature::CLSAG(signature) => signature.ring.len(),
+            #[cfg(feature = "dlsag")]
+            RingSignature::DLSAG(signature) => signature.ring.len(),
+            #[cfg(feature = "mdlsag")]
+            RingSignature::MDLSAG(signature) => signature.ring.len(),
+        };
+    }
+
+    /// Every key image of the signature, none for SAG
+    pub fn key_images(&self) -> Vec<RistrettoPoint> {
+        return match self {
+            #[cfg(feature = "sag")]
+            RingSignature::SAG(_) => Vec::new(),
+            #[cfg(feature = "lsag")]
+            RingSignature::LSAG(signature) => vec![signature.key_image],
+            #[cfg(feature = "blsag")]
+            RingSignature::BLSAG(signature) => vec![signature.key_image],
+            #[cfg(feature = "mlsag")]
+            RingSignature::MLSAG(signature) => signature.key_images.clone(),
+            #[cfg(feature = "clsag")]
+            RingSignature::CLSAG(signature) => signature.key_images.clone(),
+            #[cfg(feature = "dlsag")]
+            RingSignature::DLSAG(signature) => vec![signature.key_image],
+            #[cfg(feature = "mdlsag")]
+            RingSignature::MDLSAG(signature) => signature.key_images.clone(),
+        };
+    }
+
+    /// Verifies the signature with its own scheme
+    pub fn verify<Hash: Digest<OutputSize = U64> + Clone + Default>(self, message: &Vec<u8>) -> bool {
+        return self.verify_ref::<Hash>(message);
+    }
+
+    /// Verifies the signature without allocating more than `budget` besides the signature itself
+    ///
+    /// Where the scheme has a way to verify that allocates less, a CLSAG aggregating each row as
+    /// it reaches it, that is used once the faster one would exceed the budget. Returns
+    /// [`Error::BudgetExceeded`] if even that does not fit.
+    pub fn verify_within<Hash: Digest<OutputSize = U64> + Clone + Default>(
+        &self,
+        message: &Vec<u8>,
+        budget: &MemoryBudget,
+    ) -> Result<bool, Error> {
+        // Irrefutable in builds with CLSAG alone
+        #[cfg(feature = "clsag")]
+        #[allow(irrefutable_let_patterns)]
+        if let RingSignature::CLSAG(signature) = self {
+            let coefficients = signature.key_images.len().saturating_mul(size_of::<Scalar>());
+            let aggregates = signature.ring.len().saturating_mul(size_of::<RistrettoPoint>());
+            if budget.check(coefficients.saturating_add(aggregates)).is_ok() {
+                return Ok(signature.verify_ref::<Hash>(message));
+            }
+            budget.check(coefficients)?;
+            return Ok(signature.verify_ref_streaming::<Hash>(message));
+        }
+        budget.check(self.verify_memory())?;
+        return Ok(self.verify_ref::<Hash>(message));
+    }
+
+    /// Memory `verify` allocates besides the signature itself, other than for CLSAG
+    fn verify_memory(&self) -> usize {
+        return match self {
+            // One buffer of the points of a row
+            #[cfg(feature = "mlsag")]
+            RingSignature::MLSAG(signature) => {
+                signature.ring.first().map_or(0, |row| row.len()).saturating_mul(2 * size_of::<RistrettoPoint>())
+            }
+            #[cfg(feature = "mdlsag")]
+            RingSignature::MDLSAG(signature) => {
+                signature.ring.first().map_or(0, |row| row.len()).saturating_mul(2 * size_of::<RistrettoPoint>())
+            }
+            // The other schemes allocate nothing in proportion to the ring
+            #[allow(unreachable_patterns)]
+            _ => 0,
+        };
+    }
+
+    /// Verifies the signature if it satisfies `policy`, returning the policy violation otherwise,
+    /// so that a valid signature over a ring too small is told apart from an invalid one
+    pub fn verify_with_policy<Hash: Digest<OutputSize = U64> + Clone + Default>(
+        self,
+        message: &Vec<u8>,
+        policy: &VerifyPolicy,
+    ) -> Result<bool, Error> {
+        policy.check(self.scheme(), self.ring_size())?;
+        return Ok(self.verify::<Hash>(message));
+    }
+}
+
+impl Codec for RingSignature {
+    /// The [`SignatureBytes`] encoding that [`parse`] decodes
+    fn to_bytes(&self) -> Vec<u8> {
+        return match self {
+            #[cfg(feature = "sag")]
+            RingSignature::SAG(signature) => signature.to_tagged_bytes(),
+            #[cfg(feature = "lsag")]
+            RingSignature::LSAG(signature) => signature.to_tagged_bytes(),
+            #[cfg(feature = "blsag")]
+            RingSignature::BLSAG(signature) => signature.to_tagged_bytes(),
+            #[cfg(feature = "mlsag")]
+            RingSignature::MLSAG(signature) => signature.to_tagged_bytes(),
+            #[cfg(feature = "clsag")]
+            RingSignature::CLSAG(signature) => signature.to_tagged_bytes(),
+            #[cfg(feature = "dlsag")]
+            RingSignature::DLSAG(signature) => signature.to_tagged_bytes(),
+            #[cfg(feature = "mdlsag")]
+            RingSignature::MDLSAG(signature) => signature.to_tagged_bytes(),
+        };
+    }
+
+    fn decode(bytes: &[u8]) -> Result<RingSignature, Error> {
+        return parse(bytes);
+    }
+}
+
+crate::encoding::display_via_codec!(RingSignature);
+
+#[cfg(feature = "serde")]
+crate::codec::serde_via_codec!(RingSignature);
+
+impl LinkRef for RingSignature {
+    /// Signatures of different schemes, and SAGs, are never linked
+    fn link_ref(&self, other: &RingSignature) -> bool {
+        return match (self, other) {
+            #[cfg(feature = "lsag")]
+            (RingSignature::LSAG(signature_1), RingSignature::LSAG(signature_2)) => signature_1.link_ref(signature_2),
+            #[cfg(feature = "blsag")]
+            (RingSignature::BLSAG(signature_1), RingSignature::BLSAG(signature_2)) => signature_1.link_ref(signature_2),
+            #[cfg(feature = "mlsag")]
+            (RingSignature::MLSAG(signature_1), RingSignature::MLSAG(signature_2)) => signature_1.link_ref(signature_2),
+            #[cfg(feature = "clsag")]
+            (RingSignature::CLSAG(signature_1), RingSignature::CLSAG(signature_2)) => signature_1.link_ref(signature_2),
+            #[cfg(feature = "dlsag")]
+            (RingSignature::DLSAG(signature_1), RingSignature::DLSAG(signature_2)) => signature_1.link_ref(signature_2),
+            #[cfg(feature = "mdlsag")]
+            (RingSignature::MDLSAG(signature_1), RingSignature::MDLSAG(signature_2)) => {
+                signature_1.link_ref(signature_2)
+            }
+            #[allow(unreachable_patterns)]
+            _ => false,
+        };
+    }
+}
+
+impl VerifyRef for RingSignature {
+    fn verify_ref<Hash: Digest<OutputSize = U64> + Clone + Default>(&self, message: &Vec<u8>) -> bool {
+        return match self {
+            #[cfg(feature = "sag")]
+            RingSignature::SAG(signature) => signature.verify_ref::<Hash>(message),
+            #[cfg(feature = "lsag")]
+            RingSignature::LSAG(signature) => signature.verify_ref::<Hash>(message),
+            #[cfg(feature = "blsag")]
+            RingSignature::BLSAG(signature) => signature.verify_ref::<Hash>(message),
+            #[cfg(feature = "mlsag")]
+            RingSignature::MLSAG(signature) => signature.verify_ref::<Hash>(message),
+            #[cfg(feature = "clsag")]
+            RingSignature::CLSAG(signature) => signature.verify_ref::<Hash>(message),
+            #[cfg(feature = "dlsag")]
+            RingSignature::DLSAG(signature) => signature.verify_ref::<Hash>(message),
+            #[cfg(feature = "mdlsag")]
+            RingSignature::MDLSAG(signature) => signature.verify_ref::<Hash>(message),
+        };
+    }
+}
+
+impl TryVerify for RingSignature {
+    fn check_shape(&self) -> Result<(), Error> {
+        return match self {
+            #[cfg(feature = "sag")]
+            RingSignature::SAG(signature) => signature.check_shape(),
+            #[cfg(feature = "lsag")]
+            RingSignature::LSAG(signature) => signature.check_shape(),
+            #[cfg(feature = "blsag")]
+            RingSignature::BLSAG(signature) => signature.check_shape(),
+            #[cfg(feature = "mlsag")]
+            RingSignature::MLSAG(signature) => signature.check_shape(),
+            #[cfg(feature = "clsag")]
+            RingSignature::CLSAG(signature) => signature.check_shape(),
+            #[cfg(feature = "dlsag")]
+            RingSignature::DLSAG(signature) => signature.check_shape(),
+            #[cfg(feature = "mdlsag")]
+            RingSignature::MDLSAG(signature) => signature.check_shape(),
+        };
+    }
+}
+
+/// Verification that does not name the scheme or the hash, so that signatures of different
+/// schemes can be kept and verified together as `Box<dyn RingSignatureVerifier>`
+pub trait RingSignatureVerifier {
+    fn scheme(&self) -> SchemeId;
+    /// Number of members of the ring, signer included
+    fn ring_size(&self) -> usize;
+    fn verify(&self, message: &Vec<u8>) -> bool;
+}
+
+/// A signature together with the hash it is verified with, which makes it a
+/// [`RingSignatureVerifier`]
+pub struct WithHash<S, Hash> {
+    pub signature: S,
+    hash: PhantomData<fn() -> Hash>,
+}
+
+impl<S, Hash> WithHash<S, Hash> {
+    pub fn new(signature: S) -> WithHash<S, Hash> {
+        return WithHash {
+            signature: signature,
+            hash: PhantomData,
+        };
+    }
+}
+
+impl<S: SignatureBytes + ChallengeChain + VerifyRef, Hash: Digest<OutputSize = U64> + Clone + Default>
+    RingSignatureVerifier for WithHash<S, Hash>
+{
+    fn scheme(&self) -> SchemeId {
+        return S::SCHEME;
+    }
+
+    fn ring_size(&self) -> usize {
+        return self.signature.ring_size();
+    }
+
+    fn verify(&self, message: &Vec<u8>) -> bool {
+        return self.signature.verify_ref::<Hash>(message);
+    }
+}
+
+impl<Hash: Digest<OutputSize = U64> + Clone + Default> RingSignatureVerifier for WithHash<RingSignature, Hash> {
+    fn scheme(&self) -> SchemeId {
+        return self.signature.scheme();
+    }
+
+    fn ring_size(&self) -> usize {
+        return self.signature.ring_size();
+    }
+
+    fn verify(&self, message: &Vec<u8>) -> bool {
+        return self.signature.verify_ref::<Hash>(message);
+    }
+}
+
+#[cfg(test)]
+#[cfg(feature = "std")]
+#[cfg(feature = "sign")]
+#[cfg(all(feature = "sag", feature = "mlsag"))]
+mod test {
+    extern crate rand;
+    extern crate sha2;
+
+    use curve25519_dalek::scalar::Scalar;
+    use rand::rngs::OsRng;
+    use sha2::Sha512;
+
+    use super::*;
+    use crate::traits::SignWithRng;
+
+    #[test]
+    fn parse() {
+        let message: Vec<u8> = b"This is the message".to_vec();
+
+        let k: Scalar = Scalar::random(&mut OsRng);
+        let ring: Vec<RistrettoPoint> = (0..2).map(|_| RistrettoPoint::random(&mut OsRng)).collect();
+        let bytes = SAG::sign_with_rng_unchecked::<Sha512, _>(k, ring, 1, &message, &mut OsRng)
+            .unwrap()
+            .to_tagged_bytes();
+        let signature = super::parse(&bytes).unwrap();
+        assert_eq!(signature.scheme(), SchemeId::SAG);
+        assert_eq!(signature.ring_size(), 3);
+        assert!(signature.key_images().is_empty());
+        assert_eq!(signature.to_bytes(), bytes);
+        assert!(signature.clone().verify::<Sha512>(&message));
+        let policy = VerifyPolicy::default();
+        let too_small = signature.clone().verify_with_policy::<Sha512>(&message, &policy);
+        assert_eq!(too_small, Err(Error::RingTooSmall { size: 3, minimum: 11 }));
+        let policy = VerifyPolicy {
+            min_ring: 3,
+            ..VerifyPolicy::default()
+        };
+        assert_eq!(signature.verify_with_policy::<Sha512>(&message, &policy), Ok(true));
+
+        let ks: Vec<Scalar> = (0..2).map(|_| Scalar::random(&mut OsRng)).collect();
+        let ring: Vec<Vec<RistrettoPoint>> =
+            (0..2).map(|_| (0..2).map(|_| RistrettoPoint::random(&mut OsRng)).collect()).collect();
+        let mlsag = MLSAG::sign_with_rng_unchecked::<Sha512, _>(ks, ring, 1, &message, &mut OsRng).unwrap();
+        let signature = super::parse(&mlsag.to_tagged_bytes()).unwrap();
+        assert_eq!(signature.key_images(), mlsag.key_images);
+        assert!(signature.verify::<Sha512>(&message));
+
+        // The body of an MLSAG does not decode as another scheme. Which field fails first
+        // depends on the random responses.
+        let mut retagged = mlsag.to_tagged_bytes();
+        retagged[0] = SchemeId::SAG as u8;
+        let error = super::parse(&retagged).err();
+        assert!(error.is_some() && error != Some(Error::UnknownScheme { tag: SchemeId::SAG as u8 }));
+        retagged[0] = 0;
+        assert!(super::parse(&retagged).err() == Some(Error::UnknownScheme { tag: 0 }));
+        assert!(super::parse(&[]).err() == Some(Error::Truncated));
+    }
+
+    #[test]
+    fn link() {
+        let message: Vec<u8> = b"This is the message".to_vec();
+        let ks: Vec<Scalar> = (0..2).map(|_| Scalar::random(&mut OsRng)).collect();
+        let decoys = || -> Vec<Vec<RistrettoPoint>> {
+            (0..2).map(|_| (0..2).map(|_| RistrettoPoint::random(&mut OsRng)).collect()).collect()
+        };
+        let signature_1 =
+            MLSAG::sign_with_rng_unchecked::<Sha512, _>(ks.clone(), decoys(), 0, &message, &mut OsRng).unwrap();
+        let signature_2 = MLSAG::sign_with_rng_unchecked::<Sha512, _>(ks, decoys(), 2, &message, &mut OsRng).unwrap();
+        let signature_1 = RingSignature::MLSAG(signature_1);
+        let signature_2 = RingSignature::MLSAG(signature_2);
+        assert!(signature_1.link_ref(&signature_2));
+
+        // A SAG has no key image to link
+        let k: Scalar = Scalar::random(&mut OsRng);
+        let ring: Vec<RistrettoPoint> = (0..2).map(|_| RistrettoPoint::random(&mut OsRng)).collect();
+        let sag =
+            RingSignature::SAG(SAG::sign_with_rng_unchecked::<Sha512, _>(k, ring, 1, &message, &mut OsRng).unwrap());
+        assert!(!signature_1.link_ref(&sag));
+        assert!(!sag.link_ref(&sag));
+
+        // The encoding keeps the scheme
+        let decoded = RingSignature::from_bytes(&signature_2.to_bytes()).unwrap();
+        assert_eq!(decoded.scheme(), SchemeId::MLSAG);
+        assert!(signature_1.link_ref(&decoded));
+    }
+
+    #[test]
+    fn parse_does_not_panic() {
+        use crate::testing::corpus::generate_corpus;
+
+        let message: Vec<u8> = b"This is the message".to_vec();
+        for scheme in (1..=7u8).filter_map(SchemeId::from_u8) {
+            let corpus = match generate_corpus::<Sha512>(scheme, 2, 1, 0) {
+                Ok(corpus) => corpus,
+                Err(_) => continue,
+            };
+            let bytes = &corpus.entries[0].signature;
+            // Every truncation, and bytes flipped throughout, either fail to decode or decode to a
+            // signature that verify can handle
+            for len in 0..bytes.len() {
+                assert!(super::parse(&bytes[..len]).is_err());
+            }
+            for position in (0..bytes.len()).step_by(13) {
+                let mut flipped = bytes.clone();
+                flipped[position] ^= 0xff;
+                if let Ok(signature) = super::parse(&flipped) {
+                    signature.verify::<Sha512>(&message);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn verifier() {
+        extern crate blake2;
+        use alloc::boxed::Box;
+        use blake2::Blake2b512;
+
+        let message: Vec<u8> = b"This is the message".to_vec();
+        let k: Scalar = Scalar::random(&mut OsRng);
+        let ring: Vec<RistrettoPoint> = (0..2).map(|_| RistrettoPoint::random(&mut OsRng)).collect();
+        let sag = SAG::sign_with_rng_unchecked::<Sha512, _>(k, ring, 1, &message, &mut OsRng).unwrap();
+        let ks: Vec<Scalar> = (0..2).map(|_| Scalar::random(&mut OsRng)).collect();
+        let ring: Vec<Vec<RistrettoPoint>> =
+            (0..3).map(|_| (0..2).map(|_| RistrettoPoint::random(&mut OsRng)).collect()).collect();
+        let mlsag = MLSAG::sign_with_rng_unchecked::<Blake2b512, _>(ks, ring, 0, &message, &mut OsRng).unwrap();
+        let parsed = super::parse(&sag.to_tagged_bytes()).unwrap();
+
+        // Each signature brings its own scheme and hash
+        let verifiers: Vec<Box<dyn RingSignatureVerifier>> = vec![
+            Box::new(WithHash::<SAG, Sha512>::new(sag.clone())),
+            Box::new(WithHash::<MLSAG, Blake2b512>::new(mlsag.clone())),
+            Box::new(WithHash::<RingSignature, Sha512>::new(parsed)),
+        ];
+        let schemes: Vec<SchemeId> = verifiers.iter().map(|verifier| verifier.scheme()).collect();
+        assert_eq!(schemes, vec![SchemeId::SAG, SchemeId::MLSAG, SchemeId::SAG]);
+        let ring_sizes: Vec<usize> = verifiers.iter().map(|verifier| verifier.ring_size()).collect();
+        assert_eq!(ring_sizes, vec![3, 4, 3]);
+        assert!(verifiers.iter().all(|verifier| verifier.verify(&message)));
+        assert!(!verifiers.iter().any(|verifier| verifier.verify(&b"Another message".to_vec())));
+
+        // The wrong hash does not verify
+        assert!(!WithHash::<MLSAG, Sha512>::new(mlsag).verify(&message));
+    }
+}
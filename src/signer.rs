@@ -0,0 +1,163 @@
+//! Adapters for the RustCrypto `signature` traits
+//!
+//! Generic code written against `signature::Signer<S>` and `signature::Verifier<S>` knows nothing
+//! of rings. With the `signature2` feature, a [`RingSigner`] holds a private key together with the
+//! decoys to hide it among and signs as `Signer<SAG>` or `Signer<BLSAG>`, and a [`RingVerifier`]
+//! holds the ring signatures are expected to be made over and verifies as `Verifier<SAG>` or
+//! `Verifier<BLSAG>`.
+//!
+//! A ring signature carries its own ring, and verifying it only shows that some member of that ring
+//! signed. [`RingVerifier`] therefore also checks that the ring of the signature has the members it
+//! was given, in any order, so that a signature over a ring of the signer's choosing is rejected.
+
+use crate::prelude::*;
+
+use core::marker::PhantomData;
+
+use curve25519_dalek::ristretto::RistrettoPoint;
+use curve25519_dalek::scalar::Scalar;
+use digest::generic_array::typenum::U64;
+use digest::Digest;
+use rand_core::{CryptoRng, RngCore};
+
+#[cfg(feature = "blsag")]
+use crate::blsag::BLSAG;
+#[cfg(feature = "sag")]
+use crate::sag::SAG;
+use crate::traits::{TrySign, VerifyRef};
+
+/// A private key and the decoys it signs with, see [`crate::traits::SignWithRng::sign_with_rng`]
+pub struct RingSigner<Hash, CSPRNG> {
+    k: Scalar,
+    decoys: Vec<RistrettoPoint>,
+    secret_index: usize,
+    types: PhantomData<fn() -> (Hash, CSPRNG)>,
+}
+
+impl<Hash, CSPRNG> RingSigner<Hash, CSPRNG> {
+    /// Signatures hide `k` among `decoys`, at position `secret_index` of the ring. Signing fails
+    /// as `TrySign::try_sign` does, including for fewer decoys than the default minimum ring size
+    pub fn new(k: Scalar, decoys: Vec<RistrettoPoint>, secret_index: usize) -> RingSigner<Hash, CSPRNG> {
+        return RingSigner {
+            k: k,
+            decoys: decoys,
+            secret_index: secret_index,
+            types: PhantomData,
+        };
+    }
+}
+
+/// Verifies signatures made over a ring with the given members
+pub struct RingVerifier<Hash> {
+    ring: Vec<[u8; 32]>,
+    hash: PhantomData<fn() -> Hash>,
+}
+
+/// The compressed encodings of `ring`, sorted
+fn sorted(ring: &[RistrettoPoint]) -> Vec<[u8; 32]> {
+    let mut encodings: Vec<[u8; 32]> = ring.iter().map(|member| member.compress().to_bytes()).collect();
+    encodings.sort_unstable();
+    return encodings;
+}
+
+impl<Hash> RingVerifier<Hash> {
+    /// `ring` is every member, signer included
+    pub fn new(ring: &[RistrettoPoint]) -> RingVerifier<Hash> {
+        return RingVerifier {
+            ring: sorted(ring),
+            hash: PhantomData,
+        };
+    }
+
+    /// Whether `ring` has the expected members, in any order
+    pub fn expects(&self, ring: &[RistrettoPoint]) -> bool {
+        return sorted(ring) == self.ring;
+    }
+}
+
+#[cfg(feature = "sag")]
+impl<Hash: Digest<OutputSize = U64> + Clone + Default, CSPRNG: CryptoRng + RngCore + Default> signature2::Signer<SAG>
+    for RingSigner<Hash, CSPRNG>
+{
+    fn try_sign(&self, msg: &[u8]) -> Result<SAG, signature2::Error> {
+        return SAG::try_sign::<Hash, CSPRNG>(self.k, self.decoys.clone(), self.secret_index, &msg.to_vec())
+            .map_err(|_| signature2::Error::new());
+    }
+}
+
+#[cfg(feature = "blsag")]
+impl<Hash: Digest<OutputSize = U64> + Clone + Default, CSPRNG: CryptoRng + RngCore + Default>
+    signature2::Signer<BLSAG> for RingSigner<Hash, CSPRNG>
+{
+    fn try_sign(&self, msg: &[u8]) -> Result<BLSAG, signature2::Error> {
+        return BLSAG::try_sign::<Hash, CSPRNG>(self.k, self.decoys.clone(), self.secret_index, &msg.to_vec())
+            .map_err(|_| signature2::Error::new());
+    }
+}
+
+#[cfg(feature = "sag")]
+impl<Hash: Digest<OutputSize = U64> + Clone + Default> signature2::Verifier<SAG> for RingVerifier<Hash> {
+    fn verify(&self, msg: &[u8], signature: &SAG) -> Result<(), signature2::Error> {
+        if !self.expects(&signature.ring) || !signature.verify_ref::<Hash>(&msg.to_vec()) {
+            return Err(signature2::Error::new());
+        }
+        return Ok(());
+    }
+}
+
+#[cfg(feature = "blsag")]
+impl<Hash: Digest<OutputSize = U64> + Clone + Default> signature2::Verifier<BLSAG> for RingVerifier<Hash> {
+    fn verify(&self, msg: &[u8], signature: &BLSAG) -> Result<(), signature2::Error> {
+        if !self.expects(&signature.ring) || !signature.verify_ref::<Hash>(&msg.to_vec()) {
+            return Err(signature2::Error::new());
+        }
+        return Ok(());
+    }
+}
+
+#[cfg(test)]
+#[cfg(feature = "std")]
+#[cfg(all(feature = "sag", feature = "blsag"))]
+mod test {
+    extern crate rand;
+    extern crate sha2;
+
+    use rand::rngs::OsRng;
+    use sha2::Sha512;
+    use signature2::{Signer, Verifier};
+
+    use super::*;
+
+    /// Generic code that only knows the RustCrypto traits
+    fn round_trip<S, T: Signer<S>, V: Verifier<S>>(signer: &T, verifier: &V) -> (bool, bool) {
+        let signature: S = signer.sign(b"This is the message");
+        return (
+            verifier.verify(b"This is the message", &signature).is_ok(),
+            verifier.verify(b"This is another message", &signature).is_ok(),
+        );
+    }
+
+    #[test]
+    fn signer() {
+        let k: Scalar = Scalar::random(&mut OsRng);
+        let decoys: Vec<RistrettoPoint> = (0..10).map(|_| RistrettoPoint::random(&mut OsRng)).collect();
+        let signer = RingSigner::<Sha512, OsRng>::new(k, decoys.clone(), 1);
+        let mut ring = decoys.clone();
+        ring.push(RistrettoPoint::mul_base(&k));
+        let verifier = RingVerifier::<Sha512>::new(&ring);
+
+        assert_eq!(round_trip::<SAG, _, _>(&signer, &verifier), (true, false));
+        assert_eq!(round_trip::<BLSAG, _, _>(&signer, &verifier), (true, false));
+
+        // A valid signature over another ring is rejected
+        let mut other_decoys = decoys.clone();
+        other_decoys[0] = RistrettoPoint::random(&mut OsRng);
+        let elsewhere = RingSigner::<Sha512, OsRng>::new(k, other_decoys, 0);
+        assert_eq!(round_trip::<BLSAG, _, _>(&elsewhere, &verifier), (false, false));
+
+        let out_of_range = RingSigner::<Sha512, OsRng>::new(k, decoys.clone(), 11);
+        assert!(Signer::<SAG>::try_sign(&out_of_range, b"This is the message").is_err());
+        let small = RingSigner::<Sha512, OsRng>::new(k, decoys[..2].to_vec(), 0);
+        assert!(Signer::<BLSAG>::try_sign(&small, b"This is the message").is_err());
+    }
+}
@@ -0,0 +1,75 @@
+//! Streaming verification
+//!
+//! Verifies a sequence of signatures lazily, one per call to `next`, so that a pipeline can
+//! interleave reading signatures with verifying them instead of buffering a whole block first.
+
+use crate::prelude::*;
+
+use digest::generic_array::typenum::U64;
+use digest::Digest;
+
+use crate::traits::VerifyRef;
+
+/// The signature at `index` in the stream did not verify
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct VerifyError {
+    pub index: usize,
+}
+
+/// Verifies each `(signature, message)` pair of `signatures` as the returned iterator is advanced
+///
+/// Nothing is pulled from `signatures` until the result is iterated, and exactly one pair is
+/// pulled per result, so a slow consumer naturally slows down the producer.
+pub fn verify_stream<'a, Hash, S, I>(signatures: I) -> impl Iterator<Item = Result<(), VerifyError>> + 'a
+where
+    Hash: Digest<OutputSize = U64> + Clone + Default,
+    S: VerifyRef + 'a,
+    I: IntoIterator<Item = (S, &'a Vec<u8>)>,
+    I::IntoIter: 'a,
+{
+    return signatures
+        .into_iter()
+        .enumerate()
+        .map(|(index, (signature, message))| {
+            if signature.verify_ref::<Hash>(message) {
+                return Ok(());
+            }
+            return Err(VerifyError { index: index });
+        });
+}
+
+#[cfg(test)]
+#[cfg(feature = "std")]
+#[cfg(feature = "sign")]
+#[cfg(feature = "sag")]
+mod test {
+    extern crate rand;
+    extern crate sha2;
+
+    use curve25519_dalek::ristretto::RistrettoPoint;
+    use curve25519_dalek::scalar::Scalar;
+    use rand::rngs::OsRng;
+    use sha2::Sha512;
+
+    use super::*;
+    use crate::sag::SAG;
+    use crate::traits::SignWithRng;
+
+    #[test]
+    fn verify_stream() {
+        let mut csprng = OsRng;
+        let k: Scalar = Scalar::random(&mut csprng);
+        let ring: Vec<RistrettoPoint> = (0..2).map(|_| RistrettoPoint::random(&mut csprng)).collect();
+        let message: Vec<u8> = b"This is the message".to_vec();
+        let another_message: Vec<u8> = b"This is another message".to_vec();
+
+        let signatures = (0..3).map(|i| {
+            let signature =
+                SAG::sign_with_rng_unchecked::<Sha512, _>(k, ring.clone(), 1, &message, &mut OsRng).unwrap();
+            // The second signature is checked against the wrong message
+            return (signature, if i == 1 { &another_message } else { &message });
+        });
+        let results: Vec<Result<(), VerifyError>> = super::verify_stream::<Sha512, _, _>(signatures).collect();
+        assert_eq!(results, vec![Ok(()), Err(VerifyError { index: 1 }), Ok(())]);
+    }
+}
@@ -0,0 +1,152 @@
+//! Signatures bound to their hash function
+//!
+//! The hash function of a signature is a type parameter of `sign` and `verify_ref` only, so
+//! nothing stops a signature made with SHA-512 from being verified with BLAKE2b, which merely
+//! returns false. A [`Bound`] carries the hash function in its type, e.g.
+//! `Bound<BLSAG, Sha512>`, and verifies with that one. Code that takes a `Bound<BLSAG, Sha512>`
+//! can not be handed a signature bound to another hash function.
+//!
+//! ```ignore
+//! type Ballot = Bound<BLSAG, Sha512>;
+//!
+//! let ballot = Ballot::sign::<Scalar, Vec<RistrettoPoint>, OsRng>(k, ring, 1, &message)?;
+//! assert!(ballot.verify(&message));
+//! ```
+
+use crate::prelude::*;
+
+use core::marker::PhantomData;
+
+use digest::generic_array::typenum::U64;
+use digest::Digest;
+#[cfg(feature = "sign")]
+use rand_core::{CryptoRng, RngCore};
+
+use crate::codec::Codec;
+use crate::error::Error;
+use crate::traits::VerifyRef;
+#[cfg(feature = "sign")]
+use crate::traits::{Decoys, SignWithRng};
+
+/// A signature of the scheme `S` made with the hash function `Hash`
+pub struct Bound<S, Hash> {
+    signature: S,
+    // A `fn` so that the binding is `Send` and `Sync` whatever `Hash` is
+    hash: PhantomData<fn() -> Hash>,
+}
+
+impl<S: Clone, Hash> Clone for Bound<S, Hash> {
+    fn clone(&self) -> Bound<S, Hash> {
+        return Bound::new(self.signature.clone());
+    }
+}
+
+impl<S, Hash> Bound<S, Hash> {
+    /// Binds a signature made elsewhere, e.g. decoded from a peer, to `Hash`. This is where the
+    /// caller vouches for the hash function, every use after is checked by the compiler
+    pub fn new(signature: S) -> Bound<S, Hash> {
+        return Bound {
+            signature: signature,
+            hash: PhantomData,
+        };
+    }
+
+    pub fn signature(&self) -> &S {
+        return &self.signature;
+    }
+
+    pub fn into_inner(self) -> S {
+        return self.signature;
+    }
+}
+
+impl<S: VerifyRef, Hash: Digest<OutputSize = U64> + Clone + Default> Bound<S, Hash> {
+    /// As [`crate::traits::TrySign::try_sign`] with `Hash`
+    #[cfg(feature = "sign")]
+    pub fn sign<PrivateKey, Ring, CSPRNG: CryptoRng + RngCore + Default>(
+        k: PrivateKey,
+        ring: Ring,
+        secret_index: usize,
+        message: &Vec<u8>,
+    ) -> Result<Bound<S, Hash>, Error>
+    where
+        S: SignWithRng<PrivateKey, Ring>,
+        Ring: Decoys,
+    {
+        let mut csprng = CSPRNG::default();
+        return Ok(Bound::new(S::sign_with_rng::<Hash, CSPRNG>(k, ring, secret_index, message, &mut csprng)?));
+    }
+
+    /// As [`SignWithRng::sign_with_rng`] with `Hash`
+    #[cfg(feature = "sign")]
+    pub fn sign_with_rng<PrivateKey, Ring, CSPRNG: CryptoRng + RngCore>(
+        k: PrivateKey,
+        ring: Ring,
+        secret_index: usize,
+        message: &Vec<u8>,
+        csprng: &mut CSPRNG,
+    ) -> Result<Bound<S, Hash>, Error>
+    where
+        S: SignWithRng<PrivateKey, Ring>,
+        Ring: Decoys,
+    {
+        return Ok(Bound::new(S::sign_with_rng::<Hash, CSPRNG>(k, ring, secret_index, message, csprng)?));
+    }
+
+    /// As [`VerifyRef::verify_ref`] with `Hash`
+    pub fn verify(&self, message: &Vec<u8>) -> bool {
+        return self.signature.verify_ref::<Hash>(message);
+    }
+}
+
+/// The encoding of the signature. The hash function is not encoded, decoding binds to `Hash` as
+/// [`Bound::new`] does
+impl<S: Codec, Hash> Codec for Bound<S, Hash> {
+    fn to_bytes(&self) -> Vec<u8> {
+        return self.signature.to_bytes();
+    }
+
+    fn decode(bytes: &[u8]) -> Result<Bound<S, Hash>, Error> {
+        return Ok(Bound::new(S::decode(bytes)?));
+    }
+}
+
+#[cfg(test)]
+#[cfg(feature = "std")]
+#[cfg(feature = "sign")]
+#[cfg(feature = "blsag")]
+mod test {
+    extern crate blake2;
+    extern crate rand;
+    extern crate sha2;
+
+    use blake2::Blake2b512;
+    use curve25519_dalek::ristretto::RistrettoPoint;
+    use curve25519_dalek::scalar::Scalar;
+    use rand::rngs::OsRng;
+    use sha2::Sha512;
+
+    use super::*;
+    use crate::blsag::BLSAG;
+
+    #[test]
+    fn bound() {
+        let k: Scalar = Scalar::random(&mut OsRng);
+        let ring: Vec<RistrettoPoint> = (0..10).map(|_| RistrettoPoint::random(&mut OsRng)).collect();
+        let message: Vec<u8> = b"This is the message".to_vec();
+
+        let signature = Bound::<BLSAG, Sha512>::sign::<_, _, OsRng>(k, ring.clone(), 1, &message).unwrap();
+        assert!(signature.verify(&message));
+        assert!(!signature.verify(&b"Another message".to_vec()));
+
+        // Decoding binds to the hash function of the type decoded into
+        let decoded = Bound::<BLSAG, Sha512>::decode(&signature.to_bytes()).unwrap();
+        assert!(decoded.verify(&message));
+        let misbound = Bound::<BLSAG, Blake2b512>::decode(&signature.to_bytes()).unwrap();
+        assert!(!misbound.verify(&message));
+
+        let signature = Bound::<BLSAG, Blake2b512>::sign_with_rng(k, ring, 1, &message, &mut OsRng).unwrap();
+        assert!(signature.signature().verify_ref::<Blake2b512>(&message));
+        assert!(signature.into_inner().verify_ref::<Blake2b512>(&message));
+    }
+}
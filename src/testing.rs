@@ -0,0 +1,40 @@
+//! Helpers for testing code built on top of this library
+//!
+//! These are meant for downstream test suites, fuzzers and property tests, and panic on failure
+//! like the assertions they are built from.
+
+#[cfg(feature = "sign")]
+#[cfg(any(
+    feature = "sag",
+    feature = "lsag",
+    feature = "blsag",
+    feature = "mlsag",
+    feature = "clsag",
+    feature = "dlsag",
+    feature = "mdlsag"
+))]
+pub mod corpus;
+#[cfg(any(
+    feature = "sag",
+    feature = "lsag",
+    feature = "blsag",
+    feature = "mlsag",
+    feature = "clsag",
+    feature = "dlsag",
+    feature = "mdlsag"
+))]
+pub mod mutate;
+#[cfg(feature = "test-vectors")]
+pub mod vectors;
+
+#[cfg(feature = "sign")]
+#[cfg(any(
+    feature = "sag",
+    feature = "lsag",
+    feature = "blsag",
+    feature = "mlsag",
+    feature = "clsag",
+    feature = "dlsag",
+    feature = "mdlsag"
+))]
+pub use corpus::generate_corpus;
@@ -0,0 +1,316 @@
+//! Deterministic signature corpora
+//!
+//! Load tests of verification pipelines need many realistic signatures, some of which must be
+//! rejected. [`generate_corpus`] produces them from a seed, so that every run of a load test sees
+//! the same inputs: even entries are valid signatures, odd entries are signatures with one of
+//! their [`Mutation`]s applied. The signatures are in the [`SignatureBytes`] encoding that
+//! [`crate::parse`] decodes, and the [`Corpus`] itself has a canonical encoding to be written to
+//! disk with.
+//!
+//! Keys and nonces are derived from the seed, so anyone who knows it knows every private key. The
+//! corpus is for testing only.
+
+use crate::prelude::*;
+
+use core::marker::PhantomData;
+
+use curve25519_dalek::ristretto::RistrettoPoint;
+use curve25519_dalek::scalar::Scalar;
+use digest::generic_array::typenum::U64;
+use digest::Digest;
+use rand_core::{impls, CryptoRng, Error as RngError, RngCore};
+
+#[cfg(feature = "blsag")]
+use crate::blsag::BLSAG;
+#[cfg(feature = "clsag")]
+use crate::clsag::CLSAG;
+use crate::codec::{self, Codec, Reader};
+#[cfg(feature = "dlsag")]
+use crate::dlsag::DLSAG;
+use crate::error::Error;
+use crate::header::SchemeId;
+#[cfg(feature = "lsag")]
+use crate::lsag::LSAG;
+#[cfg(feature = "mdlsag")]
+use crate::mdlsag::MDLSAG;
+#[cfg(feature = "mlsag")]
+use crate::mlsag::MLSAG;
+#[cfg(feature = "sag")]
+use crate::sag::SAG;
+use crate::signature::SignatureBytes;
+use crate::testing::mutate::{Mutate, Mutation};
+use crate::traits::SignWithRng;
+
+/// Columns of every ring member for MLSAG, CLSAG and MDLSAG
+#[cfg(any(feature = "mlsag", feature = "clsag", feature = "mdlsag"))]
+const COLUMNS: usize = 2;
+
+/// One message and a signature of it
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CorpusEntry {
+    pub message: Vec<u8>,
+    /// The [`SignatureBytes`] encoding of the signature
+    pub signature: Vec<u8>,
+    /// Whether the signature verifies
+    pub valid: bool,
+}
+
+/// The entries produced by [`generate_corpus`]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Corpus {
+    pub entries: Vec<CorpusEntry>,
+}
+
+/// A generator hashing a seed with a counter, so the same seed always gives the same output
+///
+/// The nonces of every signature come from `Default`, which starts from the same fixed seed. That
+/// is only acceptable because the keys of a corpus are not secret either.
+pub(crate) struct SeededRng<Hash> {
+    seed: [u8; 64],
+    counter: u64,
+    hash: PhantomData<Hash>,
+}
+
+impl<Hash: Digest<OutputSize = U64> + Default> SeededRng<Hash> {
+    pub(crate) fn new(domain: &[u8], seed: u64) -> SeededRng<Hash> {
+        let mut bytes = [0u8; 64];
+        bytes.copy_from_slice(
+            &Hash::default()
+                .chain_update(domain)
+                .chain_update(seed.to_le_bytes())
+                .finalize(),
+        );
+        return SeededRng {
+            seed: bytes,
+            counter: 0,
+            hash: PhantomData,
+        };
+    }
+
+    pub(crate) fn scalar(&mut self) -> Scalar {
+        return Scalar::random(self);
+    }
+
+    pub(crate) fn point(&mut self) -> RistrettoPoint {
+        return RistrettoPoint::random(self);
+    }
+
+    #[cfg(any(feature = "dlsag", feature = "mdlsag"))]
+    fn triple(&mut self) -> (RistrettoPoint, RistrettoPoint, Scalar) {
+        return (self.point(), self.point(), self.scalar());
+    }
+}
+
+impl<Hash: Digest<OutputSize = U64> + Default> Default for SeededRng<Hash> {
+    fn default() -> SeededRng<Hash> {
+        return SeededRng::new(b"nazgul/corpus/nonces", 0);
+    }
+}
+
+impl<Hash: Digest<OutputSize = U64> + Default> RngCore for SeededRng<Hash> {
+    fn next_u32(&mut self) -> u32 {
+        return impls::next_u32_via_fill(self);
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        return impls::next_u64_via_fill(self);
+    }
+
+    fn fill_bytes(&mut self, dest: &mut [u8]) {
+        for chunk in dest.chunks_mut(64) {
+            let block = Hash::default()
+                .chain_update(self.seed)
+                .chain_update(self.counter.to_le_bytes())
+                .finalize();
+            self.counter += 1;
+            chunk.copy_from_slice(&block[..chunk.len()]);
+        }
+    }
+
+    fn try_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), RngError> {
+        self.fill_bytes(dest);
+        return Ok(());
+    }
+}
+
+impl<Hash> CryptoRng for SeededRng<Hash> {}
+
+/// Signs `message`, mutating the signature if `index` is odd, and appends the entry
+fn push<
+    Hash: Digest<OutputSize = U64> + Clone + Default,
+    PrivateKey,
+    Ring,
+    S: SignWithRng<PrivateKey, Ring> + Mutate + SignatureBytes,
+>(
+    entries: &mut Vec<CorpusEntry>,
+    k: PrivateKey,
+    ring: Ring,
+    secret_index: usize,
+    message: Vec<u8>,
+) -> Result<(), Error> {
+    let index = entries.len();
+    let signature =
+        S::sign_with_rng_unchecked::<Hash, _>(k, ring, secret_index, &message, &mut SeededRng::<Hash>::default())?;
+    let valid = index & 1 == 0;
+    let signature = if valid {
+        signature
+    } else {
+        let mutations: Vec<Mutation> = signature.mutations();
+        signature.mutate(mutations[(index / 2) % mutations.len()]).unwrap()
+    };
+    entries.push(CorpusEntry {
+        message: message,
+        signature: signature.to_tagged_bytes(),
+        valid: valid,
+    });
+    return Ok(());
+}
+
+/// `count` signatures of `scheme` over rings of `ring_size` members (the signer included),
+/// every other one invalid, derived from `seed`
+pub fn generate_corpus<Hash: Digest<OutputSize = U64> + Clone + Default>(
+    scheme: SchemeId,
+    ring_size: usize,
+    count: usize,
+    seed: u64,
+) -> Result<Corpus, Error> {
+    if ring_size < 2 {
+        return Err(Error::RingTooSmall {
+            size: ring_size,
+            minimum: 2,
+        });
+    }
+    let mut rng = SeededRng::<Hash>::new(b"nazgul/corpus", seed);
+    let mut entries: Vec<CorpusEntry> = Vec::with_capacity(count);
+    let n = ring_size - 1;
+    for index in 0..count {
+        let message: Vec<u8> = format!("corpus {} message {}", seed, index).into_bytes();
+        let secret_index = rng.next_u32() as usize % ring_size;
+        match scheme {
+            #[cfg(feature = "sag")]
+            SchemeId::SAG => {
+                let ring: Vec<RistrettoPoint> = (0..n).map(|_| rng.point()).collect();
+                push::<Hash, _, _, SAG>(&mut entries, rng.scalar(), ring, secret_index, message)?;
+            }
+            #[cfg(feature = "lsag")]
+            SchemeId::LSAG => {
+                let ring: Vec<RistrettoPoint> = (0..n).map(|_| rng.point()).collect();
+                push::<Hash, _, _, LSAG>(&mut entries, rng.scalar(), ring, secret_index, message)?;
+            }
+            #[cfg(feature = "blsag")]
+            SchemeId::BLSAG => {
+                let ring: Vec<RistrettoPoint> = (0..n).map(|_| rng.point()).collect();
+                push::<Hash, _, _, BLSAG>(&mut entries, rng.scalar(), ring, secret_index, message)?;
+            }
+            #[cfg(feature = "mlsag")]
+            SchemeId::MLSAG => {
+                let ks: Vec<Scalar> = (0..COLUMNS).map(|_| rng.scalar()).collect();
+                let ring: Vec<Vec<RistrettoPoint>> =
+                    (0..n).map(|_| (0..COLUMNS).map(|_| rng.point()).collect()).collect();
+                push::<Hash, _, _, MLSAG>(&mut entries, ks, ring, secret_index, message)?;
+            }
+            #[cfg(feature = "clsag")]
+            SchemeId::CLSAG => {
+                let ks: Vec<Scalar> = (0..COLUMNS).map(|_| rng.scalar()).collect();
+                let ring: Vec<Vec<RistrettoPoint>> =
+                    (0..n).map(|_| (0..COLUMNS).map(|_| rng.point()).collect()).collect();
+                push::<Hash, _, _, CLSAG>(&mut entries, ks, ring, secret_index, message)?;
+            }
+            #[cfg(feature = "dlsag")]
+            SchemeId::DLSAG => {
+                let k: (Scalar, RistrettoPoint, Scalar) = (rng.scalar(), rng.point(), rng.scalar());
+                let ring: Vec<(RistrettoPoint, RistrettoPoint, Scalar)> = (0..n).map(|_| rng.triple()).collect();
+                push::<Hash, _, _, DLSAG>(&mut entries, k, ring, secret_index, message)?;
+            }
+            #[cfg(feature = "mdlsag")]
+            SchemeId::MDLSAG => {
+                let ks: Vec<(Scalar, RistrettoPoint, Scalar)> = (0..COLUMNS)
+                    .map(|_| (rng.scalar(), rng.point(), rng.scalar()))
+                    .collect();
+                let ring: Vec<Vec<(RistrettoPoint, RistrettoPoint, Scalar)>> =
+                    (0..n).map(|_| (0..COLUMNS).map(|_| rng.triple()).collect()).collect();
+                push::<Hash, _, _, MDLSAG>(&mut entries, ks, ring, secret_index, message)?;
+            }
+            #[allow(unreachable_patterns)]
+            _ => return Err(Error::UnknownScheme { tag: scheme as u8 }),
+        }
+    }
+    return Ok(Corpus { entries: entries });
+}
+
+impl Codec for Corpus {
+    /// The number of entries, then for each its message and signature, both prefixed by their
+    /// length, and whether it is valid
+    fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes: Vec<u8> = Vec::new();
+        codec::write_u32(&mut bytes, self.entries.len());
+        for entry in &self.entries {
+            codec::write_u32(&mut bytes, entry.message.len());
+            bytes.extend_from_slice(&entry.message);
+            codec::write_u32(&mut bytes, entry.signature.len());
+            bytes.extend_from_slice(&entry.signature);
+            codec::write_bool(&mut bytes, entry.valid);
+        }
+        return bytes;
+    }
+
+    /// Decodes a corpus, returning the [`Error`] describing why if `bytes` is not a canonical
+    /// encoding. The signatures are not decoded, only [`crate::parse`] does that
+    fn decode(bytes: &[u8]) -> Result<Corpus, Error> {
+        let mut reader = Reader::new(bytes);
+        // Every entry takes at least two lengths and a flag
+        let n = reader.read_len(9)?;
+        let mut entries: Vec<CorpusEntry> = Vec::with_capacity(n);
+        for _ in 0..n {
+            let message_len = reader.read_len(1)?;
+            let message = reader.read_bytes(message_len)?.to_vec();
+            let signature_len = reader.read_len(1)?;
+            let signature = reader.read_bytes(signature_len)?.to_vec();
+            let valid = reader.read_bool()?;
+            entries.push(CorpusEntry {
+                message: message,
+                signature: signature,
+                valid: valid,
+            });
+        }
+        reader.finish()?;
+        return Ok(Corpus { entries: entries });
+    }
+}
+
+#[cfg(feature = "serde")]
+codec::serde_via_codec!(Corpus);
+
+#[cfg(test)]
+#[cfg(feature = "std")]
+#[cfg(all(feature = "blsag", feature = "mdlsag"))]
+mod test {
+    extern crate sha2;
+
+    use sha2::Sha512;
+
+    use super::*;
+
+    #[test]
+    fn generate_corpus() {
+        for scheme in [SchemeId::BLSAG, SchemeId::MDLSAG].iter() {
+            let corpus = super::generate_corpus::<Sha512>(*scheme, 3, 4, 7).unwrap();
+            assert_eq!(corpus.entries.len(), 4);
+            for entry in &corpus.entries {
+                let signature = crate::parse(&entry.signature).unwrap();
+                assert_eq!(signature.scheme(), *scheme);
+                assert_eq!(signature.ring_size(), 3);
+                assert_eq!(signature.verify::<Sha512>(&entry.message), entry.valid);
+            }
+            assert_eq!(corpus.entries.iter().filter(|entry| entry.valid).count(), 2);
+
+            // The same seed gives the same corpus, another seed another one
+            assert!(super::generate_corpus::<Sha512>(*scheme, 3, 4, 7).unwrap() == corpus);
+            assert!(super::generate_corpus::<Sha512>(*scheme, 3, 4, 8).unwrap() != corpus);
+            assert!(Corpus::from_bytes(&corpus.to_bytes()).unwrap() == corpus);
+        }
+
+        let too_small = super::generate_corpus::<Sha512>(SchemeId::SAG, 1, 4, 7).err();
+        assert_eq!(too_small, Some(Error::RingTooSmall { size: 1, minimum: 2 }));
+    }
+}
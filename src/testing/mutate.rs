@@ -0,0 +1,393 @@
+//! Systematic signature mutation
+//!
+//! Every scheme implements [`Mutate`], which lists the [`Mutation`]s that apply to a signature and
+//! produces modified copies of it, leaving the original untouched. [`assert_rejects_all`] checks
+//! that `verify` rejects every one of them, which is the first thing to check when researching
+//! ring substitution and malleability attacks. Some mutations leave the signature with fewer
+//! responses or members than it should have, which `verify` must reject rather than panic on.
+
+use crate::prelude::*;
+
+use curve25519_dalek::constants;
+use curve25519_dalek::ristretto::RistrettoPoint;
+use curve25519_dalek::scalar::Scalar;
+use digest::generic_array::typenum::U64;
+use digest::Digest;
+
+#[cfg(feature = "blsag")]
+use crate::blsag::BLSAG;
+#[cfg(feature = "clsag")]
+use crate::clsag::CLSAG;
+#[cfg(feature = "dlsag")]
+use crate::dlsag::DLSAG;
+#[cfg(feature = "lsag")]
+use crate::lsag::LSAG;
+#[cfg(feature = "mdlsag")]
+use crate::mdlsag::MDLSAG;
+#[cfg(feature = "mlsag")]
+use crate::mlsag::MLSAG;
+#[cfg(feature = "sag")]
+use crate::sag::SAG;
+use crate::traits::VerifyRef;
+
+/// A single change to a signature. Positions are `(row, column)` in the ring, where the column is
+/// always `0` for schemes whose ring members are single keys.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Mutation {
+    /// Adds one to the challenge
+    Challenge,
+    /// Adds one to a response
+    Response(usize, usize),
+    /// Adds the basepoint to the public key of a ring member
+    RingMember(usize, usize),
+    /// Swaps two members of the ring, keeping the responses in place
+    SwapMembers(usize, usize),
+    /// Adds the basepoint to the key image of a column
+    KeyImage(usize),
+    /// Flips which side of the channel signed, for DLSAG and MDLSAG
+    Flag,
+    /// Removes the responses of a ring member, keeping the member
+    DropResponse(usize),
+    /// Removes a member of the ring, keeping its responses
+    DropMember(usize),
+    /// Removes every member of the ring and every response
+    EmptyRing,
+}
+
+pub trait Mutate: Clone {
+    /// Every mutation that applies to this signature
+    fn mutations(&self) -> Vec<Mutation>;
+    /// A copy of this signature with `mutation` applied, or `None` if it does not apply
+    fn mutate(&self, mutation: Mutation) -> Option<Self>;
+}
+
+/// Asserts that `verify` rejects `signature` once `mutation` is applied
+pub fn assert_rejects<Hash: Digest<OutputSize = U64> + Clone + Default, S: Mutate + VerifyRef>(
+    signature: &S,
+    mutation: Mutation,
+    message: &Vec<u8>,
+) {
+    let mutated = signature.mutate(mutation).expect("mutation does not apply to this signature");
+    assert!(!mutated.verify_ref::<Hash>(message), "verify accepted {:?}", mutation);
+}
+
+/// Asserts that `verify` rejects every mutation of `signature`
+pub fn assert_rejects_all<Hash: Digest<OutputSize = U64> + Clone + Default, S: Mutate + VerifyRef>(
+    signature: &S,
+    message: &Vec<u8>,
+) {
+    for mutation in signature.mutations() {
+        assert_rejects::<Hash, S>(signature, mutation, message);
+    }
+}
+
+/// The mutations of a ring with `nr` rows of `nc` keys, `response_columns` responses per row and
+/// `key_images` key images
+fn mutations(nr: usize, nc: usize, response_columns: usize, key_images: usize, flag: bool) -> Vec<Mutation> {
+    let mut mutations: Vec<Mutation> = vec![Mutation::Challenge];
+    for i in 0..nr {
+        for j in 0..response_columns {
+            mutations.push(Mutation::Response(i, j));
+        }
+        for j in 0..nc {
+            mutations.push(Mutation::RingMember(i, j));
+        }
+        if i + 1 < nr {
+            mutations.push(Mutation::SwapMembers(i, i + 1));
+        }
+    }
+    for j in 0..key_images {
+        mutations.push(Mutation::KeyImage(j));
+    }
+    if flag {
+        mutations.push(Mutation::Flag);
+    }
+    if nr > 0 {
+        mutations.push(Mutation::DropResponse(nr - 1));
+        mutations.push(Mutation::DropMember(nr - 1));
+    }
+    mutations.push(Mutation::EmptyRing);
+    return mutations;
+}
+
+fn bump_scalar(scalar: Option<&mut Scalar>) -> Option<()> {
+    *scalar? += Scalar::ONE;
+    return Some(());
+}
+
+fn bump_point(point: Option<&mut RistrettoPoint>) -> Option<()> {
+    *point? += constants::RISTRETTO_BASEPOINT_POINT;
+    return Some(());
+}
+
+fn remove<T>(items: &mut Vec<T>, i: usize) -> Option<()> {
+    if i >= items.len() {
+        return None;
+    }
+    items.remove(i);
+    return Some(());
+}
+
+fn swap<T>(items: &mut Vec<T>, i: usize, j: usize) -> Option<()> {
+    if i == j || i >= items.len() || j >= items.len() {
+        return None;
+    }
+    items.swap(i, j);
+    return Some(());
+}
+
+#[cfg(feature = "sag")]
+impl Mutate for SAG {
+    fn mutations(&self) -> Vec<Mutation> {
+        return mutations(self.ring.len(), 1, 1, 0, false);
+    }
+
+    fn mutate(&self, mutation: Mutation) -> Option<SAG> {
+        let mut signature = self.clone();
+        match mutation {
+            Mutation::Challenge => bump_scalar(Some(&mut signature.challenge.0))?,
+            Mutation::Response(i, 0) => bump_scalar(signature.responses.get_mut(i).map(|response| &mut response.0))?,
+            Mutation::RingMember(i, 0) => bump_point(signature.ring.get_mut(i))?,
+            Mutation::SwapMembers(i, j) => swap(&mut signature.ring, i, j)?,
+            Mutation::DropResponse(i) => remove(&mut signature.responses, i)?,
+            Mutation::DropMember(i) => remove(&mut signature.ring, i)?,
+            Mutation::EmptyRing => {
+                signature.ring.clear();
+                signature.responses.clear();
+            }
+            _ => return None,
+        }
+        return Some(signature);
+    }
+}
+
+#[cfg(feature = "lsag")]
+impl Mutate for LSAG {
+    fn mutations(&self) -> Vec<Mutation> {
+        return mutations(self.ring.len(), 1, 1, 1, false);
+    }
+
+    fn mutate(&self, mutation: Mutation) -> Option<LSAG> {
+        let mut signature = self.clone();
+        match mutation {
+            Mutation::Challenge => bump_scalar(Some(&mut signature.challenge.0))?,
+            Mutation::Response(i, 0) => bump_scalar(signature.responses.get_mut(i).map(|response| &mut response.0))?,
+            Mutation::RingMember(i, 0) => bump_point(signature.ring.get_mut(i))?,
+            Mutation::SwapMembers(i, j) => swap(&mut signature.ring, i, j)?,
+            Mutation::DropResponse(i) => remove(&mut signature.responses, i)?,
+            Mutation::DropMember(i) => remove(&mut signature.ring, i)?,
+            Mutation::EmptyRing => {
+                signature.ring.clear();
+                signature.responses.clear();
+            }
+            Mutation::KeyImage(0) => bump_point(Some(&mut signature.key_image))?,
+            _ => return None,
+        }
+        return Some(signature);
+    }
+}
+
+#[cfg(feature = "blsag")]
+impl Mutate for BLSAG {
+    fn mutations(&self) -> Vec<Mutation> {
+        return mutations(self.ring.len(), 1, 1, 1, false);
+    }
+
+    fn mutate(&self, mutation: Mutation) -> Option<BLSAG> {
+        let mut signature = self.clone();
+        match mutation {
+            Mutation::Challenge => bump_scalar(Some(&mut signature.challenge.0))?,
+            Mutation::Response(i, 0) => bump_scalar(signature.responses.get_mut(i).map(|response| &mut response.0))?,
+            Mutation::RingMember(i, 0) => bump_point(signature.ring.get_mut(i))?,
+            Mutation::SwapMembers(i, j) => swap(&mut signature.ring, i, j)?,
+            Mutation::DropResponse(i) => remove(&mut signature.responses, i)?,
+            Mutation::DropMember(i) => remove(&mut signature.ring, i)?,
+            Mutation::EmptyRing => {
+                signature.ring.clear();
+                signature.responses.clear();
+            }
+            Mutation::KeyImage(0) => bump_point(Some(&mut signature.key_image))?,
+            _ => return None,
+        }
+        return Some(signature);
+    }
+}
+
+#[cfg(feature = "mlsag")]
+impl Mutate for MLSAG {
+    fn mutations(&self) -> Vec<Mutation> {
+        let nc = self.key_images.len();
+        return mutations(self.ring.len(), nc, nc, nc, false);
+    }
+
+    fn mutate(&self, mutation: Mutation) -> Option<MLSAG> {
+        let mut signature = self.clone();
+        match mutation {
+            Mutation::Challenge => bump_scalar(Some(&mut signature.challenge.0))?,
+            Mutation::Response(i, j) => bump_scalar(signature.responses.get_mut(i)?.get_mut(j).map(|response| &mut response.0))?,
+            Mutation::RingMember(i, j) => bump_point(signature.ring.get_mut(i)?.get_mut(j))?,
+            Mutation::SwapMembers(i, j) => swap(&mut signature.ring, i, j)?,
+            Mutation::DropResponse(i) => remove(&mut signature.responses, i)?,
+            Mutation::DropMember(i) => remove(&mut signature.ring, i)?,
+            Mutation::EmptyRing => {
+                signature.ring.clear();
+                signature.responses.clear();
+            }
+            Mutation::KeyImage(j) => bump_point(signature.key_images.get_mut(j))?,
+            _ => return None,
+        }
+        return Some(signature);
+    }
+}
+
+#[cfg(feature = "clsag")]
+impl Mutate for CLSAG {
+    fn mutations(&self) -> Vec<Mutation> {
+        let nc = self.key_images.len();
+        return mutations(self.ring.len(), nc, 1, nc, false);
+    }
+
+    fn mutate(&self, mutation: Mutation) -> Option<CLSAG> {
+        let mut signature = self.clone();
+        match mutation {
+            Mutation::Challenge => bump_scalar(Some(&mut signature.challenge.0))?,
+            Mutation::Response(i, 0) => bump_scalar(signature.responses.get_mut(i).map(|response| &mut response.0))?,
+            Mutation::RingMember(i, j) => bump_point(signature.ring.get_mut(i)?.get_mut(j))?,
+            Mutation::SwapMembers(i, j) => swap(&mut signature.ring, i, j)?,
+            Mutation::DropResponse(i) => remove(&mut signature.responses, i)?,
+            Mutation::DropMember(i) => remove(&mut signature.ring, i)?,
+            Mutation::EmptyRing => {
+                signature.ring.clear();
+                signature.responses.clear();
+            }
+            Mutation::KeyImage(j) => bump_point(signature.key_images.get_mut(j))?,
+            _ => return None,
+        }
+        return Some(signature);
+    }
+}
+
+#[cfg(feature = "dlsag")]
+impl Mutate for DLSAG {
+    fn mutations(&self) -> Vec<Mutation> {
+        return mutations(self.ring.len(), 1, 1, 1, true);
+    }
+
+    fn mutate(&self, mutation: Mutation) -> Option<DLSAG> {
+        let mut signature = self.clone();
+        match mutation {
+            Mutation::Challenge => bump_scalar(Some(&mut signature.challenge.0))?,
+            Mutation::Response(i, 0) => bump_scalar(signature.responses.get_mut(i).map(|response| &mut response.0))?,
+            Mutation::RingMember(i, 0) => bump_point(signature.ring.get_mut(i).map(|member| &mut member.0))?,
+            Mutation::SwapMembers(i, j) => swap(&mut signature.ring, i, j)?,
+            Mutation::DropResponse(i) => remove(&mut signature.responses, i)?,
+            Mutation::DropMember(i) => remove(&mut signature.ring, i)?,
+            Mutation::EmptyRing => {
+                signature.ring.clear();
+                signature.responses.clear();
+            }
+            Mutation::KeyImage(0) => bump_point(Some(&mut signature.key_image))?,
+            Mutation::Flag => signature.b = !signature.b,
+            _ => return None,
+        }
+        return Some(signature);
+    }
+}
+
+#[cfg(feature = "mdlsag")]
+impl Mutate for MDLSAG {
+    fn mutations(&self) -> Vec<Mutation> {
+        let nc = self.key_images.len();
+        return mutations(self.ring.len(), nc, nc, nc, true);
+    }
+
+    fn mutate(&self, mutation: Mutation) -> Option<MDLSAG> {
+        let mut signature = self.clone();
+        match mutation {
+            Mutation::Challenge => bump_scalar(Some(&mut signature.challenge.0))?,
+            Mutation::Response(i, j) => bump_scalar(signature.responses.get_mut(i)?.get_mut(j).map(|response| &mut response.0))?,
+            Mutation::RingMember(i, j) => {
+                bump_point(signature.ring.get_mut(i)?.get_mut(j).map(|member| &mut member.0))?
+            }
+            Mutation::SwapMembers(i, j) => swap(&mut signature.ring, i, j)?,
+            Mutation::DropResponse(i) => remove(&mut signature.responses, i)?,
+            Mutation::DropMember(i) => remove(&mut signature.ring, i)?,
+            Mutation::EmptyRing => {
+                signature.ring.clear();
+                signature.responses.clear();
+            }
+            Mutation::KeyImage(j) => bump_point(signature.key_images.get_mut(j))?,
+            Mutation::Flag => signature.b = !signature.b,
+        }
+        return Some(signature);
+    }
+}
+
+#[cfg(test)]
+#[cfg(feature = "std")]
+#[cfg(feature = "sign")]
+#[cfg(all(
+    feature = "sag",
+    feature = "lsag",
+    feature = "blsag",
+    feature = "mlsag",
+    feature = "clsag",
+    feature = "dlsag",
+    feature = "mdlsag"
+))]
+mod test {
+    extern crate rand;
+    extern crate sha2;
+
+    use rand::rngs::OsRng;
+    use sha2::Sha512;
+
+    use super::*;
+    use crate::traits::SignWithRng;
+
+    fn points(n: usize) -> Vec<RistrettoPoint> {
+        return (0..n).map(|_| RistrettoPoint::random(&mut OsRng)).collect();
+    }
+
+    fn triples(n: usize) -> Vec<(RistrettoPoint, RistrettoPoint, Scalar)> {
+        return (0..n)
+            .map(|_| (RistrettoPoint::random(&mut OsRng), RistrettoPoint::random(&mut OsRng), Scalar::random(&mut OsRng)))
+            .collect();
+    }
+
+    #[test]
+    fn mutate() {
+        let k = Scalar::random(&mut OsRng);
+        let ks: Vec<Scalar> = (0..2).map(|_| Scalar::random(&mut OsRng)).collect();
+        let message: Vec<u8> = b"This is the message".to_vec();
+
+        let signature = SAG::sign_with_rng_unchecked::<Sha512, _>(k, points(2), 1, &message, &mut OsRng).unwrap();
+        assert_eq!(signature.mutations().len(), 1 + 3 * 2 + 2 + 3);
+        assert!(signature.mutate(Mutation::KeyImage(0)).is_none());
+        assert_rejects_all::<Sha512, _>(&signature, &message);
+        // The original is left untouched
+        assert!(signature.verify_ref::<Sha512>(&message));
+
+        let lsag = LSAG::sign_with_rng_unchecked::<Sha512, _>(k, points(2), 1, &message, &mut OsRng).unwrap();
+        assert_rejects_all::<Sha512, _>(&lsag, &message);
+        let blsag = BLSAG::sign_with_rng_unchecked::<Sha512, _>(k, points(2), 1, &message, &mut OsRng).unwrap();
+        assert_rejects_all::<Sha512, _>(&blsag, &message);
+        let ring: Vec<Vec<RistrettoPoint>> = (0..2).map(|_| points(2)).collect();
+        let mlsag =
+            MLSAG::sign_with_rng_unchecked::<Sha512, _>(ks.clone(), ring.clone(), 1, &message, &mut OsRng).unwrap();
+        assert_rejects_all::<Sha512, _>(&mlsag, &message);
+        let clsag = CLSAG::sign_with_rng_unchecked::<Sha512, _>(ks, ring, 1, &message, &mut OsRng).unwrap();
+        assert_rejects_all::<Sha512, _>(&clsag, &message);
+
+        let k_dlsag = (Scalar::random(&mut OsRng), RistrettoPoint::random(&mut OsRng), Scalar::random(&mut OsRng));
+        let dlsag = DLSAG::sign_with_rng_unchecked::<Sha512, _>(k_dlsag, triples(2), 1, &message, &mut OsRng).unwrap();
+        assert_rejects_all::<Sha512, _>(&dlsag, &message);
+        let ks_mdlsag: Vec<(RistrettoPoint, Scalar, Scalar)> = (0..2)
+            .map(|_| (RistrettoPoint::random(&mut OsRng), Scalar::random(&mut OsRng), Scalar::random(&mut OsRng)))
+            .collect();
+        let ring_mdlsag = (0..2).map(|_| triples(2)).collect();
+        let mdlsag =
+            MDLSAG::sign_with_rng_unchecked::<Sha512, _>(ks_mdlsag, ring_mdlsag, 1, &message, &mut OsRng).unwrap();
+        assert_rejects_all::<Sha512, _>(&mdlsag, &message);
+    }
+}
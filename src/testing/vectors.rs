@@ -0,0 +1,271 @@
+//! Negative test vectors
+//!
+//! Implementations of these schemes in other languages should reject what nazgul rejects.
+//! [`negative_vectors`] lists signatures that must not verify, in the [`SignatureBytes`] encoding,
+//! each with a comment and the [`Error`] nazgul reports for it, and [`to_json`] writes them in the
+//! spirit of Wycheproof, with error names from [`Error::code`]:
+//!
+//! - non-canonical scalars and points, truncated and overlong encodings
+//! - fields swapped or signed with another hash to scalar
+//! - an identity key image. Ristretto encodings have no torsion component, so unlike Edwards
+//!   encodings a key image can not carry a small order torsion point; the identity is the one
+//!   small order point that can be encoded
+//! - every enabled scheme with one field changed, from [`generate_corpus`]
+//!
+//! The vectors are derived from fixed seeds, so they are the same on every run for a given
+//! `Hash`.
+
+use crate::prelude::*;
+
+use alloc::string::String;
+
+use curve25519_dalek::ristretto::RistrettoPoint;
+use curve25519_dalek::traits::Identity;
+use digest::generic_array::typenum::U64;
+use digest::Digest;
+
+use crate::blsag::BLSAG;
+use crate::encoding::encode_hex;
+use crate::error::Error;
+use crate::hash_to_point::DefaultHashToPoint;
+use crate::hash_to_scalar::TruncatedReduction;
+use crate::header::SchemeId;
+use crate::signature::{self, SignatureBytes};
+use crate::testing::corpus::{generate_corpus, SeededRng};
+use crate::traits::SignWithRng;
+
+/// A signature that must be rejected
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct NegativeVector {
+    pub comment: String,
+    pub message: Vec<u8>,
+    /// The [`SignatureBytes`] encoding of the signature
+    pub signature: Vec<u8>,
+    /// What [`check`] reports for it
+    pub expected: Error,
+}
+
+/// Decodes and verifies `signature`, reporting [`Error::InvalidSignature`] if it decodes but
+/// does not verify
+pub fn check<Hash: Digest<OutputSize = U64> + Clone + Default>(
+    signature: &[u8],
+    message: &Vec<u8>,
+) -> Result<(), Error> {
+    if !signature::parse(signature)?.verify::<Hash>(message) {
+        return Err(Error::InvalidSignature);
+    }
+    return Ok(());
+}
+
+fn vector(comment: &str, message: &[u8], signature: Vec<u8>, expected: Error) -> NegativeVector {
+    return NegativeVector {
+        comment: String::from(comment),
+        message: message.to_vec(),
+        signature: signature,
+        expected: expected,
+    };
+}
+
+/// The negative vectors for `Hash`
+pub fn negative_vectors<Hash: Digest<OutputSize = U64> + Clone + Default>() -> Vec<NegativeVector> {
+    let mut rng = SeededRng::<Hash>::new(b"nazgul/vectors", 0);
+    let message: Vec<u8> = b"nazgul negative vectors".to_vec();
+    let k = rng.scalar();
+    let ring: Vec<RistrettoPoint> = (0..2).map(|_| rng.point()).collect();
+    let mut csprng = SeededRng::<Hash>::default();
+    let signature = BLSAG::sign_with_rng_unchecked::<Hash, _>(k, ring.clone(), 1, &message, &mut csprng)
+        .expect("a fixed ring of 3");
+    let valid = signature.to_tagged_bytes();
+    // The tag, the challenge and the ring size, then 3 responses, 3 members and the key image
+    let responses = 37;
+    let members = responses + 3 * 32;
+    let key_image = members + 3 * 32;
+
+    let mut vectors: Vec<NegativeVector> = Vec::new();
+    vectors.push(vector("empty input", &message, Vec::new(), Error::Truncated));
+    let mut unknown = valid.clone();
+    unknown[0] = 0;
+    vectors.push(vector(
+        "unknown scheme tag",
+        &message,
+        unknown,
+        Error::UnknownScheme { tag: 0 },
+    ));
+
+    let mut bytes = valid.clone();
+    bytes[1..33].copy_from_slice(&[0xff; 32]);
+    vectors.push(vector(
+        "challenge is not a canonical scalar",
+        &message,
+        bytes,
+        Error::NonCanonicalScalar,
+    ));
+    let mut bytes = valid.clone();
+    bytes[responses..responses + 32].copy_from_slice(&[0xff; 32]);
+    vectors.push(vector(
+        "response is not a canonical scalar",
+        &message,
+        bytes,
+        Error::NonCanonicalScalar,
+    ));
+    let mut bytes = valid.clone();
+    bytes[members..members + 32].copy_from_slice(&[0xff; 32]);
+    vectors.push(vector(
+        "ring member is not a valid point",
+        &message,
+        bytes,
+        Error::InvalidPointEncoding,
+    ));
+    let mut bytes = valid.clone();
+    bytes[key_image..key_image + 32].copy_from_slice(&[0xff; 32]);
+    vectors.push(vector(
+        "key image is not a valid point",
+        &message,
+        bytes,
+        Error::InvalidPointEncoding,
+    ));
+
+    vectors.push(vector(
+        "truncated responses",
+        &message,
+        valid[..responses + 2 * 32].to_vec(),
+        Error::Truncated,
+    ));
+    let mut bytes = valid.clone();
+    bytes[33..37].copy_from_slice(&4u32.to_le_bytes());
+    vectors.push(vector(
+        "ring size larger than the encoding",
+        &message,
+        bytes,
+        Error::Truncated,
+    ));
+    let mut bytes = valid.clone();
+    bytes.push(0);
+    vectors.push(vector("trailing byte", &message, bytes, Error::TrailingBytes));
+
+    let mut bytes = valid.clone();
+    bytes.extend_from_slice(&[0u8; 64]);
+    vectors.push(vector(
+        "ring digest that does not match the ring",
+        &message,
+        bytes,
+        Error::InvalidSignature,
+    ));
+    let mut bytes = valid.clone();
+    let challenge: Vec<u8> = bytes[1..33].to_vec();
+    bytes.copy_within(responses..responses + 32, 1);
+    bytes[responses..responses + 32].copy_from_slice(&challenge);
+    vectors.push(vector(
+        "challenge and first response swapped",
+        &message,
+        bytes,
+        Error::InvalidSignature,
+    ));
+    let mut bytes = valid.clone();
+    bytes.copy_within(members..members + 32, members + 64);
+    bytes[members..members + 32].copy_from_slice(&valid[members + 64..members + 96]);
+    vectors.push(vector(
+        "first and last ring members swapped",
+        &message,
+        bytes,
+        Error::InvalidSignature,
+    ));
+    let mut identity = signature.clone();
+    identity.key_image = RistrettoPoint::identity();
+    vectors.push(vector(
+        "identity key image",
+        &message,
+        identity.to_tagged_bytes(),
+        Error::InvalidSignature,
+    ));
+    let truncated =
+        BLSAG::sign_with::<Hash, SeededRng<Hash>, DefaultHashToPoint, TruncatedReduction>(k, ring, 1, &message)
+            .expect("a fixed ring of 3");
+    vectors.push(vector(
+        "signed with a 32 byte hash to scalar",
+        &message,
+        truncated.to_tagged_bytes(),
+        Error::InvalidSignature,
+    ));
+    vectors.push(vector(
+        "signature of another message",
+        b"another message",
+        valid,
+        Error::InvalidSignature,
+    ));
+
+    // Schemes disabled in this build have no corpus
+    for scheme in (1..=7u8).filter_map(SchemeId::from_u8) {
+        let corpus = match generate_corpus::<Hash>(scheme, 3, 8, 0) {
+            Ok(corpus) => corpus,
+            Err(_) => continue,
+        };
+        for entry in corpus.entries.into_iter().filter(|entry| !entry.valid) {
+            let comment = format!("{:?} signature with one field changed", scheme);
+            vectors.push(vector(
+                &comment,
+                &entry.message,
+                entry.signature,
+                Error::InvalidSignature,
+            ));
+        }
+    }
+    return vectors;
+}
+
+/// Escapes `text` as the contents of a JSON string
+fn json_string(text: &str) -> String {
+    let mut escaped = String::new();
+    for c in text.chars() {
+        match c {
+            '"' => escaped.push_str("\\\""),
+            '\\' => escaped.push_str("\\\\"),
+            c if (c as u32) < 0x20 => escaped.push_str(&format!("\\u{:04x}", c as u32)),
+            c => escaped.push(c),
+        }
+    }
+    return escaped;
+}
+
+/// `vectors` as a JSON document with one test per vector, its message and signature in hex
+pub fn to_json(vectors: &[NegativeVector]) -> String {
+    let mut json = String::from("{\n  \"numberOfTests\": ");
+    json.push_str(&format!("{},\n  \"tests\": [", vectors.len()));
+    for (index, vector) in vectors.iter().enumerate() {
+        if index > 0 {
+            json.push(',');
+        }
+        json.push_str(&format!(
+            "\n    {{\"tcId\": {}, \"comment\": \"{}\", \"msg\": \"{}\", \"sig\": \"{}\", \"result\": \"invalid\", \"error\": \"{}\"}}",
+            index + 1,
+            json_string(&vector.comment),
+            encode_hex(&vector.message),
+            encode_hex(&vector.signature),
+            vector.expected.code(),
+        ));
+    }
+    json.push_str("\n  ]\n}\n");
+    return json;
+}
+
+#[cfg(test)]
+#[cfg(feature = "std")]
+mod test {
+    extern crate sha2;
+
+    use sha2::Sha512;
+
+    use super::*;
+
+    #[test]
+    fn negative_vectors() {
+        let vectors = super::negative_vectors::<Sha512>();
+        for vector in &vectors {
+            let outcome = check::<Sha512>(&vector.signature, &vector.message);
+            assert_eq!(outcome, Err(vector.expected), "{}", vector.comment);
+        }
+        let json = to_json(&vectors);
+        assert!(json.starts_with(&format!("{{\n  \"numberOfTests\": {},", vectors.len())));
+        assert!(json.contains("\"error\": \"non_canonical_scalar\""));
+    }
+}
@@ -1,9 +1,430 @@
 use crate::prelude::*;
+use core::ops::Range;
+use curve25519_dalek::scalar::Scalar;
 use digest::generic_array::typenum::U64;
 use digest::Digest;
+#[cfg(feature = "sign")]
 use rand_core::{CryptoRng, RngCore};
 
+use crate::error::Error;
+use crate::partial::ChallengeSteps;
+#[cfg(feature = "sign")]
+use crate::policy::SignPolicy;
+
+/// Signing, with randomness drawn from `csprng` so that callers choose the generator, e.g. a
+/// seeded one for reproducible test vectors or a hardware one. Arguments that can not be signed
+/// with, such as a `secret_index` outside the ring, are an [`Error`], see
+/// [`TrySign::check_sign_input`]
+#[cfg(feature = "sign")]
+pub trait SignWithRng<PrivateKey, Ring>: Sized {
+    /// Returns [`Error::RingTooSmall`] for rings smaller than
+    /// [`crate::policy::DEFAULT_MINIMUM_RING_SIZE`], signer included. Sign through a
+    /// [`SignPolicy`] or with `sign_with_rng_unchecked` for others
+    fn sign_with_rng<Hash: Digest<OutputSize = U64> + Clone + Default, CSPRNG: CryptoRng + RngCore>(
+        k: PrivateKey,
+        ring: Ring,
+        secret_index: usize,
+        message: &Vec<u8>,
+        csprng: &mut CSPRNG,
+    ) -> Result<Self, Error>
+    where
+        Ring: Decoys,
+    {
+        SignPolicy::default().check(ring.decoys() + 1)?;
+        return Self::sign_with_rng_unchecked::<Hash, CSPRNG>(k, ring, secret_index, message, csprng);
+    }
+
+    /// `sign_with_rng` over a ring of any size, opting out of the policy. This is what each scheme
+    /// implements, and what [`SignPolicy::sign_with_rng`] calls once its own policy is met
+    fn sign_with_rng_unchecked<Hash: Digest<OutputSize = U64> + Clone + Default, CSPRNG: CryptoRng + RngCore>(
+        k: PrivateKey,
+        ring: Ring,
+        secret_index: usize,
+        message: &Vec<u8>,
+        csprng: &mut CSPRNG,
+    ) -> Result<Self, Error>;
+}
+
+/// [`SignWithRng`] drawing from a fresh `CSPRNG::default()`, which also checks the key, ring and
+/// position before signing
+#[cfg(feature = "sign")]
+pub trait TrySign<PrivateKey, Ring>: SignWithRng<PrivateKey, Ring> {
+    /// Checks that `k` and `ring` agree on the number of keys per member and that `secret_index`
+    /// is a position in the ring
+    fn check_sign_input(k: &PrivateKey, ring: &Ring, secret_index: usize) -> Result<(), Error>;
+
+    /// Also returns [`Error::RingTooSmall`] for rings smaller than
+    /// [`crate::policy::DEFAULT_MINIMUM_RING_SIZE`], signer included. Sign through a
+    /// [`crate::policy::SignPolicy`] or [`crate::builder::Signer::allow_small_rings`] for others
+    fn try_sign<
+        Hash: Digest<OutputSize = U64> + Clone + Default,
+        CSPRNG: CryptoRng + RngCore + Default,
+    >(
+        k: PrivateKey,
+        ring: Ring,
+        secret_index: usize,
+        message: &Vec<u8>,
+    ) -> Result<Self, Error>
+    where
+        Ring: Decoys,
+    {
+        Self::check_sign_input(&k, &ring, secret_index)?;
+        SignPolicy::default().check(ring.decoys() + 1)?;
+        let mut csprng = CSPRNG::default();
+        return Self::sign_with_rng_unchecked::<Hash, CSPRNG>(k, ring, secret_index, message, &mut csprng);
+    }
+}
+
+/// A ring as signing takes it, the public keys of everyone except the signer
+pub trait Decoys {
+    fn decoys(&self) -> usize;
+}
+
+impl<T> Decoys for Vec<T> {
+    fn decoys(&self) -> usize {
+        return self.len();
+    }
+}
+
+/// [`SignWithRng`] with the position of the signer in the ring drawn uniformly from `csprng`, so
+/// that callers do not have to pick it and can not pick it with a bias. `ring` is the public keys
+/// of everyone except the signer, as for `sign_with_rng`
+#[cfg(feature = "sign")]
+pub trait SignWithHiddenIndex<PrivateKey, Member>: SignWithRng<PrivateKey, Vec<Member>> {
+    fn sign_with_hidden_index<Hash: Digest<OutputSize = U64> + Clone + Default, CSPRNG: CryptoRng + RngCore>(
+        k: PrivateKey,
+        ring: Vec<Member>,
+        message: &Vec<u8>,
+        csprng: &mut CSPRNG,
+    ) -> Result<Self, Error> {
+        let secret_index = random_index(csprng, ring.len() + 1);
+        return Self::sign_with_rng::<Hash, CSPRNG>(k, ring, secret_index, message, csprng);
+    }
+}
+
+/// Signing over a complete ring, such as one read from a chain, which already has the public key
+/// of the signer at `secret_index`. The member there is checked to be that of `k`, and the
+/// signature is the one [`SignWithRng::sign_with_rng`] gives over the ring without it, so callers
+/// need not take their own key out first
+#[cfg(feature = "sign")]
+pub trait SignWithFullRing<PrivateKey, Ring>: SignWithRng<PrivateKey, Ring> + TrySign<PrivateKey, Ring> {
+    /// Takes the member at `secret_index` out of `ring`, returning [`Error::NotInRing`] if it is
+    /// not the public key of `k`
+    fn take_signer(k: &PrivateKey, ring: &mut Ring, secret_index: usize) -> Result<(), Error>;
+
+    fn sign_with_full_ring<
+        Hash: Digest<OutputSize = U64> + Clone + Default,
+        CSPRNG: CryptoRng + RngCore + Default,
+    >(
+        k: PrivateKey,
+        ring: Ring,
+        secret_index: usize,
+        message: &Vec<u8>,
+    ) -> Result<Self, Error>
+    where
+        Ring: Decoys,
+    {
+        let mut csprng = CSPRNG::default();
+        return Self::sign_with_full_ring_and_rng::<Hash, CSPRNG>(k, ring, secret_index, message, &mut csprng);
+    }
+
+    /// `sign_with_full_ring` with randomness drawn from `csprng`, see [`SignWithRng`]
+    fn sign_with_full_ring_and_rng<Hash: Digest<OutputSize = U64> + Clone + Default, CSPRNG: CryptoRng + RngCore>(
+        k: PrivateKey,
+        mut ring: Ring,
+        secret_index: usize,
+        message: &Vec<u8>,
+        csprng: &mut CSPRNG,
+    ) -> Result<Self, Error>
+    where
+        Ring: Decoys,
+    {
+        Self::take_signer(&k, &mut ring, secret_index)?;
+        return Self::sign_with_rng::<Hash, CSPRNG>(k, ring, secret_index, message, csprng);
+    }
+}
+
+/// Signing in a context, e.g. `b"election-2025"`, for applications sharing keys. This is domain
+/// separation of the message: what is signed is a tag, the length of the context, the context and
+/// then the message, so a signature made in one context does not verify in any other. It is an
+/// ordinary signature over those bytes, which plain `verify` accepts when given them. See
+/// [`VerifyWithContext`]
+#[cfg(feature = "sign")]
+pub trait SignWithContext<PrivateKey, Ring>: SignWithRng<PrivateKey, Ring> {
+    fn sign_ctx<Hash: Digest<OutputSize = U64> + Clone + Default, CSPRNG: CryptoRng + RngCore + Default>(
+        k: PrivateKey,
+        ring: Ring,
+        secret_index: usize,
+        context: &[u8],
+        message: &Vec<u8>,
+    ) -> Result<Self, Error>
+    where
+        Ring: Decoys,
+    {
+        let mut csprng = CSPRNG::default();
+        return Self::sign_ctx_with_rng::<Hash, CSPRNG>(k, ring, secret_index, context, message, &mut csprng);
+    }
+
+    /// `sign_ctx` with randomness drawn from `csprng`, see [`SignWithRng`]
+    fn sign_ctx_with_rng<Hash: Digest<OutputSize = U64> + Clone + Default, CSPRNG: CryptoRng + RngCore>(
+        k: PrivateKey,
+        ring: Ring,
+        secret_index: usize,
+        context: &[u8],
+        message: &Vec<u8>,
+        csprng: &mut CSPRNG,
+    ) -> Result<Self, Error>
+    where
+        Ring: Decoys,
+    {
+        let bound: Vec<u8> = bind_context(context, message);
+        return Self::sign_with_rng::<Hash, CSPRNG>(k, ring, secret_index, &bound, csprng);
+    }
+}
+
+#[cfg(feature = "sign")]
+impl<PrivateKey, Ring, S: SignWithRng<PrivateKey, Ring>> SignWithContext<PrivateKey, Ring> for S {}
+
+/// Verifying signatures made with [`SignWithContext`]
+pub trait VerifyWithContext: VerifyRef {
+    /// Whether the signature is over `message` in `context`
+    fn verify_ctx<Hash: Digest<OutputSize = U64> + Clone + Default>(&self, context: &[u8], message: &Vec<u8>) -> bool {
+        return self.verify_ref::<Hash>(&bind_context(context, message));
+    }
+}
+
+impl<S: VerifyRef> VerifyWithContext for S {}
+
+/// Domain separation of messages signed in a context
+const CONTEXT: &[u8] = b"nazgul/context";
+
+/// The bytes actually signed in `context`. The length of `context` comes first, so that no two
+/// pairs of context and message give the same bytes
+pub(crate) fn bind_context(context: &[u8], message: &[u8]) -> Vec<u8> {
+    let mut bytes: Vec<u8> = CONTEXT.to_vec();
+    bytes.extend_from_slice(&(context.len() as u64).to_le_bytes());
+    bytes.extend_from_slice(context);
+    bytes.extend_from_slice(message);
+    return bytes;
+}
+
+/// Signing with the private key and ring types of a scheme named by associated types, so that
+/// generic code can write `S::SecretKey` and `S::Ring` in place of carrying them as type
+/// parameters. Every scheme has exactly one key type: DLSAG and MDLSAG, which sign from either
+/// side of a channel, take a `DLSAGKey` or `MDLSAGKey` saying which. `sign_with_key` and
+/// `try_sign_with_key` give the same signatures and errors as [`SignWithRng::sign_with_rng`] and
+/// [`TrySign::try_sign`], and `try_sign_with_key` rejects the same small rings
+#[cfg(feature = "sign")]
+pub trait Scheme: Sized {
+    type SecretKey;
+    type Ring;
+
+    fn sign_with_key<Hash: Digest<OutputSize = U64> + Clone + Default, CSPRNG: CryptoRng + RngCore + Default>(
+        k: Self::SecretKey,
+        ring: Self::Ring,
+        secret_index: usize,
+        message: &Vec<u8>,
+    ) -> Result<Self, Error>;
+
+    fn try_sign_with_key<Hash: Digest<OutputSize = U64> + Clone + Default, CSPRNG: CryptoRng + RngCore + Default>(
+        k: Self::SecretKey,
+        ring: Self::Ring,
+        secret_index: usize,
+        message: &Vec<u8>,
+    ) -> Result<Self, Error>
+    where
+        Self::Ring: Decoys,
+    {
+        SignPolicy::default().check(ring.decoys() + 1)?;
+        let mut csprng = CSPRNG::default();
+        return Self::try_sign_with_key_and_rng::<Hash, CSPRNG>(k, ring, secret_index, message, &mut csprng);
+    }
+
+    /// `try_sign_with_key` with randomness drawn from `csprng`, see [`SignWithRng`]. The ring
+    /// size is left to the caller, e.g. [`crate::builder::Signer`] checks it against its policy
+    fn try_sign_with_key_and_rng<Hash: Digest<OutputSize = U64> + Clone + Default, CSPRNG: CryptoRng + RngCore>(
+        k: Self::SecretKey,
+        ring: Self::Ring,
+        secret_index: usize,
+        message: &Vec<u8>,
+        csprng: &mut CSPRNG,
+    ) -> Result<Self, Error>;
+}
+
+/// [`VerifyRef`] that tells a malformed signature apart from one that is well formed but invalid,
+/// which is reported as [`Error::InvalidSignature`]
+pub trait TryVerify: VerifyRef {
+    /// Checks that the ring, responses and key images of the signature agree on its shape
+    fn check_shape(&self) -> Result<(), Error>;
+
+    fn try_verify<Hash: Digest<OutputSize = U64> + Clone + Default>(&self, message: &Vec<u8>) -> Result<(), Error> {
+        self.check_shape()?;
+        if !self.verify_ref::<Hash>(message) {
+            return Err(Error::InvalidSignature);
+        }
+        return Ok(());
+    }
+}
+
+/// `secret_index` must be a position among `decoys` decoys and the signer
+#[cfg(feature = "sign")]
+#[cfg(any(
+    feature = "sag",
+    feature = "lsag",
+    feature = "blsag",
+    feature = "mlsag",
+    feature = "clsag",
+    feature = "dlsag",
+    feature = "mdlsag"
+))]
+pub(crate) fn check_secret_index(decoys: usize, secret_index: usize) -> Result<(), Error> {
+    if secret_index > decoys {
+        return Err(Error::SecretIndexOutOfRange {
+            index: secret_index,
+            ring_size: decoys + 1,
+        });
+    }
+    return Ok(());
+}
+
+/// A position among `n`, which must not be 0, drawn uniformly from `csprng`. Draws in the last
+/// `2^64 % n` values are rejected, since `next_u64() % n` would give the low positions more often
+#[cfg(feature = "sign")]
+pub(crate) fn random_index<CSPRNG: RngCore>(csprng: &mut CSPRNG, n: usize) -> usize {
+    let n = n as u64;
+    let rejected = (u64::MAX % n + 1) % n;
+    loop {
+        let draw = csprng.next_u64();
+        if draw <= u64::MAX - rejected {
+            return (draw % n) as usize;
+        }
+    }
+}
+
+/// Takes `ring[secret_index]` out of `ring` if it is `signer`, the public key of the signer
+#[cfg(feature = "sign")]
+#[cfg(any(
+    feature = "sag",
+    feature = "lsag",
+    feature = "blsag",
+    feature = "mlsag",
+    feature = "clsag",
+    feature = "dlsag",
+    feature = "mdlsag"
+))]
+pub(crate) fn take_signer<T: PartialEq>(ring: &mut Vec<T>, secret_index: usize, signer: &T) -> Result<(), Error> {
+    match ring.get(secret_index) {
+        None => {
+            return Err(Error::SecretIndexOutOfRange {
+                index: secret_index,
+                ring_size: ring.len(),
+            })
+        }
+        Some(member) if member != signer => return Err(Error::NotInRing),
+        Some(_) => {}
+    }
+    ring.remove(secret_index);
+    return Ok(());
+}
+
+/// Every row of a ring matrix must have `columns` keys, and there must be at least one
+#[cfg(any(feature = "mlsag", feature = "clsag", feature = "mdlsag"))]
+pub(crate) fn check_columns<T>(columns: usize, rows: &[Vec<T>]) -> Result<(), Error> {
+    if columns == 0 {
+        return Err(Error::EmptyRing);
+    }
+    if rows.iter().any(|row| row.len() != columns) {
+        return Err(Error::RaggedRingMatrix);
+    }
+    return Ok(());
+}
+
+/// A signature needs a non-empty ring and one response per member
+#[cfg(any(
+    feature = "sag",
+    feature = "lsag",
+    feature = "blsag",
+    feature = "mlsag",
+    feature = "clsag",
+    feature = "dlsag",
+    feature = "mdlsag"
+))]
+pub(crate) fn check_responses(ring_size: usize, responses: usize) -> Result<(), Error> {
+    if ring_size == 0 {
+        return Err(Error::EmptyRing);
+    }
+    if responses != ring_size {
+        return Err(Error::ResponseCountMismatch);
+    }
+    return Ok(());
+}
+
+/// Verifying a signature, which is borrowed so that verifying one over a large ring does not take a
+/// copy of it
+pub trait VerifyRef {
+    fn verify_ref<Hash: Digest<OutputSize = U64> + Clone + Default>(&self, message: &Vec<u8>) -> bool;
+}
+
+/// Whether two signatures are by the same signer
+pub trait LinkRef {
+    fn link_ref(&self, other: &Self) -> bool;
+}
+
+/// The key images of a private key, which some schemes sign as well
+pub trait KeyImageGenRef<PrivateKey, KeyImages> {
+    fn generate_key_image_ref<Hash: Digest<OutputSize = U64> + Clone + Default>(
+        k: &PrivateKey,
+    ) -> KeyImages;
+}
+
+pub trait Weight {
+    fn weight(&self) -> u64;
+}
+
+pub trait ChallengeChain {
+    fn ring_size(&self) -> usize;
+    fn challenge(&self) -> Scalar;
+    /// Runs the challenge chain over `slots`, starting from `challenge`. Returns `None` if `slots`
+    /// is not within the ring or the signature is malformed, see [`TryVerify::check_shape`]
+    fn reconstruct_challenge<Hash: Digest<OutputSize = U64> + Clone + Default>(
+        &self,
+        message: &Vec<u8>,
+        slots: Range<usize>,
+        challenge: Scalar,
+    ) -> Option<Scalar>;
+
+    /// Whether what the signature carries besides the chain agrees with it, e.g. the ring digest
+    /// of a bLSAG. Partial verification and [`ChallengeChain::challenge_chain`] reject a signature
+    /// for which it does not, as `verify` does
+    fn is_consistent<Hash: Digest<OutputSize = U64> + Clone + Default>(&self) -> bool {
+        return true;
+    }
+
+    /// Iterates over the challenge leaving each slot as verification reconstructs it, ending with
+    /// whether the chain closes. The last step agrees with `verify`
+    fn challenge_chain<'a, Hash: Digest<OutputSize = U64> + Clone + Default>(
+        &'a self,
+        message: &'a Vec<u8>,
+    ) -> ChallengeSteps<'a, Hash, Self>
+    where
+        Self: TryVerify + Sized,
+    {
+        return ChallengeSteps::new(self, message);
+    }
+}
+
+// The traits of 1.0, kept as shims over the ones above until 3.0.0. Each is implemented for every
+// type implementing its replacement, and gives the same result.
+
+/// [`SignWithRng::sign_with_rng_unchecked`] drawing from a fresh `CSPRNG::default()`, which
+/// signs over rings of any size as 1.0 did
+///
+/// # Panics
+///
+/// On the arguments for which `sign_with_rng_unchecked` returns an [`Error`], as 1.0 did
+#[cfg(feature = "sign")]
 pub trait Sign<PrivateKey, Ring> {
+    #[deprecated(since = "2.0.0", note = "use `SignWithRng::sign_with_rng`, see CHANGELOG.md")]
     fn sign<
         Hash: Digest<OutputSize = U64> + Clone + Default,
         CSPRNG: CryptoRng + RngCore + Default,
@@ -15,19 +436,57 @@ pub trait Sign<PrivateKey, Ring> {
     ) -> Self;
 }
 
+#[cfg(feature = "sign")]
+impl<PrivateKey, Ring, S: SignWithRng<PrivateKey, Ring>> Sign<PrivateKey, Ring> for S {
+    fn sign<Hash: Digest<OutputSize = U64> + Clone + Default, CSPRNG: CryptoRng + RngCore + Default>(
+        k: PrivateKey,
+        ring: Ring,
+        secret_index: usize,
+        message: &Vec<u8>,
+    ) -> S {
+        let mut csprng = CSPRNG::default();
+        return S::sign_with_rng_unchecked::<Hash, CSPRNG>(k, ring, secret_index, message, &mut csprng)
+            .expect("arguments sign_with_rng_unchecked rejects");
+    }
+}
+
+/// [`VerifyRef`] taking the signature by value
 pub trait Verify {
+    #[deprecated(since = "2.0.0", note = "use `VerifyRef::verify_ref`, see CHANGELOG.md")]
     fn verify<Hash: Digest<OutputSize = U64> + Clone + Default>(
         signature: Self,
         message: &Vec<u8>,
     ) -> bool;
 }
 
+impl<S: VerifyRef> Verify for S {
+    fn verify<Hash: Digest<OutputSize = U64> + Clone + Default>(signature: S, message: &Vec<u8>) -> bool {
+        return signature.verify_ref::<Hash>(message);
+    }
+}
+
+/// [`LinkRef`] taking the signatures by value
 pub trait Link {
+    #[deprecated(since = "2.0.0", note = "use `LinkRef::link_ref`, see CHANGELOG.md")]
     fn link(signature_1: Self, signature_2: Self) -> bool;
 }
 
+impl<S: LinkRef> Link for S {
+    fn link(signature_1: S, signature_2: S) -> bool {
+        return signature_1.link_ref(&signature_2);
+    }
+}
+
+/// [`KeyImageGenRef`] taking the private key by value
 pub trait KeyImageGen<PrivateKey, KeyImages> {
+    #[deprecated(since = "2.0.0", note = "use `KeyImageGenRef::generate_key_image_ref`, see CHANGELOG.md")]
     fn generate_key_image<Hash: Digest<OutputSize = U64> + Clone + Default>(
         k: PrivateKey,
     ) -> KeyImages;
 }
+
+impl<PrivateKey, KeyImages, S: KeyImageGenRef<PrivateKey, KeyImages>> KeyImageGen<PrivateKey, KeyImages> for S {
+    fn generate_key_image<Hash: Digest<OutputSize = U64> + Clone + Default>(k: PrivateKey) -> KeyImages {
+        return S::generate_key_image_ref::<Hash>(&k);
+    }
+}
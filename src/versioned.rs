@@ -0,0 +1,224 @@
+//! Scheme and version bound challenges
+//!
+//! The challenges of the schemes hash the message with the points of one step of the ring, and
+//! nothing says which scheme or which version of this library they belong to. A bLSAG and a
+//! single column MLSAG over the same ring hash much the same transcript. Signing in the v2 mode
+//! of [`SignV2`] puts a domain tag, the [`SchemeId`] of the scheme and [`VERSION`] in front of the
+//! message, so that every challenge hash absorbs them and a signature made for one scheme or
+//! version never verifies as one of another. [`VerifyV2`] verifies such signatures.
+//!
+//! Signatures made with plain `sign` are unchanged and do not verify in the v2 mode, nor the
+//! other way round.
+//!
+//! Archives holding signatures of both kinds store each in a [`VersionedSignature`], which names
+//! its [`SpecVersion`] and verifies with the challenge derivation of that version. Both versions
+//! are kept and covered by the golden tests, so signatures already archived stay verifiable.
+
+use crate::prelude::*;
+
+use digest::generic_array::typenum::U64;
+use digest::Digest;
+#[cfg(feature = "sign")]
+use rand_core::{CryptoRng, RngCore};
+
+use crate::codec::Codec;
+use crate::error::Error;
+use crate::header::SchemeId;
+use crate::signature::{self, RingSignature, SignatureBytes};
+use crate::traits::VerifyRef;
+#[cfg(feature = "sign")]
+use crate::traits::{Decoys, SignWithRng};
+
+/// The format version bound into v2 challenges
+pub const VERSION: u8 = 2;
+
+/// Domain separation of messages signed in the v2 mode
+const DOMAIN: &[u8] = b"nazgul/v2";
+
+/// The bytes actually signed by a signature of `scheme` in the v2 mode
+pub(crate) fn bind_scheme(scheme: SchemeId, message: &[u8]) -> Vec<u8> {
+    let mut bytes: Vec<u8> = DOMAIN.to_vec();
+    bytes.push(scheme as u8);
+    bytes.push(VERSION);
+    bytes.extend_from_slice(message);
+    return bytes;
+}
+
+/// Signing with the scheme and [`VERSION`] bound into every challenge
+#[cfg(feature = "sign")]
+pub trait SignV2<PrivateKey, Ring>: SignWithRng<PrivateKey, Ring> + SignatureBytes {
+    fn sign_v2<Hash: Digest<OutputSize = U64> + Clone + Default, CSPRNG: CryptoRng + RngCore + Default>(
+        k: PrivateKey,
+        ring: Ring,
+        secret_index: usize,
+        message: &Vec<u8>,
+    ) -> Result<Self, Error>
+    where
+        Ring: Decoys,
+    {
+        let mut csprng = CSPRNG::default();
+        return Self::sign_v2_with_rng::<Hash, CSPRNG>(k, ring, secret_index, message, &mut csprng);
+    }
+
+    /// `sign_v2` with randomness drawn from `csprng`, see [`SignWithRng`]
+    fn sign_v2_with_rng<Hash: Digest<OutputSize = U64> + Clone + Default, CSPRNG: CryptoRng + RngCore>(
+        k: PrivateKey,
+        ring: Ring,
+        secret_index: usize,
+        message: &Vec<u8>,
+        csprng: &mut CSPRNG,
+    ) -> Result<Self, Error>
+    where
+        Ring: Decoys,
+    {
+        let bound: Vec<u8> = bind_scheme(Self::SCHEME, message);
+        return Self::sign_with_rng::<Hash, CSPRNG>(k, ring, secret_index, &bound, csprng);
+    }
+
+    /// `sign_v2_with_rng` over a ring of any size, see [`SignWithRng::sign_with_rng_unchecked`]
+    fn sign_v2_with_rng_unchecked<Hash: Digest<OutputSize = U64> + Clone + Default, CSPRNG: CryptoRng + RngCore>(
+        k: PrivateKey,
+        ring: Ring,
+        secret_index: usize,
+        message: &Vec<u8>,
+        csprng: &mut CSPRNG,
+    ) -> Result<Self, Error> {
+        let bound: Vec<u8> = bind_scheme(Self::SCHEME, message);
+        return Self::sign_with_rng_unchecked::<Hash, CSPRNG>(k, ring, secret_index, &bound, csprng);
+    }
+}
+
+#[cfg(feature = "sign")]
+impl<PrivateKey, Ring, S: SignWithRng<PrivateKey, Ring> + SignatureBytes> SignV2<PrivateKey, Ring> for S {}
+
+/// Verifying signatures made with [`SignV2`]
+pub trait VerifyV2: VerifyRef + SignatureBytes {
+    fn verify_v2<Hash: Digest<OutputSize = U64> + Clone + Default>(&self, message: &Vec<u8>) -> bool {
+        return self.verify_ref::<Hash>(&bind_scheme(Self::SCHEME, message));
+    }
+}
+
+impl<S: VerifyRef + SignatureBytes> VerifyV2 for S {}
+
+impl RingSignature {
+    /// Verifies a signature made with [`SignV2`] with its own scheme
+    pub fn verify_v2<Hash: Digest<OutputSize = U64> + Clone + Default>(&self, message: &Vec<u8>) -> bool {
+        return self.verify_ref::<Hash>(&bind_scheme(self.scheme(), message));
+    }
+}
+
+/// The challenge derivation a signature was made with
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SpecVersion {
+    /// The message alone, as plain `sign` does
+    V1 = 1,
+    /// The scheme and version before the message, as [`SignV2`] does
+    V2 = 2,
+}
+
+impl SpecVersion {
+    pub fn from_u8(version: u8) -> Option<SpecVersion> {
+        return match version {
+            1 => Some(SpecVersion::V1),
+            2 => Some(SpecVersion::V2),
+            _ => None,
+        };
+    }
+}
+
+/// A signature of any scheme together with the [`SpecVersion`] it was made with
+#[derive(Clone)]
+pub struct VersionedSignature {
+    pub version: SpecVersion,
+    pub signature: RingSignature,
+}
+
+impl VersionedSignature {
+    pub fn new(version: SpecVersion, signature: RingSignature) -> VersionedSignature {
+        return VersionedSignature {
+            version: version,
+            signature: signature,
+        };
+    }
+
+    /// Verifies the signature with the challenge derivation of its version
+    pub fn verify<Hash: Digest<OutputSize = U64> + Clone + Default>(&self, message: &Vec<u8>) -> bool {
+        return match self.version {
+            SpecVersion::V1 => self.signature.verify_ref::<Hash>(message),
+            SpecVersion::V2 => self.signature.verify_v2::<Hash>(message),
+        };
+    }
+}
+
+impl Codec for VersionedSignature {
+    /// The version as a single byte, followed by the [`SignatureBytes`] encoding
+    fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes: Vec<u8> = vec![self.version as u8];
+        bytes.extend_from_slice(&self.signature.to_bytes());
+        return bytes;
+    }
+
+    /// Returns [`Error::UnknownVersion`] for versions this build does not verify
+    fn decode(bytes: &[u8]) -> Result<VersionedSignature, Error> {
+        let (version, body) = bytes.split_first().ok_or(Error::Truncated)?;
+        let version = SpecVersion::from_u8(*version).ok_or(Error::UnknownVersion { version: *version })?;
+        return Ok(VersionedSignature::new(version, signature::parse(body)?));
+    }
+}
+
+#[cfg(test)]
+#[cfg(feature = "std")]
+#[cfg(feature = "sign")]
+#[cfg(all(feature = "blsag", feature = "mlsag"))]
+mod test {
+    extern crate rand;
+    extern crate sha2;
+
+    use curve25519_dalek::ristretto::RistrettoPoint;
+    use curve25519_dalek::scalar::Scalar;
+    use rand::rngs::OsRng;
+    use sha2::Sha512;
+
+    use super::*;
+    use crate::blsag::BLSAG;
+    use crate::mlsag::MLSAG;
+    use crate::signature::parse;
+    use crate::traits::SignWithRng;
+
+    #[test]
+    fn versioned() {
+        let k: Scalar = Scalar::random(&mut OsRng);
+        let ring: Vec<RistrettoPoint> = (0..10).map(|_| RistrettoPoint::random(&mut OsRng)).collect();
+        let message: Vec<u8> = b"This is the message".to_vec();
+
+        let signature = BLSAG::sign_v2::<Sha512, OsRng>(k, ring.clone(), 1, &message).unwrap();
+        assert!(signature.verify_v2::<Sha512>(&message));
+        assert!(!signature.verify_v2::<Sha512>(&b"Another message".to_vec()));
+        assert!(!signature.verify_ref::<Sha512>(&message));
+        assert!(parse(&signature.to_tagged_bytes()).unwrap().verify_v2::<Sha512>(&message));
+        let plain = BLSAG::sign_with_rng::<Sha512, _>(k, ring.clone(), 1, &message, &mut OsRng).unwrap();
+        assert!(!plain.verify_v2::<Sha512>(&message));
+
+        // A single column MLSAG signs a different transcript from a bLSAG
+        let columns: Vec<Vec<RistrettoPoint>> = ring.iter().map(|k_point| vec![*k_point]).collect();
+        let signature = MLSAG::sign_v2::<Sha512, OsRng>(vec![k], columns, 1, &message).unwrap();
+        assert!(signature.verify_v2::<Sha512>(&message));
+        assert!(!signature.verify_ref::<Sha512>(&bind_scheme(SchemeId::BLSAG, &message)));
+
+        // The version picks the derivation
+        let v2 = VersionedSignature::new(SpecVersion::V2, RingSignature::MLSAG(signature));
+        let decoded = VersionedSignature::decode(&v2.to_bytes()).unwrap();
+        assert_eq!(decoded.version, SpecVersion::V2);
+        assert!(decoded.verify::<Sha512>(&message));
+        let v1 = VersionedSignature::new(SpecVersion::V1, RingSignature::BLSAG(plain));
+        assert!(VersionedSignature::decode(&v1.to_bytes()).unwrap().verify::<Sha512>(&message));
+        let mut mislabelled = v1.to_bytes();
+        mislabelled[0] = SpecVersion::V2 as u8;
+        assert!(!VersionedSignature::decode(&mislabelled).unwrap().verify::<Sha512>(&message));
+        mislabelled[0] = 3;
+        assert_eq!(
+            VersionedSignature::decode(&mislabelled).err(),
+            Some(Error::UnknownVersion { version: 3 })
+        );
+    }
+}
@@ -0,0 +1,151 @@
+//! Watermarked signatures
+//!
+//! Organizations issuing anonymous credentials often need to tell signatures made under one
+//! issuer or policy version from another. A [`Watermark`] carries that information in the clear
+//! next to the signature and is absorbed into every challenge, so it can be read without verifying
+//! and can not be changed without breaking the signature. Every signer under the same watermark
+//! is equally anonymous, as the watermark says nothing about who in the ring signed.
+
+use crate::prelude::*;
+
+use digest::generic_array::typenum::U64;
+use digest::Digest;
+#[cfg(feature = "sign")]
+use rand_core::{CryptoRng, RngCore};
+
+use crate::codec;
+#[cfg(feature = "sign")]
+use crate::error::Error;
+use crate::traits::VerifyRef;
+#[cfg(feature = "sign")]
+use crate::traits::{Decoys, SignWithRng};
+
+/// Public issuance information bound into a signature
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Watermark {
+    pub issuer_id: Vec<u8>,
+    pub policy_version: u32,
+}
+
+/// A signature together with the watermark bound into it
+#[derive(Clone)]
+pub struct Watermarked<S> {
+    watermark: Watermark,
+    signature: S,
+}
+
+impl<S> Watermarked<S> {
+    /// Reassembles a watermarked signature received as separate parts. Use [`verify`] to check
+    /// that they belong together.
+    pub fn from_parts(watermark: Watermark, signature: S) -> Watermarked<S> {
+        return Watermarked {
+            watermark: watermark,
+            signature: signature,
+        };
+    }
+
+    pub fn watermark(&self) -> &Watermark {
+        return &self.watermark;
+    }
+
+    pub fn issuer_id(&self) -> &[u8] {
+        return &self.watermark.issuer_id;
+    }
+
+    pub fn policy_version(&self) -> u32 {
+        return self.watermark.policy_version;
+    }
+
+    pub fn signature(&self) -> &S {
+        return &self.signature;
+    }
+}
+
+/// The message actually signed: the watermark before `message`
+fn bind_watermark(watermark: &Watermark, message: &Vec<u8>) -> Vec<u8> {
+    let mut bytes: Vec<u8> = b"nazgul/watermark".to_vec();
+    codec::write_u32(&mut bytes, watermark.issuer_id.len());
+    bytes.extend_from_slice(&watermark.issuer_id);
+    bytes.extend_from_slice(&watermark.policy_version.to_le_bytes());
+    bytes.extend_from_slice(message);
+    return bytes;
+}
+
+/// Signs `message` with any scheme, binding `watermark` into every challenge. Takes the same
+/// arguments as the scheme's `sign` otherwise
+#[cfg(feature = "sign")]
+pub fn sign<
+    Hash: Digest<OutputSize = U64> + Clone + Default,
+    CSPRNG: CryptoRng + RngCore + Default,
+    PrivateKey,
+    Ring: Decoys,
+    S: SignWithRng<PrivateKey, Ring>,
+>(
+    k: PrivateKey,
+    ring: Ring,
+    secret_index: usize,
+    watermark: Watermark,
+    message: &Vec<u8>,
+) -> Result<Watermarked<S>, Error> {
+    let mut csprng = CSPRNG::default();
+    let signature =
+        S::sign_with_rng::<Hash, CSPRNG>(k, ring, secret_index, &bind_watermark(&watermark, message), &mut csprng)?;
+    return Ok(Watermarked {
+        watermark: watermark,
+        signature: signature,
+    });
+}
+
+/// Verifies a `watermarked` signature made with [`sign`], including that its watermark was
+/// signed along with `message`
+pub fn verify<Hash: Digest<OutputSize = U64> + Clone + Default, S: VerifyRef>(
+    watermarked: Watermarked<S>,
+    message: &Vec<u8>,
+) -> bool {
+    let bound_message = bind_watermark(&watermarked.watermark, message);
+    return watermarked.signature.verify_ref::<Hash>(&bound_message);
+}
+
+#[cfg(test)]
+#[cfg(feature = "std")]
+#[cfg(feature = "sign")]
+#[cfg(feature = "blsag")]
+mod test {
+    extern crate rand;
+    extern crate sha2;
+
+    use curve25519_dalek::ristretto::RistrettoPoint;
+    use curve25519_dalek::scalar::Scalar;
+    use rand::rngs::OsRng;
+    use sha2::Sha512;
+
+    use super::*;
+    use crate::blsag::BLSAG;
+
+    #[test]
+    fn watermark() {
+        let k: Scalar = Scalar::random(&mut OsRng);
+        let ring: Vec<RistrettoPoint> = (0..10).map(|_| RistrettoPoint::random(&mut OsRng)).collect();
+        let message: Vec<u8> = b"This is the message".to_vec();
+        let watermark = Watermark {
+            issuer_id: b"issuer".to_vec(),
+            policy_version: 2,
+        };
+
+        let watermarked: Watermarked<BLSAG> =
+            super::sign::<Sha512, OsRng, _, _, _>(k, ring, 1, watermark.clone(), &message).unwrap();
+        assert_eq!(watermarked.issuer_id(), b"issuer");
+        assert_eq!(watermarked.policy_version(), 2);
+        assert!(super::verify::<Sha512, _>(watermarked.clone(), &message));
+
+        // Moving the signature to another policy version breaks it
+        let relabelled = Watermarked::from_parts(
+            Watermark {
+                policy_version: 3,
+                ..watermark
+            },
+            watermarked.signature().clone(),
+        );
+        assert!(!super::verify::<Sha512, _>(relabelled, &message));
+    }
+}
@@ -0,0 +1,73 @@
+//! Verification weights
+//!
+//! A weight approximates the cost of verifying a signature as a single number, so that
+//! integrators can price verification (e.g. transaction fees) consistently with the work this
+//! library actually performs. It is computed from the ring dimensions only, using the unit costs
+//! below for every scalar multiplication, hash-to-point and challenge hash done by `verify`.
+//!
+//! The unit costs and the formulas are stable across versions within a major release.
+
+/// Cost of finalizing one challenge hash
+pub const HASH: u64 = 1;
+/// Cost of hashing to a point on the curve
+pub const HASH_TO_POINT: u64 = 10;
+/// Cost of one scalar multiplication (a multiscalar multiplication of size `k` costs `k` of these)
+pub const SCALAR_MULT: u64 = 50;
+
+/// Weight of verifying a SAG signature with `n` ring members
+pub fn sag(n: usize) -> u64 {
+    return n as u64 * (2 * SCALAR_MULT + HASH);
+}
+
+/// Weight of verifying an LSAG signature with `n` ring members
+///
+/// The ring is hashed to a point once, instead of once per member as in bLSAG
+pub fn lsag(n: usize) -> u64 {
+    return HASH_TO_POINT + n as u64 * (4 * SCALAR_MULT + HASH);
+}
+
+/// Weight of verifying a bLSAG signature with `n` ring members
+pub fn blsag(n: usize) -> u64 {
+    return n as u64 * (4 * SCALAR_MULT + HASH_TO_POINT + HASH);
+}
+
+/// Weight of verifying an MLSAG signature with `nr` ring members of `nc` keys each
+pub fn mlsag(nr: usize, nc: usize) -> u64 {
+    let (nr, nc) = (nr as u64, nc as u64);
+    return nr * nc * (4 * SCALAR_MULT + HASH_TO_POINT) + nr * HASH;
+}
+
+/// Weight of verifying a CLSAG signature with `nr` ring members of `nc` keys each
+pub fn clsag(nr: usize, nc: usize) -> u64 {
+    let (nr, nc) = (nr as u64, nc as u64);
+    // Aggregation coefficients, aggregated public keys and the aggregated key image
+    let aggregation = nc * HASH + (nr * nc + nc) * SCALAR_MULT;
+    return aggregation + nr * (4 * SCALAR_MULT + HASH_TO_POINT + HASH);
+}
+
+/// Weight of verifying a DLSAG signature with `n` ring members
+pub fn dlsag(n: usize) -> u64 {
+    return n as u64 * (5 * SCALAR_MULT + HASH_TO_POINT + HASH);
+}
+
+/// Weight of verifying an MDLSAG signature with `nr` ring members of `nc` key pairs each
+pub fn mdlsag(nr: usize, nc: usize) -> u64 {
+    let (nr, nc) = (nr as u64, nc as u64);
+    return nr * nc * (5 * SCALAR_MULT + HASH_TO_POINT) + nr * HASH;
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn weight() {
+        // A single column MLSAG does the same work as a bLSAG
+        assert_eq!(mlsag(11, 1), blsag(11));
+        // Linkability costs more than plain membership
+        assert!(blsag(11) > sag(11));
+        // CLSAG trades the per member work of MLSAG for a one off aggregation
+        assert!(clsag(11, 2) < mlsag(11, 2));
+        assert_eq!(sag(0), 0);
+    }
+}
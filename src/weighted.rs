@@ -0,0 +1,252 @@
+//! Stake weighted ring signatures
+//!
+//! Proves that a ring member of weight at least `W` signed, without revealing which one. Hiding
+//! the weights themselves behind commitments would need range proofs, which this library does not
+//! implement over Ristretto. Instead the closest construction is used: every member carries a
+//! public weight, the ring only contains members of weight at least the threshold, and both the
+//! threshold and the weights are bound into every challenge of an ordinary bLSAG signature. The
+//! anonymity set is therefore every member heavy enough to have signed.
+//!
+//! The weights are the verifier's to know, not the signer's to claim:
+//! [`WeightedBLSAG::verify_weighted`] takes the [`WeightedRing`] the verifier holds, e.g. read from
+//! its stake table, and looks up the weight of every key of the signature there.
+//!
+//! Not to be confused with verification weights in [`crate::weight`].
+
+use crate::prelude::*;
+
+use curve25519_dalek::ristretto::RistrettoPoint;
+#[cfg(feature = "sign")]
+use curve25519_dalek::scalar::Scalar;
+use digest::generic_array::typenum::U64;
+use digest::Digest;
+#[cfg(feature = "sign")]
+use rand_core::{CryptoRng, RngCore};
+
+use crate::blsag::BLSAG;
+use crate::codec;
+#[cfg(feature = "sign")]
+use crate::error::Error;
+use crate::traits::{Decoys, LinkRef, VerifyRef, Weight};
+#[cfg(feature = "sign")]
+use crate::traits::{check_secret_index, SignWithRng, TrySign};
+use crate::weight;
+
+/// Public keys with their weights, and the weight a signer must at least have
+#[derive(Clone)]
+pub struct WeightedRing {
+    pub members: Vec<(RistrettoPoint, u64)>,
+    pub threshold: u64,
+}
+
+impl Decoys for WeightedRing {
+    /// The members heavy enough to be in the signature
+    fn decoys(&self) -> usize {
+        return self.members.iter().filter(|member| member.1 >= self.threshold).count();
+    }
+}
+
+/// A bLSAG signature by a member of weight at least the threshold of a [`WeightedRing`]
+///
+/// It verifies only with [`WeightedBLSAG::verify_weighted`], against the weighted ring of the
+/// verifier. Signatures by the same key link regardless of the threshold or the decoys chosen.
+///
+/// Please read tests at the bottom of the source code for this module for examples on how to use
+/// it
+#[derive(Clone)]
+pub struct WeightedBLSAG {
+    pub signature: BLSAG,
+}
+
+/// The message actually signed: the threshold and every weight, in ring order, before `message`
+fn bind_weights(threshold: u64, weights: &Vec<u64>, message: &Vec<u8>) -> Vec<u8> {
+    let mut bytes: Vec<u8> = b"nazgul/weighted".to_vec();
+    bytes.extend_from_slice(&threshold.to_le_bytes());
+    codec::write_u32(&mut bytes, weights.len());
+    for weight in weights {
+        bytes.extend_from_slice(&weight.to_le_bytes());
+    }
+    bytes.extend_from_slice(message);
+    return bytes;
+}
+
+#[cfg(feature = "sign")]
+impl SignWithRng<(Scalar, u64), WeightedRing> for WeightedBLSAG {
+    /// To sign you need `k` your private key with your weight, and `ring` which is everyone else
+    /// with their weights and the threshold. Members lighter than the threshold are left out of the
+    /// signature, and you are inserted at `secret_index` among the remaining ones
+    ///
+    /// A signer lighter than the threshold, whose signature would not verify, is an
+    /// [`Error::BelowThreshold`], see [`TrySign`].
+    fn sign_with_rng_unchecked<Hash: Digest<OutputSize = U64> + Clone + Default, CSPRNG: CryptoRng + RngCore>(
+        k: (Scalar, u64),
+        ring: WeightedRing,
+        secret_index: usize,
+        message: &Vec<u8>,
+        csprng: &mut CSPRNG,
+    ) -> Result<WeightedBLSAG, Error> {
+        WeightedBLSAG::check_sign_input(&k, &ring, secret_index)?;
+        let threshold = ring.threshold;
+        let eligible: Vec<(RistrettoPoint, u64)> = ring
+            .members
+            .into_iter()
+            .filter(|member| member.1 >= threshold)
+            .collect();
+        let decoys: Vec<RistrettoPoint> = eligible.iter().map(|member| member.0).collect();
+        let mut weights: Vec<u64> = eligible.iter().map(|member| member.1).collect();
+        weights.insert(secret_index, k.1);
+
+        let bound_message = bind_weights(threshold, &weights, message);
+        let signature =
+            BLSAG::sign_with_rng_unchecked::<Hash, CSPRNG>(k.0, decoys, secret_index, &bound_message, csprng)?;
+
+        return Ok(WeightedBLSAG { signature: signature });
+    }
+}
+
+#[cfg(feature = "sign")]
+impl TrySign<(Scalar, u64), WeightedRing> for WeightedBLSAG {
+    /// Returns [`Error::BelowThreshold`] if your weight is below the threshold, and checks
+    /// `secret_index` against the members heavy enough to be in the signature
+    fn check_sign_input(k: &(Scalar, u64), ring: &WeightedRing, secret_index: usize) -> Result<(), Error> {
+        if k.1 < ring.threshold {
+            return Err(Error::BelowThreshold {
+                weight: k.1,
+                threshold: ring.threshold,
+            });
+        }
+        return check_secret_index(ring.decoys(), secret_index);
+    }
+}
+
+impl WeightedBLSAG {
+    /// Whether the signature is over `message` by a member of `ring` of weight at least its
+    /// threshold. `ring` is the verifier's, and must contain every key of the signature, signer
+    /// included, with its weight
+    pub fn verify_weighted<Hash: Digest<OutputSize = U64> + Clone + Default>(
+        &self,
+        ring: &WeightedRing,
+        message: &Vec<u8>,
+    ) -> bool {
+        let mut weights: Vec<u64> = Vec::with_capacity(self.signature.ring.len());
+        for k_point in &self.signature.ring {
+            match ring.members.iter().find(|member| member.0 == *k_point) {
+                Some(member) if member.1 >= ring.threshold => weights.push(member.1),
+                _ => return false,
+            }
+        }
+        let bound_message = bind_weights(ring.threshold, &weights, message);
+        return self.signature.verify_ref::<Hash>(&bound_message);
+    }
+}
+
+impl LinkRef for WeightedBLSAG {
+    /// This is for linking two signatures and checking if they are signed by the same person
+    fn link_ref(&self, other: &WeightedBLSAG) -> bool {
+        return self.signature.link_ref(&other.signature);
+    }
+}
+
+impl Weight for WeightedBLSAG {
+    /// Approximate cost of verifying this signature, see [`crate::weight`]
+    fn weight(&self) -> u64 {
+        return weight::blsag(self.signature.ring.len());
+    }
+}
+
+#[cfg(test)]
+#[cfg(feature = "std")]
+#[cfg(feature = "sign")]
+mod test {
+    extern crate rand;
+    extern crate sha2;
+
+    use rand::rngs::OsRng;
+    use sha2::Sha512;
+
+    use super::*;
+
+    #[test]
+    fn weighted() {
+        let k: Scalar = Scalar::random(&mut OsRng);
+        let members: Vec<(RistrettoPoint, u64)> =
+            [5, 100, 40].iter().map(|weight| (RistrettoPoint::random(&mut OsRng), *weight)).collect();
+        let decoys = WeightedRing {
+            members: members.clone(),
+            threshold: 40,
+        };
+        // The verifier knows every member, the signer included
+        let mut everyone = decoys.clone();
+        everyone.members.push((RistrettoPoint::mul_base(&k), 50));
+        let message: Vec<u8> = b"This is the message".to_vec();
+
+        let signature =
+            WeightedBLSAG::sign_with_rng_unchecked::<Sha512, _>((k, 50), decoys.clone(), 1, &message, &mut OsRng)
+                .unwrap();
+        // The member of weight 5 is left out of the anonymity set
+        assert_eq!(signature.signature.ring.len(), 3);
+        assert!(signature.verify_weighted::<Sha512>(&everyone, &message));
+        assert!(!signature.verify_weighted::<Sha512>(&everyone, &b"Another message".to_vec()));
+
+        // The threshold and the weights are the verifier's
+        let mut stricter = everyone.clone();
+        stricter.threshold = 45;
+        assert!(!signature.verify_weighted::<Sha512>(&stricter, &message));
+        let mut reweighed = everyone.clone();
+        reweighed.members[1].1 = 500;
+        assert!(!signature.verify_weighted::<Sha512>(&reweighed, &message));
+
+        let signature_1 =
+            WeightedBLSAG::sign_with_rng_unchecked::<Sha512, _>((k, 50), decoys.clone(), 0, &message, &mut OsRng)
+                .unwrap();
+        let signature_2 =
+            WeightedBLSAG::sign_with_rng_unchecked::<Sha512, _>((k, 50), decoys.clone(), 2, &message, &mut OsRng)
+                .unwrap();
+        assert!(signature_1.link_ref(&signature_2));
+
+        assert_eq!(
+            WeightedBLSAG::try_sign::<Sha512, OsRng>((k, 39), decoys.clone(), 1, &message).err(),
+            Some(Error::BelowThreshold { weight: 39, threshold: 40 })
+        );
+        assert_eq!(
+            WeightedBLSAG::try_sign::<Sha512, OsRng>((k, 50), decoys, 3, &message).err(),
+            Some(Error::SecretIndexOutOfRange { index: 3, ring_size: 3 })
+        );
+    }
+
+    #[test]
+    fn forged_weights() {
+        let light: Scalar = Scalar::random(&mut OsRng);
+        let members: Vec<(RistrettoPoint, u64)> =
+            (0..2).map(|_| (RistrettoPoint::random(&mut OsRng), 100)).collect();
+        let everyone = WeightedRing {
+            members: [members.clone(), vec![(RistrettoPoint::mul_base(&light), 5)]].concat(),
+            threshold: 40,
+        };
+        let message: Vec<u8> = b"This is the message".to_vec();
+
+        // A member of weight 5 claiming the largest weight there is
+        let forged = WeightedBLSAG::sign_with_rng_unchecked::<Sha512, _>(
+            (light, u64::MAX),
+            WeightedRing {
+                members: members,
+                threshold: 40,
+            },
+            0,
+            &message,
+            &mut OsRng,
+        )
+        .unwrap();
+        assert!(!forged.verify_weighted::<Sha512>(&everyone, &message));
+
+        // A ring of fresh keys, all claiming to be heavy
+        let k: Scalar = Scalar::random(&mut OsRng);
+        let fresh = WeightedRing {
+            members: (0..2).map(|_| (RistrettoPoint::random(&mut OsRng), u64::MAX)).collect(),
+            threshold: 40,
+        };
+        let forged =
+            WeightedBLSAG::sign_with_rng_unchecked::<Sha512, _>((k, u64::MAX), fresh, 1, &message, &mut OsRng).unwrap();
+        assert!(!forged.verify_weighted::<Sha512>(&everyone, &message));
+    }
+}
@@ -0,0 +1,290 @@
+//! Golden cross-verification
+//!
+//! `tests/golden` holds one canonically encoded signature per scheme and hash function. They were
+//! produced once and are committed, so any change to challenge derivation or to the codec that
+//! would invalidate signatures already issued in the wild makes these tests fail.
+//!
+//! Signing again with the same seeded key material and nonces must also reproduce every file byte
+//! for byte, and verification must reach the same outcomes, whichever curve25519-dalek backend the
+//! build uses. Run the tests once per backend to check that, e.g. on x86_64:
+//!
+//! - `cargo test --test golden`, which uses the AVX2 backend on CPUs that have it
+//! - `RUSTFLAGS='--cfg curve25519_dalek_backend="serial"' cargo test --test golden`
+//! - `RUSTFLAGS='--cfg curve25519_dalek_bits="32"' cargo test --test golden`
+//!
+//! curve25519-dalek 4 has no NEON backend, so aarch64 builds use the serial one.
+//!
+//! Signatures made in the v2 mode of `nazgul::versioned` have golden files of their own, named
+//! `v2_<scheme>_<hash>`, each a `VersionedSignature`. The v1 files are also checked to verify
+//! through `VersionedSignature`, so archives of both versions stay verifiable.
+//!
+//! If the format is meant to change, regenerate the files with
+//! `NAZGUL_REGENERATE_GOLDEN=1 cargo test --test golden` and explain why in the changelog.
+#![cfg(all(
+    feature = "std",
+    feature = "sign",
+    feature = "sag",
+    feature = "lsag",
+    feature = "blsag",
+    feature = "mlsag",
+    feature = "clsag",
+    feature = "dlsag",
+    feature = "mdlsag"
+))]
+
+extern crate blake2;
+extern crate curve25519_dalek;
+extern crate digest;
+extern crate nazgul;
+extern crate rand;
+extern crate sha2;
+extern crate sha3;
+
+use std::path::PathBuf;
+
+use blake2::Blake2b512;
+use curve25519_dalek::ristretto::RistrettoPoint;
+use curve25519_dalek::scalar::Scalar;
+use digest::generic_array::typenum::U64;
+use digest::Digest;
+use nazgul::blsag::BLSAG;
+use nazgul::clsag::CLSAG;
+use nazgul::codec::Codec;
+use nazgul::dlsag::DLSAG;
+use nazgul::lsag::LSAG;
+use nazgul::mdlsag::MDLSAG;
+use nazgul::mlsag::MLSAG;
+use nazgul::sag::SAG;
+use nazgul::signature::{RingSignature, SignatureBytes};
+use nazgul::traits::{SignWithRng, VerifyRef};
+use nazgul::versioned::{SignV2, SpecVersion, VersionedSignature};
+use rand::rngs::StdRng;
+use rand::{CryptoRng, RngCore, SeedableRng};
+use sha2::Sha512;
+use sha3::Keccak512;
+
+const MESSAGE: &[u8] = b"This is the message";
+
+/// Seeded generator so regenerated golden files only differ when the derivation does
+struct GoldenRng(StdRng);
+
+impl Default for GoldenRng {
+    fn default() -> Self {
+        GoldenRng(StdRng::seed_from_u64(456))
+    }
+}
+
+impl RngCore for GoldenRng {
+    fn next_u32(&mut self) -> u32 {
+        self.0.next_u32()
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        self.0.next_u64()
+    }
+
+    fn fill_bytes(&mut self, dest: &mut [u8]) {
+        self.0.fill_bytes(dest)
+    }
+
+    fn try_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), rand::Error> {
+        self.0.try_fill_bytes(dest)
+    }
+}
+
+impl CryptoRng for GoldenRng {}
+
+fn path(name: &str) -> PathBuf {
+    let mut path = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+    path.push("tests");
+    path.push("golden");
+    path.push(format!("{}.hex", name));
+    path
+}
+
+fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+fn from_hex(hex: &str) -> Vec<u8> {
+    let hex = hex.trim();
+    (0..hex.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(&hex[i..i + 2], 16).unwrap())
+        .collect()
+}
+
+/// Checks the committed signature called `name`, or writes a fresh one produced by `sign` when
+/// regenerating. Key material is drawn from its own seeded generator, separate from the nonces.
+fn check<S: SignatureBytes + VerifyRef, Hash: Digest<OutputSize = U64> + Clone + Default>(
+    name: &str,
+    sign: impl FnOnce(&mut StdRng) -> S,
+) {
+    let signed = sign(&mut StdRng::seed_from_u64(0)).to_bytes();
+    if std::env::var_os("NAZGUL_REGENERATE_GOLDEN").is_some() {
+        std::fs::write(path(name), to_hex(&signed) + "\n").unwrap();
+    }
+    let bytes = from_hex(&std::fs::read_to_string(path(name)).unwrap());
+    assert!(signed == bytes, "{} is not reproduced by signing again", name);
+    let signature = S::from_bytes(&bytes).expect(name);
+    assert_eq!(signature.to_bytes(), bytes, "{} does not re-encode identically", name);
+    assert!(signature.verify_ref::<Hash>(&MESSAGE.to_vec()), "{} no longer verifies", name);
+
+    // A flipped bit and another message fail alike on every backend
+    let mut tampered = bytes.clone();
+    tampered[0] ^= 1;
+    if let Some(signature) = S::from_bytes(&tampered) {
+        assert!(!signature.verify_ref::<Hash>(&MESSAGE.to_vec()), "{} verifies when tampered with", name);
+    }
+    let signature = S::from_bytes(&bytes).unwrap();
+    assert!(!signature.verify_ref::<Hash>(&b"Another message".to_vec()), "{} verifies another message", name);
+
+    let versioned = VersionedSignature::new(SpecVersion::V1, nazgul::parse(&signature.to_tagged_bytes()).unwrap());
+    assert!(versioned.verify::<Hash>(&MESSAGE.to_vec()), "{} does not verify as v1", name);
+}
+
+/// As [`check`] for a signature made in the v2 mode, in the file `v2_<name>`
+fn check_v2<Hash: Digest<OutputSize = U64> + Clone + Default>(
+    name: &str,
+    sign: impl FnOnce(&mut StdRng) -> RingSignature,
+) {
+    let name = format!("v2_{}", name);
+    let signed = VersionedSignature::new(SpecVersion::V2, sign(&mut StdRng::seed_from_u64(0))).to_bytes();
+    if std::env::var_os("NAZGUL_REGENERATE_GOLDEN").is_some() {
+        std::fs::write(path(&name), to_hex(&signed) + "\n").unwrap();
+    }
+    let bytes = from_hex(&std::fs::read_to_string(path(&name)).unwrap());
+    assert!(signed == bytes, "{} is not reproduced by signing again", name);
+    let signature = VersionedSignature::from_bytes(&bytes).expect(&name);
+    assert_eq!(signature.to_bytes(), bytes, "{} does not re-encode identically", name);
+    assert!(signature.verify::<Hash>(&MESSAGE.to_vec()), "{} no longer verifies", name);
+    assert!(!signature.verify::<Hash>(&b"Another message".to_vec()), "{} verifies another message", name);
+
+    // Read as v1 it does not verify
+    let mut as_v1 = bytes;
+    as_v1[0] = SpecVersion::V1 as u8;
+    let signature = VersionedSignature::from_bytes(&as_v1).unwrap();
+    assert!(!signature.verify::<Hash>(&MESSAGE.to_vec()), "{} verifies as v1", name);
+}
+
+fn points(csprng: &mut StdRng, n: usize) -> Vec<RistrettoPoint> {
+    (0..n).map(|_| RistrettoPoint::random(csprng)).collect()
+}
+
+fn triples(csprng: &mut StdRng, n: usize) -> Vec<(RistrettoPoint, RistrettoPoint, Scalar)> {
+    (0..n)
+        .map(|_| {
+            (
+                RistrettoPoint::random(csprng),
+                RistrettoPoint::random(csprng),
+                Scalar::random(csprng),
+            )
+        })
+        .collect()
+}
+
+fn schemes<Hash: Digest<OutputSize = U64> + Clone + Default>(suffix: &str) {
+    let message = MESSAGE.to_vec();
+    check::<SAG, Hash>(&format!("sag_{}", suffix), |csprng| {
+        let k = Scalar::random(csprng);
+        SAG::sign_with_rng_unchecked::<Hash, _>(k, points(csprng, 2), 1, &message, &mut GoldenRng::default()).unwrap()
+    });
+    check::<LSAG, Hash>(&format!("lsag_{}", suffix), |csprng| {
+        let k = Scalar::random(csprng);
+        LSAG::sign_with_rng_unchecked::<Hash, _>(k, points(csprng, 2), 1, &message, &mut GoldenRng::default()).unwrap()
+    });
+    check::<BLSAG, Hash>(&format!("blsag_{}", suffix), |csprng| {
+        let k = Scalar::random(csprng);
+        BLSAG::sign_with_rng_unchecked::<Hash, _>(k, points(csprng, 2), 1, &message, &mut GoldenRng::default()).unwrap()
+    });
+    check::<MLSAG, Hash>(&format!("mlsag_{}", suffix), |csprng| {
+        let ks = (0..2).map(|_| Scalar::random(csprng)).collect();
+        let ring = (0..2).map(|_| points(csprng, 2)).collect();
+        MLSAG::sign_with_rng_unchecked::<Hash, _>(ks, ring, 1, &message, &mut GoldenRng::default()).unwrap()
+    });
+    check::<CLSAG, Hash>(&format!("clsag_{}", suffix), |csprng| {
+        let ks = (0..2).map(|_| Scalar::random(csprng)).collect();
+        let ring = (0..2).map(|_| points(csprng, 2)).collect();
+        CLSAG::sign_with_rng_unchecked::<Hash, _>(ks, ring, 1, &message, &mut GoldenRng::default()).unwrap()
+    });
+    check::<DLSAG, Hash>(&format!("dlsag_{}", suffix), |csprng| {
+        let k = (Scalar::random(csprng), RistrettoPoint::random(csprng), Scalar::random(csprng));
+        let ring = triples(csprng, 2);
+        DLSAG::sign_with_rng_unchecked::<Hash, _>(k, ring, 1, &message, &mut GoldenRng::default()).unwrap()
+    });
+    check::<MDLSAG, Hash>(&format!("mdlsag_{}", suffix), |csprng| {
+        let ks = (0..2)
+            .map(|_| (RistrettoPoint::random(csprng), Scalar::random(csprng), Scalar::random(csprng)))
+            .collect::<Vec<(RistrettoPoint, Scalar, Scalar)>>();
+        let ring = (0..2).map(|_| triples(csprng, 2)).collect();
+        MDLSAG::sign_with_rng_unchecked::<Hash, _>(ks, ring, 1, &message, &mut GoldenRng::default()).unwrap()
+    });
+}
+
+fn schemes_v2<Hash: Digest<OutputSize = U64> + Clone + Default>(suffix: &str) {
+    let message = MESSAGE.to_vec();
+    check_v2::<Hash>(&format!("sag_{}", suffix), |csprng| {
+        let k = Scalar::random(csprng);
+        let ring = points(csprng, 2);
+        let signature = SAG::sign_v2_with_rng_unchecked::<Hash, _>(k, ring, 1, &message, &mut GoldenRng::default());
+        RingSignature::SAG(signature.unwrap())
+    });
+    check_v2::<Hash>(&format!("lsag_{}", suffix), |csprng| {
+        let k = Scalar::random(csprng);
+        let ring = points(csprng, 2);
+        let signature = LSAG::sign_v2_with_rng_unchecked::<Hash, _>(k, ring, 1, &message, &mut GoldenRng::default());
+        RingSignature::LSAG(signature.unwrap())
+    });
+    check_v2::<Hash>(&format!("blsag_{}", suffix), |csprng| {
+        let k = Scalar::random(csprng);
+        let ring = points(csprng, 2);
+        let signature = BLSAG::sign_v2_with_rng_unchecked::<Hash, _>(k, ring, 1, &message, &mut GoldenRng::default());
+        RingSignature::BLSAG(signature.unwrap())
+    });
+    check_v2::<Hash>(&format!("mlsag_{}", suffix), |csprng| {
+        let ks = (0..2).map(|_| Scalar::random(csprng)).collect();
+        let ring = (0..2).map(|_| points(csprng, 2)).collect();
+        let signature = MLSAG::sign_v2_with_rng_unchecked::<Hash, _>(ks, ring, 1, &message, &mut GoldenRng::default());
+        RingSignature::MLSAG(signature.unwrap())
+    });
+    check_v2::<Hash>(&format!("clsag_{}", suffix), |csprng| {
+        let ks = (0..2).map(|_| Scalar::random(csprng)).collect();
+        let ring = (0..2).map(|_| points(csprng, 2)).collect();
+        let signature = CLSAG::sign_v2_with_rng_unchecked::<Hash, _>(ks, ring, 1, &message, &mut GoldenRng::default());
+        RingSignature::CLSAG(signature.unwrap())
+    });
+    check_v2::<Hash>(&format!("dlsag_{}", suffix), |csprng| {
+        let k = (Scalar::random(csprng), RistrettoPoint::random(csprng), Scalar::random(csprng));
+        let ring = triples(csprng, 2);
+        let signature = DLSAG::sign_v2_with_rng_unchecked::<Hash, _>(k, ring, 1, &message, &mut GoldenRng::default());
+        RingSignature::DLSAG(signature.unwrap())
+    });
+    check_v2::<Hash>(&format!("mdlsag_{}", suffix), |csprng| {
+        let ks = (0..2)
+            .map(|_| (RistrettoPoint::random(csprng), Scalar::random(csprng), Scalar::random(csprng)))
+            .collect::<Vec<(RistrettoPoint, Scalar, Scalar)>>();
+        let ring = (0..2).map(|_| triples(csprng, 2)).collect();
+        let signature = MDLSAG::sign_v2_with_rng_unchecked::<Hash, _>(ks, ring, 1, &message, &mut GoldenRng::default());
+        RingSignature::MDLSAG(signature.unwrap())
+    });
+}
+
+#[test]
+fn golden_sha512() {
+    schemes::<Sha512>("sha512");
+}
+
+#[test]
+fn golden_keccak512() {
+    schemes::<Keccak512>("keccak512");
+}
+
+#[test]
+fn golden_blake2b512() {
+    schemes::<Blake2b512>("blake2b512");
+}
+
+#[test]
+fn golden_v2_sha512() {
+    schemes_v2::<Sha512>("sha512");
+}